@@ -1,71 +1,8 @@
-use quote;
-use serde_json;
-use std::collections::HashMap;
-use std::env;
-use syn;
-
-fn main() {
-    let args: Vec<String> = env::args().collect();
-
-    // Parse arguments for signature filter
-    let mut target_signature: Option<u128> = None;
-    let mut input_file: Option<String> = None;
-
-    let mut i = 1;
-    while i < args.len() {
-        match args[i].as_str() {
-            "--signature" => {
-                if i + 1 < args.len() {
-                    target_signature = args[i + 1].parse::<u128>().ok();
-                    i += 2;
-                } else {
-                    i += 1;
-                }
-            }
-            "--filter" => {
-                if i + 1 < args.len() {
-                    // Legacy frequency filter support
-                    i += 2;
-                } else {
-                    i += 1;
-                }
-            }
-            arg if !arg.starts_with('-') => {
-                input_file = Some(arg.to_string());
-                i += 1;
-            }
-            _ => i += 1,
-        }
-    }
-
-    println!("🧟 Zombie Rustc - Spectral Analysis Driver");
-    println!("==========================================");
+pub mod orbit_annotations;
 
-    if let Some(freq) = filter_freq {
-        println!("🎛️ Spectral filter: {:.2}", freq);
-
-        // Load spectral filter mapping
-        let filter_map = load_spectral_filters();
-        let target_class = find_class_for_frequency(&filter_map, freq);
-
-        if let Some(class) = target_class {
-            println!("🎯 Target class: {}", class);
-            if let Some(file) = input_file {
-                println!("📄 Analyzing with {} filter: {}", class, file);
-                run_spectral_analysis(&file, freq, &class);
-            }
-        } else {
-            println!("❌ No class found for frequency {:.2}", freq);
-        }
-    } else {
-        if let Some(file) = input_file {
-            println!("📄 Analyzing (no filter): {}", file);
-            run_normal_analysis(&file);
-        }
-    }
-}
+use std::collections::HashMap;
 
-fn load_spectral_filters() -> HashMap<String, f64> {
+pub fn load_spectral_filters() -> HashMap<String, f64> {
     if let Ok(content) = std::fs::read_to_string("spectral_filters.json") {
         serde_json::from_str(&content).unwrap_or_default()
     } else {
@@ -73,14 +10,14 @@ fn load_spectral_filters() -> HashMap<String, f64> {
     }
 }
 
-fn find_class_for_frequency(filter_map: &HashMap<String, f64>, target_freq: f64) -> Option<String> {
+pub fn find_class_for_frequency(filter_map: &HashMap<String, f64>, target_freq: f64) -> Option<String> {
     filter_map
         .iter()
         .find(|(_, &freq)| (freq - target_freq).abs() < 0.025)
         .map(|(class, _)| class.clone())
 }
 
-fn run_spectral_analysis(input_file: &str, filter_freq: f64, target_class: &str) {
+pub fn run_spectral_analysis(input_file: &str, filter_freq: f64, target_class: &str) {
     println!(
         "🔬 Running spectral analysis with filter {:.2}",
         filter_freq
@@ -101,7 +38,7 @@ fn run_spectral_analysis(input_file: &str, filter_freq: f64, target_class: &str)
     println!("   Output: {}", output_file);
 }
 
-fn extract_spectral_items(input_file: &str, target_class: &str) -> Vec<String> {
+pub fn extract_spectral_items(input_file: &str, target_class: &str) -> Vec<String> {
     let mut items = Vec::new();
 
     if let Ok(content) = std::fs::read_to_string(input_file) {
@@ -150,11 +87,11 @@ fn extract_spectral_items(input_file: &str, target_class: &str) -> Vec<String> {
     items
 }
 
-fn generate_filtered_file(output_file: &str, items: &[String], target_class: &str) {
+pub fn generate_filtered_file(output_file: &str, items: &[String], target_class: &str) {
     let mut content = String::new();
 
     // Add header
-    content.push_str(&format!("// Spectral compilation output\n"));
+    content.push_str("// Spectral compilation output\n");
     content.push_str(&format!("// Filter class: {}\n", target_class));
     content.push_str(&format!("// Generated items: {}\n\n", items.len()));
 
@@ -172,7 +109,64 @@ fn generate_filtered_file(output_file: &str, items: &[String], target_class: &st
     println!("📂 Generated spectral file: {}", output_file);
 }
 
-fn run_normal_analysis(input_file: &str) {
+pub fn run_normal_analysis(input_file: &str) {
     println!("🔬 Running normal analysis");
     println!("✅ Normal analysis complete for: {}", input_file);
 }
+
+pub fn run_orbit_annotation(input_file: &str) {
+    let annotations = match orbit_annotations::annotate_file(input_file) {
+        Ok(annotations) => annotations,
+        Err(e) => {
+            println!("❌ {}", e);
+            return;
+        }
+    };
+
+    let name = std::path::Path::new(input_file)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let meta = orbit_annotations::plugin_meta(&name, env!("CARGO_PKG_VERSION"), &annotations);
+    let report = orbit_annotations::annotations_json(&meta, &annotations);
+
+    let output_file = format!("{}.orbits.json", input_file.trim_end_matches(".rs"));
+    match serde_json::to_string_pretty(&report) {
+        Ok(json) => std::fs::write(&output_file, json)
+            .unwrap_or_else(|e| println!("❌ Failed to write {}: {}", output_file, e)),
+        Err(e) => println!("❌ Failed to serialize annotations: {}", e),
+    }
+
+    println!("🎯 Orbit annotation complete!");
+    println!("   Items annotated: {}", annotations.len());
+    println!("   Security level: {:?}", meta.security_level);
+    println!(
+        "   Audit findings: {}",
+        orbit_annotations::audit_findings(&annotations).len()
+    );
+    println!("   Output: {}", output_file);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_orbit_annotation_output() {
+        let dir = std::env::temp_dir().join(format!("zos-analysis-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("sample.rs");
+        std::fs::write(&input, "fn pid() -> i32 { unsafe { libc::getpid() } }").unwrap();
+
+        run_orbit_annotation(input.to_str().unwrap());
+
+        let output = std::fs::read_to_string(dir.join("sample.orbits.json")).unwrap();
+        let report: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(report["plugin"]["name"], "sample");
+        assert_eq!(report["plugin"]["security_level"], "Critical");
+        assert_eq!(report["items"][0]["orbit"]["orbit_id"], "23.a5");
+        assert_eq!(report["audit"].as_array().unwrap().len(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,73 @@
+use std::env;
+use zos_analysis::{
+    find_class_for_frequency, load_spectral_filters, run_normal_analysis, run_orbit_annotation,
+    run_spectral_analysis,
+};
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    let mut input_file: Option<String> = None;
+    let mut filter_freq: Option<f64> = None;
+    let mut annotate = false;
+
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--signature" => {
+                // Signature filtering is not implemented; skip its value
+                i += if i + 1 < args.len() { 2 } else { 1 };
+            }
+            "--annotate" => {
+                annotate = true;
+                i += 1;
+            }
+            "--filter" => {
+                if i + 1 < args.len() {
+                    // Legacy frequency filter support
+                    filter_freq = args[i + 1].parse::<f64>().ok();
+                    i += 2;
+                } else {
+                    i += 1;
+                }
+            }
+            arg if !arg.starts_with('-') => {
+                input_file = Some(arg.to_string());
+                i += 1;
+            }
+            _ => i += 1,
+        }
+    }
+
+    println!("🧟 Zombie Rustc - Spectral Analysis Driver");
+    println!("==========================================");
+
+    if annotate {
+        if let Some(file) = input_file {
+            println!("📄 Annotating LMFDB orbits: {}", file);
+            run_orbit_annotation(&file);
+        }
+        return;
+    }
+
+    if let Some(freq) = filter_freq {
+        println!("🎛️ Spectral filter: {:.2}", freq);
+
+        // Load spectral filter mapping
+        let filter_map = load_spectral_filters();
+        let target_class = find_class_for_frequency(&filter_map, freq);
+
+        if let Some(class) = target_class {
+            println!("🎯 Target class: {}", class);
+            if let Some(file) = input_file {
+                println!("📄 Analyzing with {} filter: {}", class, file);
+                run_spectral_analysis(&file, freq, &class);
+            }
+        } else {
+            println!("❌ No class found for frequency {:.2}", freq);
+        }
+    } else if let Some(file) = input_file {
+        println!("📄 Analyzing (no filter): {}", file);
+        run_normal_analysis(&file);
+    }
+}
@@ -0,0 +1,464 @@
+// LMFDB orbit annotations: a complexity fingerprint per item, mapped onto an
+// orbit reference and a security level for plugin metadata and audits
+// AGPL-3.0 License
+
+use proc_macro2::{Delimiter, Spacing, TokenStream, TokenTree};
+use quote::ToTokens;
+use serde::Serialize;
+use zos_types::{LMFDBOrbitRef, PluginMeta, SecurityLevel};
+
+/// Counts taken from an item's tokens
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ComplexityFingerprint {
+    pub item_name: String,
+    pub item_kind: String,
+    pub branches: u32, // if, match arms, ?, && and ||
+    pub loops: u32,
+    pub max_nesting: u32, // brace depth
+    pub calls: u32,
+    pub unsafe_blocks: u32,
+    pub raw_syscalls: u32, // libc::, asm!, syscall
+    pub io_paths: u32,     // fs, process, net
+    pub tokens: u32,
+}
+
+impl ComplexityFingerprint {
+    /// Cyclomatic complexity estimate
+    pub fn cyclomatic(&self) -> u32 {
+        1 + self.branches + self.loops
+    }
+}
+
+/// One row of the lookup table from fingerprints to LMFDB orbits. Classes
+/// follow `OrbitClass` in the orbit filter.
+pub struct OrbitEntry {
+    pub complexity_class: &'static str,
+    pub orbit_id: &'static str,
+    pub max_cyclomatic: u32,
+    pub max_nesting: u32,
+}
+
+/// Safe code, by increasing complexity; the first row that fits wins
+pub const ORBIT_TABLE: [OrbitEntry; 4] = [
+    OrbitEntry {
+        complexity_class: "Trivial",
+        orbit_id: "11.a1",
+        max_cyclomatic: 1,
+        max_nesting: 1,
+    },
+    OrbitEntry {
+        complexity_class: "Cyclic",
+        orbit_id: "11.a2",
+        max_cyclomatic: 5,
+        max_nesting: 3,
+    },
+    OrbitEntry {
+        complexity_class: "Symmetric",
+        orbit_id: "11.a3",
+        max_cyclomatic: 15,
+        max_nesting: 5,
+    },
+    OrbitEntry {
+        complexity_class: "Alternating",
+        orbit_id: "23.a1",
+        max_cyclomatic: u32::MAX,
+        max_nesting: u32::MAX,
+    },
+];
+
+/// Unsafe code or raw syscalls, whatever the complexity
+pub const SPORADIC: OrbitEntry = OrbitEntry {
+    complexity_class: "Sporadic",
+    orbit_id: "23.a4",
+    max_cyclomatic: u32::MAX,
+    max_nesting: u32::MAX,
+};
+
+/// Unsafe code making raw syscalls
+pub const MONSTER: OrbitEntry = OrbitEntry {
+    complexity_class: "Monster",
+    orbit_id: "23.a5",
+    max_cyclomatic: u32::MAX,
+    max_nesting: u32::MAX,
+};
+
+pub fn lookup_orbit(fingerprint: &ComplexityFingerprint) -> &'static OrbitEntry {
+    match (fingerprint.unsafe_blocks > 0, fingerprint.raw_syscalls > 0) {
+        (true, true) => &MONSTER,
+        (true, false) | (false, true) => &SPORADIC,
+        (false, false) => ORBIT_TABLE
+            .iter()
+            .find(|entry| {
+                fingerprint.cyclomatic() <= entry.max_cyclomatic
+                    && fingerprint.max_nesting <= entry.max_nesting
+            })
+            .unwrap_or(&ORBIT_TABLE[ORBIT_TABLE.len() - 1]),
+    }
+}
+
+/// "11.a2" -> https://www.lmfdb.org/EllipticCurve/Q/11/a/2
+pub fn lmfdb_url(orbit_id: &str) -> String {
+    let (level, class) = orbit_id.split_once('.').unwrap_or((orbit_id, ""));
+    let (iso, index) = class.split_at(
+        class
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(class.len()),
+    );
+    format!(
+        "https://www.lmfdb.org/EllipticCurve/Q/{}/{}/{}",
+        level, iso, index
+    )
+}
+
+pub fn security_level(fingerprint: &ComplexityFingerprint) -> SecurityLevel {
+    if fingerprint.raw_syscalls > 0 {
+        SecurityLevel::Critical
+    } else if fingerprint.unsafe_blocks > 0 {
+        SecurityLevel::Privileged
+    } else if fingerprint.io_paths > 0 {
+        SecurityLevel::Controlled
+    } else {
+        SecurityLevel::Safe
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ItemAnnotation {
+    pub fingerprint: ComplexityFingerprint,
+    pub orbit: LMFDBOrbitRef,
+    pub security_level: SecurityLevel,
+}
+
+impl zos_traits::LMFDBOrbitRef for ItemAnnotation {
+    fn lmfdb_orbit_id(&self) -> &str {
+        &self.orbit.orbit_id
+    }
+
+    fn lmfdb_complexity_class(&self) -> &str {
+        &self.orbit.complexity_class
+    }
+}
+
+fn rank(annotation: &ItemAnnotation) -> (SecurityLevel, u32, u32) {
+    let fingerprint = &annotation.fingerprint;
+    (
+        annotation.security_level,
+        fingerprint.cyclomatic(),
+        fingerprint.max_nesting,
+    )
+}
+
+fn walk(stream: TokenStream, depth: u32, fingerprint: &mut ComplexityFingerprint) {
+    fingerprint.max_nesting = fingerprint.max_nesting.max(depth);
+    let tokens: Vec<TokenTree> = stream.into_iter().collect();
+
+    for (i, token) in tokens.iter().enumerate() {
+        fingerprint.tokens += 1;
+        let next = tokens.get(i + 1);
+        let after_fn =
+            i > 0 && matches!(&tokens[i - 1], TokenTree::Ident(previous) if previous == "fn");
+        match token {
+            TokenTree::Ident(ident) => match ident.to_string().as_str() {
+                "if" => fingerprint.branches += 1,
+                "for" | "while" | "loop" => fingerprint.loops += 1,
+                "unsafe" => fingerprint.unsafe_blocks += 1,
+                "libc" | "asm" | "syscall" => fingerprint.raw_syscalls += 1,
+                "fs" | "process" | "net" | "Command" | "File" | "TcpStream" | "UdpSocket" => {
+                    fingerprint.io_paths += 1
+                }
+                _ if after_fn => {}
+                _ => {
+                    if matches!(next, Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis)
+                    {
+                        fingerprint.calls += 1;
+                    }
+                }
+            },
+            TokenTree::Punct(punct) => {
+                let joined = |c: char| {
+                    punct.spacing() == Spacing::Joint
+                        && matches!(next, Some(TokenTree::Punct(next)) if next.as_char() == c)
+                };
+                match punct.as_char() {
+                    '?' => fingerprint.branches += 1,
+                    '=' if joined('>') => fingerprint.branches += 1,
+                    '&' if joined('&') => fingerprint.branches += 1,
+                    '|' if joined('|') => fingerprint.branches += 1,
+                    _ => {}
+                }
+            }
+            TokenTree::Group(group) => {
+                let depth = if group.delimiter() == Delimiter::Brace {
+                    depth + 1
+                } else {
+                    depth
+                };
+                walk(group.stream(), depth, fingerprint);
+            }
+            TokenTree::Literal(_) => {}
+        }
+    }
+}
+
+pub fn fingerprint(item_name: &str, item_kind: &str, tokens: TokenStream) -> ComplexityFingerprint {
+    let mut fingerprint = ComplexityFingerprint {
+        item_name: item_name.to_string(),
+        item_kind: item_kind.to_string(),
+        ..Default::default()
+    };
+    walk(tokens, 0, &mut fingerprint);
+    fingerprint
+}
+
+fn type_name(ty: &syn::Type) -> String {
+    ty.to_token_stream().to_string().replace(' ', "")
+}
+
+fn collect(items: &[syn::Item], prefix: &str, out: &mut Vec<ComplexityFingerprint>) {
+    for item in items {
+        match item {
+            syn::Item::Fn(func) => {
+                let name = format!("{}{}", prefix, func.sig.ident);
+                out.push(fingerprint(&name, "fn", func.to_token_stream()));
+            }
+            syn::Item::Struct(s) => {
+                out.push(fingerprint(
+                    &format!("{}{}", prefix, s.ident),
+                    "struct",
+                    s.to_token_stream(),
+                ));
+            }
+            syn::Item::Enum(e) => {
+                out.push(fingerprint(
+                    &format!("{}{}", prefix, e.ident),
+                    "enum",
+                    e.to_token_stream(),
+                ));
+            }
+            // Methods one by one: the impl header's `for` is not a loop
+            syn::Item::Impl(imp) => {
+                let self_ty = type_name(&imp.self_ty);
+                for impl_item in &imp.items {
+                    if let syn::ImplItem::Fn(method) = impl_item {
+                        let name = format!("{}{}::{}", prefix, self_ty, method.sig.ident);
+                        let mut method_fingerprint =
+                            fingerprint(&name, "method", method.to_token_stream());
+                        if imp.unsafety.is_some() {
+                            method_fingerprint.unsafe_blocks += 1;
+                        }
+                        out.push(method_fingerprint);
+                    }
+                }
+            }
+            syn::Item::Trait(t) => {
+                for trait_item in &t.items {
+                    if let syn::TraitItem::Fn(method) = trait_item {
+                        if method.default.is_some() {
+                            let name = format!("{}{}::{}", prefix, t.ident, method.sig.ident);
+                            out.push(fingerprint(&name, "method", method.to_token_stream()));
+                        }
+                    }
+                }
+            }
+            syn::Item::Mod(module) => {
+                if let Some((_, items)) = &module.content {
+                    collect(items, &format!("{}{}::", prefix, module.ident), out);
+                }
+            }
+            syn::Item::Macro(m) => {
+                let name = m
+                    .ident
+                    .as_ref()
+                    .map(|ident| ident.to_string())
+                    .unwrap_or_else(|| {
+                        m.mac
+                            .path
+                            .segments
+                            .last()
+                            .map(|segment| segment.ident.to_string())
+                            .unwrap_or_default()
+                    });
+                out.push(fingerprint(
+                    &format!("{}{}", prefix, name),
+                    "macro",
+                    m.to_token_stream(),
+                ));
+            }
+            syn::Item::ForeignMod(foreign) => {
+                let mut foreign_fingerprint = fingerprint(
+                    &format!("{}extern", prefix),
+                    "extern",
+                    foreign.to_token_stream(),
+                );
+                foreign_fingerprint.raw_syscalls += 1;
+                out.push(foreign_fingerprint);
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn annotate(fingerprint: ComplexityFingerprint) -> ItemAnnotation {
+    let entry = lookup_orbit(&fingerprint);
+    ItemAnnotation {
+        security_level: security_level(&fingerprint),
+        orbit: LMFDBOrbitRef {
+            orbit_id: entry.orbit_id.to_string(),
+            complexity_class: entry.complexity_class.to_string(),
+            lmfdb_url: lmfdb_url(entry.orbit_id),
+        },
+        fingerprint,
+    }
+}
+
+pub fn annotate_source(source: &str) -> Result<Vec<ItemAnnotation>, String> {
+    let file = syn::parse_file(source).map_err(|e| format!("Failed to parse source: {}", e))?;
+    let mut fingerprints = Vec::new();
+    collect(&file.items, "", &mut fingerprints);
+    Ok(fingerprints.into_iter().map(annotate).collect())
+}
+
+pub fn annotate_file(path: &str) -> Result<Vec<ItemAnnotation>, String> {
+    let source =
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    annotate_source(&source)
+}
+
+/// Plugin metadata for a whole crate or file: its riskiest item sets both
+/// the security level and the orbit
+pub fn plugin_meta(name: &str, version: &str, annotations: &[ItemAnnotation]) -> PluginMeta {
+    let riskiest = annotations.iter().max_by_key(|annotation| rank(annotation));
+    PluginMeta {
+        name: name.to_string(),
+        version: version.to_string(),
+        security_level: riskiest
+            .map(|annotation| annotation.security_level)
+            .unwrap_or(SecurityLevel::Safe),
+        lmfdb_orbit: riskiest.map(|annotation| annotation.orbit.clone()),
+    }
+}
+
+/// Items the security audit should look at: privileged or critical ones,
+/// riskiest first
+pub fn audit_findings(annotations: &[ItemAnnotation]) -> Vec<&ItemAnnotation> {
+    let mut findings: Vec<&ItemAnnotation> = annotations
+        .iter()
+        .filter(|annotation| annotation.security_level >= SecurityLevel::Privileged)
+        .collect();
+    findings.sort_by_key(|annotation| std::cmp::Reverse(rank(annotation)));
+    findings
+}
+
+fn orbit_json(orbit: &LMFDBOrbitRef) -> serde_json::Value {
+    serde_json::json!({
+        "orbit_id": orbit.orbit_id,
+        "complexity_class": orbit.complexity_class,
+        "lmfdb_url": orbit.lmfdb_url,
+    })
+}
+
+fn annotation_json(annotation: &ItemAnnotation) -> serde_json::Value {
+    serde_json::json!({
+        "fingerprint": annotation.fingerprint,
+        "cyclomatic": annotation.fingerprint.cyclomatic(),
+        "orbit": orbit_json(&annotation.orbit),
+        "security_level": format!("{:?}", annotation.security_level),
+    })
+}
+
+/// The annotations file: plugin metadata, every item and the audit findings
+pub fn annotations_json(meta: &PluginMeta, annotations: &[ItemAnnotation]) -> serde_json::Value {
+    serde_json::json!({
+        "plugin": {
+            "name": meta.name,
+            "version": meta.version,
+            "security_level": format!("{:?}", meta.security_level),
+            "lmfdb_orbit": meta.lmfdb_orbit.as_ref().map(orbit_json),
+        },
+        "items": annotations.iter().map(annotation_json).collect::<Vec<_>>(),
+        "audit": audit_findings(annotations).into_iter().map(annotation_json).collect::<Vec<_>>(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"
+        fn add(a: u32, b: u32) -> u32 { a + b }
+
+        fn pick(x: Option<u32>) -> u32 {
+            match x { Some(v) => v, None => 0 }
+        }
+
+        fn load() -> String { std::fs::read_to_string("x").unwrap() }
+
+        fn pid() -> i32 { unsafe { libc::getpid() } }
+    "#;
+
+    fn find<'a>(annotations: &'a [ItemAnnotation], name: &str) -> &'a ItemAnnotation {
+        annotations
+            .iter()
+            .find(|annotation| annotation.fingerprint.item_name == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_orbits_and_security_levels() {
+        let annotations = annotate_source(SOURCE).unwrap();
+        assert_eq!(annotations.len(), 4);
+
+        let add = find(&annotations, "add");
+        assert_eq!(add.orbit.orbit_id, "11.a1");
+        assert_eq!(add.security_level, SecurityLevel::Safe);
+
+        // Two match arms and two levels of braces
+        let pick = find(&annotations, "pick");
+        assert_eq!(pick.fingerprint.cyclomatic(), 3);
+        assert_eq!(pick.fingerprint.max_nesting, 2);
+        assert_eq!(pick.orbit.complexity_class, "Cyclic");
+
+        assert_eq!(
+            find(&annotations, "load").security_level,
+            SecurityLevel::Controlled
+        );
+
+        let pid = find(&annotations, "pid");
+        assert_eq!(pid.orbit.orbit_id, "23.a5");
+        assert_eq!(
+            pid.orbit.lmfdb_url,
+            "https://www.lmfdb.org/EllipticCurve/Q/23/a/5"
+        );
+        assert_eq!(pid.security_level, SecurityLevel::Critical);
+    }
+
+    #[test]
+    fn test_annotations_json() {
+        let annotations = annotate_source(SOURCE).unwrap();
+        let meta = plugin_meta("sample", "0.1.0", &annotations);
+        let report = annotations_json(&meta, &annotations);
+
+        // The riskiest item sets the plugin's level and orbit
+        assert_eq!(report["plugin"]["name"], "sample");
+        assert_eq!(report["plugin"]["security_level"], "Critical");
+        assert_eq!(
+            report["plugin"]["lmfdb_orbit"]["complexity_class"],
+            "Monster"
+        );
+
+        assert_eq!(report["items"].as_array().unwrap().len(), 4);
+        assert_eq!(report["items"][1]["fingerprint"]["item_name"], "pick");
+        assert_eq!(report["items"][1]["cyclomatic"], 3);
+
+        let audit = report["audit"].as_array().unwrap();
+        assert_eq!(audit.len(), 1);
+        assert_eq!(audit[0]["fingerprint"]["item_name"], "pid");
+    }
+
+    #[test]
+    fn test_plugin_meta_without_items() {
+        let meta = plugin_meta("empty", "0.1.0", &[]);
+        assert_eq!(meta.security_level, SecurityLevel::Safe);
+        assert!(meta.lmfdb_orbit.is_none());
+    }
+}
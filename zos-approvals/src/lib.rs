@@ -0,0 +1,638 @@
+// ZOS Approvals - m-of-n co-signing for high-value actions
+// AGPL-3.0 License
+//
+// Payouts and treasury spends above a threshold wait for designated admin
+// wallets to co-sign them. Each pending action snapshots who may sign and
+// how many must, so changing the policy never changes an action already
+// asked for. Signatures are ed25519 over a fixed message naming the action,
+// its subject and amount. Everything that happens is kept as an audit trail,
+// and queued as a notice for whoever tells the approvers (the Telegram bot).
+
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Who co-signs, how many of them must, and above what amount
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ApprovalPolicy {
+    pub approvers: Vec<String>, // admin wallet addresses
+    pub required: usize,
+    pub threshold: f64, // amounts above this need approval
+    #[serde(default)]
+    pub token_thresholds: HashMap<String, f64>, // per token, instead of `threshold`
+    pub expiry_secs: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalStatus {
+    Pending,
+    Approved,
+    Rejected, // enough approvers declined that the rest can't reach `required`
+    Expired,
+    Executed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalEvent {
+    Requested,
+    Signed,
+    Declined,
+    Approved,
+    Rejected,
+    Expired,
+    Executed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoSignature {
+    pub approver: String,
+    pub signature: String, // base58 ed25519
+    pub signed_at: u64,
+    #[serde(default)]
+    pub reason: Option<String>, // given when declining
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingAction {
+    pub action_id: String,
+    pub kind: String,    // e.g. "payout", "treasury_spend"
+    pub subject: String, // id of the payout, proposal, ...
+    pub token: String,
+    pub amount: f64,
+    pub summary: String,
+    pub approvers: Vec<String>,
+    pub required: usize,
+    pub requested_at: u64,
+    pub expires_at: u64,
+    pub approvals: Vec<CoSignature>,
+    pub rejections: Vec<CoSignature>,
+    pub status: ApprovalStatus,
+}
+
+impl PendingAction {
+    /// What an approver signs to approve (or decline) this action
+    pub fn signing_message(&self, approve: bool) -> String {
+        format!(
+            "zos-{}:{}:{}:{}:{} {}",
+            if approve { "approve" } else { "decline" },
+            self.action_id,
+            self.kind,
+            self.subject,
+            self.amount,
+            self.token
+        )
+    }
+
+    fn signed_by(&self, approver: &str) -> bool {
+        self.approvals
+            .iter()
+            .chain(&self.rejections)
+            .any(|signature| signature.approver == approver)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub at: u64,
+    pub action_id: String,
+    pub event: ApprovalEvent,
+    pub actor: String,
+    #[serde(default)]
+    pub detail: Option<String>,
+}
+
+/// An action changed in a way its approvers should hear about
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalNotice {
+    pub event: ApprovalEvent,
+    pub action: PendingAction,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ApprovalBook {
+    pub actions: HashMap<String, PendingAction>,
+    pub audit: Vec<AuditEntry>,
+    pub notices: Vec<ApprovalNotice>, // not yet delivered to approvers
+    pub seq: u64,
+}
+
+/// Check an ed25519 signature from a Solana-style wallet (base58 public key
+/// and signature)
+pub fn verify_wallet_signature(
+    wallet_address: &str,
+    message: &str,
+    signature: &str,
+) -> Result<(), String> {
+    let public_key: [u8; 32] = bs58::decode(wallet_address)
+        .into_vec()
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or("Wallet address is not an ed25519 public key")?;
+    let verifying_key =
+        VerifyingKey::from_bytes(&public_key).map_err(|e| format!("Invalid wallet key: {}", e))?;
+    let signature_bytes: [u8; 64] = bs58::decode(signature)
+        .into_vec()
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or("Signature must be 64 bytes, base58")?;
+    verifying_key
+        .verify(message.as_bytes(), &Signature::from_bytes(&signature_bytes))
+        .map_err(|_| "Signature does not match approver wallet".to_string())
+}
+
+impl ApprovalPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.approvers.is_empty() {
+            return Ok(());
+        }
+        if self.required == 0 || self.required > self.approvers.len() {
+            return Err(format!(
+                "Approvals required must be between 1 and {} approvers",
+                self.approvers.len()
+            ));
+        }
+        if self.expiry_secs == 0 {
+            return Err("Approval expiry must be positive".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn threshold_for(&self, token: &str) -> f64 {
+        self.token_thresholds
+            .get(token)
+            .copied()
+            .unwrap_or(self.threshold)
+    }
+
+    /// True when `amount` of `token` needs co-signing; never without approvers
+    pub fn requires_approval(&self, token: &str, amount: f64) -> bool {
+        !self.approvers.is_empty() && amount > self.threshold_for(token)
+    }
+}
+
+impl ApprovalBook {
+    fn log(
+        &mut self,
+        action_id: &str,
+        event: ApprovalEvent,
+        actor: &str,
+        detail: Option<String>,
+        now: u64,
+    ) {
+        self.audit.push(AuditEntry {
+            at: now,
+            action_id: action_id.to_string(),
+            event,
+            actor: actor.to_string(),
+            detail,
+        });
+    }
+
+    fn notify(&mut self, event: ApprovalEvent, action_id: &str) {
+        if let Some(action) = self.actions.get(action_id) {
+            self.notices.push(ApprovalNotice {
+                event,
+                action: action.clone(),
+            });
+        }
+    }
+
+    /// The most recent action for a subject, whatever its status
+    pub fn find(&self, kind: &str, subject: &str) -> Option<&PendingAction> {
+        self.actions
+            .values()
+            .filter(|action| action.kind == kind && action.subject == subject)
+            .max_by_key(|action| action.requested_at)
+    }
+
+    /// Ask the policy's approvers to co-sign an action. A subject already
+    /// pending or approved gets its existing action back.
+    #[allow(clippy::too_many_arguments)]
+    pub fn request(
+        &mut self,
+        policy: &ApprovalPolicy,
+        kind: &str,
+        subject: &str,
+        token: &str,
+        amount: f64,
+        summary: &str,
+        requested_by: &str,
+        now: u64,
+    ) -> Result<PendingAction, String> {
+        policy.validate()?;
+        if policy.approvers.is_empty() {
+            return Err("No approvers configured".to_string());
+        }
+        if let Some(existing) = self.find(kind, subject).filter(|action| {
+            matches!(
+                action.status,
+                ApprovalStatus::Pending | ApprovalStatus::Approved
+            )
+        }) {
+            return Ok(existing.clone());
+        }
+
+        self.seq += 1;
+        let action = PendingAction {
+            action_id: format!("appr_{}_{}", now, self.seq),
+            kind: kind.to_string(),
+            subject: subject.to_string(),
+            token: token.to_string(),
+            amount,
+            summary: summary.to_string(),
+            approvers: policy.approvers.clone(),
+            required: policy.required,
+            requested_at: now,
+            expires_at: now + policy.expiry_secs,
+            approvals: Vec::new(),
+            rejections: Vec::new(),
+            status: ApprovalStatus::Pending,
+        };
+        let action_id = action.action_id.clone();
+        self.actions.insert(action_id.clone(), action.clone());
+        self.log(
+            &action_id,
+            ApprovalEvent::Requested,
+            requested_by,
+            Some(summary.to_string()),
+            now,
+        );
+        self.notify(ApprovalEvent::Requested, &action_id);
+        Ok(action)
+    }
+
+    /// Record an approver's signature for or against an action; returns it
+    /// with its new status
+    pub fn sign(
+        &mut self,
+        action_id: &str,
+        approver: &str,
+        signature: &str,
+        approve: bool,
+        reason: Option<String>,
+        now: u64,
+    ) -> Result<PendingAction, String> {
+        self.expire(now);
+        let action = self
+            .actions
+            .get(action_id)
+            .ok_or("Approval request not found")?;
+        if action.status != ApprovalStatus::Pending {
+            return Err(format!("Approval request is {:?}", action.status));
+        }
+        if !action.approvers.iter().any(|allowed| allowed == approver) {
+            return Err("Not an approver for this request".to_string());
+        }
+        if action.signed_by(approver) {
+            return Err("Approver has already signed this request".to_string());
+        }
+        verify_wallet_signature(approver, &action.signing_message(approve), signature)?;
+
+        let co_signature = CoSignature {
+            approver: approver.to_string(),
+            signature: signature.to_string(),
+            signed_at: now,
+            reason: reason.clone(),
+        };
+        let action = self
+            .actions
+            .get_mut(action_id)
+            .ok_or("Approval request not found")?;
+        let outcome = if approve {
+            action.approvals.push(co_signature);
+            (action.approvals.len() >= action.required).then_some(ApprovalStatus::Approved)
+        } else {
+            action.rejections.push(co_signature);
+            (action.approvers.len() - action.rejections.len() < action.required)
+                .then_some(ApprovalStatus::Rejected)
+        };
+        if let Some(status) = outcome {
+            action.status = status;
+        }
+
+        let event = if approve {
+            ApprovalEvent::Signed
+        } else {
+            ApprovalEvent::Declined
+        };
+        self.log(action_id, event, approver, reason, now);
+        match outcome {
+            Some(ApprovalStatus::Approved) => {
+                self.log(action_id, ApprovalEvent::Approved, approver, None, now);
+                self.notify(ApprovalEvent::Approved, action_id);
+            }
+            Some(_) => {
+                self.log(action_id, ApprovalEvent::Rejected, approver, None, now);
+                self.notify(ApprovalEvent::Rejected, action_id);
+            }
+            None => {}
+        }
+        self.actions
+            .get(action_id)
+            .cloned()
+            .ok_or("Approval request not found".to_string())
+    }
+
+    /// Expire pending actions past their deadline; returns the ones expired now
+    pub fn expire(&mut self, now: u64) -> Vec<PendingAction> {
+        let expired: Vec<String> = self
+            .actions
+            .values()
+            .filter(|action| action.status == ApprovalStatus::Pending && action.expires_at <= now)
+            .map(|action| action.action_id.clone())
+            .collect();
+
+        let mut actions = Vec::new();
+        for action_id in expired {
+            if let Some(action) = self.actions.get_mut(&action_id) {
+                action.status = ApprovalStatus::Expired;
+                actions.push(action.clone());
+            }
+            self.log(&action_id, ApprovalEvent::Expired, "system", None, now);
+            self.notify(ApprovalEvent::Expired, &action_id);
+        }
+        actions
+    }
+
+    /// The approved action was carried out; it cannot be used again
+    pub fn mark_executed(&mut self, action_id: &str, actor: &str, now: u64) -> Result<(), String> {
+        let action = self
+            .actions
+            .get_mut(action_id)
+            .ok_or("Approval request not found")?;
+        if action.status != ApprovalStatus::Approved {
+            return Err(format!(
+                "Approval request is {:?}, not approved",
+                action.status
+            ));
+        }
+        action.status = ApprovalStatus::Executed;
+        self.log(action_id, ApprovalEvent::Executed, actor, None, now);
+        self.notify(ApprovalEvent::Executed, action_id);
+        Ok(())
+    }
+
+    pub fn audit_trail(&self, action_id: &str) -> Vec<&AuditEntry> {
+        self.audit
+            .iter()
+            .filter(|entry| entry.action_id == action_id)
+            .collect()
+    }
+
+    pub fn pending(&self) -> Vec<&PendingAction> {
+        let mut pending: Vec<&PendingAction> = self
+            .actions
+            .values()
+            .filter(|action| action.status == ApprovalStatus::Pending)
+            .collect();
+        pending.sort_by_key(|action| action.requested_at);
+        pending
+    }
+
+    /// Notices for the approvers' channel, oldest first; each is handed out once
+    pub fn take_notices(&mut self) -> Vec<ApprovalNotice> {
+        std::mem::take(&mut self.notices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn wallet(seed: u8) -> (SigningKey, String) {
+        let key = SigningKey::from_bytes(&[seed; 32]);
+        let address = bs58::encode(key.verifying_key().as_bytes()).into_string();
+        (key, address)
+    }
+
+    fn sign(key: &SigningKey, message: &str) -> String {
+        bs58::encode(key.sign(message.as_bytes()).to_bytes()).into_string()
+    }
+
+    fn policy(approvers: &[String]) -> ApprovalPolicy {
+        ApprovalPolicy {
+            approvers: approvers.to_vec(),
+            required: 2,
+            threshold: 1000.0,
+            token_thresholds: HashMap::new(),
+            expiry_secs: 3600,
+        }
+    }
+
+    #[test]
+    fn two_of_three_approves() {
+        let wallets: Vec<_> = (1..=3).map(wallet).collect();
+        let addresses: Vec<String> = wallets.iter().map(|(_, address)| address.clone()).collect();
+        let policy = policy(&addresses);
+        assert!(!policy.requires_approval("USDC", 1000.0));
+        assert!(policy.requires_approval("USDC", 1000.01));
+
+        let mut book = ApprovalBook::default();
+        let action = book
+            .request(
+                &policy,
+                "payout",
+                "payout_1",
+                "USDC",
+                5000.0,
+                "5000 USDC to w",
+                "operator",
+                100,
+            )
+            .unwrap();
+        let message = action.signing_message(true);
+
+        let first = book
+            .sign(
+                &action.action_id,
+                &addresses[0],
+                &sign(&wallets[0].0, &message),
+                true,
+                None,
+                110,
+            )
+            .unwrap();
+        assert_eq!(first.status, ApprovalStatus::Pending);
+        assert!(book
+            .sign(
+                &action.action_id,
+                &addresses[0],
+                &sign(&wallets[0].0, &message),
+                true,
+                None,
+                111
+            )
+            .is_err());
+
+        let second = book
+            .sign(
+                &action.action_id,
+                &addresses[2],
+                &sign(&wallets[2].0, &message),
+                true,
+                None,
+                120,
+            )
+            .unwrap();
+        assert_eq!(second.status, ApprovalStatus::Approved);
+        book.mark_executed(&action.action_id, "host", 130).unwrap();
+        assert!(book.mark_executed(&action.action_id, "host", 131).is_err());
+
+        let events: Vec<ApprovalEvent> = book
+            .audit_trail(&action.action_id)
+            .iter()
+            .map(|entry| entry.event)
+            .collect();
+        assert_eq!(
+            events,
+            [
+                ApprovalEvent::Requested,
+                ApprovalEvent::Signed,
+                ApprovalEvent::Signed,
+                ApprovalEvent::Approved,
+                ApprovalEvent::Executed
+            ]
+        );
+        assert_eq!(book.take_notices().len(), 3); // requested, approved, executed
+    }
+
+    #[test]
+    fn forged_or_outside_signatures_are_refused() {
+        let wallets: Vec<_> = (1..=3).map(wallet).collect();
+        let addresses: Vec<String> = wallets.iter().map(|(_, address)| address.clone()).collect();
+        let (outsider_key, outsider) = wallet(9);
+        let mut book = ApprovalBook::default();
+        let action = book
+            .request(
+                &policy(&addresses),
+                "payout",
+                "payout_1",
+                "USDC",
+                5000.0,
+                "",
+                "operator",
+                100,
+            )
+            .unwrap();
+        let message = action.signing_message(true);
+
+        assert!(book
+            .sign(
+                &action.action_id,
+                &outsider,
+                &sign(&outsider_key, &message),
+                true,
+                None,
+                110
+            )
+            .is_err());
+        // Signed by another approver's key
+        assert!(book
+            .sign(
+                &action.action_id,
+                &addresses[0],
+                &sign(&wallets[1].0, &message),
+                true,
+                None,
+                110
+            )
+            .is_err());
+        // An approval signature can't be replayed as a decline
+        assert!(book
+            .sign(
+                &action.action_id,
+                &addresses[0],
+                &sign(&wallets[0].0, &message),
+                false,
+                None,
+                110
+            )
+            .is_err());
+        assert!(book.actions[&action.action_id].approvals.is_empty());
+    }
+
+    #[test]
+    fn declines_reject_once_approval_is_out_of_reach() {
+        let wallets: Vec<_> = (1..=3).map(wallet).collect();
+        let addresses: Vec<String> = wallets.iter().map(|(_, address)| address.clone()).collect();
+        let mut book = ApprovalBook::default();
+        let action = book
+            .request(
+                &policy(&addresses),
+                "treasury_spend",
+                "tspend_1",
+                "SOLFUNMEME",
+                5000.0,
+                "",
+                "dao",
+                100,
+            )
+            .unwrap();
+        let message = action.signing_message(false);
+
+        let first = book
+            .sign(
+                &action.action_id,
+                &addresses[0],
+                &sign(&wallets[0].0, &message),
+                false,
+                Some("unknown recipient".to_string()),
+                110,
+            )
+            .unwrap();
+        assert_eq!(first.status, ApprovalStatus::Pending);
+        let second = book
+            .sign(
+                &action.action_id,
+                &addresses[1],
+                &sign(&wallets[1].0, &message),
+                false,
+                None,
+                120,
+            )
+            .unwrap();
+        assert_eq!(second.status, ApprovalStatus::Rejected);
+    }
+
+    #[test]
+    fn unsigned_requests_expire() {
+        let addresses: Vec<String> = (1..=3).map(|seed| wallet(seed).1).collect();
+        let mut book = ApprovalBook::default();
+        let action = book
+            .request(
+                &policy(&addresses),
+                "payout",
+                "payout_1",
+                "USDC",
+                5000.0,
+                "",
+                "operator",
+                100,
+            )
+            .unwrap();
+
+        assert!(book.expire(3699).is_empty());
+        assert_eq!(book.expire(3700).len(), 1);
+        assert_eq!(
+            book.actions[&action.action_id].status,
+            ApprovalStatus::Expired
+        );
+        // A fresh request for the same payout starts over
+        let again = book
+            .request(
+                &policy(&addresses),
+                "payout",
+                "payout_1",
+                "USDC",
+                5000.0,
+                "",
+                "operator",
+                3800,
+            )
+            .unwrap();
+        assert_ne!(again.action_id, action.action_id);
+    }
+}
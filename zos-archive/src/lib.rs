@@ -0,0 +1,450 @@
+// ZOS Archive - cold storage for old ledger records
+// AGPL-3.0 License
+//
+// Records past their retention leave the hot store in sealed segments:
+// gzip'd JSON lines, immutable once written, each hashed together with the
+// previous segment's hash so the chain shows if any was altered or lost.
+// The index of segments stays with the hot store; the segments themselves
+// go to a directory or an S3 bucket and are read back on demand.
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// Most records one segment holds
+pub const MAX_SEGMENT_RECORDS: usize = 10_000;
+
+/// The chain's starting point
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// sha256 of the previous segment's hash followed by this segment's bytes
+pub fn link_hash(prev_hash: &str, bytes: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(prev_hash.as_bytes());
+    hasher.update(bytes);
+    hex(&hasher.finalize())
+}
+
+fn write_line<T: Serialize>(encoder: &mut GzEncoder<Vec<u8>>, value: &T) -> Result<(), String> {
+    let line =
+        serde_json::to_string(value).map_err(|e| format!("Failed to serialize record: {}", e))?;
+    encoder
+        .write_all(line.as_bytes())
+        .and_then(|_| encoder.write_all(b"\n"))
+        .map_err(|e| format!("Failed to compress segment: {}", e))
+}
+
+/// Where sealed segments are kept
+pub trait ArchiveStore: Send {
+    /// Write a segment; segments are never overwritten
+    fn put(&mut self, key: &str, bytes: &[u8]) -> Result<(), String>;
+    fn get(&self, key: &str) -> Result<Vec<u8>, String>;
+    /// Where this store points, for logs and status pages
+    fn location(&self) -> String;
+}
+
+/// Segments as files under a directory, one per key
+#[derive(Debug)]
+pub struct FileArchive {
+    dir: PathBuf,
+}
+
+impl FileArchive {
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self, String> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        Ok(Self { dir })
+    }
+}
+
+impl ArchiveStore for FileArchive {
+    fn put(&mut self, key: &str, bytes: &[u8]) -> Result<(), String> {
+        let path = self.dir.join(key);
+        if path.exists() {
+            return Err(format!("Segment {} already exists", path.display()));
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        // Written aside and renamed, so a crash never leaves half a segment
+        let partial = path.with_extension("partial");
+        let mut file = std::fs::File::create(&partial)
+            .map_err(|e| format!("Failed to create {}: {}", partial.display(), e))?;
+        file.write_all(bytes)
+            .and_then(|_| file.sync_all())
+            .map_err(|e| format!("Failed to write {}: {}", partial.display(), e))?;
+        std::fs::rename(&partial, &path)
+            .map_err(|e| format!("Failed to seal {}: {}", path.display(), e))
+    }
+
+    fn get(&self, key: &str) -> Result<Vec<u8>, String> {
+        let path = self.dir.join(key);
+        std::fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+    }
+
+    fn location(&self) -> String {
+        self.dir.display().to_string()
+    }
+}
+
+/// Segments in an S3 bucket, through the aws CLI and its usual credentials
+#[derive(Debug)]
+pub struct S3Archive {
+    url: String, // s3://bucket/prefix
+}
+
+impl S3Archive {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    fn aws(&self, args: &[&str], input: Option<&[u8]>) -> Result<Vec<u8>, String> {
+        let mut child = Command::new("aws")
+            .args(args)
+            .stdin(if input.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to run aws: {}", e))?;
+        if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+            stdin
+                .write_all(input)
+                .map_err(|e| format!("Failed to stream to aws: {}", e))?;
+        }
+        let output = child
+            .wait_with_output()
+            .map_err(|e| format!("aws failed: {}", e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "aws {}: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(output.stdout)
+    }
+}
+
+impl ArchiveStore for S3Archive {
+    fn put(&mut self, key: &str, bytes: &[u8]) -> Result<(), String> {
+        let url = format!("{}/{}", self.url, key);
+        if self
+            .aws(&["s3", "ls", &url], None)
+            .is_ok_and(|listing| !listing.is_empty())
+        {
+            return Err(format!("Segment {} already exists", url));
+        }
+        self.aws(&["s3", "cp", "-", &url], Some(bytes)).map(|_| ())
+    }
+
+    fn get(&self, key: &str) -> Result<Vec<u8>, String> {
+        self.aws(&["s3", "cp", &format!("{}/{}", self.url, key), "-"], None)
+    }
+
+    fn location(&self) -> String {
+        self.url.clone()
+    }
+}
+
+/// ZOS_ARCHIVE_LOCATION: an s3://bucket/prefix URL or a directory
+/// (default ./archive)
+pub fn store_from_env() -> Result<Box<dyn ArchiveStore>, String> {
+    let location = std::env::var("ZOS_ARCHIVE_LOCATION").unwrap_or_else(|_| "archive".to_string());
+    if location.starts_with("s3://") {
+        Ok(Box::new(S3Archive::new(&location)))
+    } else {
+        Ok(Box::new(FileArchive::open(location)?))
+    }
+}
+
+/// First line of every segment, so a segment describes itself without the index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SegmentHeader {
+    stream: String,
+    seq: u64,
+    prev_hash: String,
+    records: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SegmentMeta {
+    pub stream: String, // e.g. "commissions"
+    pub seq: u64,
+    pub key: String, // object name in the store
+    pub records: usize,
+    pub from_ts: u64, // oldest record
+    pub to_ts: u64,   // newest record
+    pub bytes: usize,
+    pub prev_hash: String,
+    pub hash: String,
+    pub sealed_at: u64,
+}
+
+/// The index of one stream's segments, oldest first; kept with the hot store
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Archive {
+    pub segments: Vec<SegmentMeta>,
+}
+
+impl Archive {
+    pub fn head_hash(&self) -> &str {
+        self.segments
+            .last()
+            .map(|segment| segment.hash.as_str())
+            .unwrap_or(GENESIS_HASH)
+    }
+
+    pub fn records(&self) -> usize {
+        self.segments.iter().map(|segment| segment.records).sum()
+    }
+
+    /// Seal up to MAX_SEGMENT_RECORDS records into the next segment and
+    /// write it. The index only grows once the store has the segment, so
+    /// the caller drops records from its hot store only on success.
+    pub fn seal<T: Serialize>(
+        &mut self,
+        store: &mut dyn ArchiveStore,
+        stream: &str,
+        records: &[T],
+        timestamp: impl Fn(&T) -> u64,
+        now: u64,
+    ) -> Result<SegmentMeta, String> {
+        if records.is_empty() || records.len() > MAX_SEGMENT_RECORDS {
+            return Err(format!(
+                "A segment holds 1 to {} records, not {}",
+                MAX_SEGMENT_RECORDS,
+                records.len()
+            ));
+        }
+        let seq = self.segments.len() as u64;
+        let prev_hash = self.head_hash().to_string();
+        let header = SegmentHeader {
+            stream: stream.to_string(),
+            seq,
+            prev_hash: prev_hash.clone(),
+            records: records.len(),
+        };
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        write_line(&mut encoder, &header)?;
+        for record in records {
+            write_line(&mut encoder, record)?;
+        }
+        let bytes = encoder
+            .finish()
+            .map_err(|e| format!("Failed to compress segment: {}", e))?;
+
+        let key = format!("{}/{:08}.jsonl.gz", stream, seq);
+        store.put(&key, &bytes)?;
+        let meta = SegmentMeta {
+            stream: stream.to_string(),
+            seq,
+            key,
+            records: records.len(),
+            from_ts: records.iter().map(&timestamp).min().unwrap_or(0),
+            to_ts: records.iter().map(&timestamp).max().unwrap_or(0),
+            bytes: bytes.len(),
+            hash: link_hash(&prev_hash, &bytes),
+            prev_hash,
+            sealed_at: now,
+        };
+        self.segments.push(meta.clone());
+        Ok(meta)
+    }
+
+    /// A segment's records, after checking its bytes against the index
+    pub fn read<T: DeserializeOwned>(
+        store: &dyn ArchiveStore,
+        meta: &SegmentMeta,
+    ) -> Result<Vec<T>, String> {
+        let bytes = store.get(&meta.key)?;
+        if link_hash(&meta.prev_hash, &bytes) != meta.hash {
+            return Err(format!("Segment {} does not match its hash", meta.key));
+        }
+        let mut text = String::new();
+        GzDecoder::new(bytes.as_slice())
+            .read_to_string(&mut text)
+            .map_err(|e| format!("Failed to decompress {}: {}", meta.key, e))?;
+        text.lines()
+            .skip(1) // header
+            .map(|line| {
+                serde_json::from_str(line)
+                    .map_err(|e| format!("Corrupt record in {}: {}", meta.key, e))
+            })
+            .collect()
+    }
+
+    /// Records from segments overlapping [from, to] that pass `filter`;
+    /// reads every such segment, so slower than the hot store
+    pub fn query<T: DeserializeOwned>(
+        &self,
+        store: &dyn ArchiveStore,
+        from: u64,
+        to: u64,
+        filter: impl Fn(&T) -> bool,
+    ) -> Result<Vec<T>, String> {
+        let mut found = Vec::new();
+        for meta in self
+            .segments
+            .iter()
+            .filter(|meta| meta.to_ts >= from && meta.from_ts <= to)
+        {
+            found.extend(Self::read::<T>(store, meta)?.into_iter().filter(&filter));
+        }
+        Ok(found)
+    }
+
+    /// Walk the chain: every segment present, unaltered and linked to the
+    /// one before it. Returns how many segments were checked.
+    pub fn verify(&self, store: &dyn ArchiveStore) -> Result<usize, String> {
+        let mut prev_hash = GENESIS_HASH.to_string();
+        for meta in &self.segments {
+            if meta.prev_hash != prev_hash {
+                return Err(format!(
+                    "Segment {} is not linked to the one before it",
+                    meta.key
+                ));
+            }
+            let bytes = store.get(&meta.key)?;
+            if link_hash(&prev_hash, &bytes) != meta.hash {
+                return Err(format!("Segment {} does not match its hash", meta.key));
+            }
+            prev_hash = meta.hash.clone();
+        }
+        Ok(self.segments.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Entry {
+        wallet: String,
+        timestamp: u64,
+    }
+
+    fn entries(count: u64) -> Vec<Entry> {
+        (0..count)
+            .map(|i| Entry {
+                wallet: format!("wallet{}", i % 3),
+                timestamp: 1_000 + i,
+            })
+            .collect()
+    }
+
+    fn temp_store(name: &str) -> FileArchive {
+        let dir = std::env::temp_dir().join(format!("zos-archive-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        FileArchive::open(dir).unwrap()
+    }
+
+    #[test]
+    fn sealed_segments_read_back() {
+        let mut store = temp_store("roundtrip");
+        let mut archive = Archive::default();
+        let records = entries(10);
+        let sealed = archive
+            .seal(
+                &mut store,
+                "payments",
+                &records,
+                |entry| entry.timestamp,
+                5_000,
+            )
+            .unwrap();
+        assert_eq!((sealed.from_ts, sealed.to_ts), (1_000, 1_009));
+        let empty: &[Entry] = &[];
+        assert!(archive
+            .seal(
+                &mut store,
+                "payments",
+                empty,
+                |entry| entry.timestamp,
+                5_000
+            )
+            .is_err());
+
+        let read: Vec<Entry> = Archive::read(&store, &sealed).unwrap();
+        assert_eq!(read, records);
+        let wallet0: Vec<Entry> = archive
+            .query(&store, 0, u64::MAX, |entry: &Entry| {
+                entry.wallet == "wallet0"
+            })
+            .unwrap();
+        assert_eq!(wallet0.len(), 4);
+    }
+
+    #[test]
+    fn segments_chain_and_detect_tampering() {
+        let mut store = temp_store("chain");
+        let mut archive = Archive::default();
+        archive
+            .seal(&mut store, "logs", &entries(3), |entry| entry.timestamp, 1)
+            .unwrap();
+        archive
+            .seal(&mut store, "logs", &entries(4), |entry| entry.timestamp, 2)
+            .unwrap();
+        assert_eq!(archive.segments[1].prev_hash, archive.segments[0].hash);
+        assert_eq!(archive.verify(&store).unwrap(), 2);
+
+        let path = store.dir.join(&archive.segments[0].key);
+        let mut bytes = std::fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        std::fs::write(&path, bytes).unwrap();
+        assert!(archive.verify(&store).is_err());
+        assert!(Archive::read::<Entry>(&store, &archive.segments[0]).is_err());
+    }
+
+    #[test]
+    fn segments_are_never_overwritten() {
+        let mut store = temp_store("overwrite");
+        store.put("logs/00000000.jsonl.gz", b"first").unwrap();
+        assert!(store.put("logs/00000000.jsonl.gz", b"second").is_err());
+    }
+
+    #[test]
+    fn queries_skip_segments_outside_the_range() {
+        let mut store = temp_store("range");
+        let mut archive = Archive::default();
+        archive
+            .seal(&mut store, "logs", &entries(5), |entry| entry.timestamp, 1)
+            .unwrap();
+        let later: Vec<Entry> = entries(5)
+            .into_iter()
+            .map(|entry| Entry {
+                timestamp: entry.timestamp + 10_000,
+                ..entry
+            })
+            .collect();
+        archive
+            .seal(&mut store, "logs", &later, |entry| entry.timestamp, 2)
+            .unwrap();
+
+        std::fs::remove_file(store.dir.join(&archive.segments[0].key)).unwrap();
+        let found: Vec<Entry> = archive
+            .query(&store, 11_000, u64::MAX, |_: &Entry| true)
+            .unwrap();
+        assert_eq!(found.len(), 5);
+    }
+}
@@ -0,0 +1,386 @@
+use serde::{Deserialize, Serialize};
+use crate::{AllocationType, CommunityResourceEconomy};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BondingPolicy {
+    pub min_bond: u64,              // below this a server earns nothing
+    pub full_allocation_bond: u64,  // bond at which a server earns its full token_allocation
+    pub min_uptime_percentage: f32, // heartbeat reports below this count as a strike
+    pub strikes_before_slash: u32,
+    pub downtime_slash_percentage: f32,
+    pub fraud_slash_percentage: f32,
+    pub unbonding_period_secs: u64,
+}
+
+impl Default for BondingPolicy {
+    fn default() -> Self {
+        Self {
+            min_bond: 1_000,
+            full_allocation_bond: 10_000,
+            min_uptime_percentage: 95.0,
+            strikes_before_slash: 3,
+            downtime_slash_percentage: 5.0,
+            fraud_slash_percentage: 50.0,
+            unbonding_period_secs: 7 * 24 * 3600,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperatorBond {
+    pub server_id: String,
+    pub operator_id: String,
+    pub bonded: u64,
+    pub activation_grant: Option<u64>, // registration allocation, paid once bonded
+    pub downtime_strikes: u32,
+    pub unbonding_since: Option<u64>,
+    pub slash_history: Vec<SlashEvent>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlashEvent {
+    pub amount: u64,
+    pub reason: String,
+    pub slashed_at: u64,
+    #[serde(default)]
+    pub fraud: bool, // fraud slashes are never insured
+}
+
+impl OperatorBond {
+    pub fn new(server_id: &str, operator_id: &str, activation_grant: u64) -> Self {
+        Self {
+            server_id: server_id.to_string(),
+            operator_id: operator_id.to_string(),
+            bonded: 0,
+            activation_grant: Some(activation_grant),
+            downtime_strikes: 0,
+            unbonding_since: None,
+            slash_history: Vec::new(),
+        }
+    }
+
+    pub fn slashed_total(&self) -> u64 {
+        self.slash_history.iter().map(|event| event.amount).sum()
+    }
+}
+
+impl CommunityResourceEconomy {
+    /// Tokens an account holds in the ledger, minus what has been slashed
+    pub fn token_balance(&self, account_id: &str) -> u64 {
+        let allocated: u64 = self.token_distribution.values()
+            .filter(|allocation| allocation.recipient_id == account_id)
+            .map(|allocation| allocation.amount)
+            .sum();
+
+        allocated.saturating_sub(self.slashed_from(account_id))
+    }
+
+    pub fn slashed_from(&self, account_id: &str) -> u64 {
+        self.operator_bonds.values()
+            .filter(|bond| bond.operator_id == account_id)
+            .map(|bond| bond.slashed_total())
+            .sum()
+    }
+
+    pub(crate) fn bonded_by(&self, account_id: &str) -> u64 {
+        self.operator_bonds.values()
+            .filter(|bond| bond.operator_id == account_id)
+            .map(|bond| bond.bonded)
+            .sum()
+    }
+
+    /// Share of token_allocation a server earns: 0 below the minimum bond or
+    /// while unbonding, scaling linearly to 1 at the full allocation bond
+    pub fn bond_factor(&self, server_id: &str) -> f32 {
+        let bond = match self.operator_bonds.get(server_id) {
+            Some(bond) if bond.unbonding_since.is_none() => bond,
+            _ => return 0.0,
+        };
+
+        if bond.bonded < self.bonding_policy.min_bond {
+            return 0.0;
+        }
+
+        (bond.bonded as f32 / self.bonding_policy.full_allocation_bond as f32).min(1.0)
+    }
+
+    pub fn effective_token_allocation(&self, server_id: &str) -> u64 {
+        self.servers.get(server_id)
+            .map(|server| (server.token_allocation as f32 * self.bond_factor(server_id)) as u64)
+            .unwrap_or(0)
+    }
+
+    /// Lock tokens behind a server. The registration grant is paid the first
+    /// time the bond reaches the minimum, scaled by bond size.
+    pub fn bond_stake(&mut self, server_id: &str, operator_id: &str, amount: u64) -> Result<u64, String> {
+        let available = self.token_balance(operator_id).saturating_sub(self.bonded_by(operator_id));
+        if amount > available {
+            return Err(format!("Insufficient unbonded balance: {} available", available));
+        }
+
+        let bond = self.operator_bonds.get_mut(server_id)
+            .ok_or("Server not found")?;
+        if bond.operator_id != operator_id {
+            return Err("Only the server operator can bond stake".to_string());
+        }
+        if bond.unbonding_since.is_some() {
+            return Err("Server is unbonding".to_string());
+        }
+
+        bond.bonded += amount;
+        let total = bond.bonded;
+        println!("🔒 {} bonded {} tokens to {} (total {})", operator_id, amount, server_id, total);
+
+        if total >= self.bonding_policy.min_bond {
+            if let Some(grant) = self.operator_bonds.get_mut(server_id).and_then(|b| b.activation_grant.take()) {
+                let scaled = (grant as f32 * self.bond_factor(server_id)) as u64;
+                self.distribute_tokens(server_id, operator_id, AllocationType::ServerContribution, scaled)?;
+                println!("✅ Server {} activated, {} tokens granted", server_id, scaled);
+            }
+        }
+
+        Ok(total)
+    }
+
+    pub fn request_unbond(&mut self, server_id: &str, operator_id: &str) -> Result<u64, String> {
+        let bond = self.operator_bonds.get_mut(server_id)
+            .ok_or("Server not found")?;
+        if bond.operator_id != operator_id {
+            return Err("Only the server operator can unbond".to_string());
+        }
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        bond.unbonding_since.get_or_insert(now);
+
+        Ok(now + self.bonding_policy.unbonding_period_secs)
+    }
+
+    /// Release the bond after the unbonding period; slashes still apply until then
+    pub fn withdraw_bond(&mut self, server_id: &str, operator_id: &str) -> Result<u64, String> {
+        let period = self.bonding_policy.unbonding_period_secs;
+        let bond = self.operator_bonds.get_mut(server_id)
+            .ok_or("Server not found")?;
+        if bond.operator_id != operator_id {
+            return Err("Only the server operator can withdraw".to_string());
+        }
+
+        let since = bond.unbonding_since.ok_or("Request unbonding first")?;
+        let now = chrono::Utc::now().timestamp() as u64;
+        if now < since + period {
+            return Err(format!("Bond unlocks in {} seconds", since + period - now));
+        }
+
+        let released = std::mem::take(&mut bond.bonded);
+        println!("🔓 {} withdrew {} tokens from {}", operator_id, released, server_id);
+        Ok(released)
+    }
+
+    /// Feed uptime measured by the heartbeat verifier. Sustained downtime
+    /// slashes the bond; returns the amount slashed.
+    pub fn record_uptime_report(&mut self, server_id: &str, uptime_percentage: f32) -> Result<u64, String> {
+        let server = self.servers.get_mut(server_id)
+            .ok_or("Server not found")?;
+        server.uptime_percentage = uptime_percentage;
+
+        let policy = self.bonding_policy.clone();
+        let bond = self.operator_bonds.get_mut(server_id)
+            .ok_or("Server has no bond")?;
+
+        if uptime_percentage >= policy.min_uptime_percentage {
+            bond.downtime_strikes = 0;
+            return Ok(0);
+        }
+
+        bond.downtime_strikes += 1;
+        if bond.downtime_strikes < policy.strikes_before_slash {
+            return Ok(0);
+        }
+
+        bond.downtime_strikes = 0;
+        self.slash_bond(server_id, policy.downtime_slash_percentage,
+                        &format!("Uptime {:.1}% below {:.1}%", uptime_percentage, policy.min_uptime_percentage), false)
+    }
+
+    /// Slash for resource claims the verifier proved false
+    pub fn slash_for_fraud(&mut self, server_id: &str, reason: &str) -> Result<u64, String> {
+        let percentage = self.bonding_policy.fraud_slash_percentage;
+        self.slash_bond(server_id, percentage, reason, true)
+    }
+
+    /// Slashed stake moves to the community pool
+    fn slash_bond(&mut self, server_id: &str, percentage: f32, reason: &str, fraud: bool) -> Result<u64, String> {
+        let bond = self.operator_bonds.get_mut(server_id)
+            .ok_or("Server has no bond")?;
+
+        let amount = (bond.bonded as f32 * percentage / 100.0) as u64;
+        bond.bonded -= amount;
+        bond.slash_history.push(SlashEvent {
+            amount,
+            reason: reason.to_string(),
+            slashed_at: chrono::Utc::now().timestamp() as u64,
+            fraud,
+        });
+        self.community_pool += amount;
+
+        if let Some(server) = self.servers.get_mut(server_id) {
+            server.reputation_score = (server.reputation_score - percentage).max(0.0);
+        }
+
+        println!("⚔️  Slashed {} tokens from {}: {}", amount, server_id, reason);
+        Ok(amount)
+    }
+
+    /// Servers ordered for scheduling: bond size first, then reputation
+    pub fn servers_by_priority(&self) -> Vec<String> {
+        let mut servers: Vec<(&String, f32)> = self.servers.keys()
+            .map(|server_id| {
+                let reputation = self.servers[server_id].reputation_score / 50.0;
+                (server_id, self.bond_factor(server_id) * reputation)
+            })
+            .filter(|(_, priority)| *priority > 0.0)
+            .collect();
+
+        servers.sort_by(|a, b| b.1.total_cmp(&a.1));
+        servers.into_iter().map(|(server_id, _)| server_id.clone()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ContributedResources, TokenAllocation};
+
+    fn resources() -> ContributedResources {
+        // 2 * 1000 + 4 * 100 + 10 * 10 + 10 * 50 = 3000 tokens
+        ContributedResources {
+            cpu_cores: 2, memory_gb: 4, storage_gb: 10, bandwidth_mbps: 10, gpu_units: 0,
+            specialized_hardware: Vec::new(),
+        }
+    }
+
+    fn fund(economy: &mut CommunityResourceEconomy, account_id: &str, amount: u64) {
+        economy.token_distribution.insert(format!("fund_{}", account_id), TokenAllocation {
+            recipient_id: account_id.to_string(),
+            allocation_type: AllocationType::UserReward,
+            amount,
+            vesting_schedule: None,
+            conditions: Vec::new(),
+            allocated_by: "test".to_string(),
+            allocated_at: 0,
+        });
+    }
+
+    fn registered(operator_id: &str) -> (CommunityResourceEconomy, String) {
+        let mut economy = CommunityResourceEconomy::new();
+        fund(&mut economy, operator_id, 20_000);
+        let server_id = economy.register_community_server(operator_id, "node", "earth", resources()).unwrap();
+        (economy, server_id)
+    }
+
+    #[test]
+    fn test_servers_earn_nothing_until_bonded() {
+        let (mut economy, server_id) = registered("operator_one");
+        assert_eq!(economy.effective_token_allocation(&server_id), 0);
+        assert_eq!(economy.distribute_server_rewards(&server_id).unwrap(), 0);
+
+        economy.bond_stake(&server_id, "operator_one", 500).unwrap();
+        assert_eq!(economy.bond_factor(&server_id), 0.0);
+        assert_eq!(economy.token_balance("operator_one"), 20_000);
+
+        // Reaching the minimum pays the registration grant once, scaled to the bond
+        assert_eq!(economy.bond_stake(&server_id, "operator_one", 500).unwrap(), 1_000);
+        assert_eq!(economy.effective_token_allocation(&server_id), 300);
+        assert_eq!(economy.token_balance("operator_one"), 20_300);
+
+        economy.bond_stake(&server_id, "operator_one", 9_000).unwrap();
+        assert_eq!(economy.bond_factor(&server_id), 1.0);
+        assert_eq!(economy.effective_token_allocation(&server_id), 3_000);
+        assert_eq!(economy.token_balance("operator_one"), 20_300);
+    }
+
+    #[test]
+    fn test_bonds_need_the_operator_and_unbonded_balance() {
+        let (mut economy, server_id) = registered("operator_one");
+        fund(&mut economy, "someone_else", 5_000);
+
+        assert!(economy.bond_stake(&server_id, "someone_else", 1_000).is_err());
+        assert!(economy.bond_stake(&server_id, "operator_one", 20_001).is_err());
+        economy.bond_stake(&server_id, "operator_one", 15_000).unwrap();
+
+        // Already-bonded tokens can't back a second bond; the grant can
+        assert!(economy.bond_stake(&server_id, "operator_one", 8_001).is_err());
+        economy.bond_stake(&server_id, "operator_one", 8_000).unwrap();
+        assert!(economy.bond_stake("server_missing", "operator_one", 1).is_err());
+    }
+
+    #[test]
+    fn test_sustained_downtime_slashes_the_bond() {
+        let (mut economy, server_id) = registered("operator_one");
+        economy.bond_stake(&server_id, "operator_one", 10_000).unwrap();
+        let balance = economy.token_balance("operator_one");
+
+        assert_eq!(economy.record_uptime_report(&server_id, 80.0).unwrap(), 0);
+        assert_eq!(economy.record_uptime_report(&server_id, 80.0).unwrap(), 0);
+        assert_eq!(economy.record_uptime_report(&server_id, 99.0).unwrap(), 0); // recovery resets strikes
+        assert_eq!(economy.record_uptime_report(&server_id, 80.0).unwrap(), 0);
+        assert_eq!(economy.record_uptime_report(&server_id, 80.0).unwrap(), 0);
+        assert_eq!(economy.record_uptime_report(&server_id, 80.0).unwrap(), 500);
+
+        let bond = &economy.operator_bonds[&server_id];
+        assert_eq!(bond.bonded, 9_500);
+        assert!(!bond.slash_history[0].fraud);
+        assert_eq!(economy.community_pool, 500);
+        assert_eq!(economy.token_balance("operator_one"), balance - 500);
+        assert_eq!(economy.servers[&server_id].reputation_score, 45.0);
+    }
+
+    #[test]
+    fn test_fraud_slashes_harder_and_is_marked() {
+        let (mut economy, server_id) = registered("operator_one");
+        economy.bond_stake(&server_id, "operator_one", 4_000).unwrap();
+
+        assert_eq!(economy.slash_for_fraud(&server_id, "claimed GPUs missing").unwrap(), 2_000);
+        assert!(economy.operator_bonds[&server_id].slash_history[0].fraud);
+        assert_eq!(economy.bond_factor(&server_id), 0.2);
+        assert_eq!(economy.servers[&server_id].reputation_score, 0.0);
+    }
+
+    #[test]
+    fn test_unbonding_stops_earnings_and_locks_the_bond() {
+        let (mut economy, server_id) = registered("operator_one");
+        economy.bond_stake(&server_id, "operator_one", 10_000).unwrap();
+
+        assert!(economy.withdraw_bond(&server_id, "operator_one").is_err());
+        assert!(economy.request_unbond(&server_id, "someone_else").is_err());
+        economy.request_unbond(&server_id, "operator_one").unwrap();
+        assert_eq!(economy.bond_factor(&server_id), 0.0);
+        assert!(economy.bond_stake(&server_id, "operator_one", 1).is_err());
+        assert!(economy.withdraw_bond(&server_id, "operator_one").is_err());
+
+        // Slashes still land during the unbonding period
+        assert_eq!(economy.slash_for_fraud(&server_id, "late finding").unwrap(), 5_000);
+
+        let period = economy.bonding_policy.unbonding_period_secs;
+        let bond = economy.operator_bonds.get_mut(&server_id).unwrap();
+        bond.unbonding_since = bond.unbonding_since.map(|since| since - period);
+        assert!(economy.withdraw_bond(&server_id, "someone_else").is_err());
+        assert_eq!(economy.withdraw_bond(&server_id, "operator_one").unwrap(), 5_000);
+        assert_eq!(economy.operator_bonds[&server_id].bonded, 0);
+    }
+
+    #[test]
+    fn test_scheduling_prefers_bonded_reputable_servers() {
+        let mut economy = CommunityResourceEconomy::new();
+        let mut servers = Vec::new();
+        for (operator_id, bond) in [("operator_low", 2_000), ("operator_high", 10_000), ("operator_none", 0)] {
+            fund(&mut economy, operator_id, 20_000);
+            let server_id = economy.register_community_server(operator_id, "node", "earth", resources()).unwrap();
+            if bond > 0 {
+                economy.bond_stake(&server_id, operator_id, bond).unwrap();
+            }
+            servers.push(server_id);
+        }
+
+        assert_eq!(economy.servers_by_priority(), vec![servers[1].clone(), servers[0].clone()]);
+    }
+}
@@ -0,0 +1,354 @@
+use serde::{Deserialize, Serialize};
+use crate::{AllocationType, CommunityResourceEconomy, ProposalStatus, TokenAllocation};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectFunding {
+    pub proposal_id: String,
+    pub milestones: Vec<ProjectMilestone>,
+    pub committee: Vec<String>,     // empty = any community member but the proposer
+    pub required_confirmations: u32,
+    pub escrowed: u64,              // moved out of the community pool on funding
+    pub released: u64,
+    pub funded_at: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectMilestone {
+    pub milestone_id: String,
+    pub description: String,
+    pub amount: u64,
+    pub status: MilestoneStatus,
+    pub evidence: Option<String>,
+    pub confirmations: Vec<String>,
+    pub released_at: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum MilestoneStatus {
+    Pending,
+    Submitted,
+    Released,
+}
+
+impl CommunityResourceEconomy {
+    /// Split a proposal's requested tokens into milestones. Amounts must add up
+    /// to the request; only the proposer can define them, before funding.
+    pub fn define_milestones(&mut self, proposal_id: &str, proposer_id: &str,
+                             milestones: Vec<(String, u64)>, committee: Vec<String>,
+                             required_confirmations: u32) -> Result<(), String> {
+        let proposal = self.governance_proposals.get(proposal_id)
+            .ok_or("Proposal not found")?;
+
+        if proposal.proposer_id != proposer_id {
+            return Err("Only the proposer can define milestones".to_string());
+        }
+
+        if self.project_funding.get(proposal_id).and_then(|f| f.funded_at).is_some() {
+            return Err("Milestones are fixed once the project is funded".to_string());
+        }
+
+        let total: u64 = milestones.iter().map(|(_, amount)| amount).sum();
+        if milestones.is_empty() || total != proposal.requested_tokens {
+            return Err(format!("Milestones total {} but proposal requests {}", total, proposal.requested_tokens));
+        }
+
+        if required_confirmations == 0 {
+            return Err("At least one confirmation is required per milestone".to_string());
+        }
+        if !committee.is_empty() && required_confirmations as usize > committee.len() {
+            return Err("More confirmations required than committee members".to_string());
+        }
+
+        let milestones = milestones.into_iter()
+            .enumerate()
+            .map(|(i, (description, amount))| ProjectMilestone {
+                milestone_id: format!("m{}", i + 1),
+                description,
+                amount,
+                status: MilestoneStatus::Pending,
+                evidence: None,
+                confirmations: Vec::new(),
+                released_at: None,
+            })
+            .collect();
+
+        self.project_funding.insert(proposal_id.to_string(), ProjectFunding {
+            proposal_id: proposal_id.to_string(),
+            milestones,
+            committee,
+            required_confirmations,
+            escrowed: 0,
+            released: 0,
+            funded_at: None,
+        });
+
+        Ok(())
+    }
+
+    /// Escrow an approved proposal's tokens from the community pool
+    pub fn fund_approved_proposal(&mut self, proposal_id: &str) -> Result<u64, String> {
+        let proposal = self.governance_proposals.get(proposal_id)
+            .ok_or("Proposal not found")?;
+
+        if !matches!(proposal.status, ProposalStatus::Approved) {
+            return Err("Only approved proposals can be funded".to_string());
+        }
+
+        let funding = self.project_funding.get_mut(proposal_id)
+            .ok_or("Define milestones before funding")?;
+
+        if funding.funded_at.is_some() {
+            return Err("Proposal already funded".to_string());
+        }
+
+        if self.community_pool < proposal.requested_tokens {
+            return Err(format!("Community pool has {} tokens, {} requested",
+                               self.community_pool, proposal.requested_tokens));
+        }
+
+        self.community_pool -= proposal.requested_tokens;
+        funding.escrowed = proposal.requested_tokens;
+        funding.funded_at = Some(chrono::Utc::now().timestamp() as u64);
+
+        println!("🏦 Escrowed {} tokens for {}", funding.escrowed, proposal.title);
+
+        Ok(funding.escrowed)
+    }
+
+    pub fn submit_milestone(&mut self, proposal_id: &str, milestone_id: &str,
+                            proposer_id: &str, evidence: &str) -> Result<(), String> {
+        let proposal = self.governance_proposals.get(proposal_id)
+            .ok_or("Proposal not found")?;
+        if proposal.proposer_id != proposer_id {
+            return Err("Only the proposer can submit milestones".to_string());
+        }
+
+        let funding = self.project_funding.get_mut(proposal_id)
+            .ok_or("Project has no milestones")?;
+        if funding.funded_at.is_none() {
+            return Err("Project is not funded".to_string());
+        }
+
+        let milestone = funding.milestones.iter_mut()
+            .find(|m| m.milestone_id == milestone_id)
+            .ok_or("Milestone not found")?;
+
+        if milestone.status == MilestoneStatus::Released {
+            return Err("Milestone already released".to_string());
+        }
+
+        milestone.status = MilestoneStatus::Submitted;
+        milestone.evidence = Some(evidence.to_string());
+        milestone.confirmations.clear();
+
+        Ok(())
+    }
+
+    /// Confirm a submitted milestone; releases its tokens to the proposer once
+    /// enough confirmations are in. Returns the amount released, if any.
+    pub fn confirm_milestone(&mut self, proposal_id: &str, milestone_id: &str,
+                             confirmer_id: &str) -> Result<Option<u64>, String> {
+        let proposal = self.governance_proposals.get_mut(proposal_id)
+            .ok_or("Proposal not found")?;
+        let funding = self.project_funding.get_mut(proposal_id)
+            .ok_or("Project has no milestones")?;
+
+        let allowed = if funding.committee.is_empty() {
+            confirmer_id != proposal.proposer_id
+        } else {
+            funding.committee.iter().any(|member| member == confirmer_id)
+        };
+        if !allowed {
+            return Err("Not allowed to confirm milestones for this project".to_string());
+        }
+
+        let milestone = funding.milestones.iter_mut()
+            .find(|m| m.milestone_id == milestone_id)
+            .ok_or("Milestone not found")?;
+
+        if milestone.status != MilestoneStatus::Submitted {
+            return Err("Milestone has not been submitted".to_string());
+        }
+        if milestone.confirmations.iter().any(|c| c == confirmer_id) {
+            return Err("Already confirmed".to_string());
+        }
+
+        milestone.confirmations.push(confirmer_id.to_string());
+        if (milestone.confirmations.len() as u32) < funding.required_confirmations {
+            return Ok(None);
+        }
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        milestone.status = MilestoneStatus::Released;
+        milestone.released_at = Some(now);
+        let amount = milestone.amount;
+        let allocation_id = format!("alloc_{}_{}", proposal_id, milestone.milestone_id);
+
+        funding.released += amount;
+        if funding.milestones.iter().all(|m| m.status == MilestoneStatus::Released) {
+            proposal.status = ProposalStatus::Implemented;
+        }
+
+        self.token_distribution.insert(allocation_id, TokenAllocation {
+            recipient_id: proposal.proposer_id.clone(),
+            allocation_type: AllocationType::CommunityProject,
+            amount,
+            vesting_schedule: None,
+            conditions: vec![format!("milestone:{}", milestone_id)],
+            allocated_by: "community_pool".to_string(),
+            allocated_at: now,
+        });
+
+        println!("💸 Milestone {} of {} released: {} tokens", milestone_id, proposal.title, amount);
+
+        Ok(Some(amount))
+    }
+
+    /// Stop a project and return unreleased escrow to the community pool
+    pub fn abandon_project(&mut self, proposal_id: &str, caller_id: &str) -> Result<u64, String> {
+        let proposal = self.governance_proposals.get_mut(proposal_id)
+            .ok_or("Proposal not found")?;
+        let funding = self.project_funding.get_mut(proposal_id)
+            .ok_or("Project has no milestones")?;
+
+        let allowed = caller_id == proposal.proposer_id
+            || funding.committee.iter().any(|member| member == caller_id);
+        if !allowed {
+            return Err("Only the proposer or committee can abandon a project".to_string());
+        }
+        if matches!(proposal.status, ProposalStatus::Implemented | ProposalStatus::Abandoned) {
+            return Err("Project already closed".to_string());
+        }
+
+        let refund = funding.escrowed - funding.released;
+        funding.escrowed = funding.released;
+        self.community_pool += refund;
+        proposal.status = ProposalStatus::Abandoned;
+
+        println!("↩️  Project {} abandoned, {} tokens returned to the community pool", proposal.title, refund);
+
+        Ok(refund)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ContributedResources, ResourceProposal};
+
+    fn economy_with_proposal(requested_tokens: u64, status: ProposalStatus) -> CommunityResourceEconomy {
+        let mut economy = CommunityResourceEconomy::new();
+        economy.governance_proposals.insert("prop1".to_string(), ResourceProposal {
+            proposal_id: "prop1".to_string(),
+            proposer_id: "proposer".to_string(),
+            title: "Mirror".to_string(),
+            description: String::new(),
+            requested_tokens,
+            requested_resources: ContributedResources {
+                cpu_cores: 0, memory_gb: 0, storage_gb: 0, bandwidth_mbps: 0, gpu_units: 0,
+                specialized_hardware: Vec::new(),
+            },
+            community_benefit: String::new(),
+            votes_for: 0,
+            votes_against: 0,
+            status,
+        });
+        economy.community_pool = 1_000;
+        economy
+    }
+
+    fn milestones() -> Vec<(String, u64)> {
+        vec![("design".to_string(), 100), ("build".to_string(), 200)]
+    }
+
+    #[test]
+    fn test_milestones_must_cover_the_request_before_funding() {
+        let mut economy = economy_with_proposal(300, ProposalStatus::Voting);
+
+        assert!(economy.define_milestones("prop1", "someone", milestones(), Vec::new(), 1).is_err());
+        assert!(economy.define_milestones("prop1", "proposer", vec![("all".to_string(), 299)], Vec::new(), 1).is_err());
+        assert!(economy.define_milestones("prop1", "proposer", milestones(), Vec::new(), 0).is_err());
+        assert!(economy.define_milestones("prop1", "proposer", milestones(), vec!["alice".to_string()], 2).is_err());
+        economy.define_milestones("prop1", "proposer", milestones(), Vec::new(), 1).unwrap();
+
+        // Still voting: nothing leaves the pool
+        assert!(economy.fund_approved_proposal("prop1").is_err());
+        assert_eq!(economy.community_pool, 1_000);
+
+        economy.governance_proposals.get_mut("prop1").unwrap().status = ProposalStatus::Approved;
+        assert_eq!(economy.fund_approved_proposal("prop1").unwrap(), 300);
+        assert_eq!(economy.community_pool, 700);
+        assert!(economy.fund_approved_proposal("prop1").is_err());
+        assert!(economy.define_milestones("prop1", "proposer", milestones(), Vec::new(), 1).is_err());
+    }
+
+    #[test]
+    fn test_funding_needs_enough_in_the_community_pool() {
+        let mut economy = economy_with_proposal(300, ProposalStatus::Approved);
+        economy.community_pool = 299;
+        economy.define_milestones("prop1", "proposer", milestones(), Vec::new(), 1).unwrap();
+
+        assert!(economy.fund_approved_proposal("prop1").is_err());
+        assert_eq!(economy.community_pool, 299);
+        assert_eq!(economy.project_funding["prop1"].escrowed, 0);
+    }
+
+    #[test]
+    fn test_milestones_release_on_committee_confirmations() {
+        let mut economy = economy_with_proposal(300, ProposalStatus::Approved);
+        let committee = vec!["alice".to_string(), "bob".to_string(), "carol".to_string()];
+        economy.define_milestones("prop1", "proposer", milestones(), committee, 2).unwrap();
+
+        // Nothing to confirm until funded and submitted
+        assert!(economy.submit_milestone("prop1", "m1", "proposer", "design doc").is_err());
+        economy.fund_approved_proposal("prop1").unwrap();
+        assert!(economy.confirm_milestone("prop1", "m1", "alice").is_err());
+        assert!(economy.submit_milestone("prop1", "m1", "alice", "design doc").is_err());
+        economy.submit_milestone("prop1", "m1", "proposer", "design doc").unwrap();
+
+        assert!(economy.confirm_milestone("prop1", "m1", "proposer").is_err());
+        assert_eq!(economy.confirm_milestone("prop1", "m1", "alice").unwrap(), None);
+        assert!(economy.confirm_milestone("prop1", "m1", "alice").is_err());
+        assert_eq!(economy.confirm_milestone("prop1", "m1", "bob").unwrap(), Some(100));
+        assert!(economy.submit_milestone("prop1", "m1", "proposer", "again").is_err());
+
+        let allocation = &economy.token_distribution["alloc_prop1_m1"];
+        assert_eq!(allocation.recipient_id, "proposer");
+        assert_eq!(allocation.amount, 100);
+        assert!(matches!(economy.governance_proposals["prop1"].status, ProposalStatus::Approved));
+
+        economy.submit_milestone("prop1", "m2", "proposer", "release notes").unwrap();
+        economy.confirm_milestone("prop1", "m2", "carol").unwrap();
+        assert_eq!(economy.confirm_milestone("prop1", "m2", "bob").unwrap(), Some(200));
+
+        assert_eq!(economy.project_funding["prop1"].released, 300);
+        assert!(matches!(economy.governance_proposals["prop1"].status, ProposalStatus::Implemented));
+    }
+
+    #[test]
+    fn test_without_a_committee_anyone_but_the_proposer_confirms() {
+        let mut economy = economy_with_proposal(300, ProposalStatus::Approved);
+        economy.define_milestones("prop1", "proposer", milestones(), Vec::new(), 1).unwrap();
+        economy.fund_approved_proposal("prop1").unwrap();
+        economy.submit_milestone("prop1", "m1", "proposer", "design doc").unwrap();
+
+        assert!(economy.confirm_milestone("prop1", "m1", "proposer").is_err());
+        assert_eq!(economy.confirm_milestone("prop1", "m1", "member").unwrap(), Some(100));
+    }
+
+    #[test]
+    fn test_abandoning_returns_unreleased_escrow() {
+        let mut economy = economy_with_proposal(300, ProposalStatus::Approved);
+        economy.define_milestones("prop1", "proposer", milestones(), vec!["alice".to_string()], 1).unwrap();
+        economy.fund_approved_proposal("prop1").unwrap();
+        economy.submit_milestone("prop1", "m1", "proposer", "design doc").unwrap();
+        economy.confirm_milestone("prop1", "m1", "alice").unwrap();
+
+        assert!(economy.abandon_project("prop1", "bystander").is_err());
+        assert_eq!(economy.abandon_project("prop1", "alice").unwrap(), 200);
+        assert_eq!(economy.community_pool, 900);
+        assert!(matches!(economy.governance_proposals["prop1"].status, ProposalStatus::Abandoned));
+        assert!(economy.abandon_project("prop1", "proposer").is_err());
+        assert!(economy.get_community_status().contains("\"escrowed_for_projects\":0"));
+    }
+}
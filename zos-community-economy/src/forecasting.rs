@@ -0,0 +1,271 @@
+use serde::{Deserialize, Serialize};
+use crate::CommunityResourceEconomy;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForecastPolicy {
+    pub sample_interval_secs: u64, // one sample per pool type per interval
+    pub season_length: usize,      // samples per season; 7 daily samples = weekly pattern
+    pub horizon: usize,            // samples projected ahead
+    pub alpha: f64,                // level smoothing
+    pub beta: f64,                 // trend smoothing
+    pub gamma: f64,                // seasonal smoothing
+    pub max_samples: usize,
+}
+
+impl Default for ForecastPolicy {
+    fn default() -> Self {
+        Self {
+            sample_interval_secs: 86400,
+            season_length: 7,
+            horizon: 28,
+            alpha: 0.5,
+            beta: 0.1,
+            gamma: 0.3,
+            max_samples: 365,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageSample {
+    pub at: u64,
+    pub allocated: u64,
+    pub capacity: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ForecastMethod {
+    SimpleExponential, // too little history for a trend
+    Holt,              // level and trend, less than two seasons of history
+    HoltWinters,       // level, trend and additive seasonality
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolForecast {
+    pub pool_type: String,
+    pub method: ForecastMethod,
+    pub current_demand: u64,
+    pub capacity: u64,
+    pub projected: Vec<(u64, f64)>, // (timestamp, demand)
+    pub peak_demand: f64,
+    pub shortfall_at: Option<u64>,  // first projected sample above capacity
+    pub shortfall_units: f64,       // peak demand over capacity
+}
+
+fn simple_exponential(series: &[f64], alpha: f64, horizon: usize) -> Vec<f64> {
+    let level = series.iter().skip(1).fold(series[0], |level, &x| alpha * x + (1.0 - alpha) * level);
+    vec![level; horizon]
+}
+
+fn holt(series: &[f64], alpha: f64, beta: f64, horizon: usize) -> Vec<f64> {
+    let mut level = series[0];
+    let mut trend = series[1] - series[0];
+    for &x in &series[1..] {
+        let last_level = level;
+        level = alpha * x + (1.0 - alpha) * (level + trend);
+        trend = beta * (level - last_level) + (1.0 - beta) * trend;
+    }
+    (1..=horizon).map(|h| level + h as f64 * trend).collect()
+}
+
+/// Additive Holt-Winters, initialised from the first two seasons
+fn holt_winters(series: &[f64], season: usize, policy: &ForecastPolicy, horizon: usize) -> Vec<f64> {
+    let mean = |s: &[f64]| s.iter().sum::<f64>() / s.len() as f64;
+    let first = mean(&series[..season]);
+    let second = mean(&series[season..2 * season]);
+
+    let mut level = first;
+    let mut trend = (second - first) / season as f64;
+    let mut seasonal: Vec<f64> = series[..season].iter().map(|x| x - first).collect();
+
+    for (i, &x) in series.iter().enumerate().skip(season) {
+        let s = seasonal[i % season];
+        let last_level = level;
+        level = policy.alpha * (x - s) + (1.0 - policy.alpha) * (level + trend);
+        trend = policy.beta * (level - last_level) + (1.0 - policy.beta) * trend;
+        seasonal[i % season] = policy.gamma * (x - level) + (1.0 - policy.gamma) * s;
+    }
+
+    (1..=horizon)
+        .map(|h| level + h as f64 * trend + seasonal[(series.len() + h - 1) % season])
+        .collect()
+}
+
+/// Project a demand series with the richest method the history supports
+pub fn forecast_series(series: &[f64], policy: &ForecastPolicy) -> Option<(ForecastMethod, Vec<f64>)> {
+    let season = policy.season_length;
+    let (method, projected) = match series.len() {
+        0 => return None,
+        n if season > 1 && n >= 2 * season =>
+            (ForecastMethod::HoltWinters, holt_winters(series, season, policy, policy.horizon)),
+        n if n >= 3 => (ForecastMethod::Holt, holt(series, policy.alpha, policy.beta, policy.horizon)),
+        _ => (ForecastMethod::SimpleExponential, simple_exponential(series, policy.alpha, policy.horizon)),
+    };
+
+    // Demand can't go negative however steep the trend
+    Some((method, projected.into_iter().map(|x| x.max(0.0)).collect()))
+}
+
+impl CommunityResourceEconomy {
+    /// Sample allocated and total capacity per pool type; called on a timer
+    /// by the host. At most one sample per type per interval.
+    pub fn record_usage_sample(&mut self) {
+        let now = chrono::Utc::now().timestamp() as u64;
+
+        let mut totals: HashMap<String, (u64, u64)> = HashMap::new();
+        for pool in self.resource_pools.values() {
+            let total = totals.entry(format!("{:?}", pool.pool_type)).or_insert((0, 0));
+            total.0 += pool.allocated_capacity;
+            total.1 += pool.total_capacity;
+        }
+
+        for (pool_type, (allocated, capacity)) in totals {
+            let history = self.usage_history.entry(pool_type).or_default();
+            if history.last().is_some_and(|last| now < last.at + self.forecast_policy.sample_interval_secs) {
+                continue;
+            }
+            history.push(UsageSample { at: now, allocated, capacity });
+            if history.len() > self.forecast_policy.max_samples {
+                let excess = history.len() - self.forecast_policy.max_samples;
+                history.drain(0..excess);
+            }
+        }
+    }
+
+    /// Projected demand per pool type against current capacity
+    pub fn forecast_capacity(&self) -> Vec<PoolForecast> {
+        let policy = &self.forecast_policy;
+        let mut forecasts: Vec<PoolForecast> = self.usage_history.iter()
+            .filter_map(|(pool_type, history)| {
+                let last = history.last()?;
+                let series: Vec<f64> = history.iter().map(|sample| sample.allocated as f64).collect();
+                let (method, projected) = forecast_series(&series, policy)?;
+
+                let projected: Vec<(u64, f64)> = projected.into_iter()
+                    .enumerate()
+                    .map(|(i, demand)| (last.at + (i as u64 + 1) * policy.sample_interval_secs, demand))
+                    .collect();
+                let peak_demand = projected.iter().map(|(_, demand)| *demand).fold(0.0, f64::max);
+                let shortfall_at = projected.iter()
+                    .find(|(_, demand)| *demand > last.capacity as f64)
+                    .map(|(at, _)| *at);
+
+                Some(PoolForecast {
+                    pool_type: pool_type.clone(),
+                    method,
+                    current_demand: last.allocated,
+                    capacity: last.capacity,
+                    projected,
+                    peak_demand,
+                    shortfall_at,
+                    shortfall_units: (peak_demand - last.capacity as f64).max(0.0),
+                })
+            })
+            .collect();
+
+        forecasts.sort_by(|a, b| a.pool_type.cmp(&b.pool_type));
+        forecasts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(at: u64, allocated: u64, capacity: u64) -> UsageSample {
+        UsageSample { at, allocated, capacity }
+    }
+
+    #[test]
+    fn test_method_follows_available_history() {
+        let policy = ForecastPolicy::default();
+        assert!(forecast_series(&[], &policy).is_none());
+
+        let (method, projected) = forecast_series(&[10.0, 10.0], &policy).unwrap();
+        assert!(matches!(method, ForecastMethod::SimpleExponential));
+        assert_eq!(projected, vec![10.0; policy.horizon]);
+
+        let (method, _) = forecast_series(&[10.0, 12.0, 14.0], &policy).unwrap();
+        assert!(matches!(method, ForecastMethod::Holt));
+
+        let (method, _) = forecast_series(&[10.0; 14], &policy).unwrap();
+        assert!(matches!(method, ForecastMethod::HoltWinters));
+    }
+
+    #[test]
+    fn test_holt_extends_a_linear_trend() {
+        let policy = ForecastPolicy { horizon: 3, ..ForecastPolicy::default() };
+        let series: Vec<f64> = (0..6).map(|i| 100.0 + 10.0 * i as f64).collect();
+
+        let (_, projected) = forecast_series(&series, &policy).unwrap();
+        for (h, demand) in projected.iter().enumerate() {
+            assert!((demand - (160.0 + 10.0 * h as f64)).abs() < 1e-6, "{:?}", projected);
+        }
+
+        // Falling demand bottoms out at zero
+        let falling: Vec<f64> = (0..6).map(|i| 50.0 - 10.0 * i as f64).collect();
+        let policy = ForecastPolicy { horizon: 10, ..policy };
+        assert_eq!(*forecast_series(&falling, &policy).unwrap().1.last().unwrap(), 0.0);
+    }
+
+    #[test]
+    fn test_holt_winters_keeps_the_weekly_shape() {
+        let week = [10.0, 10.0, 10.0, 10.0, 10.0, 40.0, 40.0]; // weekend peaks
+        let series: Vec<f64> = week.iter().cycle().take(28).copied().collect();
+        let policy = ForecastPolicy { horizon: 7, ..ForecastPolicy::default() };
+
+        let (method, projected) = forecast_series(&series, &policy).unwrap();
+        assert!(matches!(method, ForecastMethod::HoltWinters));
+        for (demand, expected) in projected.iter().zip(week) {
+            assert!((demand - expected).abs() < 1.0, "{:?}", projected);
+        }
+    }
+
+    #[test]
+    fn test_growing_demand_reports_a_shortfall() {
+        let mut economy = CommunityResourceEconomy::new();
+        let day = economy.forecast_policy.sample_interval_secs;
+        economy.usage_history.insert("Compute".to_string(),
+            (0..10).map(|i| sample(i * day, 500 + 50 * i, 1_000)).collect());
+        economy.usage_history.insert("Storage".to_string(),
+            (0..10).map(|i| sample(i * day, 100, 1_000)).collect());
+
+        let forecasts = economy.forecast_capacity();
+        assert_eq!(forecasts.len(), 2);
+        let compute = &forecasts[0];
+        assert_eq!(compute.pool_type, "Compute");
+        assert_eq!(compute.current_demand, 950);
+        assert_eq!(compute.projected[0].0, 10 * day);
+        assert!(compute.shortfall_at.is_some_and(|at| at > 9 * day));
+        assert!(compute.shortfall_units > 0.0);
+
+        let storage = &forecasts[1];
+        assert_eq!(storage.shortfall_at, None);
+        assert_eq!(storage.shortfall_units, 0.0);
+    }
+
+    #[test]
+    fn test_sampling_is_rate_limited_and_bounded() {
+        let mut economy = CommunityResourceEconomy::new();
+        economy.forecast_policy.max_samples = 3;
+        economy.register_community_server("operator_one", "node", "earth", crate::ContributedResources {
+            cpu_cores: 2, memory_gb: 0, storage_gb: 1, bandwidth_mbps: 0, gpu_units: 0,
+            specialized_hardware: Vec::new(),
+        }).unwrap();
+
+        economy.record_usage_sample();
+        economy.record_usage_sample();
+        assert_eq!(economy.usage_history["Compute"].len(), 1);
+        assert_eq!(economy.usage_history["Compute"][0].capacity, 200);
+        assert_eq!(economy.usage_history["Storage"][0].capacity, 1_024);
+
+        // Older samples make room once the interval has passed
+        economy.usage_history.insert("Compute".to_string(),
+            (1..=3).map(|i| sample(i, i, 200)).collect());
+        economy.record_usage_sample();
+        let history = &economy.usage_history["Compute"];
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0].at, 2);
+    }
+}
@@ -0,0 +1,453 @@
+use serde::{Deserialize, Serialize};
+use crate::{AllocationType, CommunityResourceEconomy, TokenAllocation, VestingSchedule};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GrantPolicy {
+    pub reviewers_per_application: u32,
+    pub approvals_required: u32,
+    pub review_bond: u64,        // staked per review, forfeited if the review never comes
+    pub review_period_secs: u64, // from taking an application to reviewing it
+    pub cliff_secs: u64,         // vesting for approved grants
+    pub vesting_secs: u64,
+}
+
+impl Default for GrantPolicy {
+    fn default() -> Self {
+        Self {
+            reviewers_per_application: 3,
+            approvals_required: 2,
+            review_bond: 100,
+            review_period_secs: 14 * 24 * 3600,
+            cliff_secs: 30 * 24 * 3600,
+            vesting_secs: 180 * 24 * 3600,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Deliverable {
+    pub description: String,
+    pub due_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GrantReview {
+    pub reviewer: String,
+    pub bond: u64,
+    pub assigned_at: u64,
+    pub approve: Option<bool>, // None until reviewed
+    pub comment: String,
+    pub reviewed_at: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum GrantStatus {
+    Submitted, // waiting for reviewers
+    InReview,
+    Approved,
+    Rejected,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GrantApplication {
+    pub application_id: String,
+    pub applicant: String,
+    pub title: String,
+    pub description: String,
+    pub requested_tokens: u64,
+    pub deliverables: Vec<Deliverable>,
+    pub reviews: Vec<GrantReview>,
+    pub status: GrantStatus,
+    pub submitted_at: u64,
+    pub decided_at: Option<u64>,
+    pub allocation_id: Option<String>, // vesting DeveloperGrant allocation, once approved
+}
+
+impl GrantApplication {
+    fn votes(&self, approve: bool) -> u32 {
+        self.reviews.iter().filter(|review| review.approve == Some(approve)).count() as u32
+    }
+}
+
+impl CommunityResourceEconomy {
+    pub fn submit_grant_application(&mut self, applicant: &str, title: &str, description: &str,
+                                    requested_tokens: u64, deliverables: Vec<Deliverable>) -> Result<String, String> {
+        if requested_tokens == 0 {
+            return Err("Grant must request tokens".to_string());
+        }
+        if deliverables.is_empty() {
+            return Err("List at least one deliverable".to_string());
+        }
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        let application_id = format!("grant_{}_{}", applicant, now);
+
+        self.grant_applications.insert(application_id.clone(), GrantApplication {
+            application_id: application_id.clone(),
+            applicant: applicant.to_string(),
+            title: title.to_string(),
+            description: description.to_string(),
+            requested_tokens,
+            deliverables,
+            reviews: Vec::new(),
+            status: GrantStatus::Submitted,
+            submitted_at: now,
+            decided_at: None,
+            allocation_id: None,
+        });
+
+        println!("📝 Grant application {}: {} requesting {} tokens", application_id, title, requested_tokens);
+        Ok(application_id)
+    }
+
+    /// Review bonds an account has staked and not yet had back; bonds on
+    /// decided applications are released whether or not the review came in
+    pub fn locked_review_bonds(&self, account_id: &str) -> u64 {
+        self.grant_applications.values()
+            .filter(|application| matches!(application.status, GrantStatus::Submitted | GrantStatus::InReview))
+            .flat_map(|application| &application.reviews)
+            .filter(|review| review.reviewer == account_id && review.approve.is_none())
+            .map(|review| review.bond)
+            .sum()
+    }
+
+    /// Take a seat on an application's review panel by staking the review bond
+    pub fn join_grant_review(&mut self, application_id: &str, reviewer: &str) -> Result<u64, String> {
+        let bond = self.grant_policy.review_bond;
+        let available = self.token_balance(reviewer)
+            .saturating_sub(self.bonded_by(reviewer))
+            .saturating_sub(self.locked_review_bonds(reviewer));
+        if available < bond {
+            return Err(format!("Review bond is {} tokens, {} available", bond, available));
+        }
+
+        let seats = self.grant_policy.reviewers_per_application as usize;
+        let application = self.grant_applications.get_mut(application_id)
+            .ok_or("Grant application not found")?;
+
+        if application.applicant == reviewer {
+            return Err("Applicants cannot review their own grant".to_string());
+        }
+        if application.status != GrantStatus::Submitted {
+            return Err("Review panel is closed".to_string());
+        }
+        if application.reviews.iter().any(|review| review.reviewer == reviewer) {
+            return Err("Already reviewing".to_string());
+        }
+
+        application.reviews.push(GrantReview {
+            reviewer: reviewer.to_string(),
+            bond,
+            assigned_at: chrono::Utc::now().timestamp() as u64,
+            approve: None,
+            comment: String::new(),
+            reviewed_at: None,
+        });
+        if application.reviews.len() >= seats {
+            application.status = GrantStatus::InReview;
+        }
+
+        Ok(bond)
+    }
+
+    /// Record a review; the reviewer's bond is released with it. Enough
+    /// approvals fund the grant, enough rejections close it.
+    pub fn submit_grant_review(&mut self, application_id: &str, reviewer: &str, approve: bool,
+                               comment: &str) -> Result<GrantStatus, String> {
+        let policy = self.grant_policy.clone();
+        let now = chrono::Utc::now().timestamp() as u64;
+        let application = self.grant_applications.get_mut(application_id)
+            .ok_or("Grant application not found")?;
+
+        if application.status != GrantStatus::InReview {
+            return Err("Application is not in review".to_string());
+        }
+        let review = application.reviews.iter_mut()
+            .find(|review| review.reviewer == reviewer)
+            .ok_or("Not a reviewer of this application")?;
+        if review.approve.is_some() {
+            return Err("Already reviewed".to_string());
+        }
+
+        review.approve = Some(approve);
+        review.comment = comment.to_string();
+        review.reviewed_at = Some(now);
+
+        let rejections_to_fail = policy.reviewers_per_application.saturating_sub(policy.approvals_required) + 1;
+        if application.votes(false) >= rejections_to_fail {
+            application.status = GrantStatus::Rejected;
+            application.decided_at = Some(now);
+            println!("❌ Grant {} rejected", application.title);
+        } else if application.votes(true) >= policy.approvals_required {
+            self.fund_grant(application_id)?;
+        }
+
+        Ok(self.grant_applications[application_id].status.clone())
+    }
+
+    /// Fund a grant its reviewers approved: a vesting DeveloperGrant
+    /// allocation out of the community pool. Can be retried if the pool was
+    /// short when the last approval came in.
+    pub fn fund_grant(&mut self, application_id: &str) -> Result<String, String> {
+        let policy = self.grant_policy.clone();
+        let application = self.grant_applications.get_mut(application_id)
+            .ok_or("Grant application not found")?;
+
+        if application.status != GrantStatus::InReview || application.votes(true) < policy.approvals_required {
+            return Err("Grant has not been approved by its reviewers".to_string());
+        }
+        if self.community_pool < application.requested_tokens {
+            return Err(format!("Grant approved but the community pool has {} of {} tokens",
+                               self.community_pool, application.requested_tokens));
+        }
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        let allocation_id = format!("alloc_{}", application_id);
+        self.community_pool -= application.requested_tokens;
+        self.token_distribution.insert(allocation_id.clone(), TokenAllocation {
+            recipient_id: application.applicant.clone(),
+            allocation_type: AllocationType::DeveloperGrant,
+            amount: application.requested_tokens,
+            vesting_schedule: Some(VestingSchedule {
+                total_amount: application.requested_tokens,
+                cliff_period: policy.cliff_secs,
+                vesting_period: policy.vesting_secs,
+                released_amount: 0,
+            }),
+            conditions: application.deliverables.iter()
+                .map(|deliverable| format!("deliverable:{}", deliverable.description))
+                .collect(),
+            allocated_by: "grant_review".to_string(),
+            allocated_at: now,
+        });
+        application.status = GrantStatus::Approved;
+        application.decided_at = Some(now);
+        application.allocation_id = Some(allocation_id.clone());
+
+        println!("✅ Grant {} approved: {} tokens vesting over {} days",
+                 application.title, application.requested_tokens, policy.vesting_secs / 86400);
+        Ok(allocation_id)
+    }
+
+    /// Reviewers who sat on an application past the review period lose their
+    /// bond to the community pool and their seat is reopened. Returns the
+    /// amount forfeited.
+    pub fn expire_grant_reviews(&mut self) -> u64 {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let period = self.grant_policy.review_period_secs;
+        let mut forfeited = 0;
+
+        for application in self.grant_applications.values_mut() {
+            if !matches!(application.status, GrantStatus::Submitted | GrantStatus::InReview) {
+                continue;
+            }
+            application.reviews.retain(|review| {
+                let expired = review.approve.is_none() && now >= review.assigned_at + period;
+                if expired {
+                    forfeited += review.bond;
+                    println!("⌛ {} forfeited a {} token review bond on {}", review.reviewer, review.bond, application.title);
+                }
+                !expired
+            });
+            if application.status == GrantStatus::InReview
+                && (application.reviews.len() as u32) < self.grant_policy.reviewers_per_application {
+                application.status = GrantStatus::Submitted;
+            }
+        }
+
+        self.community_pool += forfeited;
+        self.forfeited_review_bonds += forfeited;
+        forfeited
+    }
+
+    /// Advance released_amount on vesting allocations: nothing before the
+    /// cliff, then linearly over the vesting period
+    pub fn vest_allocations(&mut self) {
+        let now = chrono::Utc::now().timestamp() as u64;
+        for allocation in self.token_distribution.values_mut() {
+            if let Some(schedule) = allocation.vesting_schedule.as_mut() {
+                let elapsed = now.saturating_sub(allocation.allocated_at);
+                schedule.released_amount = if elapsed < schedule.cliff_period {
+                    0
+                } else if schedule.vesting_period == 0 || elapsed >= schedule.vesting_period {
+                    schedule.total_amount
+                } else {
+                    (schedule.total_amount as u128 * elapsed as u128 / schedule.vesting_period as u128) as u64
+                };
+            }
+        }
+    }
+
+    /// Governance view of grant applications, newest first
+    pub fn get_grant_applications(&self) -> String {
+        let mut applications: Vec<&GrantApplication> = self.grant_applications.values().collect();
+        applications.sort_by_key(|application| std::cmp::Reverse(application.submitted_at));
+
+        serde_json::json!({
+            "policy": self.grant_policy,
+            "applications": applications.iter().map(|application| serde_json::json!({
+                "application": application,
+                "approvals": application.votes(true),
+                "rejections": application.votes(false),
+                "open_seats": (self.grant_policy.reviewers_per_application as usize)
+                    .saturating_sub(application.reviews.len()),
+                "vesting": application.allocation_id.as_ref()
+                    .and_then(|allocation_id| self.token_distribution.get(allocation_id))
+                    .and_then(|allocation| allocation.vesting_schedule.as_ref())
+            })).collect::<Vec<_>>(),
+            "forfeited_review_bonds": self.forfeited_review_bonds
+        }).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fund(economy: &mut CommunityResourceEconomy, account_id: &str, amount: u64) {
+        economy.token_distribution.insert(format!("fund_{}", account_id), TokenAllocation {
+            recipient_id: account_id.to_string(),
+            allocation_type: AllocationType::UserReward,
+            amount,
+            vesting_schedule: None,
+            conditions: Vec::new(),
+            allocated_by: "test".to_string(),
+            allocated_at: 0,
+        });
+    }
+
+    /// An application with a full review panel of reviewer1..3
+    fn in_review(pool: u64) -> (CommunityResourceEconomy, String) {
+        let mut economy = CommunityResourceEconomy::new();
+        economy.community_pool = pool;
+        let application_id = economy.submit_grant_application("applicant", "Compiler cache", "",
+            1_000, vec![Deliverable { description: "sccache backend".to_string(), due_at: 0 }]).unwrap();
+        for reviewer in ["reviewer1", "reviewer2", "reviewer3"] {
+            fund(&mut economy, reviewer, 100);
+            economy.join_grant_review(&application_id, reviewer).unwrap();
+        }
+        (economy, application_id)
+    }
+
+    #[test]
+    fn test_review_panels_take_bonded_reviewers() {
+        let mut economy = CommunityResourceEconomy::new();
+        assert!(economy.submit_grant_application("applicant", "t", "", 0, vec![
+            Deliverable { description: "d".to_string(), due_at: 0 }]).is_err());
+        assert!(economy.submit_grant_application("applicant", "t", "", 10, Vec::new()).is_err());
+        let application_id = economy.submit_grant_application("applicant", "t", "", 10, vec![
+            Deliverable { description: "d".to_string(), due_at: 0 }]).unwrap();
+
+        fund(&mut economy, "applicant", 1_000);
+        fund(&mut economy, "reviewer1", 150);
+        fund(&mut economy, "poor", 99);
+        assert!(economy.join_grant_review(&application_id, "applicant").is_err());
+        assert!(economy.join_grant_review(&application_id, "poor").is_err());
+        assert_eq!(economy.join_grant_review(&application_id, "reviewer1").unwrap(), 100);
+        assert!(economy.join_grant_review(&application_id, "reviewer1").is_err());
+        assert_eq!(economy.locked_review_bonds("reviewer1"), 100);
+
+        // A staked bond can't back a second seat
+        let other = economy.submit_grant_application("other_applicant", "t", "", 10, vec![
+            Deliverable { description: "d".to_string(), due_at: 0 }]).unwrap();
+        assert!(economy.join_grant_review(&other, "reviewer1").is_err());
+
+        // Reviews can't start until the panel is full
+        assert!(economy.submit_grant_review(&application_id, "reviewer1", true, "").is_err());
+        for reviewer in ["reviewer2", "reviewer3"] {
+            fund(&mut economy, reviewer, 100);
+            economy.join_grant_review(&application_id, reviewer).unwrap();
+        }
+        assert_eq!(economy.grant_applications[&application_id].status, GrantStatus::InReview);
+        fund(&mut economy, "reviewer4", 100);
+        assert!(economy.join_grant_review(&application_id, "reviewer4").is_err());
+    }
+
+    #[test]
+    fn test_approved_grants_vest_from_the_community_pool() {
+        let (mut economy, application_id) = in_review(5_000);
+        assert!(economy.submit_grant_review(&application_id, "outsider", true, "").is_err());
+        assert_eq!(economy.submit_grant_review(&application_id, "reviewer1", true, "solid").unwrap(), GrantStatus::InReview);
+        assert!(economy.submit_grant_review(&application_id, "reviewer1", true, "").is_err());
+        assert_eq!(economy.locked_review_bonds("reviewer1"), 0);
+        assert_eq!(economy.submit_grant_review(&application_id, "reviewer2", true, "").unwrap(), GrantStatus::Approved);
+
+        assert_eq!(economy.community_pool, 4_000);
+        let allocation = &economy.token_distribution[&format!("alloc_{}", application_id)];
+        assert!(matches!(allocation.allocation_type, AllocationType::DeveloperGrant));
+        assert_eq!(allocation.conditions, vec!["deliverable:sccache backend".to_string()]);
+        assert_eq!(allocation.vesting_schedule.as_ref().unwrap().total_amount, 1_000);
+
+        // Decided applications release the remaining reviewer's bond
+        assert_eq!(economy.locked_review_bonds("reviewer3"), 0);
+        assert!(economy.submit_grant_review(&application_id, "reviewer3", false, "").is_err());
+    }
+
+    #[test]
+    fn test_approved_grants_wait_for_the_pool() {
+        let (mut economy, application_id) = in_review(500);
+        economy.submit_grant_review(&application_id, "reviewer1", true, "").unwrap();
+        assert!(economy.submit_grant_review(&application_id, "reviewer2", true, "").is_err());
+        assert_eq!(economy.grant_applications[&application_id].status, GrantStatus::InReview);
+
+        economy.community_pool = 1_000;
+        economy.fund_grant(&application_id).unwrap();
+        assert_eq!(economy.community_pool, 0);
+        assert!(economy.fund_grant(&application_id).is_err());
+    }
+
+    #[test]
+    fn test_enough_rejections_close_the_application() {
+        let (mut economy, application_id) = in_review(5_000);
+        assert!(economy.fund_grant(&application_id).is_err());
+        economy.submit_grant_review(&application_id, "reviewer1", false, "out of scope").unwrap();
+        economy.submit_grant_review(&application_id, "reviewer2", true, "").unwrap();
+        assert_eq!(economy.submit_grant_review(&application_id, "reviewer3", false, "").unwrap(), GrantStatus::Rejected);
+        assert_eq!(economy.community_pool, 5_000);
+    }
+
+    #[test]
+    fn test_idle_reviewers_forfeit_their_bond_and_seat() {
+        let (mut economy, application_id) = in_review(0);
+        economy.submit_grant_review(&application_id, "reviewer1", true, "").unwrap();
+        assert_eq!(economy.expire_grant_reviews(), 0);
+
+        let period = economy.grant_policy.review_period_secs;
+        for review in &mut economy.grant_applications.get_mut(&application_id).unwrap().reviews {
+            review.assigned_at -= period;
+        }
+        assert_eq!(economy.expire_grant_reviews(), 200);
+        assert_eq!(economy.community_pool, 200);
+        assert_eq!(economy.forfeited_review_bonds, 200);
+
+        let application = &economy.grant_applications[&application_id];
+        assert_eq!(application.status, GrantStatus::Submitted);
+        assert_eq!(application.reviews.len(), 1);
+        assert!(economy.get_grant_applications().contains("\"open_seats\":2"));
+    }
+
+    #[test]
+    fn test_vesting_releases_linearly_after_the_cliff() {
+        let mut economy = CommunityResourceEconomy::new();
+        let now = chrono::Utc::now().timestamp() as u64;
+        let mut vesting = |age: u64| {
+            economy.token_distribution.insert("grant".to_string(), TokenAllocation {
+                recipient_id: "applicant".to_string(),
+                allocation_type: AllocationType::DeveloperGrant,
+                amount: 1_000,
+                vesting_schedule: Some(VestingSchedule {
+                    total_amount: 1_000, cliff_period: 100, vesting_period: 1_000, released_amount: 0,
+                }),
+                conditions: Vec::new(),
+                allocated_by: "grant_review".to_string(),
+                allocated_at: now - age,
+            });
+            economy.vest_allocations();
+            economy.token_distribution["grant"].vesting_schedule.as_ref().unwrap().released_amount
+        };
+
+        assert_eq!(vesting(50), 0);
+        assert!((250..=260).contains(&vesting(250)));
+        assert_eq!(vesting(5_000), 1_000);
+    }
+}
@@ -0,0 +1,434 @@
+use serde::{Deserialize, Serialize};
+use crate::{AllocationType, CommunityResourceEconomy, TokenAllocation};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InsuranceTerms {
+    pub premium_percentage: f32,    // of each earning paid to a policyholder
+    pub coverage_percentage: f32,   // of an approved loss paid out
+    pub max_payout_per_claim: u64,
+    pub waiting_period_secs: u64,   // losses before enrolment + this are not covered
+    pub claim_window_secs: u64,     // claims must be filed within this of the loss
+    pub assessors: Vec<String>,     // empty = any community member but the claimant
+    pub required_approvals: u32,
+}
+
+impl Default for InsuranceTerms {
+    fn default() -> Self {
+        Self {
+            premium_percentage: 2.0,
+            coverage_percentage: 80.0,
+            max_payout_per_claim: 5_000,
+            waiting_period_secs: 7 * 24 * 3600,
+            claim_window_secs: 30 * 24 * 3600,
+            assessors: Vec::new(),
+            required_approvals: 2,
+        }
+    }
+}
+
+/// Opt-in mutual cover for stakers and service owners, funded by premiums
+/// skimmed off their earnings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InsurancePool {
+    pub terms: InsuranceTerms,
+    pub balance: u64,
+    pub premiums_collected: u64,
+    pub paid_out: u64,
+    pub policies: HashMap<String, InsurancePolicy>, // holder -> policy
+    pub claims: HashMap<String, InsuranceClaim>,
+    pub claims_filed: u64, // sequence for claim ids
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InsurancePolicy {
+    pub holder: String,
+    pub enrolled_at: u64,
+    pub cancelled_at: Option<u64>,
+    pub premiums_paid: u64,
+}
+
+impl InsurancePolicy {
+    fn covers(&self, loss_at: u64, waiting_period_secs: u64) -> bool {
+        loss_at >= self.enrolled_at + waiting_period_secs
+            && self.cancelled_at.is_none_or(|cancelled_at| loss_at < cancelled_at)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum CoveredEvent {
+    /// A non-fraud slash of a bond the claimant operates
+    Slash { server_id: String, slashed_at: u64 },
+    /// Payment reversed after the claimant delivered the service
+    Chargeback { reference: String, occurred_at: u64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ClaimStatus {
+    Submitted,
+    Rejected,
+    Paid,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InsuranceClaim {
+    pub claim_id: String,
+    pub claimant: String,
+    pub event: CoveredEvent,
+    pub loss: u64,
+    pub covered_amount: u64, // loss × coverage, capped per claim
+    pub evidence: String,
+    pub status: ClaimStatus,
+    pub approvals: Vec<String>,
+    pub rejections: Vec<String>,
+    pub submitted_at: u64,
+    pub paid: u64,      // less than covered_amount if the pool ran short
+    pub decided_at: Option<u64>,
+}
+
+impl CommunityResourceEconomy {
+    pub fn enroll_in_insurance(&mut self, holder: &str) -> Result<u64, String> {
+        let now = chrono::Utc::now().timestamp() as u64;
+        if self.insurance.policies.get(holder).is_some_and(|policy| policy.cancelled_at.is_none()) {
+            return Err("Already insured".to_string());
+        }
+
+        self.insurance.policies.insert(holder.to_string(), InsurancePolicy {
+            holder: holder.to_string(),
+            enrolled_at: now,
+            cancelled_at: None,
+            premiums_paid: 0,
+        });
+
+        println!("🛡️  {} enrolled in the insurance pool", holder);
+        Ok(now + self.insurance.terms.waiting_period_secs)
+    }
+
+    /// Stop paying premiums; losses before now stay claimable
+    pub fn cancel_insurance(&mut self, holder: &str) -> Result<(), String> {
+        let policy = self.insurance.policies.get_mut(holder)
+            .filter(|policy| policy.cancelled_at.is_none())
+            .ok_or("No active policy")?;
+        policy.cancelled_at = Some(chrono::Utc::now().timestamp() as u64);
+        Ok(())
+    }
+
+    /// Skim the premium off an earning. Returns what the holder keeps. Also
+    /// called by hosts for earnings paid outside this ledger.
+    pub fn collect_premium(&mut self, holder: &str, earnings: u64) -> u64 {
+        let percentage = self.insurance.terms.premium_percentage;
+        let policy = match self.insurance.policies.get_mut(holder) {
+            Some(policy) if policy.cancelled_at.is_none() => policy,
+            _ => return earnings,
+        };
+
+        let premium = (earnings as f32 * percentage / 100.0) as u64;
+        policy.premiums_paid += premium;
+        self.insurance.balance += premium;
+        self.insurance.premiums_collected += premium;
+        earnings - premium
+    }
+
+    /// File a claim for a covered loss. Slashes are checked against the bond
+    /// history; chargebacks rest on the evidence and the assessors.
+    pub fn submit_claim(&mut self, claimant: &str, event: CoveredEvent, loss: u64,
+                        evidence: &str) -> Result<String, String> {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let terms = self.insurance.terms.clone();
+        let policy = self.insurance.policies.get(claimant)
+            .ok_or("No insurance policy")?;
+
+        let (loss, loss_at) = match &event {
+            CoveredEvent::Slash { server_id, slashed_at } => {
+                let bond = self.operator_bonds.get(server_id)
+                    .filter(|bond| bond.operator_id == claimant)
+                    .ok_or("Not the operator of this server")?;
+                let slash = bond.slash_history.iter()
+                    .find(|slash| slash.slashed_at == *slashed_at)
+                    .ok_or("No such slash")?;
+                if slash.fraud {
+                    return Err("Slashes for fraud are not covered".to_string());
+                }
+                (slash.amount, slash.slashed_at)
+            }
+            CoveredEvent::Chargeback { occurred_at, .. } => (loss, *occurred_at),
+        };
+
+        if loss == 0 {
+            return Err("Nothing was lost".to_string());
+        }
+        if !policy.covers(loss_at, terms.waiting_period_secs) {
+            return Err("Loss is outside the policy's cover period".to_string());
+        }
+        if now > loss_at + terms.claim_window_secs {
+            return Err("Claim window has closed".to_string());
+        }
+        if self.insurance.claims.values().any(|claim| claim.event == event && claim.status != ClaimStatus::Rejected) {
+            return Err("This loss has already been claimed".to_string());
+        }
+
+        let covered_amount = ((loss as f32 * terms.coverage_percentage / 100.0) as u64)
+            .min(terms.max_payout_per_claim);
+        let claim_id = format!("claim_{}_{}", now, self.insurance.claims_filed);
+        self.insurance.claims_filed += 1;
+
+        self.insurance.claims.insert(claim_id.clone(), InsuranceClaim {
+            claim_id: claim_id.clone(),
+            claimant: claimant.to_string(),
+            event,
+            loss,
+            covered_amount,
+            evidence: evidence.to_string(),
+            status: ClaimStatus::Submitted,
+            approvals: Vec::new(),
+            rejections: Vec::new(),
+            submitted_at: now,
+            paid: 0,
+            decided_at: None,
+        });
+
+        println!("📨 Claim {} filed by {}: {} tokens lost, {} covered", claim_id, claimant, loss, covered_amount);
+        Ok(claim_id)
+    }
+
+    /// Record an assessor's decision. Enough approvals pay the claim from the
+    /// pool; enough rejections close it. Returns the amount paid, if any.
+    pub fn assess_claim(&mut self, claim_id: &str, assessor: &str, approve: bool) -> Result<Option<u64>, String> {
+        let terms = &self.insurance.terms;
+        let claim = self.insurance.claims.get_mut(claim_id)
+            .ok_or("Claim not found")?;
+
+        let allowed = if terms.assessors.is_empty() {
+            assessor != claim.claimant
+        } else {
+            terms.assessors.iter().any(|member| member == assessor)
+        };
+        if !allowed {
+            return Err("Not allowed to assess this claim".to_string());
+        }
+        if claim.status != ClaimStatus::Submitted {
+            return Err("Claim already decided".to_string());
+        }
+        if claim.approvals.iter().chain(&claim.rejections).any(|a| a == assessor) {
+            return Err("Already assessed".to_string());
+        }
+
+        if approve {
+            claim.approvals.push(assessor.to_string());
+        } else {
+            claim.rejections.push(assessor.to_string());
+        }
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        if claim.rejections.len() as u32 >= terms.required_approvals {
+            claim.status = ClaimStatus::Rejected;
+            claim.decided_at = Some(now);
+            println!("❌ Claim {} rejected", claim_id);
+            return Ok(None);
+        }
+        if (claim.approvals.len() as u32) < terms.required_approvals {
+            return Ok(None);
+        }
+
+        // Pay what the pool can; a shortfall shows up in the solvency report
+        let paid = claim.covered_amount.min(self.insurance.balance);
+        claim.status = ClaimStatus::Paid;
+        claim.paid = paid;
+        claim.decided_at = Some(now);
+        self.insurance.balance -= paid;
+        self.insurance.paid_out += paid;
+
+        if paid > 0 {
+            self.token_distribution.insert(format!("alloc_{}", claim_id), TokenAllocation {
+                recipient_id: claim.claimant.clone(),
+                allocation_type: AllocationType::UserReward,
+                amount: paid,
+                vesting_schedule: None,
+                conditions: vec![format!("insurance:{}", claim_id)],
+                allocated_by: "insurance_pool".to_string(),
+                allocated_at: now,
+            });
+        }
+
+        println!("🛡️  Claim {} paid: {} of {} tokens", claim_id, paid, claim.covered_amount);
+        Ok(Some(paid))
+    }
+
+    /// Pool balance against what it may owe: claims awaiting assessment and
+    /// the worst-case payout on every insured bond
+    pub fn insurance_solvency(&self) -> String {
+        let pool = &self.insurance;
+        let pending: u64 = pool.claims.values()
+            .filter(|claim| claim.status == ClaimStatus::Submitted)
+            .map(|claim| claim.covered_amount)
+            .sum();
+        let shortfall: u64 = pool.claims.values()
+            .filter(|claim| claim.status == ClaimStatus::Paid)
+            .map(|claim| claim.covered_amount - claim.paid)
+            .sum();
+        let active: Vec<&InsurancePolicy> = pool.policies.values()
+            .filter(|policy| policy.cancelled_at.is_none())
+            .collect();
+        let bond_exposure: u64 = active.iter()
+            .flat_map(|policy| self.operator_bonds.values().filter(|bond| bond.operator_id == policy.holder))
+            .map(|bond| {
+                let worst_slash = (bond.bonded as f32 * self.bonding_policy.downtime_slash_percentage / 100.0) as u64;
+                ((worst_slash as f32 * pool.terms.coverage_percentage / 100.0) as u64).min(pool.terms.max_payout_per_claim)
+            })
+            .sum();
+
+        serde_json::json!({
+            "balance": pool.balance,
+            "premiums_collected": pool.premiums_collected,
+            "paid_out": pool.paid_out,
+            "active_policies": active.len(),
+            "pending_claims": pool.claims.values().filter(|c| c.status == ClaimStatus::Submitted).count(),
+            "pending_claims_amount": pending,
+            "unpaid_shortfall": shortfall,
+            "coverage_ratio": (pending > 0).then(|| pool.balance as f64 / pending as f64),
+            "bond_exposure": bond_exposure,
+            "reserve_ratio": (bond_exposure > 0).then(|| pool.balance as f64 / bond_exposure as f64),
+            "solvent": pool.balance >= pending,
+            "terms": pool.terms
+        }).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ContributedResources;
+
+    fn fund(economy: &mut CommunityResourceEconomy, account_id: &str, amount: u64) {
+        economy.token_distribution.insert(format!("fund_{}", account_id), TokenAllocation {
+            recipient_id: account_id.to_string(),
+            allocation_type: AllocationType::UserReward,
+            amount,
+            vesting_schedule: None,
+            conditions: Vec::new(),
+            allocated_by: "test".to_string(),
+            allocated_at: 0,
+        });
+    }
+
+    /// An insured operator past the waiting period, with a bonded server
+    fn insured_operator() -> (CommunityResourceEconomy, String) {
+        let mut economy = CommunityResourceEconomy::new();
+        fund(&mut economy, "operator_one", 20_000);
+        let resources = ContributedResources {
+            cpu_cores: 1, memory_gb: 0, storage_gb: 0, bandwidth_mbps: 0, gpu_units: 0,
+            specialized_hardware: Vec::new(),
+        };
+        let server_id = economy.register_community_server("operator_one", "node", "earth", resources).unwrap();
+        economy.bond_stake(&server_id, "operator_one", 10_000).unwrap();
+
+        economy.enroll_in_insurance("operator_one").unwrap();
+        let waiting = economy.insurance.terms.waiting_period_secs;
+        economy.insurance.policies.get_mut("operator_one").unwrap().enrolled_at -= waiting;
+        economy.insurance.balance = 10_000;
+        (economy, server_id)
+    }
+
+    fn slash_for_downtime(economy: &mut CommunityResourceEconomy, server_id: &str) -> CoveredEvent {
+        for _ in 0..economy.bonding_policy.strikes_before_slash {
+            economy.record_uptime_report(server_id, 50.0).unwrap();
+        }
+        let slashed_at = economy.operator_bonds[server_id].slash_history.last().unwrap().slashed_at;
+        CoveredEvent::Slash { server_id: server_id.to_string(), slashed_at }
+    }
+
+    #[test]
+    fn test_premiums_come_out_of_insured_earnings_only() {
+        let mut economy = CommunityResourceEconomy::new();
+        assert_eq!(economy.collect_premium("holder", 1_000), 1_000);
+
+        economy.enroll_in_insurance("holder").unwrap();
+        assert!(economy.enroll_in_insurance("holder").is_err());
+        assert_eq!(economy.collect_premium("holder", 1_000), 980);
+        assert_eq!(economy.insurance.balance, 20);
+        assert_eq!(economy.insurance.policies["holder"].premiums_paid, 20);
+
+        economy.cancel_insurance("holder").unwrap();
+        assert!(economy.cancel_insurance("holder").is_err());
+        assert_eq!(economy.collect_premium("holder", 1_000), 1_000);
+        assert_eq!(economy.insurance.premiums_collected, 20);
+    }
+
+    #[test]
+    fn test_downtime_slashes_are_paid_on_enough_approvals() {
+        let (mut economy, server_id) = insured_operator();
+        let event = slash_for_downtime(&mut economy, &server_id);
+        let balance = economy.token_balance("operator_one");
+
+        // The slash amount comes from the bond history, not the claimant
+        let claim_id = economy.submit_claim("operator_one", event.clone(), 1_000_000, "outage").unwrap();
+        assert_eq!(economy.insurance.claims[&claim_id].loss, 500);
+        assert_eq!(economy.insurance.claims[&claim_id].covered_amount, 400);
+        assert!(economy.submit_claim("operator_one", event, 500, "again").is_err());
+
+        assert!(economy.assess_claim(&claim_id, "operator_one", true).is_err());
+        assert_eq!(economy.assess_claim(&claim_id, "alice", true).unwrap(), None);
+        assert!(economy.assess_claim(&claim_id, "alice", true).is_err());
+        assert_eq!(economy.assess_claim(&claim_id, "bob", true).unwrap(), Some(400));
+        assert!(economy.assess_claim(&claim_id, "carol", true).is_err());
+
+        assert_eq!(economy.insurance.balance, 9_600);
+        assert_eq!(economy.token_balance("operator_one"), balance + 400);
+    }
+
+    #[test]
+    fn test_uncovered_slashes_are_refused() {
+        let (mut economy, server_id) = insured_operator();
+
+        economy.slash_for_fraud(&server_id, "fake GPUs").unwrap();
+        let bond = economy.operator_bonds.get_mut(&server_id).unwrap();
+        bond.slash_history[0].slashed_at -= 3600; // apart from the downtime slash below
+        let slashed_at = bond.slash_history[0].slashed_at;
+        let fraud = CoveredEvent::Slash { server_id: server_id.clone(), slashed_at };
+        assert!(economy.submit_claim("operator_one", fraud, 0, "").unwrap_err().contains("fraud"));
+
+        let event = slash_for_downtime(&mut economy, &server_id);
+        economy.enroll_in_insurance("stranger").unwrap();
+        assert!(economy.submit_claim("stranger", event.clone(), 0, "").is_err());
+        assert!(economy.submit_claim("uninsured", event.clone(), 0, "").is_err());
+
+        // Losses inside the waiting period aren't covered
+        economy.insurance.policies.get_mut("operator_one").unwrap().enrolled_at = chrono::Utc::now().timestamp() as u64;
+        assert!(economy.submit_claim("operator_one", event, 0, "").unwrap_err().contains("cover period"));
+    }
+
+    #[test]
+    fn test_rejected_claims_can_be_refiled_and_payouts_stop_at_the_balance() {
+        let (mut economy, _) = insured_operator();
+        let occurred_at = chrono::Utc::now().timestamp() as u64;
+        let event = CoveredEvent::Chargeback { reference: "pay_1".to_string(), occurred_at };
+        assert!(economy.submit_claim("operator_one", event.clone(), 0, "").is_err());
+
+        let claim_id = economy.submit_claim("operator_one", event.clone(), 10_000, "delivered").unwrap();
+        assert_eq!(economy.insurance.claims[&claim_id].covered_amount, 5_000); // capped per claim
+        economy.assess_claim(&claim_id, "alice", false).unwrap();
+        assert_eq!(economy.assess_claim(&claim_id, "bob", false).unwrap(), None);
+        assert_eq!(economy.insurance.claims[&claim_id].status, ClaimStatus::Rejected);
+
+        let claim_id = economy.submit_claim("operator_one", event, 10_000, "receipts attached").unwrap();
+        economy.insurance.balance = 3_000;
+        let solvency: serde_json::Value = serde_json::from_str(&economy.insurance_solvency()).unwrap();
+        assert_eq!(solvency["solvent"], false);
+
+        economy.assess_claim(&claim_id, "alice", true).unwrap();
+        assert_eq!(economy.assess_claim(&claim_id, "bob", true).unwrap(), Some(3_000));
+        let solvency: serde_json::Value = serde_json::from_str(&economy.insurance_solvency()).unwrap();
+        assert_eq!(solvency["unpaid_shortfall"], 2_000);
+        assert_eq!(solvency["balance"], 0);
+    }
+
+    #[test]
+    fn test_claims_close_with_the_window() {
+        let (mut economy, _) = insured_operator();
+        let occurred_at = chrono::Utc::now().timestamp() as u64 - economy.insurance.terms.claim_window_secs - 60;
+        economy.insurance.policies.get_mut("operator_one").unwrap().enrolled_at = 0;
+        let event = CoveredEvent::Chargeback { reference: "pay_1".to_string(), occurred_at };
+
+        assert!(economy.submit_claim("operator_one", event, 100, "").unwrap_err().contains("window"));
+    }
+}
@@ -1,6 +1,26 @@
 use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 
+pub mod bonding;
+pub mod disbursement;
+pub mod forecasting;
+pub mod grants;
+pub mod insurance;
+pub mod settlement;
+pub mod treasury;
+pub mod verification;
+pub mod voting;
+
+use bonding::{BondingPolicy, OperatorBond};
+use disbursement::ProjectFunding;
+use forecasting::{ForecastPolicy, UsageSample};
+use grants::{GrantApplication, GrantPolicy};
+use insurance::InsurancePool;
+use settlement::SettlementState;
+use treasury::Treasury;
+use verification::{BenchmarkChallenge, BenchmarkPolicy, ServerVerification};
+use voting::DelegationRecord;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommunityResourceEconomy {
     pub servers: HashMap<String, CommunityServer>,
@@ -8,6 +28,42 @@ pub struct CommunityResourceEconomy {
     pub token_distribution: HashMap<String, TokenAllocation>,
     pub governance_proposals: HashMap<String, ResourceProposal>,
     pub community_metrics: CommunityMetrics,
+    #[serde(default)]
+    pub community_pool: u64, // community share of server rewards, funds proposals
+    #[serde(default)]
+    pub project_funding: HashMap<String, ProjectFunding>, // proposal_id -> milestones
+    #[serde(default)]
+    pub delegations: HashMap<String, String>, // delegator -> delegate
+    #[serde(default)]
+    pub delegation_log: Vec<DelegationRecord>,
+    #[serde(default)]
+    pub ballots: HashMap<String, HashMap<String, bool>>, // proposal_id -> voter -> support
+    #[serde(default)]
+    pub settlement: SettlementState,
+    #[serde(default)]
+    pub operator_bonds: HashMap<String, OperatorBond>, // server_id -> bond
+    #[serde(default)]
+    pub bonding_policy: BondingPolicy,
+    #[serde(default)]
+    pub verifications: HashMap<String, ServerVerification>, // server_id -> benchmarked capacity
+    #[serde(default)]
+    pub benchmark_challenges: HashMap<String, BenchmarkChallenge>, // outstanding, by challenge_id
+    #[serde(default)]
+    pub benchmark_policy: BenchmarkPolicy,
+    #[serde(default)]
+    pub treasury: Treasury, // share of gateway fees, spent by proposal
+    #[serde(default)]
+    pub insurance: InsurancePool,
+    #[serde(default)]
+    pub usage_history: HashMap<String, Vec<UsageSample>>, // "{:?}" of PoolType -> samples, oldest first
+    #[serde(default)]
+    pub forecast_policy: ForecastPolicy,
+    #[serde(default)]
+    pub grant_applications: HashMap<String, GrantApplication>,
+    #[serde(default)]
+    pub grant_policy: GrantPolicy,
+    #[serde(default)]
+    pub forfeited_review_bonds: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -96,6 +152,7 @@ pub enum PoolType {
     GPU,
     Development,
     Gaming,
+    #[allow(non_camel_case_types)] // pool ids are built from the variant's Debug name
     AI_Training,
     Community,
 }
@@ -173,6 +230,13 @@ pub enum ProposalStatus {
     Approved,
     Rejected,
     Implemented,
+    Abandoned,
+}
+
+impl Default for CommunityResourceEconomy {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl CommunityResourceEconomy {
@@ -197,6 +261,24 @@ impl CommunityResourceEconomy {
                 community_projects: 0,
                 average_server_uptime: 0.0,
             },
+            community_pool: 0,
+            project_funding: HashMap::new(),
+            delegations: HashMap::new(),
+            delegation_log: Vec::new(),
+            ballots: HashMap::new(),
+            settlement: SettlementState::default(),
+            operator_bonds: HashMap::new(),
+            bonding_policy: BondingPolicy::default(),
+            verifications: HashMap::new(),
+            benchmark_challenges: HashMap::new(),
+            benchmark_policy: BenchmarkPolicy::default(),
+            treasury: Treasury::default(),
+            insurance: InsurancePool::default(),
+            usage_history: HashMap::new(),
+            forecast_policy: ForecastPolicy::default(),
+            grant_applications: HashMap::new(),
+            grant_policy: GrantPolicy::default(),
+            forfeited_review_bonds: 0,
         }
     }
 
@@ -264,14 +346,14 @@ impl CommunityResourceEconomy {
         // Create resource pools for this server
         self.create_server_resource_pools(&server_id, &resources)?;
 
-        // Distribute initial tokens to server operator
-        self.distribute_tokens(&server_id, operator_id, AllocationType::ServerContribution, token_allocation)?;
+        // Initial tokens are held back until the operator bonds stake
+        self.operator_bonds.insert(server_id.clone(), OperatorBond::new(&server_id, operator_id, token_allocation));
 
         self.servers.insert(server_id.clone(), server);
         self.update_community_metrics();
 
-        println!("🖥️  Community server registered: {} by {} ({} tokens allocated)",
-                 server_name, &operator_id[..8], token_allocation);
+        println!("🖥️  Community server registered: {} by {} ({} tokens pending a {} token bond)",
+                 server_name, &operator_id[..8], token_allocation, self.bonding_policy.min_bond);
 
         Ok(server_id)
     }
@@ -279,12 +361,12 @@ impl CommunityResourceEconomy {
     pub fn allocate_resources(&mut self, server_id: &str, user_id: &str,
                             resource_type: PoolType, amount: u64) -> Result<String, String> {
 
-        let server = self.servers.get_mut(server_id)
+        let server = self.servers.get(server_id)
             .ok_or("Server not found")?;
 
         // Check if server has available resources
         let pool_id = format!("{}_{:?}", server_id, resource_type);
-        let pool = self.resource_pools.get_mut(&pool_id)
+        let pool = self.resource_pools.get(&pool_id)
             .ok_or("Resource pool not found")?;
 
         if pool.allocated_capacity + amount > pool.total_capacity {
@@ -303,6 +385,8 @@ impl CommunityResourceEconomy {
         }
 
         // Allocate resources
+        let pool = self.resource_pools.get_mut(&pool_id)
+            .ok_or("Resource pool not found")?;
         pool.allocated_capacity += amount;
         pool.beneficiaries.push(Beneficiary {
             user_id: user_id.to_string(),
@@ -346,12 +430,33 @@ impl CommunityResourceEconomy {
         Ok(proposal_id)
     }
 
+    /// Close voting on a proposal: approved on a simple majority of votes cast
+    pub fn finalize_proposal(&mut self, proposal_id: &str) -> Result<ProposalStatus, String> {
+        let proposal = self.governance_proposals.get_mut(proposal_id)
+            .ok_or("Proposal not found")?;
+
+        if !matches!(proposal.status, ProposalStatus::Voting) {
+            return Err("Proposal is not open for voting".to_string());
+        }
+
+        proposal.status = if proposal.votes_for > proposal.votes_against {
+            ProposalStatus::Approved
+        } else {
+            ProposalStatus::Rejected
+        };
+
+        println!("🗳️  Proposal {} {:?} ({} for, {} against)",
+                 proposal.title, proposal.status, proposal.votes_for, proposal.votes_against);
+
+        Ok(proposal.status.clone())
+    }
+
     pub fn distribute_server_rewards(&mut self, server_id: &str) -> Result<u64, String> {
         let server = self.servers.get(server_id)
             .ok_or("Server not found")?;
 
         // Calculate rewards based on uptime, users, and community contribution
-        let base_reward = server.token_allocation / 100; // 1% of allocation per period
+        let base_reward = self.effective_token_allocation(server_id) / 100; // 1% of bonded allocation per period
         let uptime_multiplier = server.uptime_percentage / 100.0;
         let user_multiplier = (server.active_users as f32 / 10.0).min(2.0); // Max 2x for user activity
         let reputation_multiplier = server.reputation_score / 50.0; // Normalized to 1.0 at 50 reputation
@@ -364,6 +469,8 @@ impl CommunityResourceEconomy {
         let staking_tokens = (total_reward as f32 * server.distribution_policy.staking_percentage / 100.0) as u64;
         let developer_tokens = (total_reward as f32 * server.distribution_policy.developer_percentage / 100.0) as u64;
 
+        self.community_pool += community_tokens;
+
         println!("💰 Server {} rewards distributed: {} total tokens", &server_id[..12], total_reward);
         println!("   Free tier: {}, Community: {}, Staking: {}, Developers: {}",
                  free_tier_tokens, community_tokens, staking_tokens, developer_tokens);
@@ -384,7 +491,30 @@ impl CommunityResourceEconomy {
             "token_circulation": self.community_metrics.token_circulation,
             "average_uptime": self.community_metrics.average_server_uptime,
             "community_projects": self.community_metrics.community_projects,
-            "top_servers": self.get_top_servers(5)
+            "community_pool": self.community_pool,
+            "escrowed_for_projects": self.project_funding.values()
+                .map(|funding| funding.escrowed - funding.released)
+                .sum::<u64>(),
+            "treasury": self.treasury.balances,
+            "insurance_pool": self.insurance.balance,
+            "grant_applications_in_review": self.grant_applications.values()
+                .filter(|application| matches!(application.status,
+                                               grants::GrantStatus::Submitted | grants::GrantStatus::InReview))
+                .count(),
+            "bonded_stake": self.operator_bonds.values().map(|bond| bond.bonded).sum::<u64>(),
+            "top_servers": self.get_top_servers(5),
+            "capacity_forecast": self.forecast_capacity().iter().map(|forecast| serde_json::json!({
+                "pool_type": forecast.pool_type,
+                "method": forecast.method,
+                "current_demand": forecast.current_demand,
+                "capacity": forecast.capacity,
+                "peak_demand": forecast.peak_demand,
+                "shortfall_at": forecast.shortfall_at,
+                "shortfall_units": forecast.shortfall_units,
+                "weekly": forecast.projected.iter()
+                    .step_by(self.forecast_policy.season_length.max(1))
+                    .collect::<Vec<_>>()
+            })).collect::<Vec<_>>()
         });
 
         status.to_string()
@@ -445,7 +575,7 @@ impl CommunityResourceEconomy {
         Ok(())
     }
 
-    fn check_distribution_policy(&self, server: &CommunityServer, user_id: &str, amount: u64) -> Result<bool, String> {
+    fn check_distribution_policy(&self, _server: &CommunityServer, _user_id: &str, _amount: u64) -> Result<bool, String> {
         // Simplified policy check - in real implementation would check user reputation,
         // community standing, staking status, etc.
         Ok(true)
@@ -456,6 +586,9 @@ impl CommunityResourceEconomy {
 
         let allocation_id = format!("alloc_{}_{}", recipient_id, chrono::Utc::now().timestamp());
 
+        // Insured recipients pay their premium out of every earning
+        let amount = self.collect_premium(recipient_id, amount);
+
         let allocation = TokenAllocation {
             recipient_id: recipient_id.to_string(),
             allocation_type,
@@ -487,11 +620,15 @@ impl CommunityResourceEconomy {
         let mut total_users = 0;
 
         for server in self.servers.values() {
-            total_resources.cpu_cores += server.contributed_resources.cpu_cores;
-            total_resources.memory_gb += server.contributed_resources.memory_gb;
-            total_resources.storage_gb += server.contributed_resources.storage_gb;
-            total_resources.bandwidth_mbps += server.contributed_resources.bandwidth_mbps;
-            total_resources.gpu_units += server.contributed_resources.gpu_units;
+            // Count benchmarked capacity rather than the operator's claim
+            let resources = self.verifications.get(&server.server_id)
+                .map(|verification| &verification.effective_resources)
+                .unwrap_or(&server.contributed_resources);
+            total_resources.cpu_cores += resources.cpu_cores;
+            total_resources.memory_gb += resources.memory_gb;
+            total_resources.storage_gb += resources.storage_gb;
+            total_resources.bandwidth_mbps += resources.bandwidth_mbps;
+            total_resources.gpu_units += resources.gpu_units;
 
             total_uptime += server.uptime_percentage;
             total_users += server.active_users;
@@ -499,7 +636,7 @@ impl CommunityResourceEconomy {
 
         self.community_metrics.total_contributed_resources = total_resources;
         self.community_metrics.total_active_users = total_users;
-        self.community_metrics.average_server_uptime = if self.servers.len() > 0 {
+        self.community_metrics.average_server_uptime = if !self.servers.is_empty() {
             total_uptime / self.servers.len() as f32
         } else {
             0.0
@@ -0,0 +1,345 @@
+use serde::{Deserialize, Serialize};
+use crate::CommunityResourceEconomy;
+use std::collections::HashMap;
+
+/// Optional mirroring of the internal token ledger onto an SPL token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettlementConfig {
+    pub mint_address: String,
+    pub rpc_url: String,
+    pub batch_size: usize,
+    pub divergence_tolerance: u64, // max on-chain vs settled difference per account
+    pub recipient_wallets: HashMap<String, String>, // recipient_id -> Solana address
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SettlementState {
+    pub config: Option<SettlementConfig>,
+    pub settled_balances: HashMap<String, u64>, // recipient_id -> amount already on chain
+    pub halted: Option<String>,                 // reason; no batches run until cleared
+    pub batches: Vec<SettlementBatch>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettlementBatch {
+    pub batch_id: String,
+    pub executed_at: u64,
+    pub transfers: Vec<SettlementTransfer>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettlementTransfer {
+    pub recipient_id: String,
+    pub wallet_address: String,
+    pub amount: u64,
+    pub signature: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReconciliationReport {
+    pub checked_at: u64,
+    pub accounts: Vec<AccountReconciliation>,
+    pub unsettled_total: u64,
+    pub diverged: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountReconciliation {
+    pub recipient_id: String,
+    pub internal: u64,
+    pub settled: u64,
+    pub on_chain: Option<u64>,
+}
+
+/// Chain access, kept behind a trait so deployments without Solana tooling
+/// (and dry runs) can plug in their own
+pub trait SettlementBackend {
+    fn mint_to(&self, config: &SettlementConfig, wallet_address: &str, amount: u64) -> Result<String, String>;
+    fn balance_of(&self, config: &SettlementConfig, wallet_address: &str) -> Result<u64, String>;
+}
+
+/// Uses the `spl-token` CLI with the node's configured keypair as mint authority
+pub struct SplTokenCli;
+
+impl SplTokenCli {
+    fn run(&self, config: &SettlementConfig, args: &[&str]) -> Result<String, String> {
+        let output = std::process::Command::new("spl-token")
+            .args(args)
+            .args(["--url", &config.rpc_url])
+            .output()
+            .map_err(|e| format!("Failed to run spl-token: {}", e))?;
+
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+}
+
+impl SettlementBackend for SplTokenCli {
+    fn mint_to(&self, config: &SettlementConfig, wallet_address: &str, amount: u64) -> Result<String, String> {
+        let amount = amount.to_string();
+        let output = self.run(config, &[
+            "mint", &config.mint_address, &amount, "--recipient-owner", wallet_address,
+        ])?;
+
+        output.lines()
+            .find_map(|line| line.strip_prefix("Signature: "))
+            .map(|sig| sig.trim().to_string())
+            .ok_or(format!("No signature in spl-token output: {}", output))
+    }
+
+    fn balance_of(&self, config: &SettlementConfig, wallet_address: &str) -> Result<u64, String> {
+        let output = self.run(config, &["balance", &config.mint_address, "--owner", wallet_address])?;
+        output.parse::<f64>()
+            .map(|balance| balance as u64)
+            .map_err(|e| format!("Unexpected balance output '{}': {}", output, e))
+    }
+}
+
+impl CommunityResourceEconomy {
+    pub fn configure_settlement(&mut self, config: Option<SettlementConfig>) {
+        self.settlement.config = config;
+    }
+
+    /// Total tokens allocated per recipient in the internal ledger
+    fn internal_balances(&self) -> HashMap<String, u64> {
+        let mut balances = HashMap::new();
+        for allocation in self.token_distribution.values() {
+            *balances.entry(allocation.recipient_id.clone()).or_insert(0) += allocation.amount;
+        }
+        balances
+    }
+
+    /// Mint the next batch of unsettled ledger increases on chain
+    pub fn run_settlement_batch(&mut self, backend: &dyn SettlementBackend) -> Result<SettlementBatch, String> {
+        if let Some(reason) = &self.settlement.halted {
+            return Err(format!("Settlement halted: {}", reason));
+        }
+        let config = self.settlement.config.clone()
+            .ok_or("Settlement is not configured")?;
+
+        let mut pending: Vec<(String, String, u64)> = self.internal_balances().into_iter()
+            .filter_map(|(recipient_id, internal)| {
+                let settled = self.settlement.settled_balances.get(&recipient_id).copied().unwrap_or(0);
+                let wallet = config.recipient_wallets.get(&recipient_id)?;
+                (internal > settled).then(|| (recipient_id, wallet.clone(), internal - settled))
+            })
+            .collect();
+        pending.sort();
+        pending.truncate(config.batch_size);
+
+        let mut transfers = Vec::new();
+        for (recipient_id, wallet_address, amount) in pending {
+            let result = backend.mint_to(&config, &wallet_address, amount);
+            if result.is_ok() {
+                *self.settlement.settled_balances.entry(recipient_id.clone()).or_insert(0) += amount;
+            }
+            transfers.push(SettlementTransfer {
+                recipient_id,
+                wallet_address,
+                amount,
+                signature: result.as_ref().ok().cloned(),
+                error: result.err(),
+            });
+        }
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        let batch = SettlementBatch {
+            batch_id: format!("settle_{}_{}", now, self.settlement.batches.len()),
+            executed_at: now,
+            transfers,
+        };
+
+        println!("⛓️  Settlement batch {}: {} transfers", batch.batch_id, batch.transfers.len());
+        self.settlement.batches.push(batch.clone());
+
+        Ok(batch)
+    }
+
+    /// Compare ledger, settled and on-chain balances. Halts settlement when an
+    /// account's on-chain balance drifts from what we settled.
+    pub fn reconcile_settlement(&mut self, backend: &dyn SettlementBackend) -> Result<ReconciliationReport, String> {
+        let config = self.settlement.config.clone()
+            .ok_or("Settlement is not configured")?;
+
+        let mut accounts = Vec::new();
+        let mut unsettled_total = 0;
+        let mut divergent = Vec::new();
+
+        let mut internal: Vec<(String, u64)> = self.internal_balances().into_iter().collect();
+        internal.sort();
+
+        for (recipient_id, internal) in internal {
+            let settled = self.settlement.settled_balances.get(&recipient_id).copied().unwrap_or(0);
+            unsettled_total += internal.saturating_sub(settled);
+
+            let on_chain = match config.recipient_wallets.get(&recipient_id) {
+                Some(wallet) => Some(backend.balance_of(&config, wallet)?),
+                None => None,
+            };
+
+            // On-chain may legitimately be higher (user bought tokens), never lower
+            if let Some(on_chain) = on_chain {
+                if on_chain + config.divergence_tolerance < settled {
+                    divergent.push(recipient_id.clone());
+                }
+            }
+
+            accounts.push(AccountReconciliation { recipient_id, internal, settled, on_chain });
+        }
+
+        let diverged = !divergent.is_empty();
+        if diverged {
+            let reason = format!("On-chain balance below settled amount for {}", divergent.join(", "));
+            println!("🛑 {}", reason);
+            self.settlement.halted = Some(reason);
+        }
+
+        Ok(ReconciliationReport {
+            checked_at: chrono::Utc::now().timestamp() as u64,
+            accounts,
+            unsettled_total,
+            diverged,
+        })
+    }
+
+    /// Operator acknowledgement after investigating a divergence
+    pub fn resume_settlement(&mut self) {
+        self.settlement.halted = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AllocationType, TokenAllocation};
+    use std::cell::RefCell;
+
+    /// In-memory chain: minted balances per wallet, and wallets whose mints fail
+    #[derive(Default)]
+    struct FakeChain {
+        balances: RefCell<HashMap<String, u64>>,
+        failing: Vec<String>,
+    }
+
+    impl SettlementBackend for FakeChain {
+        fn mint_to(&self, _config: &SettlementConfig, wallet_address: &str, amount: u64) -> Result<String, String> {
+            if self.failing.iter().any(|wallet| wallet == wallet_address) {
+                return Err("RPC unavailable".to_string());
+            }
+            *self.balances.borrow_mut().entry(wallet_address.to_string()).or_insert(0) += amount;
+            Ok(format!("sig_{}_{}", wallet_address, amount))
+        }
+
+        fn balance_of(&self, _config: &SettlementConfig, wallet_address: &str) -> Result<u64, String> {
+            Ok(self.balances.borrow().get(wallet_address).copied().unwrap_or(0))
+        }
+    }
+
+    fn allocate(economy: &mut CommunityResourceEconomy, allocation_id: &str, recipient_id: &str, amount: u64) {
+        economy.token_distribution.insert(allocation_id.to_string(), TokenAllocation {
+            recipient_id: recipient_id.to_string(),
+            allocation_type: AllocationType::UserReward,
+            amount,
+            vesting_schedule: None,
+            conditions: Vec::new(),
+            allocated_by: "test".to_string(),
+            allocated_at: 0,
+        });
+    }
+
+    fn configured(batch_size: usize) -> CommunityResourceEconomy {
+        let mut economy = CommunityResourceEconomy::new();
+        economy.configure_settlement(Some(SettlementConfig {
+            mint_address: "Mint111".to_string(),
+            rpc_url: "http://localhost:8899".to_string(),
+            batch_size,
+            divergence_tolerance: 5,
+            recipient_wallets: HashMap::from([
+                ("alice".to_string(), "AliceWallet".to_string()),
+                ("bob".to_string(), "BobWallet".to_string()),
+            ]),
+        }));
+        economy
+    }
+
+    #[test]
+    fn test_batches_mint_only_unsettled_increases() {
+        let mut economy = configured(10);
+        let chain = FakeChain::default();
+        allocate(&mut economy, "a1", "alice", 100);
+        allocate(&mut economy, "b1", "bob", 50);
+        allocate(&mut economy, "c1", "carol", 70); // no wallet: stays internal
+
+        let batch = economy.run_settlement_batch(&chain).unwrap();
+        assert_eq!(batch.transfers.len(), 2);
+        assert_eq!(chain.balance_of(&economy.settlement.config.clone().unwrap(), "AliceWallet").unwrap(), 100);
+
+        // Nothing new: an empty batch, no double mint
+        assert!(economy.run_settlement_batch(&chain).unwrap().transfers.is_empty());
+
+        allocate(&mut economy, "a2", "alice", 25);
+        let batch = economy.run_settlement_batch(&chain).unwrap();
+        assert_eq!(batch.transfers.len(), 1);
+        assert_eq!(batch.transfers[0].amount, 25);
+        assert_eq!(economy.settlement.settled_balances["alice"], 125);
+    }
+
+    #[test]
+    fn test_batch_size_caps_transfers_and_failures_retry() {
+        let mut economy = configured(1);
+        let chain = FakeChain { failing: vec!["AliceWallet".to_string()], ..FakeChain::default() };
+        allocate(&mut economy, "a1", "alice", 100);
+        allocate(&mut economy, "b1", "bob", 50);
+
+        let batch = economy.run_settlement_batch(&chain).unwrap();
+        assert_eq!(batch.transfers.len(), 1);
+        assert_eq!(batch.transfers[0].recipient_id, "alice");
+        assert!(batch.transfers[0].signature.is_none() && batch.transfers[0].error.is_some());
+        assert!(!economy.settlement.settled_balances.contains_key("alice"));
+
+        let chain = FakeChain { balances: chain.balances, failing: Vec::new() };
+        let batch = economy.run_settlement_batch(&chain).unwrap();
+        assert_eq!(batch.transfers[0].signature.as_deref(), Some("sig_AliceWallet_100"));
+        assert_eq!(economy.run_settlement_batch(&chain).unwrap().transfers[0].recipient_id, "bob");
+    }
+
+    #[test]
+    fn test_divergence_halts_settlement_until_resumed() {
+        let mut economy = configured(10);
+        let chain = FakeChain::default();
+        allocate(&mut economy, "a1", "alice", 100);
+        allocate(&mut economy, "b1", "bob", 50);
+        economy.run_settlement_batch(&chain).unwrap();
+        allocate(&mut economy, "a2", "alice", 10);
+
+        let report = economy.reconcile_settlement(&chain).unwrap();
+        assert!(!report.diverged);
+        assert_eq!(report.unsettled_total, 10);
+
+        // Within tolerance, and above settled (bought on market), are fine
+        chain.balances.borrow_mut().insert("AliceWallet".to_string(), 96);
+        chain.balances.borrow_mut().insert("BobWallet".to_string(), 500);
+        assert!(!economy.reconcile_settlement(&chain).unwrap().diverged);
+
+        chain.balances.borrow_mut().insert("AliceWallet".to_string(), 90);
+        assert!(economy.reconcile_settlement(&chain).unwrap().diverged);
+        assert!(economy.settlement.halted.as_deref().unwrap().contains("alice"));
+        assert!(economy.run_settlement_batch(&chain).is_err());
+
+        economy.resume_settlement();
+        assert_eq!(economy.run_settlement_batch(&chain).unwrap().transfers.len(), 1);
+    }
+
+    #[test]
+    fn test_settlement_is_off_until_configured() {
+        let mut economy = CommunityResourceEconomy::new();
+        allocate(&mut economy, "a1", "alice", 100);
+
+        assert!(economy.run_settlement_batch(&FakeChain::default()).is_err());
+        assert!(economy.reconcile_settlement(&FakeChain::default()).is_err());
+    }
+}
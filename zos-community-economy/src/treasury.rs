@@ -0,0 +1,375 @@
+use serde::{Deserialize, Serialize};
+use zos_approvals::{ApprovalBook, ApprovalNotice, ApprovalPolicy, ApprovalStatus, PendingAction};
+use crate::{CommunityResourceEconomy, ContributedResources, ProposalStatus, ResourceProposal};
+use std::collections::HashMap;
+
+/// Community treasury fed by a share of gateway fees. Balances are kept per
+/// token in the token's smallest unit, as reported by the gateway.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Treasury {
+    pub balances: HashMap<String, u64>,       // token -> base units
+    pub deposited_total: HashMap<String, u64>,
+    pub burned_total: HashMap<String, u64>,
+    pub deposits: Vec<TreasuryDeposit>,
+    pub burns: Vec<BurnRecord>,
+    pub spends: HashMap<String, TreasurySpend>, // proposal_id -> spend
+    #[serde(default)]
+    pub spend_approval_policy: ApprovalPolicy, // thresholds in base units; no approvers, no co-signing
+    #[serde(default)]
+    pub spend_approvals: ApprovalBook,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreasuryDeposit {
+    pub transfer_id: String, // gateway's id; deposits are applied once
+    pub token: String,
+    pub amount: u64,
+    pub source: String,
+    pub received_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BurnRecord {
+    pub burn_id: String,
+    pub token: String,
+    pub amount: u64,
+    pub source: String,
+    pub burned_at: u64,
+}
+
+/// Treasury spending goes through the same vote as community proposals
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreasurySpend {
+    pub proposal_id: String,
+    pub recipient: String,
+    pub token: String,
+    pub amount: u64,
+    pub purpose: String,
+    pub executed_at: Option<u64>,
+}
+
+impl CommunityResourceEconomy {
+    /// Credit the treasury with a gateway fee share. Repeated transfer ids are
+    /// ignored so the gateway can retry delivery.
+    pub fn deposit_to_treasury(&mut self, transfer_id: &str, token: &str, amount: u64,
+                               source: &str) -> Result<bool, String> {
+        if self.treasury.deposits.iter().any(|deposit| deposit.transfer_id == transfer_id) {
+            return Ok(false);
+        }
+        if amount == 0 {
+            return Err("Deposit amount must be positive".to_string());
+        }
+
+        *self.treasury.balances.entry(token.to_string()).or_insert(0) += amount;
+        *self.treasury.deposited_total.entry(token.to_string()).or_insert(0) += amount;
+        self.treasury.deposits.push(TreasuryDeposit {
+            transfer_id: transfer_id.to_string(),
+            token: token.to_string(),
+            amount,
+            source: source.to_string(),
+            received_at: chrono::Utc::now().timestamp() as u64,
+        });
+
+        Ok(true)
+    }
+
+    /// Record fees the gateway burned, for supply reporting
+    pub fn record_burn(&mut self, burn_id: &str, token: &str, amount: u64, source: &str) -> Result<bool, String> {
+        if self.treasury.burns.iter().any(|burn| burn.burn_id == burn_id) {
+            return Ok(false);
+        }
+
+        *self.treasury.burned_total.entry(token.to_string()).or_insert(0) += amount;
+        self.treasury.burns.push(BurnRecord {
+            burn_id: burn_id.to_string(),
+            token: token.to_string(),
+            amount,
+            source: source.to_string(),
+            burned_at: chrono::Utc::now().timestamp() as u64,
+        });
+
+        Ok(true)
+    }
+
+    /// Propose paying `amount` of `token` out of the treasury. The proposal
+    /// requests no community pool tokens; it is voted on like any other.
+    pub fn propose_treasury_spend(&mut self, proposer_id: &str, recipient: &str, token: &str,
+                                  amount: u64, purpose: &str) -> Result<String, String> {
+        if amount == 0 {
+            return Err("Spend amount must be positive".to_string());
+        }
+        let available = self.treasury.balances.get(token).copied().unwrap_or(0);
+        if amount > available {
+            return Err(format!("Treasury holds {} {}, {} requested", available, token, amount));
+        }
+
+        let proposal_id = format!("tspend_{}_{}", proposer_id, chrono::Utc::now().timestamp());
+
+        self.governance_proposals.insert(proposal_id.clone(), ResourceProposal {
+            proposal_id: proposal_id.clone(),
+            proposer_id: proposer_id.to_string(),
+            title: format!("Treasury spend: {} {} to {}", amount, token, recipient),
+            description: purpose.to_string(),
+            requested_tokens: 0,
+            requested_resources: ContributedResources {
+                cpu_cores: 0,
+                memory_gb: 0,
+                storage_gb: 0,
+                bandwidth_mbps: 0,
+                gpu_units: 0,
+                specialized_hardware: Vec::new(),
+            },
+            community_benefit: purpose.to_string(),
+            votes_for: 0,
+            votes_against: 0,
+            status: ProposalStatus::Voting,
+        });
+        self.treasury.spends.insert(proposal_id.clone(), TreasurySpend {
+            proposal_id: proposal_id.clone(),
+            recipient: recipient.to_string(),
+            token: token.to_string(),
+            amount,
+            purpose: purpose.to_string(),
+            executed_at: None,
+        });
+
+        println!("📋 Treasury spend proposed: {} {} to {}", amount, token, recipient);
+
+        Ok(proposal_id)
+    }
+
+    /// Admin wallets that must co-sign spends over the policy's threshold
+    /// once the vote has passed
+    pub fn set_treasury_spend_approvers(&mut self, policy: ApprovalPolicy) -> Result<(), String> {
+        policy.validate()?;
+        println!("✍️  Treasury spends over {} need {} of {} approvers",
+                 policy.threshold, policy.required, policy.approvers.len());
+        self.treasury.spend_approval_policy = policy;
+        Ok(())
+    }
+
+    /// Co-sign (or decline) a spend awaiting approval, as one of its approvers
+    pub fn sign_treasury_spend(&mut self, proposal_id: &str, approver: &str, signature: &str,
+                               approve: bool, reason: Option<String>) -> Result<PendingAction, String> {
+        let action_id = self.treasury.spend_approvals.find("treasury_spend", proposal_id)
+            .map(|action| action.action_id.clone())
+            .ok_or("Spend is not awaiting approval")?;
+        let now = chrono::Utc::now().timestamp() as u64;
+        self.treasury.spend_approvals.sign(&action_id, approver, signature, approve, reason, now)
+    }
+
+    /// Approval requests and outcomes for the approvers' channel; each is
+    /// handed out once
+    pub fn take_treasury_approval_notices(&mut self) -> Vec<ApprovalNotice> {
+        self.treasury.spend_approvals.take_notices()
+    }
+
+    /// Spends over the approval threshold also need their approvers'
+    /// signatures: the first attempt asks for them, and execution waits until
+    /// they are in. Returns the approval to mark executed, if one was needed.
+    fn check_spend_approval(&mut self, proposal_id: &str) -> Result<Option<String>, String> {
+        let spend = self.treasury.spends.get(proposal_id).ok_or("Not a treasury spend proposal")?;
+        let policy = &self.treasury.spend_approval_policy;
+        if !policy.requires_approval(&spend.token, spend.amount as f64) {
+            return Ok(None);
+        }
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        let approvals = &mut self.treasury.spend_approvals;
+        approvals.expire(now);
+        let action = match approvals.find("treasury_spend", proposal_id) {
+            Some(action) if action.status == ApprovalStatus::Approved => return Ok(Some(action.action_id.clone())),
+            Some(action) if action.status == ApprovalStatus::Rejected => {
+                return Err("Spend was rejected by the treasury approvers".to_string());
+            }
+            Some(action) if action.status == ApprovalStatus::Pending => action.clone(),
+            _ => {
+                let summary = format!("Treasury spend of {} {} to {}: {}", spend.amount, spend.token, spend.recipient, spend.purpose);
+                approvals.request(policy, "treasury_spend", proposal_id, &spend.token, spend.amount as f64,
+                                  &summary, "treasury", now)?
+            }
+        };
+        Err(format!("Spend awaits {} of {} approver signatures ({} so far, request {})",
+                    action.required, action.approvers.len(), action.approvals.len(), action.action_id))
+    }
+
+    /// Pay out an approved spend. The balance is checked again here since
+    /// other spends may have executed while this one was being voted on.
+    pub fn execute_treasury_spend(&mut self, proposal_id: &str) -> Result<TreasurySpend, String> {
+        let proposal = self.governance_proposals.get(proposal_id)
+            .ok_or("Proposal not found")?;
+        let spend = self.treasury.spends.get(proposal_id)
+            .ok_or("Not a treasury spend proposal")?;
+
+        if spend.executed_at.is_some() {
+            return Err("Spend already executed".to_string());
+        }
+        if !matches!(proposal.status, ProposalStatus::Approved) {
+            return Err("Only approved spends can be executed".to_string());
+        }
+        let approval = self.check_spend_approval(proposal_id)?;
+
+        let spend = self.treasury.spends.get_mut(proposal_id)
+            .ok_or("Not a treasury spend proposal")?;
+        let balance = self.treasury.balances.entry(spend.token.clone()).or_insert(0);
+        if *balance < spend.amount {
+            return Err(format!("Treasury holds {} {}, {} approved", balance, spend.token, spend.amount));
+        }
+
+        *balance -= spend.amount;
+        let now = chrono::Utc::now().timestamp() as u64;
+        spend.executed_at = Some(now);
+        if let Some(proposal) = self.governance_proposals.get_mut(proposal_id) {
+            proposal.status = ProposalStatus::Implemented;
+        }
+        if let Some(action_id) = approval {
+            self.treasury.spend_approvals.mark_executed(&action_id, "treasury", now)?;
+        }
+
+        println!("🏛️  Treasury paid {} {} to {}", spend.amount, spend.token, spend.recipient);
+
+        Ok(spend.clone())
+    }
+
+    pub fn treasury_report(&self) -> String {
+        let pending: Vec<_> = self.treasury.spends.values()
+            .filter(|spend| spend.executed_at.is_none())
+            .filter(|spend| self.governance_proposals.get(&spend.proposal_id)
+                .is_some_and(|proposal| matches!(proposal.status, ProposalStatus::Voting | ProposalStatus::Approved)))
+            .collect();
+
+        let mut committed: HashMap<&str, u64> = HashMap::new();
+        for spend in &pending {
+            *committed.entry(spend.token.as_str()).or_insert(0) += spend.amount;
+        }
+
+        serde_json::json!({
+            "balances": self.treasury.balances,
+            "deposited_total": self.treasury.deposited_total,
+            "burned_total": self.treasury.burned_total,
+            "pending_spends": pending,
+            "awaiting_approval": self.treasury.spend_approvals.pending(),
+            "committed_to_pending": committed,
+            "executed_spends": self.treasury.spends.values()
+                .filter(|spend| spend.executed_at.is_some())
+                .collect::<Vec<_>>(),
+            "recent_burns": self.treasury.burns.iter().rev().take(20).collect::<Vec<_>>()
+        }).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AllocationType, TokenAllocation};
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn economy_with_treasury(usdc: u64) -> CommunityResourceEconomy {
+        let mut economy = CommunityResourceEconomy::new();
+        economy.token_distribution.insert("alloc_voter".to_string(), TokenAllocation {
+            recipient_id: "voter".to_string(),
+            allocation_type: AllocationType::UserReward,
+            amount: 100,
+            vesting_schedule: None,
+            conditions: Vec::new(),
+            allocated_by: "test".to_string(),
+            allocated_at: 0,
+        });
+        economy.deposit_to_treasury("fee_1", "USDC", usdc, "gateway").unwrap();
+        economy
+    }
+
+    fn pass(economy: &mut CommunityResourceEconomy, proposal_id: &str) {
+        economy.cast_vote(proposal_id, "voter", true).unwrap();
+        economy.finalize_proposal(proposal_id).unwrap();
+    }
+
+    #[test]
+    fn test_deposits_and_burns_apply_once() {
+        let mut economy = economy_with_treasury(500);
+        assert!(!economy.deposit_to_treasury("fee_1", "USDC", 500, "gateway").unwrap());
+        assert!(economy.deposit_to_treasury("fee_2", "USDC", 0, "gateway").is_err());
+        assert!(economy.deposit_to_treasury("fee_2", "SOL", 7, "gateway").unwrap());
+
+        assert!(economy.record_burn("burn_1", "USDC", 40, "gateway").unwrap());
+        assert!(!economy.record_burn("burn_1", "USDC", 40, "gateway").unwrap());
+
+        assert_eq!(economy.treasury.balances["USDC"], 500);
+        assert_eq!(economy.treasury.balances["SOL"], 7);
+        assert_eq!(economy.treasury.deposited_total["USDC"], 500);
+        assert_eq!(economy.treasury.burned_total["USDC"], 40);
+    }
+
+    #[test]
+    fn test_spends_pay_out_only_after_the_vote() {
+        let mut economy = economy_with_treasury(500);
+        assert!(economy.propose_treasury_spend("proposer", "builder", "USDC", 501, "docs").is_err());
+        assert!(economy.propose_treasury_spend("proposer", "builder", "USDC", 0, "docs").is_err());
+        let proposal_id = economy.propose_treasury_spend("proposer", "builder", "USDC", 300, "docs").unwrap();
+        assert_eq!(economy.governance_proposals[&proposal_id].requested_tokens, 0);
+
+        assert!(economy.execute_treasury_spend(&proposal_id).is_err());
+        pass(&mut economy, &proposal_id);
+
+        let spend = economy.execute_treasury_spend(&proposal_id).unwrap();
+        assert!(spend.executed_at.is_some());
+        assert_eq!(economy.treasury.balances["USDC"], 200);
+        assert!(matches!(economy.governance_proposals[&proposal_id].status, ProposalStatus::Implemented));
+        assert!(economy.execute_treasury_spend(&proposal_id).is_err());
+    }
+
+    #[test]
+    fn test_rejected_spends_and_drained_balances_pay_nothing() {
+        let mut economy = economy_with_treasury(500);
+        let rejected = economy.propose_treasury_spend("proposer", "builder", "USDC", 100, "swag").unwrap();
+        economy.cast_vote(&rejected, "voter", false).unwrap();
+        economy.finalize_proposal(&rejected).unwrap();
+        assert!(economy.execute_treasury_spend(&rejected).is_err());
+
+        // Both fit when proposed; only one fits by the time they execute
+        let first = economy.propose_treasury_spend("alice_proposer", "builder", "USDC", 400, "audit").unwrap();
+        let second = economy.propose_treasury_spend("bob_proposer", "builder", "USDC", 400, "audit").unwrap();
+        pass(&mut economy, &first);
+        pass(&mut economy, &second);
+        economy.execute_treasury_spend(&first).unwrap();
+        assert!(economy.execute_treasury_spend(&second).is_err());
+        assert_eq!(economy.treasury.balances["USDC"], 100);
+    }
+
+    #[test]
+    fn test_large_spends_wait_for_approver_signatures() {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let approver = bs58::encode(key.verifying_key().as_bytes()).into_string();
+        let mut economy = economy_with_treasury(5_000);
+        assert!(economy.set_treasury_spend_approvers(ApprovalPolicy {
+            approvers: vec![approver.clone()], required: 2, threshold: 1_000.0,
+            token_thresholds: HashMap::new(), expiry_secs: 3600,
+        }).is_err());
+        economy.set_treasury_spend_approvers(ApprovalPolicy {
+            approvers: vec![approver.clone()], required: 1, threshold: 1_000.0,
+            token_thresholds: HashMap::new(), expiry_secs: 3600,
+        }).unwrap();
+
+        let small = economy.propose_treasury_spend("alice_proposer", "builder", "USDC", 1_000, "docs").unwrap();
+        let large = economy.propose_treasury_spend("bob_proposer", "builder", "USDC", 3_000, "audit").unwrap();
+        pass(&mut economy, &small);
+        pass(&mut economy, &large);
+        economy.execute_treasury_spend(&small).unwrap();
+
+        // The first attempt asks for signatures; retries don't ask again
+        assert!(economy.execute_treasury_spend(&large).unwrap_err().contains("awaits"));
+        assert!(economy.execute_treasury_spend(&large).is_err());
+        assert_eq!(economy.take_treasury_approval_notices().len(), 1);
+        assert_eq!(economy.treasury.balances["USDC"], 4_000);
+
+        let action = economy.treasury.spend_approvals.find("treasury_spend", &large).unwrap().clone();
+        let signature = bs58::encode(key.sign(action.signing_message(true).as_bytes()).to_bytes()).into_string();
+        assert!(economy.sign_treasury_spend(&large, &approver, "bogus", true, None).is_err());
+        economy.sign_treasury_spend(&large, &approver, &signature, true, None).unwrap();
+
+        economy.execute_treasury_spend(&large).unwrap();
+        assert_eq!(economy.treasury.balances["USDC"], 1_000);
+        let action = economy.treasury.spend_approvals.find("treasury_spend", &large).unwrap();
+        assert_eq!(action.status, ApprovalStatus::Executed);
+    }
+}
@@ -0,0 +1,623 @@
+use serde::{Deserialize, Serialize};
+use crate::{CommunityResourceEconomy, ContributedResources};
+use std::collections::HashMap;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BenchmarkKind {
+    Cpu,
+    MemoryBandwidth,
+    DiskIo,
+    Gpu,
+}
+
+impl BenchmarkKind {
+    pub const ALL: [BenchmarkKind; 4] = [
+        BenchmarkKind::Cpu,
+        BenchmarkKind::MemoryBandwidth,
+        BenchmarkKind::DiskIo,
+        BenchmarkKind::Gpu,
+    ];
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BenchmarkPolicy {
+    pub interval_secs: u64,          // how often each resource is re-checked
+    pub response_window_secs: u64,
+    pub workload_size_mb: u32,       // buffer / file size for memory and disk runs
+    pub cpu_iterations: u64,         // per thread
+    pub cpu_chunk_iterations: u64,   // CPU work is answered per chunk so it can be spot-checked
+    pub spot_checks: usize,          // chunks recomputed per CPU answer
+    pub latency_allowance_ms: u64,   // wall-clock time since issue not charged to the run
+    pub cpu_mops_per_core: f64,      // expected score per claimed core
+    pub memory_mbps_per_gb: f64,     // expected copy bandwidth per claimed GB
+    pub min_disk_mbps: f64,          // expected sequential write speed
+    pub pass_ratio: f64,             // measured/expected at or above this passes
+    pub fraud_ratio: f64,            // below this the claim is treated as fraudulent
+    pub reputation_penalty: f32,     // scaled by how far below expectation the result was
+}
+
+impl Default for BenchmarkPolicy {
+    fn default() -> Self {
+        Self {
+            interval_secs: 24 * 3600,
+            response_window_secs: 600,
+            workload_size_mb: 256,
+            cpu_iterations: 50_000_000,
+            cpu_chunk_iterations: 1_000_000,
+            spot_checks: 8,
+            latency_allowance_ms: 2_000,
+            cpu_mops_per_core: 100.0,
+            memory_mbps_per_gb: 150.0,
+            min_disk_mbps: 100.0,
+            pass_ratio: 0.9,
+            fraud_ratio: 0.5,
+            reputation_penalty: 20.0,
+        }
+    }
+}
+
+/// Standardized workload sent to a server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkChallenge {
+    pub challenge_id: String,
+    pub server_id: String,
+    pub kind: BenchmarkKind,
+    pub nonce: u64,
+    pub threads: u32,
+    pub iterations: u64,
+    #[serde(default)]
+    pub chunk_iterations: u64,
+    pub size_mb: u32,
+    pub expected: f64,
+    pub issued_at: u64,
+    #[serde(default)]
+    pub issued_at_ms: u64,
+    pub deadline: u64,
+}
+
+impl BenchmarkChallenge {
+    /// Chunks of CPU work per thread; every chunk has its own seed
+    fn chunks_per_thread(&self) -> u64 {
+        (self.iterations / self.chunk_iterations.max(1)).max(1)
+    }
+
+    pub fn cpu_chunks(&self) -> u64 {
+        self.threads as u64 * self.chunks_per_thread()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkResult {
+    pub challenge_id: String,
+    pub measured: f64, // Mops for CPU, MB/s for memory and disk, devices for GPU
+    pub checksum: u64, // ties the result to the challenge nonce
+    pub duration_ms: u64,
+    #[serde(default)]
+    pub chunk_checksums: Vec<u64>, // CPU only, one per chunk in chunk order
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkOutcome {
+    pub challenge_id: String,
+    pub kind: BenchmarkKind,
+    pub expected: f64,
+    pub measured: f64,
+    pub ratio: f64,
+    pub passed: bool,
+    pub fraud: bool,
+    pub checked_at: u64,
+    #[serde(default)]
+    pub verified: bool, // false for self-reported results, which change nothing
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerVerification {
+    pub effective_resources: ContributedResources,
+    pub last_checked: HashMap<BenchmarkKind, u64>,
+    pub history: Vec<BenchmarkOutcome>,
+}
+
+impl CommunityResourceEconomy {
+    /// Verified capacity, falling back to the claim until a benchmark has run
+    pub fn effective_resources(&self, server_id: &str) -> Option<ContributedResources> {
+        self.verifications.get(server_id)
+            .map(|verification| verification.effective_resources.clone())
+            .or_else(|| self.servers.get(server_id).map(|server| server.contributed_resources.clone()))
+    }
+
+    /// Create challenges for every claimed resource that is due a re-check
+    pub fn issue_benchmark_challenges(&mut self) -> Vec<BenchmarkChallenge> {
+        let now_ms = chrono::Utc::now().timestamp_millis() as u64;
+        let now = now_ms / 1000;
+        let policy = self.benchmark_policy.clone();
+        let mut issued = Vec::new();
+
+        for server in self.servers.values() {
+            let claimed = &server.contributed_resources;
+            let verification = self.verifications.entry(server.server_id.clone())
+                .or_insert_with(|| ServerVerification {
+                    effective_resources: claimed.clone(),
+                    last_checked: HashMap::new(),
+                    history: Vec::new(),
+                });
+
+            for kind in BenchmarkKind::ALL {
+                let expected = match kind {
+                    BenchmarkKind::Cpu => claimed.cpu_cores as f64 * policy.cpu_mops_per_core,
+                    BenchmarkKind::MemoryBandwidth => claimed.memory_gb as f64 * policy.memory_mbps_per_gb,
+                    BenchmarkKind::DiskIo if claimed.storage_gb > 0 => policy.min_disk_mbps,
+                    BenchmarkKind::DiskIo => 0.0,
+                    BenchmarkKind::Gpu => claimed.gpu_units as f64,
+                };
+                if expected <= 0.0 {
+                    continue;
+                }
+
+                let due = verification.last_checked.get(&kind)
+                    .is_none_or(|last| now >= last + policy.interval_secs);
+                let pending = self.benchmark_challenges.values()
+                    .any(|c| c.server_id == server.server_id && c.kind == kind);
+                if !due || pending {
+                    continue;
+                }
+
+                issued.push(BenchmarkChallenge {
+                    challenge_id: format!("bench_{}_{:?}_{}", server.server_id, kind, now),
+                    server_id: server.server_id.clone(),
+                    kind,
+                    nonce: rand::random(),
+                    threads: claimed.cpu_cores.max(1),
+                    iterations: policy.cpu_iterations,
+                    chunk_iterations: policy.cpu_chunk_iterations,
+                    size_mb: policy.workload_size_mb,
+                    expected,
+                    issued_at: now,
+                    issued_at_ms: now_ms,
+                    deadline: now + policy.response_window_secs,
+                });
+            }
+        }
+
+        for challenge in &issued {
+            self.benchmark_challenges.insert(challenge.challenge_id.clone(), challenge.clone());
+        }
+
+        if !issued.is_empty() {
+            println!("🧪 Issued {} benchmark challenges", issued.len());
+        }
+
+        issued
+    }
+
+    /// Score a server's answer. Only CPU answers can be checked against work
+    /// the economy recomputes; memory, disk and GPU answers are recorded as
+    /// self-reported and change neither capacity, reputation nor rewards.
+    pub fn submit_benchmark_result(&mut self, server_id: &str, result: BenchmarkResult) -> Result<BenchmarkOutcome, String> {
+        let challenge = self.benchmark_challenges.get(&result.challenge_id)
+            .ok_or("Unknown or expired challenge")?;
+        if challenge.server_id != server_id {
+            return Err("Challenge was issued to a different server".to_string());
+        }
+
+        let challenge = self.benchmark_challenges.remove(&result.challenge_id).unwrap();
+        let now_ms = chrono::Utc::now().timestamp_millis() as u64;
+        let now = now_ms / 1000;
+        let verified = challenge.kind == BenchmarkKind::Cpu;
+
+        let measured = if now > challenge.deadline {
+            println!("⏰ Benchmark {} answered after the deadline", challenge.challenge_id);
+            0.0
+        } else if verified {
+            match self.score_cpu_result(&challenge, &result, now_ms) {
+                Ok(measured) => measured,
+                Err(reason) => {
+                    println!("❌ Benchmark {} rejected: {}", challenge.challenge_id, reason);
+                    0.0
+                }
+            }
+        } else {
+            result.measured
+        };
+
+        self.apply_benchmark_outcome(challenge, measured, verified, now)
+    }
+
+    /// Check a CPU answer and score it from the work it proves. The chunk
+    /// checksums must add up to the reported checksum, and a random sample
+    /// of chunks is recomputed. The run is charged at least the wall-clock
+    /// time since issue, less the latency allowance, so understating
+    /// `duration_ms` buys nothing past that allowance.
+    fn score_cpu_result(&self, challenge: &BenchmarkChallenge, result: &BenchmarkResult,
+                        now_ms: u64) -> Result<f64, String> {
+        let chunks = challenge.cpu_chunks();
+        if result.chunk_checksums.len() as u64 != chunks {
+            return Err(format!("{} chunk checksums for {} chunks", result.chunk_checksums.len(), chunks));
+        }
+        if combine_checksums(&result.chunk_checksums) != result.checksum {
+            return Err("chunk checksums don't match the checksum".to_string());
+        }
+
+        let samples = self.benchmark_policy.spot_checks.min(chunks as usize);
+        for index in rand::seq::index::sample(&mut rand::thread_rng(), chunks as usize, samples) {
+            let expected = cpu_checksum(chunk_seed(challenge.nonce, index as u64), challenge.chunk_iterations.max(1));
+            if result.chunk_checksums[index] != expected {
+                return Err(format!("chunk {} checksum mismatch", index));
+            }
+        }
+
+        let issued_at_ms = if challenge.issued_at_ms > 0 { challenge.issued_at_ms } else { challenge.issued_at * 1000 };
+        let elapsed_ms = now_ms.saturating_sub(issued_at_ms);
+        if result.duration_ms == 0 || result.duration_ms > elapsed_ms + 1 { // 1 ms for clock resolution
+            return Err(format!("run took {} ms but {} ms have passed since issue", result.duration_ms, elapsed_ms));
+        }
+
+        let charged_ms = result.duration_ms.max(elapsed_ms.saturating_sub(self.benchmark_policy.latency_allowance_ms));
+        let ops = chunks as f64 * challenge.chunk_iterations.max(1) as f64;
+        Ok(ops / charged_ms as f64 / 1_000.0)
+    }
+
+    /// Unanswered CPU challenges count as failed, but not as fraud; unanswered
+    /// self-reported ones are only recorded
+    pub fn expire_benchmark_challenges(&mut self) -> Vec<BenchmarkOutcome> {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let expired: Vec<String> = self.benchmark_challenges.values()
+            .filter(|challenge| now > challenge.deadline)
+            .map(|challenge| challenge.challenge_id.clone())
+            .collect();
+
+        let mut outcomes = Vec::new();
+        for challenge_id in expired {
+            let challenge = self.benchmark_challenges.remove(&challenge_id).unwrap();
+            let verified = challenge.kind == BenchmarkKind::Cpu;
+            let outcome = BenchmarkOutcome {
+                challenge_id,
+                kind: challenge.kind,
+                expected: challenge.expected,
+                measured: 0.0,
+                ratio: 0.0,
+                passed: false,
+                fraud: false,
+                checked_at: now,
+                verified,
+            };
+
+            let penalty = self.benchmark_policy.reputation_penalty;
+            if let Some(server) = self.servers.get_mut(&challenge.server_id).filter(|_| verified) {
+                server.reputation_score = (server.reputation_score - penalty).max(0.0);
+            }
+            if let Some(verification) = self.verifications.get_mut(&challenge.server_id) {
+                verification.history.push(outcome.clone());
+            }
+            outcomes.push(outcome);
+        }
+
+        outcomes
+    }
+
+    fn apply_benchmark_outcome(&mut self, challenge: BenchmarkChallenge, measured: f64, verified: bool,
+                               now: u64) -> Result<BenchmarkOutcome, String> {
+        let policy = self.benchmark_policy.clone();
+        let ratio = (measured / challenge.expected).clamp(0.0, 1.0);
+        let passed = ratio >= policy.pass_ratio;
+        let fraud = verified && ratio < policy.fraud_ratio;
+
+        let claimed = self.servers.get(&challenge.server_id)
+            .map(|server| server.contributed_resources.clone())
+            .ok_or("Server not found")?;
+
+        let outcome = BenchmarkOutcome {
+            challenge_id: challenge.challenge_id.clone(),
+            kind: challenge.kind,
+            expected: challenge.expected,
+            measured,
+            ratio,
+            passed,
+            fraud,
+            checked_at: now,
+            verified,
+        };
+
+        let verification = self.verifications.entry(challenge.server_id.clone())
+            .or_insert_with(|| ServerVerification {
+                effective_resources: claimed.clone(),
+                last_checked: HashMap::new(),
+                history: Vec::new(),
+            });
+        verification.last_checked.insert(challenge.kind, now);
+        verification.history.push(outcome.clone());
+
+        if !verified {
+            println!("ℹ️  {:?} benchmark for {} is self-reported: {:.1} vs {:.1} expected, not scored",
+                     challenge.kind, challenge.server_id, measured, challenge.expected);
+            return Ok(outcome);
+        }
+
+        if let Some(server) = self.servers.get_mut(&challenge.server_id) {
+            server.reputation_score = if passed {
+                (server.reputation_score + 1.0).min(100.0)
+            } else {
+                (server.reputation_score - policy.reputation_penalty * (1.0 - ratio) as f32).max(0.0)
+            };
+        }
+
+        // A pass restores the claimed amount; a miss scales it down to what was shown
+        let shown = |claim: u32| if passed { claim } else { (claim as f64 * ratio) as u32 };
+        let verification = self.verifications.get_mut(&challenge.server_id)
+            .ok_or("Server not found")?;
+        let effective = &mut verification.effective_resources;
+        match challenge.kind {
+            BenchmarkKind::Cpu => effective.cpu_cores = shown(claimed.cpu_cores),
+            BenchmarkKind::MemoryBandwidth => effective.memory_gb = shown(claimed.memory_gb),
+            BenchmarkKind::DiskIo => effective.storage_gb = shown(claimed.storage_gb),
+            BenchmarkKind::Gpu => effective.gpu_units = shown(claimed.gpu_units),
+        }
+
+        let effective = verification.effective_resources.clone();
+        let token_allocation = self.calculate_server_token_allocation(&effective);
+        if let Some(server) = self.servers.get_mut(&challenge.server_id) {
+            server.token_allocation = token_allocation;
+        }
+        self.update_community_metrics();
+
+        if passed {
+            println!("✅ {:?} benchmark passed for {} ({:.0}%)", challenge.kind, challenge.server_id, ratio * 100.0);
+        } else {
+            println!("⚠️  {:?} benchmark for {}: {:.1} measured vs {:.1} expected",
+                     challenge.kind, challenge.server_id, measured, challenge.expected);
+        }
+
+        if fraud {
+            self.slash_for_fraud(&challenge.server_id,
+                                 &format!("{:?} benchmark at {:.0}% of claimed capacity", challenge.kind, ratio * 100.0))?;
+        }
+
+        Ok(outcome)
+    }
+}
+
+/// Seed of one chunk of CPU work, spread so neighbouring chunks share nothing
+fn chunk_seed(nonce: u64, index: u64) -> u64 {
+    let mut z = nonce ^ index.wrapping_add(1).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+fn combine_checksums(checksums: &[u64]) -> u64 {
+    checksums.iter().fold(0u64, |acc, checksum| acc.wrapping_mul(31).wrapping_add(*checksum))
+}
+
+fn cpu_checksum(nonce: u64, iterations: u64) -> u64 {
+    let mut x = nonce | 1;
+    for _ in 0..iterations {
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+    }
+    x
+}
+
+/// Run a challenge on this machine; used by server nodes to answer the economy
+pub fn run_benchmark(challenge: &BenchmarkChallenge) -> BenchmarkResult {
+    let started = Instant::now();
+    let mut chunk_checksums = Vec::new();
+
+    let measured = match challenge.kind {
+        BenchmarkKind::Cpu => {
+            // Thread t runs chunks t, t + threads, t + 2 * threads, ...
+            let (threads, chunks) = (challenge.threads.max(1) as u64, challenge.cpu_chunks());
+            let handles: Vec<_> = (0..threads)
+                .map(|thread| {
+                    let (nonce, iterations) = (challenge.nonce, challenge.chunk_iterations.max(1));
+                    std::thread::spawn(move || (thread..chunks).step_by(threads as usize)
+                        .map(|index| cpu_checksum(chunk_seed(nonce, index), iterations))
+                        .collect::<Vec<u64>>())
+                })
+                .collect();
+            let per_thread: Vec<Vec<u64>> = handles.into_iter()
+                .map(|handle| handle.join().unwrap_or_default())
+                .collect();
+            chunk_checksums = (0..chunks)
+                .map(|index| per_thread[(index % threads) as usize].get((index / threads) as usize).copied().unwrap_or(0))
+                .collect();
+            let ops = chunks as f64 * challenge.chunk_iterations.max(1) as f64;
+            ops / started.elapsed().as_secs_f64() / 1_000_000.0
+        }
+        BenchmarkKind::MemoryBandwidth => {
+            let size = challenge.size_mb as usize * 1024 * 1024;
+            let source = vec![(challenge.nonce & 0xff) as u8; size];
+            let mut dest = vec![0u8; size];
+            let copy_started = Instant::now();
+            let rounds = 8;
+            for _ in 0..rounds {
+                dest.copy_from_slice(std::hint::black_box(&source));
+            }
+            std::hint::black_box(&dest);
+            (challenge.size_mb as f64 * rounds as f64) / copy_started.elapsed().as_secs_f64()
+        }
+        BenchmarkKind::DiskIo => disk_write_mbps(challenge).unwrap_or(0.0),
+        BenchmarkKind::Gpu => std::process::Command::new("nvidia-smi")
+            .args(["--query-gpu=name", "--format=csv,noheader"])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).lines().filter(|l| !l.trim().is_empty()).count() as f64)
+            .unwrap_or(0.0),
+    };
+
+    BenchmarkResult {
+        challenge_id: challenge.challenge_id.clone(),
+        measured,
+        checksum: combine_checksums(&chunk_checksums),
+        duration_ms: (started.elapsed().as_millis() as u64).max(1),
+        chunk_checksums,
+    }
+}
+
+fn disk_write_mbps(challenge: &BenchmarkChallenge) -> std::io::Result<f64> {
+    use std::io::Write;
+
+    let path = std::env::temp_dir().join(format!("zos-bench-{}", challenge.nonce));
+    let chunk = vec![(challenge.nonce & 0xff) as u8; 1024 * 1024];
+    let started = Instant::now();
+
+    let result = (|| {
+        let mut file = std::fs::File::create(&path)?;
+        for _ in 0..challenge.size_mb {
+            file.write_all(&chunk)?;
+        }
+        file.sync_all()
+    })();
+    let elapsed = started.elapsed().as_secs_f64();
+    let _ = std::fs::remove_file(&path);
+
+    result.map(|_| challenge.size_mb as f64 / elapsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AllocationType, TokenAllocation};
+
+    /// A bonded server claiming 2 cores, 4 GB, 10 GB of disk and a GPU, with
+    /// a CPU workload small enough to run here and every chunk spot-checked
+    fn challenged() -> (CommunityResourceEconomy, String, HashMap<BenchmarkKind, BenchmarkChallenge>) {
+        let mut economy = CommunityResourceEconomy::new();
+        economy.benchmark_policy = BenchmarkPolicy {
+            cpu_iterations: 20_000,
+            cpu_chunk_iterations: 1_000,
+            spot_checks: 40,
+            cpu_mops_per_core: 0.001,
+            workload_size_mb: 1,
+            ..BenchmarkPolicy::default()
+        };
+        economy.token_distribution.insert("fund".to_string(), TokenAllocation {
+            recipient_id: "operator_one".to_string(),
+            allocation_type: AllocationType::UserReward,
+            amount: 20_000,
+            vesting_schedule: None,
+            conditions: Vec::new(),
+            allocated_by: "test".to_string(),
+            allocated_at: 0,
+        });
+        let server_id = economy.register_community_server("operator_one", "node", "earth", ContributedResources {
+            cpu_cores: 2, memory_gb: 4, storage_gb: 10, bandwidth_mbps: 0, gpu_units: 1,
+            specialized_hardware: Vec::new(),
+        }).unwrap();
+        economy.bond_stake(&server_id, "operator_one", 10_000).unwrap();
+
+        let challenges = economy.issue_benchmark_challenges().into_iter()
+            .map(|challenge| (challenge.kind, challenge))
+            .collect();
+        (economy, server_id, challenges)
+    }
+
+    #[test]
+    fn test_challenges_carry_random_nonces_and_are_not_reissued() {
+        let (mut economy, _, challenges) = challenged();
+        assert_eq!(challenges.len(), 4);
+        assert!(economy.issue_benchmark_challenges().is_empty());
+
+        let nonces: std::collections::HashSet<u64> = challenges.values().map(|c| c.nonce).collect();
+        assert_eq!(nonces.len(), 4);
+        assert_eq!(challenges[&BenchmarkKind::Cpu].cpu_chunks(), 40);
+    }
+
+    #[test]
+    fn test_honest_cpu_work_passes() {
+        let (mut economy, server_id, challenges) = challenged();
+        let result = run_benchmark(&challenges[&BenchmarkKind::Cpu]);
+        assert_eq!(result.chunk_checksums.len(), 40);
+
+        let outcome = economy.submit_benchmark_result(&server_id, result.clone()).unwrap();
+        assert!(outcome.verified && outcome.passed && !outcome.fraud);
+        assert_eq!(economy.servers[&server_id].reputation_score, 51.0);
+        assert!(economy.submit_benchmark_result(&server_id, result).is_err());
+    }
+
+    #[test]
+    fn test_skipped_or_forged_cpu_work_is_fraud() {
+        let (mut economy, server_id, challenges) = challenged();
+        let challenge = &challenges[&BenchmarkKind::Cpu];
+        let honest = run_benchmark(challenge);
+
+        // One chunk skipped, with the checksum made to agree
+        let mut skipped = honest.clone();
+        skipped.chunk_checksums[17] = 0;
+        skipped.checksum = combine_checksums(&skipped.chunk_checksums);
+        let outcome = economy.submit_benchmark_result(&server_id, skipped).unwrap();
+        assert!(outcome.verified && outcome.fraud && outcome.measured == 0.0);
+        assert_eq!(economy.operator_bonds[&server_id].slash_history.len(), 1);
+        assert_eq!(economy.effective_resources(&server_id).unwrap().cpu_cores, 0);
+
+        // Results for someone else's challenge, or with too few chunks, fail too
+        economy.benchmark_challenges.insert(challenge.challenge_id.clone(), challenge.clone());
+        assert!(economy.submit_benchmark_result("server_other", honest.clone()).is_err());
+        let mut truncated = honest;
+        truncated.chunk_checksums.pop();
+        truncated.checksum = combine_checksums(&truncated.chunk_checksums);
+        assert!(economy.submit_benchmark_result(&server_id, truncated).unwrap().fraud);
+    }
+
+    #[test]
+    fn test_cpu_runs_are_charged_wall_clock_time() {
+        let (mut economy, server_id, challenges) = challenged();
+        let mut challenge = challenges[&BenchmarkKind::Cpu].clone();
+        challenge.expected = 0.01; // passing needs the 40k ops inside ~4.4 s
+        let result = run_benchmark(&challenge);
+
+        // A run can't have taken longer than the time since issue
+        let mut too_long = result.clone();
+        too_long.duration_ms = 60_000;
+        economy.benchmark_challenges.insert(challenge.challenge_id.clone(), challenge.clone());
+        assert!(economy.submit_benchmark_result(&server_id, too_long).unwrap().fraud);
+
+        // An answer 10s after issue is charged 8s whatever duration it reports
+        challenge.issued_at_ms -= 10_000;
+        economy.benchmark_challenges.insert(challenge.challenge_id.clone(), challenge);
+        let outcome = economy.submit_benchmark_result(&server_id, result).unwrap();
+        assert!(!outcome.passed);
+        assert!(outcome.measured < 40_000.0 / 8.0 / 1_000.0 / 1_000.0 * 1.01);
+    }
+
+    #[test]
+    fn test_self_reported_results_change_nothing() {
+        let (mut economy, server_id, challenges) = challenged();
+        let allocation = economy.servers[&server_id].token_allocation;
+
+        for kind in [BenchmarkKind::MemoryBandwidth, BenchmarkKind::DiskIo, BenchmarkKind::Gpu] {
+            let result = BenchmarkResult {
+                challenge_id: challenges[&kind].challenge_id.clone(),
+                measured: 0.0,
+                checksum: 0,
+                duration_ms: 1,
+                chunk_checksums: Vec::new(),
+            };
+            let outcome = economy.submit_benchmark_result(&server_id, result).unwrap();
+            assert!(!outcome.verified && !outcome.passed && !outcome.fraud);
+        }
+
+        let effective = economy.effective_resources(&server_id).unwrap();
+        assert_eq!((effective.memory_gb, effective.storage_gb, effective.gpu_units), (4, 10, 1));
+        assert_eq!(economy.servers[&server_id].token_allocation, allocation);
+        assert_eq!(economy.servers[&server_id].reputation_score, 50.0);
+        assert!(economy.operator_bonds[&server_id].slash_history.is_empty());
+        assert_eq!(economy.verifications[&server_id].history.len(), 3);
+    }
+
+    #[test]
+    fn test_unanswered_challenges_only_cost_cpu_reputation() {
+        let (mut economy, server_id, _) = challenged();
+        for challenge in economy.benchmark_challenges.values_mut() {
+            challenge.deadline = 0;
+        }
+
+        let outcomes = economy.expire_benchmark_challenges();
+        assert_eq!(outcomes.len(), 4);
+        assert_eq!(outcomes.iter().filter(|outcome| outcome.verified).count(), 1);
+        assert_eq!(economy.servers[&server_id].reputation_score, 30.0);
+        assert!(economy.operator_bonds[&server_id].slash_history.is_empty());
+    }
+}
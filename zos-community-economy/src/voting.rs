@@ -0,0 +1,254 @@
+use serde::{Deserialize, Serialize};
+use crate::{CommunityResourceEconomy, ProposalStatus};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DelegationRecord {
+    pub delegator: String,
+    pub delegate: Option<String>, // None = revoked
+    pub timestamp: u64,
+}
+
+impl CommunityResourceEconomy {
+    /// Own voting power: tokens allocated to the account, less slashed stake
+    pub fn voting_power(&self, account_id: &str) -> u64 {
+        self.token_balance(account_id)
+    }
+
+    /// Delegate voting power. Rejected if it would create a delegation cycle.
+    pub fn delegate_votes(&mut self, delegator: &str, delegate: &str) -> Result<(), String> {
+        if delegator == delegate {
+            return Err("Cannot delegate to yourself".to_string());
+        }
+
+        // Walk the delegate's chain; reaching the delegator means a cycle
+        let mut current = delegate;
+        let mut seen = HashSet::new();
+        while let Some(next) = self.delegations.get(current) {
+            if next == delegator {
+                return Err(format!("Delegation {} → {} would create a cycle", delegator, delegate));
+            }
+            if !seen.insert(next.as_str()) {
+                break;
+            }
+            current = next;
+        }
+
+        self.delegations.insert(delegator.to_string(), delegate.to_string());
+        self.delegation_log.push(DelegationRecord {
+            delegator: delegator.to_string(),
+            delegate: Some(delegate.to_string()),
+            timestamp: chrono::Utc::now().timestamp() as u64,
+        });
+
+        println!("🤝 {} delegated voting power to {}", delegator, delegate);
+        self.retally_open_proposals();
+        Ok(())
+    }
+
+    pub fn revoke_delegation(&mut self, delegator: &str) -> Result<(), String> {
+        self.delegations.remove(delegator)
+            .ok_or("No active delegation")?;
+
+        self.delegation_log.push(DelegationRecord {
+            delegator: delegator.to_string(),
+            delegate: None,
+            timestamp: chrono::Utc::now().timestamp() as u64,
+        });
+
+        println!("🔙 {} revoked their delegation", delegator);
+        self.retally_open_proposals();
+        Ok(())
+    }
+
+    /// Record a vote; a direct vote always overrides the voter's delegation
+    pub fn cast_vote(&mut self, proposal_id: &str, voter_id: &str, support: bool) -> Result<u64, String> {
+        let proposal = self.governance_proposals.get(proposal_id)
+            .ok_or("Proposal not found")?;
+        if !matches!(proposal.status, ProposalStatus::Voting) {
+            return Err("Proposal is not open for voting".to_string());
+        }
+
+        self.ballots.entry(proposal_id.to_string())
+            .or_default()
+            .insert(voter_id.to_string(), support);
+
+        self.tally_proposal(proposal_id);
+        Ok(self.effective_voting_power(proposal_id, voter_id))
+    }
+
+    /// Whose ballot an account's power follows: its own if it voted, otherwise
+    /// the first voter up its delegation chain
+    fn resolve_voter<'a>(&'a self, ballots: &HashMap<String, bool>, account_id: &'a str) -> Option<&'a str> {
+        let mut current = account_id;
+        let mut seen = HashSet::new();
+
+        loop {
+            if ballots.contains_key(current) {
+                return Some(current);
+            }
+            if !seen.insert(current) {
+                return None; // cycles are rejected on delegation; guard anyway
+            }
+            current = self.delegations.get(current)?.as_str();
+        }
+    }
+
+    /// Own power plus everything delegated to the voter that they cast
+    pub fn effective_voting_power(&self, proposal_id: &str, voter_id: &str) -> u64 {
+        let empty = HashMap::new();
+        let ballots = self.ballots.get(proposal_id).unwrap_or(&empty);
+
+        self.power_holders()
+            .into_iter()
+            .filter(|(account, _)| self.resolve_voter(ballots, account) == Some(voter_id))
+            .map(|(_, power)| power)
+            .sum()
+    }
+
+    fn power_holders(&self) -> HashMap<&str, u64> {
+        let mut holders: HashMap<&str, u64> = HashMap::new();
+        for allocation in self.token_distribution.values() {
+            *holders.entry(allocation.recipient_id.as_str()).or_insert(0) += allocation.amount;
+        }
+        for (account, power) in holders.iter_mut() {
+            *power = power.saturating_sub(self.slashed_from(account));
+        }
+        holders
+    }
+
+    fn tally_proposal(&mut self, proposal_id: &str) {
+        let (mut votes_for, mut votes_against) = (0, 0);
+
+        if let Some(ballots) = self.ballots.get(proposal_id) {
+            for (account, power) in self.power_holders() {
+                match self.resolve_voter(ballots, account).and_then(|voter| ballots.get(voter)) {
+                    Some(true) => votes_for += power,
+                    Some(false) => votes_against += power,
+                    None => {}
+                }
+            }
+        }
+
+        if let Some(proposal) = self.governance_proposals.get_mut(proposal_id) {
+            proposal.votes_for = votes_for;
+            proposal.votes_against = votes_against;
+        }
+    }
+
+    fn retally_open_proposals(&mut self) {
+        let open: Vec<String> = self.governance_proposals.values()
+            .filter(|proposal| matches!(proposal.status, ProposalStatus::Voting))
+            .map(|proposal| proposal.proposal_id.clone())
+            .collect();
+
+        for proposal_id in open {
+            self.tally_proposal(&proposal_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AllocationType, ContributedResources, ResourceProposal, TokenAllocation};
+
+    fn economy_with_holders(holders: &[(&str, u64)]) -> CommunityResourceEconomy {
+        let mut economy = CommunityResourceEconomy::new();
+        for (account, amount) in holders {
+            economy.token_distribution.insert(format!("alloc_{}", account), TokenAllocation {
+                recipient_id: account.to_string(),
+                allocation_type: AllocationType::UserReward,
+                amount: *amount,
+                vesting_schedule: None,
+                conditions: Vec::new(),
+                allocated_by: "test".to_string(),
+                allocated_at: 0,
+            });
+        }
+        economy.governance_proposals.insert("prop1".to_string(), ResourceProposal {
+            proposal_id: "prop1".to_string(),
+            proposer_id: "proposer".to_string(),
+            title: "Mirror".to_string(),
+            description: String::new(),
+            requested_tokens: 100,
+            requested_resources: ContributedResources {
+                cpu_cores: 0, memory_gb: 0, storage_gb: 0, bandwidth_mbps: 0, gpu_units: 0,
+                specialized_hardware: Vec::new(),
+            },
+            community_benefit: String::new(),
+            votes_for: 0,
+            votes_against: 0,
+            status: ProposalStatus::Voting,
+        });
+        economy
+    }
+
+    fn tally(economy: &CommunityResourceEconomy) -> (u64, u64) {
+        let proposal = &economy.governance_proposals["prop1"];
+        (proposal.votes_for, proposal.votes_against)
+    }
+
+    #[test]
+    fn test_delegates_cast_their_delegators_power() {
+        let mut economy = economy_with_holders(&[("alice", 10), ("bob", 20), ("carol", 30)]);
+        economy.delegate_votes("alice", "bob").unwrap();
+        economy.delegate_votes("bob", "carol").unwrap();
+
+        // Power follows the chain to the first account that voted
+        assert_eq!(economy.cast_vote("prop1", "carol", true).unwrap(), 60);
+        assert_eq!(tally(&economy), (60, 0));
+
+        // A direct vote overrides the delegation, and takes its own delegators along
+        assert_eq!(economy.cast_vote("prop1", "bob", false).unwrap(), 30);
+        assert_eq!(tally(&economy), (30, 30));
+        assert_eq!(economy.effective_voting_power("prop1", "carol"), 30);
+    }
+
+    #[test]
+    fn test_revoking_or_redelegating_retallies_open_proposals() {
+        let mut economy = economy_with_holders(&[("alice", 10), ("bob", 20), ("carol", 30)]);
+        economy.delegate_votes("alice", "bob").unwrap();
+        economy.cast_vote("prop1", "bob", true).unwrap();
+        economy.cast_vote("prop1", "carol", false).unwrap();
+        assert_eq!(tally(&economy), (30, 30));
+
+        economy.delegate_votes("alice", "carol").unwrap();
+        assert_eq!(tally(&economy), (20, 40));
+
+        economy.revoke_delegation("alice").unwrap();
+        assert_eq!(tally(&economy), (20, 30));
+        assert!(economy.revoke_delegation("alice").is_err());
+
+        let log: Vec<Option<&str>> = economy.delegation_log.iter()
+            .map(|record| record.delegate.as_deref())
+            .collect();
+        assert_eq!(log, vec![Some("bob"), Some("carol"), None]);
+    }
+
+    #[test]
+    fn test_delegation_cycles_are_rejected() {
+        let mut economy = economy_with_holders(&[("alice", 10), ("bob", 20), ("carol", 30)]);
+        assert!(economy.delegate_votes("alice", "alice").is_err());
+        economy.delegate_votes("alice", "bob").unwrap();
+        economy.delegate_votes("bob", "carol").unwrap();
+
+        assert!(economy.delegate_votes("carol", "alice").is_err());
+        assert!(!economy.delegations.contains_key("carol"));
+    }
+
+    #[test]
+    fn test_votes_close_with_the_proposal() {
+        let mut economy = economy_with_holders(&[("alice", 10), ("bob", 20)]);
+        economy.cast_vote("prop1", "alice", true).unwrap();
+        economy.cast_vote("prop1", "bob", false).unwrap();
+
+        assert!(matches!(economy.finalize_proposal("prop1").unwrap(), ProposalStatus::Rejected));
+        assert!(economy.cast_vote("prop1", "alice", true).is_err());
+        assert!(economy.cast_vote("prop2", "alice", true).is_err());
+
+        // Closed proposals keep their final tally
+        economy.delegate_votes("bob", "alice").unwrap();
+        assert_eq!(tally(&economy), (10, 20));
+    }
+}
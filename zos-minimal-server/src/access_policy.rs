@@ -0,0 +1,46 @@
+// Access policies for the node's own decisions; operators add theirs from
+// ZOS_POLICY_FILE on top of the built-in ones
+// AGPL-3.0 License
+
+use zos_policy::{Fixture, Op, Policy, PolicySet};
+
+/// Built-in: new ports only while under max_users, and a wallet already
+/// holding a port may always have it again
+pub fn builtin() -> PolicySet {
+    PolicySet::new(vec![
+        Policy::permit("allocate-within-capacity", &["port:allocate"]).when(
+            "context.allocated_ports",
+            Op::Lt,
+            serde_json::json!({"attr": "context.max_users"}),
+        ),
+        Policy::permit("keep-own-port", &["port:allocate"]).when(
+            "principal.allocated_port",
+            Op::Exists,
+            serde_json::Value::Null,
+        ),
+    ])
+}
+
+/// Operator policies from ZOS_POLICY_FILE, if set
+pub fn load_operator() -> Result<PolicySet, String> {
+    PolicySet::load(std::env::var("ZOS_POLICY_FILE").ok().as_deref())
+}
+
+/// The policies the node evaluates: built-in first, then the operator's
+pub fn effective(operator: &PolicySet) -> PolicySet {
+    builtin().with(operator)
+}
+
+/// Run policy fixture files against the node's effective policies; returns
+/// the failures across all of them
+pub fn run_fixtures(paths: &[String], operator: &PolicySet) -> Result<Vec<String>, String> {
+    let policies = effective(operator);
+    let mut failures = Vec::new();
+    for path in paths {
+        let json =
+            std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        let fixture = Fixture::from_json(&json).map_err(|e| format!("{}: {}", path, e))?;
+        failures.extend(fixture.run(&policies));
+    }
+    Ok(failures)
+}
@@ -0,0 +1,223 @@
+// Wallet activity feed: modules on this node, and the gateway, community
+// ledger and game servers through the ingest endpoint, publish events on one
+// bus; each wallet's feed is assembled from what reaches it
+// AGPL-3.0 License
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+const MAX_FEED: usize = 1000; // newest events kept per wallet
+pub const MAX_PAGE: usize = 200;
+pub const MAX_INGEST_BATCH: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeedCategory {
+    Payments,
+    Commissions,
+    Games,
+    Vouches,
+    Deployments,
+    Governance,
+}
+
+impl FeedCategory {
+    pub fn parse(name: &str) -> Option<Self> {
+        serde_json::from_value(serde_json::Value::String(name.trim().to_string())).ok()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivityEvent {
+    pub id: String, // orders by time: "{at:012}-{random}"
+    pub wallet: String,
+    pub category: FeedCategory,
+    pub kind: String, // payment_sent, commission_earned, vote_cast, ...
+    pub summary: String,
+    pub counterparty: Option<String>,
+    pub amount: Option<f64>,
+    pub token: Option<String>,
+    pub reference: Option<String>, // service key, proposal id, tx id, ...
+    pub source: String,            // module or node that published it
+    #[serde(default)]
+    pub source_id: Option<String>, // the publisher's own id; repeats are dropped
+    pub at: u64,
+}
+
+/// An event as published; the bus assigns its id
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewEvent {
+    pub wallet: String,
+    pub category: FeedCategory,
+    pub kind: String,
+    pub summary: String,
+    #[serde(default)]
+    pub counterparty: Option<String>,
+    #[serde(default)]
+    pub amount: Option<f64>,
+    #[serde(default)]
+    pub token: Option<String>,
+    #[serde(default)]
+    pub reference: Option<String>,
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub source_id: Option<String>,
+    #[serde(default)]
+    pub at: Option<u64>,
+}
+
+impl NewEvent {
+    pub fn new(wallet: &str, category: FeedCategory, kind: &str, summary: String) -> Self {
+        Self {
+            wallet: wallet.to_string(),
+            category,
+            kind: kind.to_string(),
+            summary,
+            counterparty: None,
+            amount: None,
+            token: None,
+            reference: None,
+            source: None,
+            source_id: None,
+            at: None,
+        }
+    }
+
+    pub fn counterparty(mut self, wallet: &str) -> Self {
+        self.counterparty = Some(wallet.to_string());
+        self
+    }
+
+    pub fn amount(mut self, amount: f64, token: &str) -> Self {
+        self.amount = Some(amount);
+        self.token = Some(token.to_string());
+        self
+    }
+
+    pub fn reference(mut self, reference: &str) -> Self {
+        self.reference = Some(reference.to_string());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct FeedQuery {
+    pub categories: Option<String>, // comma separated
+    pub before: Option<String>,     // the previous page's next_cursor
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FeedPage {
+    pub wallet: String,
+    pub events: Vec<ActivityEvent>,
+    pub next_cursor: Option<String>, // None on the last page
+}
+
+/// The bus every module publishes on; each event lands in its wallet's feed
+#[derive(Clone, Default)]
+pub struct ActivityBus {
+    pub feeds: Arc<RwLock<HashMap<String, VecDeque<ActivityEvent>>>>, // by wallet, oldest first
+}
+
+fn random_hex(bytes: usize) -> String {
+    (0..bytes)
+        .map(|_| format!("{:02x}", rand::random::<u8>()))
+        .collect()
+}
+
+impl ActivityBus {
+    /// Publish an event from `source`. None when the publisher already sent
+    /// one with the same source_id to this wallet.
+    pub async fn publish(&self, source: &str, event: NewEvent) -> Option<ActivityEvent> {
+        let at = event
+            .at
+            .unwrap_or_else(|| chrono::Utc::now().timestamp() as u64);
+        let source = event.source.unwrap_or_else(|| source.to_string());
+        let mut feeds = self.feeds.write().await;
+        let feed = feeds.entry(event.wallet.clone()).or_default();
+        if let Some(source_id) = &event.source_id {
+            let repeat = feed.iter().any(|earlier| {
+                earlier.source == source && earlier.source_id.as_ref() == Some(source_id)
+            });
+            if repeat {
+                return None;
+            }
+        }
+
+        let event = ActivityEvent {
+            id: format!("{:012}-{}", at, random_hex(6)),
+            wallet: event.wallet,
+            category: event.category,
+            kind: event.kind,
+            summary: event.summary,
+            counterparty: event.counterparty,
+            amount: event.amount,
+            token: event.token,
+            reference: event.reference,
+            source,
+            source_id: event.source_id,
+            at,
+        };
+        // Publishers may report late; keep the feed in time order
+        let position = feed.partition_point(|earlier| earlier.id <= event.id);
+        feed.insert(position, event.clone());
+        while feed.len() > MAX_FEED {
+            feed.pop_front();
+        }
+        Some(event)
+    }
+
+    /// Newest first, `limit` at a time (max 200), in the given categories only
+    pub async fn page(&self, wallet: &str, query: &FeedQuery) -> Result<FeedPage, String> {
+        let categories: Option<BTreeSet<FeedCategory>> = match &query.categories {
+            Some(names) => Some(
+                names
+                    .split(',')
+                    .filter(|name| !name.trim().is_empty())
+                    .map(|name| {
+                        FeedCategory::parse(name)
+                            .ok_or_else(|| format!("Unknown category: {}", name))
+                    })
+                    .collect::<Result<_, _>>()?,
+            ),
+            None => None,
+        };
+        let limit = query.limit.unwrap_or(50).clamp(1, MAX_PAGE);
+
+        let feeds = self.feeds.read().await;
+        let mut matching = feeds
+            .get(wallet)
+            .into_iter()
+            .flat_map(|feed| feed.iter().rev())
+            .filter(|event| {
+                query
+                    .before
+                    .as_ref()
+                    .is_none_or(|before| event.id < *before)
+            })
+            .filter(|event| {
+                categories
+                    .as_ref()
+                    .is_none_or(|categories| categories.contains(&event.category))
+            });
+        let events: Vec<ActivityEvent> = matching.by_ref().take(limit).cloned().collect();
+        let next_cursor = match matching.next() {
+            Some(_) => events.last().map(|event| event.id.clone()),
+            None => None,
+        };
+        Ok(FeedPage {
+            wallet: wallet.to_string(),
+            events,
+            next_cursor,
+        })
+    }
+
+    /// Drop a wallet's feed, for data deletion
+    pub async fn forget(&self, wallet: &str) -> bool {
+        self.feeds.write().await.remove(wallet).is_some()
+    }
+}
@@ -0,0 +1,405 @@
+// Alert rules over node metrics, delivered to Telegram, email and webhooks
+// AGPL-3.0 License
+
+use crate::service_health::HealthState;
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const ERROR_RATE_WINDOW_SECS: u64 = 300;
+const RESOLVED_RETENTION_SECS: u64 = 86400;
+const NODE_SUBJECT: &str = "node";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    ErrorRate,       // per service: share of 5xx over the last five minutes
+    DiskUsedPercent, // node: data directory usage
+    ServiceDown,     // per service: 1 while delisted by health probes
+    DdnsFailing,     // node: 1 while the domain does not resolve to our IP
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Comparison {
+    Above,
+    Below,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Channel {
+    Telegram { chat_id: String },
+    Email { to: String },
+    Webhook { url: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertRule {
+    pub id: String,
+    pub name: String,
+    pub metric: Metric,
+    pub subject: Option<String>, // service key to watch; None watches all
+    pub comparison: Comparison,
+    pub threshold: f64,
+    pub for_secs: u64,            // how long the condition must hold before firing
+    pub repeat_secs: Option<u64>, // re-notify while firing and unacknowledged
+    pub channels: Vec<Channel>,
+}
+
+impl AlertRule {
+    fn breached(&self, value: f64) -> bool {
+        match self.comparison {
+            Comparison::Above => value > self.threshold,
+            Comparison::Below => value < self.threshold,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertStatus {
+    Pending, // breached, waiting out for_secs
+    Firing,
+    Resolved,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Acknowledgement {
+    pub by: String,
+    pub note: Option<String>,
+    pub at: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Alert {
+    pub id: String, // "{rule_id}:{subject}"
+    pub rule_id: String,
+    pub rule_name: String,
+    pub subject: String,
+    pub value: f64,
+    pub status: AlertStatus,
+    pub since: u64,
+    pub fired_at: Option<u64>,
+    pub resolved_at: Option<u64>,
+    pub last_notified: Option<u64>,
+    pub acknowledged: Option<Acknowledgement>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Silence {
+    pub id: String,
+    pub rule_id: Option<String>, // None matches every rule
+    pub subject: Option<String>, // None matches every subject
+    pub ends_at: u64,
+    pub created_by: String,
+    pub reason: String,
+}
+
+impl Silence {
+    fn covers(&self, alert: &Alert, now: u64) -> bool {
+        now < self.ends_at
+            && self.rule_id.as_ref().is_none_or(|id| *id == alert.rule_id)
+            && self
+                .subject
+                .as_ref()
+                .is_none_or(|subject| *subject == alert.subject)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertEvent {
+    Firing,
+    Reminder,
+    Resolved,
+}
+
+pub struct Sample {
+    pub metric: Metric,
+    pub subject: String,
+    pub value: f64,
+}
+
+/// Read the metrics at least one rule watches
+pub async fn sample(state: &AppState, rules: &[AlertRule], now: u64) -> Vec<Sample> {
+    let watched = |metric: Metric| rules.iter().any(|rule| rule.metric == metric);
+    let mut samples = Vec::new();
+
+    if watched(Metric::ErrorRate) {
+        for (service_key, rate) in state
+            .service_logs
+            .error_rates(ERROR_RATE_WINDOW_SECS, now)
+            .await
+        {
+            samples.push(Sample {
+                metric: Metric::ErrorRate,
+                subject: service_key,
+                value: rate,
+            });
+        }
+    }
+
+    if watched(Metric::ServiceDown) {
+        for (service_key, health) in state.service_health.read().await.iter() {
+            samples.push(Sample {
+                metric: Metric::ServiceDown,
+                subject: service_key.clone(),
+                value: if health.state == HealthState::Delisted {
+                    1.0
+                } else {
+                    0.0
+                },
+            });
+        }
+    }
+
+    if watched(Metric::DiskUsedPercent) {
+        if let Some(used) = crate::doctor::disk_used_percent(&crate::migrations::state_dir()).await
+        {
+            samples.push(Sample {
+                metric: Metric::DiskUsedPercent,
+                subject: NODE_SUBJECT.to_string(),
+                value: used as f64,
+            });
+        }
+    }
+
+    if watched(Metric::DdnsFailing) {
+        let check = crate::doctor::check_ddns(&state.config.domain).await;
+        samples.push(Sample {
+            metric: Metric::DdnsFailing,
+            subject: NODE_SUBJECT.to_string(),
+            value: if check.status == crate::doctor::CheckStatus::Fail {
+                1.0
+            } else {
+                0.0
+            },
+        });
+    }
+
+    samples
+}
+
+/// Advance every alert with fresh samples. Returns the notifications due;
+/// silencing is applied by the caller.
+pub fn evaluate(
+    rules: &[AlertRule],
+    alerts: &mut HashMap<String, Alert>,
+    samples: &[Sample],
+    now: u64,
+) -> Vec<(Alert, AlertEvent)> {
+    let mut events = Vec::new();
+
+    for rule in rules {
+        let matching = samples.iter().filter(|sample| {
+            sample.metric == rule.metric
+                && rule
+                    .subject
+                    .as_ref()
+                    .is_none_or(|subject| *subject == sample.subject)
+        });
+
+        for sample in matching {
+            let id = format!("{}:{}", rule.id, sample.subject);
+            let breached = rule.breached(sample.value);
+
+            let active = alerts
+                .get(&id)
+                .is_some_and(|alert| alert.status != AlertStatus::Resolved);
+            if !active {
+                if !breached {
+                    continue;
+                }
+                alerts.insert(
+                    id.clone(),
+                    Alert {
+                        id: id.clone(),
+                        rule_id: rule.id.clone(),
+                        rule_name: rule.name.clone(),
+                        subject: sample.subject.clone(),
+                        value: sample.value,
+                        status: AlertStatus::Pending,
+                        since: now,
+                        fired_at: None,
+                        resolved_at: None,
+                        last_notified: None,
+                        acknowledged: None,
+                    },
+                );
+            }
+            let Some(alert) = alerts.get_mut(&id) else {
+                continue;
+            };
+            alert.value = sample.value;
+
+            if !breached {
+                if alert.status == AlertStatus::Firing {
+                    alert.status = AlertStatus::Resolved;
+                    alert.resolved_at = Some(now);
+                    events.push((alert.clone(), AlertEvent::Resolved));
+                } else {
+                    // Never fired, nothing to tell anyone
+                    alerts.remove(&id);
+                }
+                continue;
+            }
+
+            match alert.status {
+                AlertStatus::Pending if now >= alert.since + rule.for_secs => {
+                    alert.status = AlertStatus::Firing;
+                    alert.fired_at = Some(now);
+                    alert.last_notified = Some(now);
+                    events.push((alert.clone(), AlertEvent::Firing));
+                }
+                AlertStatus::Firing if alert.acknowledged.is_none() => {
+                    let reminder_due = rule
+                        .repeat_secs
+                        .is_some_and(|repeat| now >= alert.last_notified.unwrap_or(0) + repeat);
+                    if reminder_due {
+                        alert.last_notified = Some(now);
+                        events.push((alert.clone(), AlertEvent::Reminder));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Alerts whose rule was deleted resolve quietly; old resolved ones go
+    alerts.retain(|_, alert| {
+        rules.iter().any(|rule| rule.id == alert.rule_id)
+            && alert
+                .resolved_at
+                .is_none_or(|resolved| now < resolved + RESOLVED_RETENTION_SECS)
+    });
+
+    events
+}
+
+fn message(alert: &Alert, event: AlertEvent) -> String {
+    let icon = match event {
+        AlertEvent::Firing => "🚨",
+        AlertEvent::Reminder => "⏰",
+        AlertEvent::Resolved => "✅",
+    };
+    format!(
+        "{} [{:?}] {} on {} (value {:.2})",
+        icon, event, alert.rule_name, alert.subject, alert.value
+    )
+}
+
+async fn deliver(
+    channel: &Channel,
+    alert: &Alert,
+    event: AlertEvent,
+    domain: &str,
+) -> Result<(), String> {
+    let clients = crate::http_clients::shared();
+    let text = message(alert, event);
+
+    match channel {
+        Channel::Telegram { chat_id } => {
+            if crate::dev_mode::capture_telegram(chat_id, &text) {
+                return Ok(());
+            }
+            let token = crate::secrets::resolve("ZOS_TELEGRAM_BOT_TOKEN")
+                .ok_or("ZOS_TELEGRAM_BOT_TOKEN is not set")?;
+            let request = clients
+                .post(&format!(
+                    "https://api.telegram.org/bot{}/sendMessage",
+                    token
+                ))
+                .json(&serde_json::json!({ "chat_id": chat_id, "text": text }));
+            let response = clients
+                .send_with_retry(request)
+                .await
+                .map_err(|e| e.to_string())?;
+            if !response.status().is_success() {
+                return Err(format!("Telegram answered {}", response.status()));
+            }
+        }
+        Channel::Email { to } => {
+            use tokio::io::AsyncWriteExt;
+
+            let from = std::env::var("ZOS_ALERT_EMAIL_FROM")
+                .unwrap_or_else(|_| format!("zos-alerts@{}", domain));
+            let mail = format!(
+                "From: {}\nTo: {}\nSubject: {}\n\n{}\n",
+                from,
+                to,
+                text,
+                serde_json::to_string_pretty(alert).unwrap_or_default()
+            );
+
+            let mut sendmail = tokio::process::Command::new("sendmail")
+                .arg("-t")
+                .stdin(std::process::Stdio::piped())
+                .spawn()
+                .map_err(|e| format!("Failed to run sendmail: {}", e))?;
+            if let Some(mut stdin) = sendmail.stdin.take() {
+                stdin
+                    .write_all(mail.as_bytes())
+                    .await
+                    .map_err(|e| format!("Failed to write mail: {}", e))?;
+            }
+            let status = sendmail.wait().await.map_err(|e| e.to_string())?;
+            if !status.success() {
+                return Err(format!("sendmail exited with {}", status));
+            }
+        }
+        Channel::Webhook { url } => {
+            let request = clients.post(url).json(&serde_json::json!({
+                "event": event,
+                "message": text,
+                "alert": alert
+            }));
+            let response = clients
+                .send_with_retry(request)
+                .await
+                .map_err(|e| e.to_string())?;
+            if !response.status().is_success() {
+                return Err(format!("Webhook answered {}", response.status()));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Sample, evaluate and notify; run periodically by the supervisor
+pub async fn evaluate_due(state: AppState) -> Result<(), String> {
+    let now = chrono::Utc::now().timestamp() as u64;
+    let rules: Vec<AlertRule> = state.alert_rules.read().await.values().cloned().collect();
+
+    let samples = sample(&state, &rules, now).await;
+    let events = evaluate(&rules, &mut *state.alerts.write().await, &samples, now);
+
+    let silences = state.alert_silences.read().await.clone();
+    for (alert, event) in events {
+        if silences.values().any(|silence| silence.covers(&alert, now)) {
+            println!("🔕 Silenced: {}", message(&alert, event));
+            continue;
+        }
+        println!("{}", message(&alert, event));
+
+        let channels = rules
+            .iter()
+            .find(|rule| rule.id == alert.rule_id)
+            .map(|rule| rule.channels.clone())
+            .unwrap_or_default();
+        for channel in &channels {
+            if let Err(e) = deliver(channel, &alert, event, &state.config.domain).await {
+                println!("⚠️  Alert delivery to {:?} failed: {}", channel, e);
+            }
+        }
+    }
+
+    // Expired silences are of no further use
+    state
+        .alert_silences
+        .write()
+        .await
+        .retain(|_, silence| now < silence.ends_at);
+
+    Ok(())
+}
@@ -0,0 +1,168 @@
+// API versioning: /api/v{N} prefixes, version negotiation and a
+// compatibility shim for unversioned paths
+// AGPL-3.0 License
+
+use axum::body::Body;
+use axum::http::{header, HeaderMap, HeaderValue, Request, StatusCode, Uri};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+
+pub const SUPPORTED_VERSIONS: &[u32] = &[1];
+pub const CURRENT_VERSION: u32 = 1;
+
+/// Clients pin a version with this header or with
+/// `Accept: application/vnd.zos.v1+json`
+pub const VERSION_HEADER: &str = "x-zos-api-version";
+
+/// An unversioned path prefix still answered by the versioned handlers
+/// during its transition window
+pub struct Shim {
+    pub legacy: &'static str,
+    pub under: &'static str, // where the rest of the path lives below /api/v{N}/
+    pub deprecated_at: i64,  // unix seconds
+    pub sunset: i64,         // after this, un-negotiated calls get 410
+}
+
+pub const SHIMS: &[Shim] = &[
+    Shim {
+        legacy: "/api/",
+        under: "",
+        deprecated_at: 1792108800, // 2026-10-16
+        sunset: 1807833600,        // 2027-04-16
+    },
+    Shim {
+        legacy: "/traces/",
+        under: "traces/",
+        deprecated_at: 1792108800,
+        sunset: 1807833600,
+    },
+];
+
+/// `/api/v2/...` → Some(2)
+fn path_version(path: &str) -> Option<u32> {
+    let rest = path.strip_prefix("/api/v")?;
+    let digits = rest.split('/').next()?;
+    digits.parse().ok()
+}
+
+/// Version the client asked for, from the version header or the media type
+pub fn requested_version(headers: &HeaderMap) -> Option<u32> {
+    if let Some(value) = headers
+        .get(VERSION_HEADER)
+        .and_then(|value| value.to_str().ok())
+    {
+        return value.trim().trim_start_matches('v').parse().ok();
+    }
+    headers
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())?
+        .split(',')
+        .find_map(|media| {
+            media
+                .trim()
+                .strip_prefix("application/vnd.zos.v")?
+                .split(['+', ';'])
+                .next()?
+                .parse()
+                .ok()
+        })
+}
+
+pub fn http_date(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .unwrap_or_default()
+        .format("%a, %d %b %Y %H:%M:%S GMT")
+        .to_string()
+}
+
+fn unsupported(status: StatusCode, requested: u32) -> Response {
+    (
+        status,
+        Json(serde_json::json!({
+            "error": format!("API version {} is not supported", requested),
+            "supported_versions": SUPPORTED_VERSIONS,
+            "current_version": CURRENT_VERSION,
+        })),
+    )
+        .into_response()
+}
+
+fn rewrite(uri: &Uri, path: String) -> Option<Uri> {
+    let path_and_query = match uri.query() {
+        Some(query) => format!("{}?{}", path, query),
+        None => path,
+    };
+    path_and_query.parse().ok()
+}
+
+fn with_version(mut response: Response, version: u32) -> Response {
+    response
+        .headers_mut()
+        .insert(VERSION_HEADER, HeaderValue::from(version));
+    response
+}
+
+/// Runs before routing. Versioned paths pass through if the version
+/// exists; unversioned API paths are rewritten to the version the client
+/// negotiated, or to the current one with Deprecation, Sunset and a
+/// successor Link until the shim's sunset.
+pub async fn negotiate(mut request: Request<Body>, next: Next) -> Response {
+    let path = request.uri().path().to_string();
+
+    if let Some(version) = path_version(&path) {
+        if !SUPPORTED_VERSIONS.contains(&version) {
+            return unsupported(StatusCode::NOT_FOUND, version);
+        }
+        return with_version(next.run(request).await, version);
+    }
+
+    // Not part of the versioned API
+    let Some(shim) = SHIMS.iter().find(|shim| path.starts_with(shim.legacy)) else {
+        return next.run(request).await;
+    };
+
+    let requested = requested_version(request.headers());
+    if let Some(version) = requested.filter(|v| !SUPPORTED_VERSIONS.contains(v)) {
+        return unsupported(StatusCode::NOT_ACCEPTABLE, version);
+    }
+    let version = requested.unwrap_or(CURRENT_VERSION);
+    let successor = format!(
+        "/api/v{}/{}{}",
+        version,
+        shim.under,
+        &path[shim.legacy.len()..]
+    );
+
+    // Clients that negotiated a version are not using the retiring paths
+    let mut deprecation = HeaderMap::new();
+    if requested.is_none() {
+        let link = format!("<{}>; rel=\"successor-version\"", successor);
+        for (name, value) in [
+            ("deprecation", format!("@{}", shim.deprecated_at)),
+            ("sunset", http_date(shim.sunset)),
+            ("link", link),
+        ] {
+            if let Ok(value) = HeaderValue::from_str(&value) {
+                deprecation.insert(name, value);
+            }
+        }
+
+        if chrono::Utc::now().timestamp() >= shim.sunset {
+            let body = Json(serde_json::json!({
+                "error": "This unversioned endpoint has been retired",
+                "successor": successor,
+            }));
+            return (StatusCode::GONE, deprecation, body).into_response();
+        }
+    }
+
+    match rewrite(request.uri(), successor) {
+        Some(uri) => *request.uri_mut() = uri,
+        None => return StatusCode::BAD_REQUEST.into_response(),
+    }
+
+    let mut response = with_version(next.run(request).await, version);
+    response.headers_mut().extend(deprecation);
+    response
+}
@@ -0,0 +1,461 @@
+// Heavy analysis jobs (spectral, dependency) queued behind interactive
+// traffic, run niced and optionally in a cgroup, paused under pressure
+// AGPL-3.0 License
+
+use crate::AppState;
+use axum::body::Body;
+use axum::extract::State;
+use axum::http::Request;
+use axum::middleware::Next;
+use axum::response::Response;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Finished jobs kept for the dashboard
+pub const MAX_JOBS: usize = 100;
+
+/// Output lines kept per job
+pub const OUTPUT_TAIL_LINES: usize = 40;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    SpectralAnalysis,
+    DependencyAnalysis,
+}
+
+/// Both rank below interactive requests; `Low` jobs start before
+/// `Background` ones
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobPriority {
+    Low,
+    #[default]
+    Background,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Paused,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(self) -> bool {
+        matches!(self, Self::Completed | Self::Failed | Self::Cancelled)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct JobRequest {
+    pub kind: JobKind,
+    #[serde(default)]
+    pub priority: JobPriority,
+    pub target: Option<String>, // spectral: source file in the workspace; dependency: one crate
+    pub filter: Option<f64>,    // spectral frequency filter
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchJob {
+    pub id: String,
+    pub kind: JobKind,
+    pub priority: JobPriority,
+    pub command: Vec<String>, // before the nice/cgroup wrapper
+    pub status: JobStatus,
+    pub submitted_at: u64,
+    pub started_at: Option<u64>,
+    pub finished_at: Option<u64>,
+    pub pid: Option<u32>,
+    pub pause_reason: Option<String>,
+    pub pauses: u32,
+    #[serde(default)]
+    pub checkpoints: u32, // times requeued for a maintenance window
+    pub exit_code: Option<i32>,
+    pub output_tail: Vec<String>,
+    pub error: Option<String>,
+}
+
+/// How jobs are confined and when they yield
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobLimits {
+    pub nice: i32,
+    pub cgroup: bool, // run under `systemd-run --scope` with the quotas below
+    pub cpu_quota_percent: u32,
+    pub memory_max_mb: u64,
+    pub max_running: usize,
+    pub max_interactive_in_flight: usize, // busier than this, nothing new starts
+    pub pause_load_per_cpu: f64,
+    pub resume_load_per_cpu: f64,
+    pub pause_memory_available_percent: f64,
+    pub resume_memory_available_percent: f64,
+}
+
+impl JobLimits {
+    /// ZOS_JOB_NICE, ZOS_JOB_CGROUP=1, ZOS_JOB_CPU_QUOTA, ZOS_JOB_MEMORY_MB,
+    /// ZOS_JOB_PAUSE_LOAD and ZOS_JOB_PAUSE_MEM_PERCENT
+    pub fn load() -> Self {
+        let env_f64 = |name: &str, default: f64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+        let pause_load = env_f64("ZOS_JOB_PAUSE_LOAD", 1.5);
+        let pause_memory = env_f64("ZOS_JOB_PAUSE_MEM_PERCENT", 10.0);
+
+        Self {
+            nice: env_f64("ZOS_JOB_NICE", 19.0) as i32,
+            cgroup: std::env::var("ZOS_JOB_CGROUP").is_ok_and(|v| v == "1"),
+            cpu_quota_percent: env_f64("ZOS_JOB_CPU_QUOTA", 50.0) as u32,
+            memory_max_mb: env_f64("ZOS_JOB_MEMORY_MB", 1024.0) as u64,
+            max_running: 1,
+            max_interactive_in_flight: 4,
+            // Resume well below the pause point so jobs do not flap
+            pause_load_per_cpu: pause_load,
+            resume_load_per_cpu: pause_load * 2.0 / 3.0,
+            pause_memory_available_percent: pause_memory,
+            resume_memory_available_percent: pause_memory * 2.0,
+        }
+    }
+}
+
+/// Interactive requests being served right now, counted by `track_interactive`
+#[derive(Debug, Clone, Default)]
+pub struct InteractiveLoad(Arc<AtomicUsize>);
+
+impl InteractiveLoad {
+    pub fn in_flight(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+pub async fn track_interactive(
+    State(state): State<AppState>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    state.interactive.0.fetch_add(1, Ordering::Relaxed);
+    // Decrements even when the client goes away mid-request
+    let _guard = InFlight(state.interactive.clone());
+    next.run(request).await
+}
+
+struct InFlight(InteractiveLoad);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0 .0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Pressure {
+    pub load_per_cpu: f64,
+    pub memory_available_percent: f64,
+}
+
+/// 1-minute load average per CPU and MemAvailable/MemTotal, from /proc
+pub fn sample_pressure() -> Option<Pressure> {
+    let loadavg = std::fs::read_to_string("/proc/loadavg").ok()?;
+    let load: f64 = loadavg.split_whitespace().next()?.parse().ok()?;
+    let cpus = std::thread::available_parallelism().map_or(1, |n| n.get()) as f64;
+
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let field = |name: &str| -> Option<f64> {
+        meminfo
+            .lines()
+            .find(|line| line.starts_with(name))?
+            .split_whitespace()
+            .nth(1)?
+            .parse()
+            .ok()
+    };
+    let total = field("MemTotal:")?;
+    let available = field("MemAvailable:")?;
+
+    Some(Pressure {
+        load_per_cpu: load / cpus,
+        memory_available_percent: available / total * 100.0,
+    })
+}
+
+/// Why jobs should stop for now, if they should
+pub fn pause_reason(limits: &JobLimits, pressure: &Pressure) -> Option<String> {
+    if pressure.load_per_cpu >= limits.pause_load_per_cpu {
+        Some(format!("load {:.2} per CPU", pressure.load_per_cpu))
+    } else if pressure.memory_available_percent <= limits.pause_memory_available_percent {
+        Some(format!(
+            "{:.0}% memory available",
+            pressure.memory_available_percent
+        ))
+    } else {
+        None
+    }
+}
+
+pub fn can_resume(limits: &JobLimits, pressure: &Pressure) -> bool {
+    pressure.load_per_cpu <= limits.resume_load_per_cpu
+        && pressure.memory_available_percent >= limits.resume_memory_available_percent
+}
+
+/// The command a job runs. Spectral targets must be files inside the workspace.
+pub fn job_command(workspace: &Path, request: &JobRequest) -> Result<Vec<String>, String> {
+    match request.kind {
+        JobKind::SpectralAnalysis => {
+            let target = request
+                .target
+                .as_ref()
+                .ok_or("Spectral analysis needs a target file")?;
+            let root = workspace
+                .canonicalize()
+                .map_err(|e| format!("Workspace unavailable: {}", e))?;
+            let file = root
+                .join(target)
+                .canonicalize()
+                .map_err(|e| format!("{}: {}", target, e))?;
+            if !file.starts_with(&root) || !file.is_file() {
+                return Err(format!("{} is not a file in the workspace", target));
+            }
+
+            let analyzer = std::env::var("ZOS_SPECTRAL_ANALYZER")
+                .unwrap_or_else(|_| "zos-analysis".to_string());
+            let mut command = vec![analyzer];
+            if let Some(filter) = request.filter {
+                command.extend(["--filter".to_string(), filter.to_string()]);
+            }
+            command.push(file.display().to_string());
+            Ok(command)
+        }
+        JobKind::DependencyAnalysis => {
+            let mut command: Vec<String> = ["cargo", "tree", "--offline", "--prefix", "depth"]
+                .iter()
+                .map(|arg| arg.to_string())
+                .collect();
+            match &request.target {
+                Some(krate)
+                    if krate
+                        .chars()
+                        .all(|c| c.is_alphanumeric() || c == '-' || c == '_') =>
+                {
+                    command.extend(["-p".to_string(), krate.clone()]);
+                }
+                Some(krate) => return Err(format!("{} is not a crate name", krate)),
+                None => command.push("--workspace".to_string()),
+            }
+            Ok(command)
+        }
+    }
+}
+
+/// Wrap a command in nice and, when enabled, a systemd scope with CPU and
+/// memory quotas
+pub fn confined(limits: &JobLimits, command: &[String]) -> Vec<String> {
+    let mut wrapped = Vec::new();
+    if limits.cgroup {
+        wrapped.extend([
+            "systemd-run".to_string(),
+            "--user".to_string(),
+            "--scope".to_string(),
+            "--quiet".to_string(),
+            "-p".to_string(),
+            format!("CPUQuota={}%", limits.cpu_quota_percent),
+            "-p".to_string(),
+            format!("MemoryMax={}M", limits.memory_max_mb),
+            "--".to_string(),
+        ]);
+    }
+    wrapped.extend([
+        "nice".to_string(),
+        "-n".to_string(),
+        limits.nice.to_string(),
+    ]);
+    wrapped.extend(command.iter().cloned());
+    wrapped
+}
+
+/// Next job to start: lowest priority rank, then oldest
+pub fn next_queued(jobs: &HashMap<String, BatchJob>) -> Option<String> {
+    jobs.values()
+        .filter(|job| job.status == JobStatus::Queued)
+        .min_by_key(|job| (job.priority, job.submitted_at))
+        .map(|job| job.id.clone())
+}
+
+/// Drop the oldest finished jobs beyond MAX_JOBS
+pub fn trim_jobs(jobs: &mut HashMap<String, BatchJob>) {
+    let mut finished: Vec<(u64, String)> = jobs
+        .values()
+        .filter(|job| job.status.is_finished())
+        .map(|job| (job.submitted_at, job.id.clone()))
+        .collect();
+    finished.sort();
+    let excess = finished.len().saturating_sub(MAX_JOBS);
+    for (_, id) in finished.into_iter().take(excess) {
+        jobs.remove(&id);
+    }
+}
+
+/// Stop or continue the job's whole process group
+pub fn signal(pid: u32, sig: &str) -> Result<(), String> {
+    let status = std::process::Command::new("kill")
+        .args([&format!("-{}", sig), "--", &format!("-{}", pid)])
+        .status()
+        .map_err(|e| format!("Could not run kill: {}", e))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("kill -{} {} failed", sig, pid))
+    }
+}
+
+pub fn cancel(job: &mut BatchJob) -> Result<(), String> {
+    match job.status {
+        JobStatus::Queued => {}
+        JobStatus::Running | JobStatus::Paused => {
+            if let Some(pid) = job.pid {
+                signal(pid, "KILL")?;
+            }
+        }
+        _ => return Err("Job already finished".to_string()),
+    }
+    job.status = JobStatus::Cancelled;
+    job.finished_at = Some(chrono::Utc::now().timestamp() as u64);
+    Ok(())
+}
+
+/// Jobs that were running when the node stopped cannot be resumed
+pub fn mark_interrupted(jobs: &mut HashMap<String, BatchJob>, now: u64) {
+    for job in jobs.values_mut() {
+        if matches!(job.status, JobStatus::Running | JobStatus::Paused) {
+            job.status = JobStatus::Failed;
+            job.finished_at = Some(now);
+            job.error = Some("Interrupted by a node restart".to_string());
+        }
+    }
+}
+
+async fn run(state: AppState, id: String, command: Vec<String>, workspace: PathBuf) {
+    // Own process group, so pausing and cancelling reach its children too
+    let mut process = tokio::process::Command::new(&command[0]);
+    process
+        .args(&command[1..])
+        .current_dir(&workspace)
+        .process_group(0)
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped());
+    let result = match process.spawn() {
+        Ok(child) => {
+            if let Some(job) = state.batch_jobs.write().await.get_mut(&id) {
+                job.pid = child.id();
+            }
+            child.wait_with_output().await.map_err(|e| e.to_string())
+        }
+        Err(e) => Err(format!("Could not start {}: {}", command[0], e)),
+    };
+
+    let mut jobs = state.batch_jobs.write().await;
+    let Some(job) = jobs.get_mut(&id) else {
+        return;
+    };
+    // Requeued by a maintenance checkpoint; it will run again
+    if job.status == JobStatus::Queued {
+        return;
+    }
+    job.pid = None;
+    if job.status == JobStatus::Cancelled {
+        return;
+    }
+    job.finished_at = Some(chrono::Utc::now().timestamp() as u64);
+    match result {
+        Ok(output) => {
+            let text = format!(
+                "{}{}",
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            );
+            let lines: Vec<&str> = text.lines().collect();
+            job.output_tail = lines[lines.len().saturating_sub(OUTPUT_TAIL_LINES)..]
+                .iter()
+                .map(|line| line.to_string())
+                .collect();
+            job.exit_code = output.status.code();
+            job.status = if output.status.success() {
+                JobStatus::Completed
+            } else {
+                JobStatus::Failed
+            };
+        }
+        Err(e) => {
+            job.status = JobStatus::Failed;
+            job.error = Some(e);
+        }
+    }
+    println!("🧮 Job {} {:?}", id, job.status);
+}
+
+/// Pause running jobs under pressure, resume them once it eases, and start
+/// queued ones while interactive traffic is light
+pub async fn schedule(state: AppState) -> Result<(), String> {
+    let limits = &state.config.batch_jobs;
+    let Some(pressure) = sample_pressure() else {
+        return Err("Cannot read /proc load and memory".to_string());
+    };
+    let now = chrono::Utc::now().timestamp() as u64;
+    let pause = crate::maintenance::active_window(&*state.maintenance_windows.read().await, now)
+        .map(|window| format!("maintenance: {}", window.reason))
+        .or_else(|| pause_reason(limits, &pressure));
+
+    let mut jobs = state.batch_jobs.write().await;
+    for job in jobs.values_mut() {
+        match (job.status, &pause, job.pid) {
+            (JobStatus::Running, Some(reason), Some(pid)) => {
+                signal(pid, "STOP")?;
+                println!("⏸️  Job {} paused: {}", job.id, reason);
+                job.status = JobStatus::Paused;
+                job.pause_reason = Some(reason.clone());
+                job.pauses += 1;
+            }
+            (JobStatus::Paused, None, Some(pid)) if can_resume(limits, &pressure) => {
+                signal(pid, "CONT")?;
+                println!("▶️  Job {} resumed", job.id);
+                job.status = JobStatus::Running;
+                job.pause_reason = None;
+            }
+            _ => {}
+        }
+    }
+
+    let active = jobs
+        .values()
+        .filter(|job| matches!(job.status, JobStatus::Running | JobStatus::Paused))
+        .count();
+    let busy = state.interactive.in_flight() > limits.max_interactive_in_flight;
+    if pause.is_some() || busy || active >= limits.max_running {
+        return Ok(());
+    }
+    let Some(id) = next_queued(&jobs) else {
+        return Ok(());
+    };
+    let Some(job) = jobs.get_mut(&id) else {
+        return Ok(());
+    };
+    job.status = JobStatus::Running;
+    job.started_at = Some(chrono::Utc::now().timestamp() as u64);
+    let command = confined(limits, &job.command);
+    println!("🧮 Job {} started: {}", id, job.command.join(" "));
+    tokio::spawn(run(
+        state.clone(),
+        id,
+        command,
+        crate::build_orchestrator::workspace_dir(),
+    ));
+    Ok(())
+}
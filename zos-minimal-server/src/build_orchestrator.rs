@@ -0,0 +1,460 @@
+// Workspace builds: member crates built in dependency order, skipping the
+// ones whose sources have not changed since their last good build
+// AGPL-3.0 License
+
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Runs kept for the dashboard
+pub const MAX_RUNS: usize = 50;
+
+pub fn workspace_dir() -> PathBuf {
+    PathBuf::from(std::env::var("ZOS_WORKSPACE_DIR").unwrap_or_else(|_| ".".to_string()))
+}
+
+/// A workspace member and the members it depends on by path
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceCrate {
+    pub name: String,
+    pub dir: PathBuf,
+    pub deps: Vec<String>,
+}
+
+/// Members of the workspace at `dir`, from `cargo metadata`
+pub fn read_workspace(dir: &Path) -> Result<Vec<WorkspaceCrate>, String> {
+    let output = std::process::Command::new("cargo")
+        .args(["metadata", "--format-version", "1", "--no-deps"])
+        .current_dir(dir)
+        .output()
+        .map_err(|e| format!("Could not run cargo metadata: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "cargo metadata failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let metadata: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Invalid cargo metadata: {}", e))?;
+
+    let packages = metadata["packages"].as_array().cloned().unwrap_or_default();
+    let members: HashSet<&str> = packages
+        .iter()
+        .filter_map(|package| package["name"].as_str())
+        .collect();
+
+    Ok(packages
+        .iter()
+        .filter_map(|package| {
+            let manifest = PathBuf::from(package["manifest_path"].as_str()?);
+            let deps: BTreeSet<String> = package["dependencies"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter(|dep| dep["path"].is_string())
+                .filter_map(|dep| dep["name"].as_str())
+                .filter(|name| members.contains(name))
+                .map(str::to_string)
+                .collect();
+            Some(WorkspaceCrate {
+                name: package["name"].as_str()?.to_string(),
+                dir: manifest.parent()?.to_path_buf(),
+                deps: deps.into_iter().collect(),
+            })
+        })
+        .collect())
+}
+
+/// Dependencies before dependents, ties broken by name so runs are repeatable
+pub fn build_order(crates: &[WorkspaceCrate]) -> Result<Vec<String>, String> {
+    let mut waiting: BTreeMap<&str, BTreeSet<&str>> = crates
+        .iter()
+        .map(|krate| {
+            (
+                krate.name.as_str(),
+                krate.deps.iter().map(String::as_str).collect(),
+            )
+        })
+        .collect();
+
+    let mut order = Vec::new();
+    while !waiting.is_empty() {
+        let ready: Vec<&str> = waiting
+            .iter()
+            .filter(|(_, deps)| deps.is_empty())
+            .map(|(name, _)| *name)
+            .collect();
+        if ready.is_empty() {
+            let stuck: Vec<&str> = waiting.keys().copied().collect();
+            return Err(format!("Dependency cycle among {}", stuck.join(", ")));
+        }
+        for name in ready {
+            waiting.remove(name);
+            for deps in waiting.values_mut() {
+                deps.remove(name);
+            }
+            order.push(name.to_string());
+        }
+    }
+    Ok(order)
+}
+
+fn hash_dir(hasher: &mut Sha256, root: &Path, dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .collect();
+    paths.sort();
+    for path in paths {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        // Nested packages are fingerprinted on their own
+        let nested = path.is_dir() && path.join("Cargo.toml").exists();
+        if name.starts_with('.') || name == "target" || nested {
+            continue;
+        }
+        if path.is_dir() {
+            hash_dir(hasher, root, &path);
+        } else if let Ok(content) = std::fs::read(&path) {
+            hasher.update(
+                path.strip_prefix(root)
+                    .unwrap_or(&path)
+                    .to_string_lossy()
+                    .as_bytes(),
+            );
+            hasher.update(&content);
+        }
+    }
+}
+
+/// Hash of a crate's sources and manifest; `target`, dot directories and
+/// nested packages are skipped
+pub fn fingerprint(krate: &WorkspaceCrate) -> String {
+    let mut hasher = Sha256::new();
+    hash_dir(&mut hasher, &krate.dir, &krate.dir);
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Last good build of a crate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedBuild {
+    pub fingerprint: String,
+    pub profile: String,
+    pub built_at: u64,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BuildReason {
+    Changed,
+    DependencyChanged { dependency: String },
+    Forced,
+    Unchanged,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrateStatus {
+    Pending,
+    Building,
+    Built,
+    Cached,
+    Failed,
+    Skipped, // a dependency failed
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrateBuild {
+    pub name: String,
+    pub deps: Vec<String>,
+    pub fingerprint: String,
+    pub reason: BuildReason,
+    pub status: CrateStatus,
+    pub duration_ms: Option<u64>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct BuildRequest {
+    #[serde(default)]
+    pub release: bool,
+    #[serde(default)]
+    pub force: bool,
+    #[serde(default)]
+    pub crates: Vec<String>, // build only these and what they depend on; empty for all
+}
+
+impl BuildRequest {
+    pub fn profile(&self) -> &'static str {
+        if self.release {
+            "release"
+        } else {
+            "dev"
+        }
+    }
+}
+
+/// The crates a request builds, in order, each with why it is (not) rebuilt.
+/// Reads the workspace and hashes sources, so run it off the async runtime.
+pub fn plan(
+    workspace: &Path,
+    request: &BuildRequest,
+    cache: &HashMap<String, CachedBuild>,
+) -> Result<Vec<CrateBuild>, String> {
+    let crates = read_workspace(workspace)?;
+    let by_name: HashMap<&str, &WorkspaceCrate> = crates
+        .iter()
+        .map(|krate| (krate.name.as_str(), krate))
+        .collect();
+
+    let mut wanted: HashSet<&str> = HashSet::new();
+    let mut stack: Vec<&str> = if request.crates.is_empty() {
+        by_name.keys().copied().collect()
+    } else {
+        request.crates.iter().map(String::as_str).collect()
+    };
+    while let Some(name) = stack.pop() {
+        let krate = by_name
+            .get(name)
+            .ok_or_else(|| format!("{} is not a workspace member", name))?;
+        if wanted.insert(name) {
+            stack.extend(krate.deps.iter().map(String::as_str));
+        }
+    }
+
+    let mut rebuilt: HashSet<String> = HashSet::new();
+    let mut builds = Vec::new();
+    for name in build_order(&crates)? {
+        if !wanted.contains(name.as_str()) {
+            continue;
+        }
+        let krate = by_name[name.as_str()];
+        let fingerprint = fingerprint(krate);
+        let cached = cache
+            .get(&name)
+            .filter(|cached| cached.profile == request.profile());
+
+        let reason = if request.force {
+            BuildReason::Forced
+        } else if cached.is_none_or(|cached| cached.fingerprint != fingerprint) {
+            BuildReason::Changed
+        } else if let Some(dependency) = krate.deps.iter().find(|dep| rebuilt.contains(*dep)) {
+            BuildReason::DependencyChanged {
+                dependency: dependency.clone(),
+            }
+        } else {
+            BuildReason::Unchanged
+        };
+        if reason != BuildReason::Unchanged {
+            rebuilt.insert(name.clone());
+        }
+
+        builds.push(CrateBuild {
+            name,
+            deps: krate.deps.clone(),
+            fingerprint,
+            reason,
+            status: CrateStatus::Pending,
+            duration_ms: None,
+            error: None,
+        });
+    }
+    Ok(builds)
+}
+
+/// Build one planned crate, or say why it was not built
+pub async fn build_crate(
+    workspace: &Path,
+    build: &CrateBuild,
+    release: bool,
+    failed: &HashSet<String>,
+) -> (CrateStatus, Option<u64>, Option<String>) {
+    if let Some(dep) = build.deps.iter().find(|dep| failed.contains(*dep)) {
+        return (
+            CrateStatus::Skipped,
+            None,
+            Some(format!("dependency {} failed", dep)),
+        );
+    }
+    if build.reason == BuildReason::Unchanged {
+        return (CrateStatus::Cached, None, None);
+    }
+
+    let mut command = tokio::process::Command::new("cargo");
+    command
+        .args(["build", "-p", &build.name])
+        .current_dir(workspace);
+    if release {
+        command.arg("--release");
+    }
+    let started = Instant::now();
+    let output = command.output().await;
+    let duration_ms = started.elapsed().as_millis() as u64;
+
+    match output {
+        Ok(output) if output.status.success() => (CrateStatus::Built, Some(duration_ms), None),
+        Ok(output) => {
+            // The tail carries the errors; the head is progress noise
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let lines: Vec<&str> = stderr.lines().collect();
+            let tail = lines[lines.len().saturating_sub(20)..].join("\n");
+            (CrateStatus::Failed, Some(duration_ms), Some(tail))
+        }
+        Err(e) => (
+            CrateStatus::Failed,
+            None,
+            Some(format!("Could not run cargo: {}", e)),
+        ),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    Running,
+    Succeeded,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildRun {
+    pub id: String,
+    pub profile: String,
+    pub status: RunStatus,
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+    pub crates: Vec<CrateBuild>,
+}
+
+impl BuildRun {
+    pub fn duration_ms(&self) -> u64 {
+        self.crates.iter().filter_map(|c| c.duration_ms).sum()
+    }
+}
+
+/// Per-crate build times across finished runs, for the CI/CD dashboard
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CrateTimes {
+    pub builds: u64,
+    pub failures: u64,
+    pub last_ms: Option<u64>,
+    pub average_ms: u64,
+    pub max_ms: u64,
+}
+
+pub fn crate_times<'a>(runs: impl Iterator<Item = &'a BuildRun>) -> BTreeMap<String, CrateTimes> {
+    let mut runs: Vec<&BuildRun> = runs.collect();
+    runs.sort_by_key(|run| run.started_at);
+
+    let mut times: BTreeMap<String, CrateTimes> = BTreeMap::new();
+    let mut totals: HashMap<String, u64> = HashMap::new();
+    for build in runs.iter().flat_map(|run| &run.crates) {
+        let Some(duration_ms) = build.duration_ms else {
+            continue;
+        };
+        let entry = times.entry(build.name.clone()).or_default();
+        entry.builds += 1;
+        if build.status == CrateStatus::Failed {
+            entry.failures += 1;
+        }
+        entry.last_ms = Some(duration_ms);
+        entry.max_ms = entry.max_ms.max(duration_ms);
+        let total = totals.entry(build.name.clone()).or_default();
+        *total += duration_ms;
+        entry.average_ms = *total / entry.builds;
+    }
+    times
+}
+
+fn now() -> u64 {
+    chrono::Utc::now().timestamp() as u64
+}
+
+/// Carry out a planned run, updating it in place so the dashboard can
+/// follow along; good builds refresh the cache
+pub async fn execute(state: AppState, id: String, workspace: PathBuf, release: bool) {
+    let builds = match state.build_runs.read().await.get(&id) {
+        Some(run) => run.crates.clone(),
+        None => return,
+    };
+
+    let mut failed = HashSet::new();
+    for (index, build) in builds.iter().enumerate() {
+        if let Some(run) = state.build_runs.write().await.get_mut(&id) {
+            run.crates[index].status = CrateStatus::Building;
+        }
+        let (status, duration_ms, error) = build_crate(&workspace, build, release, &failed).await;
+        println!(
+            "🔨 {} {:?}{}",
+            build.name,
+            status,
+            duration_ms
+                .map(|ms| format!(" in {}ms", ms))
+                .unwrap_or_default()
+        );
+
+        match status {
+            CrateStatus::Failed | CrateStatus::Skipped => {
+                failed.insert(build.name.clone());
+            }
+            CrateStatus::Built => {
+                state.build_cache.write().await.insert(
+                    build.name.clone(),
+                    CachedBuild {
+                        fingerprint: build.fingerprint.clone(),
+                        profile: if release { "release" } else { "dev" }.to_string(),
+                        built_at: now(),
+                        duration_ms: duration_ms.unwrap_or_default(),
+                    },
+                );
+            }
+            _ => {}
+        }
+        if let Some(run) = state.build_runs.write().await.get_mut(&id) {
+            let entry = &mut run.crates[index];
+            entry.status = status;
+            entry.duration_ms = duration_ms;
+            entry.error = error;
+        }
+    }
+
+    if let Some(run) = state.build_runs.write().await.get_mut(&id) {
+        run.status = if failed.is_empty() {
+            RunStatus::Succeeded
+        } else {
+            RunStatus::Failed
+        };
+        run.finished_at = Some(now());
+        println!(
+            "🏗️  Build {} {:?}: {} crates, {}ms building",
+            run.id,
+            run.status,
+            run.crates.len(),
+            run.duration_ms()
+        );
+    }
+}
+
+/// Drop the oldest finished runs beyond `MAX_RUNS`
+pub fn trim_runs(runs: &mut HashMap<String, BuildRun>) {
+    let mut finished: Vec<(u64, String)> = runs
+        .values()
+        .filter(|run| run.status != RunStatus::Running)
+        .map(|run| (run.started_at, run.id.clone()))
+        .collect();
+    finished.sort();
+    let excess = runs.len().saturating_sub(MAX_RUNS);
+    for (_, id) in finished.into_iter().take(excess) {
+        runs.remove(&id);
+    }
+}
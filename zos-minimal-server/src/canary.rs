@@ -0,0 +1,385 @@
+// Canary releases: a share of a service's unpinned calls goes to a new
+// version while its request logs are compared with the current version's,
+// ending in a recommendation to promote or roll back that is acted on when
+// the owner allows it
+// AGPL-3.0 License
+
+use crate::activity_feed::{FeedCategory, NewEvent};
+use crate::service_health::OwnerNotification;
+use crate::service_logs::ServiceLogEntry;
+use crate::service_versions::ServiceVersions;
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+
+/// When a canary has seen enough, and how much worse it may do
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CanaryPolicy {
+    pub min_samples: usize,           // calls each side needs before a verdict
+    pub min_duration_secs: u64,       // and how long the canary runs at least
+    pub window_secs: u64,             // calls compared: the most recent this long
+    pub max_error_rate_increase: f64, // share of 5xx over the baseline's
+    pub significance: f64,            // Mann-Whitney p-value for "slower"
+    pub max_p95_ratio: f64,           // slower only counts past this p95 ratio
+}
+
+impl Default for CanaryPolicy {
+    fn default() -> Self {
+        Self {
+            min_samples: 100,
+            min_duration_secs: 600,
+            window_secs: 3600,
+            max_error_rate_increase: 0.01,
+            significance: 0.05,
+            max_p95_ratio: 1.2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Recommendation {
+    Promote,
+    Rollback,
+    Continue, // not enough evidence yet
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SampleStats {
+    pub requests: usize,
+    pub errors: usize,
+    pub error_rate: f64,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+}
+
+/// One-sided Mann-Whitney U test that the canary's latencies run higher
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencyTest {
+    pub u: f64, // pairs where the canary was slower, ties counting half
+    pub z: f64,
+    pub p_value: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CanaryAnalysis {
+    pub at: u64,
+    pub baseline: SampleStats,
+    pub canary: SampleStats,
+    pub latency: Option<LatencyTest>,
+    pub recommendation: Recommendation,
+    pub reasons: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Canary {
+    pub version: String,
+    pub baseline: String, // the current version when the canary started
+    pub weight_pct: u8,
+    pub auto_act: bool, // promote or roll back on the recommendation
+    pub policy: CanaryPolicy,
+    pub started_at: u64,
+    pub last_analysis: Option<CanaryAnalysis>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CanaryRequest {
+    pub version: String,
+    pub weight_pct: Option<u8>,
+    #[serde(default)]
+    pub auto_act: bool,
+    #[serde(default)]
+    pub policy: CanaryPolicy,
+}
+
+pub fn start(
+    versions: &mut ServiceVersions,
+    request: CanaryRequest,
+    now: u64,
+) -> Result<Canary, String> {
+    if let Some(running) = &versions.canary {
+        return Err(format!(
+            "Version {} is already in canary; promote or roll it back first",
+            running.version
+        ));
+    }
+    let baseline = versions
+        .current
+        .clone()
+        .ok_or("Service has no current version to compare with")?;
+    if baseline == request.version {
+        return Err("The current version cannot be its own canary".to_string());
+    }
+    let version = versions
+        .get(&request.version)
+        .ok_or_else(|| format!("Unknown version {}", request.version))?;
+    if version.deprecated_at.is_some() {
+        return Err("Cannot canary a deprecated version".to_string());
+    }
+    if version.port.is_none() {
+        return Err("A canary needs its own deployment; publish it with a port".to_string());
+    }
+    let weight_pct = request.weight_pct.unwrap_or(10);
+    if !(1..=50).contains(&weight_pct) {
+        return Err("weight_pct must be between 1 and 50".to_string());
+    }
+
+    let canary = Canary {
+        version: request.version,
+        baseline,
+        weight_pct,
+        auto_act: request.auto_act,
+        policy: request.policy,
+        started_at: now,
+        last_analysis: None,
+    };
+    versions.canary = Some(canary.clone());
+    Ok(canary)
+}
+
+/// Whether an unpinned call goes to the canary
+pub fn routes_to_canary(canary: &Canary) -> bool {
+    rand::random::<u8>() % 100 < canary.weight_pct
+}
+
+fn percentile(sorted: &[u64], pct: usize) -> u64 {
+    match (sorted.len() * pct).div_ceil(100).checked_sub(1) {
+        Some(index) => sorted[index],
+        None => 0,
+    }
+}
+
+fn stats(entries: &[&ServiceLogEntry]) -> SampleStats {
+    let mut latencies: Vec<u64> = entries.iter().map(|entry| entry.latency_ms).collect();
+    latencies.sort_unstable();
+    let errors = entries.iter().filter(|entry| entry.status >= 500).count();
+    SampleStats {
+        requests: entries.len(),
+        errors,
+        error_rate: errors as f64 / entries.len().max(1) as f64,
+        p50_ms: percentile(&latencies, 50),
+        p95_ms: percentile(&latencies, 95),
+    }
+}
+
+/// Standard normal upper tail, from the Abramowitz and Stegun 7.1.26
+/// approximation of erf (absolute error under 1.5e-7)
+fn upper_tail(z: f64) -> f64 {
+    let x = z.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let poly = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let erfc = poly * (-x * x).exp();
+    if z >= 0.0 {
+        erfc / 2.0
+    } else {
+        1.0 - erfc / 2.0
+    }
+}
+
+/// Normal approximation with tie correction; None when either side is empty
+/// or every latency is the same
+pub fn mann_whitney(baseline: &[u64], canary: &[u64]) -> Option<LatencyTest> {
+    let (n1, n2) = (canary.len() as f64, baseline.len() as f64);
+    if canary.is_empty() || baseline.is_empty() {
+        return None;
+    }
+    let mut pooled: Vec<(u64, bool)> = canary
+        .iter()
+        .map(|&latency| (latency, true))
+        .chain(baseline.iter().map(|&latency| (latency, false)))
+        .collect();
+    pooled.sort_unstable_by_key(|&(latency, _)| latency);
+
+    // Average ranks over ties
+    let (mut canary_ranks, mut ties) = (0.0, 0.0);
+    let mut start = 0;
+    while start < pooled.len() {
+        let end = start
+            + pooled[start..]
+                .iter()
+                .take_while(|(latency, _)| *latency == pooled[start].0)
+                .count();
+        let rank = (start + end + 1) as f64 / 2.0;
+        let tied = (end - start) as f64;
+        canary_ranks += rank * pooled[start..end].iter().filter(|(_, c)| *c).count() as f64;
+        ties += tied * tied * tied - tied;
+        start = end;
+    }
+
+    let n = n1 + n2;
+    let u = canary_ranks - n1 * (n1 + 1.0) / 2.0;
+    let variance = n1 * n2 / 12.0 * ((n + 1.0) - ties / (n * (n - 1.0)));
+    if variance <= 0.0 {
+        return None;
+    }
+    // Continuity correction toward the null
+    let z = (u - n1 * n2 / 2.0 - 0.5) / variance.sqrt();
+    Some(LatencyTest {
+        u,
+        z,
+        p_value: upper_tail(z),
+    })
+}
+
+/// Compare the canary's calls with the baseline's over the policy window
+pub fn analyze(canary: &Canary, entries: &[ServiceLogEntry], now: u64) -> CanaryAnalysis {
+    let policy = &canary.policy;
+    let since = canary
+        .started_at
+        .max(now.saturating_sub(policy.window_secs));
+    let served_by = |version: &str| -> Vec<&ServiceLogEntry> {
+        entries
+            .iter()
+            .filter(|entry| entry.at >= since && entry.version.as_deref() == Some(version))
+            .collect()
+    };
+    let (baseline_entries, canary_entries) =
+        (served_by(&canary.baseline), served_by(&canary.version));
+    let (baseline, canary_stats) = (stats(&baseline_entries), stats(&canary_entries));
+    let latencies = |entries: &[&ServiceLogEntry]| -> Vec<u64> {
+        entries.iter().map(|entry| entry.latency_ms).collect()
+    };
+    let latency = mann_whitney(&latencies(&baseline_entries), &latencies(&canary_entries));
+
+    let mut reasons = Vec::new();
+    let error_increase = canary_stats.error_rate - baseline.error_rate;
+    if error_increase > policy.max_error_rate_increase {
+        reasons.push(format!(
+            "Error rate {:.2}% against {:.2}% on {}",
+            canary_stats.error_rate * 100.0,
+            baseline.error_rate * 100.0,
+            canary.baseline
+        ));
+    }
+    let slower = latency
+        .as_ref()
+        .is_some_and(|test| test.p_value < policy.significance);
+    if slower && canary_stats.p95_ms as f64 > baseline.p95_ms.max(1) as f64 * policy.max_p95_ratio {
+        reasons.push(format!(
+            "Slower (p = {:.4}): p95 {}ms against {}ms",
+            latency.as_ref().map(|test| test.p_value).unwrap_or(1.0),
+            canary_stats.p95_ms,
+            baseline.p95_ms
+        ));
+    }
+
+    // A regression past its threshold rolls back without waiting for the
+    // baseline's sample or the minimum duration
+    let enough = canary_stats.requests >= policy.min_samples
+        && baseline.requests >= policy.min_samples
+        && now >= canary.started_at + policy.min_duration_secs;
+    let recommendation = if !reasons.is_empty() && canary_stats.requests >= policy.min_samples {
+        Recommendation::Rollback
+    } else if !enough {
+        reasons.push(format!(
+            "Waiting for {} calls on each side and {}s of running ({} canary, {} baseline so far)",
+            policy.min_samples, policy.min_duration_secs, canary_stats.requests, baseline.requests
+        ));
+        Recommendation::Continue
+    } else {
+        reasons.push("Error rate and latency within the policy".to_string());
+        Recommendation::Promote
+    };
+
+    CanaryAnalysis {
+        at: now,
+        baseline,
+        canary: canary_stats,
+        latency,
+        recommendation,
+        reasons,
+    }
+}
+
+/// End the canary: promoted, it becomes the current version; rolled back,
+/// its calls go to the baseline again. The owner hears about it either way.
+pub async fn conclude(
+    state: &AppState,
+    service_key: &str,
+    outcome: Recommendation,
+    why: &str,
+) -> Result<Canary, String> {
+    let now = chrono::Utc::now().timestamp() as u64;
+    let canary = {
+        let mut all = state.service_versions.write().await;
+        let versions = all
+            .get_mut(service_key)
+            .ok_or("Service publishes no versions")?;
+        let canary = versions.canary.take().ok_or("No canary is running")?;
+        match outcome {
+            Recommendation::Promote => versions.current = Some(canary.version.clone()),
+            Recommendation::Rollback => {}
+            Recommendation::Continue => {
+                versions.canary = Some(canary);
+                return Err("Nothing to conclude".to_string());
+            }
+        }
+        canary
+    };
+
+    let (verb, kind) = match outcome {
+        Recommendation::Promote => ("promoted", "canary_promoted"),
+        _ => ("rolled back", "canary_rolled_back"),
+    };
+    let message = format!("Canary {} {}: {}", canary.version, verb, why);
+    println!("🐤 {} {}", service_key, message);
+    if let Some(owner) = state
+        .services
+        .read()
+        .await
+        .get(service_key)
+        .map(|service| service.wallet_address.clone())
+    {
+        state
+            .owner_notifications
+            .write()
+            .await
+            .entry(owner.clone())
+            .or_default()
+            .push(OwnerNotification {
+                service_key: service_key.to_string(),
+                message: message.clone(),
+                timestamp: now,
+            });
+        let event =
+            NewEvent::new(&owner, FeedCategory::Deployments, kind, message).reference(service_key);
+        state.activity.publish("canary", event).await;
+    }
+    Ok(canary)
+}
+
+/// Re-run every canary's analysis, and act on it where the owner allowed
+pub async fn analyze_due(state: AppState) -> Result<(), String> {
+    let now = chrono::Utc::now().timestamp() as u64;
+    let running: Vec<(String, Canary)> = state
+        .service_versions
+        .read()
+        .await
+        .iter()
+        .filter_map(|(key, versions)| Some((key.clone(), versions.canary.clone()?)))
+        .collect();
+
+    for (service_key, canary) in running {
+        let since = now.saturating_sub(canary.policy.window_secs);
+        let entries = state.service_logs.since(&service_key, since).await;
+        let analysis = analyze(&canary, &entries, now);
+        let decision = analysis.recommendation;
+        let why = analysis.reasons.join("; ");
+        if let Some(running) = state
+            .service_versions
+            .write()
+            .await
+            .get_mut(&service_key)
+            .and_then(|versions| versions.canary.as_mut())
+            .filter(|running| running.version == canary.version)
+        {
+            running.last_analysis = Some(analysis);
+        }
+        if canary.auto_act && decision != Recommendation::Continue {
+            conclude(&state, &service_key, decision, &why).await?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,1763 @@
+// Route metadata registry and client SDK generation
+// AGPL-3.0 License
+
+use serde_json::json;
+
+/// Field, parameter and body types, as the generators spell them in Rust
+/// and TypeScript
+#[derive(Debug, Clone, Copy)]
+pub enum Ty {
+    Str,
+    U64,
+    F64,
+    Bool,
+    Json, // anything; serde_json::Value / unknown
+    StrMap,
+    Opt(&'static Ty),
+    Named(&'static str), // one of TYPES
+}
+
+/// The three API surfaces the clients cover
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Group {
+    ServiceCalls,
+    Payments,
+    Dashboard,
+}
+
+/// Payment routes are answered by the public gateway, everything else by a node
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Server {
+    Node,
+    Gateway,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Auth {
+    None,
+    Bearer(&'static str), // OIDC access token with this scope
+    PaymentToken,         // X-Payment-Token, when the service charges
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Reply {
+    Json,
+    Bytes,
+}
+
+pub struct TypeDef {
+    pub name: &'static str,
+    pub fields: &'static [(&'static str, Ty)],
+}
+
+pub struct RouteSpec {
+    pub name: &'static str, // snake_case; camelCased for TypeScript
+    pub group: Group,
+    pub server: Server,
+    pub method: &'static str, // "ANY" takes the method as an argument
+    pub path: &'static str,   // axum syntax, as registered: :param and *rest
+    pub summary: &'static str,
+    pub auth: Auth,
+    pub query: &'static [(&'static str, Ty)], // all optional
+    pub body: Option<Ty>,
+    pub reply: Reply,
+}
+
+/// Request bodies with a fixed shape. Bodies that are tagged enums on the
+/// server (identity links, alert rules) go out as plain JSON.
+pub const TYPES: &[TypeDef] = &[
+    TypeDef {
+        name: "AllocatePortRequest",
+        fields: &[("wallet", Ty::Str)],
+    },
+    TypeDef {
+        name: "FromTemplateRequest",
+        fields: &[
+            ("wallet", Ty::Str),
+            ("template", Ty::Str),
+            ("service_name", Ty::Opt(&Ty::Str)),
+            ("env", Ty::StrMap),
+        ],
+    },
+    TypeDef {
+        name: "PrewarmPolicy",
+        fields: &[
+            ("enabled", Ty::Bool),
+            ("lead_secs", Ty::U64),
+            ("min_predicted_requests", Ty::F64),
+            ("warm_cost_credits", Ty::U64),
+            ("daily_budget_credits", Ty::U64),
+            ("cooldown_secs", Ty::U64),
+        ],
+    },
+    TypeDef {
+        name: "AcknowledgeRequest",
+        fields: &[("by", Ty::Str), ("note", Ty::Opt(&Ty::Str))],
+    },
+    TypeDef {
+        name: "SilenceRequest",
+        fields: &[
+            ("rule_id", Ty::Opt(&Ty::Str)),
+            ("subject", Ty::Opt(&Ty::Str)),
+            ("duration_secs", Ty::U64),
+            ("reason", Ty::Str),
+        ],
+    },
+    TypeDef {
+        name: "PlanChoice",
+        fields: &[("plan_id", Ty::Str)],
+    },
+    TypeDef {
+        name: "QuoteRequest",
+        fields: &[
+            ("from_token", Ty::Str),
+            ("to_token", Ty::Str),
+            ("amount", Ty::F64),
+        ],
+    },
+    TypeDef {
+        name: "SwapRequest",
+        fields: &[
+            ("from_token", Ty::Str),
+            ("to_token", Ty::Str),
+            ("amount", Ty::F64),
+            ("slippage_tolerance", Ty::F64),
+        ],
+    },
+    TypeDef {
+        name: "EstimateRequest",
+        fields: &[
+            ("payload_mb", Ty::F64),
+            ("duration_secs", Ty::F64),
+            ("requests", Ty::U64),
+        ],
+    },
+];
+
+/// Every route a client may call. Keep in step with the router in
+/// serve_http and the gateway's create_gateway_routes.
+pub const ROUTES: &[RouteSpec] = &[
+    // Service calls
+    RouteSpec {
+        name: "call_service",
+        group: Group::ServiceCalls,
+        server: Server::Node,
+        method: "ANY",
+        path: "/:wallet/:service/*rest",
+        summary: "Call a hosted service; the body is passed through unchanged",
+        auth: Auth::PaymentToken,
+        query: &[],
+        body: None,
+        reply: Reply::Bytes,
+    },
+    RouteSpec {
+        name: "service_logs",
+        group: Group::ServiceCalls,
+        server: Server::Node,
+        method: "GET",
+        path: "/:wallet/:service/logs",
+        summary: "Recent request log entries for one of the caller's services",
+        auth: Auth::Bearer("services:logs"),
+        query: &[
+            ("status", Ty::Str),
+            ("since", Ty::U64),
+            ("until", Ty::U64),
+            ("min_latency_ms", Ty::U64),
+            ("consumer", Ty::Str),
+            ("limit", Ty::U64),
+        ],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "get_trace",
+        group: Group::ServiceCalls,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/traces/:trace_id",
+        summary: "Span tree of one request across gateway, libp2p, node and backend",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    // Payments
+    RouteSpec {
+        name: "quote",
+        group: Group::Payments,
+        server: Server::Gateway,
+        method: "POST",
+        path: "/:wallet/:service/quote",
+        summary: "Price a token swap without executing it",
+        auth: Auth::None,
+        query: &[],
+        body: Some(Ty::Named("QuoteRequest")),
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "swap",
+        group: Group::Payments,
+        server: Server::Gateway,
+        method: "POST",
+        path: "/:wallet/:service/swap",
+        summary: "Swap tokens through the service's pool",
+        auth: Auth::None,
+        query: &[],
+        body: Some(Ty::Named("SwapRequest")),
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "estimate",
+        group: Group::Payments,
+        server: Server::Gateway,
+        method: "POST",
+        path: "/:wallet/:service/estimate",
+        summary: "Signed price for a declared payload size, duration and request count",
+        auth: Auth::None,
+        query: &[],
+        body: Some(Ty::Named("EstimateRequest")),
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "get_contract",
+        group: Group::Payments,
+        server: Server::Gateway,
+        method: "GET",
+        path: "/:wallet/:service/contract",
+        summary: "Request and response JSON Schemas and their violation rates",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "receipts_public_key",
+        group: Group::Payments,
+        server: Server::Gateway,
+        method: "GET",
+        path: "/receipts/public-key",
+        summary: "Key the gateway signs usage receipts with",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "get_receipt",
+        group: Group::Payments,
+        server: Server::Gateway,
+        method: "GET",
+        path: "/receipts/:receipt_id",
+        summary: "A recent usage receipt, by the X-Receipt-Id of a paid response",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "verify_receipt",
+        group: Group::Payments,
+        server: Server::Gateway,
+        method: "POST",
+        path: "/receipts/verify",
+        summary: "Check a stored receipt against the gateway's key",
+        auth: Auth::None,
+        query: &[],
+        body: Some(Ty::Json),
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "list_rate_plans",
+        group: Group::Payments,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/services/:wallet/:service/plans",
+        summary: "Subscription plans a service offers",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "set_rate_plans",
+        group: Group::Payments,
+        server: Server::Node,
+        method: "PUT",
+        path: "/api/v1/services/:wallet/:service/plans",
+        summary: "Replace the plans of one of the caller's services, e.g. {\"plans\":[...]}",
+        auth: Auth::Bearer("subscriptions:manage"),
+        query: &[],
+        body: Some(Ty::Json),
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "subscribe",
+        group: Group::Payments,
+        server: Server::Node,
+        method: "POST",
+        path: "/api/v1/services/:wallet/:service/subscription",
+        summary: "Subscribe to a plan, paying the first period from credits",
+        auth: Auth::Bearer("subscriptions:manage"),
+        query: &[],
+        body: Some(Ty::Named("PlanChoice")),
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "change_subscription_plan",
+        group: Group::Payments,
+        server: Server::Node,
+        method: "PUT",
+        path: "/api/v1/services/:wallet/:service/subscription",
+        summary: "Switch plans, prorated over the rest of the period",
+        auth: Auth::Bearer("subscriptions:manage"),
+        query: &[],
+        body: Some(Ty::Named("PlanChoice")),
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "cancel_subscription",
+        group: Group::Payments,
+        server: Server::Node,
+        method: "DELETE",
+        path: "/api/v1/services/:wallet/:service/subscription",
+        summary: "Cancel at the end of the paid period",
+        auth: Auth::Bearer("subscriptions:manage"),
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "list_subscriptions",
+        group: Group::Payments,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/subscriptions",
+        summary: "The caller's subscriptions, usage and credits",
+        auth: Auth::Bearer("subscriptions:manage"),
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    // Dashboard
+    RouteSpec {
+        name: "health",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "GET",
+        path: "/health",
+        summary: "Node liveness",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "allocate_port",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "POST",
+        path: "/api/v1/allocate-port",
+        summary: "Allocate a service port for a wallet",
+        auth: Auth::None,
+        query: &[],
+        body: Some(Ty::Named("AllocatePortRequest")),
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "wallet_status",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/status/:wallet",
+        summary: "Session, credits and allocated port for a wallet",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "doctor_report",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/doctor",
+        summary: "Node self-checks",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "list_tasks",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/tasks",
+        summary: "Supervised background tasks and their restarts",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "list_services",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/services",
+        summary: "Services listed in the marketplace",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "list_service_templates",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/services/templates",
+        summary: "Templates a service can be created from",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "create_service_from_template",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "POST",
+        path: "/api/v1/services/from-template",
+        summary: "Create and register a service from a template",
+        auth: Auth::None,
+        query: &[],
+        body: Some(Ty::Named("FromTemplateRequest")),
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "wallet_service_health",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/services/:wallet/health",
+        summary: "Health of every service a wallet owns",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "prewarm_status",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/services/:wallet/:service/prewarm",
+        summary: "Pre-warm policy, forecast and spend for a service",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "wallet_dependency_graph",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/services/:wallet/dependencies",
+        summary: "A wallet's services and their dependencies with cascade status",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "service_dependencies",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/services/:wallet/:service/dependencies",
+        summary: "What a service depends on, what depends on it, and its cascade status",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "set_service_dependencies",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "PUT",
+        path: "/api/v1/services/:wallet/:service/dependencies",
+        summary: "Replace a service's dependencies, e.g. {\"depends_on\":[\"0xabc_gpu-pool\"]}",
+        auth: Auth::Bearer("services:manage"),
+        query: &[],
+        body: Some(Ty::Json),
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "set_prewarm_policy",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "PUT",
+        path: "/api/v1/services/:wallet/:service/prewarm",
+        summary: "Replace a service's pre-warm policy; the cost per pre-warm is the node's",
+        auth: Auth::Bearer("services:manage"),
+        query: &[],
+        body: Some(Ty::Named("PrewarmPolicy")),
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "list_builds",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/builds",
+        summary: "Recent workspace builds and per-crate build times",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "start_build",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "POST",
+        path: "/api/v1/builds",
+        summary: "Build changed workspace crates in dependency order",
+        auth: Auth::Bearer("node:admin"),
+        query: &[],
+        body: Some(Ty::Json),
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "get_build",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/builds/:id",
+        summary: "One build run with each crate's status, reason and time",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "list_jobs",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/jobs",
+        summary: "Analysis jobs, their limits and current node pressure",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "submit_job",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "POST",
+        path: "/api/v1/jobs",
+        summary: "Queue a spectral or dependency analysis job",
+        auth: Auth::Bearer("node:admin"),
+        query: &[],
+        body: Some(Ty::Json),
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "get_job",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/jobs/:id",
+        summary: "One analysis job with its status and output tail",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "cancel_job",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "DELETE",
+        path: "/api/v1/jobs/:id",
+        summary: "Cancel a queued, running or paused job",
+        auth: Auth::Bearer("node:admin"),
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "list_maintenance_windows",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/maintenance",
+        summary: "Active, next and scheduled maintenance windows",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "schedule_maintenance_window",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "POST",
+        path: "/api/v1/maintenance",
+        summary: "Schedule a maintenance window; other traffic gets 503 during it",
+        auth: Auth::Bearer("node:admin"),
+        query: &[],
+        body: Some(Ty::Json),
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "cancel_maintenance_window",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "DELETE",
+        path: "/api/v1/maintenance/:id",
+        summary: "Cancel a maintenance window or end it early",
+        auth: Auth::Bearer("node:admin"),
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "issue_resume_token",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "POST",
+        path: "/api/v1/sessions/resume-tokens",
+        summary: "A resume token for this device, e.g. {\"wallet\":\"...\",\"device\":\"phone\"}",
+        auth: Auth::None,
+        query: &[],
+        body: Some(Ty::Json),
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "save_session_snapshot",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "PUT",
+        path: "/api/v1/sessions/current",
+        summary:
+            "Store dashboard state, the open game session and pending payments (X-Resume-Token)",
+        auth: Auth::None,
+        query: &[],
+        body: Some(Ty::Json),
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "resume_session",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "POST",
+        path: "/api/v1/sessions/resume",
+        summary: "Restore a session after reconnecting (X-Resume-Token)",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "revoke_resume_token",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "DELETE",
+        path: "/api/v1/sessions/current",
+        summary: "Revoke this device's resume token (X-Resume-Token)",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "list_secrets",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/secrets",
+        summary: "Stored secret names and versions, and secrets still read from the environment",
+        auth: Auth::Bearer("secrets:admin"),
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "get_secret",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/secrets/:name",
+        summary: "Open a stored secret (node admins only)",
+        auth: Auth::Bearer("secrets:admin"),
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "put_secret",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "PUT",
+        path: "/api/v1/secrets/:name",
+        summary: "Store or replace a secret, e.g. {\"value\":\"...\"}",
+        auth: Auth::Bearer("secrets:admin"),
+        query: &[],
+        body: Some(Ty::Json),
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "delete_secret",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "DELETE",
+        path: "/api/v1/secrets/:name",
+        summary: "Delete a stored secret; its environment variable applies again",
+        auth: Auth::Bearer("secrets:admin"),
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "send_message",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "POST",
+        path: "/api/v1/messages",
+        summary: "Send an encrypted message to another linked wallet",
+        auth: Auth::Bearer("messages"),
+        query: &[],
+        body: Some(Ty::Json),
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "get_messaging_key",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/messages/:wallet/key",
+        summary: "The X25519 key to encrypt messages to a wallet",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "set_messaging_settings",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "PUT",
+        path: "/api/v1/messages/:wallet/settings",
+        summary: "Publish a messaging key and turn the Telegram relay on or off",
+        auth: Auth::Bearer("messages"),
+        query: &[],
+        body: Some(Ty::Json),
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "block_wallet",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "PUT",
+        path: "/api/v1/messages/:wallet/blocked/:other",
+        summary: "Refuse messages from a wallet",
+        auth: Auth::Bearer("messages"),
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "unblock_wallet",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "DELETE",
+        path: "/api/v1/messages/:wallet/blocked/:other",
+        summary: "Accept messages from a wallet again",
+        auth: Auth::Bearer("messages"),
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "get_inbox",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/messages/:wallet/inbox",
+        summary: "Unexpired messages to the wallet, newest first",
+        auth: Auth::Bearer("messages"),
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "mark_message_read",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "POST",
+        path: "/api/v1/messages/:wallet/inbox/:id/read",
+        summary: "Mark a message read",
+        auth: Auth::Bearer("messages"),
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "delete_message",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "DELETE",
+        path: "/api/v1/messages/:wallet/inbox/:id",
+        summary: "Delete a message from the inbox",
+        auth: Auth::Bearer("messages"),
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "list_service_versions",
+        group: Group::ServiceCalls,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/services/:wallet/:service/versions",
+        summary: "A service's versions and changelogs, newest first",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "publish_service_version",
+        group: Group::ServiceCalls,
+        server: Server::Node,
+        method: "POST",
+        path: "/api/v1/services/:wallet/:service/versions",
+        summary: "Publish a version with its changelog and, optionally, its own port",
+        auth: Auth::Bearer("services:versions"),
+        query: &[],
+        body: Some(Ty::Json),
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "deprecate_service_version",
+        group: Group::ServiceCalls,
+        server: Server::Node,
+        method: "POST",
+        path: "/api/v1/services/:wallet/:service/versions/:version/deprecate",
+        summary: "Deprecate a version and notify the consumers pinned to it",
+        auth: Auth::Bearer("services:versions"),
+        query: &[],
+        body: Some(Ty::Json),
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "get_version_pin",
+        group: Group::ServiceCalls,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/services/:wallet/:service/pin",
+        summary: "The version your calls to a service are pinned to",
+        auth: Auth::Bearer("services:versions"),
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "pin_service_version",
+        group: Group::ServiceCalls,
+        server: Server::Node,
+        method: "PUT",
+        path: "/api/v1/services/:wallet/:service/pin",
+        summary: "Pin your calls to a version, e.g. {\"version\":\"1.2.0\"}",
+        auth: Auth::Bearer("services:versions"),
+        query: &[],
+        body: Some(Ty::Json),
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "unpin_service_version",
+        group: Group::ServiceCalls,
+        server: Server::Node,
+        method: "DELETE",
+        path: "/api/v1/services/:wallet/:service/pin",
+        summary: "Go back to the service's current version",
+        auth: Auth::Bearer("services:versions"),
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "introspect",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/introspect",
+        summary: "Compiled modules and their health, feature flags, plugins and build provenance",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "get_onboarding",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/onboarding/:wallet",
+        summary: "Onboarding steps left for a wallet, with deep links and credit rewards",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "claim_onboarding_rewards",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "POST",
+        path: "/api/v1/onboarding/:wallet/claim",
+        summary: "Pay the credit rewards owed for onboarding steps done, each once",
+        auth: Auth::Bearer("onboarding"),
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "get_identity",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/identity/:wallet",
+        summary: "Accounts and endpoints linked to a wallet",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "link_identity",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "POST",
+        path: "/api/v1/identity/:wallet/link",
+        summary: "Link an account, e.g. {\"kind\":\"telegram\",\"telegram_id\":1,\"proof\":{\"telegram_login\":{...}}}",
+        auth: Auth::Bearer("identity:manage"),
+        query: &[],
+        body: Some(Ty::Json),
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "unlink_identity",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "DELETE",
+        path: "/api/v1/identity/:wallet/link/:kind",
+        summary: "Remove one kind of link from a wallet",
+        auth: Auth::Bearer("identity:manage"),
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "unix_link_challenge",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "POST",
+        path: "/api/v1/identity/:wallet/unix-challenge",
+        summary: "A nonce the Unix account writes to ~/.zos-link to prove it before linking",
+        auth: Auth::Bearer("identity:manage"),
+        query: &[],
+        body: Some(Ty::Json),
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "export_wallet_data",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/export/:wallet",
+        summary: "Gzipped archive of everything the node and its gateway hold about a wallet",
+        auth: Auth::Bearer("data:export"),
+        query: &[],
+        body: None,
+        reply: Reply::Bytes,
+    },
+    RouteSpec {
+        name: "request_deletion",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "POST",
+        path: "/api/v1/export/:wallet/delete",
+        summary: "Ask for a wallet's data to be deleted",
+        auth: Auth::Bearer("data:export"),
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "deletion_status",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/export/:wallet/delete",
+        summary: "Progress of a wallet's deletion request",
+        auth: Auth::Bearer("data:export"),
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "list_alerts",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/alerts",
+        summary: "Pending, firing and resolved alerts, newest first",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "list_alert_rules",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/alerts/rules",
+        summary: "Configured alert rules",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "create_alert_rule",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "POST",
+        path: "/api/v1/alerts/rules",
+        summary: "Add an alert rule",
+        auth: Auth::Bearer("node:admin"),
+        query: &[],
+        body: Some(Ty::Json),
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "delete_alert_rule",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "DELETE",
+        path: "/api/v1/alerts/rules/:id",
+        summary: "Remove an alert rule",
+        auth: Auth::Bearer("node:admin"),
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "acknowledge_alert",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "POST",
+        path: "/api/v1/alerts/:id/ack",
+        summary: "Stop reminders for a firing alert",
+        auth: Auth::Bearer("node:admin"),
+        query: &[],
+        body: Some(Ty::Named("AcknowledgeRequest")),
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "list_mirrors",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/mirrors",
+        summary: "Mirrored crates with their last drift and advisory report",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "set_mirror",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "PUT",
+        path: "/api/v1/mirrors/:name",
+        summary: "Register a mirrored crate or record the upstream version it is based on",
+        auth: Auth::Bearer("node:admin"),
+        query: &[],
+        body: Some(Ty::Json),
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "delete_mirror",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "DELETE",
+        path: "/api/v1/mirrors/:name",
+        summary: "Stop monitoring a mirrored crate",
+        auth: Auth::Bearer("node:admin"),
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "list_drift_work_items",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/mirrors/work-items",
+        summary: "Work items for mirrors behind upstream or exposed to advisories",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "list_silences",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "GET",
+        path: "/api/v1/alerts/silences",
+        summary: "Active alert silences",
+        auth: Auth::None,
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "create_silence",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "POST",
+        path: "/api/v1/alerts/silences",
+        summary: "Silence a rule or subject for a while",
+        auth: Auth::Bearer("node:admin"),
+        query: &[],
+        body: Some(Ty::Named("SilenceRequest")),
+        reply: Reply::Json,
+    },
+    RouteSpec {
+        name: "delete_silence",
+        group: Group::Dashboard,
+        server: Server::Node,
+        method: "DELETE",
+        path: "/api/v1/alerts/silences/:id",
+        summary: "End a silence early",
+        auth: Auth::Bearer("node:admin"),
+        query: &[],
+        body: None,
+        reply: Reply::Json,
+    },
+];
+
+impl Ty {
+    fn spec_name(&self) -> String {
+        match self {
+            Ty::Str => "string".to_string(),
+            Ty::U64 => "u64".to_string(),
+            Ty::F64 => "f64".to_string(),
+            Ty::Bool => "bool".to_string(),
+            Ty::Json => "json".to_string(),
+            Ty::StrMap => "map<string, string>".to_string(),
+            Ty::Opt(inner) => format!("{}?", inner.spec_name()),
+            Ty::Named(name) => name.to_string(),
+        }
+    }
+
+    fn rust(&self) -> String {
+        match self {
+            Ty::Str => "String".to_string(),
+            Ty::U64 => "u64".to_string(),
+            Ty::F64 => "f64".to_string(),
+            Ty::Bool => "bool".to_string(),
+            Ty::Json => "serde_json::Value".to_string(),
+            Ty::StrMap => "HashMap<String, String>".to_string(),
+            Ty::Opt(inner) => format!("Option<{}>", inner.rust()),
+            Ty::Named(name) => name.to_string(),
+        }
+    }
+
+    fn typescript(&self) -> String {
+        match self {
+            Ty::Str => "string".to_string(),
+            Ty::U64 | Ty::F64 => "number".to_string(),
+            Ty::Bool => "boolean".to_string(),
+            Ty::Json => "unknown".to_string(),
+            Ty::StrMap => "Record<string, string>".to_string(),
+            Ty::Opt(inner) => inner.typescript(),
+            Ty::Named(name) => name.to_string(),
+        }
+    }
+}
+
+impl Group {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Group::ServiceCalls => "service_calls",
+            Group::Payments => "payments",
+            Group::Dashboard => "dashboard",
+        }
+    }
+}
+
+enum Segment {
+    Literal(&'static str),
+    Param(&'static str),
+    Rest(&'static str),
+}
+
+fn segments(path: &'static str) -> Vec<Segment> {
+    path.trim_start_matches('/')
+        .split('/')
+        .map(|segment| {
+            if let Some(name) = segment.strip_prefix(':') {
+                Segment::Param(name)
+            } else if let Some(name) = segment.strip_prefix('*') {
+                Segment::Rest(name)
+            } else {
+                Segment::Literal(segment)
+            }
+        })
+        .collect()
+}
+
+fn path_params(path: &'static str) -> Vec<&'static str> {
+    segments(path)
+        .into_iter()
+        .filter_map(|segment| match segment {
+            Segment::Param(name) | Segment::Rest(name) => Some(name),
+            Segment::Literal(_) => None,
+        })
+        .collect()
+}
+
+fn camel_case(name: &str) -> String {
+    let mut out = String::new();
+    let mut upper = false;
+    for c in name.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            out.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Query parameters travel as one struct (Rust) or object (TypeScript)
+fn query_type_name(route: &RouteSpec) -> String {
+    let name = camel_case(route.name);
+    let mut chars = name.chars();
+    let first = chars.next().map(|c| c.to_uppercase().to_string());
+    format!("{}{}Query", first.unwrap_or_default(), chars.as_str())
+}
+
+/// The registry as JSON, served at /api/v1/sdk/spec for other generators
+pub fn spec() -> serde_json::Value {
+    let types: Vec<serde_json::Value> = TYPES
+        .iter()
+        .map(|def| {
+            let fields: serde_json::Map<String, serde_json::Value> = def
+                .fields
+                .iter()
+                .map(|(name, ty)| (name.to_string(), json!(ty.spec_name())))
+                .collect();
+            json!({ "name": def.name, "fields": fields })
+        })
+        .collect();
+
+    let routes: Vec<serde_json::Value> = ROUTES
+        .iter()
+        .map(|route| {
+            let query: serde_json::Map<String, serde_json::Value> = route
+                .query
+                .iter()
+                .map(|(name, ty)| (name.to_string(), json!(ty.spec_name())))
+                .collect();
+            json!({
+                "name": route.name,
+                "group": route.group.as_str(),
+                "server": match route.server {
+                    Server::Node => "node",
+                    Server::Gateway => "gateway",
+                },
+                "method": route.method,
+                "path": route.path,
+                "path_params": path_params(route.path),
+                "summary": route.summary,
+                "auth": match route.auth {
+                    Auth::None => json!(null),
+                    Auth::Bearer(scope) => json!({ "bearer": scope }),
+                    Auth::PaymentToken => json!({ "header": "X-Payment-Token" }),
+                },
+                "query": query,
+                "body": route.body.map(|ty| ty.spec_name()),
+                "reply": match route.reply {
+                    Reply::Json => "json",
+                    Reply::Bytes => "bytes",
+                },
+            })
+        })
+        .collect();
+
+    json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "types": types,
+        "routes": routes,
+    })
+}
+
+const RUST_PRELUDE: &str = r#"//! ZOS client, generated from the node's route registry (GET /api/v1/sdk/spec).
+//! Do not edit; regenerate with `zos-minimal-server generate-sdk`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug)]
+pub enum Error {
+    Http(reqwest::Error),
+    Json(serde_json::Error),
+    Status { status: u16, body: String },
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Http(e) => write!(f, "request failed: {}", e),
+            Error::Json(e) => write!(f, "invalid JSON: {}", e),
+            Error::Status { status, body } => write!(f, "ZOS returned {}: {}", status, body),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Http(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Json(e)
+    }
+}
+
+/// Percent-encode one path segment
+fn segment(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[derive(Clone)]
+pub struct Client {
+    node_url: String,
+    gateway_url: String,
+    http: reqwest::Client,
+    bearer: Option<String>,
+    payment_token: Option<String>,
+}
+
+impl Client {
+    /// Payment routes go to the same URL unless `with_gateway` says otherwise
+    pub fn new(node_url: &str) -> Self {
+        Self {
+            node_url: node_url.trim_end_matches('/').to_string(),
+            gateway_url: node_url.trim_end_matches('/').to_string(),
+            http: reqwest::Client::new(),
+            bearer: None,
+            payment_token: None,
+        }
+    }
+
+    pub fn with_gateway(mut self, gateway_url: &str) -> Self {
+        self.gateway_url = gateway_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_bearer(mut self, token: &str) -> Self {
+        self.bearer = Some(token.to_string());
+        self
+    }
+
+    pub fn with_payment_token(mut self, token: &str) -> Self {
+        self.payment_token = Some(token.to_string());
+        self
+    }
+
+    async fn send(
+        &self,
+        gateway: bool,
+        method: &str,
+        path: &str,
+        query: &[(&str, Option<String>)],
+        body: Option<(&str, Vec<u8>)>,
+    ) -> Result<reqwest::Response, Error> {
+        let base = if gateway { &self.gateway_url } else { &self.node_url };
+        let method = reqwest::Method::from_bytes(method.as_bytes()).unwrap_or(reqwest::Method::GET);
+        let query: Vec<(&str, String)> = query
+            .iter()
+            .filter_map(|(name, value)| value.clone().map(|value| (*name, value)))
+            .collect();
+
+        let mut request = self.http.request(method, format!("{}{}", base, path)).query(&query);
+        if let Some(token) = &self.bearer {
+            request = request.bearer_auth(token);
+        }
+        if let Some(token) = &self.payment_token {
+            request = request.header("X-Payment-Token", token);
+        }
+        if let Some((content_type, body)) = body {
+            request = request.header("Content-Type", content_type).body(body);
+        }
+
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(Error::Status {
+                status: response.status().as_u16(),
+                body: response.text().await.unwrap_or_default(),
+            });
+        }
+        Ok(response)
+    }
+"#;
+
+fn rust_struct(def: &TypeDef) -> String {
+    let mut out = format!(
+        "#[derive(Debug, Clone, Default, Serialize, Deserialize)]\npub struct {} {{\n",
+        def.name
+    );
+    for (name, ty) in def.fields {
+        if let Ty::Opt(_) = ty {
+            out.push_str("    #[serde(default, skip_serializing_if = \"Option::is_none\")]\n");
+        }
+        out.push_str(&format!("    pub {}: {},\n", name, ty.rust()));
+    }
+    out.push_str("}\n\n");
+    out
+}
+
+fn rust_query_struct(name: &str, fields: &[(&'static str, Ty)]) -> String {
+    let mut out = format!(
+        "#[derive(Debug, Clone, Default, Serialize, Deserialize)]\npub struct {} {{\n",
+        name
+    );
+    for (field, ty) in fields {
+        out.push_str(&format!("    pub {}: Option<{}>,\n", field, ty.rust()));
+    }
+    out.push_str("}\n\n");
+    out
+}
+
+fn rust_method(route: &RouteSpec) -> String {
+    let mut args = vec!["&self".to_string()];
+    let mut format_parts = Vec::new();
+    let mut format_args = Vec::new();
+    for segment in segments(route.path) {
+        match segment {
+            Segment::Literal(literal) => format_parts.push(literal.to_string()),
+            Segment::Param(name) => {
+                args.push(format!("{}: &str", name));
+                format_parts.push("{}".to_string());
+                format_args.push(format!("segment({})", name));
+            }
+            Segment::Rest(name) => {
+                args.push(format!("{}: &str", name));
+                format_parts.push("{}".to_string());
+                format_args.push(format!("{}.trim_start_matches('/')", name));
+            }
+        }
+    }
+
+    let method = if route.method == "ANY" {
+        args.push("method: &str".to_string());
+        "method".to_string()
+    } else {
+        format!("{:?}", route.method)
+    };
+
+    let body = match (route.method, route.body) {
+        ("ANY", _) => {
+            args.push("body: Option<Vec<u8>>".to_string());
+            "body.map(|body| (\"application/octet-stream\", body))".to_string()
+        }
+        (_, Some(ty)) => {
+            args.push(format!("body: &{}", ty.rust()));
+            "Some((\"application/json\", serde_json::to_vec(body)?))".to_string()
+        }
+        (_, None) => "None".to_string(),
+    };
+
+    if !route.query.is_empty() {
+        args.push(format!("query: &{}", query_type_name(route)));
+    }
+    let query: Vec<String> = route
+        .query
+        .iter()
+        .map(|(name, _)| {
+            format!(
+                "(\"{0}\", query.{0}.as_ref().map(|value| value.to_string()))",
+                name
+            )
+        })
+        .collect();
+
+    let (reply_ty, reply_expr) = match route.reply {
+        Reply::Json => ("serde_json::Value", "response.json().await?"),
+        Reply::Bytes => ("Vec<u8>", "response.bytes().await?.to_vec()"),
+    };
+
+    let path = if format_args.is_empty() {
+        format!("{:?}.to_string()", route.path)
+    } else {
+        format!(
+            "format!(\"/{}\", {})",
+            format_parts.join("/"),
+            format_args.join(", ")
+        )
+    };
+
+    format!(
+        "\n    /// {summary}\n    pub async fn {name}({args}) -> Result<{reply_ty}, Error> {{\n        \
+         let path = {path};\n        \
+         let response = self\n            \
+         .send({gateway}, {method}, &path, &[{query}], {body})\n            \
+         .await?;\n        \
+         Ok({reply_expr})\n    }}\n",
+        summary = route.summary,
+        name = route.name,
+        args = args.join(", "),
+        gateway = route.server == Server::Gateway,
+        query = query.join(", "),
+    )
+}
+
+/// src/lib.rs of the generated Rust crate
+pub fn rust_client() -> String {
+    let mut out = String::from(RUST_PRELUDE);
+    for route in ROUTES {
+        out.push_str(&rust_method(route));
+    }
+    out.push_str("}\n\n");
+    for def in TYPES {
+        out.push_str(&rust_struct(def));
+    }
+    for route in ROUTES.iter().filter(|route| !route.query.is_empty()) {
+        out.push_str(&rust_query_struct(&query_type_name(route), route.query));
+    }
+    out.truncate(out.trim_end().len());
+    out.push('\n');
+    out
+}
+
+/// Cargo.toml of the generated Rust crate
+pub fn rust_manifest() -> String {
+    format!(
+        "[package]\n\
+         name = \"zos-client\"\n\
+         version = \"{}\"\n\
+         edition = \"2021\"\n\
+         license = \"AGPL-3.0\"\n\
+         description = \"ZOS client generated from the node's route registry\"\n\n\
+         [dependencies]\n\
+         reqwest = {{ version = \"0.11\", features = [\"json\"] }}\n\
+         serde = {{ version = \"1.0\", features = [\"derive\"] }}\n\
+         serde_json = \"1.0\"\n",
+        env!("CARGO_PKG_VERSION")
+    )
+}
+
+const TYPESCRIPT_PRELUDE: &str = r#"// ZOS client, generated from the node's route registry (GET /api/v1/sdk/spec).
+// Do not edit; regenerate with `zos-minimal-server generate-sdk`.
+
+export class ZosError extends Error {
+  constructor(public status: number, public body: string) {
+    super(`ZOS returned ${status}: ${body}`);
+  }
+}
+
+export interface ZosClientOptions {
+  gatewayUrl?: string; // payment routes; defaults to the node URL
+  bearer?: string;
+  paymentToken?: string;
+  fetch?: typeof fetch;
+}
+
+export class ZosClient {
+  private nodeUrl: string;
+  private gatewayUrl: string;
+
+  constructor(nodeUrl: string, private options: ZosClientOptions = {}) {
+    this.nodeUrl = nodeUrl.replace(/\/+$/, "");
+    this.gatewayUrl = (options.gatewayUrl ?? nodeUrl).replace(/\/+$/, "");
+  }
+
+  private async send(
+    gateway: boolean,
+    method: string,
+    path: string,
+    query: object,
+    body?: { contentType: string; data: BodyInit },
+  ): Promise<Response> {
+    const params = new URLSearchParams();
+    for (const [name, value] of Object.entries(query)) {
+      if (value !== undefined && value !== null) params.append(name, String(value));
+    }
+    const search = params.toString();
+
+    const headers: Record<string, string> = {};
+    if (this.options.bearer) headers["Authorization"] = `Bearer ${this.options.bearer}`;
+    if (this.options.paymentToken) headers["X-Payment-Token"] = this.options.paymentToken;
+    if (body) headers["Content-Type"] = body.contentType;
+
+    const base = gateway ? this.gatewayUrl : this.nodeUrl;
+    const response = await (this.options.fetch ?? fetch)(`${base}${path}${search ? `?${search}` : ""}`, {
+      method,
+      headers,
+      body: body?.data,
+    });
+    if (!response.ok) throw new ZosError(response.status, await response.text());
+    return response;
+  }
+"#;
+
+fn typescript_interface(def: &TypeDef) -> String {
+    let mut out = format!("export interface {} {{\n", def.name);
+    for (name, ty) in def.fields {
+        let optional = matches!(ty, Ty::Opt(_));
+        out.push_str(&format!(
+            "  {}{}: {};\n",
+            name,
+            if optional { "?" } else { "" },
+            ty.typescript()
+        ));
+    }
+    out.push_str("}\n\n");
+    out
+}
+
+fn typescript_method(route: &RouteSpec) -> String {
+    let mut args = Vec::new();
+    let mut path = String::new();
+    for segment in segments(route.path) {
+        path.push('/');
+        match segment {
+            Segment::Literal(literal) => path.push_str(literal),
+            Segment::Param(name) => {
+                args.push(format!("{}: string", camel_case(name)));
+                path.push_str(&format!("${{encodeURIComponent({})}}", camel_case(name)));
+            }
+            Segment::Rest(name) => {
+                args.push(format!("{}: string", camel_case(name)));
+                path.push_str(&format!("${{{}.replace(/^\\/+/, \"\")}}", camel_case(name)));
+            }
+        }
+    }
+
+    let method = if route.method == "ANY" {
+        args.push("method: string".to_string());
+        "method".to_string()
+    } else {
+        format!("\"{}\"", route.method)
+    };
+
+    let body = match (route.method, route.body) {
+        ("ANY", _) => {
+            args.push("body?: BodyInit".to_string());
+            "body === undefined ? undefined : { contentType: \"application/octet-stream\", data: body }"
+                .to_string()
+        }
+        (_, Some(ty)) => {
+            args.push(format!("body: {}", ty.typescript()));
+            "{ contentType: \"application/json\", data: JSON.stringify(body) }".to_string()
+        }
+        (_, None) => "undefined".to_string(),
+    };
+
+    let query = if route.query.is_empty() {
+        "{}".to_string()
+    } else {
+        args.push(format!("query: {} = {{}}", query_type_name(route)));
+        "query".to_string()
+    };
+
+    let (reply_ty, reply_expr) = match route.reply {
+        Reply::Json => ("unknown", "response.json()"),
+        Reply::Bytes => ("ArrayBuffer", "response.arrayBuffer()"),
+    };
+
+    format!(
+        "\n  /** {summary} */\n  async {name}({args}): Promise<{reply_ty}> {{\n    \
+         const response = await this.send({gateway}, {method}, `{path}`, {query}, {body});\n    \
+         return {reply_expr};\n  }}\n",
+        summary = route.summary,
+        name = camel_case(route.name),
+        args = args.join(", "),
+        gateway = route.server == Server::Gateway,
+    )
+}
+
+/// zos-client.ts; needs nothing beyond fetch
+pub fn typescript_client() -> String {
+    let mut out = String::from(TYPESCRIPT_PRELUDE);
+    for route in ROUTES {
+        out.push_str(&typescript_method(route));
+    }
+    out.push_str("}\n\n");
+    for def in TYPES {
+        out.push_str(&typescript_interface(def));
+    }
+    for route in ROUTES.iter().filter(|route| !route.query.is_empty()) {
+        out.push_str(&format!("export interface {} {{\n", query_type_name(route)));
+        for (name, ty) in route.query {
+            out.push_str(&format!("  {}?: {};\n", name, ty.typescript()));
+        }
+        out.push_str("}\n\n");
+    }
+    out.truncate(out.trim_end().len());
+    out.push('\n');
+    out
+}
+
+/// The build task: `generate-sdk [dir]` writes rust/ and typescript/ under dir
+pub fn write_sdk(dir: &std::path::Path) -> std::io::Result<Vec<std::path::PathBuf>> {
+    let files = [
+        (dir.join("rust").join("Cargo.toml"), rust_manifest()),
+        (dir.join("rust").join("src").join("lib.rs"), rust_client()),
+        (
+            dir.join("typescript").join("zos-client.ts"),
+            typescript_client(),
+        ),
+        (
+            dir.join("spec.json"),
+            serde_json::to_string_pretty(&spec()).unwrap_or_default(),
+        ),
+    ];
+
+    let mut written = Vec::new();
+    for (path, contents) in files {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, contents)?;
+        written.push(path);
+    }
+    Ok(written)
+}
@@ -0,0 +1,210 @@
+// Wallet data export and deletion requests (GDPR-style)
+// AGPL-3.0 License
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// One module's records for a wallet, written as `sections/{name}.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportSection {
+    pub name: String,
+    pub records: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportManifest {
+    pub wallet_address: String,
+    pub generated_at: u64,
+    pub node_domain: String,
+    pub files: HashMap<String, String>, // file name -> sha256
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeletionRequest {
+    pub request_id: String,
+    pub pseudonym: String, // the wallet itself is not retained
+    pub requested_at: u64,
+    pub completed_at: Option<u64>,
+    pub status: DeletionStatus,
+    pub anonymized_records: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DeletionStatus {
+    Pending,
+    Completed,
+    Rejected,
+}
+
+/// Stable pseudonym that replaces a wallet in anonymized records.
+/// Ledger rows keep their amounts and ids so totals still reconcile.
+pub fn pseudonymize_wallet(wallet_address: &str) -> String {
+    let digest = Sha256::digest(wallet_address.as_bytes());
+    format!("anon_{}", hex_encode(&digest[..8]))
+}
+
+pub fn signing_key() -> String {
+    crate::secrets::resolve("ZOS_EXPORT_SIGNING_KEY")
+        .unwrap_or_else(|| "zos-dev-export-key".to_string())
+}
+
+pub fn sign_manifest(key: &str, manifest: &ExportManifest) -> Result<String, String> {
+    let mut unsigned = manifest.clone();
+    unsigned.signature = String::new();
+
+    let payload = serde_json::to_vec(&unsigned)
+        .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+
+    let mut mac = HmacSha256::new_from_slice(key.as_bytes())
+        .map_err(|e| format!("Invalid signing key: {}", e))?;
+    mac.update(&payload);
+
+    Ok(hex_encode(&mac.finalize().into_bytes()))
+}
+
+pub fn verify_manifest(key: &str, manifest: &ExportManifest) -> bool {
+    sign_manifest(key, manifest)
+        .map(|expected| expected == manifest.signature)
+        .unwrap_or(false)
+}
+
+/// Write every section plus a signed manifest into a tar.gz and return its bytes
+pub async fn build_export_archive(
+    wallet_address: &str,
+    node_domain: &str,
+    sections: Vec<ExportSection>,
+) -> Result<Vec<u8>, String> {
+    let pseudonym = pseudonymize_wallet(wallet_address);
+    let export_dir = format!("/tmp/zos-export-{}", pseudonym);
+    let archive_path = format!("{}.tar.gz", export_dir);
+
+    let _ = tokio::fs::remove_dir_all(&export_dir).await;
+    tokio::fs::create_dir_all(format!("{}/sections", export_dir))
+        .await
+        .map_err(|e| format!("Failed to create export dir: {}", e))?;
+
+    let mut files = HashMap::new();
+    for section in &sections {
+        let file_name = format!("sections/{}.json", section.name);
+        let contents = serde_json::to_vec_pretty(&section.records)
+            .map_err(|e| format!("Failed to serialize {}: {}", section.name, e))?;
+
+        files.insert(file_name.clone(), hex_encode(&Sha256::digest(&contents)));
+        tokio::fs::write(format!("{}/{}", export_dir, file_name), contents)
+            .await
+            .map_err(|e| format!("Failed to write {}: {}", file_name, e))?;
+    }
+
+    let mut manifest = ExportManifest {
+        wallet_address: wallet_address.to_string(),
+        generated_at: chrono::Utc::now().timestamp() as u64,
+        node_domain: node_domain.to_string(),
+        files,
+        signature: String::new(),
+    };
+    manifest.signature = sign_manifest(&signing_key(), &manifest)?;
+
+    let manifest_json = serde_json::to_vec_pretty(&manifest)
+        .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+    tokio::fs::write(format!("{}/manifest.json", export_dir), manifest_json)
+        .await
+        .map_err(|e| format!("Failed to write manifest: {}", e))?;
+
+    let tar_output = tokio::process::Command::new("tar")
+        .args(["-czf", &archive_path, "-C", &export_dir, "."])
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run tar: {}", e))?;
+
+    if !tar_output.status.success() {
+        return Err(format!(
+            "tar failed: {}",
+            String::from_utf8_lossy(&tar_output.stderr)
+        ));
+    }
+
+    let archive = tokio::fs::read(&archive_path)
+        .await
+        .map_err(|e| format!("Failed to read archive: {}", e))?;
+
+    let _ = tokio::fs::remove_dir_all(&export_dir).await;
+    let _ = tokio::fs::remove_file(&archive_path).await;
+
+    println!(
+        "📦 Data export built for {} ({} sections, {} bytes)",
+        pseudonym,
+        sections.len(),
+        archive.len()
+    );
+
+    Ok(archive)
+}
+
+/// The wallet's payments, earnings, referrals and keys on the gateway,
+/// fetched with the operator key since the node already checked the caller
+pub async fn fetch_gateway_records(
+    gateway_url: &str,
+    wallet_address: &str,
+) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    let operator_key =
+        crate::secrets::resolve("ZOS_OPERATOR_KEY").ok_or("ZOS_OPERATOR_KEY is not set")?;
+    let url = format!(
+        "{}/{}/export",
+        gateway_url.trim_end_matches('/'),
+        wallet_address
+    );
+    let response = crate::http_clients::shared()
+        .get(&url)
+        .header("X-Operator-Key", operator_key)
+        .send()
+        .await
+        .map_err(|e| format!("Gateway unreachable: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Gateway answered {}", response.status()));
+    }
+    match response.json().await {
+        Ok(serde_json::Value::Object(records)) => Ok(records),
+        Ok(_) => Err("Gateway export is not an object".to_string()),
+        Err(e) => Err(format!("Invalid gateway export: {}", e)),
+    }
+}
+
+/// Replace the wallet with its pseudonym in the gateway's ledger. Returns
+/// the number of records the gateway changed.
+pub async fn erase_gateway_records(
+    gateway_url: &str,
+    wallet_address: &str,
+    pseudonym: &str,
+) -> Result<usize, String> {
+    let operator_key =
+        crate::secrets::resolve("ZOS_OPERATOR_KEY").ok_or("ZOS_OPERATOR_KEY is not set")?;
+    let url = format!(
+        "{}/{}/erase",
+        gateway_url.trim_end_matches('/'),
+        wallet_address
+    );
+    let response = crate::http_clients::shared()
+        .post(&url)
+        .header("X-Operator-Key", operator_key)
+        .json(&serde_json::json!({ "pseudonym": pseudonym }))
+        .send()
+        .await
+        .map_err(|e| format!("Gateway unreachable: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Gateway answered {}", response.status()));
+    }
+    let body: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Invalid gateway answer: {}", e))?;
+    Ok(body["anonymized_records"].as_u64().unwrap_or(0) as usize)
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
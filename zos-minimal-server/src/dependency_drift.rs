@@ -0,0 +1,315 @@
+// Drift of mirrored/forked crates behind their upstream releases, and the
+// advisories the mirrored versions are still exposed to
+// AGPL-3.0 License
+
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A crate we build from a mirror or fork instead of crates.io
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MirroredCrate {
+    pub name: String,
+    #[serde(default)]
+    pub upstream: String, // crates.io name, when it differs from ours
+    pub mirror_url: String,
+    pub version: String, // upstream version the mirror is based on
+    #[serde(default = "default_check_interval")]
+    pub check_interval_secs: u64,
+    #[serde(default = "default_patch_tolerance")]
+    pub patch_tolerance: u64, // patch releases behind before a work item opens
+    #[serde(default)]
+    pub last_report: Option<DriftReport>,
+}
+
+fn default_check_interval() -> u64 {
+    86400
+}
+
+fn default_patch_tolerance() -> u64 {
+    3
+}
+
+impl MirroredCrate {
+    pub fn upstream_name(&self) -> &str {
+        if self.upstream.is_empty() {
+            &self.name
+        } else {
+            &self.upstream
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "behind", rename_all = "snake_case")]
+pub enum Drift {
+    Current,
+    Patch(u64),
+    Minor(u64),
+    Major(u64),
+}
+
+/// A RustSec advisory (as published through OSV) affecting the mirrored version
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Advisory {
+    pub id: String,
+    #[serde(default)]
+    pub aliases: Vec<String>, // CVE ids, when assigned
+    pub summary: String,
+    pub fixed_in: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DriftReport {
+    pub mirror_version: String,
+    pub upstream_version: String,
+    pub drift: Drift,
+    pub advisories: Vec<Advisory>,
+    pub checked_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DriftWorkItem {
+    pub id: String,
+    pub mirror: String,
+    pub reasons: Vec<String>,
+    pub opened_at: u64,
+    pub updated_at: u64,
+    pub resolved_at: Option<u64>,
+}
+
+/// `major.minor.patch`, ignoring pre-release and build metadata
+pub fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let core = version
+        .trim()
+        .trim_start_matches('v')
+        .split(['-', '+'])
+        .next()?;
+    let mut parts = core.split('.').map(|part| part.parse::<u64>().ok());
+    Some((
+        parts.next()??,
+        parts.next()??,
+        parts.next().unwrap_or(Some(0))?,
+    ))
+}
+
+/// How far `mirror` is behind `upstream`; a mirror ahead of upstream is current
+pub fn semver_drift(mirror: &str, upstream: &str) -> Option<Drift> {
+    let (m_major, m_minor, m_patch) = parse_version(mirror)?;
+    let (u_major, u_minor, u_patch) = parse_version(upstream)?;
+    Some(if u_major > m_major {
+        Drift::Major(u_major - m_major)
+    } else if u_major < m_major {
+        Drift::Current
+    } else if u_minor > m_minor {
+        Drift::Minor(u_minor - m_minor)
+    } else if u_minor < m_minor {
+        Drift::Current
+    } else if u_patch > m_patch {
+        Drift::Patch(u_patch - m_patch)
+    } else {
+        Drift::Current
+    })
+}
+
+/// Why a mirror needs attention; empty when it does not
+pub fn reasons(mirror: &MirroredCrate, report: &DriftReport) -> Vec<String> {
+    let mut reasons = Vec::new();
+    match report.drift {
+        Drift::Major(behind) => reasons.push(format!(
+            "{} major release(s) behind upstream {}",
+            behind, report.upstream_version
+        )),
+        Drift::Minor(behind) => reasons.push(format!(
+            "{} minor release(s) behind upstream {}",
+            behind, report.upstream_version
+        )),
+        Drift::Patch(behind) if behind > mirror.patch_tolerance => reasons.push(format!(
+            "{} patch release(s) behind upstream {}",
+            behind, report.upstream_version
+        )),
+        Drift::Patch(_) | Drift::Current => {}
+    }
+    for advisory in &report.advisories {
+        reasons.push(format!(
+            "{} {}: {}{}",
+            advisory.id,
+            advisory.aliases.join(" "),
+            advisory.summary,
+            advisory
+                .fixed_in
+                .as_ref()
+                .map(|fixed| format!(" (fixed in {})", fixed))
+                .unwrap_or_default()
+        ));
+    }
+    reasons
+}
+
+/// Open, update or resolve the mirror's work item; returns what changed
+pub fn reconcile(
+    items: &mut HashMap<String, DriftWorkItem>,
+    mirror: &str,
+    reasons: Vec<String>,
+    now: u64,
+) -> Option<String> {
+    let open = items
+        .values_mut()
+        .find(|item| item.mirror == mirror && item.resolved_at.is_none());
+    match (open, reasons.is_empty()) {
+        (Some(item), true) => {
+            item.resolved_at = Some(now);
+            item.updated_at = now;
+            Some(format!("resolved {}", item.id))
+        }
+        (Some(item), false) if item.reasons != reasons => {
+            item.reasons = reasons;
+            item.updated_at = now;
+            Some(format!("updated {}", item.id))
+        }
+        (Some(_), false) | (None, true) => None,
+        (None, false) => {
+            let id = format!("drift_{}_{}", mirror, now);
+            items.insert(
+                id.clone(),
+                DriftWorkItem {
+                    id: id.clone(),
+                    mirror: mirror.to_string(),
+                    reasons,
+                    opened_at: now,
+                    updated_at: now,
+                    resolved_at: None,
+                },
+            );
+            Some(format!("opened {}", id))
+        }
+    }
+}
+
+async fn latest_upstream(name: &str) -> Result<String, String> {
+    let clients = crate::http_clients::shared();
+    // crates.io refuses requests without a User-Agent
+    let request = clients
+        .get(&format!("https://crates.io/api/v1/crates/{}", name))
+        .header("User-Agent", "zos-minimal-server dependency drift monitor");
+    let response = clients
+        .send_with_retry(request)
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "crates.io answered {} for {}",
+            response.status(),
+            name
+        ));
+    }
+    let body: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
+    let krate = &body["crate"];
+    krate["max_stable_version"]
+        .as_str()
+        .or_else(|| krate["max_version"].as_str())
+        .map(str::to_string)
+        .ok_or_else(|| format!("crates.io has no version for {}", name))
+}
+
+/// RustSec advisories for the exact version, through the OSV API
+async fn advisories(name: &str, version: &str) -> Result<Vec<Advisory>, String> {
+    let clients = crate::http_clients::shared();
+    let request = clients
+        .post("https://api.osv.dev/v1/query")
+        .json(&serde_json::json!({
+            "package": { "name": name, "ecosystem": "crates.io" },
+            "version": version,
+        }));
+    let response = clients
+        .send_with_retry(request)
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("OSV answered {} for {}", response.status(), name));
+    }
+    let body: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
+
+    let vulns = body["vulns"].as_array().cloned().unwrap_or_default();
+    Ok(vulns
+        .iter()
+        .map(|vuln| Advisory {
+            id: vuln["id"].as_str().unwrap_or_default().to_string(),
+            aliases: vuln["aliases"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|alias| alias.as_str().map(str::to_string))
+                .collect(),
+            summary: vuln["summary"].as_str().unwrap_or_default().to_string(),
+            fixed_in: vuln["affected"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .flat_map(|affected| affected["ranges"].as_array().into_iter().flatten())
+                .flat_map(|range| range["events"].as_array().into_iter().flatten())
+                .find_map(|event| event["fixed"].as_str().map(str::to_string)),
+        })
+        .collect())
+}
+
+async fn check(mirror: &MirroredCrate, now: u64) -> Result<DriftReport, String> {
+    let upstream_version = latest_upstream(mirror.upstream_name()).await?;
+    let drift = semver_drift(&mirror.version, &upstream_version)
+        .ok_or_else(|| format!("{} is not a semver version", mirror.version))?;
+    let advisories = advisories(mirror.upstream_name(), &mirror.version).await?;
+    Ok(DriftReport {
+        mirror_version: mirror.version.clone(),
+        upstream_version,
+        drift,
+        advisories,
+        checked_at: now,
+    })
+}
+
+/// Check the mirrors whose interval has passed; one failing lookup does not
+/// hold up the rest
+pub async fn check_due(state: AppState) -> Result<(), String> {
+    let now = chrono::Utc::now().timestamp() as u64;
+    let due: Vec<MirroredCrate> = state
+        .mirrors
+        .read()
+        .await
+        .values()
+        .filter(|mirror| {
+            let last_checked = mirror.last_report.as_ref().map_or(0, |r| r.checked_at);
+            now >= last_checked + mirror.check_interval_secs
+        })
+        .cloned()
+        .collect();
+
+    let mut failures = Vec::new();
+    for mirror in due {
+        let report = match check(&mirror, now).await {
+            Ok(report) => report,
+            Err(e) => {
+                failures.push(format!("{}: {}", mirror.name, e));
+                continue;
+            }
+        };
+
+        let change = reconcile(
+            &mut *state.drift_work_items.write().await,
+            &mirror.name,
+            reasons(&mirror, &report),
+            now,
+        );
+        if let Some(change) = change {
+            println!("📦 Mirror {} drift work item {}", mirror.name, change);
+        }
+        if let Some(entry) = state.mirrors.write().await.get_mut(&mirror.name) {
+            entry.last_report = Some(report);
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(failures.join("; "))
+    }
+}
@@ -0,0 +1,640 @@
+// Credential-less local development: deterministic test wallets, state kept
+// in memory, Telegram messages and cloud provisioning captured instead of
+// sent, and demo services seeded on every start
+// AGPL-3.0 License
+
+use crate::activity_feed::{FeedCategory, NewEvent};
+use crate::identity::IdentityLink;
+use crate::messaging::MessagingSettings;
+use crate::service_templates::{ProbeKind, RegisteredHealthCheck, RegisteredService};
+use crate::{AppState, UserSession};
+use axum::{
+    extract::State,
+    http::{Request, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ed25519_dalek::{Signer, SigningKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+
+/// Test wallets, in the order their keys are derived
+pub const WALLET_NAMES: &[&str] = &["alice", "bob", "carol"];
+
+/// Captured Telegram messages kept for inspection
+const TELEGRAM_KEPT: usize = 500;
+
+/// Credits each test wallet starts with
+const STARTING_CREDITS: u64 = 1_000;
+
+static DEV: OnceLock<DevEnvironment> = OnceLock::new();
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DevWallet {
+    pub name: String,
+    pub address: String,    // base58 ed25519 public key, as Solana wallets have
+    pub secret_key: String, // base58 seed; test funds only, never reuse
+    #[serde(skip)]
+    seed: [u8; 32],
+}
+
+impl DevWallet {
+    fn derive(name: &str) -> Self {
+        let seed = seed_for(&format!("wallet:{}", name));
+        let verifying_key = SigningKey::from_bytes(&seed).verifying_key();
+        Self {
+            name: name.to_string(),
+            address: bs58::encode(verifying_key.as_bytes()).into_string(),
+            secret_key: bs58::encode(seed).into_string(),
+            seed,
+        }
+    }
+
+    /// Base58 signature over `message`, as a wallet extension would give it
+    pub fn sign(&self, message: &str) -> String {
+        let signature = SigningKey::from_bytes(&self.seed).sign(message.as_bytes());
+        bs58::encode(signature.to_bytes()).into_string()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CapturedTelegram {
+    pub chat_id: String,
+    pub text: String,
+    pub at: u64,
+}
+
+/// A call that would have provisioned cloud instances or changed the host,
+/// answered by the mock cloud instead
+#[derive(Debug, Clone, Serialize)]
+pub struct MockOperation {
+    pub id: String,
+    pub method: String,
+    pub path: String,
+    pub kind: &'static str, // "launch_instance" | "host_script"
+    pub instance: Option<MockInstance>,
+    pub at: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MockInstance {
+    pub id: String,
+    pub display_name: String,
+    pub shape: String,
+    pub region: String,
+    pub lifecycle_state: String,
+}
+
+#[derive(Debug)]
+pub struct DevEnvironment {
+    pub port: u16,
+    pub data_dir: String,
+    pub wallets: Vec<DevWallet>,
+    telegram: Mutex<VecDeque<CapturedTelegram>>,
+    operations: Mutex<Vec<MockOperation>>,
+}
+
+impl DevEnvironment {
+    pub fn wallet(&self, name_or_address: &str) -> Option<&DevWallet> {
+        self.wallets
+            .iter()
+            .find(|wallet| wallet.name == name_or_address || wallet.address == name_or_address)
+    }
+
+    pub fn telegram(&self) -> Vec<CapturedTelegram> {
+        self.telegram
+            .lock()
+            .map(|sent| sent.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn operations(&self) -> Vec<MockOperation> {
+        self.operations
+            .lock()
+            .map(|operations| operations.clone())
+            .unwrap_or_default()
+    }
+}
+
+fn seed_for(label: &str) -> [u8; 32] {
+    Sha256::digest(format!("zos-dev:{}", label).as_bytes()).into()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn now() -> u64 {
+    chrono::Utc::now().timestamp() as u64
+}
+
+pub fn active() -> Option<&'static DevEnvironment> {
+    DEV.get()
+}
+
+/// Point every store, key and credential at a fresh scratch directory and
+/// fixed test values; call before anything reads the environment
+pub fn init(port: u16) -> Result<&'static DevEnvironment, String> {
+    let data_dir = std::env::temp_dir()
+        .join(format!("zos-dev-{}", port))
+        .display()
+        .to_string();
+    // Every start is the same seed; nothing from a previous run carries over
+    match std::fs::remove_dir_all(&data_dir) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(format!("Cannot clear {}: {}", data_dir, e)),
+    }
+    std::fs::create_dir_all(&data_dir).map_err(|e| format!("Cannot create {}: {}", data_dir, e))?;
+
+    let wallets: Vec<DevWallet> = WALLET_NAMES
+        .iter()
+        .map(|name| DevWallet::derive(name))
+        .collect();
+
+    let master_key_file = format!("{}/secrets_master_key", data_dir);
+    std::fs::write(&master_key_file, hex(&seed_for("secrets-master-key")))
+        .map_err(|e| format!("Cannot write {}: {}", master_key_file, e))?;
+
+    let env = [
+        ("ZOS_DATA_DIR", data_dir.clone()),
+        ("ZOS_DOMAIN", "localhost".to_string()),
+        (
+            "ZOS_PORT_AUTHORITY",
+            format!("{}/port-authority.json", data_dir),
+        ),
+        ("ZOS_SECRETS_MASTER_KEY_FILE", master_key_file),
+        ("ZOS_ADMIN_WALLETS", wallets[0].address.clone()),
+        ("ZOS_OIDC_SIGNING_KEY", hex(&seed_for("oidc-signing-key"))),
+        ("ZOS_EXPORT_SIGNING_KEY", "zos-dev-export-key".to_string()),
+        ("ZOS_FEED_INGEST_KEY", "zos-dev-feed-key".to_string()),
+        ("ZOS_TELEGRAM_BOT_TOKEN", "zos-dev-telegram".to_string()), // never leaves the node
+        ("ZOS_SECURITY_PROFILE", "dev".to_string()),
+    ];
+    for (name, value) in env {
+        std::env::set_var(name, value);
+    }
+    // Nothing in dev mode reaches out to real key servers or peers
+    for name in [
+        "ZOS_SECRETS_KMS_URL",
+        "ZOS_SECRETS_KMS_TOKEN",
+        "ZOS_SOURCE_PEERS",
+        "ZOS_INSTANCE_NAME",
+    ] {
+        std::env::remove_var(name);
+    }
+
+    let environment = DevEnvironment {
+        port,
+        data_dir,
+        wallets,
+        telegram: Mutex::new(VecDeque::new()),
+        operations: Mutex::new(Vec::new()),
+    };
+    DEV.set(environment)
+        .map_err(|_| "Dev mode is already initialized".to_string())?;
+    Ok(DEV.get().expect("just set"))
+}
+
+/// Keep a Telegram message for /api/v1/dev/telegram instead of sending it;
+/// false outside dev mode, where the caller sends it for real
+pub fn capture_telegram(chat_id: &str, text: &str) -> bool {
+    let Some(dev) = active() else {
+        return false;
+    };
+    println!("📨 [dev] Telegram to {}: {}", chat_id, text);
+    if let Ok(mut sent) = dev.telegram.lock() {
+        sent.push_back(CapturedTelegram {
+            chat_id: chat_id.to_string(),
+            text: text.to_string(),
+            at: now(),
+        });
+        while sent.len() > TELEGRAM_KEPT {
+            sent.pop_front();
+        }
+    }
+    true
+}
+
+/// Routes that launch instances or run scripts against the host
+fn provisioning_kind(path: &str) -> Option<&'static str> {
+    match path {
+        "/deploy" | "/deploy/dev-to-staging" | "/deploy/staging-to-prod" | "/bootstrap/prod" => {
+            Some("launch_instance")
+        }
+        "/rebuild"
+        | "/update-self"
+        | "/deploy/rollout"
+        | "/install/qa-service"
+        | "/manage/qa/update"
+        | "/webhook/git"
+        | "/poll-git"
+        | "/build-cross" => Some("host_script"),
+        _ if path.starts_with("/instance/checkout/")
+            || path.starts_with("/deploy/verify-hash/") =>
+        {
+            Some("host_script")
+        }
+        _ => None,
+    }
+}
+
+/// Answer provisioning calls from the mock cloud, so a contributor's
+/// machine is never reconfigured and no cloud credentials are needed
+pub async fn mock_provisioning(
+    request: Request<axum::body::Body>,
+    next: axum::middleware::Next,
+) -> Response {
+    let (Some(dev), Some(kind)) = (active(), provisioning_kind(request.uri().path())) else {
+        return next.run(request).await;
+    };
+
+    let Ok(mut operations) = dev.operations.lock() else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let number = operations.len() + 1;
+    let instance = (kind == "launch_instance").then(|| MockInstance {
+        id: format!("ocid1.instance.dev.local.{:06}", number),
+        display_name: format!("zos-dev-{}", number),
+        shape: "VM.Standard.A1.Flex".to_string(),
+        region: "dev-local-1".to_string(),
+        lifecycle_state: "RUNNING".to_string(),
+    });
+    let operation = MockOperation {
+        id: format!("devop_{}", number),
+        method: request.method().to_string(),
+        path: request.uri().path().to_string(),
+        kind,
+        instance,
+        at: now(),
+    };
+    println!(
+        "☁️  [dev] Mocked {} {} ({})",
+        operation.method, operation.path, kind
+    );
+    operations.push(operation.clone());
+
+    Json(serde_json::json!({
+        "status": "mocked",
+        "dev_mode": true,
+        "operation": operation,
+    }))
+    .into_response()
+}
+
+/// Test wallets with credits and linked accounts, plus demo services
+/// answered from this process
+pub async fn seed(state: &AppState, dev: &DevEnvironment) -> Result<(), String> {
+    let [alice, bob, carol] = [&dev.wallets[0], &dev.wallets[1], &dev.wallets[2]];
+
+    {
+        let mut sessions = state.user_sessions.write().await;
+        for wallet in &dev.wallets {
+            sessions.insert(
+                wallet.address.clone(),
+                UserSession {
+                    wallet_address: wallet.address.clone(),
+                    allocated_port: None,
+                    credits: STARTING_CREDITS,
+                    last_activity: now(),
+                },
+            );
+        }
+    }
+
+    {
+        let mut identities = state.identities.write().await;
+        let links = [
+            (
+                alice,
+                IdentityLink::GameProfile {
+                    user_id: "alice_player".to_string(),
+                    display_name: Some("Alice".to_string()),
+                },
+            ),
+            (
+                bob,
+                IdentityLink::GameProfile {
+                    user_id: "bob_player".to_string(),
+                    display_name: Some("Bob".to_string()),
+                },
+            ),
+            (
+                bob,
+                IdentityLink::Telegram {
+                    telegram_id: 100_000_002,
+                    username: Some("bob_dev".to_string()),
+                },
+            ),
+            (
+                carol,
+                IdentityLink::Telegram {
+                    telegram_id: 100_000_003,
+                    username: Some("carol_dev".to_string()),
+                },
+            ),
+        ];
+        for (wallet, link) in links {
+            crate::identity::link(&mut identities, &wallet.address, &state.config.domain, link)?;
+        }
+    }
+    // Every wallet can be mailed, and mail to bob is relayed to the captured
+    // Telegram outbox. The keys are placeholders: clients publish their own
+    // X25519 key to read what they are sent.
+    {
+        let mut messaging = state.messaging.write().await;
+        for wallet in &dev.wallets {
+            messaging.insert(
+                wallet.address.clone(),
+                MessagingSettings {
+                    public_key: Some(
+                        STANDARD.encode(seed_for(&format!("messaging:{}", wallet.name))),
+                    ),
+                    telegram_relay: wallet.name == "bob",
+                    ..Default::default()
+                },
+            );
+        }
+    }
+
+    for (owner, service_name, template) in [
+        (alice, "echo", "dev-echo"),
+        (bob, "door-game", "dev-door-game"),
+    ] {
+        let service_key = format!("{}_{}", owner.address, service_name);
+        let port = state.ports.allocate(&service_key, "service")?;
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", port))
+            .await
+            .map_err(|e| format!("Cannot bind demo service on {}: {}", port, e))?;
+        let backend = match template {
+            "dev-echo" => echo_backend(),
+            _ => door_game_backend(),
+        };
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, backend).await;
+        });
+
+        state.services.write().await.insert(
+            service_key.clone(),
+            RegisteredService {
+                wallet_address: owner.address.clone(),
+                service_name: service_name.to_string(),
+                template: template.to_string(),
+                port,
+                pricing_tier: "free".to_string(),
+                work_dir: format!("{}/zos-services/{}", dev.data_dir, service_key),
+                created_at: now(),
+            },
+        );
+        state.health_checks.write().await.insert(
+            service_key.clone(),
+            RegisteredHealthCheck {
+                service_key: service_key.clone(),
+                kind: ProbeKind::Http,
+                url: format!("http://127.0.0.1:{}/health", port),
+                interval_secs: 30,
+                timeout_secs: 5,
+                failure_threshold: 3,
+            },
+        );
+        state
+            .activity
+            .publish(
+                "dev",
+                NewEvent {
+                    wallet: owner.address.clone(),
+                    category: FeedCategory::Deployments,
+                    kind: "service_created".to_string(),
+                    summary: format!("Demo service {} is up on port {}", service_name, port),
+                    counterparty: None,
+                    amount: None,
+                    token: None,
+                    reference: Some(service_key),
+                    source: None,
+                    source_id: None,
+                    at: None,
+                },
+            )
+            .await;
+    }
+
+    state
+        .activity
+        .publish(
+            "dev",
+            NewEvent {
+                wallet: bob.address.clone(),
+                category: FeedCategory::Games,
+                kind: "high_score".to_string(),
+                summary: "New high score in door-game: 3 guesses".to_string(),
+                counterparty: None,
+                amount: Some(3.0),
+                token: None,
+                reference: Some("door-game".to_string()),
+                source: None,
+                source_id: None,
+                at: None,
+            },
+        )
+        .await;
+
+    Ok(())
+}
+
+/// Answers with what it was sent
+fn echo_backend() -> Router {
+    Router::new()
+        .route("/health", get(|| async { "ok" }))
+        .fallback(|request: Request<axum::body::Body>| async move {
+            let (parts, body) = request.into_parts();
+            let body = axum::body::to_bytes(body, 1 << 20).await.unwrap_or_default();
+            Json(serde_json::json!({
+                "service": "echo",
+                "method": parts.method.to_string(),
+                "path": parts.uri.path(),
+                "query": parts.uri.query(),
+                "caller": parts.headers.get("x-wallet-address").and_then(|value| value.to_str().ok()),
+                "body": String::from_utf8_lossy(&body),
+            }))
+        })
+}
+
+#[derive(Debug, Default)]
+struct DoorGame {
+    guesses: HashMap<String, u32>, // caller -> guesses this round
+    best: HashMap<String, u32>,    // caller -> fewest guesses to win
+}
+
+#[derive(Debug, Deserialize)]
+struct Guess {
+    guess: u32,
+}
+
+/// The number each caller is looking for; the same on every run
+fn secret_number(caller: &str) -> u32 {
+    u32::from(seed_for(&format!("door-game:{}", caller))[0]) % 100 + 1
+}
+
+/// A guess-the-number door game, with a score table per caller
+fn door_game_backend() -> Router {
+    type Game = std::sync::Arc<Mutex<DoorGame>>;
+
+    async fn guess(
+        State(game): State<Game>,
+        headers: axum::http::HeaderMap,
+        Json(guess): Json<Guess>,
+    ) -> Json<serde_json::Value> {
+        let caller = headers
+            .get("x-wallet-address")
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("anonymous")
+            .to_string();
+        let secret = secret_number(&caller);
+        let Ok(mut game) = game.lock() else {
+            return Json(serde_json::json!({ "error": "Game state is poisoned" }));
+        };
+        let guesses = {
+            let guesses = game.guesses.entry(caller.clone()).or_insert(0);
+            *guesses += 1;
+            *guesses
+        };
+        if guess.guess != secret {
+            let hint = if guess.guess < secret {
+                "higher"
+            } else {
+                "lower"
+            };
+            return Json(serde_json::json!({ "result": hint, "guesses": guesses }));
+        }
+        game.guesses.remove(&caller);
+        let best = game.best.entry(caller).or_insert(guesses);
+        *best = (*best).min(guesses);
+        Json(serde_json::json!({ "result": "correct", "guesses": guesses, "best": *best }))
+    }
+
+    async fn scores(State(game): State<Game>) -> Json<serde_json::Value> {
+        let mut table: Vec<(String, u32)> = game
+            .lock()
+            .map(|game| {
+                game.best
+                    .iter()
+                    .map(|(caller, best)| (caller.clone(), *best))
+                    .collect()
+            })
+            .unwrap_or_default();
+        table.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+        Json(serde_json::json!({ "scores": table }))
+    }
+
+    Router::new()
+        .route("/health", get(|| async { "ok" }))
+        .route(
+            "/",
+            get(|| async {
+                Json(serde_json::json!({
+                    "game": "door-game",
+                    "rules": "Guess a number from 1 to 100; POST /guess {\"guess\": n}",
+                    "commands": ["POST /guess", "GET /scores"],
+                }))
+            }),
+        )
+        .route("/guess", post(guess))
+        .route("/scores", get(scores))
+        .with_state(Game::default())
+}
+
+/// Dev environment: wallets with their keys, demo services and the URLs
+/// that exercise them
+async fn summary(State(state): State<AppState>) -> Response {
+    let Some(dev) = active() else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let base = format!("http://localhost:{}", dev.port);
+    let mut demo: Vec<RegisteredService> = state
+        .services
+        .read()
+        .await
+        .values()
+        .filter(|service| service.template.starts_with("dev-"))
+        .cloned()
+        .collect();
+    demo.sort_by(|a, b| a.service_name.cmp(&b.service_name));
+    let services: Vec<serde_json::Value> = demo
+        .iter()
+        .map(|service| {
+            serde_json::json!({
+                "service_name": service.service_name,
+                "owner": service.wallet_address,
+                "port": service.port,
+                "url": format!("{}/{}/{}", base, service.wallet_address, service.service_name),
+            })
+        })
+        .collect();
+    Json(serde_json::json!({
+        "dev_mode": true,
+        "data_dir": dev.data_dir,
+        "admin_wallet": dev.wallets[0].address,
+        "wallets": dev.wallets,
+        "services": services,
+        "feed_ingest_key": "zos-dev-feed-key",
+        "telegram_captured": dev.telegram().len(),
+        "mock_operations": dev.operations().len(),
+    }))
+    .into_response()
+}
+
+async fn captured_telegram() -> Json<Vec<CapturedTelegram>> {
+    Json(active().map(DevEnvironment::telegram).unwrap_or_default())
+}
+
+async fn mock_operations() -> Json<Vec<MockOperation>> {
+    Json(active().map(DevEnvironment::operations).unwrap_or_default())
+}
+
+#[derive(Debug, Deserialize)]
+struct SignRequest {
+    wallet: String, // test wallet name or address
+    message: String,
+}
+
+/// Sign OIDC challenges and the like as a test wallet
+async fn sign(Json(req): Json<SignRequest>) -> Response {
+    match active().and_then(|dev| dev.wallet(&req.wallet)) {
+        Some(wallet) => Json(serde_json::json!({
+            "wallet": wallet.address,
+            "signature": wallet.sign(&req.message),
+        }))
+        .into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": format!("No test wallet {}", req.wallet) })),
+        )
+            .into_response(),
+    }
+}
+
+/// Mounted under /api/v1 only in dev mode
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/dev", get(summary))
+        .route("/dev/telegram", get(captured_telegram))
+        .route("/dev/operations", get(mock_operations))
+        .route("/dev/sign", post(sign))
+}
+
+pub fn print_banner(dev: &DevEnvironment) {
+    println!("🧪 Dev mode: test wallets, in-memory state, nothing leaves this machine");
+    println!("   Scratch dir: {}", dev.data_dir);
+    for wallet in &dev.wallets {
+        println!("   {:<6} {}", wallet.name, wallet.address);
+    }
+    println!(
+        "   Keys, demo services and captured messages: http://localhost:{}/api/v1/dev",
+        dev.port
+    );
+}
@@ -0,0 +1,334 @@
+// W3C trace context propagation and a span collector with a trace viewer
+// AGPL-3.0 License
+
+use crate::AppState;
+use axum::body::Body;
+use axum::extract::State;
+use axum::http::{HeaderMap, HeaderValue, Request};
+use axum::middleware::Next;
+use axum::response::Response;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+pub const TRACEPARENT: &str = "traceparent";
+
+/// The `traceparent` header: version 00, trace id, parent span id, flags
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceParent {
+    pub trace_id: String, // 32 lowercase hex
+    pub span_id: String,  // 16 lowercase hex
+    pub sampled: bool,
+}
+
+fn random_hex(bytes: usize) -> String {
+    (0..bytes)
+        .map(|_| format!("{:02x}", rand::random::<u8>()))
+        .collect()
+}
+
+fn is_hex_id(value: &str, len: usize) -> bool {
+    value.len() == len
+        && value
+            .bytes()
+            .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+        && value.bytes().any(|b| b != b'0')
+}
+
+impl TraceParent {
+    pub fn root() -> Self {
+        Self {
+            trace_id: random_hex(16),
+            span_id: random_hex(8),
+            sampled: true,
+        }
+    }
+
+    /// Invalid headers are ignored, as the spec asks, and a new trace starts
+    pub fn parse(header: &str) -> Option<Self> {
+        let mut fields = header.trim().split('-');
+        let (version, trace_id, span_id, flags) = (
+            fields.next()?,
+            fields.next()?,
+            fields.next()?,
+            fields.next()?,
+        );
+        if version != "00" || fields.next().is_some() {
+            return None;
+        }
+        if !is_hex_id(trace_id, 32) || !is_hex_id(span_id, 16) || flags.len() != 2 {
+            return None;
+        }
+        let flags = u8::from_str_radix(flags, 16).ok()?;
+
+        Some(Self {
+            trace_id: trace_id.to_string(),
+            span_id: span_id.to_string(),
+            sampled: flags & 1 == 1,
+        })
+    }
+
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        headers
+            .get(TRACEPARENT)
+            .and_then(|value| value.to_str().ok())
+            .and_then(Self::parse)
+    }
+
+    /// Same trace, new span under this one
+    pub fn child(&self) -> Self {
+        Self {
+            trace_id: self.trace_id.clone(),
+            span_id: random_hex(8),
+            sampled: self.sampled,
+        }
+    }
+
+    pub fn header_value(&self) -> String {
+        format!(
+            "00-{}-{}-{}",
+            self.trace_id,
+            self.span_id,
+            if self.sampled { "01" } else { "00" }
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Span {
+    pub trace_id: String,
+    pub span_id: String,
+    pub parent_span_id: Option<String>,
+    pub name: String,
+    pub component: String, // gateway, libp2p, node, backend
+    pub start_us: u64,
+    pub duration_us: u64,
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    #[serde(default)]
+    pub attributes: HashMap<String, String>,
+}
+
+/// A span being timed; `finish` stamps the duration
+pub struct OpenSpan {
+    pub span: Span,
+    started: std::time::Instant,
+}
+
+impl OpenSpan {
+    pub fn start(
+        context: &TraceParent,
+        parent: Option<&TraceParent>,
+        name: &str,
+        component: &str,
+    ) -> Self {
+        Self {
+            span: Span {
+                trace_id: context.trace_id.clone(),
+                span_id: context.span_id.clone(),
+                parent_span_id: parent.map(|parent| parent.span_id.clone()),
+                name: name.to_string(),
+                component: component.to_string(),
+                start_us: chrono::Utc::now().timestamp_micros() as u64,
+                duration_us: 0,
+                status: None,
+                error: None,
+                attributes: HashMap::new(),
+            },
+            started: std::time::Instant::now(),
+        }
+    }
+
+    pub fn finish(mut self, status: Option<u16>, error: Option<String>) -> Span {
+        self.span.duration_us = self.started.elapsed().as_micros() as u64;
+        self.span.status = status;
+        self.span.error = error;
+        self.span
+    }
+}
+
+/// Spans for the most recent traces seen or reported to this node
+#[derive(Clone)]
+pub struct SpanStore {
+    traces: Arc<RwLock<HashMap<String, Vec<Span>>>>,
+    order: Arc<RwLock<VecDeque<String>>>, // trace ids, oldest first
+    pub max_traces: usize,
+}
+
+impl Default for SpanStore {
+    fn default() -> Self {
+        Self {
+            traces: Arc::new(RwLock::new(HashMap::new())),
+            order: Arc::new(RwLock::new(VecDeque::new())),
+            max_traces: 2000,
+        }
+    }
+}
+
+impl SpanStore {
+    pub async fn record(&self, span: Span) {
+        let mut traces = self.traces.write().await;
+        let mut order = self.order.write().await;
+
+        if !traces.contains_key(&span.trace_id) {
+            order.push_back(span.trace_id.clone());
+            while order.len() > self.max_traces {
+                if let Some(oldest) = order.pop_front() {
+                    traces.remove(&oldest);
+                }
+            }
+        }
+        traces.entry(span.trace_id.clone()).or_default().push(span);
+    }
+
+    pub async fn spans(&self, trace_id: &str) -> Vec<Span> {
+        let mut spans = self
+            .traces
+            .read()
+            .await
+            .get(trace_id)
+            .cloned()
+            .unwrap_or_default();
+        spans.sort_by_key(|span| span.start_us);
+        spans
+    }
+}
+
+const MAX_TREE_DEPTH: usize = 64; // reported spans may be malformed or cyclic
+
+fn subtree(span: &Span, spans: &[Span], depth: usize) -> serde_json::Value {
+    let children: Vec<serde_json::Value> = if depth < MAX_TREE_DEPTH {
+        spans
+            .iter()
+            .filter(|child| child.parent_span_id.as_deref() == Some(span.span_id.as_str()))
+            .map(|child| subtree(child, spans, depth + 1))
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let mut node = serde_json::json!(span);
+    node["children"] = serde_json::json!(children);
+    node
+}
+
+/// Nest spans under their parents. Spans whose parent never reported (an
+/// edge outside ZOS, or a hop that dropped its span) become roots.
+pub fn span_tree(spans: &[Span]) -> Vec<serde_json::Value> {
+    spans
+        .iter()
+        .filter(|span| {
+            span.parent_span_id
+                .as_ref()
+                .is_none_or(|parent| !spans.iter().any(|other| other.span_id == *parent))
+        })
+        .map(|root| subtree(root, spans, 0))
+        .collect()
+}
+
+fn html_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn render_node(node: &serde_json::Value, trace_start: u64, out: &mut String) {
+    let offset_ms = node["start_us"]
+        .as_u64()
+        .unwrap_or(0)
+        .saturating_sub(trace_start) as f64
+        / 1000.0;
+    let duration_ms = node["duration_us"].as_u64().unwrap_or(0) as f64 / 1000.0;
+    let failed = node["error"].is_string() || node["status"].as_u64().unwrap_or(0) >= 500;
+
+    out.push_str(&format!(
+        "<li{}><b>{}</b> <i>{}</i> +{:.1}ms {:.1}ms {}{}",
+        if failed { " style=\"color:#c00\"" } else { "" },
+        html_escape(node["component"].as_str().unwrap_or("")),
+        html_escape(node["name"].as_str().unwrap_or("")),
+        offset_ms,
+        duration_ms,
+        node["status"]
+            .as_u64()
+            .map(|status| status.to_string())
+            .unwrap_or_default(),
+        node["error"]
+            .as_str()
+            .map(|error| format!(" — {}", html_escape(error)))
+            .unwrap_or_default()
+    ));
+    if let Some(children) = node["children"]
+        .as_array()
+        .filter(|children| !children.is_empty())
+    {
+        out.push_str("<ul>");
+        for child in children {
+            render_node(child, trace_start, out);
+        }
+        out.push_str("</ul>");
+    }
+    out.push_str("</li>");
+}
+
+pub fn render_html(trace_id: &str, spans: &[Span]) -> String {
+    let trace_start = spans.iter().map(|span| span.start_us).min().unwrap_or(0);
+    let mut out = format!(
+        "<html><head><title>Trace {0}</title></head>\
+         <body style=\"font-family: monospace\"><h3>Trace {0}</h3><ul>",
+        html_escape(trace_id)
+    );
+    for root in span_tree(spans) {
+        render_node(&root, trace_start, &mut out);
+    }
+    out.push_str("</ul></body></html>");
+    out
+}
+
+/// Continue the caller's trace (or start one), time the request as a node
+/// span and hand the context to handlers through request extensions
+pub async fn trace_requests(
+    State(state): State<AppState>,
+    mut request: Request<Body>,
+    next: Next,
+) -> Response {
+    let parent = TraceParent::from_headers(request.headers());
+    let context = parent
+        .as_ref()
+        .map(TraceParent::child)
+        .unwrap_or_else(TraceParent::root);
+
+    let mut open = OpenSpan::start(
+        &context,
+        parent.as_ref(),
+        &format!("{} {}", request.method(), request.uri().path()),
+        "node",
+    );
+    open.span
+        .attributes
+        .insert("node".to_string(), state.config.domain.clone());
+    request.extensions_mut().insert(context.clone());
+
+    let mut response = next.run(request).await;
+
+    let status = response.status();
+    let span = open.finish(
+        Some(status.as_u16()),
+        status.is_server_error().then(|| {
+            status
+                .canonical_reason()
+                .unwrap_or("server error")
+                .to_string()
+        }),
+    );
+    if context.sampled {
+        state.spans.record(span).await;
+    }
+
+    if let Ok(value) = HeaderValue::from_str(&context.header_value()) {
+        response.headers_mut().insert(TRACEPARENT, value);
+    }
+    response
+}
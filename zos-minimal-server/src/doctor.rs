@@ -0,0 +1,475 @@
+// Node doctor - one-command operator diagnostics
+// AGPL-3.0 License
+
+use crate::ServerConfig;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DoctorCheck {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    pub remediation: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DoctorReport {
+    pub generated_at: String,
+    pub overall: CheckStatus,
+    pub checks: Vec<DoctorCheck>,
+}
+
+impl DoctorCheck {
+    fn pass(name: &str, detail: String) -> Self {
+        Self {
+            name: name.to_string(),
+            status: CheckStatus::Pass,
+            detail,
+            remediation: None,
+        }
+    }
+
+    fn warn(name: &str, detail: String, remediation: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            status: CheckStatus::Warn,
+            detail,
+            remediation: Some(remediation.to_string()),
+        }
+    }
+
+    fn fail(name: &str, detail: String, remediation: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            status: CheckStatus::Fail,
+            detail,
+            remediation: Some(remediation.to_string()),
+        }
+    }
+}
+
+pub async fn run_doctor(config: &ServerConfig) -> DoctorReport {
+    let data_dir = crate::migrations::state_dir();
+
+    let checks = vec![
+        check_port_bound(config.http_port).await,
+        check_systemd_unit().await,
+        check_cert_expiry(&config.domain).await,
+        check_disk_space(&data_dir).await,
+        check_git_divergence().await,
+        check_ddns(&config.domain).await,
+        check_data_integrity(&data_dir).await,
+        check_security_headers(&config.security_headers),
+        check_secrets(),
+        check_policies(),
+    ];
+
+    let overall = checks
+        .iter()
+        .map(|check| check.status)
+        .max_by_key(|status| match status {
+            CheckStatus::Pass => 0,
+            CheckStatus::Warn => 1,
+            CheckStatus::Fail => 2,
+        })
+        .unwrap_or(CheckStatus::Pass);
+
+    DoctorReport {
+        generated_at: chrono::Utc::now().to_rfc3339(),
+        overall,
+        checks,
+    }
+}
+
+pub fn print_report(report: &DoctorReport) {
+    println!("🩺 ZOS Node Doctor");
+    println!("==================");
+
+    for check in &report.checks {
+        let icon = match check.status {
+            CheckStatus::Pass => "✅",
+            CheckStatus::Warn => "⚠️ ",
+            CheckStatus::Fail => "❌",
+        };
+        println!("{} {}: {}", icon, check.name, check.detail);
+        if let Some(hint) = &check.remediation {
+            println!("   → {}", hint);
+        }
+    }
+
+    println!("Overall: {:?}", report.overall);
+}
+
+async fn command_stdout(program: &str, args: &[&str]) -> Option<String> {
+    let output = tokio::process::Command::new(program)
+        .args(args)
+        .output()
+        .await
+        .ok()?;
+
+    if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        None
+    }
+}
+
+async fn check_port_bound(port: u16) -> DoctorCheck {
+    match tokio::net::TcpStream::connect(("127.0.0.1", port)).await {
+        Ok(_) => DoctorCheck::pass("port", format!("Port {} is accepting connections", port)),
+        Err(e) => DoctorCheck::fail(
+            "port",
+            format!("Port {} is not reachable: {}", port, e),
+            "Start the server with `zos-minimal-server serve <port>` or check ZOS_HTTP_PORT",
+        ),
+    }
+}
+
+async fn check_systemd_unit() -> DoctorCheck {
+    let unit =
+        std::env::var("ZOS_SYSTEMD_UNIT").unwrap_or_else(|_| "zos-server.service".to_string());
+
+    // is-active exits non-zero for inactive units, so read stdout regardless
+    let state = tokio::process::Command::new("systemctl")
+        .args(["is-active", &unit])
+        .output()
+        .await
+        .ok()
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|state| !state.is_empty());
+
+    match state {
+        Some(state) if state == "active" => {
+            DoctorCheck::pass("systemd", format!("{} is active", unit))
+        }
+        Some(state) => DoctorCheck::fail(
+            "systemd",
+            format!("{} is {}", unit, state),
+            &format!(
+                "sudo systemctl restart {} && journalctl -u {} -n 50",
+                unit, unit
+            ),
+        ),
+        None => DoctorCheck::warn(
+            "systemd",
+            format!("{} is not active or systemctl is unavailable", unit),
+            "Install the unit with `zos-minimal-server deploy-systemd` or set ZOS_SYSTEMD_UNIT",
+        ),
+    }
+}
+
+async fn check_cert_expiry(domain: &str) -> DoctorCheck {
+    let cert_path = std::env::var("ZOS_TLS_CERT")
+        .unwrap_or_else(|_| format!("/etc/letsencrypt/live/{}/cert.pem", domain));
+
+    if !std::path::Path::new(&cert_path).exists() {
+        return DoctorCheck::warn(
+            "certificate",
+            format!("No certificate at {}", cert_path),
+            "Run setup-https.sh or point ZOS_TLS_CERT at the served certificate",
+        );
+    }
+
+    let end_date = command_stdout(
+        "openssl",
+        &["x509", "-enddate", "-noout", "-in", &cert_path],
+    )
+    .await
+    .and_then(|line| line.strip_prefix("notAfter=").map(|s| s.to_string()))
+    .and_then(|date| {
+        chrono::NaiveDateTime::parse_from_str(date.trim_end_matches(" GMT"), "%b %e %H:%M:%S %Y")
+            .ok()
+    });
+
+    match end_date {
+        Some(expires) => {
+            let days_left = (expires - chrono::Utc::now().naive_utc()).num_days();
+            if days_left < 0 {
+                DoctorCheck::fail(
+                    "certificate",
+                    format!("Certificate expired {} days ago", -days_left),
+                    "Renew immediately: sudo certbot renew && sudo systemctl reload nginx",
+                )
+            } else if days_left < 14 {
+                DoctorCheck::warn(
+                    "certificate",
+                    format!("Certificate expires in {} days", days_left),
+                    "Renew soon: sudo certbot renew",
+                )
+            } else {
+                DoctorCheck::pass(
+                    "certificate",
+                    format!("Certificate valid for {} days", days_left),
+                )
+            }
+        }
+        None => DoctorCheck::warn(
+            "certificate",
+            format!("Could not read expiry from {}", cert_path),
+            "Check that openssl is installed and the certificate is PEM encoded",
+        ),
+    }
+}
+
+pub async fn disk_used_percent(data_dir: &str) -> Option<u32> {
+    command_stdout("df", &["-P", data_dir])
+        .await
+        .and_then(|out| {
+            out.lines()
+                .nth(1)
+                .and_then(|line| line.split_whitespace().nth(4))
+                .and_then(|field| field.trim_end_matches('%').parse::<u32>().ok())
+        })
+}
+
+fn check_security_headers(headers: &crate::security_headers::SecurityHeaders) -> DoctorCheck {
+    let (errors, warnings) = headers.audit();
+    if !errors.is_empty() {
+        DoctorCheck::fail(
+            "security_headers",
+            format!("{:?} profile: {}", headers.profile, errors.join("; ")),
+            "Fix ZOS_CSP, ZOS_HSTS_MAX_AGE, ZOS_FRAME_OPTIONS or ZOS_REFERRER_POLICY, or unset them for the profile defaults",
+        )
+    } else if !warnings.is_empty() {
+        DoctorCheck::warn(
+            "security_headers",
+            format!("{:?} profile: {}", headers.profile, warnings.join("; ")),
+            "Set ZOS_SECURITY_PROFILE=prod on public nodes",
+        )
+    } else {
+        DoctorCheck::pass(
+            "security_headers",
+            format!("{:?} profile headers are sound", headers.profile),
+        )
+    }
+}
+
+fn check_secrets() -> DoctorCheck {
+    let plaintext = crate::secrets::plaintext_in_env();
+    if !plaintext.is_empty() {
+        return DoctorCheck::warn(
+            "secrets",
+            format!("Read from plaintext environment: {}", plaintext.join(", ")),
+            "PUT each to /api/v1/secrets/:name, then remove it from the environment",
+        );
+    }
+    let Some(store) = crate::secrets::store() else {
+        return DoctorCheck::pass(
+            "secrets",
+            "No secrets in the environment; the store opens when the node serves".to_string(),
+        );
+    };
+    let stored = store.names().len();
+    match &store.source {
+        crate::secrets::MasterKeySource::File(path)
+            if path.starts_with(&crate::migrations::state_dir()) =>
+        {
+            DoctorCheck::warn(
+                "secrets",
+                format!("{} stored, master key {} sits beside them", stored, path),
+                "Keep the master key elsewhere (ZOS_SECRETS_MASTER_KEY_FILE) or use ZOS_SECRETS_KMS_URL",
+            )
+        }
+        source => DoctorCheck::pass(
+            "secrets",
+            format!("{} stored, master key from {:?}", stored, source),
+        ),
+    }
+}
+
+fn check_policies() -> DoctorCheck {
+    match crate::access_policy::load_operator() {
+        Err(e) => DoctorCheck::fail(
+            "policies",
+            e,
+            "Fix ZOS_POLICY_FILE; until then only the built-in policies apply",
+        ),
+        Ok(operator) => DoctorCheck::pass(
+            "policies",
+            format!(
+                "{} built-in, {} operator policies",
+                crate::access_policy::builtin().policies.len(),
+                operator.policies.len()
+            ),
+        ),
+    }
+}
+
+async fn check_disk_space(data_dir: &str) -> DoctorCheck {
+    match disk_used_percent(data_dir).await {
+        Some(used) if used >= 95 => DoctorCheck::fail(
+            "disk",
+            format!("{} is {}% full", data_dir, used),
+            "Free space now: cargo clean, rotate build_logs/, prune /tmp/zos-*",
+        ),
+        Some(used) if used >= 80 => DoctorCheck::warn(
+            "disk",
+            format!("{} is {}% full", data_dir, used),
+            "Rotate logs and remove old target/ directories",
+        ),
+        Some(used) => DoctorCheck::pass("disk", format!("{} is {}% full", data_dir, used)),
+        None => DoctorCheck::warn(
+            "disk",
+            format!("Could not read disk usage for {}", data_dir),
+            "Check that the state directory under ZOS_DATA_DIR exists and df is available",
+        ),
+    }
+}
+
+async fn check_git_divergence() -> DoctorCheck {
+    let branch = command_stdout("git", &["branch", "--show-current"])
+        .await
+        .unwrap_or_else(|| "main".to_string());
+
+    let counts = command_stdout(
+        "git",
+        &[
+            "rev-list",
+            "--left-right",
+            "--count",
+            &format!("HEAD...origin/{}", branch),
+        ],
+    )
+    .await
+    .and_then(|out| {
+        let mut parts = out.split_whitespace().filter_map(|n| n.parse::<u32>().ok());
+        Some((parts.next()?, parts.next()?))
+    });
+
+    match counts {
+        Some((0, 0)) => DoctorCheck::pass("git", format!("{} matches origin", branch)),
+        Some((ahead, 0)) => DoctorCheck::warn(
+            "git",
+            format!("{} is {} commits ahead of origin", branch, ahead),
+            "Push local commits or reset to origin before the next deploy",
+        ),
+        Some((0, behind)) => DoctorCheck::warn(
+            "git",
+            format!("{} is {} commits behind origin", branch, behind),
+            "POST /poll-git with auto_deploy=true to update",
+        ),
+        Some((ahead, behind)) => DoctorCheck::fail(
+            "git",
+            format!("{} diverged: {} ahead, {} behind", branch, ahead, behind),
+            "Resolve manually: git fetch && git rebase origin/<branch>",
+        ),
+        None => DoctorCheck::warn(
+            "git",
+            "Could not compare with origin".to_string(),
+            "Run `git fetch origin` and check the remote configuration",
+        ),
+    }
+}
+
+pub async fn check_ddns(domain: &str) -> DoctorCheck {
+    if domain == "localhost" {
+        return DoctorCheck::pass("ddns", "Local domain, DDNS not used".to_string());
+    }
+
+    let resolved: Vec<String> = match tokio::net::lookup_host((domain, 443)).await {
+        Ok(addrs) => addrs.map(|addr| addr.ip().to_string()).collect(),
+        Err(e) => {
+            return DoctorCheck::fail(
+                "ddns",
+                format!("{} does not resolve: {}", domain, e),
+                "Check the DNS record and run namecheap_ddns.py",
+            )
+        }
+    };
+
+    let ipify = crate::http_clients::shared().get("https://api.ipify.org");
+    let public_ip = match crate::http_clients::shared().send_with_retry(ipify).await {
+        Ok(response) => response.text().await.ok(),
+        Err(_) => None,
+    };
+
+    match public_ip {
+        Some(ip) if resolved.contains(&ip) => {
+            DoctorCheck::pass("ddns", format!("{} → {}", domain, ip))
+        }
+        Some(ip) => DoctorCheck::fail(
+            "ddns",
+            format!("{} → {:?} but public IP is {}", domain, resolved, ip),
+            "Force an update: POST /api/ddns/update or run namecheap_ddns.py",
+        ),
+        None => DoctorCheck::warn(
+            "ddns",
+            format!("{} → {:?}, public IP unknown", domain, resolved),
+            "Check outbound connectivity",
+        ),
+    }
+}
+
+async fn check_data_integrity(data_dir: &str) -> DoctorCheck {
+    let mut entries = match tokio::fs::read_dir(data_dir).await {
+        Ok(entries) => entries,
+        Err(e) => {
+            return DoctorCheck::warn(
+                "data",
+                format!("Cannot read {}: {}", data_dir, e),
+                "Create the state directory under ZOS_DATA_DIR and make it writable by the service user",
+            )
+        }
+    };
+
+    let mut checked = 0;
+    let mut corrupt = Vec::new();
+
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        if path.extension().map(|ext| ext == "json").unwrap_or(false) {
+            checked += 1;
+            let valid = tokio::fs::read(&path)
+                .await
+                .ok()
+                .map(|bytes| serde_json::from_slice::<serde_json::Value>(&bytes).is_ok())
+                .unwrap_or(false);
+            if !valid {
+                corrupt.push(path.display().to_string());
+            }
+        }
+    }
+
+    if corrupt.is_empty() {
+        DoctorCheck::pass("data", format!("{} data files parse cleanly", checked))
+    } else {
+        DoctorCheck::fail(
+            "data",
+            format!("Corrupt data files: {}", corrupt.join(", ")),
+            "Restore the listed files from the latest snapshot",
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_data_check_reads_the_stores_it_is_given() {
+        let dir = std::env::temp_dir().join(format!("zos-doctor-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let dir = dir.to_string_lossy().into_owned();
+
+        let missing = check_data_integrity(&dir).await;
+        assert_eq!(missing.status, CheckStatus::Warn);
+
+        crate::migrations::save_store(&dir, "alerts", &serde_json::json!({})).unwrap();
+        let clean = check_data_integrity(&dir).await;
+        assert_eq!(clean.status, CheckStatus::Pass);
+        assert!(clean.detail.starts_with("1 data files"));
+
+        std::fs::write(format!("{}/prewarm.json", dir), "{ half written").unwrap();
+        let corrupt = check_data_integrity(&dir).await;
+        assert_eq!(corrupt.status, CheckStatus::Fail);
+        assert!(corrupt.detail.contains("prewarm.json"));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,162 @@
+// Shared outbound HTTP client: one connection pool, per-destination
+// timeouts, retry with backoff and proxy support
+// AGPL-3.0 License
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpClientConfig {
+    pub proxy: Option<String>,
+    pub connect_timeout_secs: u64,
+    pub default_timeout_secs: u64,
+    pub destination_timeouts: HashMap<String, u64>, // host -> request timeout secs
+    pub pool_idle_timeout_secs: u64,
+    pub pool_max_idle_per_host: usize,
+    pub retry: RetryPolicy,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+}
+
+impl HttpClientConfig {
+    /// ZOS_HTTP_PROXY, ZOS_HTTP_TIMEOUT_SECS, ZOS_HTTP_RETRIES and
+    /// ZOS_HTTP_TIMEOUTS ("host=secs,host=secs")
+    pub fn load() -> Self {
+        let env_u64 = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+
+        let destination_timeouts = std::env::var("ZOS_HTTP_TIMEOUTS")
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|entry| {
+                let (host, secs) = entry.split_once('=')?;
+                Some((host.trim().to_string(), secs.trim().parse().ok()?))
+            })
+            .collect();
+
+        Self {
+            proxy: std::env::var("ZOS_HTTP_PROXY")
+                .ok()
+                .filter(|p| !p.is_empty()),
+            connect_timeout_secs: 5,
+            default_timeout_secs: env_u64("ZOS_HTTP_TIMEOUT_SECS", 30),
+            destination_timeouts,
+            pool_idle_timeout_secs: 90,
+            pool_max_idle_per_host: 8,
+            retry: RetryPolicy {
+                max_retries: env_u64("ZOS_HTTP_RETRIES", 2) as u32,
+                initial_backoff_ms: 250,
+                max_backoff_ms: 5000,
+            },
+        }
+    }
+}
+
+pub struct HttpClients {
+    client: reqwest::Client,
+    config: HttpClientConfig,
+}
+
+static SHARED: OnceLock<HttpClients> = OnceLock::new();
+
+/// Install the client built from the server config; call once at startup
+pub fn init(config: &HttpClientConfig) {
+    if SHARED.set(HttpClients::build(config.clone())).is_err() {
+        println!("⚠️  HTTP clients already initialized");
+    }
+}
+
+/// The process-wide client, built from the environment if `init` was not called
+pub fn shared() -> &'static HttpClients {
+    SHARED.get_or_init(|| HttpClients::build(HttpClientConfig::load()))
+}
+
+impl HttpClients {
+    fn build(config: HttpClientConfig) -> Self {
+        let mut builder = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(config.connect_timeout_secs))
+            .pool_idle_timeout(Duration::from_secs(config.pool_idle_timeout_secs))
+            .pool_max_idle_per_host(config.pool_max_idle_per_host)
+            .tcp_keepalive(Duration::from_secs(60))
+            .user_agent(concat!("zos-minimal-server/", env!("CARGO_PKG_VERSION")));
+
+        if let Some(proxy) = &config.proxy {
+            match reqwest::Proxy::all(proxy) {
+                // Local services are never sent through the proxy
+                Ok(proxy) => {
+                    builder = builder.proxy(
+                        proxy.no_proxy(reqwest::NoProxy::from_string("localhost,127.0.0.1,::1")),
+                    )
+                }
+                Err(e) => println!("⚠️  Ignoring invalid proxy {}: {}", proxy, e),
+            }
+        }
+
+        let client = builder.build().unwrap_or_else(|e| {
+            println!("⚠️  Falling back to default HTTP client: {}", e);
+            reqwest::Client::new()
+        });
+
+        Self { client, config }
+    }
+
+    fn timeout_for(&self, url: &str) -> Duration {
+        let host = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string));
+
+        let secs = host
+            .and_then(|host| self.config.destination_timeouts.get(&host).copied())
+            .unwrap_or(self.config.default_timeout_secs);
+
+        Duration::from_secs(secs)
+    }
+
+    pub fn get(&self, url: &str) -> reqwest::RequestBuilder {
+        self.client.get(url).timeout(self.timeout_for(url))
+    }
+
+    pub fn post(&self, url: &str) -> reqwest::RequestBuilder {
+        self.client.post(url).timeout(self.timeout_for(url))
+    }
+
+    /// Send, retrying connection errors, timeouts and 5xx responses with
+    /// exponential backoff. Only use for requests that are safe to repeat.
+    pub async fn send_with_retry(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        let retry = &self.config.retry;
+        let mut backoff = retry.initial_backoff_ms;
+        let mut attempt = 0;
+
+        loop {
+            // Streaming bodies cannot be cloned; send those once
+            let next = match request.try_clone() {
+                Some(next) if attempt < retry.max_retries => next,
+                _ => return request.send().await,
+            };
+
+            match next.send().await {
+                Ok(response) if !response.status().is_server_error() => return Ok(response),
+                Err(e) if !(e.is_connect() || e.is_timeout()) => return Err(e),
+                _ => {}
+            }
+
+            attempt += 1;
+            tokio::time::sleep(Duration::from_millis(backoff)).await;
+            backoff = (backoff * 2).min(retry.max_backoff_ms);
+        }
+    }
+}
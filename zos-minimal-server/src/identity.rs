@@ -0,0 +1,523 @@
+// One identity per wallet: Unix account, game profile, Telegram link and
+// gateway endpoints, kept consistent as links change
+// AGPL-3.0 License
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// How old a Telegram login may be when it is presented as proof
+const TELEGRAM_LOGIN_MAX_AGE_SECS: u64 = 86_400;
+/// How long a Unix link challenge stays open
+pub const UNIX_CHALLENGE_TTL_SECS: u64 = 600;
+/// Where in the account's home directory the challenge nonce is written
+pub const UNIX_CHALLENGE_FILE: &str = ".zos-link";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnixLink {
+    pub username: String,
+    pub uid: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameProfileLink {
+    pub user_id: String, // retro games user id
+    pub display_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelegramLink {
+    pub telegram_id: i64,
+    pub username: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Identity {
+    pub wallet_address: String,
+    pub unix_account: Option<UnixLink>,
+    pub game_profile: Option<GameProfileLink>,
+    pub telegram: Option<TelegramLink>,
+    pub gateway_endpoints: Vec<String>, // public URLs under https://{domain}/{wallet}/
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// A change to one of a wallet's links
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum IdentityLink {
+    Unix {
+        username: String,
+        uid: Option<u32>,
+    },
+    GameProfile {
+        user_id: String,
+        display_name: Option<String>,
+    },
+    Telegram {
+        telegram_id: i64,
+        username: Option<String>,
+    },
+    GatewayEndpoint {
+        url: String,
+    },
+}
+
+/// A link request: the link, and proof the wallet also controls the account
+/// behind it where the node can check that
+#[derive(Debug, Clone, Deserialize)]
+pub struct LinkRequest {
+    #[serde(flatten)]
+    pub link: IdentityLink,
+    #[serde(default)]
+    pub proof: LinkProof,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LinkProof {
+    /// The fields of a Telegram Login Widget callback, `hash` included
+    pub telegram_login: Option<HashMap<String, serde_json::Value>>,
+    /// The nonce from the Unix challenge, as written to ~/.zos-link
+    pub unix_nonce: Option<String>,
+}
+
+/// An open Unix link challenge for one wallet
+#[derive(Debug, Clone, Serialize)]
+pub struct UnixChallenge {
+    pub username: String,
+    pub nonce: String,
+    pub expires_at: u64,
+}
+
+impl UnixChallenge {
+    pub fn new(username: &str) -> Self {
+        use rand::RngCore;
+
+        let mut bytes = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        Self {
+            username: username.to_string(),
+            nonce: hex_encode(&bytes),
+            expires_at: now() + UNIX_CHALLENGE_TTL_SECS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdentityIssue {
+    pub link: String,
+    pub message: String,
+}
+
+fn now() -> u64 {
+    chrono::Utc::now().timestamp() as u64
+}
+
+fn issue(link: &str, message: String) -> IdentityIssue {
+    IdentityIssue {
+        link: link.to_string(),
+        message,
+    }
+}
+
+/// Same rules as useradd's default NAME_REGEX
+fn valid_unix_username(username: &str) -> bool {
+    let mut chars = username.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_lowercase() || c == '_')
+        && username.len() <= 32
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+}
+
+impl Identity {
+    fn new(wallet_address: &str) -> Self {
+        let created_at = now();
+        Self {
+            wallet_address: wallet_address.to_string(),
+            unix_account: None,
+            game_profile: None,
+            telegram: None,
+            gateway_endpoints: Vec::new(),
+            created_at,
+            updated_at: created_at,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.unix_account.is_none()
+            && self.game_profile.is_none()
+            && self.telegram.is_none()
+            && self.gateway_endpoints.is_empty()
+    }
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Check a Telegram Login Widget callback: the hash is the HMAC-SHA256 of
+/// the sorted fields under SHA256(bot token), so only Telegram can make it
+/// for `telegram_id`
+pub fn verify_telegram_login(
+    bot_token: &str,
+    fields: &HashMap<String, serde_json::Value>,
+    telegram_id: i64,
+    now: u64,
+) -> Result<(), String> {
+    let text = |value: &serde_json::Value| match value {
+        serde_json::Value::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+    let hash = fields
+        .get("hash")
+        .map(text)
+        .ok_or("Telegram login has no hash")?;
+    let data_check_string = fields
+        .iter()
+        .filter(|(key, _)| key.as_str() != "hash")
+        .map(|(key, value)| (key.as_str(), text(value)))
+        .collect::<BTreeMap<_, _>>()
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let secret = Sha256::digest(bot_token.as_bytes());
+    let mut mac =
+        HmacSha256::new_from_slice(&secret).map_err(|e| format!("Invalid bot token: {}", e))?;
+    mac.update(data_check_string.as_bytes());
+    if hex_encode(&mac.finalize().into_bytes()) != hash.to_lowercase() {
+        return Err("Telegram login hash does not match".to_string());
+    }
+
+    if fields.get("id").map(text) != Some(telegram_id.to_string()) {
+        return Err("Telegram login is for another account".to_string());
+    }
+    let auth_date: u64 = fields
+        .get("auth_date")
+        .map(text)
+        .and_then(|date| date.parse().ok())
+        .ok_or("Telegram login has no auth_date")?;
+    if auth_date > now + 60 || now.saturating_sub(auth_date) > TELEGRAM_LOGIN_MAX_AGE_SECS {
+        return Err("Telegram login is too old; sign in again".to_string());
+    }
+    Ok(())
+}
+
+/// (uid, home) of `username` in passwd-format `passwd`
+fn passwd_entry(passwd: &str, username: &str) -> Option<(u32, String)> {
+    passwd.lines().find_map(|line| {
+        let fields: Vec<&str> = line.split(':').collect();
+        if fields.len() < 7 || fields[0] != username {
+            return None;
+        }
+        Some((fields[2].parse().ok()?, fields[5].to_string()))
+    })
+}
+
+/// Check the account answered its challenge: ~/.zos-link holds the nonce
+/// and belongs to the account, so only someone who can log in as it could
+/// have written it. Returns the account's uid.
+pub fn verify_unix_challenge(passwd: &str, username: &str, nonce: &str) -> Result<u32, String> {
+    use std::os::unix::fs::MetadataExt;
+
+    let (uid, home) =
+        passwd_entry(passwd, username).ok_or(format!("No Unix account {}", username))?;
+    let path = std::path::Path::new(&home).join(UNIX_CHALLENGE_FILE);
+    let metadata =
+        std::fs::symlink_metadata(&path).map_err(|_| format!("{} not found", path.display()))?;
+    if !metadata.is_file() || metadata.uid() != uid {
+        return Err(format!(
+            "{} is not a file owned by {}",
+            path.display(),
+            username
+        ));
+    }
+    let written = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    if written.trim() != nonce {
+        return Err(format!(
+            "{} does not hold the challenge nonce",
+            path.display()
+        ));
+    }
+    Ok(uid)
+}
+
+/// The wallet other than `wallet_address` already holding a link, if any
+fn claimed_by<'a>(
+    identities: &'a HashMap<String, Identity>,
+    wallet_address: &str,
+    link: &IdentityLink,
+) -> Option<&'a str> {
+    identities
+        .values()
+        .filter(|identity| identity.wallet_address != wallet_address)
+        .find(|identity| match link {
+            IdentityLink::Unix { username, .. } => identity
+                .unix_account
+                .as_ref()
+                .is_some_and(|unix| &unix.username == username),
+            IdentityLink::GameProfile { user_id, .. } => identity
+                .game_profile
+                .as_ref()
+                .is_some_and(|game| &game.user_id == user_id),
+            IdentityLink::Telegram { telegram_id, .. } => identity
+                .telegram
+                .as_ref()
+                .is_some_and(|telegram| telegram.telegram_id == *telegram_id),
+            IdentityLink::GatewayEndpoint { url } => identity.gateway_endpoints.contains(url),
+        })
+        .map(|identity| identity.wallet_address.as_str())
+}
+
+/// Add or replace one link. Links that would give two wallets the same
+/// account, profile, chat or endpoint are refused.
+pub fn link(
+    identities: &mut HashMap<String, Identity>,
+    wallet_address: &str,
+    domain: &str,
+    link: IdentityLink,
+) -> Result<Identity, String> {
+    match &link {
+        IdentityLink::Unix { username, .. } if !valid_unix_username(username) => {
+            return Err(format!("Invalid Unix username: {}", username));
+        }
+        IdentityLink::GameProfile { user_id, .. } if user_id.trim().is_empty() => {
+            return Err("Game profile user_id is empty".to_string());
+        }
+        IdentityLink::GatewayEndpoint { url } => {
+            let prefix = format!("https://{}/{}/", domain, wallet_address);
+            if !url.starts_with(&prefix) {
+                return Err(format!("Gateway endpoint must be under {}", prefix));
+            }
+        }
+        _ => {}
+    }
+
+    if let Some(owner) = claimed_by(identities, wallet_address, &link) {
+        return Err(format!("Already linked to wallet {}", owner));
+    }
+
+    let identity = identities
+        .entry(wallet_address.to_string())
+        .or_insert_with(|| Identity::new(wallet_address));
+
+    match link {
+        IdentityLink::Unix { username, uid } => {
+            identity.unix_account = Some(UnixLink { username, uid })
+        }
+        IdentityLink::GameProfile {
+            user_id,
+            display_name,
+        } => {
+            identity.game_profile = Some(GameProfileLink {
+                user_id,
+                display_name,
+            })
+        }
+        IdentityLink::Telegram {
+            telegram_id,
+            username,
+        } => {
+            identity.telegram = Some(TelegramLink {
+                telegram_id,
+                username,
+            })
+        }
+        IdentityLink::GatewayEndpoint { url } => {
+            if !identity.gateway_endpoints.contains(&url) {
+                identity.gateway_endpoints.push(url);
+            }
+        }
+    }
+    identity.updated_at = now();
+
+    Ok(identity.clone())
+}
+
+/// Remove one link ("unix", "game_profile", "telegram" or "gateway_endpoints");
+/// the identity itself goes once nothing is linked
+pub fn unlink(
+    identities: &mut HashMap<String, Identity>,
+    wallet_address: &str,
+    kind: &str,
+) -> Result<Option<Identity>, String> {
+    let identity = identities
+        .get_mut(wallet_address)
+        .ok_or("No identity for wallet")?;
+
+    match kind {
+        "unix" => identity.unix_account = None,
+        "game_profile" => identity.game_profile = None,
+        "telegram" => identity.telegram = None,
+        "gateway_endpoints" => identity.gateway_endpoints.clear(),
+        _ => return Err(format!("Unknown link kind: {}", kind)),
+    }
+    identity.updated_at = now();
+
+    if identity.is_empty() {
+        identities.remove(wallet_address);
+        return Ok(None);
+    }
+    Ok(identities.get(wallet_address).cloned())
+}
+
+/// Problems with a wallet's links as they stand now. `link` refuses
+/// conflicts as they are made; this catches what drifted afterwards, such
+/// as services removed behind an endpoint or duplicates from restored state.
+pub fn check(
+    identities: &HashMap<String, Identity>,
+    identity: &Identity,
+    service_endpoints: &[String],
+) -> Vec<IdentityIssue> {
+    let mut issues = Vec::new();
+
+    if let Some(unix) = &identity.unix_account {
+        let unix_link = IdentityLink::Unix {
+            username: unix.username.clone(),
+            uid: unix.uid,
+        };
+        if let Some(owner) = claimed_by(identities, &identity.wallet_address, &unix_link) {
+            issues.push(issue(
+                "unix",
+                format!("{} is also linked to wallet {}", unix.username, owner),
+            ));
+        }
+
+        // Door games run under the player's Unix account
+        if let Some(game) = &identity.game_profile {
+            if game.user_id != unix.username {
+                issues.push(issue(
+                    "game_profile",
+                    format!(
+                        "Game user {} does not match Unix account {}",
+                        game.user_id, unix.username
+                    ),
+                ));
+            }
+        }
+    }
+
+    if let Some(game) = &identity.game_profile {
+        let game_link = IdentityLink::GameProfile {
+            user_id: game.user_id.clone(),
+            display_name: None,
+        };
+        if let Some(owner) = claimed_by(identities, &identity.wallet_address, &game_link) {
+            issues.push(issue(
+                "game_profile",
+                format!("{} is also linked to wallet {}", game.user_id, owner),
+            ));
+        }
+    }
+
+    if let Some(telegram) = &identity.telegram {
+        let telegram_link = IdentityLink::Telegram {
+            telegram_id: telegram.telegram_id,
+            username: None,
+        };
+        if let Some(owner) = claimed_by(identities, &identity.wallet_address, &telegram_link) {
+            issues.push(issue(
+                "telegram",
+                format!(
+                    "Telegram {} is also linked to wallet {}",
+                    telegram.telegram_id, owner
+                ),
+            ));
+        }
+    }
+
+    for url in &identity.gateway_endpoints {
+        if !service_endpoints
+            .iter()
+            .any(|endpoint| url.starts_with(endpoint.as_str()))
+        {
+            issues.push(issue(
+                "gateway_endpoints",
+                format!("{} does not point at a registered service", url),
+            ));
+        }
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn telegram_login(
+        bot_token: &str,
+        id: i64,
+        auth_date: u64,
+    ) -> HashMap<String, serde_json::Value> {
+        let mut fields: HashMap<String, serde_json::Value> = HashMap::from([
+            ("id".to_string(), serde_json::json!(id)),
+            ("first_name".to_string(), serde_json::json!("Ada")),
+            ("auth_date".to_string(), serde_json::json!(auth_date)),
+        ]);
+        let data_check_string = format!("auth_date={}\nfirst_name=Ada\nid={}", auth_date, id);
+        let mut mac = HmacSha256::new_from_slice(&Sha256::digest(bot_token.as_bytes())).unwrap();
+        mac.update(data_check_string.as_bytes());
+        fields.insert(
+            "hash".to_string(),
+            serde_json::json!(hex_encode(&mac.finalize().into_bytes())),
+        );
+        fields
+    }
+
+    #[test]
+    fn test_telegram_login_signed_by_the_bot_is_accepted() {
+        let now = now();
+        let fields = telegram_login("bot-token", 42, now - 10);
+        assert!(verify_telegram_login("bot-token", &fields, 42, now).is_ok());
+    }
+
+    #[test]
+    fn test_forged_or_stale_telegram_login_is_refused() {
+        let now = now();
+        let fields = telegram_login("another-bot", 42, now - 10);
+        assert!(verify_telegram_login("bot-token", &fields, 42, now).is_err());
+
+        let mut tampered = telegram_login("bot-token", 42, now - 10);
+        tampered.insert("first_name".to_string(), serde_json::json!("Eve"));
+        assert!(verify_telegram_login("bot-token", &tampered, 42, now).is_err());
+
+        let fields = telegram_login("bot-token", 42, now - 10);
+        assert!(verify_telegram_login("bot-token", &fields, 43, now).is_err());
+
+        let stale = telegram_login("bot-token", 42, now - TELEGRAM_LOGIN_MAX_AGE_SECS - 1);
+        assert!(verify_telegram_login("bot-token", &stale, 42, now).is_err());
+    }
+
+    #[test]
+    fn test_unix_challenge_needs_the_nonce_in_the_accounts_home() {
+        use std::os::unix::fs::MetadataExt;
+
+        let home = std::env::temp_dir().join(format!("zos-identity-test-{}", std::process::id()));
+        std::fs::create_dir_all(&home).unwrap();
+        let uid = std::fs::metadata(&home).unwrap().uid();
+        let passwd = format!("ada:x:{}:{}::{}:/bin/sh\n", uid, uid, home.display());
+
+        assert!(verify_unix_challenge(&passwd, "ada", "nonce").is_err());
+        std::fs::write(home.join(UNIX_CHALLENGE_FILE), "wrong\n").unwrap();
+        assert!(verify_unix_challenge(&passwd, "ada", "nonce").is_err());
+        std::fs::write(home.join(UNIX_CHALLENGE_FILE), "nonce\n").unwrap();
+        assert_eq!(verify_unix_challenge(&passwd, "ada", "nonce"), Ok(uid));
+
+        // The same file does not prove an account with another uid
+        let other = format!(
+            "bob:x:{}:{}::{}:/bin/sh\n",
+            uid + 1,
+            uid + 1,
+            home.display()
+        );
+        assert!(verify_unix_challenge(&other, "bob", "nonce").is_err());
+        assert!(verify_unix_challenge(&passwd, "bob", "nonce").is_err());
+
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+}
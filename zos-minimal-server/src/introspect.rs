@@ -0,0 +1,230 @@
+// Self-introspection: what this node is built from and running with, for
+// the dashboard and for peers checking each other before federating
+// AGPL-3.0 License
+
+use crate::ServerConfig;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::OnceLock;
+
+/// Bumped when the introspection document changes shape
+pub const SCHEMA: &str = "zos-introspect/1";
+
+/// Modules compiled into this binary
+pub const MODULES: &[(&str, &str)] = &[
+    ("access_policy", "Declarative policies for port allocation"),
+    ("alerting", "Alert rules, silences and notifications"),
+    (
+        "api_versions",
+        "Versioned API and deprecation of unversioned paths",
+    ),
+    ("batch_jobs", "Throttled, pausable analysis jobs"),
+    (
+        "build_orchestrator",
+        "Dependency-ordered, cached workspace builds",
+    ),
+    ("client_sdk", "Route registry and generated client SDKs"),
+    ("data_export", "Wallet data export and deletion"),
+    (
+        "dependency_drift",
+        "Mirrored crate drift and advisory exposure",
+    ),
+    (
+        "distributed_tracing",
+        "W3C trace context and span collection",
+    ),
+    ("doctor", "Operator diagnostics"),
+    ("http_clients", "Outbound HTTP clients, proxy and timeouts"),
+    ("identity", "Wallet identity links"),
+    ("introspect", "This document"),
+    (
+        "maintenance",
+        "Maintenance windows, request holding and checkpoints",
+    ),
+    ("messaging", "Encrypted wallet-to-wallet messages"),
+    ("load_test", "Load generation against sandbox deployments"),
+    ("migrations", "Versioned persisted state"),
+    ("oidc", "OpenID Connect provider"),
+    ("onboarding", "Onboarding wizard and rewards"),
+    ("prewarm", "Service prewarming"),
+    ("proxy", "Service call proxy"),
+    (
+        "secrets",
+        "Envelope-encrypted secrets under a file or KMS master key",
+    ),
+    (
+        "security_headers",
+        "CSP, HSTS, frame and referrer policy for HTML pages",
+    ),
+    (
+        "service_health",
+        "Health probes, delisting and dependencies",
+    ),
+    ("service_logs", "Per-service log capture"),
+    ("service_templates", "Service templates"),
+    ("service_versions", "Service changelogs and version pins"),
+    (
+        "session_resume",
+        "Resume tokens restoring dashboard, game and payment state",
+    ),
+    (
+        "source_distribution",
+        "Content-addressed release tarballs replicated between peers",
+    ),
+    ("subscriptions", "Rate plans and subscription quotas"),
+    ("supervisor", "Background task supervision"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModuleStatus {
+    Ok,
+    Degraded,
+    Idle, // compiled in, nothing to do yet
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModuleHealth {
+    pub name: String,
+    pub description: String,
+    pub status: ModuleStatus,
+    pub detail: String,
+}
+
+/// Every compiled module, with what the caller observed about it; modules
+/// nobody reported on are simply Ok
+pub fn modules(mut observed: HashMap<&str, (ModuleStatus, String)>) -> Vec<ModuleHealth> {
+    MODULES
+        .iter()
+        .map(|(name, description)| {
+            let (status, detail) = observed
+                .remove(name)
+                .unwrap_or((ModuleStatus::Ok, "compiled in".to_string()));
+            ModuleHealth {
+                name: name.to_string(),
+                description: description.to_string(),
+                status,
+                detail,
+            }
+        })
+        .collect()
+}
+
+fn env_set(name: &str) -> bool {
+    std::env::var(name).is_ok_and(|value| !value.is_empty())
+}
+
+/// Optional behaviour switched on by configuration. Only whether each is on
+/// is reported, never the values behind it.
+pub fn feature_flags() -> BTreeMap<&'static str, bool> {
+    BTreeMap::from([
+        ("debug_build", cfg!(debug_assertions)),
+        ("email_alerts", env_set("ZOS_ALERT_EMAIL_FROM")),
+        (
+            "telegram_alerts",
+            crate::secrets::is_set("ZOS_TELEGRAM_BOT_TOKEN"),
+        ),
+        (
+            "signed_exports",
+            crate::secrets::is_set("ZOS_EXPORT_SIGNING_KEY"),
+        ),
+        (
+            "persistent_oidc_key",
+            crate::secrets::is_set("ZOS_OIDC_SIGNING_KEY"),
+        ),
+        ("tls", env_set("ZOS_TLS_CERT")),
+        ("http_proxy", env_set("ZOS_HTTP_PROXY")),
+        ("systemd_supervision", env_set("ZOS_SYSTEMD_UNIT")),
+        ("custom_templates", env_set("ZOS_TEMPLATE_DIR")),
+    ])
+}
+
+/// A plugin manifest found in the plugin directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginEntry {
+    pub name: String,
+    pub version: String,
+    pub security_level: String,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    #[serde(default)]
+    pub event_schema_version: Option<u32>,
+}
+
+pub fn plugin_dir() -> String {
+    std::env::var("ZOS_PLUGIN_DIR").unwrap_or_else(|_| {
+        let data_dir = std::env::var("ZOS_DATA_DIR").unwrap_or_else(|_| "/tmp".to_string());
+        format!("{}/zos-plugins", data_dir)
+    })
+}
+
+/// `*.manifest.json` files in the plugin directory; unreadable ones are skipped
+pub fn plugin_inventory(dir: &str) -> Vec<PluginEntry> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut plugins: Vec<PluginEntry> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .ends_with(".manifest.json")
+        })
+        .filter_map(|entry| std::fs::read_to_string(entry.path()).ok())
+        .filter_map(|content| serde_json::from_str(&content).ok())
+        .collect();
+    plugins.sort_by(|a, b| a.name.cmp(&b.name));
+    plugins
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Two nodes configured alike report the same fingerprint
+pub fn config_fingerprint(config: &ServerConfig) -> String {
+    let canonical = serde_json::to_vec(config).unwrap_or_default();
+    hex(&Sha256::digest(&canonical))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildProvenance {
+    pub version: String,
+    pub git_hash: Option<String>,
+    pub binary_sha256: Option<String>,
+    pub sbom_sha256: Option<String>,
+}
+
+fn file_sha256(path: &std::path::Path) -> Option<String> {
+    std::fs::read(path)
+        .ok()
+        .map(|bytes| hex(&Sha256::digest(&bytes)))
+}
+
+/// The git hash comes from the build (ZOS_GIT_HASH at compile time), the
+/// environment, or the `.git-hash` file the deploy scripts write; the SBOM
+/// is whatever ZOS_SBOM_PATH points at. Hashed once per process.
+pub fn build_provenance() -> &'static BuildProvenance {
+    static PROVENANCE: OnceLock<BuildProvenance> = OnceLock::new();
+    PROVENANCE.get_or_init(|| {
+        let git_hash = option_env!("ZOS_GIT_HASH")
+            .map(str::to_string)
+            .or_else(|| std::env::var("ZOS_GIT_HASH").ok())
+            .or_else(|| std::fs::read_to_string(".git-hash").ok())
+            .map(|hash| hash.trim().to_string())
+            .filter(|hash| !hash.is_empty());
+
+        BuildProvenance {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            git_hash,
+            binary_sha256: std::env::current_exe()
+                .ok()
+                .and_then(|path| file_sha256(&path)),
+            sbom_sha256: std::env::var("ZOS_SBOM_PATH")
+                .ok()
+                .and_then(|path| file_sha256(std::path::Path::new(&path))),
+        }
+    })
+}
@@ -0,0 +1,377 @@
+// Load generation against a sandbox deployment: synthetic wallets allocate
+// ports, call services, and quote and swap through the gateway sandbox
+// AGPL-3.0 License
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, Semaphore};
+
+/// Sent on every gateway request so quotes and swaps only touch the
+/// sandbox's fake pools
+pub const SANDBOX_HEADER: &str = "X-ZOS-Sandbox";
+
+/// Requests allowed in flight before new ones are dropped; the generator is
+/// open-loop, so a slow target shows up as drops, not as a lower rate
+pub const MAX_IN_FLIGHT: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scenario {
+    AllocatePort,
+    ServiceCall,
+    Quote,
+    Swap,
+}
+
+impl Scenario {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "allocate" | "allocate-port" => Some(Self::AllocatePort),
+            "call" | "service-call" => Some(Self::ServiceCall),
+            "quote" => Some(Self::Quote),
+            "swap" => Some(Self::Swap),
+            _ => None,
+        }
+    }
+
+    fn needs_gateway(self) -> bool {
+        matches!(self, Self::Quote | Self::Swap)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LoadTestConfig {
+    pub node: String,            // zos node, e.g. a QA instance
+    pub gateway: Option<String>, // gateway serving the sandbox; quotes and swaps need it
+    pub service: String,         // wallet/service called by the service-call scenario
+    pub wallets: usize,
+    pub rps: u32,
+    pub duration_secs: u64,
+    pub mix: Vec<(Scenario, u32)>, // relative weights
+    pub report: Option<String>,    // write the JSON report here too
+}
+
+impl Default for LoadTestConfig {
+    fn default() -> Self {
+        Self {
+            node: "http://localhost:8082".to_string(),
+            gateway: None,
+            service: "loadtest/echo".to_string(),
+            wallets: 50,
+            rps: 20,
+            duration_secs: 30,
+            mix: vec![
+                (Scenario::AllocatePort, 1),
+                (Scenario::ServiceCall, 6),
+                (Scenario::Quote, 2),
+                (Scenario::Swap, 1),
+            ],
+            report: None,
+        }
+    }
+}
+
+impl LoadTestConfig {
+    /// `--node URL --gateway URL --service W/S --wallets N --rps N
+    /// --duration SECS --mix call=6,quote=2 --report PATH`
+    pub fn from_args(params: &[String]) -> Result<Self, String> {
+        let mut config = Self::default();
+        let mut args = params.iter();
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("{} needs a value", flag))?;
+            let number = |value: &str| {
+                value
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .ok_or_else(|| format!("{} must be a positive number", flag))
+            };
+            match flag.as_str() {
+                "--node" => config.node = value.trim_end_matches('/').to_string(),
+                "--gateway" => config.gateway = Some(value.trim_end_matches('/').to_string()),
+                "--service" => config.service = value.trim_matches('/').to_string(),
+                "--wallets" => config.wallets = number(value)? as usize,
+                "--rps" => config.rps = number(value)? as u32,
+                "--duration" => config.duration_secs = number(value)?,
+                "--mix" => config.mix = parse_mix(value)?,
+                "--report" => config.report = Some(value.clone()),
+                _ => return Err(format!("Unknown option {}", flag)),
+            }
+        }
+
+        if config.gateway.is_none() {
+            config.mix.retain(|(scenario, _)| !scenario.needs_gateway());
+        }
+        if config.mix.iter().all(|(_, weight)| *weight == 0) {
+            return Err("Nothing to run: give --gateway or a --mix without quote/swap".to_string());
+        }
+        if config.service.split('/').count() != 2 {
+            return Err("--service must be wallet/service".to_string());
+        }
+        Ok(config)
+    }
+}
+
+fn parse_mix(value: &str) -> Result<Vec<(Scenario, u32)>, String> {
+    value
+        .split(',')
+        .map(|entry| {
+            let (name, weight) = entry.split_once('=').unwrap_or((entry, "1"));
+            let scenario =
+                Scenario::parse(name.trim()).ok_or_else(|| format!("Unknown scenario {}", name))?;
+            let weight = weight
+                .trim()
+                .parse()
+                .map_err(|_| format!("Bad weight for {}", name))?;
+            Ok((scenario, weight))
+        })
+        .collect()
+}
+
+/// Wallets no real user has; the prefix makes their sessions easy to find
+/// and purge on the target
+pub fn synthetic_wallets(count: usize, run: &str) -> Vec<String> {
+    (0..count)
+        .map(|i| format!("loadtest{}w{:05}", run, i))
+        .collect()
+}
+
+/// Pick the scenario for request `n` so the mix holds over any window
+fn scenario_for(mix: &[(Scenario, u32)], n: u64) -> Scenario {
+    let total: u64 = mix.iter().map(|(_, weight)| *weight as u64).sum();
+    let mut slot = n % total.max(1);
+    for (scenario, weight) in mix {
+        if slot < *weight as u64 {
+            return *scenario;
+        }
+        slot -= *weight as u64;
+    }
+    mix[0].0
+}
+
+struct Sample {
+    scenario: Scenario,
+    status: Option<u16>, // None when the request never got an answer
+    latency_ms: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScenarioReport {
+    pub requests: u64,
+    pub ok: u64,
+    pub rate_limited: u64, // 429s: the limiter doing its job
+    pub errors: u64,       // other non-2xx answers and transport failures
+    pub error_rate: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+    pub statuses: BTreeMap<String, u64>, // "200", "429", "transport", ...
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LoadReport {
+    pub node: String,
+    pub gateway: Option<String>,
+    pub started_at: u64,
+    pub duration_secs: f64,
+    pub wallets: usize,
+    pub target_rps: u32,
+    pub achieved_rps: f64,
+    pub dropped: u64, // not sent because MAX_IN_FLIGHT were outstanding
+    pub scenarios: BTreeMap<Scenario, ScenarioReport>,
+}
+
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn summarize(samples: &[Sample]) -> BTreeMap<Scenario, ScenarioReport> {
+    let mut latencies: BTreeMap<Scenario, Vec<f64>> = BTreeMap::new();
+    let mut reports: BTreeMap<Scenario, ScenarioReport> = BTreeMap::new();
+    for sample in samples {
+        let report = reports.entry(sample.scenario).or_default();
+        report.requests += 1;
+        match sample.status {
+            Some(status) if (200..300).contains(&status) => report.ok += 1,
+            Some(429) => report.rate_limited += 1,
+            _ => report.errors += 1,
+        }
+        let status = sample
+            .status
+            .map_or("transport".to_string(), |status| status.to_string());
+        *report.statuses.entry(status).or_default() += 1;
+        latencies
+            .entry(sample.scenario)
+            .or_default()
+            .push(sample.latency_ms);
+    }
+
+    for (scenario, report) in reports.iter_mut() {
+        let mut sorted = latencies.remove(scenario).unwrap_or_default();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        report.error_rate = report.errors as f64 / report.requests as f64;
+        report.p50_ms = percentile(&sorted, 50.0);
+        report.p90_ms = percentile(&sorted, 90.0);
+        report.p99_ms = percentile(&sorted, 99.0);
+        report.max_ms = sorted.last().copied().unwrap_or_default();
+    }
+    reports
+}
+
+/// Refuse to send swaps anywhere but a sandbox
+async fn check_sandbox(gateway: &str) -> Result<(), String> {
+    let clients = crate::http_clients::shared();
+    let response = clients
+        .get(&format!("{}/sandbox/status", gateway))
+        .header(SANDBOX_HEADER, "true")
+        .send()
+        .await
+        .map_err(|e| format!("Gateway sandbox unreachable: {}", e))?;
+    let status: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Gateway sandbox answered garbage: {}", e))?;
+    if status["sandbox"].as_bool() != Some(true) {
+        return Err(format!("{} is not serving a sandbox", gateway));
+    }
+    Ok(())
+}
+
+async fn fire(config: &LoadTestConfig, scenario: Scenario, wallet: &str) -> Option<u16> {
+    let clients = crate::http_clients::shared();
+    let gateway = config.gateway.as_deref().unwrap_or_default();
+    let request = match scenario {
+        Scenario::AllocatePort => clients
+            .post(&format!("{}/api/v1/allocate-port", config.node))
+            .json(&serde_json::json!({ "wallet": wallet })),
+        Scenario::ServiceCall => clients
+            .post(&format!("{}/{}", config.node, config.service))
+            .header("x-wallet-address", wallet)
+            .json(&serde_json::json!({ "loadtest": true })),
+        Scenario::Quote => clients
+            .post(&format!("{}/{}/quote", gateway, config.service))
+            .header(SANDBOX_HEADER, "true")
+            .header("x-wallet-address", wallet)
+            .json(&serde_json::json!({
+                "from_token": "USDC",
+                "to_token": "SOL",
+                "amount": 1.0,
+            })),
+        Scenario::Swap => clients
+            .post(&format!("{}/{}/swap", gateway, config.service))
+            .header(SANDBOX_HEADER, "true")
+            .header("x-wallet-address", wallet)
+            .json(&serde_json::json!({
+                "from_token": "USDC",
+                "to_token": "SOL",
+                "amount": 1.0,
+                "slippage_tolerance": 0.01,
+            })),
+    };
+    // No retries: a retried request would hide the error it measures
+    request
+        .send()
+        .await
+        .ok()
+        .map(|response| response.status().as_u16())
+}
+
+/// Drive the configured mix at a fixed rate for the configured time
+pub async fn run(config: LoadTestConfig) -> Result<LoadReport, String> {
+    if let Some(gateway) = &config.gateway {
+        check_sandbox(gateway).await?;
+    }
+
+    let started_at = chrono::Utc::now().timestamp() as u64;
+    let wallets = synthetic_wallets(config.wallets, &format!("{:x}", started_at));
+    let config = Arc::new(config);
+    let in_flight = Arc::new(Semaphore::new(MAX_IN_FLIGHT));
+    let (tx, mut rx) = mpsc::unbounded_channel::<Sample>();
+
+    let started = Instant::now();
+    let deadline = started + Duration::from_secs(config.duration_secs);
+    let mut ticker = tokio::time::interval(Duration::from_secs_f64(1.0 / config.rps as f64));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Burst);
+    let mut sent = 0u64;
+    let mut dropped = 0u64;
+
+    while Instant::now() < deadline {
+        ticker.tick().await;
+        let scenario = scenario_for(&config.mix, sent);
+        let wallet = wallets[sent as usize % wallets.len()].clone();
+        sent += 1;
+
+        let Ok(permit) = in_flight.clone().try_acquire_owned() else {
+            dropped += 1;
+            continue;
+        };
+        let (config, tx) = (config.clone(), tx.clone());
+        tokio::spawn(async move {
+            let begun = Instant::now();
+            let status = fire(&config, scenario, &wallet).await;
+            let _ = tx.send(Sample {
+                scenario,
+                status,
+                latency_ms: begun.elapsed().as_secs_f64() * 1000.0,
+            });
+            drop(permit);
+        });
+    }
+    drop(tx);
+
+    let mut samples = Vec::new();
+    while let Some(sample) = rx.recv().await {
+        samples.push(sample);
+    }
+    let elapsed = started.elapsed().as_secs_f64();
+
+    Ok(LoadReport {
+        node: config.node.clone(),
+        gateway: config.gateway.clone(),
+        started_at,
+        duration_secs: elapsed,
+        wallets: wallets.len(),
+        target_rps: config.rps,
+        achieved_rps: samples.len() as f64 / elapsed,
+        dropped,
+        scenarios: summarize(&samples),
+    })
+}
+
+pub fn print_report(report: &LoadReport) {
+    println!(
+        "📈 {:.1} req/s achieved of {} targeted over {:.0}s with {} wallets ({} dropped)",
+        report.achieved_rps,
+        report.target_rps,
+        report.duration_secs,
+        report.wallets,
+        report.dropped
+    );
+    println!(
+        "   {:<14} {:>8} {:>8} {:>8} {:>8} {:>9} {:>9} {:>9} {:>9}",
+        "scenario", "requests", "ok", "429", "errors", "p50 ms", "p90 ms", "p99 ms", "max ms"
+    );
+    for (scenario, s) in &report.scenarios {
+        println!(
+            "   {:<14} {:>8} {:>8} {:>8} {:>8} {:>9.1} {:>9.1} {:>9.1} {:>9.1}",
+            format!("{:?}", scenario),
+            s.requests,
+            s.ok,
+            s.rate_limited,
+            s.errors,
+            s.p50_ms,
+            s.p90_ms,
+            s.p99_ms,
+            s.max_ms
+        );
+    }
+}
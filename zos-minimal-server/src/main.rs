@@ -1,23 +1,84 @@
 use axum::{
-    extract::{ConnectInfo, Path, State},
-    http::{header, StatusCode},
-    response::{Html, Json, Response},
-    routing::{get, post},
+    body::Body,
+    extract::{ConnectInfo, Form, Path, Query, State},
+    http::{header, Request, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Html, IntoResponse, Json, Redirect, Response,
+    },
+    routing::{any, delete, get, post, put},
     Router,
 };
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::env;
 use std::net::SocketAddr;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
-use tokio::time::interval;
 use tower::ServiceBuilder;
 use tower_http::trace::TraceLayer;
 use tracing::info;
 
+mod access_policy;
+mod activity_feed;
+mod alerting;
+mod api_versions;
+mod batch_jobs;
+mod build_orchestrator;
+mod canary;
+mod client_sdk;
+mod data_export;
+mod dependency_drift;
+mod dev_mode;
+mod distributed_tracing;
+mod doctor;
+mod http_clients;
+mod identity;
+mod introspect;
+mod load_test;
+mod maintenance;
+mod messaging;
+mod migrations;
+mod offline_sync;
+mod oidc;
+mod onboarding;
+mod port_authority;
+mod prewarm;
+mod proxy;
+mod secrets;
+mod security_headers;
+mod service_health;
+mod service_logs;
+mod service_templates;
+mod service_versions;
+mod session_resume;
+mod source_distribution;
+mod subscriptions;
+mod supervisor;
+
+use crate::activity_feed::{FeedCategory, FeedQuery, NewEvent};
+use crate::alerting::{Acknowledgement, Alert, AlertRule, Silence};
+use crate::batch_jobs::{BatchJob, JobStatus};
+use crate::build_orchestrator::{BuildRequest, BuildRun, CachedBuild, RunStatus};
+use crate::data_export::{DeletionRequest, DeletionStatus, ExportSection};
+use crate::dependency_drift::{DriftWorkItem, MirroredCrate};
+use crate::distributed_tracing::{OpenSpan, Span, SpanStore, TraceParent, TRACEPARENT};
+use crate::identity::{Identity, IdentityLink, LinkRequest, UnixChallenge};
+use crate::maintenance::{MaintenanceWindow, WindowRequest};
+use crate::messaging::{Message, MessagingSettings};
+use crate::onboarding::{OnboardingProgress, OnboardingStep};
+use crate::prewarm::{PrewarmPolicy, PrewarmState};
+use crate::service_health::{OwnerNotification, ServiceHealth};
+use crate::service_logs::{LogQuery, ServiceLogEntry, ServiceLogStore};
+use crate::service_templates::{
+    CompletedStep, FromTemplateRequest, ProbeKind, RegisteredHealthCheck, RegisteredService,
+};
+use crate::service_versions::{ServiceVersions, VersionPin};
+use crate::source_distribution::{PeerStatus, Release};
+use crate::subscriptions::{RatePlan, Subscription, SubscriptionStatus};
+
 // CLI Command Handling
 fn parse_args() -> (String, Vec<String>) {
     let args: Vec<String> = env::args().collect();
@@ -179,6 +240,44 @@ pub struct AppState {
     pub client_db: Arc<RwLock<HashMap<String, ClientRecord>>>,
     pub config: ServerConfig,
     pub tracer: ResourceTracer,
+    pub deletion_requests: Arc<RwLock<HashMap<String, DeletionRequest>>>,
+    pub services: Arc<RwLock<HashMap<String, RegisteredService>>>,
+    pub health_checks: Arc<RwLock<HashMap<String, RegisteredHealthCheck>>>,
+    pub service_health: Arc<RwLock<HashMap<String, ServiceHealth>>>,
+    pub service_dependencies: Arc<RwLock<HashMap<String, Vec<String>>>>, // service key -> keys it needs
+    pub owner_notifications: Arc<RwLock<HashMap<String, Vec<OwnerNotification>>>>,
+    pub prewarm: Arc<RwLock<HashMap<String, PrewarmState>>>,
+    pub identities: Arc<RwLock<HashMap<String, Identity>>>,
+    pub unix_challenges: Arc<RwLock<HashMap<String, UnixChallenge>>>, // by wallet
+    pub oidc: Arc<RwLock<oidc::OidcProvider>>,
+    pub proxy_client: proxy::ProxyClient,
+    pub supervisor: supervisor::Supervisor,
+    pub service_logs: ServiceLogStore,
+    pub alert_rules: Arc<RwLock<HashMap<String, AlertRule>>>,
+    pub alert_silences: Arc<RwLock<HashMap<String, Silence>>>,
+    pub alerts: Arc<RwLock<HashMap<String, Alert>>>,
+    pub rate_plans: Arc<RwLock<HashMap<String, Vec<RatePlan>>>>, // by service key
+    pub subscriptions: Arc<RwLock<HashMap<String, Subscription>>>, // by Subscription::key
+    pub spans: SpanStore,
+    pub onboarding: Arc<RwLock<HashMap<String, OnboardingProgress>>>, // by wallet
+    pub mirrors: Arc<RwLock<HashMap<String, MirroredCrate>>>,         // by crate name
+    pub drift_work_items: Arc<RwLock<HashMap<String, DriftWorkItem>>>,
+    pub build_runs: Arc<RwLock<HashMap<String, BuildRun>>>,
+    pub build_cache: Arc<RwLock<HashMap<String, CachedBuild>>>, // by crate name
+    pub messages: Arc<RwLock<HashMap<String, Message>>>,
+    pub messaging: Arc<RwLock<HashMap<String, MessagingSettings>>>, // by wallet
+    pub service_versions: Arc<RwLock<HashMap<String, ServiceVersions>>>, // by service key
+    pub version_pins: Arc<RwLock<HashMap<String, VersionPin>>>,     // by VersionPin::key
+    pub batch_jobs: Arc<RwLock<HashMap<String, BatchJob>>>,
+    pub interactive: batch_jobs::InteractiveLoad,
+    pub releases: Arc<RwLock<HashMap<String, Release>>>, // by hash
+    pub source_peers: Arc<RwLock<HashMap<String, PeerStatus>>>, // by url
+    pub maintenance_windows: Arc<RwLock<HashMap<String, MaintenanceWindow>>>,
+    pub maintenance_held: maintenance::HeldRequests,
+    pub resumable_sessions: Arc<RwLock<HashMap<String, session_resume::ResumableSession>>>, // by token hash
+    pub ports: port_authority::PortManager,
+    pub activity: activity_feed::ActivityBus,
+    pub offline_sync: Arc<RwLock<HashMap<String, VecDeque<offline_sync::ActionResult>>>>, // by wallet
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -186,6 +285,14 @@ pub struct ServerConfig {
     pub http_port: u16,
     pub domain: String,
     pub max_users: u32,
+    pub http_client: http_clients::HttpClientConfig,
+    pub batch_jobs: batch_jobs::JobLimits,
+    pub source_peers: Vec<String>,
+    pub security_headers: security_headers::SecurityHeaders,
+    pub admin_wallets: Vec<String>,      // may manage node secrets
+    pub gateway_url: Option<String>,     // gateway whose records wallet exports include
+    pub policies: zos_policy::PolicySet, // operator policies, on top of the built-in ones
+    pub prewarm_cost_credits: u64,       // charged to the owner per pre-warm
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -198,13 +305,35 @@ pub struct UserSession {
 
 impl ServerConfig {
     pub fn load() -> Self {
+        let domain = std::env::var("ZOS_DOMAIN").unwrap_or("localhost".to_string());
         Self {
             http_port: std::env::var("ZOS_HTTP_PORT")
                 .unwrap_or("8080".to_string())
                 .parse()
                 .unwrap_or(8080),
-            domain: std::env::var("ZOS_DOMAIN").unwrap_or("localhost".to_string()),
+            security_headers: security_headers::SecurityHeaders::load(&domain),
+            domain,
             max_users: 50,
+            http_client: http_clients::HttpClientConfig::load(),
+            batch_jobs: batch_jobs::JobLimits::load(),
+            source_peers: source_distribution::load_peers(),
+            admin_wallets: std::env::var("ZOS_ADMIN_WALLETS")
+                .unwrap_or_default()
+                .split(',')
+                .map(|wallet| wallet.trim().to_string())
+                .filter(|wallet| !wallet.is_empty())
+                .collect(),
+            gateway_url: std::env::var("ZOS_GATEWAY_URL")
+                .ok()
+                .filter(|url| !url.is_empty()),
+            policies: access_policy::load_operator().unwrap_or_else(|e| {
+                println!("⚠️  Ignoring operator policies: {}", e);
+                zos_policy::PolicySet::default()
+            }),
+            prewarm_cost_credits: std::env::var("ZOS_PREWARM_COST_CREDITS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(5),
         }
     }
 }
@@ -214,12 +343,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let (command, params) = parse_args();
 
     match command.as_str() {
-        "serve" => {
+        "serve" | "dev" => {
             let port = params
-                .get(0)
+                .iter()
+                .find(|p| !p.starts_with("--"))
                 .unwrap_or(&"8080".to_string())
                 .parse()
                 .unwrap_or(8080);
+            if command == "dev" || params.iter().any(|p| p == "--dev") {
+                dev_mode::init(port)?;
+            }
             serve_http(port).await?;
         }
         "deploy-qa" => {
@@ -255,6 +388,99 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         "network-status" => {
             network_status_command().await?;
         }
+        "generate-sdk" => {
+            let dir = params.first().map(String::as_str).unwrap_or("sdk");
+            for path in client_sdk::write_sdk(std::path::Path::new(dir))? {
+                println!("📝 {}", path.display());
+            }
+        }
+        "doctor" => {
+            let report = doctor::run_doctor(&ServerConfig::load()).await;
+            doctor::print_report(&report);
+            if report.overall == doctor::CheckStatus::Fail {
+                return Err("Doctor found failing checks".into());
+            }
+        }
+        "migrate" => {
+            let dry_run = params.iter().any(|p| p == "--dry-run");
+            let reports = migrations::migrate_all(&migrations::state_dir(), dry_run)?;
+            if reports.is_empty() {
+                println!("✅ All stores are at their current schema version");
+            }
+            for report in reports {
+                println!(
+                    "{} {} v{} → v{}",
+                    if report.dry_run { "🔍" } else { "🔄" },
+                    report.store,
+                    report.from_version,
+                    report.to_version
+                );
+                for step in &report.steps {
+                    println!("   {}", step);
+                }
+                if let Some(backup) = &report.backup {
+                    println!("   backup: {}", backup);
+                }
+            }
+        }
+        "build" => {
+            let request = BuildRequest {
+                release: params.iter().any(|p| p == "--release"),
+                force: params.iter().any(|p| p == "--force"),
+                crates: params
+                    .iter()
+                    .filter(|p| !p.starts_with("--"))
+                    .cloned()
+                    .collect(),
+            };
+            build_command(&request).await?;
+        }
+        "loadtest" => {
+            let config = load_test::LoadTestConfig::from_args(&params)?;
+            let report_path = config.report.clone();
+            println!(
+                "🚦 Load test: {} req/s for {}s against {}{}",
+                config.rps,
+                config.duration_secs,
+                config.node,
+                config
+                    .gateway
+                    .as_ref()
+                    .map(|gateway| format!(" and sandbox {}", gateway))
+                    .unwrap_or_default()
+            );
+            let report = load_test::run(config).await?;
+            load_test::print_report(&report);
+            if let Some(path) = report_path {
+                std::fs::write(&path, serde_json::to_string_pretty(&report)?)?;
+                println!("📝 {}", path);
+            }
+        }
+        "policy-test" => {
+            if params.is_empty() {
+                return Err("Fixture files required for policy-test".into());
+            }
+            let failures = access_policy::run_fixtures(&params, &ServerConfig::load().policies)?;
+            for failure in &failures {
+                println!("❌ {}", failure);
+            }
+            if !failures.is_empty() {
+                return Err(format!("{} policy cases failed", failures.len()).into());
+            }
+            println!("✅ All policy cases pass");
+        }
+        "secrets-exec" => {
+            let (program, args) = params
+                .split_first()
+                .ok_or("Command required for secrets-exec")?;
+            secrets::init().await?;
+            let status = std::process::Command::new(program)
+                .args(args)
+                .envs(secrets::known_env())
+                .status()
+                .map_err(|e| format!("Cannot run {}: {}", program, e))?;
+            std::process::exit(status.code().unwrap_or(1));
+        }
         "deploy-systemd" => {
             let service = params.get(0).unwrap_or(&"qa".to_string()).clone();
             let port = params
@@ -267,6 +493,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         _ => {
             println!("ZOS Server Commands:");
             println!("  serve [port]           - Start HTTP server (default: 8080)");
+            println!("  dev [port]             - Start with test wallets, in-memory state and demo services; no credentials (also serve --dev)");
             println!("  deploy-qa <hash>       - Deploy to QA with hash verification");
             println!("  deploy-prod <hash>     - Deploy to Production");
             println!("  setup-qa [port]        - Setup QA instance (default: 8082)");
@@ -274,6 +501,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             println!("  status                 - Get current git and binary hashes");
             println!("  bootstrap              - Bootstrap entire pipeline");
             println!("  network-status         - Show all known servers");
+            println!("  doctor                 - Run node diagnostics with remediation hints");
+            println!("  migrate [--dry-run]    - Migrate persisted state to the current schema");
+            println!("  build [--release] [--force] [crate...] - Build changed workspace crates in dependency order");
+            println!("  loadtest [--node URL] [--gateway URL] [--rps N] [--duration S] [--wallets N] [--mix call=6,quote=2] [--report PATH] - Load a sandbox deployment with synthetic wallets");
+            println!("  policy-test <fixture.json...> - Check policy fixtures against the node's policies");
+            println!("  secrets-exec <command...> - Run a command with the node's stored secrets in its environment");
             println!("  deploy-systemd [qa|prod] [port] - Deploy service to systemd");
         }
     }
@@ -290,24 +523,252 @@ async fn serve_http(port: u16) -> Result<(), Box<dyn std::error::Error>> {
     tracing_subscriber::fmt::init();
 
     let config = ServerConfig::load();
+    http_clients::init(&config.http_client);
+    // Before anything below resolves a secret
+    let secret_store = secrets::init().await?;
+    println!(
+        "🔐 Secrets: {} stored, master key from {:?}",
+        secret_store.names().len(),
+        secret_store.source
+    );
 
     println!("🚀 ZOS Stage 1 Server");
     println!("   Domain: {}", config.domain);
     println!("   Port: {}", config.http_port);
+    if let Some(dev) = dev_mode::active() {
+        dev_mode::print_banner(dev);
+    }
 
     let state = AppState {
         user_sessions: Arc::new(RwLock::new(HashMap::new())),
         client_db: Arc::new(RwLock::new(HashMap::new())),
         config: config.clone(),
         tracer: ResourceTracer::new(),
+        deletion_requests: Arc::new(RwLock::new(HashMap::new())),
+        services: Arc::new(RwLock::new(HashMap::new())),
+        health_checks: Arc::new(RwLock::new(HashMap::new())),
+        service_health: Arc::new(RwLock::new(HashMap::new())),
+        service_dependencies: Arc::new(RwLock::new(HashMap::new())),
+        owner_notifications: Arc::new(RwLock::new(HashMap::new())),
+        prewarm: Arc::new(RwLock::new(HashMap::new())),
+        identities: Arc::new(RwLock::new(HashMap::new())),
+        unix_challenges: Arc::new(RwLock::new(HashMap::new())),
+        oidc: Arc::new(RwLock::new(oidc::OidcProvider::new(&config.domain))),
+        proxy_client: proxy::client(),
+        supervisor: supervisor::Supervisor::default(),
+        service_logs: ServiceLogStore::default(),
+        alert_rules: Arc::new(RwLock::new(HashMap::new())),
+        alert_silences: Arc::new(RwLock::new(HashMap::new())),
+        alerts: Arc::new(RwLock::new(HashMap::new())),
+        rate_plans: Arc::new(RwLock::new(HashMap::new())),
+        subscriptions: Arc::new(RwLock::new(HashMap::new())),
+        spans: SpanStore::default(),
+        onboarding: Arc::new(RwLock::new(HashMap::new())),
+        mirrors: Arc::new(RwLock::new(HashMap::new())),
+        drift_work_items: Arc::new(RwLock::new(HashMap::new())),
+        build_runs: Arc::new(RwLock::new(HashMap::new())),
+        build_cache: Arc::new(RwLock::new(HashMap::new())),
+        messages: Arc::new(RwLock::new(HashMap::new())),
+        messaging: Arc::new(RwLock::new(HashMap::new())),
+        service_versions: Arc::new(RwLock::new(HashMap::new())),
+        version_pins: Arc::new(RwLock::new(HashMap::new())),
+        batch_jobs: Arc::new(RwLock::new(HashMap::new())),
+        interactive: batch_jobs::InteractiveLoad::default(),
+        releases: Arc::new(RwLock::new(HashMap::new())),
+        source_peers: Arc::new(RwLock::new(HashMap::new())),
+        maintenance_windows: Arc::new(RwLock::new(HashMap::new())),
+        maintenance_held: maintenance::HeldRequests::default(),
+        resumable_sessions: Arc::new(RwLock::new(HashMap::new())),
+        ports: port_authority::PortManager::load(config.http_port),
+        activity: activity_feed::ActivityBus::default(),
+        offline_sync: Arc::new(RwLock::new(HashMap::new())),
+    };
+
+    // Dev mode starts from the same seed every time and never reads state;
+    // otherwise refuse to start on state we cannot read rather than overwrite it
+    let reports = match dev_mode::active() {
+        Some(dev) => {
+            dev_mode::seed(&state, dev).await?;
+            Vec::new()
+        }
+        None => migrations::load_state(&state).await?,
     };
+    for report in reports {
+        println!(
+            "🔄 Migrated {} v{} → v{} (backup: {})",
+            report.store,
+            report.from_version,
+            report.to_version,
+            report.backup.unwrap_or_default()
+        );
+    }
+    batch_jobs::mark_interrupted(
+        &mut *state.batch_jobs.write().await,
+        chrono::Utc::now().timestamp() as u64,
+    );
+    register_ports(&state).await;
+
+    // The public API; unversioned /api/* and /traces/* paths reach it through
+    // the api_versions shim until their sunset
+    let api_v1 = Router::new()
+        .route("/allocate-port", post(allocate_port))
+        .route("/ports", get(port_ledger))
+        .route("/status/:wallet", get(user_status))
+        .route("/doctor", get(doctor_report))
+        .route("/introspect", get(introspect_node))
+        .route("/tasks", get(list_tasks))
+        .route("/builds", get(list_builds).post(start_build))
+        .route("/builds/:id", get(get_build))
+        .route("/jobs", get(list_jobs).post(submit_job))
+        .route("/jobs/:id", get(get_job).delete(cancel_job))
+        .route(
+            "/maintenance",
+            get(list_maintenance_windows).post(schedule_maintenance_window),
+        )
+        .route("/maintenance/:id", delete(cancel_maintenance_window))
+        .route("/sessions/resume-tokens", post(issue_resume_token))
+        .route(
+            "/sessions/current",
+            put(save_session_snapshot).delete(revoke_resume_token),
+        )
+        .route("/sessions/resume", post(resume_session))
+        .route("/sessions/sync", post(sync_offline_actions))
+        .route("/secrets", get(list_secrets))
+        .route(
+            "/secrets/:name",
+            get(get_secret).put(put_secret).delete(delete_secret),
+        )
+        .route("/sdk/spec", get(sdk_spec))
+        .route("/sdk/rust", get(sdk_rust))
+        .route("/sdk/rust/Cargo.toml", get(sdk_rust_manifest))
+        .route("/sdk/typescript", get(sdk_typescript))
+        .route("/alerts", get(list_alerts))
+        .route(
+            "/alerts/rules",
+            get(list_alert_rules).post(create_alert_rule),
+        )
+        .route("/alerts/rules/:id", delete(delete_alert_rule))
+        .route("/alerts/silences", get(list_silences).post(create_silence))
+        .route("/alerts/silences/:id", delete(delete_silence))
+        .route("/alerts/:id/ack", post(acknowledge_alert))
+        .route("/mirrors", get(list_mirrors))
+        .route("/mirrors/work-items", get(list_drift_work_items))
+        .route("/mirrors/:name", put(set_mirror).delete(delete_mirror))
+        .route("/services", get(list_marketplace_services))
+        .route("/services/templates", get(list_service_templates))
+        .route("/services/:wallet/health", get(wallet_service_health))
+        .route(
+            "/services/:wallet/dependencies",
+            get(wallet_dependency_graph),
+        )
+        .route(
+            "/services/:wallet/:service/dependencies",
+            get(service_dependencies).put(set_service_dependencies),
+        )
+        .route(
+            "/services/:wallet/:service/prewarm",
+            get(prewarm_status).put(set_prewarm_policy),
+        )
+        .route(
+            "/services/from-template",
+            post(create_service_from_template),
+        )
+        .route(
+            "/services/:wallet/:service/plans",
+            get(list_rate_plans).put(set_rate_plans),
+        )
+        .route(
+            "/services/:wallet/:service/subscription",
+            post(subscribe)
+                .put(change_subscription_plan)
+                .delete(cancel_subscription),
+        )
+        .route(
+            "/services/:wallet/:service/versions",
+            get(list_service_versions).post(publish_service_version),
+        )
+        .route(
+            "/services/:wallet/:service/versions/:version/deprecate",
+            post(deprecate_service_version),
+        )
+        .route(
+            "/services/:wallet/:service/canary",
+            get(canary_status).post(start_canary),
+        )
+        .route(
+            "/services/:wallet/:service/canary/promote",
+            post(promote_canary),
+        )
+        .route(
+            "/services/:wallet/:service/canary/rollback",
+            post(rollback_canary),
+        )
+        .route(
+            "/services/:wallet/:service/pin",
+            get(get_version_pin)
+                .put(pin_service_version)
+                .delete(unpin_service_version),
+        )
+        .route("/subscriptions", get(list_subscriptions))
+        .route("/onboarding/:wallet", get(onboarding_status))
+        .route("/onboarding/:wallet/claim", post(claim_onboarding_rewards))
+        .route("/identity/:wallet", get(get_identity))
+        .route("/messages", post(send_message))
+        .route("/messages/:wallet/key", get(get_messaging_key))
+        .route("/messages/:wallet/settings", put(set_messaging_settings))
+        .route(
+            "/messages/:wallet/blocked/:other",
+            put(block_wallet).delete(unblock_wallet),
+        )
+        .route("/messages/:wallet/inbox", get(get_inbox))
+        .route("/messages/:wallet/inbox/:id", delete(delete_message))
+        .route("/messages/:wallet/inbox/:id/read", post(mark_message_read))
+        .route("/feed/events", post(ingest_feed_events))
+        .route("/feed/:wallet", get(activity_feed))
+        .route("/identity/:wallet/link", post(link_identity))
+        .route(
+            "/identity/:wallet/unix-challenge",
+            post(unix_link_challenge),
+        )
+        .route("/identity/:wallet/link/:kind", delete(unlink_identity))
+        .route("/export/verify", post(verify_export_manifest))
+        .route("/export/:wallet", get(export_wallet_data))
+        .route(
+            "/export/:wallet/delete",
+            get(deletion_status).post(request_deletion),
+        )
+        .route("/traces/:trace_id", get(view_trace))
+        .route("/traces/spans", post(collect_spans))
+        .merge(match dev_mode::active() {
+            Some(_) => dev_mode::routes(),
+            None => Router::new(),
+        });
+
+    // Subscribers' calls are counted against their plan before routing on,
+    // and batch jobs hold back while they are in flight
+    let service_calls = Router::new()
+        .route("/:wallet/:service", any(service_call))
+        .route("/:wallet/:service/*rest", any(service_call_path))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            subscriptions::enforce_quota,
+        ))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            batch_jobs::track_interactive,
+        ));
 
     let app = Router::new()
         .route("/", get(homepage))
         .route("/health", get(health))
         .route("/dashboard/:wallet", get(dashboard))
-        .route("/api/allocate-port", post(allocate_port))
-        .route("/api/status/:wallet", get(user_status))
+        .nest("/api/v1", api_v1)
+        .route("/.well-known/openid-configuration", get(oidc_discovery))
+        .route("/oauth/jwks", get(oidc_jwks))
+        .route("/oauth/clients", post(register_oidc_client))
+        .route("/oauth/authorize", get(oidc_authorize).post(oidc_consent))
+        .route("/oauth/token", post(oidc_token))
+        .route("/oauth/userinfo", get(oidc_userinfo))
         .route("/deploy", post(deploy_zos2))
         .route("/rebuild", post(rebuild_self))
         .route("/update-self", post(update_self_systemd))
@@ -329,8 +790,27 @@ async fn serve_http(port: u16) -> Result<(), Box<dyn std::error::Error>> {
         .route("/install/:branch", get(serve_installer_branch))
         .route("/download/binary", get(serve_binary))
         .route("/tarball", get(serve_tarball))
+        .route("/releases", get(list_releases))
+        .route("/releases/latest", get(latest_release))
+        .route("/blobs/:hash", get(serve_blob))
         .route("/security/clients", get(list_clients))
-        .route("/:wallet/:service", get(service_call))
+        // Service responses are the owners' to secure; only our pages get these
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            security_headers::apply,
+        ))
+        .merge(service_calls)
+        .route("/:wallet/:service/logs", get(service_logs))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            maintenance::gate,
+        ))
+        // In dev mode the mock cloud answers deploys and host scripts
+        .layer(axum::middleware::from_fn(dev_mode::mock_provisioning))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            distributed_tracing::trace_requests,
+        ))
         .layer(ServiceBuilder::new().layer(TraceLayer::new_for_http()))
         .with_state(state.clone());
 
@@ -341,10 +821,116 @@ async fn serve_http(port: u16) -> Result<(), Box<dyn std::error::Error>> {
     let listener = tokio::net::TcpListener::bind(&addr).await?;
     println!("🌐 Server running on {}", addr);
 
-    tokio::select! {
-        _ = axum::serve(listener, app) => {},
-        _ = background_tasks(state) => {}
-    }
+    let tasks = &state.supervisor;
+    tasks
+        .spawn(
+            "health-probes",
+            state.clone(),
+            Duration::from_secs(5),
+            Duration::from_secs(60),
+            supervisor::RestartPolicy::Always,
+            service_health::probe_due,
+        )
+        .await;
+    tasks
+        .spawn(
+            "prewarm",
+            state.clone(),
+            Duration::from_secs(60),
+            Duration::from_secs(120),
+            supervisor::RestartPolicy::Always,
+            prewarm::prewarm_due,
+        )
+        .await;
+    tasks
+        .spawn(
+            "housekeeping",
+            state.clone(),
+            Duration::from_secs(60),
+            Duration::from_secs(30),
+            supervisor::RestartPolicy::OnFailure { max_failures: 10 },
+            housekeeping,
+        )
+        .await;
+    tasks
+        .spawn(
+            "alerting",
+            state.clone(),
+            Duration::from_secs(30),
+            Duration::from_secs(120),
+            supervisor::RestartPolicy::Always,
+            alerting::evaluate_due,
+        )
+        .await;
+    tasks
+        .spawn(
+            "subscription-billing",
+            state.clone(),
+            Duration::from_secs(60),
+            Duration::from_secs(30),
+            supervisor::RestartPolicy::Always,
+            subscriptions::bill_due,
+        )
+        .await;
+    tasks
+        .spawn(
+            "dependency-drift",
+            state.clone(),
+            Duration::from_secs(300),
+            Duration::from_secs(300),
+            supervisor::RestartPolicy::Always,
+            dependency_drift::check_due,
+        )
+        .await;
+    tasks
+        .spawn(
+            "batch-jobs",
+            state.clone(),
+            Duration::from_secs(5),
+            Duration::from_secs(60),
+            supervisor::RestartPolicy::Always,
+            batch_jobs::schedule,
+        )
+        .await;
+    tasks
+        .spawn(
+            "source-peers",
+            state.clone(),
+            Duration::from_secs(300),
+            Duration::from_secs(600),
+            supervisor::RestartPolicy::Always,
+            source_distribution::sync_peers,
+        )
+        .await;
+    tasks
+        .spawn(
+            "maintenance",
+            state.clone(),
+            Duration::from_secs(5),
+            Duration::from_secs(60),
+            supervisor::RestartPolicy::Always,
+            maintenance::check_windows,
+        )
+        .await;
+    tasks
+        .spawn(
+            "canary-analysis",
+            state.clone(),
+            Duration::from_secs(60),
+            Duration::from_secs(60),
+            supervisor::RestartPolicy::Always,
+            canary::analyze_due,
+        )
+        .await;
+
+    // Versions are resolved before routing so shimmed paths reach the
+    // versioned handlers
+    let app = tower::Layer::layer(&axum::middleware::from_fn(api_versions::negotiate), app);
+    axum::serve(
+        listener,
+        axum::ServiceExt::<axum::http::Request<axum::body::Body>>::into_make_service(app),
+    )
+    .await?;
 
     Ok(())
 }
@@ -362,7 +948,8 @@ async fn homepage() -> Html<&'static str> {
         <ul>
             <li><code>GET /health</code> - Health check</li>
             <li><code>GET /dashboard/{wallet}</code> - User dashboard</li>
-            <li><code>POST /api/allocate-port</code> - Allocate port</li>
+            <li><code>POST /api/v1/allocate-port</code> - Allocate port</li>
+            <li><code>GET /api/v1/ports</code> - Port partitions of the instances on this host</li>
             <li><code>GET /{wallet}/{service}</code> - Call service</li>
         </ul>
 
@@ -424,157 +1011,4527 @@ async fn health() -> Json<serde_json::Value> {
         "unknown".to_string()
     };
 
-    let cwd = std::env::current_dir()
-        .map(|p| p.to_string_lossy().to_string())
-        .unwrap_or_else(|_| "unknown".to_string());
+    let cwd = std::env::current_dir()
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| "unknown".to_string());
+
+    let port = std::env::var("ZOS_HTTP_PORT").unwrap_or_else(|_| "8080".to_string());
+
+    Json(serde_json::json!({
+        "status": "healthy",
+        "version": "1.0.0-stage1",
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "env": {
+            "cwd": cwd,
+            "port": port,
+            "pid": std::process::id(),
+            "binary_path": binary_path.to_string_lossy()
+        },
+        "git": {
+            "commit": git_commit,
+            "commit_short": git_commit_short,
+            "branch": git_branch,
+            "age": commit_age
+        },
+        "binary": {
+            "hash": binary_hash,
+            "hash_short": binary_hash_short
+        }
+    }))
+}
+
+async fn dashboard(Path(wallet): Path<String>) -> Html<String> {
+    Html(format!(
+        r#"
+    <html>
+    <head><title>ZOS Dashboard - {}</title></head>
+    <body style="font-family: Arial; margin: 0; padding: 20px; background: #f5f5f5;">
+        <div id="maintenance-banner" style="display: none; background: #FFF3CD; color: #664D03; padding: 12px 20px; border-radius: 8px; margin-bottom: 20px;"></div>
+        <div id="offline-banner" style="display: none; background: #E2E3E5; color: #41464B; padding: 12px 20px; border-radius: 8px; margin-bottom: 20px;"></div>
+        <ul id="sync-conflicts" style="display: none; background: #F8D7DA; color: #842029; padding: 12px 20px 12px 40px; border-radius: 8px; margin-bottom: 20px;"></ul>
+        <h1>🎯 ZOS Dashboard</h1>
+        <p>Wallet: <code>{}</code></p>
+
+        <div style="background: white; padding: 20px; border-radius: 8px; margin: 20px 0;">
+            <h3>📊 Status</h3>
+            <p>Credits: <strong id="status-credits">100</strong></p>
+            <p>Port: <strong id="status-port">None allocated</strong></p>
+            <button onclick="allocatePort()" style="background: #4CAF50; color: white; border: none; padding: 10px 20px; border-radius: 4px; cursor: pointer;">
+                Allocate Port
+            </button>
+        </div>
+
+        <div style="background: white; padding: 20px; border-radius: 8px; margin: 20px 0;">
+            <h3>🎮 Free Services</h3>
+            <button onclick="callService('pi')" style="margin: 5px; padding: 8px 16px; border: 1px solid #ddd; border-radius: 4px; cursor: pointer;">
+                🥧 Calculate Pi
+            </button>
+            <button onclick="callService('fibonacci')" style="margin: 5px; padding: 8px 16px; border: 1px solid #ddd; border-radius: 4px; cursor: pointer;">
+                🐰 Fibonacci
+            </button>
+            <button onclick="callService('primes')" style="margin: 5px; padding: 8px 16px; border: 1px solid #ddd; border-radius: 4px; cursor: pointer;">
+                🎭 Primes
+            </button>
+        </div>
+
+        <div style="background: white; padding: 20px; border-radius: 8px; margin: 20px 0;">
+            <h3>🩺 My Services</h3>
+            <ul id="service-health"><li>Loading...</li></ul>
+        </div>
+
+        <div style="background: white; padding: 20px; border-radius: 8px; margin: 20px 0;">
+            <h3>🧩 Dependencies</h3>
+            <svg id="dependency-graph" width="100%" height="0"></svg>
+            <p id="dependency-empty">No dependencies declared</p>
+        </div>
+
+        <div style="background: white; padding: 20px; border-radius: 8px; margin: 20px 0;">
+            <h3>📰 Activity</h3>
+            <div id="feed-filters"></div>
+            <ul id="activity-feed"><li>Loading...</li></ul>
+            <button id="feed-more" onclick="loadFeed(false)" style="display: none; padding: 8px 16px; border: 1px solid #ddd; border-radius: 4px; cursor: pointer;">
+                Load more
+            </button>
+        </div>
+
+        <script>
+            // Offline use: each panel's last answer is kept in IndexedDB and
+            // shown while the network is gone. Actions taken meanwhile wait in
+            // an outbox and are replayed on reconnect; the server reports any
+            // that no longer fit as conflicts.
+            const offlineWallet = '{}';
+            const offlineDb = new Promise((resolve, reject) => {{
+                const open = indexedDB.open('zos-dashboard', 1);
+                open.onupgradeneeded = () => {{
+                    open.result.createObjectStore('cache');
+                    open.result.createObjectStore('outbox', {{ keyPath: 'id' }});
+                }};
+                open.onsuccess = () => resolve(open.result);
+                open.onerror = () => reject(open.error);
+            }});
+            function idb(store, mode, op) {{
+                return offlineDb.then(db => new Promise((resolve, reject) => {{
+                    const request = op(db.transaction(store, mode).objectStore(store));
+                    request.onsuccess = () => resolve(request.result);
+                    request.onerror = () => reject(request.error);
+                }}));
+            }}
+            let offlineSince = null;
+            // The response and its JSON; offline, no response and the saved JSON
+            async function cachedJson(name, url, options) {{
+                const key = offlineWallet + ':' + name;
+                try {{
+                    const response = await fetch(url, options);
+                    const result = await response.json();
+                    if (response.ok) idb('cache', 'readwrite', s => s.put({{ at: Date.now(), result }}, key)).catch(() => {{}});
+                    return {{ response, result }};
+                }} catch (e) {{
+                    const saved = await idb('cache', 'readonly', s => s.get(key)).catch(() => null);
+                    if (!saved) throw e;
+                    offlineSince = Math.min(offlineSince || saved.at, saved.at);
+                    updateOfflineBanner();
+                    return {{ response: null, result: saved.result }};
+                }}
+            }}
+            async function outbox() {{
+                const actions = await idb('outbox', 'readonly', s => s.getAll()).catch(() => []);
+                return actions.filter(a => a.wallet === offlineWallet).sort((a, b) => a.queued_at - b.queued_at);
+            }}
+            async function queueAction(action) {{
+                action.wallet = offlineWallet;
+                action.id = crypto.randomUUID();
+                action.queued_at = Math.floor(Date.now() / 1000);
+                await idb('outbox', 'readwrite', s => s.put(action));
+                updateOfflineBanner();
+            }}
+            async function updateOfflineBanner() {{
+                const waiting = (await outbox()).length;
+                const banner = document.getElementById('offline-banner');
+                const offline = !navigator.onLine || offlineSince;
+                banner.style.display = offline || waiting ? 'block' : 'none';
+                banner.textContent = (offline
+                    ? '📴 Offline' + (offlineSince ? ', showing what was saved at ' + new Date(offlineSince).toLocaleString() : '')
+                    : '🔁 Back online')
+                    + (waiting ? '. ' + waiting + ' action(s) waiting to sync' : '');
+            }}
+            async function flushOutbox() {{
+                const token = localStorage.getItem(resumeKey);
+                const actions = (await outbox()).slice(0, 50);
+                if (!token || !actions.length) return;
+                let result;
+                try {{
+                    const response = await fetch('/api/v1/sessions/sync', {{
+                        method: 'POST',
+                        headers: {{ 'Content-Type': 'application/json', 'X-Resume-Token': token }},
+                        body: JSON.stringify({{ actions }})
+                    }});
+                    if (!response.ok) return;
+                    result = await response.json();
+                }} catch (e) {{
+                    return;
+                }}
+                const conflicts = document.getElementById('sync-conflicts');
+                for (const r of result.results) {{
+                    await idb('outbox', 'readwrite', s => s.delete(r.id));
+                    if (r.kind === 'save_snapshot' && r.server_value && r.server_value.version) resumeVersion = r.server_value.version;
+                    if (r.outcome === 'applied') continue;
+                    const item = document.createElement('li');
+                    item.textContent = (r.outcome === 'conflict' ? '⚠️ Conflict: ' : '❌ Not applied: ')
+                        + r.kind.replace('_', ' ') + ' from ' + new Date(r.queued_at * 1000).toLocaleString() + ' - ' + r.detail;
+                    conflicts.appendChild(item);
+                    conflicts.style.display = 'block';
+                }}
+                offlineSince = null;
+                loadStatus();
+                updateOfflineBanner();
+            }}
+            window.addEventListener('offline', updateOfflineBanner);
+
+            let knownPort = null;
+            async function loadStatus() {{
+                const {{ result }} = await cachedJson('status', '/api/v1/status/' + offlineWallet);
+                if (result.status === 'not_found') return;
+                knownPort = result.allocated_port;
+                document.getElementById('status-credits').textContent = result.credits;
+                document.getElementById('status-port').textContent = knownPort || 'None allocated';
+            }}
+            loadStatus();
+
+            async function allocatePort() {{
+                try {{
+                    const response = await fetch('/api/v1/allocate-port', {{
+                        method: 'POST',
+                        headers: {{ 'Content-Type': 'application/json' }},
+                        body: JSON.stringify({{ wallet: '{}' }})
+                    }});
+                    const result = await response.json();
+                    alert('Port allocated: ' + result.port);
+                    location.reload();
+                }} catch (e) {{
+                    if (navigator.onLine && !offlineSince) return alert('Error: ' + e.message);
+                    await queueAction({{ kind: 'allocate_port', expected_port: knownPort }});
+                    alert('📴 Offline: the port will be allocated when you reconnect');
+                }}
+            }}
+
+            async function loadServiceHealth() {{
+                const {{ result }} = await cachedJson('services', '/api/v1/services/{}/health');
+                const list = document.getElementById('service-health');
+                list.innerHTML = result.services.length ? '' : '<li>No services yet</li>';
+                for (const s of result.services) {{
+                    const item = document.createElement('li');
+                    item.textContent = s.service_name + ' (port ' + s.port + '): ' + s.health.state
+                        + (s.health.last_error ? ' - ' + s.health.last_error : '')
+                        + (s.cascade.status === 'degraded' ? ' ⚠️ degraded by ' + s.cascade.causes.join(', ') : '');
+                    list.appendChild(item);
+                }}
+                for (const n of result.notifications.slice(-5)) {{
+                    const item = document.createElement('li');
+                    item.textContent = '🔔 ' + n.service_key + ' ' + n.message;
+                    list.appendChild(item);
+                }}
+            }}
+            loadServiceHealth();
+
+            // Columns by depth: a wallet's own services on the left, what
+            // they depend on to the right
+            async function loadDependencyGraph() {{
+                const {{ result: graph }} = await cachedJson('dependencies', '/api/v1/services/{}/dependencies');
+                if (!graph.edges.length) return;
+                document.getElementById('dependency-empty').style.display = 'none';
+
+                const depth = {{}};
+                for (const n of graph.nodes) depth[n.service_key] = 0;
+                for (let changed = true, rounds = 0; changed && rounds < graph.nodes.length; rounds++) {{
+                    changed = false;
+                    for (const e of graph.edges) {{
+                        if (depth[e.to] < depth[e.from] + 1) {{ depth[e.to] = depth[e.from] + 1; changed = true; }}
+                    }}
+                }}
+                const rows = {{}};
+                const pos = {{}};
+                for (const n of graph.nodes) {{
+                    const d = depth[n.service_key];
+                    rows[d] = (rows[d] || 0) + 1;
+                    pos[n.service_key] = {{ x: 20 + d * 220, y: 30 + (rows[d] - 1) * 50 }};
+                }}
+                const colors = {{ healthy: '#4CAF50', degraded: '#FF9800', down: '#F44336', unknown: '#9E9E9E' }};
+                const esc = s => s.replace(/[&<>"]/g, c => ({{ '&': '&amp;', '<': '&lt;', '>': '&gt;', '"': '&quot;' }})[c]);
+                const svg = document.getElementById('dependency-graph');
+                svg.setAttribute('height', 20 + Math.max(...Object.values(rows)) * 50);
+                let markup = '';
+                for (const e of graph.edges) {{
+                    const a = pos[e.from], b = pos[e.to];
+                    markup += `<line x1="${{a.x + 180}}" y1="${{a.y}}" x2="${{b.x}}" y2="${{b.y}}" stroke="gray"/>`;
+                }}
+                for (const n of graph.nodes) {{
+                    const p = pos[n.service_key];
+                    const status = n.cascade.status;
+                    const title = status === 'degraded' ? 'caused by ' + n.cascade.causes.join(', ') : status;
+                    markup += `<g><title>${{esc(title)}}</title>`
+                        + `<rect x="${{p.x}}" y="${{p.y - 15}}" width="180" height="30" rx="4" fill="${{colors[status]}}" opacity="${{n.owned ? 1 : 0.6}}"/>`
+                        + `<text x="${{p.x + 8}}" y="${{p.y + 5}}" fill="white" font-size="12">${{esc(n.service_key.slice(-24))}}</text></g>`;
+                }}
+                svg.innerHTML = markup;
+            }}
+            loadDependencyGraph();
+
+            async function loadMaintenance() {{
+                const response = await fetch('/api/v1/maintenance');
+                const result = await response.json();
+                const window_ = result.active || result.next;
+                if (!window_) return;
+                const banner = document.getElementById('maintenance-banner');
+                banner.style.display = 'block';
+                const tick = () => {{
+                    const now = Math.floor(Date.now() / 1000);
+                    const target = result.active ? window_.ends_at : window_.starts_at;
+                    const left = Math.max(0, target - now);
+                    const clock = Math.floor(left / 3600) + 'h ' + Math.floor(left % 3600 / 60) + 'm ' + left % 60 + 's';
+                    banner.textContent = (result.active ? '🛠️ Maintenance in progress, ends in ' : '🛠️ Maintenance starts in ')
+                        + clock + ': ' + window_.reason;
+                }};
+                tick();
+                setInterval(tick, 1000);
+            }}
+            loadMaintenance();
+
+            // Activity feed, read with an `activity:read` token the wallet
+            // signed in for; filters narrow it to some categories
+            const feedWallet = '{}';
+            const feedCategories = {{
+                payments: '💸 Payments', commissions: '🤝 Commissions', games: '🎮 Games',
+                vouches: '🫱 Vouches', deployments: '🚀 Deployments', governance: '🗳️ Governance'
+            }};
+            const feedSelected = new Set();
+            let feedCursor = null;
+            for (const [category, label] of Object.entries(feedCategories)) {{
+                const button = document.createElement('button');
+                button.textContent = label;
+                button.style.cssText = 'margin: 0 5px 10px 0; padding: 6px 12px; border: 1px solid #ddd; border-radius: 4px; cursor: pointer; background: white;';
+                button.onclick = () => {{
+                    if (feedSelected.has(category)) feedSelected.delete(category); else feedSelected.add(category);
+                    button.style.background = feedSelected.has(category) ? '#E3F2FD' : 'white';
+                    loadFeed(true);
+                }};
+                document.getElementById('feed-filters').appendChild(button);
+            }}
+            async function loadFeed(reset) {{
+                const list = document.getElementById('activity-feed');
+                const more = document.getElementById('feed-more');
+                if (reset) feedCursor = null;
+                const params = new URLSearchParams({{ limit: '20' }});
+                if (feedSelected.size) params.set('categories', [...feedSelected].join(','));
+                if (feedCursor) params.set('before', feedCursor);
+                const token = localStorage.getItem('zos_access_token_' + feedWallet) || '';
+                const {{ response, result: page }} = await cachedJson('feed?' + params, '/api/v1/feed/' + feedWallet + '?' + params, {{
+                    headers: {{ 'Authorization': 'Bearer ' + token }}
+                }});
+                if (response && (response.status === 401 || response.status === 403)) {{
+                    list.innerHTML = '<li>Sign in with the activity:read scope to see your activity</li>';
+                    more.style.display = 'none';
+                    return;
+                }}
+                if (reset) list.innerHTML = page.events.length ? '' : '<li>No activity yet</li>';
+                for (const e of page.events) {{
+                    const item = document.createElement('li');
+                    item.textContent = new Date(e.at * 1000).toLocaleString() + ' '
+                        + feedCategories[e.category].split(' ')[0] + ' ' + e.summary
+                        + (e.amount != null ? ' (' + e.amount + ' ' + (e.token || '') + ')' : '');
+                    list.appendChild(item);
+                }}
+                feedCursor = page.next_cursor;
+                more.style.display = feedCursor ? 'inline-block' : 'none';
+            }}
+            loadFeed(true);
+
+            async function callService(service) {{
+                try {{
+                    const response = await fetch('/{}/'+service);
+                    const result = await response.json();
+                    alert(service + ' result: ' + JSON.stringify(result.result));
+                }} catch (e) {{
+                    alert('Error: ' + e.message);
+                }}
+            }}
+
+            // Resumable session: on a network switch or reload the dashboard
+            // comes back where it was
+            const resumeKey = 'zos_resume_token_' + '{}';
+            let resumeVersion = 0;
+            let resumed = {{}};
+            async function issueResumeToken() {{
+                const response = await fetch('/api/v1/sessions/resume-tokens', {{
+                    method: 'POST',
+                    headers: {{ 'Content-Type': 'application/json' }},
+                    body: JSON.stringify({{ wallet: '{}', device: navigator.userAgent.slice(0, 64) }})
+                }});
+                const result = await response.json();
+                localStorage.setItem(resumeKey, result.token);
+                resumeVersion = result.version;
+            }}
+            async function resume() {{
+                const token = localStorage.getItem(resumeKey);
+                if (!token) return issueResumeToken();
+                const {{ response, result }} = await cachedJson('session', '/api/v1/sessions/resume', {{
+                    method: 'POST', headers: {{ 'X-Resume-Token': token }}
+                }});
+                if (response && response.status === 401) return issueResumeToken();
+                resumeVersion = result.version;
+                resumed = result;
+                if (result.dashboard && result.dashboard.scroll_y) window.scrollTo(0, result.dashboard.scroll_y);
+                if (result.lapsed_payments.length) {{
+                    alert('⏳ ' + result.lapsed_payments.length + ' payment(s) expired while you were away; start them again');
+                }}
+            }}
+            async function saveSnapshot() {{
+                const token = localStorage.getItem(resumeKey);
+                if (!token) return;
+                const snapshot = {{
+                    dashboard: {{ ...resumed.dashboard, scroll_y: window.scrollY }},
+                    game_session: resumed.game_session || null,
+                    pending_payments: resumed.pending_payments || []
+                }};
+                if (!navigator.onLine) {{
+                    // Only the newest snapshot is worth replaying
+                    for (const queued of await outbox()) {{
+                        if (queued.kind === 'save_snapshot') await idb('outbox', 'readwrite', s => s.delete(queued.id));
+                    }}
+                    return queueAction({{ kind: 'save_snapshot', snapshot, base_version: resumeVersion }});
+                }}
+                fetch('/api/v1/sessions/current', {{
+                    method: 'PUT',
+                    keepalive: true,
+                    headers: {{ 'Content-Type': 'application/json', 'X-Resume-Token': token }},
+                    body: JSON.stringify({{ snapshot, base_version: resumeVersion }})
+                }}).then(r => r.json()).then(r => {{ if (r.version) resumeVersion = r.version; }}).catch(() => {{}});
+            }}
+            resume().then(flushOutbox).then(updateOfflineBanner);
+            window.addEventListener('online', async () => {{
+                await resume();
+                await flushOutbox();
+                loadServiceHealth();
+                loadFeed(true);
+            }});
+            document.addEventListener('visibilitychange', () => {{
+                if (document.visibilityState === 'hidden') saveSnapshot();
+            }});
+        </script>
+    </body>
+    </html>
+    "#,
+        wallet, wallet, wallet, wallet, wallet, wallet, wallet, wallet, wallet, wallet
+    ))
+}
+
+/// Claim this instance's partition of the host port range and record the
+/// ports restored state already uses, so other instances on the host skip them
+async fn register_ports(state: &AppState) {
+    let ports = &state.ports;
+    match ports.register(state.config.http_port) {
+        Ok(partition) => println!(
+            "🔌 {} holds ports {} (capacity {}) in {}",
+            ports.instance,
+            partition
+                .ranges
+                .iter()
+                .map(|range| format!("{}-{}", range.start, range.end))
+                .collect::<Vec<_>>()
+                .join(", "),
+            partition.capacity,
+            ports.path
+        ),
+        Err(e) => {
+            println!(
+                "⚠️  Port authority unavailable, allocations will retry it: {}",
+                e
+            );
+            return;
+        }
+    }
+
+    let services = state.services.read().await;
+    let restored = services
+        .iter()
+        .map(|(key, service)| (service.port, key.clone(), "service"))
+        .chain(
+            state
+                .user_sessions
+                .read()
+                .await
+                .values()
+                .filter_map(|session| {
+                    Some((session.allocated_port?, session.wallet_address.clone()))
+                })
+                .filter(|(port, _)| !services.values().any(|service| service.port == *port))
+                .map(|(port, wallet)| (port, wallet, "session")),
+        )
+        .collect::<Vec<_>>();
+    for (port, owner, purpose) in restored {
+        if let Err(e) = ports.reserve(port, &owner, purpose) {
+            println!("⚠️  Restored {} port clashes on this host: {}", purpose, e);
+        }
+    }
+}
+
+async fn port_ledger(
+    State(state): State<AppState>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let ledger = state.ports.ledger().map_err(|e| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({ "error": e })),
+        )
+    })?;
+    Ok(Json(serde_json::json!({
+        "instance": state.ports.instance,
+        "host_range": port_authority::HOST_RANGE,
+        "partitions": ledger.partitions,
+        "assignments": ledger.assignments,
+    })))
+}
+
+async fn allocate_port(
+    State(state): State<AppState>,
+    axum::Json(request): axum::Json<serde_json::Value>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let wallet = request.get("wallet").and_then(|w| w.as_str()).ok_or((
+        StatusCode::BAD_REQUEST,
+        Json(serde_json::json!({ "error": "A wallet is required" })),
+    ))?;
+    let port = allocate_session_port(&state, wallet).await?;
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "port": port,
+        "expires_in_seconds": 300
+    })))
+}
+
+/// Give a wallet's session a port, if the access policy allows it
+async fn allocate_session_port(
+    state: &AppState,
+    wallet: &str,
+) -> Result<u16, (StatusCode, Json<serde_json::Value>)> {
+    let mut sessions = state.user_sessions.write().await;
+    let request = zos_policy::Request::new("port:allocate")
+        .principal(match sessions.get(wallet) {
+            Some(session) => serde_json::json!({
+                "wallet": wallet,
+                "credits": session.credits,
+                "allocated_port": session.allocated_port,
+            }),
+            None => serde_json::json!({ "wallet": wallet, "credits": 100 }),
+        })
+        .context(serde_json::json!({
+            "allocated_ports": sessions.values().filter(|s| s.allocated_port.is_some()).count(),
+            "max_users": state.config.max_users,
+        }));
+    let decision = access_policy::effective(&state.config.policies).evaluate(&request);
+    if !decision.allowed {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({
+                "error": decision.reason,
+                "policies": decision.policies,
+            })),
+        ));
+    }
+
+    let port = state.ports.allocate(wallet, "session").map_err(|e| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({ "error": e })),
+        )
+    })?;
+
+    let session = sessions.entry(wallet.to_string()).or_insert(UserSession {
+        wallet_address: wallet.to_string(),
+        allocated_port: None,
+        credits: 100,
+        last_activity: chrono::Utc::now().timestamp() as u64,
+    });
+
+    session.allocated_port = Some(port);
+    session.last_activity = chrono::Utc::now().timestamp() as u64;
+
+    // Ports lapse with the session, so the step is recorded as it happens
+    onboarding::record(
+        state
+            .onboarding
+            .write()
+            .await
+            .entry(wallet.to_string())
+            .or_insert_with(|| OnboardingProgress::new(wallet)),
+        &[OnboardingStep::AllocatePort],
+    );
+
+    println!("🔌 Port {} allocated to {}", port, &wallet[..8]);
+    Ok(port)
+}
+
+async fn user_status(
+    Path(wallet): Path<String>,
+    State(state): State<AppState>,
+) -> Json<serde_json::Value> {
+    let sessions = state.user_sessions.read().await;
+
+    if let Some(session) = sessions.get(&wallet) {
+        Json(serde_json::json!({
+            "wallet": wallet,
+            "credits": session.credits,
+            "allocated_port": session.allocated_port,
+            "last_activity": session.last_activity
+        }))
+    } else {
+        Json(serde_json::json!({
+            "wallet": wallet,
+            "status": "not_found"
+        }))
+    }
+}
+
+/// GET /api/v1/sdk/spec — the route registry the clients are generated from
+async fn sdk_spec() -> Json<serde_json::Value> {
+    Json(client_sdk::spec())
+}
+
+async fn sdk_rust() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/x-rust; charset=utf-8")],
+        client_sdk::rust_client(),
+    )
+}
+
+async fn sdk_rust_manifest() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        client_sdk::rust_manifest(),
+    )
+}
+
+async fn sdk_typescript() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        client_sdk::typescript_client(),
+    )
+}
+
+async fn list_tasks(State(state): State<AppState>) -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "tasks": state.supervisor.statuses().await
+    }))
+}
+
+/// Build changed workspace crates from the command line, sharing the
+/// server's build cache
+async fn build_command(request: &BuildRequest) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = migrations::state_dir();
+    let mut cache: HashMap<String, CachedBuild> =
+        migrations::load_store(&dir, "build_cache")?.unwrap_or_default();
+    let workspace = build_orchestrator::workspace_dir();
+    let builds = build_orchestrator::plan(&workspace, request, &cache)?;
+
+    let mut failed = std::collections::HashSet::new();
+    for build in &builds {
+        let (status, duration_ms, error) =
+            build_orchestrator::build_crate(&workspace, build, request.release, &failed).await;
+        println!(
+            "🔨 {:<24} {:?} ({:?}){}",
+            build.name,
+            status,
+            build.reason,
+            duration_ms
+                .map(|ms| format!(" in {}ms", ms))
+                .unwrap_or_default()
+        );
+        if let Some(error) = error {
+            println!("{}", error);
+        }
+        match status {
+            build_orchestrator::CrateStatus::Built => {
+                cache.insert(
+                    build.name.clone(),
+                    CachedBuild {
+                        fingerprint: build.fingerprint.clone(),
+                        profile: request.profile().to_string(),
+                        built_at: chrono::Utc::now().timestamp() as u64,
+                        duration_ms: duration_ms.unwrap_or_default(),
+                    },
+                );
+            }
+            build_orchestrator::CrateStatus::Failed | build_orchestrator::CrateStatus::Skipped => {
+                failed.insert(build.name.clone());
+            }
+            _ => {}
+        }
+    }
+    migrations::save_store(&dir, "build_cache", &cache)?;
+
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("{} crates failed or were skipped", failed.len()).into())
+    }
+}
+
+/// GET /api/v1/builds — recent orchestrated builds and per-crate build
+/// times, for the CI/CD dashboard
+async fn list_builds(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let runs = state.build_runs.read().await;
+    let mut recent: Vec<serde_json::Value> = runs
+        .values()
+        .map(|run| {
+            serde_json::json!({
+                "id": run.id,
+                "profile": run.profile,
+                "status": run.status,
+                "started_at": run.started_at,
+                "finished_at": run.finished_at,
+                "crates": run.crates.len(),
+                "built": run.crates.iter().filter(|c| c.status == build_orchestrator::CrateStatus::Built).count(),
+                "cached": run.crates.iter().filter(|c| c.status == build_orchestrator::CrateStatus::Cached).count(),
+                "duration_ms": run.duration_ms(),
+            })
+        })
+        .collect();
+    recent.sort_by_key(|run| std::cmp::Reverse(run["started_at"].as_u64()));
+
+    Json(serde_json::json!({
+        "runs": recent,
+        "crate_times": build_orchestrator::crate_times(runs.values()),
+    }))
+}
+
+async fn get_build(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    match state.build_runs.read().await.get(&id) {
+        Some(run) => (StatusCode::OK, Json(serde_json::json!({ "run": run }))),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Build not found" })),
+        ),
+    }
+}
+
+/// POST /api/v1/builds — plan a workspace build and run it in the
+/// background; one build at a time
+async fn start_build(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Json(request): Json<BuildRequest>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if let Err(refusal) = require_admin(&state, &headers, "node:admin").await {
+        return refusal;
+    }
+    let workspace = build_orchestrator::workspace_dir();
+    let cache = state.build_cache.read().await.clone();
+    let planned = {
+        let workspace = workspace.clone();
+        let request = request.clone();
+        tokio::task::spawn_blocking(move || build_orchestrator::plan(&workspace, &request, &cache))
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|plan| plan)
+    };
+    let crates = match planned {
+        Ok(crates) => crates,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": e })),
+            )
+        }
+    };
+
+    let run = BuildRun {
+        id: format!("build_{}", chrono::Utc::now().timestamp_millis()),
+        profile: request.profile().to_string(),
+        status: RunStatus::Running,
+        started_at: chrono::Utc::now().timestamp() as u64,
+        finished_at: None,
+        crates,
+    };
+    {
+        let mut runs = state.build_runs.write().await;
+        if runs.values().any(|run| run.status == RunStatus::Running) {
+            return (
+                StatusCode::CONFLICT,
+                Json(serde_json::json!({ "error": "A build is already running" })),
+            );
+        }
+        runs.insert(run.id.clone(), run.clone());
+        build_orchestrator::trim_runs(&mut runs);
+    }
+
+    println!("🏗️  Build {} planned: {} crates", run.id, run.crates.len());
+    tokio::spawn(build_orchestrator::execute(
+        state.clone(),
+        run.id.clone(),
+        workspace,
+        request.release,
+    ));
+
+    (
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({ "run": run })),
+    )
+}
+
+async fn list_jobs(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let mut jobs: Vec<BatchJob> = state.batch_jobs.read().await.values().cloned().collect();
+    jobs.sort_by_key(|job| std::cmp::Reverse(job.submitted_at));
+    Json(serde_json::json!({
+        "jobs": jobs,
+        "limits": state.config.batch_jobs,
+        "pressure": batch_jobs::sample_pressure(),
+        "interactive_in_flight": state.interactive.in_flight(),
+    }))
+}
+
+async fn get_job(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    match state.batch_jobs.read().await.get(&id) {
+        Some(job) => (StatusCode::OK, Json(serde_json::json!({ "job": job }))),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Job not found" })),
+        ),
+    }
+}
+
+/// POST /api/v1/jobs — queue a spectral or dependency analysis; the
+/// batch-jobs task starts it when the node has room
+async fn submit_job(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Json(request): Json<batch_jobs::JobRequest>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if let Err(refusal) = require_admin(&state, &headers, "node:admin").await {
+        return refusal;
+    }
+    let command = match batch_jobs::job_command(&build_orchestrator::workspace_dir(), &request) {
+        Ok(command) => command,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": e })),
+            )
+        }
+    };
+
+    let job = BatchJob {
+        id: format!("job_{}", chrono::Utc::now().timestamp_millis()),
+        kind: request.kind,
+        priority: request.priority,
+        command,
+        status: JobStatus::Queued,
+        submitted_at: chrono::Utc::now().timestamp() as u64,
+        started_at: None,
+        finished_at: None,
+        pid: None,
+        pause_reason: None,
+        pauses: 0,
+        checkpoints: 0,
+        exit_code: None,
+        output_tail: Vec::new(),
+        error: None,
+    };
+    {
+        let mut jobs = state.batch_jobs.write().await;
+        if jobs.contains_key(&job.id) {
+            return (
+                StatusCode::CONFLICT,
+                Json(serde_json::json!({ "error": "A job was just submitted; try again" })),
+            );
+        }
+        jobs.insert(job.id.clone(), job.clone());
+        batch_jobs::trim_jobs(&mut jobs);
+    }
+
+    println!("🧮 Job {} queued: {:?}", job.id, job.kind);
+    (
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({ "job": job })),
+    )
+}
+
+async fn cancel_job(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if let Err(refusal) = require_admin(&state, &headers, "node:admin").await {
+        return refusal;
+    }
+    let mut jobs = state.batch_jobs.write().await;
+    let Some(job) = jobs.get_mut(&id) else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Job not found" })),
+        );
+    };
+    match batch_jobs::cancel(job) {
+        Ok(()) => (StatusCode::OK, Json(serde_json::json!({ "job": job }))),
+        Err(e) => (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({ "error": e })),
+        ),
+    }
+}
+
+/// GET /api/v1/maintenance — the active and next windows, and all scheduled ones
+async fn list_maintenance_windows(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let now = chrono::Utc::now().timestamp() as u64;
+    let windows = state.maintenance_windows.read().await;
+    let mut scheduled: Vec<&MaintenanceWindow> = windows
+        .values()
+        .filter(|window| window.cancelled_at.is_none() && window.ends_at > now)
+        .collect();
+    scheduled.sort_by_key(|window| window.starts_at);
+
+    Json(serde_json::json!({
+        "now": now,
+        "active": maintenance::active_window(&windows, now),
+        "next": maintenance::next_window(&windows, now),
+        "scheduled": scheduled,
+        "held_requests": state.maintenance_held.held()
+    }))
+}
+
+/// POST /api/v1/maintenance — schedule a window
+async fn schedule_maintenance_window(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Json(request): Json<WindowRequest>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if let Err(refusal) = require_admin(&state, &headers, "node:admin").await {
+        return refusal;
+    }
+    let now = chrono::Utc::now().timestamp() as u64;
+    let mut windows = state.maintenance_windows.write().await;
+    if let Err(e) = maintenance::validate(&windows, &request, now) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": e })),
+        );
+    }
+
+    let window = MaintenanceWindow {
+        id: format!("mw_{}", chrono::Utc::now().timestamp_millis()),
+        starts_at: request.starts_at,
+        ends_at: request.ends_at,
+        reason: request.reason,
+        queue_secs: request.queue_secs,
+        created_at: now,
+        checkpointed_at: None,
+        cancelled_at: None,
+    };
+    println!(
+        "🛠️  Maintenance window {} scheduled {}..{}: {}",
+        window.id, window.starts_at, window.ends_at, window.reason
+    );
+    windows.insert(window.id.clone(), window.clone());
+    (
+        StatusCode::CREATED,
+        Json(serde_json::json!({ "window": window })),
+    )
+}
+
+/// DELETE /api/v1/maintenance/:id — cancel a window, or end an active one early
+async fn cancel_maintenance_window(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if let Err(refusal) = require_admin(&state, &headers, "node:admin").await {
+        return refusal;
+    }
+    let mut windows = state.maintenance_windows.write().await;
+    match windows.get_mut(&id) {
+        Some(window) if window.cancelled_at.is_none() => {
+            window.cancelled_at = Some(chrono::Utc::now().timestamp() as u64);
+            println!("🛠️  Maintenance window {} cancelled", id);
+            (
+                StatusCode::OK,
+                Json(serde_json::json!({ "window": window })),
+            )
+        }
+        Some(_) => (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({ "error": "Window already cancelled" })),
+        ),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Window not found" })),
+        ),
+    }
+}
+
+fn resume_token(headers: &axum::http::HeaderMap) -> String {
+    headers
+        .get("x-resume-token")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// POST /api/v1/sessions/resume-tokens — a token for this device to resume with
+async fn issue_resume_token(
+    State(state): State<AppState>,
+    Json(request): Json<session_resume::IssueRequest>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let now = chrono::Utc::now().timestamp() as u64;
+    match session_resume::issue(&mut *state.resumable_sessions.write().await, request, now) {
+        Ok(token) => (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "token": token,
+                "idle_ttl_secs": session_resume::IDLE_TTL_SECS,
+                "version": 0
+            })),
+        ),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": e })),
+        ),
+    }
+}
+
+/// PUT /api/v1/sessions/current — store where the client is now
+async fn save_session_snapshot(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Json(request): Json<session_resume::SaveRequest>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let now = chrono::Utc::now().timestamp() as u64;
+    let mut sessions = state.resumable_sessions.write().await;
+    let session = match session_resume::lookup(&mut sessions, &resume_token(&headers), now) {
+        Ok(session) => session,
+        Err(e) => {
+            return (
+                StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({ "error": e })),
+            )
+        }
+    };
+    match session_resume::save(session, request) {
+        Ok(version) => (
+            StatusCode::OK,
+            Json(serde_json::json!({ "version": version })),
+        ),
+        Err(e) => (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({ "error": e, "version": session.version })),
+        ),
+    }
+}
+
+/// POST /api/v1/sessions/resume — everything a reconnecting client needs
+/// to pick up where it left off, in one call
+async fn resume_session(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let now = chrono::Utc::now().timestamp() as u64;
+    let session = match session_resume::lookup(
+        &mut *state.resumable_sessions.write().await,
+        &resume_token(&headers),
+        now,
+    ) {
+        Ok(session) => session.clone(),
+        Err(e) => {
+            return (
+                StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({ "error": e })),
+            )
+        }
+    };
+
+    let (pending, lapsed) = session_resume::partition_payments(&session.snapshot, now);
+    let account = state
+        .user_sessions
+        .read()
+        .await
+        .get(&session.wallet_address)
+        .cloned();
+    let maintenance =
+        maintenance::active_window(&*state.maintenance_windows.read().await, now).cloned();
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "wallet": session.wallet_address,
+            "version": session.version,
+            "dashboard": session.snapshot.dashboard,
+            "game_session": session.snapshot.game_session,
+            "pending_payments": pending,
+            "lapsed_payments": lapsed,
+            "account": account,
+            "maintenance": maintenance,
+            "idle_ttl_secs": session_resume::IDLE_TTL_SECS
+        })),
+    )
+}
+
+/// POST /api/v1/sessions/sync — replay the actions this device queued while
+/// offline, in the order it queued them. Each comes back applied, rejected
+/// or in conflict with what the server has now; none is applied twice.
+async fn sync_offline_actions(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Json(mut request): Json<offline_sync::SyncRequest>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let now = chrono::Utc::now().timestamp() as u64;
+    let token = resume_token(&headers);
+    let wallet =
+        match session_resume::lookup(&mut *state.resumable_sessions.write().await, &token, now) {
+            Ok(session) => session.wallet_address.clone(),
+            Err(e) => {
+                return (
+                    StatusCode::UNAUTHORIZED,
+                    Json(serde_json::json!({ "error": e })),
+                )
+            }
+        };
+    if let Err(e) = offline_sync::validate(&mut request) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": e })),
+        );
+    }
+
+    let mut results = Vec::new();
+    for queued in &request.actions {
+        let answered = state
+            .offline_sync
+            .read()
+            .await
+            .get(&wallet)
+            .and_then(|log| offline_sync::previous(log, &queued.id));
+        if let Some(result) = answered {
+            results.push(result);
+            continue;
+        }
+
+        let result = match &queued.action {
+            offline_sync::Action::AllocatePort { expected_port } => {
+                let current = state
+                    .user_sessions
+                    .read()
+                    .await
+                    .get(&wallet)
+                    .and_then(|session| session.allocated_port);
+                match offline_sync::port_conflict(*expected_port, current) {
+                    Some(conflict) => offline_sync::ActionResult::new(
+                        queued,
+                        offline_sync::Outcome::Conflict,
+                        conflict,
+                        now,
+                    )
+                    .with_server_value(serde_json::json!({ "allocated_port": current })),
+                    None => match allocate_session_port(&state, &wallet).await {
+                        Ok(port) => offline_sync::ActionResult::new(
+                            queued,
+                            offline_sync::Outcome::Applied,
+                            format!("Port {} allocated", port),
+                            now,
+                        )
+                        .with_server_value(serde_json::json!({ "allocated_port": port })),
+                        Err((_, Json(refusal))) => offline_sync::ActionResult::new(
+                            queued,
+                            offline_sync::Outcome::Rejected,
+                            refusal["error"].as_str().unwrap_or_default().to_string(),
+                            now,
+                        ),
+                    },
+                }
+            }
+            offline_sync::Action::SaveSnapshot {
+                snapshot,
+                base_version,
+            } => {
+                let mut sessions = state.resumable_sessions.write().await;
+                match session_resume::lookup(&mut sessions, &token, now) {
+                    Ok(session) => {
+                        let save = session_resume::SaveRequest {
+                            snapshot: snapshot.clone(),
+                            base_version: *base_version,
+                        };
+                        match session_resume::save(session, save) {
+                            Ok(version) => offline_sync::ActionResult::new(
+                                queued,
+                                offline_sync::Outcome::Applied,
+                                format!("Snapshot saved as version {}", version),
+                                now,
+                            )
+                            .with_server_value(serde_json::json!({ "version": version })),
+                            // Size refusals are not conflicts; the base version is
+                            Err(e) if base_version.is_some_and(|base| base != session.version) => {
+                                offline_sync::ActionResult::new(
+                                    queued,
+                                    offline_sync::Outcome::Conflict,
+                                    e,
+                                    now,
+                                )
+                                .with_server_value(
+                                    serde_json::json!({
+                                        "version": session.version,
+                                        "snapshot": session.snapshot,
+                                    }),
+                                )
+                            }
+                            Err(e) => offline_sync::ActionResult::new(
+                                queued,
+                                offline_sync::Outcome::Rejected,
+                                e,
+                                now,
+                            ),
+                        }
+                    }
+                    Err(e) => offline_sync::ActionResult::new(
+                        queued,
+                        offline_sync::Outcome::Rejected,
+                        e,
+                        now,
+                    ),
+                }
+            }
+        };
+        offline_sync::remember(
+            state
+                .offline_sync
+                .write()
+                .await
+                .entry(wallet.clone())
+                .or_default(),
+            result.clone(),
+        );
+        results.push(result);
+    }
+
+    let conflicts = results
+        .iter()
+        .filter(|result| result.outcome == offline_sync::Outcome::Conflict)
+        .count();
+    if conflicts > 0 {
+        println!(
+            "🔁 Offline sync for {}: {} of {} actions in conflict",
+            wallet,
+            conflicts,
+            results.len()
+        );
+    }
+    let account = state.user_sessions.read().await.get(&wallet).cloned();
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "wallet": wallet,
+            "results": results,
+            "conflicts": conflicts,
+            "account": account,
+        })),
+    )
+}
+
+/// DELETE /api/v1/sessions/current — sign this device out
+async fn revoke_resume_token(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let hash = session_resume::token_hash(&resume_token(&headers));
+    match state.resumable_sessions.write().await.remove(&hash) {
+        Some(_) => (StatusCode::OK, Json(serde_json::json!({ "revoked": true }))),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Unknown resume token" })),
+        ),
+    }
+}
+
+/// Wallet behind a `secrets:admin` access token, if it is a node admin
+async fn secrets_admin(
+    state: &AppState,
+    headers: &axum::http::HeaderMap,
+) -> Result<String, (StatusCode, Json<serde_json::Value>)> {
+    require_admin(state, headers, "secrets:admin").await
+}
+
+/// The admin wallet behind a token carrying `scope`
+async fn require_admin(
+    state: &AppState,
+    headers: &axum::http::HeaderMap,
+    scope: &str,
+) -> Result<String, (StatusCode, Json<serde_json::Value>)> {
+    let token = bearer_token(headers).unwrap_or_default();
+    let wallet = state
+        .oidc
+        .read()
+        .await
+        .authorize_bearer(&token, scope)
+        .map(|grant| grant.wallet_address)
+        .map_err(|e| {
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({ "error": e })),
+            )
+        })?;
+    if !state.config.admin_wallets.contains(&wallet) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({ "error": "Not an admin of this node (ZOS_ADMIN_WALLETS)" })),
+        ));
+    }
+    Ok(wallet)
+}
+
+fn secret_store() -> Result<&'static secrets::SecretStore, (StatusCode, Json<serde_json::Value>)> {
+    secrets::store().ok_or((
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(serde_json::json!({ "error": "Secrets store not initialized" })),
+    ))
+}
+
+/// GET /api/v1/secrets — names and versions only, plus known secrets still
+/// read from the environment
+async fn list_secrets(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if let Err(refusal) = secrets_admin(&state, &headers).await {
+        return refusal;
+    }
+    let store = match secret_store() {
+        Ok(store) => store,
+        Err(refusal) => return refusal,
+    };
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "secrets": store.list(),
+            "plaintext_in_env": secrets::plaintext_in_env(),
+            "master_key": store.source,
+        })),
+    )
+}
+
+/// GET /api/v1/secrets/:name — the opened value
+async fn get_secret(
+    Path(name): Path<String>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let admin = match secrets_admin(&state, &headers).await {
+        Ok(admin) => admin,
+        Err(refusal) => return refusal,
+    };
+    let store = match secret_store() {
+        Ok(store) => store,
+        Err(refusal) => return refusal,
+    };
+    match store.open(&name) {
+        Ok(Some(value)) => {
+            println!("🔐 Secret {} read by {}", name, admin);
+            (
+                StatusCode::OK,
+                Json(serde_json::json!({ "secret": store.info(&name), "value": value })),
+            )
+        }
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Secret not found" })),
+        ),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e })),
+        ),
+    }
+}
+
+#[derive(Deserialize)]
+struct SecretValue {
+    value: String,
+}
+
+/// PUT /api/v1/secrets/:name — create or replace, e.g. {"value":"..."}
+async fn put_secret(
+    Path(name): Path<String>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Json(req): Json<SecretValue>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let admin = match secrets_admin(&state, &headers).await {
+        Ok(admin) => admin,
+        Err(refusal) => return refusal,
+    };
+    let store = match secret_store() {
+        Ok(store) => store,
+        Err(refusal) => return refusal,
+    };
+    match store.put(&name, &req.value) {
+        Ok(info) => {
+            println!("🔐 Secret {} v{} stored by {}", name, info.version, admin);
+            (StatusCode::OK, Json(serde_json::json!({ "secret": info })))
+        }
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": e })),
+        ),
+    }
+}
+
+/// DELETE /api/v1/secrets/:name — the environment variable applies again
+async fn delete_secret(
+    Path(name): Path<String>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let admin = match secrets_admin(&state, &headers).await {
+        Ok(admin) => admin,
+        Err(refusal) => return refusal,
+    };
+    let store = match secret_store() {
+        Ok(store) => store,
+        Err(refusal) => return refusal,
+    };
+    match store.delete(&name) {
+        Ok(true) => {
+            println!("🔐 Secret {} deleted by {}", name, admin);
+            (StatusCode::OK, Json(serde_json::json!({ "deleted": name })))
+        }
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Secret not found" })),
+        ),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e })),
+        ),
+    }
+}
+
+async fn doctor_report(State(state): State<AppState>) -> Json<doctor::DoctorReport> {
+    let _trace = state.tracer.start_trace("doctor");
+    let report = doctor::run_doctor(&state.config).await;
+    _trace.finish();
+    Json(report)
+}
+
+/// GET /api/v1/introspect — modules, feature flags, plugins, config
+/// fingerprint and build provenance, for the dashboard and federating peers
+async fn introspect_node(State(state): State<AppState>) -> Json<serde_json::Value> {
+    use introspect::ModuleStatus;
+
+    let mut observed = HashMap::new();
+
+    let service_count = state.services.read().await.len();
+    let delisted = state
+        .service_health
+        .read()
+        .await
+        .values()
+        .filter(|health| health.state == service_health::HealthState::Delisted)
+        .count();
+    observed.insert(
+        "service_health",
+        (
+            if delisted > 0 {
+                ModuleStatus::Degraded
+            } else {
+                ModuleStatus::Ok
+            },
+            format!("{} services, {} delisted", service_count, delisted),
+        ),
+    );
+
+    let firing = state
+        .alerts
+        .read()
+        .await
+        .values()
+        .filter(|alert| alert.status == alerting::AlertStatus::Firing)
+        .count();
+    observed.insert(
+        "alerting",
+        (
+            if firing > 0 {
+                ModuleStatus::Degraded
+            } else {
+                ModuleStatus::Ok
+            },
+            format!("{} alerts firing", firing),
+        ),
+    );
+
+    let tasks = state.supervisor.statuses().await;
+    let unhealthy = tasks
+        .iter()
+        .filter(|task| {
+            matches!(
+                task.state,
+                supervisor::TaskState::Stalled | supervisor::TaskState::Backoff
+            )
+        })
+        .count();
+    observed.insert(
+        "supervisor",
+        (
+            if unhealthy > 0 {
+                ModuleStatus::Degraded
+            } else {
+                ModuleStatus::Ok
+            },
+            format!(
+                "{} tasks, {} stalled or backing off",
+                tasks.len(),
+                unhealthy
+            ),
+        ),
+    );
+
+    let onboarding = state.onboarding.read().await.len();
+    observed.insert(
+        "onboarding",
+        (
+            if onboarding > 0 {
+                ModuleStatus::Ok
+            } else {
+                ModuleStatus::Idle
+            },
+            format!("{} wallets onboarding", onboarding),
+        ),
+    );
+    let open_drift = state
+        .drift_work_items
+        .read()
+        .await
+        .values()
+        .filter(|item| item.resolved_at.is_none())
+        .count();
+    let mirrors = state.mirrors.read().await.len();
+    let build_runs = state.build_runs.read().await;
+    let last_build = build_runs.values().max_by_key(|run| run.started_at);
+    observed.insert(
+        "build_orchestrator",
+        match last_build {
+            None => (ModuleStatus::Idle, "no builds yet".to_string()),
+            Some(run) => (
+                if run.status == RunStatus::Failed {
+                    ModuleStatus::Degraded
+                } else {
+                    ModuleStatus::Ok
+                },
+                format!("last build {} {:?}", run.id, run.status),
+            ),
+        },
+    );
+    drop(build_runs);
+    observed.insert(
+        "dependency_drift",
+        (
+            match (mirrors, open_drift) {
+                (0, _) => ModuleStatus::Idle,
+                (_, 0) => ModuleStatus::Ok,
+                _ => ModuleStatus::Degraded,
+            },
+            format!("{} mirrors, {} open work items", mirrors, open_drift),
+        ),
+    );
+    observed.insert(
+        "migrations",
+        (
+            ModuleStatus::Ok,
+            format!(
+                "{} stores in {}",
+                migrations::STORES.len(),
+                migrations::state_dir()
+            ),
+        ),
+    );
+
+    // Hashing the binary reads it from disk, once
+    let provenance = tokio::task::spawn_blocking(introspect::build_provenance)
+        .await
+        .ok();
+
+    Json(serde_json::json!({
+        "schema": introspect::SCHEMA,
+        "domain": state.config.domain,
+        "generated_at": Utc::now().timestamp(),
+        "modules": introspect::modules(observed),
+        "features": introspect::feature_flags(),
+        "plugins": introspect::plugin_inventory(&introspect::plugin_dir()),
+        "config_fingerprint": introspect::config_fingerprint(&state.config),
+        "build": provenance,
+        "api_versions": api_versions::SUPPORTED_VERSIONS
+    }))
+}
+
+async fn list_alerts(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let mut alerts: Vec<Alert> = state.alerts.read().await.values().cloned().collect();
+    alerts.sort_by_key(|alert| std::cmp::Reverse(alert.since));
+    Json(serde_json::json!({ "alerts": alerts }))
+}
+
+async fn list_alert_rules(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let rules: Vec<AlertRule> = state.alert_rules.read().await.values().cloned().collect();
+    Json(serde_json::json!({ "rules": rules }))
+}
+
+async fn create_alert_rule(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Json(mut rule): Json<AlertRule>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if let Err(refusal) = require_admin(&state, &headers, "node:admin").await {
+        return refusal;
+    }
+    if rule.channels.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "A rule needs at least one channel" })),
+        );
+    }
+    let bad_webhook = rule.channels.iter().any(|channel| {
+        matches!(channel, alerting::Channel::Webhook { url }
+            if !(url.starts_with("https://") || url.starts_with("http://")))
+    });
+    if bad_webhook {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "Webhook URLs must be http(s)" })),
+        );
+    }
+    if rule.id.is_empty() {
+        rule.id = format!("rule_{}", chrono::Utc::now().timestamp_millis());
+    }
+
+    println!(
+        "🚨 Alert rule {}: {:?} {:?} {} for {}s",
+        rule.name, rule.metric, rule.comparison, rule.threshold, rule.for_secs
+    );
+    state
+        .alert_rules
+        .write()
+        .await
+        .insert(rule.id.clone(), rule.clone());
+
+    (StatusCode::OK, Json(serde_json::json!({ "rule": rule })))
+}
+
+async fn delete_alert_rule(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if let Err(refusal) = require_admin(&state, &headers, "node:admin").await {
+        return refusal;
+    }
+    match state.alert_rules.write().await.remove(&id) {
+        Some(rule) => (
+            StatusCode::OK,
+            Json(serde_json::json!({ "deleted": rule.id })),
+        ),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Rule not found" })),
+        ),
+    }
+}
+
+/// GET /api/v1/mirrors — mirrored crates with their last drift report
+async fn list_mirrors(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let mut mirrors: Vec<MirroredCrate> = state.mirrors.read().await.values().cloned().collect();
+    mirrors.sort_by(|a, b| a.name.cmp(&b.name));
+    Json(serde_json::json!({ "mirrors": mirrors }))
+}
+
+/// PUT /api/v1/mirrors/:name — register a mirror, or record the upstream
+/// version it has been rebased on; either way it is checked again next tick
+async fn set_mirror(
+    Path(name): Path<String>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Json(mut mirror): Json<MirroredCrate>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if let Err(refusal) = require_admin(&state, &headers, "node:admin").await {
+        return refusal;
+    }
+    if dependency_drift::parse_version(&mirror.version).is_none() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "version must be major.minor.patch" })),
+        );
+    }
+    mirror.name = name;
+    mirror.last_report = None;
+
+    println!(
+        "📦 Mirror {} at {} ({})",
+        mirror.name, mirror.version, mirror.mirror_url
+    );
+    state
+        .mirrors
+        .write()
+        .await
+        .insert(mirror.name.clone(), mirror.clone());
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "mirror": mirror })),
+    )
+}
+
+async fn delete_mirror(
+    Path(name): Path<String>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if let Err(refusal) = require_admin(&state, &headers, "node:admin").await {
+        return refusal;
+    }
+    match state.mirrors.write().await.remove(&name) {
+        Some(mirror) => {
+            // Nobody is left to act on its open item
+            let now = chrono::Utc::now().timestamp() as u64;
+            dependency_drift::reconcile(
+                &mut *state.drift_work_items.write().await,
+                &mirror.name,
+                Vec::new(),
+                now,
+            );
+            (
+                StatusCode::OK,
+                Json(serde_json::json!({ "deleted": mirror.name })),
+            )
+        }
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Mirror not found" })),
+        ),
+    }
+}
+
+/// GET /api/v1/mirrors/work-items — open drift work items first, newest first
+async fn list_drift_work_items(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let mut items: Vec<DriftWorkItem> = state
+        .drift_work_items
+        .read()
+        .await
+        .values()
+        .cloned()
+        .collect();
+    items.sort_by_key(|item| {
+        (
+            item.resolved_at.is_some(),
+            std::cmp::Reverse(item.opened_at),
+        )
+    });
+    let open = items
+        .iter()
+        .filter(|item| item.resolved_at.is_none())
+        .count();
+    Json(serde_json::json!({ "open": open, "work_items": items }))
+}
+
+#[derive(Debug, Deserialize)]
+struct AcknowledgeRequest {
+    by: String,
+    note: Option<String>,
+}
+
+/// Stops reminders for a firing alert; it still resolves on its own
+async fn acknowledge_alert(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Json(req): Json<AcknowledgeRequest>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if let Err(refusal) = require_admin(&state, &headers, "node:admin").await {
+        return refusal;
+    }
+    let mut alerts = state.alerts.write().await;
+    let alert = match alerts.get_mut(&id) {
+        Some(alert) => alert,
+        None => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": "Alert not found" })),
+            )
+        }
+    };
+
+    alert.acknowledged = Some(Acknowledgement {
+        by: req.by,
+        note: req.note,
+        at: chrono::Utc::now().timestamp() as u64,
+    });
+    (StatusCode::OK, Json(serde_json::json!({ "alert": alert })))
+}
+
+async fn list_silences(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let silences: Vec<Silence> = state
+        .alert_silences
+        .read()
+        .await
+        .values()
+        .cloned()
+        .collect();
+    Json(serde_json::json!({ "silences": silences }))
+}
+
+#[derive(Debug, Deserialize)]
+struct SilenceRequest {
+    rule_id: Option<String>,
+    subject: Option<String>,
+    duration_secs: u64,
+    reason: String,
+}
+
+async fn create_silence(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Json(req): Json<SilenceRequest>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let admin = match require_admin(&state, &headers, "node:admin").await {
+        Ok(admin) => admin,
+        Err(refusal) => return refusal,
+    };
+    if req.duration_secs == 0 {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "duration_secs must be positive" })),
+        );
+    }
+
+    let now = chrono::Utc::now();
+    let silence = Silence {
+        id: format!("silence_{}", now.timestamp_millis()),
+        rule_id: req.rule_id,
+        subject: req.subject,
+        ends_at: now.timestamp() as u64 + req.duration_secs,
+        created_by: admin,
+        reason: req.reason,
+    };
+
+    println!(
+        "🔕 Silence {} by {}: {}",
+        silence.id, silence.created_by, silence.reason
+    );
+    state
+        .alert_silences
+        .write()
+        .await
+        .insert(silence.id.clone(), silence.clone());
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "silence": silence })),
+    )
+}
+
+async fn delete_silence(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if let Err(refusal) = require_admin(&state, &headers, "node:admin").await {
+        return refusal;
+    }
+    match state.alert_silences.write().await.remove(&id) {
+        Some(silence) => (
+            StatusCode::OK,
+            Json(serde_json::json!({ "deleted": silence.id })),
+        ),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Silence not found" })),
+        ),
+    }
+}
+
+async fn list_marketplace_services(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let services = state.services.read().await;
+    let health = state.service_health.read().await;
+    let dependencies = state.service_dependencies.read().await;
+
+    let listed: Vec<serde_json::Value> = services
+        .iter()
+        .filter(|(key, _)| {
+            health
+                .get(*key)
+                .map(|h| h.state != service_health::HealthState::Delisted)
+                .unwrap_or(true)
+        })
+        .map(|(key, service)| {
+            serde_json::json!({
+                "service": service,
+                "health": health.get(key).cloned().unwrap_or_default(),
+                "cascade": service_health::cascade_status(key, &dependencies, &services, &health)
+            })
+        })
+        .collect();
+
+    Json(serde_json::json!({
+        "services": listed,
+        "delisted": services.len() - listed.len()
+    }))
+}
+
+async fn wallet_service_health(
+    Path(wallet): Path<String>,
+    State(state): State<AppState>,
+) -> Json<serde_json::Value> {
+    let services = state.services.read().await;
+    let health = state.service_health.read().await;
+    let dependencies = state.service_dependencies.read().await;
+
+    let own: Vec<serde_json::Value> = services
+        .iter()
+        .filter(|(_, service)| service.wallet_address == wallet)
+        .map(|(key, service)| {
+            serde_json::json!({
+                "service_name": service.service_name,
+                "port": service.port,
+                "health": health.get(key).cloned().unwrap_or_default(),
+                "depends_on": dependencies.get(key).cloned().unwrap_or_default(),
+                "cascade": service_health::cascade_status(key, &dependencies, &services, &health)
+            })
+        })
+        .collect();
+
+    let notifications = state
+        .owner_notifications
+        .read()
+        .await
+        .get(&wallet)
+        .cloned()
+        .unwrap_or_default();
+
+    Json(serde_json::json!({
+        "wallet": wallet,
+        "services": own,
+        "notifications": notifications
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+struct DependencyDeclaration {
+    depends_on: Vec<String>, // service keys, "{wallet}_{service}"
+}
+
+async fn service_dependencies(
+    Path((wallet, service)): Path<(String, String)>,
+    State(state): State<AppState>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let service_key = format!("{}_{}", wallet, service);
+    let services = state.services.read().await;
+    if !services.contains_key(&service_key) {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Service not found" })),
+        );
+    }
+    let health = state.service_health.read().await;
+    let dependencies = state.service_dependencies.read().await;
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "service_key": service_key,
+            "depends_on": dependencies.get(&service_key).cloned().unwrap_or_default(),
+            "dependents": service_health::transitive_dependents(&service_key, &dependencies),
+            "cascade": service_health::cascade_status(&service_key, &dependencies, &services, &health)
+        })),
+    )
+}
+
+/// Replace a service's dependencies; an empty list clears them
+async fn set_service_dependencies(
+    Path((wallet, service)): Path<(String, String)>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Json(declaration): Json<DependencyDeclaration>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if let Err(refusal) = require_wallet_scope(&state, &headers, &wallet, "services:manage").await {
+        return refusal;
+    }
+    let service_key = format!("{}_{}", wallet, service);
+    let services = state.services.read().await;
+    if !services.contains_key(&service_key) {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Service not found" })),
+        );
+    }
+
+    let mut depends_on = declaration.depends_on;
+    depends_on.sort();
+    depends_on.dedup();
+    if let Some(unknown) = depends_on
+        .iter()
+        .find(|dependency| **dependency == service_key || !services.contains_key(*dependency))
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": format!("Not a dependency: {}", unknown) })),
+        );
+    }
+
+    // Same lock order as the readers: services, health, dependencies
+    let health = state.service_health.read().await;
+    let mut dependencies = state.service_dependencies.write().await;
+    if let Some(cycle) = service_health::dependency_cycle(&service_key, &depends_on, &dependencies)
+    {
+        return (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({
+                "error": "Dependencies would form a cycle",
+                "cycle": cycle
+            })),
+        );
+    }
+
+    println!("🧩 {} depends on {:?}", service_key, depends_on);
+    if depends_on.is_empty() {
+        dependencies.remove(&service_key);
+    } else {
+        dependencies.insert(service_key.clone(), depends_on.clone());
+    }
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "service_key": service_key,
+            "depends_on": depends_on,
+            "cascade": service_health::cascade_status(&service_key, &dependencies, &services, &health)
+        })),
+    )
+}
+
+/// A wallet's services and everything they depend on, as nodes and edges
+async fn wallet_dependency_graph(
+    Path(wallet): Path<String>,
+    State(state): State<AppState>,
+) -> Json<serde_json::Value> {
+    let services = state.services.read().await;
+    let health = state.service_health.read().await;
+    let dependencies = state.service_dependencies.read().await;
+
+    let mut keys: Vec<String> = services
+        .iter()
+        .filter(|(_, service)| service.wallet_address == wallet)
+        .map(|(key, _)| key.clone())
+        .collect();
+    keys.sort();
+    for key in keys.clone() {
+        for dependency in service_health::transitive_dependencies(&key, &dependencies) {
+            if !keys.contains(&dependency) {
+                keys.push(dependency);
+            }
+        }
+    }
+
+    let nodes: Vec<serde_json::Value> = keys
+        .iter()
+        .map(|key| {
+            serde_json::json!({
+                "service_key": key,
+                "owned": services.get(key).is_some_and(|s| s.wallet_address == wallet),
+                "registered": services.contains_key(key),
+                "health": health.get(key).map(|h| h.state.clone()),
+                "cascade": service_health::cascade_status(key, &dependencies, &services, &health)
+            })
+        })
+        .collect();
+    let edges: Vec<serde_json::Value> = keys
+        .iter()
+        .flat_map(|key| {
+            dependencies
+                .get(key)
+                .into_iter()
+                .flatten()
+                .map(move |dependency| serde_json::json!({ "from": key, "to": dependency }))
+        })
+        .collect();
+
+    Json(serde_json::json!({
+        "wallet": wallet,
+        "nodes": nodes,
+        "edges": edges
+    }))
+}
+
+async fn prewarm_status(
+    Path((wallet, service)): Path<(String, String)>,
+    State(state): State<AppState>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let service_key = format!("{}_{}", wallet, service);
+    if !state.services.read().await.contains_key(&service_key) {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Service not found" })),
+        );
+    }
+
+    let now = chrono::Utc::now().timestamp() as u64;
+    let mut entry = state
+        .prewarm
+        .read()
+        .await
+        .get(&service_key)
+        .cloned()
+        .unwrap_or_default();
+    entry.policy.warm_cost_credits = state.config.prewarm_cost_credits;
+
+    // Expected requests for each of the next 24 hours
+    let forecast: Vec<f64> = (1..=24)
+        .map(|hour| entry.model.predict(now + hour * 3600))
+        .collect();
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "service": service,
+            "policy": entry.policy,
+            "forecast": forecast,
+            "remaining_budget_credits": entry.remaining_budget(now),
+            "last_warmed": entry.last_warmed,
+            "history": entry.history
+        })),
+    )
+}
+
+/// PUT /api/v1/services/:wallet/:service/prewarm — the owner's policy.
+/// The cost of a pre-warm is the node's, whatever the request says.
+async fn set_prewarm_policy(
+    Path((wallet, service)): Path<(String, String)>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Json(mut policy): Json<PrewarmPolicy>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if let Err(refusal) = require_wallet_scope(&state, &headers, &wallet, "services:manage").await {
+        return refusal;
+    }
+    policy.warm_cost_credits = state.config.prewarm_cost_credits;
+    let service_key = format!("{}_{}", wallet, service);
+    if !state.services.read().await.contains_key(&service_key) {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Service not found" })),
+        );
+    }
+
+    println!(
+        "🔥 Pre-warming for {} {} (budget {} credits/day)",
+        service_key,
+        if policy.enabled {
+            "enabled"
+        } else {
+            "disabled"
+        },
+        policy.daily_budget_credits
+    );
+
+    state
+        .prewarm
+        .write()
+        .await
+        .entry(service_key)
+        .or_default()
+        .policy = policy.clone();
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "policy": policy })),
+    )
+}
+
+/// Wallet behind a `subscriptions:manage` access token
+async fn billing_wallet(
+    state: &AppState,
+    headers: &axum::http::HeaderMap,
+) -> Result<String, (StatusCode, Json<serde_json::Value>)> {
+    let token = bearer_token(headers).unwrap_or_default();
+    state
+        .oidc
+        .read()
+        .await
+        .authorize_bearer(&token, "subscriptions:manage")
+        .map(|grant| grant.wallet_address)
+        .map_err(|e| {
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({ "error": e })),
+            )
+        })
+}
+
+async fn list_rate_plans(
+    Path((wallet, service)): Path<(String, String)>,
+    State(state): State<AppState>,
+) -> Json<serde_json::Value> {
+    let service_key = format!("{}_{}", wallet, service);
+    let plans = state
+        .rate_plans
+        .read()
+        .await
+        .get(&service_key)
+        .cloned()
+        .unwrap_or_default();
+    Json(serde_json::json!({ "service": service_key, "plans": plans }))
+}
+
+#[derive(Debug, Deserialize)]
+struct RatePlansRequest {
+    plans: Vec<RatePlan>,
+}
+
+/// Replace a service's plans. Existing subscribers keep their plan until it
+/// is withdrawn, then run out their paid period.
+async fn set_rate_plans(
+    Path((wallet, service)): Path<(String, String)>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Json(req): Json<RatePlansRequest>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    match billing_wallet(&state, &headers).await {
+        Ok(owner) if owner == wallet => {}
+        Ok(_) => {
+            return (
+                StatusCode::FORBIDDEN,
+                Json(serde_json::json!({ "error": "Only the service owner can set its plans" })),
+            )
+        }
+        Err(refusal) => return refusal,
+    }
+
+    let service_key = format!("{}_{}", wallet, service);
+    if !state.services.read().await.contains_key(&service_key) {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Service not found" })),
+        );
+    }
+    let mut ids: Vec<&str> = req.plans.iter().map(|plan| plan.id.as_str()).collect();
+    ids.sort_unstable();
+    ids.dedup();
+    if ids.len() != req.plans.len() || ids.contains(&"") {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "Every plan needs a unique id" })),
+        );
+    }
+
+    println!("💳 {} now offers {} plan(s)", service_key, req.plans.len());
+    state
+        .rate_plans
+        .write()
+        .await
+        .insert(service_key.clone(), req.plans.clone());
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "service": service_key, "plans": req.plans })),
+    )
+}
+
+#[derive(Debug, Deserialize)]
+struct PlanChoice {
+    plan_id: String,
+}
+
+async fn find_plan(state: &AppState, service_key: &str, plan_id: &str) -> Option<RatePlan> {
+    state
+        .rate_plans
+        .read()
+        .await
+        .get(service_key)?
+        .iter()
+        .find(|plan| plan.id == plan_id)
+        .cloned()
+}
+
+/// Subscribe the token's wallet, charging the first period now
+async fn subscribe(
+    Path((wallet, service)): Path<(String, String)>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Json(choice): Json<PlanChoice>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let subscriber = match billing_wallet(&state, &headers).await {
+        Ok(subscriber) => subscriber,
+        Err(refusal) => return refusal,
+    };
+    let service_key = format!("{}_{}", wallet, service);
+    let plan = match find_plan(&state, &service_key, &choice.plan_id).await {
+        Some(plan) => plan,
+        None => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": "Plan not found" })),
+            )
+        }
+    };
+
+    let now = chrono::Utc::now().timestamp() as u64;
+    let result = subscriptions::subscribe(
+        &mut *state.subscriptions.write().await,
+        &mut *state.user_sessions.write().await,
+        &subscriber,
+        &service_key,
+        &plan,
+        now,
+    );
+    match result {
+        Ok(subscription) => {
+            println!(
+                "💳 {} subscribed to {} ({})",
+                subscriber, service_key, plan.name
+            );
+            let paid = plan.monthly_price_credits as f64;
+            let sent = NewEvent::new(
+                &subscriber,
+                FeedCategory::Payments,
+                "payment_sent",
+                format!("Subscribed to {} on the {} plan", service, plan.name),
+            );
+            let received = NewEvent::new(
+                &wallet,
+                FeedCategory::Payments,
+                "payment_received",
+                format!(
+                    "{} subscribed to {} on the {} plan",
+                    subscriber, service, plan.name
+                ),
+            );
+            for (event, other) in [(sent, &wallet), (received, &subscriber)] {
+                let event = event
+                    .counterparty(other)
+                    .amount(paid, "credits")
+                    .reference(&service_key);
+                state.activity.publish("subscriptions", event).await;
+            }
+            (
+                StatusCode::OK,
+                Json(serde_json::json!({ "subscription": subscription })),
+            )
+        }
+        Err(e) => (
+            StatusCode::PAYMENT_REQUIRED,
+            Json(serde_json::json!({ "error": e })),
+        ),
+    }
+}
+
+/// Move to another plan, prorated over what is left of the period
+async fn change_subscription_plan(
+    Path((wallet, service)): Path<(String, String)>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Json(choice): Json<PlanChoice>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let subscriber = match billing_wallet(&state, &headers).await {
+        Ok(subscriber) => subscriber,
+        Err(refusal) => return refusal,
+    };
+    let service_key = format!("{}_{}", wallet, service);
+    // Plans are looked up before taking the subscriptions lock, which the
+    // quota middleware takes after rate_plans
+    let plans = state
+        .rate_plans
+        .read()
+        .await
+        .get(&service_key)
+        .cloned()
+        .unwrap_or_default();
+    let new = match plans.iter().find(|plan| plan.id == choice.plan_id) {
+        Some(plan) => plan,
+        None => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": "Plan not found" })),
+            )
+        }
+    };
+
+    let mut subscriptions = state.subscriptions.write().await;
+    let subscription = match subscriptions.get_mut(&Subscription::key(&subscriber, &service_key)) {
+        Some(subscription) if subscription.status != SubscriptionStatus::Cancelled => subscription,
+        _ => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": "Not subscribed" })),
+            )
+        }
+    };
+    let old = match plans.iter().find(|plan| plan.id == subscription.plan_id) {
+        Some(plan) => plan,
+        None => {
+            return (
+                StatusCode::CONFLICT,
+                Json(
+                    serde_json::json!({ "error": "Current plan was withdrawn; resubscribe when it ends" }),
+                ),
+            )
+        }
+    };
+
+    let now = chrono::Utc::now().timestamp() as u64;
+    let result = subscriptions::change_plan(
+        subscription,
+        &mut *state.user_sessions.write().await,
+        old,
+        new,
+        now,
+    );
+    match result {
+        Ok(net_credits) => (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "subscription": subscription,
+                "charged_credits": net_credits
+            })),
+        ),
+        Err(e) => (
+            StatusCode::PAYMENT_REQUIRED,
+            Json(serde_json::json!({ "error": e })),
+        ),
+    }
+}
+
+/// Cancel at the end of the paid period; overage is still billed then
+async fn cancel_subscription(
+    Path((wallet, service)): Path<(String, String)>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let subscriber = match billing_wallet(&state, &headers).await {
+        Ok(subscriber) => subscriber,
+        Err(refusal) => return refusal,
+    };
+    let service_key = format!("{}_{}", wallet, service);
+
+    let mut subscriptions = state.subscriptions.write().await;
+    match subscriptions.get_mut(&Subscription::key(&subscriber, &service_key)) {
+        Some(subscription) if subscription.status != SubscriptionStatus::Cancelled => {
+            subscription.cancel_at_period_end = true;
+            (
+                StatusCode::OK,
+                Json(serde_json::json!({ "subscription": subscription })),
+            )
+        }
+        _ => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Not subscribed" })),
+        ),
+    }
+}
+
+async fn list_subscriptions(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let subscriber = match billing_wallet(&state, &headers).await {
+        Ok(subscriber) => subscriber,
+        Err(refusal) => return refusal,
+    };
+    let subscriptions: Vec<Subscription> = state
+        .subscriptions
+        .read()
+        .await
+        .values()
+        .filter(|subscription| subscription.subscriber == subscriber)
+        .cloned()
+        .collect();
+    let credits = state
+        .user_sessions
+        .read()
+        .await
+        .get(&subscriber)
+        .map(|session| session.credits);
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "subscriptions": subscriptions, "credits": credits })),
+    )
+}
+
+/// Wallet behind a `services:versions` access token
+async fn versions_wallet(
+    state: &AppState,
+    headers: &axum::http::HeaderMap,
+) -> Result<String, (StatusCode, Json<serde_json::Value>)> {
+    let token = bearer_token(headers).unwrap_or_default();
+    state
+        .oidc
+        .read()
+        .await
+        .authorize_bearer(&token, "services:versions")
+        .map(|grant| grant.wallet_address)
+        .map_err(|e| {
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({ "error": e })),
+            )
+        })
+}
+
+/// The service's versions and changelogs, newest first
+async fn list_service_versions(
+    Path((wallet, service)): Path<(String, String)>,
+    State(state): State<AppState>,
+) -> Json<serde_json::Value> {
+    let service_key = format!("{}_{}", wallet, service);
+    let mut versions = state
+        .service_versions
+        .read()
+        .await
+        .get(&service_key)
+        .cloned()
+        .unwrap_or_default();
+    versions.versions.reverse();
+    Json(serde_json::json!({
+        "service": service_key,
+        "current": versions.current,
+        "versions": versions.versions,
+        "canary": versions.canary,
+    }))
+}
+
+/// Publish a version. One with its own port is a separate deployment that
+/// pinned consumers keep reaching after a newer one becomes current.
+async fn publish_service_version(
+    Path((wallet, service)): Path<(String, String)>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Json(req): Json<service_versions::PublishRequest>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    match versions_wallet(&state, &headers).await {
+        Ok(owner) if owner == wallet => {}
+        Ok(_) => {
+            return (
+                StatusCode::FORBIDDEN,
+                Json(serde_json::json!({ "error": "Only the service owner can publish versions" })),
+            )
+        }
+        Err(refusal) => return refusal,
+    }
+
+    let service_key = format!("{}_{}", wallet, service);
+    if !state.services.read().await.contains_key(&service_key) {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Service not found" })),
+        );
+    }
+    if let Some(port) = req.port {
+        let taken = state
+            .services
+            .read()
+            .await
+            .values()
+            .any(|other| other.port == port && other.wallet_address != wallet)
+            || state.user_sessions.read().await.values().any(|session| {
+                session.allocated_port == Some(port) && session.wallet_address != wallet
+            });
+        if taken {
+            return (
+                StatusCode::CONFLICT,
+                Json(serde_json::json!({ "error": "Port belongs to another wallet" })),
+            );
+        }
+        if let Err(e) = state.ports.reserve(port, &service_key, "version") {
+            return (
+                StatusCode::CONFLICT,
+                Json(serde_json::json!({ "error": e })),
+            );
+        }
+    }
+
+    let now = chrono::Utc::now().timestamp() as u64;
+    let mut all = state.service_versions.write().await;
+    let versions = all.entry(service_key.clone()).or_default();
+    match service_versions::publish(versions, req, now) {
+        Ok(version) => {
+            println!("🏷️ {} published version {}", service_key, version.version);
+            state
+                .activity
+                .publish(
+                    "service_versions",
+                    NewEvent::new(
+                        &wallet,
+                        FeedCategory::Deployments,
+                        "version_published",
+                        format!("Published {} version {}", service, version.version),
+                    )
+                    .reference(&service_key),
+                )
+                .await;
+            (
+                StatusCode::CREATED,
+                Json(serde_json::json!({
+                    "service": service_key,
+                    "current": versions.current,
+                    "version": version,
+                })),
+            )
+        }
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": e })),
+        ),
+    }
+}
+
+/// Deprecate a version; consumers pinned to it hear about it in their
+/// notifications
+async fn deprecate_service_version(
+    Path((wallet, service, version)): Path<(String, String, String)>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Json(req): Json<service_versions::DeprecateRequest>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    match versions_wallet(&state, &headers).await {
+        Ok(owner) if owner == wallet => {}
+        Ok(_) => {
+            return (
+                StatusCode::FORBIDDEN,
+                Json(
+                    serde_json::json!({ "error": "Only the service owner can deprecate versions" }),
+                ),
+            )
+        }
+        Err(refusal) => return refusal,
+    }
+
+    let service_key = format!("{}_{}", wallet, service);
+    let now = chrono::Utc::now().timestamp() as u64;
+    let mut all = state.service_versions.write().await;
+    let Some(versions) = all.get_mut(&service_key) else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Service publishes no versions" })),
+        );
+    };
+    let result = service_versions::deprecate(
+        versions,
+        &*state.version_pins.read().await,
+        &mut *state.owner_notifications.write().await,
+        &service_key,
+        &version,
+        req,
+        now,
+    );
+    match result {
+        Ok(notified) => {
+            println!(
+                "🏷️ {} deprecated version {} ({} pinned consumer(s) notified)",
+                service_key, version, notified
+            );
+            (
+                StatusCode::OK,
+                Json(serde_json::json!({
+                    "service": service_key,
+                    "version": versions.get(&version),
+                    "notified": notified,
+                })),
+            )
+        }
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": e })),
+        ),
+    }
+}
+
+/// The caller's `services:versions` token must be the service owner's
+async fn require_version_owner(
+    state: &AppState,
+    headers: &axum::http::HeaderMap,
+    wallet: &str,
+) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    match versions_wallet(state, headers).await {
+        Ok(owner) if owner == wallet => Ok(()),
+        Ok(_) => Err((
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({ "error": "Only the service owner can manage its canary" })),
+        )),
+        Err(refusal) => Err(refusal),
+    }
+}
+
+/// Start sending a share of unpinned calls to a published version
+async fn start_canary(
+    Path((wallet, service)): Path<(String, String)>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Json(req): Json<canary::CanaryRequest>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if let Err(refusal) = require_version_owner(&state, &headers, &wallet).await {
+        return refusal;
+    }
+    let service_key = format!("{}_{}", wallet, service);
+    let now = chrono::Utc::now().timestamp() as u64;
+    let mut all = state.service_versions.write().await;
+    let Some(versions) = all.get_mut(&service_key) else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Service publishes no versions" })),
+        );
+    };
+    match canary::start(versions, req, now) {
+        Ok(canary) => {
+            println!(
+                "🐤 {} canary {} takes {}% of calls",
+                service_key, canary.version, canary.weight_pct
+            );
+            (
+                StatusCode::CREATED,
+                Json(serde_json::json!({ "service": service_key, "canary": canary })),
+            )
+        }
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": e })),
+        ),
+    }
+}
+
+/// The running canary with an analysis of its calls so far
+async fn canary_status(
+    Path((wallet, service)): Path<(String, String)>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if let Err(refusal) = require_version_owner(&state, &headers, &wallet).await {
+        return refusal;
+    }
+    let service_key = format!("{}_{}", wallet, service);
+    let running = state
+        .service_versions
+        .read()
+        .await
+        .get(&service_key)
+        .and_then(|versions| versions.canary.clone());
+    let Some(running) = running else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "No canary is running" })),
+        );
+    };
+
+    let now = chrono::Utc::now().timestamp() as u64;
+    let since = now.saturating_sub(running.policy.window_secs);
+    let entries = state.service_logs.since(&service_key, since).await;
+    let analysis = canary::analyze(&running, &entries, now);
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "service": service_key,
+            "canary": running,
+            "analysis": analysis,
+        })),
+    )
+}
+
+async fn end_canary(
+    state: &AppState,
+    headers: &axum::http::HeaderMap,
+    wallet: &str,
+    service: &str,
+    outcome: canary::Recommendation,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if let Err(refusal) = require_version_owner(state, headers, wallet).await {
+        return refusal;
+    }
+    let service_key = format!("{}_{}", wallet, service);
+    match canary::conclude(state, &service_key, outcome, "by the owner").await {
+        Ok(ended) => (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "service": service_key,
+                "outcome": outcome,
+                "canary": ended,
+                "current": state
+                    .service_versions
+                    .read()
+                    .await
+                    .get(&service_key)
+                    .and_then(|versions| versions.current.clone()),
+            })),
+        ),
+        Err(e) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": e })),
+        ),
+    }
+}
+
+/// Make the canary the current version
+async fn promote_canary(
+    Path((wallet, service)): Path<(String, String)>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> (StatusCode, Json<serde_json::Value>) {
+    end_canary(
+        &state,
+        &headers,
+        &wallet,
+        &service,
+        canary::Recommendation::Promote,
+    )
+    .await
+}
+
+/// Send every unpinned call to the baseline again
+async fn rollback_canary(
+    Path((wallet, service)): Path<(String, String)>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> (StatusCode, Json<serde_json::Value>) {
+    end_canary(
+        &state,
+        &headers,
+        &wallet,
+        &service,
+        canary::Recommendation::Rollback,
+    )
+    .await
+}
+
+async fn get_version_pin(
+    Path((wallet, service)): Path<(String, String)>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let consumer = match versions_wallet(&state, &headers).await {
+        Ok(consumer) => consumer,
+        Err(refusal) => return refusal,
+    };
+    let service_key = format!("{}_{}", wallet, service);
+    let pin = state
+        .version_pins
+        .read()
+        .await
+        .get(&VersionPin::key(&consumer, &service_key))
+        .cloned();
+    let version = match &pin {
+        Some(pin) => state
+            .service_versions
+            .read()
+            .await
+            .get(&service_key)
+            .and_then(|versions| versions.get(&pin.version))
+            .cloned(),
+        None => None,
+    };
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "pin": pin, "version": version })),
+    )
+}
+
+#[derive(Debug, Deserialize)]
+struct PinRequest {
+    version: String,
+}
+
+/// Pin the token's wallet to a version; its calls go there until unpinned
+/// or overridden per call with the `X-Service-Version` header
+async fn pin_service_version(
+    Path((wallet, service)): Path<(String, String)>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Json(req): Json<PinRequest>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let consumer = match versions_wallet(&state, &headers).await {
+        Ok(consumer) => consumer,
+        Err(refusal) => return refusal,
+    };
+    let service_key = format!("{}_{}", wallet, service);
+    let now = chrono::Utc::now().timestamp() as u64;
+    let version = {
+        let all = state.service_versions.read().await;
+        match service_versions::resolve(all.get(&service_key), Some(&req.version), None, now) {
+            Ok(version) => version.cloned(),
+            Err((status, e)) => return (status, Json(serde_json::json!({ "error": e }))),
+        }
+    };
+    if version.as_ref().is_some_and(|v| v.deprecated_at.is_some()) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "Cannot pin a deprecated version" })),
+        );
+    }
+
+    let pin = VersionPin {
+        consumer: consumer.clone(),
+        service_key: service_key.clone(),
+        version: req.version,
+        pinned_at: now,
+    };
+    state
+        .version_pins
+        .write()
+        .await
+        .insert(VersionPin::key(&consumer, &service_key), pin.clone());
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "pin": pin, "version": version })),
+    )
+}
+
+async fn unpin_service_version(
+    Path((wallet, service)): Path<(String, String)>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let consumer = match versions_wallet(&state, &headers).await {
+        Ok(consumer) => consumer,
+        Err(refusal) => return refusal,
+    };
+    let service_key = format!("{}_{}", wallet, service);
+    match state
+        .version_pins
+        .write()
+        .await
+        .remove(&VersionPin::key(&consumer, &service_key))
+    {
+        Some(pin) => (StatusCode::OK, Json(serde_json::json!({ "unpinned": pin }))),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Not pinned" })),
+        ),
+    }
+}
+
+async fn list_service_templates() -> Json<serde_json::Value> {
+    let templates = service_templates::load_templates();
+
+    Json(serde_json::json!({
+        "template_dir": service_templates::template_dir(),
+        "templates": templates
+    }))
+}
+
+async fn create_service_from_template(
+    State(state): State<AppState>,
+    Json(req): Json<FromTemplateRequest>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let _trace = state.tracer.start_trace("create_service_from_template");
+
+    let templates = service_templates::load_templates();
+    let template = match templates.get(&req.template) {
+        Some(template) => template.clone(),
+        None => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({
+                    "status": "error",
+                    "message": format!("Unknown template: {}", req.template)
+                })),
+            )
+        }
+    };
+
+    let service_name = req
+        .service_name
+        .clone()
+        .unwrap_or_else(|| template.name.clone());
+    let service_key = format!("{}_{}", req.wallet, service_name);
+
+    let mut completed = Vec::new();
+    let result = provision_from_template(
+        &state,
+        &req,
+        &template,
+        &service_name,
+        &service_key,
+        &mut completed,
+    )
+    .await;
+
+    _trace.finish();
+
+    match result {
+        Ok(service) => {
+            println!(
+                "🧩 Service {} created from template {}",
+                service_key, req.template
+            );
+            state
+                .activity
+                .publish(
+                    "templates",
+                    NewEvent::new(
+                        &req.wallet,
+                        FeedCategory::Deployments,
+                        "service_deployed",
+                        format!(
+                            "Deployed {} from the {} template",
+                            service_name, req.template
+                        ),
+                    )
+                    .reference(&service_key),
+                )
+                .await;
+            (
+                StatusCode::OK,
+                Json(serde_json::json!({
+                    "status": "success",
+                    "service": service,
+                    "url": format!("https://{}/{}/{}", state.config.domain, req.wallet, service_name)
+                })),
+            )
+        }
+        Err(e) => {
+            let rolled_back = completed.len();
+            rollback_template_steps(&state, &req.wallet, completed).await;
+            println!("↩️  Template provisioning failed, rolled back: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "status": "error",
+                    "message": e,
+                    "rolled_back_steps": rolled_back
+                })),
+            )
+        }
+    }
+}
+
+async fn provision_from_template(
+    state: &AppState,
+    req: &FromTemplateRequest,
+    template: &service_templates::ServiceTemplate,
+    service_name: &str,
+    service_key: &str,
+    completed: &mut Vec<CompletedStep>,
+) -> Result<RegisteredService, String> {
+    if state.services.read().await.contains_key(service_key) {
+        return Err(format!("Service {} already exists", service_key));
+    }
+
+    // 1. Allocate a port from this instance's partition of the host range
+    let port = {
+        let port = state.ports.allocate(service_key, "service")?;
+        let mut sessions = state.user_sessions.write().await;
+        let session = sessions.entry(req.wallet.clone()).or_insert(UserSession {
+            wallet_address: req.wallet.clone(),
+            allocated_port: None,
+            credits: 100,
+            last_activity: chrono::Utc::now().timestamp() as u64,
+        });
+        session.allocated_port = Some(port);
+        port
+    };
+    completed.push(CompletedStep::PortAllocated(port));
+
+    // 2. Register the service
+    let data_dir = std::env::var("ZOS_DATA_DIR").unwrap_or_else(|_| "/tmp".to_string());
+    let service = RegisteredService {
+        wallet_address: req.wallet.clone(),
+        service_name: service_name.to_string(),
+        template: template.name.clone(),
+        port,
+        pricing_tier: template.pricing_tier.clone(),
+        work_dir: format!("{}/zos-services/{}", data_dir, service_key),
+        created_at: chrono::Utc::now().timestamp() as u64,
+    };
+    state
+        .services
+        .write()
+        .await
+        .insert(service_key.to_string(), service.clone());
+    completed.push(CompletedStep::ServiceRegistered(service_key.to_string()));
+
+    // 3. Deploy the workload
+    service_templates::deploy_workload(&service, template, &req.env).await?;
+    completed.push(CompletedStep::WorkloadDeployed(service.work_dir.clone()));
+
+    // 4. Register the health check
+    state.health_checks.write().await.insert(
+        service_key.to_string(),
+        RegisteredHealthCheck {
+            service_key: service_key.to_string(),
+            kind: template.health_check.kind,
+            url: match template.health_check.kind {
+                ProbeKind::Http => {
+                    format!("http://127.0.0.1:{}{}", port, template.health_check.path)
+                }
+                ProbeKind::Libp2pPing => format!("127.0.0.1:{}", port),
+            },
+            interval_secs: template.health_check.interval_secs,
+            timeout_secs: template.health_check.timeout_secs,
+            failure_threshold: template.health_check.failure_threshold,
+        },
+    );
+    completed.push(CompletedStep::HealthCheckRegistered(
+        service_key.to_string(),
+    ));
+
+    Ok(service)
+}
+
+async fn rollback_template_steps(state: &AppState, wallet: &str, completed: Vec<CompletedStep>) {
+    for step in completed.into_iter().rev() {
+        match step {
+            CompletedStep::HealthCheckRegistered(key) => {
+                state.health_checks.write().await.remove(&key);
+                state.service_health.write().await.remove(&key);
+            }
+            CompletedStep::WorkloadDeployed(work_dir) => {
+                service_templates::remove_workload(&work_dir).await;
+            }
+            CompletedStep::ServiceRegistered(key) => {
+                state.services.write().await.remove(&key);
+            }
+            CompletedStep::PortAllocated(port) => {
+                if let Err(e) = state.ports.release(port) {
+                    println!("⚠️  Port {} stays recorded: {}", port, e);
+                }
+                if let Some(session) = state.user_sessions.write().await.get_mut(wallet) {
+                    if session.allocated_port == Some(port) {
+                        session.allocated_port = None;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The caller's `data:export` token must speak for `wallet`, or a node
+/// admin's `data:admin` token may act for any wallet
+async fn require_data_access(
+    state: &AppState,
+    headers: &axum::http::HeaderMap,
+    wallet: &str,
+) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    let token = bearer_token(headers).unwrap_or_default();
+    let oidc = state.oidc.read().await;
+    if let Ok(grant) = oidc.authorize_bearer(&token, "data:export") {
+        if grant.wallet_address == wallet {
+            return Ok(());
+        }
+    }
+    match oidc.authorize_bearer(&token, "data:admin") {
+        Ok(grant) if state.config.admin_wallets.contains(&grant.wallet_address) => Ok(()),
+        Ok(_) => Err((
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({ "error": "Not an admin of this node (ZOS_ADMIN_WALLETS)" })),
+        )),
+        Err(_) => match oidc.authorize_bearer(&token, "data:export") {
+            Ok(_) => Err((
+                StatusCode::FORBIDDEN,
+                Json(serde_json::json!({ "error": "Token is for another wallet" })),
+            )),
+            Err(e) => Err((
+                StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({ "error": e })),
+            )),
+        },
+    }
+}
+
+async fn export_wallet_data(
+    Path(wallet): Path<String>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> Response {
+    if let Err(refusal) = require_data_access(&state, &headers, &wallet).await {
+        return refusal.into_response();
+    }
+    let _trace = state.tracer.start_trace("export_wallet_data");
+
+    // Payments, earnings and referrals live in the gateway's ledger; an
+    // archive without them would look complete and not be
+    let gateway_records = match &state.config.gateway_url {
+        Some(gateway_url) => match data_export::fetch_gateway_records(gateway_url, &wallet).await {
+            Ok(records) => records,
+            Err(e) => {
+                println!("❌ Data export failed: {}", e);
+                return (
+                    StatusCode::BAD_GATEWAY,
+                    Json(serde_json::json!({ "error": format!("Gateway records unavailable: {}", e) })),
+                )
+                    .into_response();
+            }
+        },
+        None => serde_json::Map::new(),
+    };
+
+    let sessions: Vec<UserSession> = state
+        .user_sessions
+        .read()
+        .await
+        .values()
+        .filter(|session| session.wallet_address == wallet)
+        .cloned()
+        .collect();
+
+    let deletion_requests: Vec<DeletionRequest> = state
+        .deletion_requests
+        .read()
+        .await
+        .get(&data_export::pseudonymize_wallet(&wallet))
+        .cloned()
+        .into_iter()
+        .collect();
+
+    let onboarding: Vec<OnboardingProgress> = state
+        .onboarding
+        .read()
+        .await
+        .get(&wallet)
+        .cloned()
+        .into_iter()
+        .collect();
+
+    let messages: Vec<Message> = state
+        .messages
+        .read()
+        .await
+        .values()
+        .filter(|message| message.from == wallet || message.to == wallet)
+        .cloned()
+        .collect();
+    let messaging_settings = state.messaging.read().await.get(&wallet).cloned();
+    let version_pins: Vec<VersionPin> = state
+        .version_pins
+        .read()
+        .await
+        .values()
+        .filter(|pin| pin.consumer == wallet)
+        .cloned()
+        .collect();
+
+    let mut sections = vec![
+        ExportSection {
+            name: "sessions".to_string(),
+            records: serde_json::json!(sessions),
+        },
+        ExportSection {
+            name: "messages".to_string(),
+            records: serde_json::json!({
+                "messages": messages,
+                "settings": messaging_settings,
+            }),
+        },
+        ExportSection {
+            name: "version_pins".to_string(),
+            records: serde_json::json!(version_pins),
+        },
+        ExportSection {
+            name: "onboarding".to_string(),
+            records: serde_json::json!(onboarding),
+        },
+        ExportSection {
+            name: "deletion_requests".to_string(),
+            records: serde_json::json!(deletion_requests),
+        },
+    ];
+    sections.extend(
+        gateway_records
+            .into_iter()
+            .map(|(name, records)| ExportSection {
+                name: format!("gateway_{}", name),
+                records,
+            }),
+    );
+
+    let archive =
+        match data_export::build_export_archive(&wallet, &state.config.domain, sections).await {
+            Ok(archive) => archive,
+            Err(e) => {
+                println!("❌ Data export failed: {}", e);
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        };
+
+    _trace.finish();
+
+    Response::builder()
+        .header(header::CONTENT_TYPE, "application/gzip")
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!(
+                "attachment; filename=\"zos-export-{}.tar.gz\"",
+                data_export::pseudonymize_wallet(&wallet)
+            ),
+        )
+        .body(archive.into())
+        .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())
+}
+
+/// Public URL of each service the wallet has registered on this node
+async fn wallet_service_endpoints(state: &AppState, wallet: &str) -> Vec<String> {
+    let mut endpoints: Vec<String> = state
+        .services
+        .read()
+        .await
+        .values()
+        .filter(|service| service.wallet_address == wallet)
+        .map(|service| {
+            format!(
+                "https://{}/{}/{}",
+                state.config.domain, wallet, service.service_name
+            )
+        })
+        .collect();
+    endpoints.sort();
+    endpoints
+}
+
+/// Steps the node can see a wallet has done. An endpoint only counts once
+/// it points at one of the wallet's services, and a Telegram link only
+/// exists once the account proved it at link time.
+async fn observed_onboarding_steps(state: &AppState, wallet: &str) -> Vec<OnboardingStep> {
+    let service_endpoints = wallet_service_endpoints(state, wallet).await;
+    let mut observed = Vec::new();
+    if let Some(identity) = state.identities.read().await.get(wallet) {
+        if identity.gateway_endpoints.iter().any(|url| {
+            service_endpoints
+                .iter()
+                .any(|endpoint| url.starts_with(endpoint.as_str()))
+        }) {
+            observed.push(OnboardingStep::RegisterEndpoint);
+        }
+        if identity.telegram.is_some() {
+            observed.push(OnboardingStep::LinkTelegram);
+        }
+    }
+    if !service_endpoints.is_empty() {
+        observed.push(OnboardingStep::CreateService);
+    }
+    if state
+        .user_sessions
+        .read()
+        .await
+        .get(wallet)
+        .is_some_and(|session| session.allocated_port.is_some())
+    {
+        observed.push(OnboardingStep::AllocatePort);
+    }
+    observed
+}
+
+/// GET /api/v1/onboarding/:wallet — the wizard's remaining steps with deep
+/// links, and the rewards waiting to be claimed. Changes nothing.
+async fn onboarding_status(
+    Path(wallet): Path<String>,
+    State(state): State<AppState>,
+) -> Json<serde_json::Value> {
+    let observed = observed_onboarding_steps(&state, &wallet).await;
+    let mut progress = state
+        .onboarding
+        .read()
+        .await
+        .get(&wallet)
+        .cloned()
+        .unwrap_or_else(|| OnboardingProgress::new(&wallet));
+    onboarding::record(&mut progress, &observed);
+
+    let mut report = onboarding::report(&progress, &state.config.domain);
+    report["credits"] = serde_json::json!(state
+        .user_sessions
+        .read()
+        .await
+        .get(&wallet)
+        .map(|session| session.credits));
+    Json(report)
+}
+
+/// POST /api/v1/onboarding/:wallet/claim — record the steps done and pay the
+/// rewards still owed, each once, to the wallet holding the token
+async fn claim_onboarding_rewards(
+    Path(wallet): Path<String>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if let Err(refusal) = require_wallet_scope(&state, &headers, &wallet, "onboarding").await {
+        return refusal;
+    }
+    let observed = observed_onboarding_steps(&state, &wallet).await;
+
+    let mut sessions = state.user_sessions.write().await;
+    let mut all_progress = state.onboarding.write().await;
+    let progress = all_progress
+        .entry(wallet.clone())
+        .or_insert_with(|| OnboardingProgress::new(&wallet));
+    onboarding::record(progress, &observed);
+    let granted = onboarding::claim(progress, &mut sessions);
+    if granted > 0 {
+        println!("🎁 {} onboarding credits granted to {}", granted, wallet);
+    }
+
+    let mut report = onboarding::report(progress, &state.config.domain);
+    report["credits_granted"] = serde_json::json!(granted);
+    report["credits"] = serde_json::json!(sessions.get(&wallet).map(|session| session.credits));
+    (StatusCode::OK, Json(report))
+}
+
+async fn get_identity(
+    Path(wallet): Path<String>,
+    State(state): State<AppState>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let service_endpoints = wallet_service_endpoints(&state, &wallet).await;
+    let session = state.user_sessions.read().await.get(&wallet).cloned();
+    let identities = state.identities.read().await;
+
+    let identity = match identities.get(&wallet) {
+        Some(identity) => identity,
+        None if session.is_some() || !service_endpoints.is_empty() => {
+            return (
+                StatusCode::OK,
+                Json(serde_json::json!({
+                    "wallet_address": wallet,
+                    "identity": null,
+                    "session": session,
+                    "service_endpoints": service_endpoints,
+                    "issues": []
+                })),
+            );
+        }
+        None => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": "No identity for wallet" })),
+            )
+        }
+    };
+
+    let issues = identity::check(&identities, identity, &service_endpoints);
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "wallet_address": wallet,
+            "identity": identity,
+            "session": session,
+            "service_endpoints": service_endpoints,
+            "issues": issues
+        })),
+    )
+}
+
+#[derive(Debug, Deserialize)]
+struct UnixChallengeRequest {
+    username: String,
+}
+
+/// POST /api/v1/identity/:wallet/unix-challenge — a nonce for the account
+/// to write to ~/.zos-link before the Unix link is made
+async fn unix_link_challenge(
+    Path(wallet): Path<String>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Json(request): Json<UnixChallengeRequest>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if let Err(refusal) = require_wallet_scope(&state, &headers, &wallet, "identity:manage").await {
+        return refusal;
+    }
+    let challenge = UnixChallenge::new(&request.username);
+    state
+        .unix_challenges
+        .write()
+        .await
+        .insert(wallet.clone(), challenge.clone());
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "username": challenge.username,
+            "nonce": challenge.nonce,
+            "expires_at": challenge.expires_at,
+            "instructions": format!(
+                "As {}, run: echo {} > ~/{}",
+                challenge.username,
+                challenge.nonce,
+                identity::UNIX_CHALLENGE_FILE
+            )
+        })),
+    )
+}
+
+/// Telegram and Unix links need proof the wallet also controls the account;
+/// the uid recorded is the account's own, not the one asked for
+async fn verify_link_proof(
+    state: &AppState,
+    wallet: &str,
+    request: LinkRequest,
+) -> Result<IdentityLink, String> {
+    match request.link {
+        IdentityLink::Telegram {
+            telegram_id,
+            username,
+        } => {
+            let fields = request
+                .proof
+                .telegram_login
+                .ok_or("Telegram links need proof.telegram_login from the login widget")?;
+            let bot_token = crate::secrets::resolve("ZOS_TELEGRAM_BOT_TOKEN")
+                .ok_or("ZOS_TELEGRAM_BOT_TOKEN is not set")?;
+            let now = chrono::Utc::now().timestamp() as u64;
+            identity::verify_telegram_login(&bot_token, &fields, telegram_id, now)?;
+            Ok(IdentityLink::Telegram {
+                telegram_id,
+                username,
+            })
+        }
+        IdentityLink::Unix { username, .. } => {
+            let nonce = request
+                .proof
+                .unix_nonce
+                .ok_or("Unix links need proof.unix_nonce from the unix-challenge")?;
+            let mut challenges = state.unix_challenges.write().await;
+            let now = chrono::Utc::now().timestamp() as u64;
+            match challenges.get(wallet) {
+                Some(challenge)
+                    if challenge.username == username
+                        && challenge.nonce == nonce
+                        && challenge.expires_at > now => {}
+                _ => return Err("No open challenge for this account and nonce".to_string()),
+            }
+            let passwd = std::fs::read_to_string("/etc/passwd")
+                .map_err(|e| format!("Cannot read /etc/passwd: {}", e))?;
+            let uid = identity::verify_unix_challenge(&passwd, &username, &nonce)?;
+            challenges.remove(wallet);
+            Ok(IdentityLink::Unix {
+                username,
+                uid: Some(uid),
+            })
+        }
+        link => Ok(link),
+    }
+}
+
+async fn link_identity(
+    Path(wallet): Path<String>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Json(request): Json<LinkRequest>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if let Err(refusal) = require_wallet_scope(&state, &headers, &wallet, "identity:manage").await {
+        return refusal;
+    }
+    let link = match verify_link_proof(&state, &wallet, request).await {
+        Ok(link) => link,
+        Err(e) => {
+            return (
+                StatusCode::FORBIDDEN,
+                Json(serde_json::json!({ "error": e })),
+            )
+        }
+    };
+
+    let service_endpoints = wallet_service_endpoints(&state, &wallet).await;
+    let mut identities = state.identities.write().await;
+
+    match identity::link(&mut identities, &wallet, &state.config.domain, link) {
+        Ok(identity) => {
+            println!("🪪 Identity link updated for {}", wallet);
+            let issues = identity::check(&identities, &identity, &service_endpoints);
+            (
+                StatusCode::OK,
+                Json(serde_json::json!({ "identity": identity, "issues": issues })),
+            )
+        }
+        Err(e) => (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({ "error": e })),
+        ),
+    }
+}
+
+async fn unlink_identity(
+    Path((wallet, kind)): Path<(String, String)>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if let Err(refusal) = require_wallet_scope(&state, &headers, &wallet, "identity:manage").await {
+        return refusal;
+    }
+    let mut identities = state.identities.write().await;
+
+    match identity::unlink(&mut identities, &wallet, &kind) {
+        Ok(identity) => {
+            println!("🪪 Identity link {} removed for {}", kind, wallet);
+            (
+                StatusCode::OK,
+                Json(serde_json::json!({ "identity": identity })),
+            )
+        }
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": e })),
+        ),
+    }
+}
+
+/// The caller's access token must carry `scope` and speak for `wallet`
+async fn require_wallet_scope(
+    state: &AppState,
+    headers: &axum::http::HeaderMap,
+    wallet: &str,
+    scope: &str,
+) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    let token = bearer_token(headers).unwrap_or_default();
+    let grant = state
+        .oidc
+        .read()
+        .await
+        .authorize_bearer(&token, scope)
+        .map_err(|e| {
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({ "error": e })),
+            )
+        })?;
+    if grant.wallet_address != wallet {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({ "error": "Token is for another wallet" })),
+        ));
+    }
+    Ok(())
+}
+
+/// POST /api/v1/messages — send an encrypted message from the token's wallet
+async fn send_message(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Json(request): Json<messaging::SendRequest>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let token = bearer_token(&headers).unwrap_or_default();
+    let from = match state.oidc.read().await.authorize_bearer(&token, "messages") {
+        Ok(grant) => grant.wallet_address,
+        Err(e) => {
+            return (
+                StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({ "error": e })),
+            )
+        }
+    };
+
+    let now = chrono::Utc::now().timestamp() as u64;
+    let identities = state.identities.read().await;
+    let settings = state.messaging.read().await;
+    let sent = messaging::send(
+        &mut *state.messages.write().await,
+        &settings,
+        |wallet| identities.contains_key(wallet),
+        &from,
+        request,
+        now,
+    );
+    let message = match sent {
+        Ok(message) => message,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": e })),
+            )
+        }
+    };
+
+    let relay_to = identities
+        .get(&message.to)
+        .and_then(|identity| identity.telegram.as_ref())
+        .map(|telegram| telegram.telegram_id)
+        .filter(|_| settings.get(&message.to).is_some_and(|s| s.telegram_relay));
+    if let Some(telegram_id) = relay_to {
+        let message = message.clone();
+        let domain = state.config.domain.clone();
+        tokio::spawn(async move {
+            if let Err(e) = messaging::relay_to_telegram(telegram_id, &message, &domain).await {
+                println!("⚠️  Telegram relay for {} failed: {}", message.id, e);
+            }
+        });
+    }
+
+    println!(
+        "📨 {:?} message {} from {} to {}",
+        message.kind, message.id, message.from, message.to
+    );
+    if message.kind == messaging::MessageKind::VouchRequest {
+        let vouch = |wallet: &str, kind: &str, summary: String, other: &str| {
+            NewEvent::new(wallet, FeedCategory::Vouches, kind, summary)
+                .counterparty(other)
+                .reference(&message.id)
+        };
+        let sent = vouch(
+            &message.from,
+            "vouch_requested",
+            format!("Asked {} to vouch for you", message.to),
+            &message.to,
+        );
+        let received = vouch(
+            &message.to,
+            "vouch_request_received",
+            format!("{} asked you to vouch for them", message.from),
+            &message.from,
+        );
+        state.activity.publish("messaging", sent).await;
+        state.activity.publish("messaging", received).await;
+    }
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "id": message.id,
+            "expires_at": message.expires_at,
+            "telegram_relay": relay_to.is_some(),
+        })),
+    )
+}
+
+/// GET /api/v1/messages/:wallet/key — the key to encrypt messages to a wallet
+async fn get_messaging_key(
+    Path(wallet): Path<String>,
+    State(state): State<AppState>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    match state
+        .messaging
+        .read()
+        .await
+        .get(&wallet)
+        .and_then(|settings| settings.public_key.clone())
+    {
+        Some(public_key) => (
+            StatusCode::OK,
+            Json(serde_json::json!({ "wallet_address": wallet, "public_key": public_key })),
+        ),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Wallet has not published a messaging key" })),
+        ),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct MessagingSettingsUpdate {
+    public_key: Option<String>,
+    telegram_relay: Option<bool>,
+}
+
+async fn set_messaging_settings(
+    Path(wallet): Path<String>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Json(update): Json<MessagingSettingsUpdate>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if let Err(refusal) = require_wallet_scope(&state, &headers, &wallet, "messages").await {
+        return refusal;
+    }
+    if let Some(Err(e)) = update
+        .public_key
+        .as_deref()
+        .map(messaging::validate_public_key)
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": e })),
+        );
+    }
+
+    let mut all_settings = state.messaging.write().await;
+    let settings = all_settings.entry(wallet).or_default();
+    if update.public_key.is_some() {
+        settings.public_key = update.public_key;
+    }
+    if let Some(telegram_relay) = update.telegram_relay {
+        settings.telegram_relay = telegram_relay;
+    }
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "settings": settings })),
+    )
+}
+
+async fn block_wallet(
+    Path((wallet, other)): Path<(String, String)>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if let Err(refusal) = require_wallet_scope(&state, &headers, &wallet, "messages").await {
+        return refusal;
+    }
+    let mut all_settings = state.messaging.write().await;
+    let settings = all_settings.entry(wallet).or_default();
+    settings.blocked.insert(other);
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "blocked": settings.blocked })),
+    )
+}
+
+async fn unblock_wallet(
+    Path((wallet, other)): Path<(String, String)>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if let Err(refusal) = require_wallet_scope(&state, &headers, &wallet, "messages").await {
+        return refusal;
+    }
+    let mut all_settings = state.messaging.write().await;
+    let settings = all_settings.entry(wallet).or_default();
+    settings.blocked.remove(&other);
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "blocked": settings.blocked })),
+    )
+}
+
+/// GET /api/v1/messages/:wallet/inbox — the dashboard inbox, still encrypted
+async fn get_inbox(
+    Path(wallet): Path<String>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if let Err(refusal) = require_wallet_scope(&state, &headers, &wallet, "messages").await {
+        return refusal;
+    }
+    let now = chrono::Utc::now().timestamp() as u64;
+    let inbox = messaging::inbox(&*state.messages.read().await, &wallet, now);
+    let unread = inbox
+        .iter()
+        .filter(|message| message.read_at.is_none())
+        .count();
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "unread": unread, "messages": inbox })),
+    )
+}
+
+async fn mark_message_read(
+    Path((wallet, id)): Path<(String, String)>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if let Err(refusal) = require_wallet_scope(&state, &headers, &wallet, "messages").await {
+        return refusal;
+    }
+    let mut messages = state.messages.write().await;
+    match messages.get_mut(&id).filter(|message| message.to == wallet) {
+        Some(message) => {
+            let read_at = *message
+                .read_at
+                .get_or_insert(chrono::Utc::now().timestamp() as u64);
+            (
+                StatusCode::OK,
+                Json(serde_json::json!({ "id": id, "read_at": read_at })),
+            )
+        }
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Message not found" })),
+        ),
+    }
+}
+
+async fn delete_message(
+    Path((wallet, id)): Path<(String, String)>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if let Err(refusal) = require_wallet_scope(&state, &headers, &wallet, "messages").await {
+        return refusal;
+    }
+    let mut messages = state.messages.write().await;
+    match messages.get(&id) {
+        Some(message) if message.to == wallet => {
+            messages.remove(&id);
+            (StatusCode::OK, Json(serde_json::json!({ "deleted": id })))
+        }
+        _ => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Message not found" })),
+        ),
+    }
+}
+
+/// GET /api/v1/feed/:wallet — the wallet's activity, newest first, with
+/// `?categories=payments,vouches`, `?before=<next_cursor>` and `?limit=`
+async fn activity_feed(
+    Path(wallet): Path<String>,
+    Query(query): Query<FeedQuery>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let token = bearer_token(&headers).unwrap_or_default();
+    match state
+        .oidc
+        .read()
+        .await
+        .authorize_bearer(&token, "activity:read")
+    {
+        Ok(grant) if grant.wallet_address == wallet => {}
+        Ok(_) => {
+            return (
+                StatusCode::FORBIDDEN,
+                Json(serde_json::json!({ "error": "Token is for another wallet" })),
+            )
+        }
+        Err(e) => {
+            return (
+                StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({ "error": e })),
+            )
+        }
+    }
+
+    match state.activity.page(&wallet, &query).await {
+        Ok(page) => (StatusCode::OK, Json(serde_json::json!(page))),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": e })),
+        ),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct FeedIngest {
+    source: String,
+    events: Vec<NewEvent>,
+}
+
+/// POST /api/v1/feed/events — the gateway, community ledger and game servers
+/// publish their wallets' events here with the X-Feed-Key shared key
+async fn ingest_feed_events(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Json(ingest): Json<FeedIngest>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let Some(key) = crate::secrets::resolve("ZOS_FEED_INGEST_KEY") else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({ "error": "Feed ingest is not configured on this node" })),
+        );
+    };
+    let presented = headers
+        .get("x-feed-key")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    if presented != key {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({ "error": "Invalid feed key" })),
+        );
+    }
+    if ingest.events.len() > activity_feed::MAX_INGEST_BATCH {
+        return (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            Json(serde_json::json!({
+                "error": format!("At most {} events per batch", activity_feed::MAX_INGEST_BATCH)
+            })),
+        );
+    }
+
+    let received = ingest.events.len();
+    let mut accepted = 0;
+    for event in ingest.events {
+        if state
+            .activity
+            .publish(&ingest.source, event)
+            .await
+            .is_some()
+        {
+            accepted += 1;
+        }
+    }
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "accepted": accepted,
+            "duplicates": received - accepted,
+        })),
+    )
+}
+
+async fn oidc_discovery(State(state): State<AppState>) -> Json<serde_json::Value> {
+    Json(state.oidc.read().await.discovery())
+}
+
+async fn oidc_jwks(State(state): State<AppState>) -> Json<serde_json::Value> {
+    Json(state.oidc.read().await.jwks())
+}
+
+async fn register_oidc_client(
+    State(state): State<AppState>,
+    Json(registration): Json<oidc::ClientRegistration>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    match state.oidc.write().await.register_client(registration) {
+        Ok((client, client_secret)) => {
+            println!(
+                "🔐 OIDC client {} registered ({})",
+                client.client_id, client.name
+            );
+            (
+                StatusCode::CREATED,
+                Json(serde_json::json!({
+                    "client": client,
+                    "client_secret": client_secret,
+                    "note": "The client secret is shown once; store it now"
+                })),
+            )
+        }
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": e })),
+        ),
+    }
+}
+
+async fn oidc_authorize(
+    State(state): State<AppState>,
+    Query(request): Query<oidc::AuthorizeRequest>,
+) -> Result<Html<String>, (StatusCode, Json<serde_json::Value>)> {
+    let (challenge_id, message, client, scopes) = state
+        .oidc
+        .write()
+        .await
+        .begin_authorization(request)
+        .map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": e })),
+            )
+        })?;
+
+    Ok(Html(oidc::consent_page(
+        &challenge_id,
+        &message,
+        &client,
+        &scopes,
+    )))
+}
+
+async fn oidc_consent(
+    State(state): State<AppState>,
+    Form(submission): Form<oidc::ConsentSubmission>,
+) -> Result<Redirect, (StatusCode, Json<serde_json::Value>)> {
+    let wallet = submission.wallet_address.clone();
+    let redirect = state
+        .oidc
+        .write()
+        .await
+        .complete_authorization(submission)
+        .map_err(|e| {
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({ "error": e })),
+            )
+        })?;
+
+    println!("🔐 OIDC login approved for {}", wallet);
+    Ok(Redirect::to(&redirect))
+}
+
+async fn oidc_token(
+    State(state): State<AppState>,
+    Form(token_request): Form<oidc::TokenRequest>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    match state.oidc.write().await.exchange_code(token_request) {
+        Ok(tokens) => (StatusCode::OK, Json(serde_json::json!(tokens))),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": e })),
+        ),
+    }
+}
+
+async fn oidc_userinfo(
+    State(state): State<AppState>,
+    request: Request<Body>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+
+    let grant = match state.oidc.read().await.authorize_bearer(token, "openid") {
+        Ok(grant) => grant,
+        Err(e) => {
+            return (
+                StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({ "error": e })),
+            )
+        }
+    };
+
+    let mut userinfo = serde_json::json!({
+        "sub": grant.wallet_address,
+        "zos_capabilities": grant.scopes,
+    });
+    if grant.scopes.iter().any(|scope| scope == "profile") {
+        userinfo["identity"] =
+            serde_json::json!(state.identities.read().await.get(&grant.wallet_address));
+    }
+
+    (StatusCode::OK, Json(userinfo))
+}
+
+async fn verify_export_manifest(
+    Json(manifest): Json<data_export::ExportManifest>,
+) -> Json<serde_json::Value> {
+    let valid = data_export::verify_manifest(&data_export::signing_key(), &manifest);
+
+    Json(serde_json::json!({
+        "valid": valid,
+        "generated_at": manifest.generated_at,
+        "files": manifest.files.len()
+    }))
+}
+
+async fn request_deletion(
+    Path(wallet): Path<String>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if let Err(refusal) = require_data_access(&state, &headers, &wallet).await {
+        return refusal;
+    }
+    let pseudonym = data_export::pseudonymize_wallet(&wallet);
+    let now = chrono::Utc::now().timestamp() as u64;
+
+    // The gateway's ledger goes first: if it can't be reached nothing here
+    // is removed yet, and the request can simply be repeated
+    let gateway_records = match &state.config.gateway_url {
+        Some(gateway_url) => {
+            match data_export::erase_gateway_records(gateway_url, &wallet, &pseudonym).await {
+                Ok(changed) => changed,
+                Err(e) => {
+                    println!("❌ Deletion of {} stopped: {}", pseudonym, e);
+                    return (
+                        StatusCode::BAD_GATEWAY,
+                        Json(
+                            serde_json::json!({ "error": format!("Gateway records not erased: {}", e) }),
+                        ),
+                    );
+                }
+            }
+        }
+        None => 0,
+    };
+
+    // Sessions hold no ledger data, so they are dropped outright
+    let removed_sessions = {
+        let mut sessions = state.user_sessions.write().await;
+        let before = sessions.len();
+        sessions.retain(|_, session| session.wallet_address != wallet);
+        before - sessions.len()
+    };
+    if let Err(e) = state.ports.release_owner(&wallet, "session") {
+        println!("⚠️  Session ports of a deleted wallet stay recorded: {}", e);
+    }
+    let removed_identity = state.identities.write().await.remove(&wallet).is_some();
+    let removed_onboarding = state.onboarding.write().await.remove(&wallet).is_some();
+    let removed_messages = {
+        let mut messages = state.messages.write().await;
+        let before = messages.len();
+        messages.retain(|_, message| message.from != wallet && message.to != wallet);
+        before - messages.len()
+    };
+    let removed_messaging = state.messaging.write().await.remove(&wallet).is_some();
+    let removed_feed = state.activity.forget(&wallet).await;
+    let removed_sync = state.offline_sync.write().await.remove(&wallet).is_some();
+    let removed_pins = {
+        let mut pins = state.version_pins.write().await;
+        let before = pins.len();
+        pins.retain(|_, pin| pin.consumer != wallet);
+        before - pins.len()
+    };
+
+    let request = DeletionRequest {
+        request_id: format!("del_{}_{}", pseudonym, now),
+        pseudonym: pseudonym.clone(),
+        requested_at: now,
+        completed_at: Some(now),
+        status: DeletionStatus::Completed,
+        anonymized_records: removed_sessions
+            + removed_identity as usize
+            + removed_onboarding as usize
+            + removed_messages
+            + removed_messaging as usize
+            + removed_feed as usize
+            + removed_sync as usize
+            + removed_pins
+            + gateway_records,
+    };
+
+    state
+        .deletion_requests
+        .write()
+        .await
+        .insert(pseudonym.clone(), request.clone());
+
+    println!(
+        "🗑️  Deletion request {} completed ({} records)",
+        request.request_id, request.anonymized_records
+    );
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "request": request,
+            "note": "Ledger records are kept with the wallet replaced by its pseudonym"
+        })),
+    )
+}
 
-    let port = std::env::var("ZOS_HTTP_PORT").unwrap_or_else(|_| "8080".to_string());
+async fn deletion_status(
+    Path(wallet): Path<String>,
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if let Err(refusal) = require_data_access(&state, &headers, &wallet).await {
+        return refusal;
+    }
+    let pseudonym = data_export::pseudonymize_wallet(&wallet);
+    let requests = state.deletion_requests.read().await;
+
+    match requests.get(&pseudonym) {
+        Some(request) => (
+            StatusCode::OK,
+            Json(serde_json::json!({ "request": request })),
+        ),
+        None => (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "pseudonym": pseudonym,
+                "status": "not_found"
+            })),
+        ),
+    }
+}
 
-    Json(serde_json::json!({
-        "status": "healthy",
-        "version": "1.0.0-stage1",
-        "timestamp": chrono::Utc::now().to_rfc3339(),
-        "env": {
-            "cwd": cwd,
-            "port": port,
-            "pid": std::process::id(),
-            "binary_path": binary_path.to_string_lossy()
-        },
-        "git": {
-            "commit": git_commit,
-            "commit_short": git_commit_short,
-            "branch": git_branch,
-            "age": commit_age
-        },
-        "binary": {
-            "hash": binary_hash,
-            "hash_short": binary_hash_short
-        }
-    }))
+async fn service_call(
+    Path((wallet, service)): Path<(String, String)>,
+    State(state): State<AppState>,
+    request: Request<Body>,
+) -> Response {
+    dispatch_service_call(state, wallet, service, String::new(), request).await
 }
 
-async fn dashboard(Path(wallet): Path<String>) -> Html<String> {
-    Html(format!(
-        r#"
-    <html>
-    <head><title>ZOS Dashboard - {}</title></head>
-    <body style="font-family: Arial; margin: 0; padding: 20px; background: #f5f5f5;">
-        <h1>🎯 ZOS Dashboard</h1>
-        <p>Wallet: <code>{}</code></p>
+async fn service_call_path(
+    Path((wallet, service, rest)): Path<(String, String, String)>,
+    State(state): State<AppState>,
+    request: Request<Body>,
+) -> Response {
+    dispatch_service_call(state, wallet, service, rest, request).await
+}
 
-        <div style="background: white; padding: 20px; border-radius: 8px; margin: 20px 0;">
-            <h3>📊 Status</h3>
-            <p>Credits: <strong>100</strong></p>
-            <p>Port: <strong>None allocated</strong></p>
-            <button onclick="allocatePort()" style="background: #4CAF50; color: white; border: none; padding: 10px 20px; border-radius: 4px; cursor: pointer;">
-                Allocate Port
-            </button>
-        </div>
+fn bearer_token(headers: &axum::http::HeaderMap) -> Option<String> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::to_string)
+}
 
-        <div style="background: white; padding: 20px; border-radius: 8px; margin: 20px 0;">
-            <h3>🎮 Free Services</h3>
-            <button onclick="callService('pi')" style="margin: 5px; padding: 8px 16px; border: 1px solid #ddd; border-radius: 4px; cursor: pointer;">
-                🥧 Calculate Pi
-            </button>
-            <button onclick="callService('fibonacci')" style="margin: 5px; padding: 8px 16px; border: 1px solid #ddd; border-radius: 4px; cursor: pointer;">
-                🐰 Fibonacci
-            </button>
-            <button onclick="callService('primes')" style="margin: 5px; padding: 8px 16px; border: 1px solid #ddd; border-radius: 4px; cursor: pointer;">
-                🎭 Primes
-            </button>
-        </div>
+/// Wallet a `services:call` access token speaks for. Unlike X-Wallet-Address
+/// this cannot be claimed by someone else, so anything billed uses it.
+async fn token_wallet(state: &AppState, headers: &axum::http::HeaderMap) -> Option<String> {
+    let token = bearer_token(headers)?;
+    state
+        .oidc
+        .read()
+        .await
+        .authorize_bearer(&token, "services:call")
+        .ok()
+        .map(|grant| grant.wallet_address)
+}
 
-        <script>
-            async function allocatePort() {{
-                try {{
-                    const response = await fetch('/api/allocate-port', {{
-                        method: 'POST',
-                        headers: {{ 'Content-Type': 'application/json' }},
-                        body: JSON.stringify({{ wallet: '{}' }})
-                    }});
-                    const result = await response.json();
-                    alert('Port allocated: ' + result.port);
-                    location.reload();
-                }} catch (e) {{
-                    alert('Error: ' + e.message);
-                }}
-            }}
+/// Route a service call and log it for the service owner
+async fn dispatch_service_call(
+    state: AppState,
+    wallet: String,
+    service: String,
+    rest: String,
+    request: Request<Body>,
+) -> Response {
+    let started = Instant::now();
+    let service_key = format!("{}_{}", wallet, service);
+    let method = request.method().to_string();
+    let request_bytes = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok());
+    let consumer = match bearer_token(request.headers()) {
+        Some(_) => token_wallet(&state, request.headers()).await,
+        None => request
+            .headers()
+            .get("x-wallet-address")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string),
+    };
 
-            async function callService(service) {{
-                try {{
-                    const response = await fetch('/{}/'+service);
-                    const result = await response.json();
-                    alert(service + ' result: ' + JSON.stringify(result.result));
-                }} catch (e) {{
-                    alert('Error: ' + e.message);
-                }}
-            }}
-        </script>
-    </body>
-    </html>
-    "#,
-        wallet, wallet, wallet, wallet
-    ))
+    let response = route_service_call(&state, &wallet, &service, &rest, request).await;
+
+    state
+        .service_logs
+        .record(
+            &service_key,
+            ServiceLogEntry {
+                id: 0,
+                at: chrono::Utc::now().timestamp() as u64,
+                method,
+                path: format!("/{}", rest),
+                status: response.status().as_u16(),
+                latency_ms: started.elapsed().as_millis() as u64,
+                request_bytes,
+                response_bytes: axum::body::HttpBody::size_hint(response.body()).exact(),
+                consumer: consumer.map(|wallet| data_export::pseudonymize_wallet(&wallet)),
+                version: response
+                    .headers()
+                    .get(service_versions::VERSION_HEADER)
+                    .and_then(|value| value.to_str().ok())
+                    .map(str::to_string),
+            },
+        )
+        .await;
+    response
 }
 
-async fn allocate_port(
-    State(state): State<AppState>,
-    axum::Json(request): axum::Json<serde_json::Value>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    let wallet = request
-        .get("wallet")
-        .and_then(|w| w.as_str())
-        .ok_or(StatusCode::BAD_REQUEST)?;
+async fn route_service_call(
+    state: &AppState,
+    wallet: &str,
+    service: &str,
+    rest: &str,
+    request: Request<Body>,
+) -> Response {
+    let service_key = format!("{}_{}", wallet, service);
+    if service_health::is_delisted(state, &service_key).await {
+        let health = state.service_health.read().await.get(&service_key).cloned();
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({
+                "error": "Service unavailable",
+                "service": service,
+                "health": health
+            })),
+        )
+            .into_response();
+    }
 
-    let port = 20000 + (wallet.len() % 1000) as u16;
+    prewarm::record_request(state, &service_key, chrono::Utc::now().timestamp() as u64).await;
 
-    let mut sessions = state.user_sessions.write().await;
-    let session = sessions.entry(wallet.to_string()).or_insert(UserSession {
-        wallet_address: wallet.to_string(),
-        allocated_port: None,
-        credits: 100,
-        last_activity: chrono::Utc::now().timestamp() as u64,
-    });
+    // Apps signed in through OIDC call as the user's wallet
+    let mut request = request;
+    if let Some(token) = bearer_token(request.headers()) {
+        match state
+            .oidc
+            .read()
+            .await
+            .authorize_bearer(&token, "services:call")
+        {
+            Ok(grant) => {
+                let headers = request.headers_mut();
+                headers.remove(header::AUTHORIZATION);
+                if let Ok(value) = grant.wallet_address.parse() {
+                    headers.insert("x-wallet-address", value);
+                }
+            }
+            Err(e) => {
+                return (
+                    StatusCode::UNAUTHORIZED,
+                    Json(serde_json::json!({ "error": e })),
+                )
+                    .into_response()
+            }
+        }
+    }
 
-    session.allocated_port = Some(port);
-    session.last_activity = chrono::Utc::now().timestamp() as u64;
+    // Registered services get their bodies streamed straight through
+    let registered = state.services.read().await.get(&service_key).cloned();
+    if let Some(mut registered) = registered {
+        // The version asked for, else the caller's pin, else the current one;
+        // versions deployed separately are answered from their own port
+        let requested = request
+            .headers()
+            .get(service_versions::VERSION_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let pinned = match request
+            .headers()
+            .get("x-wallet-address")
+            .and_then(|value| value.to_str().ok())
+        {
+            Some(consumer) => state
+                .version_pins
+                .read()
+                .await
+                .get(&VersionPin::key(consumer, &service_key))
+                .map(|pin| pin.version.clone()),
+            None => None,
+        };
+        let version = {
+            let versions = state.service_versions.read().await;
+            // Calls that ask for nothing in particular share the canary
+            let canary = versions
+                .get(&service_key)
+                .and_then(|versions| versions.canary.as_ref())
+                .filter(|canary| {
+                    requested.is_none() && pinned.is_none() && canary::routes_to_canary(canary)
+                })
+                .map(|canary| canary.version.clone());
+            let resolved = service_versions::resolve(
+                versions.get(&service_key),
+                requested.as_deref().or(canary.as_deref()),
+                pinned.as_deref(),
+                chrono::Utc::now().timestamp() as u64,
+            );
+            match resolved {
+                Ok(version) => version.cloned(),
+                Err((status, e)) => {
+                    return (
+                        status,
+                        Json(serde_json::json!({ "error": e, "service": service })),
+                    )
+                        .into_response()
+                }
+            }
+        };
+        if let Some(port) = version.as_ref().and_then(|version| version.port) {
+            registered.port = port;
+        }
 
-    println!("🔌 Port {} allocated to {}", port, &wallet[..8]);
+        // The backend continues the trace under its own span
+        let node_span = request.extensions().get::<TraceParent>().cloned();
+        let backend = node_span.as_ref().map(|parent| {
+            let context = parent.child();
+            if let Ok(value) = context.header_value().parse() {
+                request.headers_mut().insert(TRACEPARENT, value);
+            }
+            let mut open = OpenSpan::start(&context, Some(parent), "proxy.forward", "backend");
+            open.span
+                .attributes
+                .insert("service".to_string(), service_key.clone());
+            open
+        });
 
-    Ok(Json(serde_json::json!({
-        "success": true,
-        "port": port,
-        "expires_in_seconds": 300
-    })))
+        let result = proxy::forward(&state.proxy_client, &registered, rest, request).await;
+
+        if let Some(open) = backend {
+            let span = match &result {
+                Ok(response) => open.finish(Some(response.status().as_u16()), None),
+                Err(e) => open.finish(None, Some(e.clone())),
+            };
+            if node_span.is_some_and(|parent| parent.sampled) {
+                state.spans.record(span).await;
+            }
+        }
+
+        return match result {
+            Ok(mut response) => {
+                if let Some(version) = &version {
+                    service_versions::annotate(response.headers_mut(), version);
+                }
+                response
+            }
+            Err(e) => (
+                StatusCode::BAD_GATEWAY,
+                Json(serde_json::json!({ "error": e, "service": service })),
+            )
+                .into_response(),
+        };
+    }
+
+    builtin_service_call(wallet, service).into_response()
 }
 
-async fn user_status(
-    Path(wallet): Path<String>,
+/// GET /{wallet}/{service}/logs — the owner's request log, newest first, or
+/// a live tail over SSE with `follow=true`
+async fn service_logs(
+    Path((wallet, service)): Path<(String, String)>,
     State(state): State<AppState>,
-) -> Json<serde_json::Value> {
-    let sessions = state.user_sessions.read().await;
+    Query(query): Query<LogQuery>,
+    headers: axum::http::HeaderMap,
+) -> Response {
+    let token = bearer_token(&headers).unwrap_or_default();
+    let grant = match state
+        .oidc
+        .read()
+        .await
+        .authorize_bearer(&token, "services:logs")
+    {
+        Ok(grant) => grant,
+        Err(e) => {
+            return (
+                StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({ "error": e })),
+            )
+                .into_response()
+        }
+    };
+    if grant.wallet_address != wallet {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({ "error": "Only the service owner can read its logs" })),
+        )
+            .into_response();
+    }
 
-    if let Some(session) = sessions.get(&wallet) {
-        Json(serde_json::json!({
-            "wallet": wallet,
-            "credits": session.credits,
-            "allocated_port": session.allocated_port,
-            "last_activity": session.last_activity
-        }))
-    } else {
-        Json(serde_json::json!({
-            "wallet": wallet,
-            "status": "not_found"
+    let service_key = format!("{}_{}", wallet, service);
+    if !query.follow.unwrap_or(false) {
+        let entries = state.service_logs.query(&service_key, &query).await;
+        let (stored, bytes_used) = state.service_logs.usage(&service_key).await;
+        return Json(serde_json::json!({
+            "service": service,
+            "entries": entries,
+            "stored": stored,
+            "bytes_used": bytes_used,
+            "quota_bytes": state.service_logs.quota_bytes
         }))
+        .into_response();
     }
+
+    // Reconnecting clients pick up where they left off
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(u64::MAX);
+    let (backlog, live) = state
+        .service_logs
+        .subscribe(&service_key, last_event_id)
+        .await;
+
+    let backlog = futures_util::stream::iter(backlog);
+    let live = futures_util::stream::unfold(live, |mut live| async move {
+        loop {
+            match live.recv().await {
+                Ok(entry) => return Some((entry, live)),
+                // A slow reader misses entries rather than stalling the service
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+    let events = futures_util::StreamExt::filter_map(
+        futures_util::StreamExt::chain(backlog, live),
+        move |entry| {
+            let event = query
+                .matches(&entry)
+                .then(|| Event::default().id(entry.id.to_string()).json_data(&entry));
+            async move { event }
+        },
+    );
+
+    Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response()
 }
 
-async fn service_call(
-    Path((wallet, service)): Path<(String, String)>,
-    State(_state): State<AppState>,
-) -> Json<serde_json::Value> {
+fn builtin_service_call(wallet: &str, service: &str) -> (StatusCode, Json<serde_json::Value>) {
     // Simple service implementations
-    let result = match service.as_str() {
+    let result = match service {
         "pi" => "π ≈ 3.1415926536 (calculated using Leibniz formula)".to_string(),
         "fibonacci" => "🐰 Fibonacci sequence: 1, 1, 2, 3, 5, 8, 13, 21, 34, 55...".to_string(),
         "primes" => "🎭 Prime numbers: 2, 3, 5, 7, 11, 13, 17, 19, 23, 29...".to_string(),
@@ -583,12 +5540,15 @@ async fn service_call(
 
     println!("🎯 Service call: {} -> {}", service, &wallet[..8]);
 
-    Json(serde_json::json!({
-        "service": service,
-        "wallet": wallet,
-        "result": result,
-        "timestamp": chrono::Utc::now().to_rfc3339()
-    }))
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "service": service,
+            "wallet": wallet,
+            "result": result,
+            "timestamp": chrono::Utc::now().to_rfc3339()
+        })),
+    )
 }
 
 #[derive(Debug, Deserialize)]
@@ -654,6 +5614,7 @@ struct DeployRequest {
     rebuild_self: bool,
     prepare_windows: bool,
     deploy_method: Option<String>, // "systemd", "binary", "docker"
+    port_capacity: Option<u16>,    // ports the instance may hand out; default 1000
 }
 
 #[derive(Debug, Serialize)]
@@ -664,11 +5625,45 @@ struct DeployResponse {
     message: String,
 }
 
-async fn deploy_zos2(Json(req): Json<DeployRequest>) -> Json<DeployResponse> {
+async fn deploy_zos2(
+    State(state): State<AppState>,
+    Json(req): Json<DeployRequest>,
+) -> Json<DeployResponse> {
     println!("🚀 ZOS1 deploying ZOS2 instance: {}", req.instance_name);
 
     let instance_name = req.instance_name.clone();
     let target_port = req.target_port;
+
+    // Partition the host's ports before the instance starts, so it cannot
+    // collide with this one or any other deployed alongside
+    let port_capacity = req.port_capacity.unwrap_or(state.ports.capacity);
+    match state
+        .ports
+        .register_instance(&instance_name, port_capacity, target_port)
+    {
+        Ok(partition) => println!(
+            "🔌 {} gets {} ports",
+            instance_name,
+            partition
+                .ranges
+                .iter()
+                .map(|range| range.len() as u32)
+                .sum::<u32>()
+        ),
+        Err(e) => {
+            return Json(DeployResponse {
+                status: "error".to_string(),
+                instance_name,
+                port: target_port,
+                message: format!("Port authority refused the instance: {}", e),
+            })
+        }
+    }
+    let port_authority = state.ports.path.clone();
+    let port_authority_dir = std::path::Path::new(&port_authority)
+        .parent()
+        .map(|dir| dir.display().to_string())
+        .unwrap_or_else(|| "/var/lib/zos".to_string());
     let deploy_method = req
         .deploy_method
         .clone()
@@ -678,6 +5673,7 @@ async fn deploy_zos2(Json(req): Json<DeployRequest>) -> Json<DeployResponse> {
 
     // Deploy ZOS2 instance
     let deploy_result = tokio::spawn(async move {
+        let instance = &req.instance_name;
         let script = if deploy_method == "systemd" {
             format!(
                 r#"#!/bin/bash
@@ -691,6 +5687,8 @@ cargo build --release --bin zos-minimal-server
 sudo useradd -r -s /bin/false -d /opt/{} -m {} 2>/dev/null || true
 sudo mkdir -p /opt/{}/{{bin,data,config,logs}}
 sudo chown -R {}:{} /opt/{}
+# Every instance on the host updates the shared port ledger
+sudo install -d -m 0777 {port_authority_dir}
 
 # Install ZOS2 binary
 sudo cp target/release/zos-minimal-server /opt/{}/bin/
@@ -714,12 +5712,15 @@ RestartSec=5
 Environment=ZOS_HTTP_PORT={}
 Environment=ZOS_DATA_DIR=/opt/{}/data
 Environment=ZOS_LOG_LEVEL=info
+Environment=ZOS_INSTANCE_NAME={instance}
+Environment=ZOS_PORT_CAPACITY={port_capacity}
+Environment=ZOS_PORT_AUTHORITY={port_authority}
 
 NoNewPrivileges=true
 PrivateTmp=true
 ProtectSystem=strict
 ProtectHome=true
-ReadWritePaths=/opt/{}/data /opt/{}/logs
+ReadWritePaths=/opt/{}/data /opt/{}/logs {port_authority_dir}
 
 [Install]
 WantedBy=multi-user.target
@@ -766,6 +5767,8 @@ cargo build --release --bin zos-minimal-server
 sudo useradd -r -s /bin/false -d /opt/{} -m {} 2>/dev/null || true
 sudo mkdir -p /opt/{}/{{bin,data,config,logs}}
 sudo chown -R {}:{} /opt/{}
+# Every instance on the host updates the shared port ledger
+sudo install -d -m 0777 {port_authority_dir}
 
 # Install ZOS2 binary
 sudo cp target/release/zos-minimal-server /opt/{}/bin/
@@ -789,12 +5792,15 @@ RestartSec=5
 Environment=ZOS_HTTP_PORT={}
 Environment=ZOS_DATA_DIR=/opt/{}/data
 Environment=ZOS_LOG_LEVEL=info
+Environment=ZOS_INSTANCE_NAME={instance}
+Environment=ZOS_PORT_CAPACITY={port_capacity}
+Environment=ZOS_PORT_AUTHORITY={port_authority}
 
 NoNewPrivileges=true
 PrivateTmp=true
 ProtectSystem=strict
 ProtectHome=true
-ReadWritePaths=/opt/{}/data /opt/{}/logs
+ReadWritePaths=/opt/{}/data /opt/{}/logs {port_authority_dir}
 
 [Install]
 WantedBy=multi-user.target
@@ -846,7 +5852,7 @@ echo "✅ ZOS2 deployed successfully"
                     if req.rebuild_self {
                         tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
                         let rebuild_url = format!("http://localhost:{}/rebuild", req.target_port);
-                        let _ = reqwest::Client::new()
+                        let _ = http_clients::shared()
                             .post(&rebuild_url)
                             .json(&serde_json::json!({"prepare_windows": req.prepare_windows}))
                             .send()
@@ -1004,15 +6010,23 @@ async fn serve_source() -> Json<serde_json::Value> {
             "/source": "Source information (this endpoint)",
             "/install.sh": "Installation script",
             "/tarball": "Source tarball download",
+            "/releases": "Published source releases and peer mirrors",
+            "/blobs/{hash}": "Release tarball by sha256",
             "/health": "Health check",
             "/deploy": "Deploy new ZOS instance"
         }
     }))
 }
 
-async fn serve_installer() -> Response<String> {
+async fn serve_installer(State(state): State<AppState>) -> Response<String> {
     println!("🚀 Serving ZOS installer script");
 
+    let release = source_distribution::latest_release(&state).await;
+    let mirrors = match &release {
+        Some(release) => source_distribution::mirrors_for(&state, &release.hash).await,
+        None => Vec::new(),
+    };
+
     let installer_script = r#"#!/bin/bash
 # ZOS Universal Installer - Reproducible Binary Installation
 
@@ -1021,6 +6035,7 @@ set -e
 echo "🚀 ZOS Universal Installer"
 echo "=========================="
 
+@SOURCE@
 # Get system info
 ARCH=$(uname -m)
 OS=$(uname -s)
@@ -1096,7 +6111,11 @@ echo "🌐 Start your server: zos-server serve 8080"
             header::CONTENT_DISPOSITION,
             "attachment; filename=\"install.sh\"",
         )
-        .body(installer_script.to_string())
+        .body(installer_script.replacen(
+            "@SOURCE@",
+            &source_distribution::installer_source_section(release.as_ref(), &mirrors),
+            1,
+        ))
         .unwrap()
 }
 
@@ -1123,7 +6142,7 @@ async fn serve_installer_branch(Path(branch): Path<String>) -> Response<String>
         .unwrap()
 }
 
-async fn serve_tarball() -> Result<Vec<u8>, StatusCode> {
+async fn serve_tarball(State(state): State<AppState>) -> Result<Vec<u8>, StatusCode> {
     println!("📦 Creating and serving ZOS tarball from clean git checkout");
 
     // Create clean checkout directory
@@ -1178,6 +6197,14 @@ async fn serve_tarball() -> Result<Vec<u8>, StatusCode> {
         return Err(StatusCode::INTERNAL_SERVER_ERROR);
     }
 
+    let git_commit = tokio::process::Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .current_dir(checkout_dir)
+        .output()
+        .await
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .unwrap_or_else(|_| "unknown".to_string());
+
     // Clean up checkout directory
     let _ = tokio::process::Command::new("rm")
         .args(&["-rf", checkout_dir])
@@ -1185,8 +6212,51 @@ async fn serve_tarball() -> Result<Vec<u8>, StatusCode> {
         .await;
 
     // Read the tarball
-    tokio::fs::read(tarball_path)
+    let tarball = tokio::fs::read(tarball_path)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Publish it so peers can replicate it and installers can fetch it from them
+    match source_distribution::publish(&state, &tarball, &git_commit, "local").await {
+        Ok(release) => println!("📦 Published release {}", release.hash),
+        Err(e) => println!("⚠️  Could not publish release: {}", e),
+    }
+    Ok(tarball)
+}
+
+/// GET /releases — published releases, newest first, and peer health
+async fn list_releases(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let mut releases: Vec<Release> = state.releases.read().await.values().cloned().collect();
+    releases.sort_by_key(|release| std::cmp::Reverse(release.published_at));
+    let mut peers: Vec<PeerStatus> = state.source_peers.read().await.values().cloned().collect();
+    peers.sort_by(|a, b| a.url.cmp(&b.url));
+
+    Json(serde_json::json!({ "releases": releases, "peers": peers }))
+}
+
+/// GET /releases/latest — what peers replicate from each other
+async fn latest_release(State(state): State<AppState>) -> Result<Json<Release>, StatusCode> {
+    source_distribution::latest_release(&state)
         .await
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// GET /blobs/:hash — a release tarball, served only if it still matches its hash
+async fn serve_blob(Path(hash): Path<String>) -> Result<Response<Body>, StatusCode> {
+    if !source_distribution::is_hash(&hash) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let bytes = source_distribution::read_blob(&hash)
+        .await
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/gzip")
+        .header(header::ETAG, format!("\"{}\"", hash))
+        .header(header::CACHE_CONTROL, "public, max-age=31536000, immutable")
+        .body(Body::from(bytes))
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
 }
 
@@ -1527,26 +6597,48 @@ async fn get_git_info() -> serde_json::Value {
     })
 }
 
-async fn background_tasks(state: AppState) {
-    let mut interval = interval(Duration::from_secs(60));
-
-    loop {
-        interval.tick().await;
+async fn housekeeping(state: AppState) -> Result<(), String> {
+    // Clean up old sessions
+    let mut sessions = state.user_sessions.write().await;
+    let current_time = chrono::Utc::now().timestamp() as u64;
+
+    let before_count = sessions.len();
+    let mut lapsed = Vec::new();
+    sessions.retain(|wallet, session| {
+        let keep = current_time - session.last_activity < 3600; // Keep for 1 hour
+        if !keep {
+            lapsed.push(wallet.clone());
+        }
+        keep
+    });
+    let after_count = sessions.len();
+    for wallet in lapsed {
+        if let Err(e) = state.ports.release_owner(&wallet, "session") {
+            println!(
+                "⚠️  Session port of {} stays recorded: {}",
+                &wallet[..8.min(wallet.len())],
+                e
+            );
+        }
+    }
 
-        // Clean up old sessions
-        let mut sessions = state.user_sessions.write().await;
-        let current_time = chrono::Utc::now().timestamp() as u64;
+    if before_count != after_count {
+        println!("🧹 Cleaned up {} old sessions", before_count - after_count);
+    }
+    drop(sessions);
 
-        let before_count = sessions.len();
-        sessions.retain(|_, session| {
-            current_time - session.last_activity < 3600 // Keep for 1 hour
-        });
-        let after_count = sessions.len();
+    let expired = messaging::purge_expired(&mut *state.messages.write().await, current_time);
+    if expired > 0 {
+        println!("🧹 Dropped {} expired messages", expired);
+    }
 
-        if before_count != after_count {
-            println!("🧹 Cleaned up {} old sessions", before_count - after_count);
-        }
+    // Dev mode state lives in memory only
+    if dev_mode::active().is_some() {
+        return Ok(());
     }
+    migrations::save_state(&state)
+        .await
+        .map_err(|e| format!("Failed to persist state: {}", e))
 }
 
 // Client tracking middleware
@@ -1873,6 +6965,63 @@ fi
     }))
 }
 
+#[derive(Debug, Deserialize)]
+struct TraceViewQuery {
+    format: Option<String>,
+}
+
+/// GET /api/v1/traces/{trace_id} — the span tree of one request across the
+/// gateway, libp2p, this node and the backend; `?format=html` to browse it
+async fn view_trace(
+    Path(trace_id): Path<String>,
+    State(state): State<AppState>,
+    Query(query): Query<TraceViewQuery>,
+) -> Response {
+    let spans = state.spans.spans(&trace_id).await;
+    if spans.is_empty() {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Trace not found" })),
+        )
+            .into_response();
+    }
+
+    if query.format.as_deref() == Some("html") {
+        return Html(distributed_tracing::render_html(&trace_id, &spans)).into_response();
+    }
+    Json(serde_json::json!({
+        "trace_id": trace_id,
+        "span_count": spans.len(),
+        "tree": distributed_tracing::span_tree(&spans)
+    }))
+    .into_response()
+}
+
+/// POST /api/v1/traces/spans — spans from the gateway and backend services
+async fn collect_spans(
+    State(state): State<AppState>,
+    Json(spans): Json<Vec<Span>>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let invalid = spans.iter().any(|span| {
+        TraceParent::parse(&format!("00-{}-{}-01", span.trace_id, span.span_id)).is_none()
+    });
+    if invalid {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "Spans need W3C trace and span ids" })),
+        );
+    }
+
+    let accepted = spans.len();
+    for span in spans {
+        state.spans.record(span).await;
+    }
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "accepted": accepted })),
+    )
+}
+
 async fn get_traces(State(state): State<AppState>) -> Json<serde_json::Value> {
     let _trace = state.tracer.start_trace("get_traces");
 
@@ -2193,7 +7342,8 @@ async fn network_status_command() -> Result<(), Box<dyn std::error::Error>> {
         let name = known_names[i];
         print!("{} ({}): ", name, port);
 
-        match reqwest::get(&format!("http://localhost:{}/health", port)).await {
+        let health_url = format!("http://localhost:{}/health", port);
+        match http_clients::shared().get(&health_url).send().await {
             Ok(response) if response.status().is_success() => {
                 if let Ok(health) = response.json::<serde_json::Value>().await {
                     let status = health["status"].as_str().unwrap_or("unknown");
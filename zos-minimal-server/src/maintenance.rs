@@ -0,0 +1,240 @@
+// Scheduled maintenance windows: deploys run inside them, other traffic is
+// held briefly or turned away with Retry-After, and work in progress is
+// checkpointed when one opens
+// AGPL-3.0 License
+
+use crate::batch_jobs::{self, JobStatus};
+use crate::AppState;
+use axum::body::Body;
+use axum::extract::State;
+use axum::http::{header, Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Longest a window may be scheduled for
+pub const MAX_WINDOW_SECS: u64 = 6 * 3600;
+
+/// Longest a request is held waiting for a window to close
+pub const MAX_QUEUE_SECS: u64 = 30;
+
+/// Requests held at once; beyond this they get 503 straight away
+pub const MAX_HELD: usize = 256;
+
+/// Paths served during a window: health, the deploy machinery itself and
+/// the maintenance API
+const CRITICAL_PREFIXES: &[&str] = &[
+    "/health",
+    "/ping",
+    "/api/v1/maintenance",
+    "/api/v1/tasks",
+    "/api/v1/doctor",
+    "/api/v1/introspect",
+    "/deploy",
+    "/rebuild",
+    "/update-self",
+    "/webhook/git",
+    "/poll-git",
+    "/install.sh",
+    "/install",
+    "/tarball",
+    "/releases",
+    "/blobs",
+    "/dashboard", // for the countdown banner
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceWindow {
+    pub id: String,
+    pub starts_at: u64,
+    pub ends_at: u64,
+    pub reason: String,
+    pub queue_secs: u64, // hold requests this long when the window closes within it
+    pub created_at: u64,
+    pub checkpointed_at: Option<u64>,
+    pub cancelled_at: Option<u64>,
+}
+
+impl MaintenanceWindow {
+    pub fn is_active(&self, now: u64) -> bool {
+        self.cancelled_at.is_none() && self.starts_at <= now && now < self.ends_at
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WindowRequest {
+    pub starts_at: u64,
+    pub ends_at: u64,
+    pub reason: String,
+    #[serde(default = "default_queue_secs")]
+    pub queue_secs: u64,
+}
+
+fn default_queue_secs() -> u64 {
+    10
+}
+
+/// Requests being held for a window to close
+#[derive(Debug, Clone, Default)]
+pub struct HeldRequests(Arc<AtomicUsize>);
+
+impl HeldRequests {
+    pub fn held(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+struct Held(HeldRequests);
+
+impl Drop for Held {
+    fn drop(&mut self) {
+        self.0 .0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+pub fn active_window(
+    windows: &HashMap<String, MaintenanceWindow>,
+    now: u64,
+) -> Option<&MaintenanceWindow> {
+    windows.values().find(|window| window.is_active(now))
+}
+
+/// The next window that has not started, for the dashboard countdown
+pub fn next_window(
+    windows: &HashMap<String, MaintenanceWindow>,
+    now: u64,
+) -> Option<&MaintenanceWindow> {
+    windows
+        .values()
+        .filter(|window| window.cancelled_at.is_none() && window.starts_at > now)
+        .min_by_key(|window| window.starts_at)
+}
+
+pub fn validate(
+    windows: &HashMap<String, MaintenanceWindow>,
+    request: &WindowRequest,
+    now: u64,
+) -> Result<(), String> {
+    if request.ends_at <= request.starts_at || request.ends_at <= now {
+        return Err("A window must end after it starts and in the future".to_string());
+    }
+    if request.ends_at - request.starts_at > MAX_WINDOW_SECS {
+        return Err(format!("Windows are at most {} seconds", MAX_WINDOW_SECS));
+    }
+    if request.queue_secs > MAX_QUEUE_SECS {
+        return Err(format!("queue_secs is at most {}", MAX_QUEUE_SECS));
+    }
+    if request.reason.trim().is_empty() {
+        return Err("A reason is required".to_string());
+    }
+    let overlapping = windows.values().find(|window| {
+        window.cancelled_at.is_none()
+            && window.ends_at > now
+            && request.starts_at < window.ends_at
+            && window.starts_at < request.ends_at
+    });
+    match overlapping {
+        Some(window) => Err(format!("Overlaps window {}", window.id)),
+        None => Ok(()),
+    }
+}
+
+fn is_critical(path: &str) -> bool {
+    CRITICAL_PREFIXES
+        .iter()
+        .any(|prefix| path == *prefix || path.starts_with(&format!("{}/", prefix)))
+}
+
+fn unavailable(window: &MaintenanceWindow, now: u64) -> Response {
+    let retry_after = window.ends_at.saturating_sub(now).max(1);
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [(header::RETRY_AFTER, retry_after.to_string())],
+        axum::Json(serde_json::json!({
+            "error": "Down for maintenance",
+            "reason": window.reason,
+            "ends_at": window.ends_at,
+            "retry_after_secs": retry_after
+        })),
+    )
+        .into_response()
+}
+
+/// Let critical requests through a window; hold the rest while the window
+/// is about to close, otherwise answer 503 with Retry-After
+pub async fn gate(State(state): State<AppState>, request: Request<Body>, next: Next) -> Response {
+    if is_critical(request.uri().path()) {
+        return next.run(request).await;
+    }
+    let now = chrono::Utc::now().timestamp() as u64;
+    let window = match active_window(&*state.maintenance_windows.read().await, now) {
+        Some(window) => window.clone(),
+        None => return next.run(request).await,
+    };
+
+    let remaining = window.ends_at.saturating_sub(now);
+    if remaining > window.queue_secs || state.maintenance_held.held() >= MAX_HELD {
+        return unavailable(&window, now);
+    }
+    state.maintenance_held.0.fetch_add(1, Ordering::Relaxed);
+    let _held = Held(state.maintenance_held.clone());
+
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(window.queue_secs);
+    while tokio::time::Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let now = chrono::Utc::now().timestamp() as u64;
+        let windows = state.maintenance_windows.read().await;
+        if active_window(&windows, now).is_none() {
+            drop(windows);
+            return next.run(request).await;
+        }
+    }
+    unavailable(&window, chrono::Utc::now().timestamp() as u64)
+}
+
+/// Jobs cannot be serialized mid-run, so running ones are stopped and put
+/// back in the queue to start again once the window closes
+fn checkpoint_jobs(jobs: &mut HashMap<String, batch_jobs::BatchJob>) -> Result<usize, String> {
+    let mut requeued = 0;
+    for job in jobs.values_mut() {
+        if !matches!(job.status, JobStatus::Running | JobStatus::Paused) {
+            continue;
+        }
+        if let Some(pid) = job.pid.take() {
+            batch_jobs::signal(pid, "KILL")?;
+        }
+        job.status = JobStatus::Queued;
+        job.started_at = None;
+        job.pause_reason = None;
+        job.checkpoints += 1;
+        requeued += 1;
+    }
+    Ok(requeued)
+}
+
+/// Checkpoint once when a window opens: requeue analysis jobs and persist
+/// every store, user sessions included, so a deploy can restart the node
+pub async fn check_windows(state: AppState) -> Result<(), String> {
+    let now = chrono::Utc::now().timestamp() as u64;
+    let opening = active_window(&*state.maintenance_windows.read().await, now)
+        .filter(|window| window.checkpointed_at.is_none())
+        .map(|window| window.id.clone());
+    let Some(id) = opening else {
+        return Ok(());
+    };
+
+    let requeued = checkpoint_jobs(&mut *state.batch_jobs.write().await)?;
+    if let Some(window) = state.maintenance_windows.write().await.get_mut(&id) {
+        window.checkpointed_at = Some(now);
+    }
+    crate::migrations::save_state(&state).await?;
+    println!(
+        "🛠️  Maintenance window {} open: {} jobs requeued, state checkpointed",
+        id, requeued
+    );
+    Ok(())
+}
@@ -0,0 +1,202 @@
+// Wallet-to-wallet messages: end-to-end encrypted by the clients, held here
+// until read or expired, announced over Telegram if the recipient wants
+// AGPL-3.0 License
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+pub const DEFAULT_TTL_SECS: u64 = 7 * 86400;
+pub const MAX_TTL_SECS: u64 = 30 * 86400;
+pub const MAX_CIPHERTEXT_BYTES: usize = 16 * 1024;
+pub const MAX_INBOX: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageKind {
+    VouchRequest,
+    Dispute,
+    MarketplaceInquiry,
+    General,
+}
+
+/// NaCl box (X25519, XSalsa20-Poly1305) sealed by the sender for the
+/// recipient's published key; this node never sees the plaintext
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Envelope {
+    pub sender_public_key: String, // base64, 32 bytes
+    pub nonce: String,             // base64, 24 bytes
+    pub ciphertext: String,        // base64
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Message {
+    pub id: String,
+    pub from: String,
+    pub to: String,
+    pub kind: MessageKind,
+    pub context: Option<String>, // service key, dispute id, vouch id, ...
+    pub envelope: Envelope,
+    pub sent_at: u64,
+    pub expires_at: u64,
+    pub read_at: Option<u64>,
+}
+
+/// A wallet's messaging preferences
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MessagingSettings {
+    pub public_key: Option<String>, // base64 X25519 key senders encrypt to
+    #[serde(default)]
+    pub telegram_relay: bool, // DM the linked Telegram account on new mail
+    #[serde(default)]
+    pub blocked: BTreeSet<String>, // wallets whose messages are refused
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SendRequest {
+    pub to: String,
+    pub kind: MessageKind,
+    pub context: Option<String>,
+    pub envelope: Envelope,
+    pub ttl_secs: Option<u64>,
+}
+
+fn decoded_len(field: &str, value: &str) -> Result<usize, String> {
+    STANDARD
+        .decode(value)
+        .map(|bytes| bytes.len())
+        .map_err(|_| format!("{} is not base64", field))
+}
+
+pub fn validate_public_key(key: &str) -> Result<(), String> {
+    match decoded_len("public_key", key)? {
+        32 => Ok(()),
+        _ => Err("public_key must be a 32-byte X25519 key".to_string()),
+    }
+}
+
+fn validate_envelope(envelope: &Envelope) -> Result<(), String> {
+    if decoded_len("sender_public_key", &envelope.sender_public_key)? != 32 {
+        return Err("sender_public_key must be 32 bytes".to_string());
+    }
+    if decoded_len("nonce", &envelope.nonce)? != 24 {
+        return Err("nonce must be 24 bytes".to_string());
+    }
+    match decoded_len("ciphertext", &envelope.ciphertext)? {
+        0 => Err("ciphertext is empty".to_string()),
+        len if len > MAX_CIPHERTEXT_BYTES => Err(format!(
+            "ciphertext is larger than {} bytes",
+            MAX_CIPHERTEXT_BYTES
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Check and store a message from `from`. Both wallets must have linked an
+/// identity here, and the recipient must have published a key and not
+/// blocked the sender.
+pub fn send(
+    messages: &mut HashMap<String, Message>,
+    settings: &HashMap<String, MessagingSettings>,
+    linked: impl Fn(&str) -> bool,
+    from: &str,
+    request: SendRequest,
+    now: u64,
+) -> Result<Message, String> {
+    if request.to == from {
+        return Err("Cannot message yourself".to_string());
+    }
+    if !linked(from) {
+        return Err("Link an identity before sending messages".to_string());
+    }
+    if !linked(&request.to) {
+        return Err("Recipient has no linked identity".to_string());
+    }
+    let recipient = settings
+        .get(&request.to)
+        .filter(|settings| settings.public_key.is_some())
+        .ok_or("Recipient has not published a messaging key")?;
+    if recipient.blocked.contains(from) {
+        return Err("Recipient does not accept your messages".to_string());
+    }
+    validate_envelope(&request.envelope)?;
+
+    let inbox = messages
+        .values()
+        .filter(|message| message.to == request.to)
+        .count();
+    if inbox >= MAX_INBOX {
+        return Err("Recipient's inbox is full".to_string());
+    }
+
+    let ttl = request
+        .ttl_secs
+        .unwrap_or(DEFAULT_TTL_SECS)
+        .clamp(60, MAX_TTL_SECS);
+    let message = Message {
+        id: format!("msg_{}_{:08x}", now, rand::random::<u32>()),
+        from: from.to_string(),
+        to: request.to,
+        kind: request.kind,
+        context: request.context,
+        envelope: request.envelope,
+        sent_at: now,
+        expires_at: now + ttl,
+        read_at: None,
+    };
+    messages.insert(message.id.clone(), message.clone());
+    Ok(message)
+}
+
+/// A wallet's unexpired messages, newest first
+pub fn inbox(messages: &HashMap<String, Message>, wallet: &str, now: u64) -> Vec<Message> {
+    let mut inbox: Vec<Message> = messages
+        .values()
+        .filter(|message| message.to == wallet && message.expires_at > now)
+        .cloned()
+        .collect();
+    inbox.sort_by_key(|message| std::cmp::Reverse(message.sent_at));
+    inbox
+}
+
+/// Drop expired messages; returns how many
+pub fn purge_expired(messages: &mut HashMap<String, Message>, now: u64) -> usize {
+    let before = messages.len();
+    messages.retain(|_, message| message.expires_at > now);
+    before - messages.len()
+}
+
+/// Tell the recipient on Telegram that mail arrived. Only the sender and
+/// kind go out; the content stays encrypted in the inbox.
+pub async fn relay_to_telegram(
+    telegram_id: i64,
+    message: &Message,
+    domain: &str,
+) -> Result<(), String> {
+    let text = format!(
+        "📨 New {:?} message from {} — read it in your inbox at https://{}/dashboard/{}",
+        message.kind, message.from, domain, message.to
+    );
+    if crate::dev_mode::capture_telegram(&telegram_id.to_string(), &text) {
+        return Ok(());
+    }
+    let token = crate::secrets::resolve("ZOS_TELEGRAM_BOT_TOKEN")
+        .ok_or("ZOS_TELEGRAM_BOT_TOKEN is not set")?;
+
+    let clients = crate::http_clients::shared();
+    let request = clients
+        .post(&format!(
+            "https://api.telegram.org/bot{}/sendMessage",
+            token
+        ))
+        .json(&serde_json::json!({ "chat_id": telegram_id, "text": text }));
+    let response = clients
+        .send_with_retry(request)
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("Telegram answered {}", response.status()));
+    }
+    Ok(())
+}
@@ -0,0 +1,518 @@
+// Versioned on-disk state with ordered schema migrations
+// AGPL-3.0 License
+
+use crate::AppState;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Current schema version of every persisted store. Bump the version and
+/// register a migration from the previous one whenever a stored type changes.
+pub const STORES: &[(&str, u32)] = &[
+    ("services", 1),
+    ("health_checks", 1),
+    ("service_health", 1),
+    ("service_dependencies", 1),
+    ("owner_notifications", 1),
+    ("deletion_requests", 1),
+    ("prewarm", 1),
+    ("identities", 1),
+    ("oidc_clients", 1),
+    ("alert_rules", 1),
+    ("alert_silences", 1),
+    ("alerts", 1),
+    ("rate_plans", 1),
+    ("subscriptions", 1),
+    ("onboarding", 1),
+    ("mirrors", 1),
+    ("drift_work_items", 1),
+    ("build_runs", 1),
+    ("build_cache", 1),
+    ("messages", 1),
+    ("messaging", 1),
+    ("service_versions", 1),
+    ("version_pins", 1),
+    ("batch_jobs", 1),
+    ("releases", 1),
+    ("maintenance_windows", 1),
+    ("user_sessions", 1),
+    ("resumable_sessions", 1),
+    ("activity_feed", 1),
+    ("offline_sync", 1),
+];
+
+/// One step that rewrites a store's data from `from_version` to `from_version + 1`
+pub struct Migration {
+    pub store: &'static str,
+    pub from_version: u32,
+    pub description: &'static str,
+    pub apply: fn(Value) -> Result<Value, String>,
+}
+
+/// Registered steps, in order
+pub fn migrations() -> Vec<Migration> {
+    STORES
+        .iter()
+        .map(|(store, _)| Migration {
+            store,
+            from_version: 0,
+            description: "wrap unversioned data in a schema envelope",
+            apply: Ok,
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoreEnvelope {
+    schema_version: u32,
+    written_at: u64,
+    data: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigrationReport {
+    pub store: String,
+    pub from_version: u32,
+    pub to_version: u32,
+    pub steps: Vec<String>,
+    pub backup: Option<String>,
+    pub dry_run: bool,
+}
+
+pub fn state_dir() -> String {
+    let data_dir = std::env::var("ZOS_DATA_DIR").unwrap_or_else(|_| "/tmp".to_string());
+    format!("{}/zos-state", data_dir)
+}
+
+fn store_path(dir: &str, store: &str) -> String {
+    format!("{}/{}.json", dir, store)
+}
+
+pub fn current_version(store: &str) -> Result<u32, String> {
+    STORES
+        .iter()
+        .find(|(name, _)| *name == store)
+        .map(|(_, version)| *version)
+        .ok_or(format!("Unknown store: {}", store))
+}
+
+/// Files written before versioning are bare JSON and count as version 0
+fn read_envelope(path: &str) -> Result<Option<StoreEnvelope>, String> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Failed to read {}: {}", path, e)),
+    };
+
+    let value: Value =
+        serde_json::from_str(&contents).map_err(|e| format!("Corrupt store {}: {}", path, e))?;
+
+    let versioned = value.get("schema_version").is_some() && value.get("data").is_some();
+    if versioned {
+        serde_json::from_value(value)
+            .map(Some)
+            .map_err(|e| format!("Corrupt envelope in {}: {}", path, e))
+    } else {
+        Ok(Some(StoreEnvelope {
+            schema_version: 0,
+            written_at: 0,
+            data: value,
+        }))
+    }
+}
+
+fn write_envelope(path: &str, envelope: &StoreEnvelope) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(envelope)
+        .map_err(|e| format!("Failed to serialize {}: {}", path, e))?;
+
+    // Write then rename so a crash never leaves a half-written store
+    let tmp_path = format!("{}.tmp", path);
+    std::fs::write(&tmp_path, json).map_err(|e| format!("Failed to write {}: {}", tmp_path, e))?;
+    std::fs::rename(&tmp_path, path).map_err(|e| format!("Failed to replace {}: {}", path, e))
+}
+
+/// Bring one store up to its current version. Refuses stores written by a
+/// newer release. Returns None when nothing needed migrating.
+pub fn migrate_store(
+    dir: &str,
+    store: &str,
+    dry_run: bool,
+) -> Result<Option<MigrationReport>, String> {
+    let target = current_version(store)?;
+    let path = store_path(dir, store);
+
+    let mut envelope = match read_envelope(&path)? {
+        Some(envelope) => envelope,
+        None => return Ok(None),
+    };
+
+    let from_version = envelope.schema_version;
+    if from_version > target {
+        return Err(format!(
+            "{} is schema version {}, this build supports up to {}; refusing to start",
+            path, from_version, target
+        ));
+    }
+    if from_version == target {
+        return Ok(None);
+    }
+
+    let registered = migrations();
+    let mut steps = Vec::new();
+    let mut version = from_version;
+    while version < target {
+        let step = registered
+            .iter()
+            .find(|m| m.store == store && m.from_version == version)
+            .ok_or(format!(
+                "No migration registered for {} from version {}",
+                store, version
+            ))?;
+
+        if !dry_run {
+            envelope.data = (step.apply)(envelope.data)
+                .map_err(|e| format!("Migration {} v{} failed: {}", store, version, e))?;
+        }
+        steps.push(format!(
+            "v{} → v{}: {}",
+            version,
+            version + 1,
+            step.description
+        ));
+        version += 1;
+    }
+
+    let mut backup = None;
+    if !dry_run {
+        let backup_path = format!("{}.v{}.bak", path, from_version);
+        std::fs::copy(&path, &backup_path)
+            .map_err(|e| format!("Failed to back up {}: {}", path, e))?;
+        backup = Some(backup_path);
+
+        envelope.schema_version = target;
+        envelope.written_at = chrono::Utc::now().timestamp() as u64;
+        write_envelope(&path, &envelope)?;
+    }
+
+    Ok(Some(MigrationReport {
+        store: store.to_string(),
+        from_version,
+        to_version: target,
+        steps,
+        backup,
+        dry_run,
+    }))
+}
+
+/// Check or migrate every store without loading it
+pub fn migrate_all(dir: &str, dry_run: bool) -> Result<Vec<MigrationReport>, String> {
+    let mut reports = Vec::new();
+    for (store, _) in STORES {
+        if let Some(report) = migrate_store(dir, store, dry_run)? {
+            reports.push(report);
+        }
+    }
+    Ok(reports)
+}
+
+/// Read a store that is already at its current version
+pub fn load_store<T: DeserializeOwned>(dir: &str, store: &str) -> Result<Option<T>, String> {
+    let path = store_path(dir, store);
+    let envelope = match read_envelope(&path)? {
+        Some(envelope) => envelope,
+        None => return Ok(None),
+    };
+
+    if envelope.schema_version != current_version(store)? {
+        return Err(format!("{} has not been migrated", path));
+    }
+
+    serde_json::from_value(envelope.data)
+        .map(Some)
+        .map_err(|e| format!("Failed to load {}: {}", path, e))
+}
+
+pub fn save_store<T: Serialize>(dir: &str, store: &str, data: &T) -> Result<(), String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir, e))?;
+
+    let envelope = StoreEnvelope {
+        schema_version: current_version(store)?,
+        written_at: chrono::Utc::now().timestamp() as u64,
+        data: serde_json::to_value(data)
+            .map_err(|e| format!("Failed to serialize {}: {}", store, e))?,
+    };
+
+    write_envelope(&store_path(dir, store), &envelope)
+}
+
+fn load_into<T: DeserializeOwned>(
+    dir: &str,
+    store: &str,
+    target: &mut HashMap<String, T>,
+) -> Result<(), String> {
+    if let Some(loaded) = load_store::<HashMap<String, T>>(dir, store)? {
+        println!("📂 Loaded {} {} records", loaded.len(), store);
+        *target = loaded;
+    }
+    Ok(())
+}
+
+/// Migrate and load persisted state at startup
+pub async fn load_state(state: &AppState) -> Result<Vec<MigrationReport>, String> {
+    let dir = state_dir();
+    let reports = migrate_all(&dir, false)?;
+
+    load_into(&dir, "services", &mut *state.services.write().await)?;
+    load_into(
+        &dir,
+        "health_checks",
+        &mut *state.health_checks.write().await,
+    )?;
+    load_into(
+        &dir,
+        "service_health",
+        &mut *state.service_health.write().await,
+    )?;
+    load_into(
+        &dir,
+        "service_dependencies",
+        &mut *state.service_dependencies.write().await,
+    )?;
+    load_into(
+        &dir,
+        "owner_notifications",
+        &mut *state.owner_notifications.write().await,
+    )?;
+    load_into(
+        &dir,
+        "deletion_requests",
+        &mut *state.deletion_requests.write().await,
+    )?;
+    load_into(&dir, "prewarm", &mut *state.prewarm.write().await)?;
+    load_into(&dir, "identities", &mut *state.identities.write().await)?;
+    load_into(&dir, "oidc_clients", &mut state.oidc.write().await.clients)?;
+    load_into(&dir, "alert_rules", &mut *state.alert_rules.write().await)?;
+    load_into(
+        &dir,
+        "alert_silences",
+        &mut *state.alert_silences.write().await,
+    )?;
+    load_into(&dir, "alerts", &mut *state.alerts.write().await)?;
+    load_into(&dir, "rate_plans", &mut *state.rate_plans.write().await)?;
+    load_into(
+        &dir,
+        "subscriptions",
+        &mut *state.subscriptions.write().await,
+    )?;
+    load_into(&dir, "onboarding", &mut *state.onboarding.write().await)?;
+    load_into(&dir, "mirrors", &mut *state.mirrors.write().await)?;
+    load_into(
+        &dir,
+        "drift_work_items",
+        &mut *state.drift_work_items.write().await,
+    )?;
+    load_into(&dir, "build_runs", &mut *state.build_runs.write().await)?;
+    load_into(&dir, "build_cache", &mut *state.build_cache.write().await)?;
+    load_into(&dir, "messages", &mut *state.messages.write().await)?;
+    load_into(&dir, "messaging", &mut *state.messaging.write().await)?;
+    load_into(
+        &dir,
+        "service_versions",
+        &mut *state.service_versions.write().await,
+    )?;
+    load_into(&dir, "version_pins", &mut *state.version_pins.write().await)?;
+    load_into(&dir, "batch_jobs", &mut *state.batch_jobs.write().await)?;
+    load_into(&dir, "releases", &mut *state.releases.write().await)?;
+    load_into(
+        &dir,
+        "maintenance_windows",
+        &mut *state.maintenance_windows.write().await,
+    )?;
+    load_into(
+        &dir,
+        "user_sessions",
+        &mut *state.user_sessions.write().await,
+    )?;
+    load_into(
+        &dir,
+        "resumable_sessions",
+        &mut *state.resumable_sessions.write().await,
+    )?;
+    load_into(
+        &dir,
+        "activity_feed",
+        &mut *state.activity.feeds.write().await,
+    )?;
+    load_into(&dir, "offline_sync", &mut *state.offline_sync.write().await)?;
+
+    Ok(reports)
+}
+
+pub async fn save_state(state: &AppState) -> Result<(), String> {
+    let dir = state_dir();
+
+    save_store(&dir, "services", &*state.services.read().await)?;
+    save_store(&dir, "health_checks", &*state.health_checks.read().await)?;
+    save_store(&dir, "service_health", &*state.service_health.read().await)?;
+    save_store(
+        &dir,
+        "service_dependencies",
+        &*state.service_dependencies.read().await,
+    )?;
+    save_store(
+        &dir,
+        "owner_notifications",
+        &*state.owner_notifications.read().await,
+    )?;
+    save_store(
+        &dir,
+        "deletion_requests",
+        &*state.deletion_requests.read().await,
+    )?;
+    save_store(&dir, "prewarm", &*state.prewarm.read().await)?;
+    save_store(&dir, "identities", &*state.identities.read().await)?;
+    save_store(&dir, "oidc_clients", &state.oidc.read().await.clients)?;
+    save_store(&dir, "alert_rules", &*state.alert_rules.read().await)?;
+    save_store(&dir, "alert_silences", &*state.alert_silences.read().await)?;
+    save_store(&dir, "alerts", &*state.alerts.read().await)?;
+    save_store(&dir, "rate_plans", &*state.rate_plans.read().await)?;
+    save_store(&dir, "subscriptions", &*state.subscriptions.read().await)?;
+    save_store(&dir, "onboarding", &*state.onboarding.read().await)?;
+    save_store(&dir, "mirrors", &*state.mirrors.read().await)?;
+    save_store(
+        &dir,
+        "drift_work_items",
+        &*state.drift_work_items.read().await,
+    )?;
+    save_store(&dir, "build_runs", &*state.build_runs.read().await)?;
+    save_store(&dir, "build_cache", &*state.build_cache.read().await)?;
+    save_store(&dir, "messages", &*state.messages.read().await)?;
+    save_store(&dir, "messaging", &*state.messaging.read().await)?;
+    save_store(
+        &dir,
+        "service_versions",
+        &*state.service_versions.read().await,
+    )?;
+    save_store(&dir, "version_pins", &*state.version_pins.read().await)?;
+    save_store(&dir, "batch_jobs", &*state.batch_jobs.read().await)?;
+    save_store(&dir, "releases", &*state.releases.read().await)?;
+    save_store(
+        &dir,
+        "maintenance_windows",
+        &*state.maintenance_windows.read().await,
+    )?;
+    save_store(&dir, "user_sessions", &*state.user_sessions.read().await)?;
+    save_store(
+        &dir,
+        "resumable_sessions",
+        &*state.resumable_sessions.read().await,
+    )?;
+    save_store(&dir, "activity_feed", &*state.activity.feeds.read().await)?;
+    save_store(&dir, "offline_sync", &*state.offline_sync.read().await)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> String {
+        let dir =
+            std::env::temp_dir().join(format!("zos-migrations-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_every_store_has_one_step_per_version() {
+        let registered = migrations();
+        for (store, version) in STORES {
+            let mut steps: Vec<u32> = registered
+                .iter()
+                .filter(|m| m.store == *store)
+                .map(|m| m.from_version)
+                .collect();
+            steps.sort();
+            assert_eq!(steps, (0..*version).collect::<Vec<_>>(), "{}", store);
+        }
+        assert!(registered
+            .iter()
+            .all(|m| STORES.iter().any(|(store, _)| *store == m.store)));
+    }
+
+    #[test]
+    fn test_unversioned_store_migrates_once() {
+        let dir = scratch("once");
+        let path = store_path(&dir, "services");
+        std::fs::write(&path, r#"{"svc": {"port": 8080}}"#).unwrap();
+
+        // A dry run reports the steps and leaves the file alone
+        let report = migrate_store(&dir, "services", true).unwrap().unwrap();
+        assert_eq!((report.from_version, report.to_version), (0, 1));
+        assert!(report.backup.is_none());
+        assert!(load_store::<Value>(&dir, "services").is_err());
+
+        let report = migrate_store(&dir, "services", false).unwrap().unwrap();
+        assert_eq!(report.steps.len(), 1);
+        let backup = report.backup.unwrap();
+        assert_eq!(
+            std::fs::read_to_string(backup).unwrap(),
+            r#"{"svc": {"port": 8080}}"#
+        );
+        let loaded: Value = load_store(&dir, "services").unwrap().unwrap();
+        assert_eq!(loaded["svc"]["port"], 8080);
+
+        // Already current: nothing to do, however often it runs
+        assert!(migrate_store(&dir, "services", false).unwrap().is_none());
+        assert!(migrate_all(&dir, false).unwrap().is_empty());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_newer_or_unknown_stores_are_refused() {
+        let dir = scratch("newer");
+        std::fs::write(
+            store_path(&dir, "alerts"),
+            r#"{"schema_version": 99, "written_at": 0, "data": {}}"#,
+        )
+        .unwrap();
+        assert!(migrate_store(&dir, "alerts", false)
+            .unwrap_err()
+            .contains("refusing to start"));
+        assert!(migrate_all(&dir, true).is_err());
+        assert!(load_store::<Value>(&dir, "alerts").is_err());
+
+        assert!(current_version("no_such_store").is_err());
+        assert!(save_store(&dir, "no_such_store", &0).is_err());
+        assert!(migrate_store(&dir, "mirrors", false).unwrap().is_none()); // not written yet
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_state_lives_under_the_data_dir() {
+        std::env::set_var("ZOS_DATA_DIR", "/var/lib/zos");
+        assert_eq!(state_dir(), "/var/lib/zos/zos-state");
+        assert_eq!(
+            store_path(&state_dir(), "alerts"),
+            "/var/lib/zos/zos-state/alerts.json"
+        );
+        std::env::remove_var("ZOS_DATA_DIR");
+        assert_eq!(state_dir(), "/tmp/zos-state");
+    }
+
+    #[test]
+    fn test_saved_stores_load_back() {
+        let dir = scratch("save");
+        let data = HashMap::from([("a".to_string(), 1u32), ("b".to_string(), 2)]);
+        save_store(&dir, "prewarm", &data).unwrap();
+        assert_eq!(
+            load_store::<HashMap<String, u32>>(&dir, "prewarm").unwrap(),
+            Some(data)
+        );
+        assert!(!std::path::Path::new(&format!("{}.tmp", store_path(&dir, "prewarm"))).exists());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
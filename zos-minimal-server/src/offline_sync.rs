@@ -0,0 +1,138 @@
+// Replaying what a dashboard queued while it was offline: each action is
+// applied at most once, and one that no longer fits the server's state is
+// reported back as a conflict instead of being applied over it
+// AGPL-3.0 License
+
+use crate::session_resume::SessionSnapshot;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Actions one sync call may replay
+pub const MAX_BATCH: usize = 50;
+
+/// Results kept per wallet, so an action replayed after a lost reply gets
+/// its first answer again
+const REMEMBERED: usize = 200;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Action {
+    AllocatePort {
+        expected_port: Option<u16>, // the port the client last saw, if any
+    },
+    SaveSnapshot {
+        snapshot: SessionSnapshot,
+        base_version: Option<u64>,
+    },
+}
+
+impl Action {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Action::AllocatePort { .. } => "allocate_port",
+            Action::SaveSnapshot { .. } => "save_snapshot",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct QueuedAction {
+    pub id: String, // chosen by the client when it queued the action
+    pub queued_at: u64,
+    #[serde(flatten)]
+    pub action: Action,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SyncRequest {
+    pub actions: Vec<QueuedAction>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Applied,
+    Conflict, // the server changed since the client queued it; see server_value
+    Rejected,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActionResult {
+    pub id: String,
+    pub kind: String,
+    pub outcome: Outcome,
+    pub detail: String,
+    #[serde(default)]
+    pub server_value: serde_json::Value, // the server's side of a conflict
+    pub queued_at: u64,
+    pub resolved_at: u64,
+    #[serde(default)]
+    pub replayed: bool, // answered from an earlier sync of the same action
+}
+
+impl ActionResult {
+    pub fn new(queued: &QueuedAction, outcome: Outcome, detail: String, now: u64) -> Self {
+        Self {
+            id: queued.id.clone(),
+            kind: queued.action.kind().to_string(),
+            outcome,
+            detail,
+            server_value: serde_json::Value::Null,
+            queued_at: queued.queued_at,
+            resolved_at: now,
+            replayed: false,
+        }
+    }
+
+    pub fn with_server_value(mut self, value: serde_json::Value) -> Self {
+        self.server_value = value;
+        self
+    }
+}
+
+/// The answer already given for an action, if this is a replay
+pub fn previous(log: &VecDeque<ActionResult>, id: &str) -> Option<ActionResult> {
+    log.iter().find(|result| result.id == id).map(|result| {
+        let mut result = result.clone();
+        result.replayed = true;
+        result
+    })
+}
+
+pub fn remember(log: &mut VecDeque<ActionResult>, result: ActionResult) {
+    log.push_back(result);
+    while log.len() > REMEMBERED {
+        log.pop_front();
+    }
+}
+
+/// A port allocation queued offline conflicts when the session got a
+/// different port in the meantime, from another device or tab
+pub fn port_conflict(expected: Option<u16>, current: Option<u16>) -> Option<String> {
+    match current {
+        Some(current) if Some(current) != expected => Some(format!(
+            "Port {} was allocated while you were offline{}",
+            current,
+            expected
+                .map(|port| format!(" (you had {})", port))
+                .unwrap_or_default()
+        )),
+        _ => None,
+    }
+}
+
+/// Order a batch the way the client queued it, refusing malformed ones
+pub fn validate(request: &mut SyncRequest) -> Result<(), String> {
+    if request.actions.len() > MAX_BATCH {
+        return Err(format!("At most {} actions per sync", MAX_BATCH));
+    }
+    if request
+        .actions
+        .iter()
+        .any(|queued| queued.id.is_empty() || queued.id.len() > 64)
+    {
+        return Err("Every action needs an id of at most 64 characters".to_string());
+    }
+    request.actions.sort_by_key(|queued| queued.queued_at);
+    Ok(())
+}
@@ -0,0 +1,847 @@
+// OpenID Connect provider: third-party apps sign users in with their wallet
+// AGPL-3.0 License
+
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+const CHALLENGE_TTL_SECS: u64 = 300;
+const CODE_TTL_SECS: u64 = 60;
+const TOKEN_TTL_SECS: u64 = 3600;
+
+/// Scopes a client may request and the ZOS capability each one grants
+pub const SCOPES: &[(&str, &str)] = &[
+    ("openid", "Confirm which wallet you signed in with"),
+    (
+        "profile",
+        "See your linked Unix account, game profile and Telegram",
+    ),
+    ("earnings:read", "Read your earnings and commission history"),
+    (
+        "services:call",
+        "Call services on your behalf, paid from your credits",
+    ),
+    ("services:logs", "Read the request logs of your services"),
+    (
+        "services:manage",
+        "Change your services' pre-warming and dependencies",
+    ),
+    (
+        "subscriptions:manage",
+        "Subscribe to plans and set your services' plans, paid from your credits",
+    ),
+    (
+        "messages",
+        "Send messages as your wallet and read your inbox",
+    ),
+    (
+        "services:versions",
+        "Publish your services' versions and pin the versions you call",
+    ),
+    ("onboarding", "Claim your onboarding credit rewards"),
+    (
+        "identity:manage",
+        "Link and unlink your Unix account, game profile, Telegram and endpoints",
+    ),
+    (
+        "secrets:admin",
+        "Read and change this node's secrets, if your wallet is one of its admins",
+    ),
+    (
+        "node:admin",
+        "Run this node's builds and jobs, maintenance, alerts and mirrors, if your wallet is one of its admins",
+    ),
+    (
+        "activity:read",
+        "Read your activity feed: payments, commissions, games, vouches, deployments and votes",
+    ),
+    (
+        "data:export",
+        "Download everything this node holds about your wallet, or have it deleted",
+    ),
+    (
+        "data:admin",
+        "Export or delete any wallet's data, if your wallet is one of this node's admins",
+    ),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OidcClient {
+    pub client_id: String,
+    pub secret_hash: String, // sha256 of the client secret, hex
+    pub name: String,
+    pub redirect_uris: Vec<String>,
+    pub allowed_scopes: Vec<String>,
+    pub owner_wallet: String,
+    pub created_at: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ClientRegistration {
+    pub name: String,
+    pub redirect_uris: Vec<String>,
+    pub allowed_scopes: Vec<String>,
+    pub owner_wallet: String,
+}
+
+/// Query of GET /oauth/authorize
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthorizeRequest {
+    pub response_type: String,
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub scope: String,
+    pub state: Option<String>,
+    pub nonce: Option<String>,
+    pub code_challenge: Option<String>, // PKCE, S256 only
+    pub code_challenge_method: Option<String>,
+}
+
+/// Consent form posted back with the wallet's signature over the challenge
+#[derive(Debug, Deserialize)]
+pub struct ConsentSubmission {
+    pub challenge_id: String,
+    pub wallet_address: String,
+    pub signature: String, // base58 or base64
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TokenRequest {
+    pub grant_type: String,
+    pub code: String,
+    pub redirect_uri: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub code_verifier: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: u64,
+    pub id_token: String,
+    pub scope: String,
+}
+
+#[derive(Debug, Clone)]
+struct LoginChallenge {
+    request: AuthorizeRequest,
+    scopes: Vec<String>,
+    message: String,
+    expires_at: u64,
+}
+
+#[derive(Debug, Clone)]
+struct AuthorizationCode {
+    request: AuthorizeRequest,
+    wallet_address: String,
+    scopes: Vec<String>,
+    auth_time: u64,
+    expires_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AccessGrant {
+    pub wallet_address: String,
+    pub client_id: String,
+    pub scopes: Vec<String>,
+    pub expires_at: u64,
+}
+
+pub struct OidcProvider {
+    issuer: String,
+    signing_key: SigningKey,
+    pub clients: HashMap<String, OidcClient>,
+    challenges: HashMap<String, LoginChallenge>,
+    codes: HashMap<String, AuthorizationCode>,
+    access_tokens: HashMap<String, AccessGrant>,
+}
+
+fn now() -> u64 {
+    chrono::Utc::now().timestamp() as u64
+}
+
+fn random_token(bytes: usize) -> String {
+    let mut buf = vec![0u8; bytes];
+    rand::thread_rng().fill_bytes(&mut buf);
+    URL_SAFE_NO_PAD.encode(buf)
+}
+
+fn sha256_hex(input: &str) -> String {
+    Sha256::digest(input.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// The ZOS_OIDC_SIGNING_KEY secret (hex seed), else a key kept in the state directory
+/// so issued tokens stay verifiable across restarts
+fn load_signing_key() -> SigningKey {
+    let from_hex = |hex: &str| -> Option<[u8; 32]> {
+        let bytes: Vec<u8> = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+            .collect::<Option<_>>()?;
+        bytes.try_into().ok()
+    };
+
+    if let Some(seed) =
+        crate::secrets::resolve("ZOS_OIDC_SIGNING_KEY").and_then(|hex| from_hex(hex.trim()))
+    {
+        return SigningKey::from_bytes(&seed);
+    }
+
+    let path = format!("{}/oidc_signing_key", crate::migrations::state_dir());
+    if let Some(seed) = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|hex| from_hex(hex.trim()))
+    {
+        return SigningKey::from_bytes(&seed);
+    }
+
+    let mut seed = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut seed);
+    let hex: String = seed.iter().map(|b| format!("{:02x}", b)).collect();
+    let saved = std::fs::create_dir_all(crate::migrations::state_dir())
+        .and_then(|_| std::fs::write(&path, hex));
+    if let Err(e) = saved {
+        println!(
+            "⚠️  OIDC signing key not saved, tokens die with this process: {}",
+            e
+        );
+    }
+    SigningKey::from_bytes(&seed)
+}
+
+/// Check an ed25519 signature from a Solana-style wallet (base58 public key)
+pub fn verify_wallet_signature(
+    wallet_address: &str,
+    message: &str,
+    signature: &str,
+) -> Result<(), String> {
+    let public_key: [u8; 32] = bs58::decode(wallet_address)
+        .into_vec()
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or("Wallet address is not an ed25519 public key")?;
+    let verifying_key =
+        VerifyingKey::from_bytes(&public_key).map_err(|e| format!("Invalid wallet key: {}", e))?;
+
+    let signature_bytes: [u8; 64] = bs58::decode(signature)
+        .into_vec()
+        .ok()
+        .or_else(|| STANDARD.decode(signature).ok())
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or("Signature must be 64 bytes, base58 or base64")?;
+
+    verifying_key
+        .verify(message.as_bytes(), &Signature::from_bytes(&signature_bytes))
+        .map_err(|_| "Signature does not match wallet".to_string())
+}
+
+impl OidcProvider {
+    pub fn new(domain: &str) -> Self {
+        Self {
+            issuer: format!("https://{}", domain),
+            signing_key: load_signing_key(),
+            clients: HashMap::new(),
+            challenges: HashMap::new(),
+            codes: HashMap::new(),
+            access_tokens: HashMap::new(),
+        }
+    }
+
+    fn key_id(&self) -> String {
+        sha256_hex(&URL_SAFE_NO_PAD.encode(self.signing_key.verifying_key().as_bytes()))[..16]
+            .to_string()
+    }
+
+    pub fn discovery(&self) -> serde_json::Value {
+        serde_json::json!({
+            "issuer": self.issuer,
+            "authorization_endpoint": format!("{}/oauth/authorize", self.issuer),
+            "token_endpoint": format!("{}/oauth/token", self.issuer),
+            "userinfo_endpoint": format!("{}/oauth/userinfo", self.issuer),
+            "jwks_uri": format!("{}/oauth/jwks", self.issuer),
+            "registration_endpoint": format!("{}/oauth/clients", self.issuer),
+            "response_types_supported": ["code"],
+            "grant_types_supported": ["authorization_code"],
+            "subject_types_supported": ["public"],
+            "id_token_signing_alg_values_supported": ["EdDSA"],
+            "code_challenge_methods_supported": ["S256"],
+            "token_endpoint_auth_methods_supported": ["client_secret_post", "none"],
+            "scopes_supported": SCOPES.iter().map(|(scope, _)| *scope).collect::<Vec<_>>(),
+        })
+    }
+
+    pub fn jwks(&self) -> serde_json::Value {
+        serde_json::json!({
+            "keys": [{
+                "kty": "OKP",
+                "crv": "Ed25519",
+                "use": "sig",
+                "alg": "EdDSA",
+                "kid": self.key_id(),
+                "x": URL_SAFE_NO_PAD.encode(self.signing_key.verifying_key().as_bytes()),
+            }]
+        })
+    }
+
+    /// Register a client; the secret is returned once and only its hash kept
+    pub fn register_client(
+        &mut self,
+        registration: ClientRegistration,
+    ) -> Result<(OidcClient, String), String> {
+        if registration.redirect_uris.is_empty() {
+            return Err("At least one redirect_uri is required".to_string());
+        }
+        if let Some(uri) = registration
+            .redirect_uris
+            .iter()
+            .find(|uri| !(uri.starts_with("https://") || uri.starts_with("http://localhost")))
+        {
+            return Err(format!("Redirect URI must use https: {}", uri));
+        }
+        if let Some(scope) = registration
+            .allowed_scopes
+            .iter()
+            .find(|scope| !SCOPES.iter().any(|(known, _)| known == scope))
+        {
+            return Err(format!("Unknown scope: {}", scope));
+        }
+
+        let client_secret = random_token(32);
+        let client = OidcClient {
+            client_id: format!("zos_{}", random_token(12)),
+            secret_hash: sha256_hex(&client_secret),
+            name: registration.name,
+            redirect_uris: registration.redirect_uris,
+            allowed_scopes: registration.allowed_scopes,
+            owner_wallet: registration.owner_wallet,
+            created_at: now(),
+        };
+
+        self.clients
+            .insert(client.client_id.clone(), client.clone());
+        Ok((client, client_secret))
+    }
+
+    fn prune(&mut self) {
+        let now = now();
+        self.challenges.retain(|_, c| c.expires_at > now);
+        self.codes.retain(|_, c| c.expires_at > now);
+        self.access_tokens.retain(|_, t| t.expires_at > now);
+    }
+
+    /// Validate an authorization request and open a login challenge for the
+    /// consent screen. Returns (challenge_id, message to sign, client, scopes).
+    pub fn begin_authorization(
+        &mut self,
+        request: AuthorizeRequest,
+    ) -> Result<(String, String, OidcClient, Vec<String>), String> {
+        self.prune();
+
+        let client = self
+            .clients
+            .get(&request.client_id)
+            .cloned()
+            .ok_or("Unknown client_id")?;
+        if !client.redirect_uris.contains(&request.redirect_uri) {
+            return Err("redirect_uri is not registered for this client".to_string());
+        }
+        if request.response_type != "code" {
+            return Err("Only response_type=code is supported".to_string());
+        }
+        if request.code_challenge.is_some()
+            && request.code_challenge_method.as_deref() != Some("S256")
+        {
+            return Err("Only S256 code challenges are supported".to_string());
+        }
+
+        let scopes: Vec<String> = request
+            .scope
+            .split_whitespace()
+            .map(str::to_string)
+            .collect();
+        if !scopes.iter().any(|scope| scope == "openid") {
+            return Err("The openid scope is required".to_string());
+        }
+        if let Some(scope) = scopes
+            .iter()
+            .find(|scope| !client.allowed_scopes.contains(scope))
+        {
+            return Err(format!("Client may not request scope {}", scope));
+        }
+
+        let challenge_id = random_token(16);
+        let message = format!(
+            "Sign in to {} with {}\nScopes: {}\nChallenge: {}",
+            self.issuer,
+            client.name,
+            scopes.join(" "),
+            challenge_id
+        );
+
+        self.challenges.insert(
+            challenge_id.clone(),
+            LoginChallenge {
+                request,
+                scopes: scopes.clone(),
+                message: message.clone(),
+                expires_at: now() + CHALLENGE_TTL_SECS,
+            },
+        );
+
+        Ok((challenge_id, message, client, scopes))
+    }
+
+    /// Verify the signed challenge and return the redirect carrying the code
+    pub fn complete_authorization(
+        &mut self,
+        submission: ConsentSubmission,
+    ) -> Result<String, String> {
+        self.prune();
+
+        let challenge = self
+            .challenges
+            .remove(&submission.challenge_id)
+            .ok_or("Login challenge expired or already used")?;
+
+        verify_wallet_signature(
+            &submission.wallet_address,
+            &challenge.message,
+            &submission.signature,
+        )?;
+
+        let code = random_token(24);
+        let now = now();
+        let mut redirect = format!(
+            "{}{}code={}",
+            challenge.request.redirect_uri,
+            if challenge.request.redirect_uri.contains('?') {
+                '&'
+            } else {
+                '?'
+            },
+            code
+        );
+        if let Some(state) = &challenge.request.state {
+            redirect.push_str(&format!("&state={}", url_encode(state)));
+        }
+
+        self.codes.insert(
+            code,
+            AuthorizationCode {
+                request: challenge.request,
+                wallet_address: submission.wallet_address,
+                scopes: challenge.scopes,
+                auth_time: now,
+                expires_at: now + CODE_TTL_SECS,
+            },
+        );
+
+        Ok(redirect)
+    }
+
+    /// Exchange an authorization code for an access token and ID token
+    pub fn exchange_code(&mut self, token_request: TokenRequest) -> Result<TokenResponse, String> {
+        self.prune();
+
+        if token_request.grant_type != "authorization_code" {
+            return Err("unsupported_grant_type".to_string());
+        }
+
+        // Codes are single use, even when the exchange fails
+        let code = self
+            .codes
+            .remove(&token_request.code)
+            .ok_or("invalid_grant")?;
+        if code.request.client_id != token_request.client_id
+            || code.request.redirect_uri != token_request.redirect_uri
+        {
+            return Err("invalid_grant".to_string());
+        }
+
+        let client = self
+            .clients
+            .get(&token_request.client_id)
+            .ok_or("invalid_client")?;
+
+        // Public clients prove possession with PKCE, confidential ones with their secret
+        match (&code.request.code_challenge, &token_request.client_secret) {
+            (Some(challenge), _) => {
+                let verifier = token_request
+                    .code_verifier
+                    .as_deref()
+                    .ok_or("invalid_grant")?;
+                if &URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes())) != challenge {
+                    return Err("invalid_grant".to_string());
+                }
+            }
+            (None, Some(secret)) if sha256_hex(secret) == client.secret_hash => {}
+            _ => return Err("invalid_client".to_string()),
+        }
+
+        let now = now();
+        let mut claims = serde_json::json!({
+            "iss": self.issuer,
+            "sub": code.wallet_address,
+            "aud": client.client_id,
+            "iat": now,
+            "exp": now + TOKEN_TTL_SECS,
+            "auth_time": code.auth_time,
+            "zos_capabilities": code.scopes,
+        });
+        if let Some(nonce) = &code.request.nonce {
+            claims["nonce"] = serde_json::json!(nonce);
+        }
+        let id_token = self.sign_jwt(&claims)?;
+
+        let access_token = random_token(32);
+        self.access_tokens.insert(
+            access_token.clone(),
+            AccessGrant {
+                wallet_address: code.wallet_address,
+                client_id: client.client_id.clone(),
+                scopes: code.scopes.clone(),
+                expires_at: now + TOKEN_TTL_SECS,
+            },
+        );
+
+        Ok(TokenResponse {
+            access_token,
+            token_type: "Bearer".to_string(),
+            expires_in: TOKEN_TTL_SECS,
+            id_token,
+            scope: code.scopes.join(" "),
+        })
+    }
+
+    fn sign_jwt(&self, claims: &serde_json::Value) -> Result<String, String> {
+        let header = serde_json::json!({ "alg": "EdDSA", "typ": "JWT", "kid": self.key_id() });
+        let encode = |value: &serde_json::Value| {
+            serde_json::to_vec(value)
+                .map(|bytes| URL_SAFE_NO_PAD.encode(bytes))
+                .map_err(|e| format!("Failed to encode token: {}", e))
+        };
+
+        let signing_input = format!("{}.{}", encode(&header)?, encode(claims)?);
+        let signature = self.signing_key.sign(signing_input.as_bytes());
+        Ok(format!(
+            "{}.{}",
+            signing_input,
+            URL_SAFE_NO_PAD.encode(signature.to_bytes())
+        ))
+    }
+
+    /// The grant behind a bearer token, if it is live and carries `scope`
+    pub fn authorize_bearer(&self, token: &str, scope: &str) -> Result<AccessGrant, String> {
+        let grant = self
+            .access_tokens
+            .get(token)
+            .filter(|grant| grant.expires_at > now())
+            .ok_or("invalid_token")?;
+        if !grant.scopes.iter().any(|granted| granted == scope) {
+            return Err("insufficient_scope".to_string());
+        }
+        Ok(grant.clone())
+    }
+}
+
+fn url_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn html_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Consent screen: lists what the app asks for and has the wallet sign the challenge
+pub fn consent_page(
+    challenge_id: &str,
+    message: &str,
+    client: &OidcClient,
+    scopes: &[String],
+) -> String {
+    let scope_items: String = scopes
+        .iter()
+        .map(|scope| {
+            let description = SCOPES
+                .iter()
+                .find(|(known, _)| known == scope)
+                .map(|(_, description)| *description)
+                .unwrap_or("");
+            format!(
+                "<li><code>{}</code> — {}</li>",
+                html_escape(scope),
+                description
+            )
+        })
+        .collect();
+
+    format!(
+        r#"
+    <html>
+    <head><title>Sign in with ZOS - {name}</title></head>
+    <body style="font-family: Arial; margin: 0; padding: 20px; background: #f5f5f5;">
+        <h1>🔐 Sign in with ZOS</h1>
+
+        <div style="background: white; padding: 20px; border-radius: 8px; margin: 20px 0;">
+            <h3><strong>{name}</strong> wants to:</h3>
+            <ul>{scope_items}</ul>
+            <button onclick="approve()" style="background: #4CAF50; color: white; border: none; padding: 10px 20px; border-radius: 4px; cursor: pointer;">
+                Sign with wallet and allow
+            </button>
+            <p id="status"></p>
+        </div>
+
+        <form id="consent" method="post" action="/oauth/authorize">
+            <input type="hidden" name="challenge_id" value="{challenge_id}">
+            <input type="hidden" name="wallet_address" id="wallet_address">
+            <input type="hidden" name="signature" id="signature">
+        </form>
+
+        <script>
+            const message = {message};
+
+            async function approve() {{
+                try {{
+                    const wallet = window.solana;
+                    if (!wallet) {{
+                        document.getElementById('status').textContent = 'No Solana wallet found in this browser';
+                        return;
+                    }}
+                    await wallet.connect();
+                    const signed = await wallet.signMessage(new TextEncoder().encode(message), 'utf8');
+                    document.getElementById('wallet_address').value = wallet.publicKey.toString();
+                    document.getElementById('signature').value = btoa(String.fromCharCode(...signed.signature));
+                    document.getElementById('consent').submit();
+                }} catch (error) {{
+                    document.getElementById('status').textContent = 'Signing failed: ' + error.message;
+                }}
+            }}
+        </script>
+    </body>
+    </html>
+    "#,
+        name = html_escape(&client.name),
+        scope_items = scope_items,
+        challenge_id = html_escape(challenge_id),
+        message = serde_json::to_string(message)
+            .unwrap_or_default()
+            .replace('<', "\\u003c"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_provider(domain: &str, seed: u8) -> OidcProvider {
+        OidcProvider {
+            issuer: format!("https://{}", domain),
+            signing_key: SigningKey::from_bytes(&[seed; 32]),
+            clients: HashMap::new(),
+            challenges: HashMap::new(),
+            codes: HashMap::new(),
+            access_tokens: HashMap::new(),
+        }
+    }
+
+    /// Sign in `wallet` through `client` for `scope`, as the consent screen would
+    fn sign_in(
+        provider: &mut OidcProvider,
+        wallet: &SigningKey,
+        scope: &str,
+    ) -> (OidcClient, TokenResponse) {
+        let (client, secret) = provider
+            .register_client(ClientRegistration {
+                name: "Test app".to_string(),
+                redirect_uris: vec!["https://app.test/callback".to_string()],
+                allowed_scopes: vec!["openid".to_string(), "services:manage".to_string()],
+                owner_wallet: "owner".to_string(),
+            })
+            .unwrap();
+        let (challenge_id, message, _, _) = provider
+            .begin_authorization(AuthorizeRequest {
+                response_type: "code".to_string(),
+                client_id: client.client_id.clone(),
+                redirect_uri: "https://app.test/callback".to_string(),
+                scope: scope.to_string(),
+                state: None,
+                nonce: Some("n-1".to_string()),
+                code_challenge: None,
+                code_challenge_method: None,
+            })
+            .unwrap();
+        let redirect = provider
+            .complete_authorization(ConsentSubmission {
+                challenge_id,
+                wallet_address: bs58::encode(wallet.verifying_key().as_bytes()).into_string(),
+                signature: bs58::encode(wallet.sign(message.as_bytes()).to_bytes()).into_string(),
+            })
+            .unwrap();
+        let code = redirect.split("code=").nth(1).unwrap().to_string();
+        let tokens = provider
+            .exchange_code(TokenRequest {
+                grant_type: "authorization_code".to_string(),
+                code,
+                redirect_uri: "https://app.test/callback".to_string(),
+                client_id: client.client_id.clone(),
+                client_secret: Some(secret),
+                code_verifier: None,
+            })
+            .unwrap();
+        (client, tokens)
+    }
+
+    /// The ID token's claims, if its signature verifies under the JWKS key
+    fn verify_id_token(jwks: &serde_json::Value, token: &str) -> Option<serde_json::Value> {
+        let (signing_input, signature) = token.rsplit_once('.')?;
+        let key: [u8; 32] = URL_SAFE_NO_PAD
+            .decode(jwks["keys"][0]["x"].as_str()?)
+            .ok()?
+            .try_into()
+            .ok()?;
+        let signature: [u8; 64] = URL_SAFE_NO_PAD.decode(signature).ok()?.try_into().ok()?;
+        VerifyingKey::from_bytes(&key)
+            .ok()?
+            .verify(signing_input.as_bytes(), &Signature::from_bytes(&signature))
+            .ok()?;
+        let claims = signing_input.split('.').nth(1)?;
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(claims).ok()?).ok()
+    }
+
+    #[test]
+    fn test_id_token_is_signed_for_the_issuer_and_client() {
+        let mut provider = test_provider("node.test", 1);
+        let wallet = SigningKey::from_bytes(&[9; 32]);
+        let (client, tokens) = sign_in(&mut provider, &wallet, "openid services:manage");
+
+        let claims = verify_id_token(&provider.jwks(), &tokens.id_token).unwrap();
+        assert_eq!(claims["iss"], "https://node.test");
+        assert_eq!(claims["aud"], client.client_id.as_str());
+        assert_eq!(claims["nonce"], "n-1");
+        let (iat, exp) = (
+            claims["iat"].as_u64().unwrap(),
+            claims["exp"].as_u64().unwrap(),
+        );
+        assert!(iat <= now() && now() < exp);
+
+        // Another issuer's key does not verify it, nor does a tampered token
+        assert!(
+            verify_id_token(&test_provider("other.test", 2).jwks(), &tokens.id_token).is_none()
+        );
+        let mut tampered = tokens.id_token.clone();
+        tampered.pop();
+        tampered.push(if tokens.id_token.ends_with('A') {
+            'B'
+        } else {
+            'A'
+        });
+        assert!(verify_id_token(&provider.jwks(), &tampered).is_none());
+    }
+
+    #[test]
+    fn test_bearer_tokens_need_a_live_grant_with_the_scope() {
+        let mut provider = test_provider("node.test", 1);
+        let wallet = SigningKey::from_bytes(&[9; 32]);
+        let (client, tokens) = sign_in(&mut provider, &wallet, "openid services:manage");
+
+        let grant = provider
+            .authorize_bearer(&tokens.access_token, "services:manage")
+            .unwrap();
+        assert_eq!(grant.client_id, client.client_id);
+        assert_eq!(
+            provider
+                .authorize_bearer(&tokens.access_token, "secrets:admin")
+                .unwrap_err(),
+            "insufficient_scope"
+        );
+
+        // Tokens are only good at the provider that issued them
+        let mut other = test_provider("other.test", 2);
+        assert!(other
+            .authorize_bearer(&tokens.access_token, "services:manage")
+            .is_err());
+        let (_, other_tokens) = sign_in(&mut other, &wallet, "openid");
+        assert!(provider
+            .authorize_bearer(&other_tokens.access_token, "openid")
+            .is_err());
+
+        // An expired grant is refused, and one only just live is not
+        let access_grant = provider
+            .access_tokens
+            .get_mut(&tokens.access_token)
+            .unwrap();
+        access_grant.expires_at = now() + 1;
+        assert!(provider
+            .authorize_bearer(&tokens.access_token, "services:manage")
+            .is_ok());
+        provider
+            .access_tokens
+            .get_mut(&tokens.access_token)
+            .unwrap()
+            .expires_at = now();
+        assert_eq!(
+            provider
+                .authorize_bearer(&tokens.access_token, "services:manage")
+                .unwrap_err(),
+            "invalid_token"
+        );
+    }
+
+    #[test]
+    fn test_codes_are_bound_to_their_client_and_used_once() {
+        let mut provider = test_provider("node.test", 1);
+        let wallet = SigningKey::from_bytes(&[9; 32]);
+        let (client, _) = sign_in(&mut provider, &wallet, "openid");
+
+        // A scope the client was not registered for is refused before consent
+        let request = AuthorizeRequest {
+            response_type: "code".to_string(),
+            client_id: client.client_id.clone(),
+            redirect_uri: "https://app.test/callback".to_string(),
+            scope: "openid secrets:admin".to_string(),
+            state: None,
+            nonce: None,
+            code_challenge: None,
+            code_challenge_method: None,
+        };
+        assert!(provider.begin_authorization(request.clone()).is_err());
+
+        // A consent signed by another wallet does not produce a code
+        let (challenge_id, message, _, _) = provider
+            .begin_authorization(AuthorizeRequest {
+                scope: "openid".to_string(),
+                ..request
+            })
+            .unwrap();
+        let impostor = SigningKey::from_bytes(&[7; 32]);
+        assert!(provider
+            .complete_authorization(ConsentSubmission {
+                challenge_id: challenge_id.clone(),
+                wallet_address: bs58::encode(wallet.verifying_key().as_bytes()).into_string(),
+                signature: bs58::encode(impostor.sign(message.as_bytes()).to_bytes()).into_string(),
+            })
+            .is_err());
+        assert!(!provider.challenges.contains_key(&challenge_id)); // and the challenge is spent
+    }
+}
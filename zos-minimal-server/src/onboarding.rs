@@ -0,0 +1,278 @@
+// Onboarding wizard: the steps a new wallet goes through, tracked per wallet
+// with a credit reward for each one
+// AGPL-3.0 License
+
+use crate::UserSession;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnboardingStep {
+    AllocatePort,
+    RegisterEndpoint,
+    LinkTelegram,
+    CreateService,
+}
+
+impl OnboardingStep {
+    /// In the order the wizard walks through them
+    pub const ALL: [OnboardingStep; 4] = [
+        OnboardingStep::AllocatePort,
+        OnboardingStep::RegisterEndpoint,
+        OnboardingStep::LinkTelegram,
+        OnboardingStep::CreateService,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            OnboardingStep::AllocatePort => "Allocate a port",
+            OnboardingStep::RegisterEndpoint => "Register a public endpoint",
+            OnboardingStep::LinkTelegram => "Link your Telegram account",
+            OnboardingStep::CreateService => "Create your first service",
+        }
+    }
+
+    pub fn reward_credits(self) -> u64 {
+        match self {
+            OnboardingStep::AllocatePort => 10,
+            OnboardingStep::RegisterEndpoint => 20,
+            OnboardingStep::LinkTelegram => 20,
+            OnboardingStep::CreateService => 50,
+        }
+    }
+
+    /// Where the dashboard or the bot picks the step up
+    pub fn deep_link(self, domain: &str, wallet: &str) -> String {
+        match self {
+            OnboardingStep::AllocatePort => {
+                format!("https://{}/dashboard/{}#allocate-port", domain, wallet)
+            }
+            OnboardingStep::RegisterEndpoint => {
+                format!("https://{}/dashboard/{}#endpoints", domain, wallet)
+            }
+            OnboardingStep::LinkTelegram => format!(
+                "https://t.me/{}?start=link_{}",
+                std::env::var("ZOS_TELEGRAM_BOT").unwrap_or_else(|_| "zos_bouncer_bot".to_string()),
+                wallet
+            ),
+            OnboardingStep::CreateService => {
+                format!("https://{}/dashboard/{}#services", domain, wallet)
+            }
+        }
+    }
+
+    /// The API call that completes the step
+    pub fn api(self, wallet: &str) -> (&'static str, String) {
+        match self {
+            OnboardingStep::AllocatePort => ("POST", "/api/v1/allocate-port".to_string()),
+            OnboardingStep::RegisterEndpoint | OnboardingStep::LinkTelegram => {
+                ("POST", format!("/api/v1/identity/{}/link", wallet))
+            }
+            OnboardingStep::CreateService => ("POST", "/api/v1/services/from-template".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepCompletion {
+    pub step: OnboardingStep,
+    pub completed_at: u64,
+    pub rewarded_credits: Option<u64>, // None until the wallet has a session to credit
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OnboardingProgress {
+    pub wallet_address: String,
+    pub started_at: u64,
+    pub completed: Vec<StepCompletion>,
+    pub finished_at: Option<u64>,
+}
+
+impl OnboardingProgress {
+    pub fn new(wallet_address: &str) -> Self {
+        Self {
+            wallet_address: wallet_address.to_string(),
+            started_at: chrono::Utc::now().timestamp() as u64,
+            completed: Vec::new(),
+            finished_at: None,
+        }
+    }
+
+    pub fn is_complete(&self, step: OnboardingStep) -> bool {
+        self.completed
+            .iter()
+            .any(|completion| completion.step == step)
+    }
+
+    pub fn next_step(&self) -> Option<OnboardingStep> {
+        OnboardingStep::ALL
+            .into_iter()
+            .find(|step| !self.is_complete(*step))
+    }
+}
+
+/// Record steps seen done. Completion sticks: undoing a step later (an
+/// expired port, an unlinked account) does not reopen it. Nothing is paid
+/// here; rewards wait for the wallet to `claim` them.
+pub fn record(progress: &mut OnboardingProgress, observed: &[OnboardingStep]) {
+    let now = chrono::Utc::now().timestamp() as u64;
+    for step in observed {
+        if !progress.is_complete(*step) {
+            progress.completed.push(StepCompletion {
+                step: *step,
+                completed_at: now,
+                rewarded_credits: None,
+            });
+        }
+    }
+    if progress.finished_at.is_none() && progress.next_step().is_none() {
+        progress.finished_at = Some(now);
+        println!("🎓 Onboarding finished for {}", progress.wallet_address);
+    }
+}
+
+/// Pay the rewards still owed for recorded steps into the wallet's session.
+/// Each step is paid once. Returns the credits granted now.
+pub fn claim(
+    progress: &mut OnboardingProgress,
+    sessions: &mut HashMap<String, UserSession>,
+) -> u64 {
+    let Some(session) = sessions.get_mut(&progress.wallet_address) else {
+        return 0;
+    };
+    let mut granted = 0;
+    for completion in &mut progress.completed {
+        if completion.rewarded_credits.is_none() {
+            let reward = completion.step.reward_credits();
+            session.credits += reward;
+            completion.rewarded_credits = Some(reward);
+            granted += reward;
+        }
+    }
+    granted
+}
+
+pub fn report(progress: &OnboardingProgress, domain: &str) -> serde_json::Value {
+    let wallet = &progress.wallet_address;
+    let steps: Vec<serde_json::Value> = OnboardingStep::ALL
+        .into_iter()
+        .map(|step| {
+            let completion = progress
+                .completed
+                .iter()
+                .find(|completion| completion.step == step);
+            let (method, path) = step.api(wallet);
+            serde_json::json!({
+                "step": step,
+                "title": step.title(),
+                "done": completion.is_some(),
+                "completed_at": completion.map(|c| c.completed_at),
+                "reward_credits": step.reward_credits(),
+                "rewarded": completion.is_some_and(|c| c.rewarded_credits.is_some()),
+                "claimable": completion.is_some_and(|c| c.rewarded_credits.is_none()),
+                "deep_link": step.deep_link(domain, wallet),
+                "api": { "method": method, "path": path }
+            })
+        })
+        .collect();
+
+    let remaining: Vec<OnboardingStep> = OnboardingStep::ALL
+        .into_iter()
+        .filter(|step| !progress.is_complete(*step))
+        .collect();
+    let credits_remaining: u64 = remaining.iter().map(|step| step.reward_credits()).sum();
+    let credits_earned: u64 = progress
+        .completed
+        .iter()
+        .filter_map(|completion| completion.rewarded_credits)
+        .sum();
+    let credits_claimable: u64 = progress
+        .completed
+        .iter()
+        .filter(|completion| completion.rewarded_credits.is_none())
+        .map(|completion| completion.step.reward_credits())
+        .sum();
+
+    serde_json::json!({
+        "wallet_address": wallet,
+        "started_at": progress.started_at,
+        "finished_at": progress.finished_at,
+        "next_step": progress.next_step(),
+        "remaining": remaining,
+        "steps": steps,
+        "credits_earned": credits_earned,
+        "credits_claimable": credits_claimable,
+        "credits_remaining": credits_remaining
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(wallet: &str) -> HashMap<String, UserSession> {
+        HashMap::from([(
+            wallet.to_string(),
+            UserSession {
+                wallet_address: wallet.to_string(),
+                allocated_port: None,
+                credits: 0,
+                last_activity: 0,
+            },
+        )])
+    }
+
+    #[test]
+    fn test_recording_a_step_grants_nothing() {
+        let mut progress = OnboardingProgress::new("wallet1");
+        let sessions = session("wallet1");
+        record(&mut progress, &[OnboardingStep::AllocatePort]);
+        assert!(progress.is_complete(OnboardingStep::AllocatePort));
+        assert_eq!(sessions["wallet1"].credits, 0);
+        assert_eq!(report(&progress, "node.test")["credits_claimable"], 10);
+    }
+
+    #[test]
+    fn test_each_step_is_paid_once() {
+        let mut progress = OnboardingProgress::new("wallet1");
+        let mut sessions = session("wallet1");
+        record(
+            &mut progress,
+            &[OnboardingStep::AllocatePort, OnboardingStep::LinkTelegram],
+        );
+        assert_eq!(claim(&mut progress, &mut sessions), 30);
+        assert_eq!(claim(&mut progress, &mut sessions), 0);
+
+        // Seen again, e.g. after unlinking and relinking: still paid once
+        record(&mut progress, &[OnboardingStep::LinkTelegram]);
+        assert_eq!(claim(&mut progress, &mut sessions), 0);
+        assert_eq!(progress.completed.len(), 2);
+
+        record(&mut progress, &[OnboardingStep::CreateService]);
+        assert_eq!(claim(&mut progress, &mut sessions), 50);
+        assert_eq!(sessions["wallet1"].credits, 80);
+    }
+
+    #[test]
+    fn test_rewards_wait_for_a_session() {
+        let mut progress = OnboardingProgress::new("wallet1");
+        let mut sessions = HashMap::new();
+        record(&mut progress, &[OnboardingStep::RegisterEndpoint]);
+        assert_eq!(claim(&mut progress, &mut sessions), 0);
+        assert!(progress.completed[0].rewarded_credits.is_none());
+
+        let mut sessions = session("wallet1");
+        assert_eq!(claim(&mut progress, &mut sessions), 20);
+        assert_eq!(claim(&mut progress, &mut sessions), 0);
+    }
+
+    #[test]
+    fn test_claims_pay_only_the_progress_owner() {
+        let mut progress = OnboardingProgress::new("wallet1");
+        let mut sessions = session("wallet2");
+        record(&mut progress, &[OnboardingStep::AllocatePort]);
+        assert_eq!(claim(&mut progress, &mut sessions), 0);
+        assert_eq!(sessions["wallet2"].credits, 0);
+    }
+}
@@ -0,0 +1,560 @@
+// Host-level port authority: instances deployed on the same host (ZOS1 and
+// the ZOS2/ZOS3 it deploys) each get their own slice of the user port range,
+// sized by capacity, and record every port they hand out in one shared
+// ledger so allocations never collide
+// AGPL-3.0 License
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Ports the host hands out to sessions and services
+pub const HOST_RANGE: (u16, u16) = (20000, 29999);
+const DEFAULT_CAPACITY: u16 = 1000;
+const LOCK_ATTEMPTS: u32 = 250; // 20ms apart
+const STALE_LOCK_SECS: u64 = 30;
+
+/// ZOS_PORT_AUTHORITY: the ledger shared by every instance on the host
+pub fn ledger_path() -> String {
+    std::env::var("ZOS_PORT_AUTHORITY")
+        .unwrap_or_else(|_| "/var/lib/zos/port-authority.json".to_string())
+}
+
+/// Inclusive range of ports
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PortRange {
+    pub start: u16,
+    pub end: u16,
+}
+
+impl PortRange {
+    pub fn len(&self) -> u16 {
+        self.end - self.start + 1
+    }
+
+    pub fn contains(&self, port: u16) -> bool {
+        (self.start..=self.end).contains(&port)
+    }
+}
+
+/// A slice of the host range and the capacity it was sized for
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Partition {
+    pub ranges: Vec<PortRange>,
+    pub capacity: u16,
+    pub assigned_at: u64,
+}
+
+impl Partition {
+    fn contains(&self, port: u16) -> bool {
+        self.ranges.iter().any(|range| range.contains(port))
+    }
+
+    fn size(&self) -> u16 {
+        self.ranges.iter().map(PortRange::len).sum()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortAssignment {
+    pub instance: String,
+    pub owner: String,   // wallet, service key or instance name
+    pub purpose: String, // session, service, version, http
+    pub assigned_at: u64,
+}
+
+/// Partitions by instance and every assigned port, as persisted
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PortLedger {
+    pub partitions: BTreeMap<String, Partition>,
+    pub assignments: BTreeMap<u16, PortAssignment>,
+}
+
+impl PortLedger {
+    fn partition_of(&self, port: u16) -> Option<&str> {
+        self.partitions
+            .iter()
+            .find(|(_, partition)| partition.contains(port))
+            .map(|(instance, _)| instance.as_str())
+    }
+
+    /// First unpartitioned run of ports in the host range that is `wanted`
+    /// long, or else the longest one there is
+    fn free_run(&self, wanted: u16, also_taken: &[PortRange]) -> Option<PortRange> {
+        let mut taken: Vec<PortRange> = self
+            .partitions
+            .values()
+            .flat_map(|partition| partition.ranges.iter().copied())
+            .chain(also_taken.iter().copied())
+            .collect();
+        taken.sort_by_key(|range| range.start);
+
+        let mut gaps = Vec::new();
+        let mut next = HOST_RANGE.0;
+        for range in &taken {
+            if range.start > next {
+                gaps.push(PortRange {
+                    start: next,
+                    end: range.start - 1,
+                });
+            }
+            next = next.max(range.end + 1);
+        }
+        if next <= HOST_RANGE.1 {
+            gaps.push(PortRange {
+                start: next,
+                end: HOST_RANGE.1,
+            });
+        }
+
+        let fits = gaps
+            .iter()
+            .find(|gap| gap.len() >= wanted)
+            .map(|gap| PortRange {
+                start: gap.start,
+                end: gap.start + wanted - 1,
+            });
+        fits.or_else(|| gaps.into_iter().max_by_key(PortRange::len))
+    }
+
+    /// Make sure an instance holds at least `capacity` ports, carving more
+    /// out of the host range when its capacity grew. Partitions never
+    /// shrink: ports already handed out stay inside their instance's slice.
+    pub fn partition(
+        &mut self,
+        instance: &str,
+        capacity: u16,
+        now: u64,
+    ) -> Result<&Partition, String> {
+        if capacity == 0 {
+            return Err("Port capacity must be at least 1".to_string());
+        }
+        let mut partition = self.partitions.get(instance).cloned().unwrap_or(Partition {
+            ranges: Vec::new(),
+            capacity,
+            assigned_at: now,
+        });
+        let mut carved: Vec<PortRange> = Vec::new();
+        let carved_size = |carved: &[PortRange]| carved.iter().map(PortRange::len).sum::<u16>();
+        while partition.size() + carved_size(&carved) < capacity {
+            let missing = capacity - partition.size() - carved_size(&carved);
+            let run = self.free_run(missing, &carved).ok_or_else(|| {
+                format!(
+                    "Host port range {}-{} cannot fit {} more ports for {}",
+                    HOST_RANGE.0, HOST_RANGE.1, missing, instance
+                )
+            })?;
+            carved.push(run);
+        }
+
+        partition.ranges.extend(carved);
+        partition.ranges.sort_by_key(|range| range.start);
+        partition.capacity = partition.capacity.max(capacity);
+        self.partitions.insert(instance.to_string(), partition);
+        Ok(&self.partitions[instance])
+    }
+
+    /// A port for `owner` inside the instance's partition; the same one again
+    /// when the owner already holds a port for this purpose
+    pub fn allocate(
+        &mut self,
+        instance: &str,
+        owner: &str,
+        purpose: &str,
+        now: u64,
+        usable: impl Fn(u16) -> bool,
+    ) -> Result<u16, String> {
+        if let Some((port, _)) = self.assignments.iter().find(|(_, assignment)| {
+            assignment.instance == instance
+                && assignment.owner == owner
+                && assignment.purpose == purpose
+        }) {
+            return Ok(*port);
+        }
+        let partition = self
+            .partitions
+            .get(instance)
+            .ok_or_else(|| format!("{} has no port partition", instance))?;
+        let port = partition
+            .ranges
+            .iter()
+            .flat_map(|range| range.start..=range.end)
+            .find(|port| !self.assignments.contains_key(port) && usable(*port))
+            .ok_or_else(|| format!("No free ports left in {}'s partition", instance))?;
+        self.assignments.insert(
+            port,
+            PortAssignment {
+                instance: instance.to_string(),
+                owner: owner.to_string(),
+                purpose: purpose.to_string(),
+                assigned_at: now,
+            },
+        );
+        Ok(port)
+    }
+
+    /// Record a port chosen by the caller (an HTTP port, a version's own
+    /// port). Refused when another instance holds it or it lies in another
+    /// instance's partition.
+    pub fn reserve(
+        &mut self,
+        instance: &str,
+        port: u16,
+        owner: &str,
+        purpose: &str,
+        now: u64,
+    ) -> Result<(), String> {
+        if let Some(assignment) = self.assignments.get(&port) {
+            if assignment.instance != instance || assignment.owner != owner {
+                return Err(format!(
+                    "Port {} is assigned to {} on {}",
+                    port, assignment.owner, assignment.instance
+                ));
+            }
+            return Ok(());
+        }
+        if let Some(other) = self.partition_of(port).filter(|other| *other != instance) {
+            return Err(format!("Port {} is in {}'s partition", port, other));
+        }
+        self.assignments.insert(
+            port,
+            PortAssignment {
+                instance: instance.to_string(),
+                owner: owner.to_string(),
+                purpose: purpose.to_string(),
+                assigned_at: now,
+            },
+        );
+        Ok(())
+    }
+
+    /// Ports an owner held for a purpose, now free again
+    pub fn release_owner(&mut self, instance: &str, owner: &str, purpose: &str) -> Vec<u16> {
+        let held: Vec<u16> = self
+            .assignments
+            .iter()
+            .filter(|(_, assignment)| {
+                assignment.instance == instance
+                    && assignment.owner == owner
+                    && assignment.purpose == purpose
+            })
+            .map(|(port, _)| *port)
+            .collect();
+        for port in &held {
+            self.assignments.remove(port);
+        }
+        held
+    }
+
+    pub fn release(&mut self, instance: &str, port: u16) -> bool {
+        match self.assignments.get(&port) {
+            Some(assignment) if assignment.instance == instance => {
+                self.assignments.remove(&port);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Held while an instance reads and rewrites the ledger; removed on drop
+struct LedgerLock(String);
+
+impl LedgerLock {
+    fn acquire(path: &str) -> Result<Self, String> {
+        let lock_path = format!("{}.lock", path);
+        if let Some(dir) = std::path::Path::new(path).parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("Cannot create {:?}: {}", dir, e))?;
+        }
+        for _ in 0..LOCK_ATTEMPTS {
+            match std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&lock_path)
+            {
+                Ok(_) => return Ok(Self(lock_path)),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    // A holder that died mid-update leaves its lock behind
+                    let stale = std::fs::metadata(&lock_path)
+                        .and_then(|meta| meta.modified())
+                        .ok()
+                        .and_then(|modified| modified.elapsed().ok())
+                        .is_some_and(|age| age.as_secs() > STALE_LOCK_SECS);
+                    if stale {
+                        let _ = std::fs::remove_file(&lock_path);
+                    } else {
+                        std::thread::sleep(std::time::Duration::from_millis(20));
+                    }
+                }
+                Err(e) => return Err(format!("Cannot lock {}: {}", path, e)),
+            }
+        }
+        Err(format!("Timed out waiting for {}", lock_path))
+    }
+}
+
+impl Drop for LedgerLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// This instance's view of the authority: which instance it is, how many
+/// ports it needs, and where the host's ledger lives
+#[derive(Debug, Clone)]
+pub struct PortManager {
+    pub instance: String,
+    pub capacity: u16,
+    pub path: String,
+}
+
+impl PortManager {
+    /// ZOS_INSTANCE_NAME (default zos-{http_port}) and ZOS_PORT_CAPACITY
+    pub fn load(http_port: u16) -> Self {
+        Self {
+            instance: std::env::var("ZOS_INSTANCE_NAME")
+                .unwrap_or_else(|_| format!("zos-{}", http_port)),
+            capacity: std::env::var("ZOS_PORT_CAPACITY")
+                .ok()
+                .and_then(|capacity| capacity.parse().ok())
+                .unwrap_or(DEFAULT_CAPACITY),
+            path: ledger_path(),
+        }
+    }
+
+    /// Read, change and write back the ledger under the host-wide lock
+    fn update<T>(
+        &self,
+        change: impl FnOnce(&mut PortLedger) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let _lock = LedgerLock::acquire(&self.path)?;
+        let mut ledger = self.ledger()?;
+        let result = change(&mut ledger)?;
+        let json = serde_json::to_string_pretty(&ledger)
+            .map_err(|e| format!("Failed to serialize port ledger: {}", e))?;
+        let tmp_path = format!("{}.tmp", self.path);
+        std::fs::write(&tmp_path, json)
+            .map_err(|e| format!("Failed to write {}: {}", tmp_path, e))?;
+        std::fs::rename(&tmp_path, &self.path)
+            .map_err(|e| format!("Failed to replace {}: {}", self.path, e))?;
+        Ok(result)
+    }
+
+    pub fn ledger(&self) -> Result<PortLedger, String> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(json) => serde_json::from_str(&json)
+                .map_err(|e| format!("Corrupt port ledger {}: {}", self.path, e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(PortLedger::default()),
+            Err(e) => Err(format!("Failed to read {}: {}", self.path, e)),
+        }
+    }
+
+    /// Claim this instance's partition and record its HTTP port. Run at startup.
+    pub fn register(&self, http_port: u16) -> Result<Partition, String> {
+        let now = chrono::Utc::now().timestamp() as u64;
+        self.update(|ledger| {
+            ledger.reserve(&self.instance, http_port, &self.instance, "http", now)?;
+            ledger
+                .partition(&self.instance, self.capacity, now)
+                .cloned()
+        })
+    }
+
+    /// Partition another instance before deploying it, with its HTTP port
+    pub fn register_instance(
+        &self,
+        instance: &str,
+        capacity: u16,
+        http_port: u16,
+    ) -> Result<Partition, String> {
+        let now = chrono::Utc::now().timestamp() as u64;
+        self.update(|ledger| {
+            ledger.reserve(instance, http_port, instance, "http", now)?;
+            ledger.partition(instance, capacity, now).cloned()
+        })
+    }
+
+    /// A free port from this instance's partition that nothing on the host
+    /// is listening on. Claims the partition first if startup could not.
+    pub fn allocate(&self, owner: &str, purpose: &str) -> Result<u16, String> {
+        let now = chrono::Utc::now().timestamp() as u64;
+        self.update(|ledger| {
+            ledger.partition(&self.instance, self.capacity, now)?;
+            ledger.allocate(&self.instance, owner, purpose, now, |port| {
+                std::net::TcpListener::bind(("127.0.0.1", port)).is_ok()
+            })
+        })
+    }
+
+    pub fn reserve(&self, port: u16, owner: &str, purpose: &str) -> Result<(), String> {
+        let now = chrono::Utc::now().timestamp() as u64;
+        self.update(|ledger| ledger.reserve(&self.instance, port, owner, purpose, now))
+    }
+
+    pub fn release(&self, port: u16) -> Result<bool, String> {
+        self.update(|ledger| Ok(ledger.release(&self.instance, port)))
+    }
+
+    pub fn release_owner(&self, owner: &str, purpose: &str) -> Result<Vec<u16>, String> {
+        self.update(|ledger| Ok(ledger.release_owner(&self.instance, owner, purpose)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partitions_never_overlap_and_only_grow() {
+        let mut ledger = PortLedger::default();
+        let zos1 = ledger.partition("zos1", 100, 0).unwrap().clone();
+        let zos2 = ledger.partition("zos2", 50, 0).unwrap().clone();
+        assert_eq!(
+            zos1.ranges,
+            vec![PortRange {
+                start: 20000,
+                end: 20099
+            }]
+        );
+        assert_eq!(
+            zos2.ranges,
+            vec![PortRange {
+                start: 20100,
+                end: 20149
+            }]
+        );
+
+        // zos1 grows past zos2, so its new ports come from after it
+        let grown = ledger.partition("zos1", 120, 0).unwrap().clone();
+        assert_eq!(grown.size(), 120);
+        assert_eq!(
+            grown.ranges[1],
+            PortRange {
+                start: 20150,
+                end: 20169
+            }
+        );
+        assert_eq!(ledger.partition("zos1", 10, 0).unwrap().size(), 120);
+
+        assert!(ledger.partition("zos3", 0, 0).is_err());
+        assert!(ledger.partition("huge", u16::MAX, 0).is_err());
+    }
+
+    #[test]
+    fn test_allocations_skip_taken_and_busy_ports() {
+        let mut ledger = PortLedger::default();
+        ledger.partition("zos1", 3, 0).unwrap();
+        ledger.partition("zos2", 3, 0).unwrap();
+
+        let first = ledger
+            .allocate("zos1", "wallet1", "session", 0, |_| true)
+            .unwrap();
+        assert_eq!(first, 20000);
+        // The same owner and purpose get the same port back
+        assert_eq!(
+            ledger
+                .allocate("zos1", "wallet1", "session", 0, |_| true)
+                .unwrap(),
+            first
+        );
+        // A port something else on the host listens on is passed over
+        let second = ledger
+            .allocate("zos1", "wallet2", "session", 0, |port| port != 20001)
+            .unwrap();
+        assert_eq!(second, 20002);
+        assert_eq!(
+            ledger
+                .allocate("zos1", "wallet3", "session", 0, |_| true)
+                .unwrap(),
+            20001
+        );
+        assert!(ledger
+            .allocate("zos1", "wallet4", "session", 0, |_| true)
+            .is_err());
+
+        // zos2 allocates from its own slice, never zos1's
+        assert_eq!(
+            ledger
+                .allocate("zos2", "wallet1", "session", 0, |_| true)
+                .unwrap(),
+            20003
+        );
+        assert!(ledger
+            .allocate("zos9", "wallet1", "session", 0, |_| true)
+            .is_err());
+    }
+
+    #[test]
+    fn test_reservations_respect_other_instances() {
+        let mut ledger = PortLedger::default();
+        ledger.partition("zos1", 10, 0).unwrap();
+        ledger.reserve("zos1", 8080, "zos1", "http", 0).unwrap();
+        ledger.reserve("zos1", 8080, "zos1", "http", 0).unwrap(); // idempotent
+        assert!(ledger.reserve("zos2", 8080, "zos2", "http", 0).is_err());
+        assert!(ledger.reserve("zos2", 20005, "zos2", "version", 0).is_err());
+        ledger.reserve("zos1", 20005, "svc", "version", 0).unwrap();
+        assert!(ledger
+            .reserve("zos1", 20005, "other", "version", 0)
+            .is_err());
+    }
+
+    #[test]
+    fn test_released_ports_are_reused() {
+        let mut ledger = PortLedger::default();
+        ledger.partition("zos1", 4, 0).unwrap();
+        ledger.partition("zos2", 4, 0).unwrap();
+        let a = ledger
+            .allocate("zos1", "wallet1", "session", 0, |_| true)
+            .unwrap();
+        let b = ledger
+            .allocate("zos1", "wallet1", "service", 0, |_| true)
+            .unwrap();
+        let c = ledger
+            .allocate("zos2", "wallet1", "session", 0, |_| true)
+            .unwrap();
+
+        // Only the instance that holds a port may release it
+        assert!(!ledger.release("zos2", a));
+        assert!(ledger.release("zos1", a));
+        assert!(!ledger.release("zos1", a));
+        assert_eq!(
+            ledger
+                .allocate("zos1", "wallet2", "session", 0, |_| true)
+                .unwrap(),
+            a
+        );
+
+        assert_eq!(ledger.release_owner("zos1", "wallet1", "service"), vec![b]);
+        assert!(ledger
+            .release_owner("zos1", "wallet1", "service")
+            .is_empty());
+        assert!(ledger.assignments.contains_key(&c));
+    }
+
+    #[test]
+    fn test_manager_persists_the_shared_ledger() {
+        let dir = std::env::temp_dir().join(format!("zos-ports-{}", std::process::id()));
+        let path = dir
+            .join("port-authority.json")
+            .to_string_lossy()
+            .into_owned();
+        let manager = |instance: &str| PortManager {
+            instance: instance.to_string(),
+            capacity: 5,
+            path: path.clone(),
+        };
+
+        let zos1 = manager("zos1").register(18080).unwrap();
+        let zos2 = manager("zos2").register(18081).unwrap();
+        assert!(zos1.ranges.iter().all(|range| zos2
+            .ranges
+            .iter()
+            .all(|other| other.end < range.start || other.start > range.end)));
+        assert!(manager("zos2").reserve(18080, "svc", "version").is_err());
+
+        let ledger = manager("zos1").ledger().unwrap();
+        assert_eq!(ledger.partitions.len(), 2);
+        assert_eq!(ledger.assignments[&18080].owner, "zos1");
+        assert!(!std::path::Path::new(&format!("{}.lock", path)).exists());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
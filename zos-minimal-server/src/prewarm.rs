@@ -0,0 +1,297 @@
+// Traffic prediction and budgeted pre-warming for registered services
+// AGPL-3.0 License
+
+use crate::service_templates::RegisteredService;
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const HOURS_PER_WEEK: usize = 168;
+const SMOOTHING: f64 = 0.3; // weight of the newest observation per bucket
+
+/// Requests per hour, learned per hour-of-day and hour-of-week
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrafficModel {
+    pub hour_of_week: Vec<Option<f64>>,
+    pub hour_of_day: Vec<Option<f64>>,
+    pub current_hour: u64, // hours since the epoch
+    pub current_count: u64,
+}
+
+impl Default for TrafficModel {
+    fn default() -> Self {
+        Self {
+            hour_of_week: vec![None; HOURS_PER_WEEK],
+            hour_of_day: vec![None; 24],
+            current_hour: 0,
+            current_count: 0,
+        }
+    }
+}
+
+fn smooth(bucket: &mut Option<f64>, observed: f64) {
+    *bucket = Some(match *bucket {
+        Some(previous) => SMOOTHING * observed + (1.0 - SMOOTHING) * previous,
+        None => observed,
+    });
+}
+
+impl TrafficModel {
+    pub fn record(&mut self, now: u64) {
+        self.roll_to(now / 3600);
+        self.current_count += 1;
+    }
+
+    /// Close out finished hours, counting silent hours as zero traffic
+    fn roll_to(&mut self, hour: u64) {
+        if self.current_hour == 0 {
+            self.current_hour = hour;
+        }
+
+        while self.current_hour < hour {
+            let observed = self.current_count as f64;
+            let epoch_hour = self.current_hour as usize;
+            smooth(
+                &mut self.hour_of_week[epoch_hour % HOURS_PER_WEEK],
+                observed,
+            );
+            smooth(&mut self.hour_of_day[epoch_hour % 24], observed);
+
+            self.current_count = 0;
+            self.current_hour += 1;
+
+            // A week of silence tells us everything a longer gap would
+            if hour - self.current_hour > HOURS_PER_WEEK as u64 {
+                self.current_hour = hour - HOURS_PER_WEEK as u64;
+            }
+        }
+    }
+
+    /// Expected requests in the hour containing `at`. Falls back to the daily
+    /// pattern until a full week has been observed for that slot.
+    pub fn predict(&self, at: u64) -> f64 {
+        let hour = (at / 3600) as usize;
+        self.hour_of_week[hour % HOURS_PER_WEEK]
+            .or(self.hour_of_day[hour % 24])
+            .unwrap_or(0.0)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrewarmPolicy {
+    pub enabled: bool,
+    pub lead_secs: u64,              // how far ahead of a spike to warm
+    pub min_predicted_requests: f64, // per hour, to count as a spike
+    pub warm_cost_credits: u64, // the node's price (ZOS_PREWARM_COST_CREDITS), shown to the owner
+    pub daily_budget_credits: u64,
+    pub cooldown_secs: u64, // minimum time between pre-warms
+}
+
+impl Default for PrewarmPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            lead_secs: 600,
+            min_predicted_requests: 20.0,
+            warm_cost_credits: 5,
+            daily_budget_credits: 50,
+            cooldown_secs: 3600,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrewarmEvent {
+    pub timestamp: u64,
+    pub predicted_requests: f64,
+    pub credits_charged: u64,
+    pub outcome: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PrewarmState {
+    pub policy: PrewarmPolicy,
+    pub model: TrafficModel,
+    pub budget_day: u64, // days since the epoch
+    pub spent_today: u64,
+    pub last_warmed: Option<u64>,
+    pub history: Vec<PrewarmEvent>,
+}
+
+impl PrewarmState {
+    pub fn remaining_budget(&self, now: u64) -> u64 {
+        if self.budget_day == now / 86400 {
+            self.policy
+                .daily_budget_credits
+                .saturating_sub(self.spent_today)
+        } else {
+            self.policy.daily_budget_credits
+        }
+    }
+
+    fn push_event(&mut self, event: PrewarmEvent) {
+        self.history.push(event);
+        if self.history.len() > 100 {
+            self.history.remove(0);
+        }
+    }
+}
+
+/// Count a request toward the service's traffic model
+pub async fn record_request(state: &AppState, service_key: &str, now: u64) {
+    if !state.services.read().await.contains_key(service_key) {
+        return;
+    }
+
+    state
+        .prewarm
+        .write()
+        .await
+        .entry(service_key.to_string())
+        .or_default()
+        .model
+        .record(now);
+}
+
+/// Start the service process if it is not listening and hit its health
+/// endpoint so caches and lazy initialization are done before traffic lands
+async fn warm_service(state: &AppState, service: &RegisteredService) -> Result<(), String> {
+    let address = format!("127.0.0.1:{}", service.port);
+    let listening = tokio::net::TcpStream::connect(&address).await.is_ok();
+
+    if !listening {
+        let run_script = format!("{}/run.sh", service.work_dir);
+        if !std::path::Path::new(&run_script).exists() {
+            return Err("No run script to start".to_string());
+        }
+
+        tokio::process::Command::new("bash")
+            .arg(&run_script)
+            .current_dir(&service.work_dir)
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Failed to start {}: {}", service.service_name, e))?;
+
+        // Give the process a moment to bind before priming
+        for _ in 0..20 {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            if tokio::net::TcpStream::connect(&address).await.is_ok() {
+                break;
+            }
+        }
+    }
+
+    let service_key = format!("{}_{}", service.wallet_address, service.service_name);
+    let url = state
+        .health_checks
+        .read()
+        .await
+        .get(&service_key)
+        .map(|check| check.url.clone());
+
+    if let Some(url) = url.filter(|url| url.starts_with("http")) {
+        let clients = crate::http_clients::shared();
+        clients
+            .send_with_retry(clients.get(&url).timeout(Duration::from_secs(10)))
+            .await
+            .map_err(|e| format!("Priming request failed: {}", e))?;
+    }
+
+    Ok(())
+}
+
+/// Pre-warm services ahead of predicted spikes, within the owner's daily
+/// budget and current credits
+pub async fn prewarm_due(state: AppState) -> Result<(), String> {
+    let now = chrono::Utc::now().timestamp() as u64;
+
+    let due: Vec<(String, f64)> = {
+        let mut prewarm = state.prewarm.write().await;
+        prewarm
+            .iter_mut()
+            .filter_map(|(service_key, entry)| {
+                entry.model.roll_to(now / 3600);
+                let policy = &entry.policy;
+                let predicted = entry.model.predict(now + policy.lead_secs);
+                let cooled = entry
+                    .last_warmed
+                    .is_none_or(|last| now >= last + policy.cooldown_secs);
+
+                (policy.enabled && cooled && predicted >= policy.min_predicted_requests)
+                    .then(|| (service_key.clone(), predicted))
+            })
+            .collect()
+    };
+
+    for (service_key, predicted) in due {
+        let service = match state.services.read().await.get(&service_key) {
+            Some(service) => service.clone(),
+            None => continue,
+        };
+
+        // Reserve credits before warming so the budget can never be overrun
+        let charged = {
+            let mut prewarm = state.prewarm.write().await;
+            let entry = match prewarm.get_mut(&service_key) {
+                Some(entry) => entry,
+                None => continue,
+            };
+            let cost = state.config.prewarm_cost_credits;
+            entry.last_warmed = Some(now);
+
+            let mut sessions = state.user_sessions.write().await;
+            let credits = sessions
+                .get_mut(&service.wallet_address)
+                .map(|session| &mut session.credits);
+
+            let refusal = match credits {
+                _ if entry.remaining_budget(now) < cost => Some("daily budget exhausted"),
+                Some(credits) if *credits >= cost => {
+                    *credits -= cost;
+                    None
+                }
+                _ => Some("insufficient credits"),
+            };
+
+            if let Some(reason) = refusal {
+                entry.push_event(PrewarmEvent {
+                    timestamp: now,
+                    predicted_requests: predicted,
+                    credits_charged: 0,
+                    outcome: format!("skipped: {}", reason),
+                });
+                continue;
+            }
+
+            if entry.budget_day != now / 86400 {
+                entry.budget_day = now / 86400;
+                entry.spent_today = 0;
+            }
+            entry.spent_today += cost;
+            cost
+        };
+
+        let result = warm_service(&state, &service).await;
+        println!(
+            "🔥 Pre-warm {} (≈{:.0} req/h expected): {}",
+            service_key,
+            predicted,
+            result.as_ref().map(|_| "ok").unwrap_or("failed")
+        );
+
+        if let Some(entry) = state.prewarm.write().await.get_mut(&service_key) {
+            entry.push_event(PrewarmEvent {
+                timestamp: now,
+                predicted_requests: predicted,
+                credits_charged: charged,
+                outcome: match result {
+                    Ok(()) => "warmed".to_string(),
+                    Err(e) => format!("failed: {}", e),
+                },
+            });
+        }
+    }
+
+    Ok(())
+}
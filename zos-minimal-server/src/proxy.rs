@@ -0,0 +1,92 @@
+// Pass-through proxying of service calls to their local backends
+// AGPL-3.0 License
+
+use crate::service_templates::RegisteredService;
+use axum::body::Body;
+use axum::http::{header, HeaderMap, HeaderName, Request, Uri};
+use axum::response::Response;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use std::time::Duration;
+
+pub type ProxyClient = Client<HttpConnector, Body>;
+
+/// Shared client so backend connections are kept alive between calls
+pub fn client() -> ProxyClient {
+    let mut connector = HttpConnector::new();
+    connector.set_connect_timeout(Some(Duration::from_secs(5)));
+    connector.set_nodelay(true);
+
+    Client::builder(TokioExecutor::new())
+        .pool_idle_timeout(Duration::from_secs(90))
+        .build(connector)
+}
+
+/// Connection-level headers that must not be forwarded (RFC 9110 §7.6.1)
+const HOP_BY_HOP: [&str; 8] = [
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+fn strip_hop_by_hop(headers: &mut HeaderMap) {
+    // Headers named in Connection are hop-by-hop too
+    let named: Vec<HeaderName> = headers
+        .get_all(header::CONNECTION)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|name| HeaderName::try_from(name.trim()).ok())
+        .collect();
+
+    for name in named {
+        headers.remove(name);
+    }
+    for name in HOP_BY_HOP {
+        headers.remove(name);
+    }
+}
+
+/// Forward a request to the service's backend without buffering or parsing
+/// either body; `rest` is the path after `/{wallet}/{service}`.
+pub async fn forward(
+    client: &ProxyClient,
+    service: &RegisteredService,
+    rest: &str,
+    request: Request<Body>,
+) -> Result<Response, String> {
+    let (mut parts, body) = request.into_parts();
+
+    let query = parts
+        .uri
+        .query()
+        .map(|query| format!("?{}", query))
+        .unwrap_or_default();
+    parts.uri = format!(
+        "http://127.0.0.1:{}/{}{}",
+        service.port,
+        rest.trim_start_matches('/'),
+        query
+    )
+    .parse::<Uri>()
+    .map_err(|e| format!("Invalid backend URI: {}", e))?;
+
+    strip_hop_by_hop(&mut parts.headers);
+    parts.headers.remove(header::HOST);
+
+    let response = client
+        .request(Request::from_parts(parts, body))
+        .await
+        .map_err(|e| format!("Backend for {} unreachable: {}", service.service_name, e))?;
+
+    let (mut parts, incoming) = response.into_parts();
+    strip_hop_by_hop(&mut parts.headers);
+
+    Ok(Response::from_parts(parts, Body::new(incoming)))
+}
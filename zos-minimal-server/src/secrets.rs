@@ -0,0 +1,506 @@
+// Secrets store: bot tokens, signing keys and the like, sealed with a data
+// key per secret that is itself wrapped by a master key from a file or KMS
+// AGPL-3.0 License
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+
+/// Secrets the node reads, or hands to the tools it runs through
+/// `secrets-exec`. Until one is stored here it is still taken from the
+/// environment variable of the same name.
+pub const KNOWN: &[(&str, &str)] = &[
+    (
+        "ZOS_TELEGRAM_BOT_TOKEN",
+        "Telegram alerts and message relays",
+    ),
+    (
+        "ZOS_EXPORT_SIGNING_KEY",
+        "HMAC key for data export manifests",
+    ),
+    (
+        "ZOS_OIDC_SIGNING_KEY",
+        "Hex seed of the OIDC token signing key",
+    ),
+    (
+        "ZOS_FEED_INGEST_KEY",
+        "Shared key other modules use to publish activity feed events",
+    ),
+    (
+        "ZOS_OPERATOR_KEY",
+        "Gateway operator key, to export and erase wallets' gateway records",
+    ),
+    (
+        "NAMECHEAP_PASSWORD",
+        "Namecheap dynamic DNS password, for namecheap_ddns.py and the stage1 DDNS client",
+    ),
+    (
+        "OCI_PRIVATE_KEY",
+        "Oracle Cloud API signing key (PEM), for the OCI CLI and Terraform",
+    ),
+    (
+        "OCI_PRIVATE_KEY_PASSWORD",
+        "Passphrase of the Oracle Cloud API signing key",
+    ),
+];
+
+/// A secret at rest: the value sealed under its own data key, and the data
+/// key sealed under the master key. Both are bound to the secret's name.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SealedSecret {
+    pub name: String,
+    pub ciphertext: String, // base64
+    pub nonce: String,
+    pub wrapped_key: String,
+    pub key_nonce: String,
+    pub version: u32,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// What the API shows about a secret without opening it
+#[derive(Debug, Clone, Serialize)]
+pub struct SecretInfo {
+    pub name: String,
+    pub version: u32,
+    pub created_at: u64,
+    pub updated_at: u64,
+    pub description: Option<&'static str>,
+}
+
+impl From<&SealedSecret> for SecretInfo {
+    fn from(sealed: &SealedSecret) -> Self {
+        Self {
+            name: sealed.name.clone(),
+            version: sealed.version,
+            created_at: sealed.created_at,
+            updated_at: sealed.updated_at,
+            description: describe(&sealed.name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MasterKeySource {
+    File(String),
+    Kms(String),
+}
+
+pub struct SecretStore {
+    master: Key,
+    pub source: MasterKeySource,
+    path: String,
+    secrets: RwLock<HashMap<String, SealedSecret>>,
+}
+
+static STORE: OnceLock<SecretStore> = OnceLock::new();
+
+fn describe(name: &str) -> Option<&'static str> {
+    KNOWN
+        .iter()
+        .find(|(known, _)| *known == name)
+        .map(|(_, description)| *description)
+}
+
+pub fn store_path() -> String {
+    format!("{}/secrets.json", crate::migrations::state_dir())
+}
+
+fn default_master_key_file() -> String {
+    format!("{}/secrets_master_key", crate::migrations::state_dir())
+}
+
+fn random<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes
+}
+
+fn decode_key(text: &str) -> Option<Key> {
+    let text = text.trim();
+    let bytes: Vec<u8> = (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect::<Option<_>>()?;
+    let bytes: [u8; 32] = bytes.try_into().ok()?;
+    Some(Key::from(bytes))
+}
+
+fn write_private(path: &str, contents: &[u8]) -> Result<(), String> {
+    use std::io::Write;
+    #[cfg(unix)]
+    use std::os::unix::fs::OpenOptionsExt;
+
+    if let Some(dir) = std::path::Path::new(path).parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("Cannot create {:?}: {}", dir, e))?;
+    }
+    let partial = format!("{}.partial", path);
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    options
+        .open(&partial)
+        .and_then(|mut file| file.write_all(contents))
+        .map_err(|e| format!("Cannot write {}: {}", partial, e))?;
+    std::fs::rename(&partial, path).map_err(|e| format!("Cannot replace {}: {}", path, e))
+}
+
+/// ZOS_SECRETS_MASTER_KEY_FILE (hex, created on first start when missing)
+fn master_key_from_file() -> Result<(Key, MasterKeySource), String> {
+    let path =
+        std::env::var("ZOS_SECRETS_MASTER_KEY_FILE").unwrap_or_else(|_| default_master_key_file());
+    match std::fs::read_to_string(&path) {
+        Ok(text) => {
+            let key =
+                decode_key(&text).ok_or_else(|| format!("{} is not a 32-byte hex key", path))?;
+            Ok((key, MasterKeySource::File(path)))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let key: [u8; 32] = random();
+            let hex: String = key.iter().map(|b| format!("{:02x}", b)).collect();
+            write_private(&path, hex.as_bytes())?;
+            println!("🔐 Created secrets master key at {}; back it up", path);
+            Ok((Key::from(key), MasterKeySource::File(path)))
+        }
+        Err(e) => Err(format!("Cannot read {}: {}", path, e)),
+    }
+}
+
+/// ZOS_SECRETS_KMS_URL answers {"key": "<hex>"} to a GET carrying
+/// ZOS_SECRETS_KMS_TOKEN as a bearer token
+async fn master_key_from_kms(url: &str) -> Result<(Key, MasterKeySource), String> {
+    let clients = crate::http_clients::shared();
+    let mut request = clients.get(url);
+    if let Ok(token) = std::env::var("ZOS_SECRETS_KMS_TOKEN") {
+        request = request.bearer_auth(token);
+    }
+    let response = clients
+        .send_with_retry(request)
+        .await
+        .map_err(|e| format!("KMS unreachable: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("KMS returned {}", response.status()));
+    }
+    let body: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
+    let key = body["key"]
+        .as_str()
+        .and_then(decode_key)
+        .ok_or("KMS answer has no 32-byte hex key")?;
+    Ok((key, MasterKeySource::Kms(url.to_string())))
+}
+
+/// Load the master key and the sealed secrets; call once at startup. A
+/// store that cannot be opened stops the node rather than losing secrets.
+pub async fn init() -> Result<&'static SecretStore, String> {
+    if let Some(store) = STORE.get() {
+        return Ok(store);
+    }
+    let (master, source) = match std::env::var("ZOS_SECRETS_KMS_URL") {
+        Ok(url) if !url.is_empty() => master_key_from_kms(&url).await?,
+        _ => master_key_from_file()?,
+    };
+
+    let path = store_path();
+    let secrets: HashMap<String, SealedSecret> = match std::fs::read(&path) {
+        Ok(bytes) => {
+            serde_json::from_slice(&bytes).map_err(|e| format!("Cannot parse {}: {}", path, e))?
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+        Err(e) => return Err(format!("Cannot read {}: {}", path, e)),
+    };
+
+    let store = SecretStore {
+        master,
+        source,
+        path,
+        secrets: RwLock::new(secrets),
+    };
+    // Catch a master key that does not match the store before anything needs it
+    for name in store.names() {
+        store.open(&name)?;
+    }
+    Ok(STORE.get_or_init(|| store))
+}
+
+pub fn store() -> Option<&'static SecretStore> {
+    STORE.get()
+}
+
+/// A secret's value: from the store once initialized, else the environment
+pub fn resolve(name: &str) -> Option<String> {
+    let stored = store().and_then(|store| match store.open(name) {
+        Ok(value) => value,
+        Err(e) => {
+            println!("⚠️  Secret {} cannot be opened: {}", name, e);
+            None
+        }
+    });
+    stored.or_else(|| std::env::var(name).ok().filter(|value| !value.is_empty()))
+}
+
+pub fn is_set(name: &str) -> bool {
+    store().is_some_and(|store| store.contains(name))
+        || std::env::var(name).is_ok_and(|value| !value.is_empty())
+}
+
+/// Every known secret that has a value, as environment variables for a
+/// tool run through `secrets-exec`
+pub fn known_env() -> Vec<(&'static str, String)> {
+    KNOWN
+        .iter()
+        .filter_map(|(name, _)| resolve(name).map(|value| (*name, value)))
+        .collect()
+}
+
+/// Known secrets still read from plaintext environment variables
+pub fn plaintext_in_env() -> Vec<&'static str> {
+    KNOWN
+        .iter()
+        .map(|(name, _)| *name)
+        .filter(|name| !store().is_some_and(|store| store.contains(name)))
+        .filter(|name| std::env::var(name).is_ok_and(|value| !value.is_empty()))
+        .collect()
+}
+
+pub fn validate_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit() || b == b'_');
+    if valid {
+        Ok(())
+    } else {
+        Err("Secret names are 1-64 characters of A-Z, 0-9 and _".to_string())
+    }
+}
+
+fn seal(key: &Key, plaintext: &[u8], name: &str) -> Result<(String, String), String> {
+    let nonce: [u8; 12] = random();
+    let ciphertext = ChaCha20Poly1305::new(key)
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad: name.as_bytes(),
+            },
+        )
+        .map_err(|_| "Encryption failed".to_string())?;
+    Ok((STANDARD.encode(ciphertext), STANDARD.encode(nonce)))
+}
+
+fn unseal(key: &Key, ciphertext: &str, nonce: &str, name: &str) -> Result<Vec<u8>, String> {
+    let ciphertext = STANDARD.decode(ciphertext).map_err(|e| e.to_string())?;
+    let nonce = STANDARD.decode(nonce).map_err(|e| e.to_string())?;
+    if nonce.len() != 12 {
+        return Err("Bad nonce".to_string());
+    }
+    ChaCha20Poly1305::new(key)
+        .decrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: &ciphertext,
+                aad: name.as_bytes(),
+            },
+        )
+        .map_err(|_| "Wrong master key or tampered secret".to_string())
+}
+
+impl SecretStore {
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .secrets
+            .read()
+            .map(|secrets| secrets.keys().cloned().collect())
+            .unwrap_or_default();
+        names.sort();
+        names
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.secrets
+            .read()
+            .is_ok_and(|secrets| secrets.contains_key(name))
+    }
+
+    pub fn list(&self) -> Vec<SecretInfo> {
+        let mut infos: Vec<SecretInfo> = self
+            .secrets
+            .read()
+            .map(|secrets| secrets.values().map(SecretInfo::from).collect())
+            .unwrap_or_default();
+        infos.sort_by(|a, b| a.name.cmp(&b.name));
+        infos
+    }
+
+    pub fn info(&self, name: &str) -> Option<SecretInfo> {
+        self.secrets.read().ok()?.get(name).map(SecretInfo::from)
+    }
+
+    /// Unwrap the data key, then the value
+    pub fn open(&self, name: &str) -> Result<Option<String>, String> {
+        let sealed = match self.secrets.read() {
+            Ok(secrets) => match secrets.get(name) {
+                Some(sealed) => sealed.clone(),
+                None => return Ok(None),
+            },
+            Err(_) => return Err("Secrets store lock poisoned".to_string()),
+        };
+        let data_key = unseal(&self.master, &sealed.wrapped_key, &sealed.key_nonce, name)?;
+        let data_key: [u8; 32] = data_key
+            .try_into()
+            .map_err(|_| "Bad data key".to_string())?;
+        let value = unseal(
+            &Key::from(data_key),
+            &sealed.ciphertext,
+            &sealed.nonce,
+            name,
+        )?;
+        String::from_utf8(value)
+            .map(Some)
+            .map_err(|_| "Secret is not UTF-8".to_string())
+    }
+
+    /// Seal a value under a fresh data key; replacing a secret bumps its version
+    pub fn put(&self, name: &str, value: &str) -> Result<SecretInfo, String> {
+        validate_name(name)?;
+        if value.is_empty() {
+            return Err("A secret needs a value".to_string());
+        }
+        let data_key: [u8; 32] = random();
+        let (ciphertext, nonce) = seal(&Key::from(data_key), value.as_bytes(), name)?;
+        let (wrapped_key, key_nonce) = seal(&self.master, &data_key, name)?;
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        let mut secrets = self
+            .secrets
+            .write()
+            .map_err(|_| "Secrets store lock poisoned".to_string())?;
+        let previous = secrets.get(name);
+        let sealed = SealedSecret {
+            name: name.to_string(),
+            ciphertext,
+            nonce,
+            wrapped_key,
+            key_nonce,
+            version: previous.map_or(1, |previous| previous.version + 1),
+            created_at: previous.map_or(now, |previous| previous.created_at),
+            updated_at: now,
+        };
+        let info = SecretInfo::from(&sealed);
+        // On disk first, so a failed write leaves memory matching the file
+        let mut updated = secrets.clone();
+        updated.insert(name.to_string(), sealed);
+        self.save(&updated)?;
+        *secrets = updated;
+        Ok(info)
+    }
+
+    pub fn delete(&self, name: &str) -> Result<bool, String> {
+        let mut secrets = self
+            .secrets
+            .write()
+            .map_err(|_| "Secrets store lock poisoned".to_string())?;
+        if !secrets.contains_key(name) {
+            return Ok(false);
+        }
+        let mut updated = secrets.clone();
+        updated.remove(name);
+        self.save(&updated)?;
+        *secrets = updated;
+        Ok(true)
+    }
+
+    fn save(&self, secrets: &HashMap<String, SealedSecret>) -> Result<(), String> {
+        let bytes = serde_json::to_vec_pretty(secrets).map_err(|e| e.to_string())?;
+        write_private(&self.path, &bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store_at(path: &str, master: [u8; 32]) -> SecretStore {
+        SecretStore {
+            master: Key::from(master),
+            source: MasterKeySource::File("test".to_string()),
+            path: path.to_string(),
+            secrets: RwLock::new(HashMap::new()),
+        }
+    }
+
+    fn scratch(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("zos-secrets-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_sealed_secrets_round_trip_only_under_their_key_and_name() {
+        let dir = scratch("round-trip");
+        let path = format!("{}/secrets.json", dir);
+        let store = store_at(&path, [1; 32]);
+
+        assert_eq!(store.put("BOT_TOKEN", "123:abc").unwrap().version, 1);
+        assert_eq!(store.open("BOT_TOKEN").unwrap().as_deref(), Some("123:abc"));
+        assert_eq!(store.put("BOT_TOKEN", "456:def").unwrap().version, 2);
+        assert_eq!(store.open("BOT_TOKEN").unwrap().as_deref(), Some("456:def"));
+        assert_eq!(store.open("MISSING").unwrap(), None);
+
+        // The file holds no plaintext, and reopens under the same master key only
+        let on_disk = std::fs::read_to_string(&path).unwrap();
+        assert!(!on_disk.contains("456:def"));
+        let sealed: HashMap<String, SealedSecret> = serde_json::from_str(&on_disk).unwrap();
+        let reopened = store_at(&path, [1; 32]);
+        *reopened.secrets.write().unwrap() = sealed.clone();
+        assert_eq!(
+            reopened.open("BOT_TOKEN").unwrap().as_deref(),
+            Some("456:def")
+        );
+        let wrong_master = store_at(&path, [2; 32]);
+        *wrong_master.secrets.write().unwrap() = sealed.clone();
+        assert!(wrong_master.open("BOT_TOKEN").is_err());
+
+        // A sealed value copied under another name does not open
+        let mut renamed = sealed["BOT_TOKEN"].clone();
+        renamed.name = "OTHER_TOKEN".to_string();
+        reopened
+            .secrets
+            .write()
+            .unwrap()
+            .insert("OTHER_TOKEN".to_string(), renamed);
+        assert!(reopened.open("OTHER_TOKEN").is_err());
+
+        assert!(store.put("bad-name", "x").is_err());
+        assert!(store.put("EMPTY", "").is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_failed_save_leaves_memory_as_it_was() {
+        let dir = scratch("failed-save");
+        let path = format!("{}/secrets.json", dir);
+        let store = store_at(&path, [1; 32]);
+        store.put("KEPT", "value").unwrap();
+
+        // A file where the store's directory should be makes every write fail
+        std::fs::remove_dir_all(&dir).unwrap();
+        std::fs::write(&dir, b"not a directory").unwrap();
+        assert!(store.put("ADDED", "value").is_err());
+        assert!(!store.contains("ADDED"));
+        assert!(store.put("KEPT", "changed").is_err());
+        assert_eq!(store.open("KEPT").unwrap().as_deref(), Some("value"));
+        assert_eq!(store.info("KEPT").unwrap().version, 1);
+        assert!(store.delete("KEPT").is_err());
+        assert!(store.contains("KEPT"));
+        let _ = std::fs::remove_file(dir);
+    }
+}
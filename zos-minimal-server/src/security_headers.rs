@@ -0,0 +1,204 @@
+// Security headers for the dashboard and other HTML pages: CSP, HSTS,
+// frame and referrer policy, from a dev or prod profile
+// AGPL-3.0 License
+
+use crate::AppState;
+use axum::body::Body;
+use axum::extract::State;
+use axum::http::{header, HeaderValue, Request};
+use axum::middleware::Next;
+use axum::response::Response;
+use serde::{Deserialize, Serialize};
+
+/// HSTS shorter than this is not worth preloading; prod must meet it
+pub const MIN_PROD_HSTS_SECS: u64 = 180 * 86_400;
+
+const FRAME_OPTIONS: &[&str] = &["DENY", "SAMEORIGIN"];
+const REFERRER_POLICIES: &[&str] = &[
+    "no-referrer",
+    "same-origin",
+    "strict-origin",
+    "strict-origin-when-cross-origin",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Profile {
+    Dev,
+    Prod,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecurityHeaders {
+    pub profile: Profile,
+    pub content_security_policy: String,
+    pub hsts_max_age_secs: Option<u64>, // None: no Strict-Transport-Security
+    pub frame_options: String,
+    pub referrer_policy: String,
+}
+
+impl SecurityHeaders {
+    /// The dashboard's scripts and styles are inline, so both profiles allow
+    /// 'unsafe-inline'; dev also lets pages talk to any local port
+    pub fn profile(profile: Profile) -> Self {
+        match profile {
+            Profile::Dev => Self {
+                profile,
+                content_security_policy: "default-src 'self'; script-src 'self' 'unsafe-inline'; \
+                    style-src 'self' 'unsafe-inline'; img-src 'self' data:; \
+                    connect-src 'self' http://localhost:* ws://localhost:*; frame-ancestors 'self'"
+                    .to_string(),
+                hsts_max_age_secs: None,
+                frame_options: "SAMEORIGIN".to_string(),
+                referrer_policy: "strict-origin-when-cross-origin".to_string(),
+            },
+            Profile::Prod => Self {
+                profile,
+                content_security_policy: "default-src 'self'; script-src 'self' 'unsafe-inline'; \
+                    style-src 'self' 'unsafe-inline'; img-src 'self' data:; connect-src 'self'; \
+                    frame-ancestors 'none'; base-uri 'self'; form-action 'self'; object-src 'none'"
+                    .to_string(),
+                hsts_max_age_secs: Some(365 * 86_400),
+                frame_options: "DENY".to_string(),
+                referrer_policy: "strict-origin-when-cross-origin".to_string(),
+            },
+        }
+    }
+
+    /// ZOS_SECURITY_PROFILE=dev|prod (prod unless the domain is local), with
+    /// ZOS_CSP, ZOS_HSTS_MAX_AGE (0 turns it off), ZOS_FRAME_OPTIONS and
+    /// ZOS_REFERRER_POLICY overriding single headers
+    pub fn load(domain: &str) -> Self {
+        let profile = match std::env::var("ZOS_SECURITY_PROFILE").as_deref() {
+            Ok("dev") => Profile::Dev,
+            Ok("prod") => Profile::Prod,
+            _ if domain == "localhost" || domain == "127.0.0.1" => Profile::Dev,
+            _ => Profile::Prod,
+        };
+        let mut headers = Self::profile(profile);
+
+        if let Ok(csp) = std::env::var("ZOS_CSP") {
+            headers.content_security_policy = csp;
+        }
+        if let Some(secs) = std::env::var("ZOS_HSTS_MAX_AGE")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+        {
+            headers.hsts_max_age_secs = (secs > 0).then_some(secs);
+        }
+        if let Ok(frame_options) = std::env::var("ZOS_FRAME_OPTIONS") {
+            headers.frame_options = frame_options;
+        }
+        if let Ok(referrer_policy) = std::env::var("ZOS_REFERRER_POLICY") {
+            headers.referrer_policy = referrer_policy;
+        }
+        headers
+    }
+
+    fn csp_directive(&self, name: &str) -> Option<&str> {
+        self.content_security_policy
+            .split(';')
+            .map(str::trim)
+            .find(|directive| directive.split_whitespace().next() == Some(name))
+    }
+
+    /// Problems with the configured headers: errors make the doctor fail,
+    /// warnings are fine in dev
+    pub fn audit(&self) -> (Vec<String>, Vec<String>) {
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+
+        if HeaderValue::from_str(&self.content_security_policy).is_err() {
+            errors.push("Content-Security-Policy is not a valid header value".to_string());
+        }
+        if self.csp_directive("default-src").is_none() {
+            errors.push("Content-Security-Policy has no default-src".to_string());
+        }
+        if self.content_security_policy.contains("'unsafe-eval'") {
+            errors.push("Content-Security-Policy allows 'unsafe-eval'".to_string());
+        }
+        for directive in ["default-src", "script-src"] {
+            let wildcard = self
+                .csp_directive(directive)
+                .is_some_and(|d| d.split_whitespace().skip(1).any(|source| source == "*"));
+            if wildcard {
+                errors.push(format!("{} allows any origin", directive));
+            }
+        }
+        if !FRAME_OPTIONS.contains(&self.frame_options.as_str()) {
+            errors.push(format!(
+                "X-Frame-Options {} is not one of {:?}",
+                self.frame_options, FRAME_OPTIONS
+            ));
+        }
+        if !REFERRER_POLICIES.contains(&self.referrer_policy.as_str()) {
+            errors.push(format!(
+                "Referrer-Policy {} leaks more than {:?}",
+                self.referrer_policy, REFERRER_POLICIES
+            ));
+        }
+
+        let prod_issues = match self.profile {
+            Profile::Prod => &mut errors,
+            Profile::Dev => &mut warnings,
+        };
+        match self.hsts_max_age_secs {
+            None => prod_issues.push("Strict-Transport-Security is off".to_string()),
+            Some(secs) if secs < MIN_PROD_HSTS_SECS => prod_issues.push(format!(
+                "HSTS max-age {}s is under {}s",
+                secs, MIN_PROD_HSTS_SECS
+            )),
+            Some(_) => {}
+        }
+        if self.csp_directive("frame-ancestors").is_none() {
+            prod_issues.push("Content-Security-Policy has no frame-ancestors".to_string());
+        }
+        if self.content_security_policy.contains("localhost") {
+            prod_issues.push("Content-Security-Policy allows localhost".to_string());
+        }
+
+        (errors, warnings)
+    }
+}
+
+/// Add the configured headers to HTML responses; JSON and downloads are
+/// left alone, as are headers a handler already set
+pub async fn apply(State(state): State<AppState>, request: Request<Body>, next: Next) -> Response {
+    let mut response = next.run(request).await;
+    let is_html = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/html"));
+    if !is_html {
+        return response;
+    }
+
+    let config = &state.config.security_headers;
+    let mut values = vec![
+        (
+            header::CONTENT_SECURITY_POLICY,
+            config.content_security_policy.clone(),
+        ),
+        (header::X_FRAME_OPTIONS, config.frame_options.clone()),
+        (header::REFERRER_POLICY, config.referrer_policy.clone()),
+        (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+    ];
+    if let Some(secs) = config.hsts_max_age_secs {
+        values.push((
+            header::STRICT_TRANSPORT_SECURITY,
+            format!("max-age={}; includeSubDomains", secs),
+        ));
+    }
+
+    let headers = response.headers_mut();
+    for (name, value) in values {
+        if headers.contains_key(&name) {
+            continue;
+        }
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            headers.insert(name, value);
+        }
+    }
+    response
+}
@@ -0,0 +1,313 @@
+// Health probing for registered services with auto-delisting
+// AGPL-3.0 License
+
+use crate::service_templates::{ProbeKind, RegisteredHealthCheck, RegisteredService};
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum HealthState {
+    Unknown,
+    Healthy,
+    Failing,  // below the threshold, still listed
+    Delisted, // service calls get 503 until a probe succeeds
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceHealth {
+    pub state: HealthState,
+    pub consecutive_failures: u32,
+    pub last_checked: u64,
+    pub last_success: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+impl Default for ServiceHealth {
+    fn default() -> Self {
+        Self {
+            state: HealthState::Unknown,
+            consecutive_failures: 0,
+            last_checked: 0,
+            last_success: None,
+            last_error: None,
+        }
+    }
+}
+
+/// A service's own health combined with that of everything it depends on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum CascadeStatus {
+    Unknown, // not probed yet, dependencies fine
+    Healthy,
+    Degraded { causes: Vec<String> }, // failing itself, or dependencies down anywhere below it
+    Down,                             // delisted itself
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OwnerNotification {
+    pub service_key: String,
+    pub message: String,
+    pub timestamp: u64,
+}
+
+pub async fn probe(check: &RegisteredHealthCheck) -> Result<(), String> {
+    let timeout = Duration::from_secs(check.timeout_secs);
+
+    match check.kind {
+        ProbeKind::Http => {
+            let response = crate::http_clients::shared()
+                .get(&check.url)
+                .timeout(timeout)
+                .send()
+                .await
+                .map_err(|e| e.to_string())?;
+
+            if response.status().is_success() {
+                Ok(())
+            } else {
+                Err(format!("HTTP {}", response.status()))
+            }
+        }
+        ProbeKind::Libp2pPing => {
+            tokio::time::timeout(timeout, tokio::net::TcpStream::connect(&check.url))
+                .await
+                .map_err(|_| "ping timed out".to_string())?
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        }
+    }
+}
+
+/// Apply a probe result. Returns a message for the owner when the service is
+/// delisted or recovers.
+pub fn record_probe(
+    health: &mut ServiceHealth,
+    failure_threshold: u32,
+    result: Result<(), String>,
+    now: u64,
+) -> Option<String> {
+    let was_delisted = health.state == HealthState::Delisted;
+    health.last_checked = now;
+
+    match result {
+        Ok(()) => {
+            health.consecutive_failures = 0;
+            health.last_success = Some(now);
+            health.last_error = None;
+            health.state = HealthState::Healthy;
+
+            was_delisted.then(|| "recovered and is listed again".to_string())
+        }
+        Err(e) => {
+            health.consecutive_failures += 1;
+            health.last_error = Some(e.clone());
+
+            if health.consecutive_failures < failure_threshold {
+                health.state = HealthState::Failing;
+                None
+            } else {
+                health.state = HealthState::Delisted;
+                (!was_delisted).then(|| {
+                    format!(
+                        "delisted after {} failed health checks: {}",
+                        health.consecutive_failures, e
+                    )
+                })
+            }
+        }
+    }
+}
+
+pub async fn is_delisted(state: &AppState, service_key: &str) -> bool {
+    state
+        .service_health
+        .read()
+        .await
+        .get(service_key)
+        .map(|health| health.state == HealthState::Delisted)
+        .unwrap_or(false)
+}
+
+/// Services below `service_key` in the dependency graph, nearest first
+pub fn transitive_dependencies(
+    service_key: &str,
+    dependencies: &HashMap<String, Vec<String>>,
+) -> Vec<String> {
+    let mut seen = HashSet::from([service_key.to_string()]);
+    let mut order = Vec::new();
+    let mut queue = std::collections::VecDeque::from([service_key.to_string()]);
+
+    while let Some(key) = queue.pop_front() {
+        for dependency in dependencies.get(&key).into_iter().flatten() {
+            if seen.insert(dependency.clone()) {
+                order.push(dependency.clone());
+                queue.push_back(dependency.clone());
+            }
+        }
+    }
+    order
+}
+
+/// Services that depend on `service_key`, directly or further up
+pub fn transitive_dependents(
+    service_key: &str,
+    dependencies: &HashMap<String, Vec<String>>,
+) -> Vec<String> {
+    let mut dependents: Vec<String> = dependencies
+        .keys()
+        .filter(|key| *key != service_key)
+        .filter(|key| {
+            transitive_dependencies(key, dependencies)
+                .iter()
+                .any(|d| d == service_key)
+        })
+        .cloned()
+        .collect();
+    dependents.sort();
+    dependents
+}
+
+/// The path back to `service_key` if depending on `depends_on` would close a
+/// cycle
+pub fn dependency_cycle(
+    service_key: &str,
+    depends_on: &[String],
+    dependencies: &HashMap<String, Vec<String>>,
+) -> Option<Vec<String>> {
+    fn path_to(
+        from: &str,
+        target: &str,
+        dependencies: &HashMap<String, Vec<String>>,
+        visited: &mut HashSet<String>,
+    ) -> Option<Vec<String>> {
+        if from == target {
+            return Some(vec![from.to_string()]);
+        }
+        if !visited.insert(from.to_string()) {
+            return None;
+        }
+        dependencies
+            .get(from)
+            .into_iter()
+            .flatten()
+            .find_map(|next| {
+                path_to(next, target, dependencies, visited).map(|mut path| {
+                    path.insert(0, from.to_string());
+                    path
+                })
+            })
+    }
+
+    let mut visited = HashSet::new();
+    depends_on.iter().find_map(|dependency| {
+        path_to(dependency, service_key, dependencies, &mut visited).map(|mut path| {
+            path.insert(0, service_key.to_string());
+            path
+        })
+    })
+}
+
+/// Cascade status of one service. Dependencies that are delisted or no
+/// longer registered count as down and degrade everything above them.
+pub fn cascade_status(
+    service_key: &str,
+    dependencies: &HashMap<String, Vec<String>>,
+    services: &HashMap<String, RegisteredService>,
+    health: &HashMap<String, ServiceHealth>,
+) -> CascadeStatus {
+    let own = health
+        .get(service_key)
+        .map(|h| h.state.clone())
+        .unwrap_or(HealthState::Unknown);
+    if own == HealthState::Delisted {
+        return CascadeStatus::Down;
+    }
+
+    let mut causes: Vec<String> = transitive_dependencies(service_key, dependencies)
+        .into_iter()
+        .filter(|dependency| {
+            !services.contains_key(dependency)
+                || health
+                    .get(dependency)
+                    .is_some_and(|h| h.state == HealthState::Delisted)
+        })
+        .collect();
+    if own == HealthState::Failing {
+        causes.insert(0, service_key.to_string());
+    }
+
+    match (causes.is_empty(), own) {
+        (false, _) => CascadeStatus::Degraded { causes },
+        (true, HealthState::Healthy) => CascadeStatus::Healthy,
+        _ => CascadeStatus::Unknown,
+    }
+}
+
+pub async fn probe_due(state: AppState) -> Result<(), String> {
+    let now = chrono::Utc::now().timestamp() as u64;
+
+    let due: Vec<RegisteredHealthCheck> = {
+        let checks = state.health_checks.read().await;
+        let health = state.service_health.read().await;
+        checks
+            .values()
+            .filter(|check| {
+                let last_checked = health
+                    .get(&check.service_key)
+                    .map(|h| h.last_checked)
+                    .unwrap_or(0);
+                now >= last_checked + check.interval_secs
+            })
+            .cloned()
+            .collect()
+    };
+
+    for check in due {
+        let result = probe(&check).await;
+
+        let message = {
+            let mut health = state.service_health.write().await;
+            let entry = health.entry(check.service_key.clone()).or_default();
+            record_probe(entry, check.failure_threshold, result, now)
+        };
+
+        if let Some(message) = message {
+            println!("🩺 {} {}", check.service_key, message);
+            notify_owner(&state, &check.service_key, message.clone(), now).await;
+
+            // Owners of everything built on top hear about it too
+            let dependents = transitive_dependents(
+                &check.service_key,
+                &*state.service_dependencies.read().await,
+            );
+            for dependent in dependents {
+                let cascade = format!("dependency {} {}", check.service_key, message);
+                notify_owner(&state, &dependent, cascade, now).await;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn notify_owner(state: &AppState, service_key: &str, message: String, now: u64) {
+    let wallet = match state.services.read().await.get(service_key) {
+        Some(service) => service.wallet_address.clone(),
+        None => return,
+    };
+
+    state
+        .owner_notifications
+        .write()
+        .await
+        .entry(wallet)
+        .or_default()
+        .push(OwnerNotification {
+            service_key: service_key.to_string(),
+            message,
+            timestamp: now,
+        });
+}
@@ -0,0 +1,226 @@
+// Per-request structured logs for service owners, with quotas and live tail
+// AGPL-3.0 License
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::{broadcast, RwLock};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceLogEntry {
+    pub id: u64,
+    pub at: u64,
+    pub method: String,
+    pub path: String, // after /{wallet}/{service}, without the query
+    pub status: u16,
+    pub latency_ms: u64,            // until response headers; bodies are streamed
+    pub request_bytes: Option<u64>, // from Content-Length, None when chunked
+    pub response_bytes: Option<u64>,
+    pub consumer: Option<String>, // pseudonymized caller wallet
+    #[serde(default)]
+    pub version: Option<String>, // service version that answered
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct LogQuery {
+    pub status: Option<String>, // "404", or a class like "5xx"
+    pub since: Option<u64>,
+    pub until: Option<u64>,
+    pub min_latency_ms: Option<u64>,
+    pub consumer: Option<String>, // pseudonym, or a wallet to pseudonymize
+    pub limit: Option<usize>,
+    pub follow: Option<bool>, // live tail over SSE
+}
+
+impl LogQuery {
+    pub fn matches(&self, entry: &ServiceLogEntry) -> bool {
+        let status_ok = match self.status.as_deref() {
+            Some(class) if class.len() == 3 && class.ends_with("xx") => {
+                class[..1] == entry.status.to_string()[..1]
+            }
+            Some(code) => code
+                .parse::<u16>()
+                .map(|code| code == entry.status)
+                .unwrap_or(false),
+            None => true,
+        };
+        let consumer_ok = match &self.consumer {
+            Some(consumer) => {
+                let wanted = if consumer.starts_with("anon_") {
+                    consumer.clone()
+                } else {
+                    crate::data_export::pseudonymize_wallet(consumer)
+                };
+                entry.consumer.as_deref() == Some(wanted.as_str())
+            }
+            None => true,
+        };
+
+        status_ok
+            && consumer_ok
+            && self.since.map(|since| entry.at >= since).unwrap_or(true)
+            && self.until.map(|until| entry.at <= until).unwrap_or(true)
+            && self
+                .min_latency_ms
+                .map(|min| entry.latency_ms >= min)
+                .unwrap_or(true)
+    }
+}
+
+struct ServiceLog {
+    entries: VecDeque<(ServiceLogEntry, usize)>, // entry and its serialized size
+    bytes_used: usize,
+    next_id: u64,
+    live: broadcast::Sender<ServiceLogEntry>,
+}
+
+impl ServiceLog {
+    fn new() -> Self {
+        Self {
+            entries: VecDeque::new(),
+            bytes_used: 0,
+            next_id: 1,
+            live: broadcast::channel(256).0,
+        }
+    }
+}
+
+/// Logs for every service on this node. Each service keeps its newest
+/// entries within `quota_bytes` and `retention_secs`, whichever bites first.
+#[derive(Clone)]
+pub struct ServiceLogStore {
+    logs: Arc<RwLock<HashMap<String, ServiceLog>>>,
+    pub quota_bytes: usize,
+    pub retention_secs: u64,
+}
+
+impl Default for ServiceLogStore {
+    fn default() -> Self {
+        Self {
+            logs: Arc::new(RwLock::new(HashMap::new())),
+            quota_bytes: std::env::var("ZOS_SERVICE_LOG_QUOTA_KB")
+                .ok()
+                .and_then(|kb| kb.parse::<usize>().ok())
+                .unwrap_or(1024)
+                * 1024,
+            retention_secs: 7 * 86400,
+        }
+    }
+}
+
+impl ServiceLogStore {
+    /// Store an entry (its id is assigned here) and push it to live tails
+    pub async fn record(&self, service_key: &str, mut entry: ServiceLogEntry) {
+        let mut logs = self.logs.write().await;
+        let log = logs
+            .entry(service_key.to_string())
+            .or_insert_with(ServiceLog::new);
+
+        entry.id = log.next_id;
+        log.next_id += 1;
+
+        let size = serde_json::to_vec(&entry)
+            .map(|json| json.len())
+            .unwrap_or(0);
+        log.entries.push_back((entry.clone(), size));
+        log.bytes_used += size;
+
+        let oldest_kept = entry.at.saturating_sub(self.retention_secs);
+        while let Some((oldest, oldest_size)) = log.entries.front() {
+            if log.bytes_used <= self.quota_bytes && oldest.at >= oldest_kept {
+                break;
+            }
+            log.bytes_used -= oldest_size;
+            log.entries.pop_front();
+        }
+
+        // No tail connected is the normal case
+        let _ = log.live.send(entry);
+    }
+
+    /// Newest matching entries first, up to the query's limit (max 1000)
+    pub async fn query(&self, service_key: &str, query: &LogQuery) -> Vec<ServiceLogEntry> {
+        let limit = query.limit.unwrap_or(100).min(1000);
+        self.logs
+            .read()
+            .await
+            .get(service_key)
+            .map(|log| {
+                log.entries
+                    .iter()
+                    .rev()
+                    .map(|(entry, _)| entry)
+                    .filter(|entry| query.matches(entry))
+                    .take(limit)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Entries recorded at or after `since`, oldest first
+    pub async fn since(&self, service_key: &str, since: u64) -> Vec<ServiceLogEntry> {
+        self.logs
+            .read()
+            .await
+            .get(service_key)
+            .map(|log| {
+                log.entries
+                    .iter()
+                    .map(|(entry, _)| entry)
+                    .filter(|entry| entry.at >= since)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Entries after `after_id` (for SSE Last-Event-ID resumption) and a
+    /// receiver for everything recorded from now on
+    pub async fn subscribe(
+        &self,
+        service_key: &str,
+        after_id: u64,
+    ) -> (Vec<ServiceLogEntry>, broadcast::Receiver<ServiceLogEntry>) {
+        let mut logs = self.logs.write().await;
+        let log = logs
+            .entry(service_key.to_string())
+            .or_insert_with(ServiceLog::new);
+
+        let backlog = log
+            .entries
+            .iter()
+            .map(|(entry, _)| entry)
+            .filter(|entry| entry.id > after_id)
+            .cloned()
+            .collect();
+        (backlog, log.live.subscribe())
+    }
+
+    /// Share of 5xx responses per service over the last `window_secs`, for
+    /// services that saw any requests in that window
+    pub async fn error_rates(&self, window_secs: u64, now: u64) -> Vec<(String, f64)> {
+        let since = now.saturating_sub(window_secs);
+        self.logs
+            .read()
+            .await
+            .iter()
+            .filter_map(|(service_key, log)| {
+                let recent = log.entries.iter().filter(|(entry, _)| entry.at >= since);
+                let (total, errors) = recent.fold((0u32, 0u32), |(total, errors), (entry, _)| {
+                    (total + 1, errors + u32::from(entry.status >= 500))
+                });
+                (total > 0).then(|| (service_key.clone(), errors as f64 / total as f64))
+            })
+            .collect()
+    }
+
+    pub async fn usage(&self, service_key: &str) -> (usize, usize) {
+        self.logs
+            .read()
+            .await
+            .get(service_key)
+            .map(|log| (log.entries.len(), log.bytes_used))
+            .unwrap_or((0, 0))
+    }
+}
@@ -0,0 +1,196 @@
+// Config-driven service templates for one-click service creation
+// AGPL-3.0 License
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceTemplate {
+    pub name: String,
+    pub description: Option<String>,
+    pub pricing_tier: String, // Free, Basic, Premium, Enterprise
+    pub resources: ResourceNeeds,
+    pub health_check: HealthCheckSpec,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    pub command: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceNeeds {
+    pub cpu_percent: f32,
+    pub memory_mb: u64,
+    pub disk_mb: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthCheckSpec {
+    #[serde(default)]
+    pub kind: ProbeKind,
+    #[serde(default = "default_health_path")]
+    pub path: String, // only used by http probes
+    pub interval_secs: u64,
+    pub timeout_secs: u64,
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeKind {
+    #[default]
+    Http,
+    Libp2pPing,
+}
+
+fn default_health_path() -> String {
+    "/health".to_string()
+}
+
+fn default_failure_threshold() -> u32 {
+    3
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegisteredService {
+    pub wallet_address: String,
+    pub service_name: String,
+    pub template: String,
+    pub port: u16,
+    pub pricing_tier: String,
+    pub work_dir: String,
+    pub created_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegisteredHealthCheck {
+    pub service_key: String,
+    pub kind: ProbeKind,
+    pub url: String, // http URL, or host:port for libp2p pings
+    pub interval_secs: u64,
+    pub timeout_secs: u64,
+    pub failure_threshold: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FromTemplateRequest {
+    pub wallet: String,
+    pub template: String,
+    pub service_name: Option<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+}
+
+/// A step that completed and must be undone if a later step fails
+#[derive(Debug)]
+pub enum CompletedStep {
+    PortAllocated(u16),
+    ServiceRegistered(String),
+    WorkloadDeployed(String),
+    HealthCheckRegistered(String),
+}
+
+pub fn template_dir() -> String {
+    std::env::var("ZOS_TEMPLATE_DIR").unwrap_or_else(|_| "templates/services".to_string())
+}
+
+pub fn load_templates() -> HashMap<String, ServiceTemplate> {
+    let mut templates = HashMap::new();
+
+    let entries = match std::fs::read_dir(template_dir()) {
+        Ok(entries) => entries,
+        Err(_) => return templates,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let parsed = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => std::fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|content| parse_template(&content)),
+            _ => continue,
+        };
+
+        match parsed {
+            Ok(template) => {
+                templates.insert(template.name.clone(), template);
+            }
+            Err(e) => println!("⚠️  Skipping template {}: {}", path.display(), e),
+        }
+    }
+
+    templates
+}
+
+pub fn parse_template(content: &str) -> Result<ServiceTemplate, String> {
+    let template: ServiceTemplate =
+        toml::from_str(content).map_err(|e| format!("Invalid template: {}", e))?;
+
+    if !matches!(
+        template.pricing_tier.as_str(),
+        "Free" | "Basic" | "Premium" | "Enterprise"
+    ) {
+        return Err(format!("Unknown pricing tier: {}", template.pricing_tier));
+    }
+
+    if !template.health_check.path.starts_with('/') {
+        return Err("health_check.path must start with '/'".to_string());
+    }
+
+    Ok(template)
+}
+
+/// Lay down the workload directory and env file the service unit runs from
+pub async fn deploy_workload(
+    service: &RegisteredService,
+    template: &ServiceTemplate,
+    env_overrides: &HashMap<String, String>,
+) -> Result<(), String> {
+    tokio::fs::create_dir_all(&service.work_dir)
+        .await
+        .map_err(|e| format!("Failed to create {}: {}", service.work_dir, e))?;
+
+    let mut env = template.env.clone();
+    env.extend(env_overrides.clone());
+    env.insert("ZOS_SERVICE_PORT".to_string(), service.port.to_string());
+    env.insert(
+        "ZOS_SERVICE_WALLET".to_string(),
+        service.wallet_address.clone(),
+    );
+
+    let mut env_lines: Vec<String> = env.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+    env_lines.sort();
+
+    tokio::fs::write(
+        format!("{}/service.env", service.work_dir),
+        env_lines.join("\n") + "\n",
+    )
+    .await
+    .map_err(|e| format!("Failed to write env file: {}", e))?;
+
+    if let Some(command) = &template.command {
+        tokio::fs::write(
+            format!("{}/run.sh", service.work_dir),
+            format!(
+                "#!/bin/bash\nset -a\nsource {}/service.env\nexec {}\n",
+                service.work_dir, command
+            ),
+        )
+        .await
+        .map_err(|e| format!("Failed to write run script: {}", e))?;
+    }
+
+    println!(
+        "🚀 Workload deployed: {} on port {} ({} MB RAM, {}% CPU)",
+        service.service_name,
+        service.port,
+        template.resources.memory_mb,
+        template.resources.cpu_percent
+    );
+
+    Ok(())
+}
+
+pub async fn remove_workload(work_dir: &str) {
+    let _ = tokio::fs::remove_dir_all(work_dir).await;
+}
@@ -0,0 +1,214 @@
+// Service versions with changelogs, and consumers pinning the version they
+// call when versions are separate backend deployments
+// AGPL-3.0 License
+
+use crate::service_health::OwnerNotification;
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Header a call uses to ask for a version; replies carry the version served
+pub const VERSION_HEADER: &str = "x-service-version";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceVersion {
+    pub version: String, // major.minor.patch
+    pub changelog: String,
+    pub port: Option<u16>, // its own deployment; None shares the service's port
+    pub released_at: u64,
+    pub deprecated_at: Option<u64>,
+    pub deprecation_note: Option<String>,
+    pub sunset_at: Option<u64>, // calls for it are refused from then on
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ServiceVersions {
+    pub current: Option<String>, // served to calls that ask for nothing
+    pub versions: Vec<ServiceVersion>,
+    #[serde(default)]
+    pub canary: Option<crate::canary::Canary>, // takes a share of unpinned calls
+}
+
+impl ServiceVersions {
+    pub fn get(&self, version: &str) -> Option<&ServiceVersion> {
+        self.versions.iter().find(|v| v.version == version)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersionPin {
+    pub consumer: String,
+    pub service_key: String,
+    pub version: String,
+    pub pinned_at: u64,
+}
+
+impl VersionPin {
+    pub fn key(consumer: &str, service_key: &str) -> String {
+        format!("{}:{}", consumer, service_key)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PublishRequest {
+    pub version: String,
+    pub changelog: String,
+    pub port: Option<u16>,
+    #[serde(default = "default_make_current")]
+    pub make_current: bool,
+}
+
+fn default_make_current() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeprecateRequest {
+    pub note: Option<String>,
+    pub sunset_at: Option<u64>,
+}
+
+pub fn publish(
+    versions: &mut ServiceVersions,
+    request: PublishRequest,
+    now: u64,
+) -> Result<ServiceVersion, String> {
+    if crate::dependency_drift::parse_version(&request.version).is_none() {
+        return Err("version must be major.minor.patch".to_string());
+    }
+    if versions.get(&request.version).is_some() {
+        return Err(format!(
+            "Version {} already published; publish a new one",
+            request.version
+        ));
+    }
+    if request.changelog.trim().is_empty() {
+        return Err("A version needs a changelog".to_string());
+    }
+
+    let version = ServiceVersion {
+        version: request.version,
+        changelog: request.changelog,
+        port: request.port,
+        released_at: now,
+        deprecated_at: None,
+        deprecation_note: None,
+        sunset_at: None,
+    };
+    if request.make_current || versions.current.is_none() {
+        versions.current = Some(version.version.clone());
+    }
+    versions.versions.push(version.clone());
+    Ok(version)
+}
+
+/// Mark a version deprecated and tell everyone pinned to it, through their
+/// notification feed
+pub fn deprecate(
+    versions: &mut ServiceVersions,
+    pins: &HashMap<String, VersionPin>,
+    notifications: &mut HashMap<String, Vec<OwnerNotification>>,
+    service_key: &str,
+    version: &str,
+    request: DeprecateRequest,
+    now: u64,
+) -> Result<usize, String> {
+    if versions.current.as_deref() == Some(version) {
+        return Err("Make another version current before deprecating this one".to_string());
+    }
+    if versions
+        .canary
+        .as_ref()
+        .is_some_and(|canary| canary.version == version)
+    {
+        return Err("Roll back the canary before deprecating it".to_string());
+    }
+    if request.sunset_at.is_some_and(|sunset| sunset <= now) {
+        return Err("sunset_at must be in the future".to_string());
+    }
+    let entry = versions
+        .versions
+        .iter_mut()
+        .find(|v| v.version == version)
+        .ok_or("Version not found")?;
+    entry.deprecated_at = Some(now);
+    entry.deprecation_note = request.note;
+    entry.sunset_at = request.sunset_at;
+
+    let message = format!(
+        "Version {} you pinned is deprecated{}{}",
+        version,
+        entry
+            .sunset_at
+            .map(|sunset| format!(" and stops answering at {}", sunset))
+            .unwrap_or_default(),
+        entry
+            .deprecation_note
+            .as_ref()
+            .map(|note| format!(": {}", note))
+            .unwrap_or_default()
+    );
+    let pinned: Vec<&VersionPin> = pins
+        .values()
+        .filter(|pin| pin.service_key == service_key && pin.version == version)
+        .collect();
+    for pin in &pinned {
+        notifications
+            .entry(pin.consumer.clone())
+            .or_default()
+            .push(OwnerNotification {
+                service_key: service_key.to_string(),
+                message: message.clone(),
+                timestamp: now,
+            });
+    }
+    Ok(pinned.len())
+}
+
+/// The version a call gets: the one it asks for, else the caller's pin,
+/// else the service's current one. None when the service has no versions.
+pub fn resolve<'a>(
+    versions: Option<&'a ServiceVersions>,
+    requested: Option<&str>,
+    pinned: Option<&str>,
+    now: u64,
+) -> Result<Option<&'a ServiceVersion>, (StatusCode, String)> {
+    let Some(versions) = versions else {
+        return match requested {
+            Some(_) => Err((
+                StatusCode::NOT_FOUND,
+                "Service publishes no versions".to_string(),
+            )),
+            None => Ok(None),
+        };
+    };
+    let Some(wanted) = requested.or(pinned).or(versions.current.as_deref()) else {
+        return Ok(None);
+    };
+    let version = versions
+        .get(wanted)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("Unknown version {}", wanted)))?;
+    if version.sunset_at.is_some_and(|sunset| sunset <= now) {
+        return Err((
+            StatusCode::GONE,
+            format!("Version {} was retired; see the changelog", wanted),
+        ));
+    }
+    Ok(Some(version))
+}
+
+/// Tell the caller which version answered, and when it is going away
+pub fn annotate(headers: &mut HeaderMap, version: &ServiceVersion) {
+    let mut values = vec![(VERSION_HEADER, version.version.clone())];
+    if let Some(deprecated_at) = version.deprecated_at {
+        values.push(("deprecation", format!("@{}", deprecated_at)));
+    }
+    if let Some(sunset) = version.sunset_at {
+        values.push(("sunset", crate::api_versions::http_date(sunset as i64)));
+    }
+    for (name, value) in values {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            headers.insert(name, value);
+        }
+    }
+}
@@ -0,0 +1,184 @@
+// Resumable sessions for clients that drop off and reconnect: a token maps
+// to a server-side snapshot of where the client was, restored in one call
+// AGPL-3.0 License
+
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+/// A token unused this long stops resuming anything
+pub const IDLE_TTL_SECS: u64 = 7 * 86_400;
+
+/// Largest snapshot a client may store
+pub const MAX_SNAPSHOT_BYTES: usize = 16 * 1024;
+
+/// Tokens one wallet may hold at once, one per device; the oldest goes first
+pub const MAX_TOKENS_PER_WALLET: usize = 10;
+
+/// The game session the client had open
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameSessionRef {
+    pub session_id: String,
+    pub game: String,
+    pub node: Option<String>, // base URL of the node hosting it, when known
+}
+
+/// A payment the client had started but not finished: a quote, estimate or
+/// subscription it was about to confirm
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingPayment {
+    pub flow_id: String,
+    pub kind: String, // "swap", "estimate", "subscription", ...
+    pub service_key: Option<String>,
+    pub amount: Option<f64>,
+    pub step: String,            // where in the flow the client was
+    pub expires_at: Option<u64>, // a quote or estimate past this must be redone
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionSnapshot {
+    #[serde(default)]
+    pub dashboard: serde_json::Value, // filters, open panels: the client's own shape
+    #[serde(default)]
+    pub game_session: Option<GameSessionRef>,
+    #[serde(default)]
+    pub pending_payments: Vec<PendingPayment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResumableSession {
+    pub wallet_address: String,
+    pub device: Option<String>,
+    pub snapshot: SessionSnapshot,
+    pub version: u64, // bumped on every save, so a stale writer can be refused
+    pub created_at: u64,
+    pub last_seen: u64,
+}
+
+impl ResumableSession {
+    pub fn is_expired(&self, now: u64) -> bool {
+        now >= self.last_seen + IDLE_TTL_SECS
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IssueRequest {
+    pub wallet: String,
+    #[serde(default)]
+    pub device: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SaveRequest {
+    pub snapshot: SessionSnapshot,
+    #[serde(default)]
+    pub base_version: Option<u64>, // the version this snapshot was built on
+}
+
+/// Sessions are stored by token hash; the token itself is only ever held
+/// by the client
+pub fn token_hash(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Issue a token for a wallet, dropping expired sessions and the wallet's
+/// oldest beyond its limit. Returns the token.
+pub fn issue(
+    sessions: &mut HashMap<String, ResumableSession>,
+    request: IssueRequest,
+    now: u64,
+) -> Result<String, String> {
+    if request.wallet.trim().is_empty() {
+        return Err("A wallet is required".to_string());
+    }
+    sessions.retain(|_, session| !session.is_expired(now));
+
+    let mut held: Vec<(String, u64)> = sessions
+        .iter()
+        .filter(|(_, session)| session.wallet_address == request.wallet)
+        .map(|(hash, session)| (hash.clone(), session.last_seen))
+        .collect();
+    held.sort_by_key(|(_, last_seen)| *last_seen);
+    while held.len() >= MAX_TOKENS_PER_WALLET {
+        let (hash, _) = held.remove(0);
+        sessions.remove(&hash);
+    }
+
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let token = format!(
+        "zrs_{}",
+        bytes
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>()
+    );
+    sessions.insert(
+        token_hash(&token),
+        ResumableSession {
+            wallet_address: request.wallet,
+            device: request.device,
+            snapshot: SessionSnapshot::default(),
+            version: 0,
+            created_at: now,
+            last_seen: now,
+        },
+    );
+    Ok(token)
+}
+
+/// The live session behind a token, touched so it stays resumable
+pub fn lookup<'a>(
+    sessions: &'a mut HashMap<String, ResumableSession>,
+    token: &str,
+    now: u64,
+) -> Result<&'a mut ResumableSession, String> {
+    let session = sessions
+        .get_mut(&token_hash(token))
+        .filter(|session| !session.is_expired(now))
+        .ok_or("Unknown or expired resume token")?;
+    session.last_seen = now;
+    Ok(session)
+}
+
+/// Replace the snapshot. A write based on an older version than the one
+/// stored came from a device that missed an update, and is refused.
+pub fn save(session: &mut ResumableSession, request: SaveRequest) -> Result<u64, String> {
+    if let Some(base) = request.base_version {
+        if base != session.version {
+            return Err(format!(
+                "Snapshot is at version {}, not {}; resume first",
+                session.version, base
+            ));
+        }
+    }
+    let size = serde_json::to_vec(&request.snapshot)
+        .map(|bytes| bytes.len())
+        .unwrap_or(usize::MAX);
+    if size > MAX_SNAPSHOT_BYTES {
+        return Err(format!(
+            "Snapshots are at most {} bytes",
+            MAX_SNAPSHOT_BYTES
+        ));
+    }
+    session.snapshot = request.snapshot;
+    session.version += 1;
+    Ok(session.version)
+}
+
+/// Split pending payments into those still good and those whose quote or
+/// estimate lapsed while the client was away
+pub fn partition_payments(
+    snapshot: &SessionSnapshot,
+    now: u64,
+) -> (Vec<PendingPayment>, Vec<PendingPayment>) {
+    snapshot
+        .pending_payments
+        .iter()
+        .cloned()
+        .partition(|payment| payment.expires_at.is_none_or(|expires_at| expires_at > now))
+}
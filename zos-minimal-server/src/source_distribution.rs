@@ -0,0 +1,253 @@
+// Release tarballs as content-addressed blobs, replicated between peer
+// nodes so the installer can fetch source from any healthy one
+// AGPL-3.0 License
+
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// A published source tarball, addressed by the sha256 of its bytes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Release {
+    pub hash: String,
+    pub size: u64,
+    pub git_commit: String,
+    pub published_at: u64,
+    pub origin: String, // "local", or the peer it was replicated from
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerStatus {
+    pub url: String,
+    pub healthy: bool,
+    pub latest: Option<String>, // hash of the peer's newest release
+    pub checked_at: u64,
+    pub error: Option<String>,
+}
+
+/// Peer base URLs from ZOS_SOURCE_PEERS ("http://a:8080,http://b:8080")
+pub fn load_peers() -> Vec<String> {
+    std::env::var("ZOS_SOURCE_PEERS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|peer| peer.trim().trim_end_matches('/').to_string())
+        .filter(|peer| peer.starts_with("http://") || peer.starts_with("https://"))
+        .collect()
+}
+
+pub fn blob_dir() -> String {
+    let data_dir = std::env::var("ZOS_DATA_DIR").unwrap_or_else(|_| "/tmp".to_string());
+    format!("{}/zos-blobs", data_dir)
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+pub fn is_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+/// Store bytes under their hash. Written to a temp file first so a blob
+/// is either whole or absent.
+pub async fn store_blob(bytes: &[u8]) -> Result<String, String> {
+    let hash = sha256_hex(bytes);
+    let dir = blob_dir();
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| format!("Cannot create {}: {}", dir, e))?;
+
+    let path = format!("{}/{}", dir, hash);
+    let partial = format!("{}.partial", path);
+    tokio::fs::write(&partial, bytes)
+        .await
+        .map_err(|e| format!("Cannot write blob {}: {}", hash, e))?;
+    tokio::fs::rename(&partial, &path)
+        .await
+        .map_err(|e| format!("Cannot store blob {}: {}", hash, e))?;
+    Ok(hash)
+}
+
+/// A blob's bytes, if held and still matching their hash
+pub async fn read_blob(hash: &str) -> Option<Vec<u8>> {
+    if !is_hash(hash) {
+        return None;
+    }
+    let bytes = tokio::fs::read(format!("{}/{}", blob_dir(), hash))
+        .await
+        .ok()?;
+    if sha256_hex(&bytes) != hash {
+        println!("⚠️  Blob {} is corrupt; not serving it", hash);
+        return None;
+    }
+    Some(bytes)
+}
+
+/// Store a tarball and record it as the newest release
+pub async fn publish(
+    state: &AppState,
+    bytes: &[u8],
+    git_commit: &str,
+    origin: &str,
+) -> Result<Release, String> {
+    let hash = store_blob(bytes).await?;
+    let release = Release {
+        hash: hash.clone(),
+        size: bytes.len() as u64,
+        git_commit: git_commit.to_string(),
+        published_at: chrono::Utc::now().timestamp() as u64,
+        origin: origin.to_string(),
+    };
+    state
+        .releases
+        .write()
+        .await
+        .entry(hash)
+        .or_insert_with(|| release.clone());
+    Ok(release)
+}
+
+pub async fn latest_release(state: &AppState) -> Option<Release> {
+    state
+        .releases
+        .read()
+        .await
+        .values()
+        .max_by_key(|release| release.published_at)
+        .cloned()
+}
+
+/// Healthy peers advertising this release, for the installer to try
+pub async fn mirrors_for(state: &AppState, hash: &str) -> Vec<String> {
+    let mut mirrors: Vec<String> = state
+        .source_peers
+        .read()
+        .await
+        .values()
+        .filter(|peer| peer.healthy && peer.latest.as_deref() == Some(hash))
+        .map(|peer| peer.url.clone())
+        .collect();
+    mirrors.sort();
+    mirrors
+}
+
+async fn check_peer(peer: &str, state: &AppState) -> Result<Option<String>, String> {
+    let clients = crate::http_clients::shared();
+    let response = clients
+        .send_with_retry(clients.get(&format!("{}/releases/latest", peer)))
+        .await
+        .map_err(|e| e.to_string())?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !response.status().is_success() {
+        return Err(format!("/releases/latest returned {}", response.status()));
+    }
+    let release: Release = response.json().await.map_err(|e| e.to_string())?;
+    if !is_hash(&release.hash) {
+        return Err(format!("Advertised an invalid hash {}", release.hash));
+    }
+    if state.releases.read().await.contains_key(&release.hash) {
+        return Ok(Some(release.hash));
+    }
+
+    // Replicate it, trusting the bytes only if they match the hash
+    let response = clients
+        .send_with_retry(clients.get(&format!("{}/blobs/{}", peer, release.hash)))
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "Blob {} returned {}",
+            release.hash,
+            response.status()
+        ));
+    }
+    let bytes = response.bytes().await.map_err(|e| e.to_string())?;
+    if sha256_hex(&bytes) != release.hash {
+        return Err(format!("Blob {} failed hash verification", release.hash));
+    }
+    publish(state, &bytes, &release.git_commit, peer).await?;
+    println!(
+        "📦 Replicated release {} from {}",
+        &release.hash[..12],
+        peer
+    );
+    Ok(Some(release.hash))
+}
+
+/// Check every configured peer and replicate releases we do not have.
+/// Fails only when there are peers and none answered.
+pub async fn sync_peers(state: AppState) -> Result<(), String> {
+    let peers = state.config.source_peers.clone();
+    let mut failures = Vec::new();
+    for peer in &peers {
+        let result = check_peer(peer, &state).await;
+        if let Err(e) = &result {
+            failures.push(format!("{}: {}", peer, e));
+        }
+        let status = PeerStatus {
+            url: peer.clone(),
+            healthy: result.is_ok(),
+            latest: result.as_ref().ok().cloned().flatten(),
+            checked_at: chrono::Utc::now().timestamp() as u64,
+            error: result.err(),
+        };
+        state
+            .source_peers
+            .write()
+            .await
+            .insert(peer.clone(), status);
+    }
+
+    if !peers.is_empty() && failures.len() == peers.len() {
+        return Err(failures.join("; "));
+    }
+    Ok(())
+}
+
+/// Shell that fetches a release from the first mirror whose bytes match
+/// its hash; `install.sh source` runs it
+pub fn installer_source_section(release: Option<&Release>, mirrors: &[String]) -> String {
+    let (hash, commit) = release.map_or(("", ""), |r| (r.hash.as_str(), r.git_commit.as_str()));
+    format!(
+        r#"ZOS_SOURCE_HASH="${{ZOS_SOURCE_HASH:-{hash}}}"
+ZOS_SOURCE_COMMIT="{commit}"
+ZOS_SOURCE_MIRRORS="${{ZOS_SOURCE_MIRRORS:-{mirrors}}}"
+
+fetch_source() {{
+    SERVER_URL="${{ZOS_SERVER_URL:-http://localhost:8080}}"
+    if [ -z "$ZOS_SOURCE_HASH" ]; then
+        echo "❌ No published source release to fetch"
+        exit 1
+    fi
+    for MIRROR in "$SERVER_URL" $ZOS_SOURCE_MIRRORS; do
+        echo "📦 Fetching source $ZOS_SOURCE_HASH from $MIRROR..."
+        if curl -fsSL "$MIRROR/blobs/$ZOS_SOURCE_HASH" -o zos-source.tar.gz; then
+            ACTUAL_HASH=$(sha256sum zos-source.tar.gz | cut -d' ' -f1)
+            if [ "$ACTUAL_HASH" = "$ZOS_SOURCE_HASH" ]; then
+                mkdir -p zos-source
+                tar -xzf zos-source.tar.gz -C zos-source --strip-components=1
+                echo "✅ Source verified and extracted to ./zos-source (commit $ZOS_SOURCE_COMMIT)"
+                return 0
+            fi
+            echo "⚠️  $MIRROR served bytes that do not match; trying the next mirror"
+        fi
+    done
+    echo "❌ No mirror served the release"
+    exit 1
+}}
+
+if [ "$1" = "source" ]; then
+    fetch_source
+    exit 0
+fi
+"#,
+        hash = hash,
+        commit = commit,
+        mirrors = mirrors.join(" "),
+    )
+}
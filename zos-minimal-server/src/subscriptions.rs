@@ -0,0 +1,378 @@
+// Rate plan subscriptions: monthly plans with included requests, overage
+// and proration, billed against wallet credits
+// AGPL-3.0 License
+
+use crate::{AppState, UserSession};
+use axum::body::Body;
+use axum::extract::State;
+use axum::http::{HeaderValue, Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub const PERIOD_SECS: u64 = 30 * 86400;
+const MAX_CHARGES: usize = 48; // per subscription, newest kept
+
+/// An owner-defined plan for one service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RatePlan {
+    pub id: String,
+    pub name: String,
+    pub monthly_price_credits: u64,
+    pub included_requests: u64,
+    pub overage_credits_per_request: Option<u64>, // None: hard cap at the quota
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SubscriptionStatus {
+    Active,
+    PastDue, // renewal failed for lack of credits; calls refused until paid
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ChargeKind {
+    Subscription,
+    Proration,
+    Overage,
+}
+
+/// One movement of credits; negative amounts were refunded to the subscriber
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Charge {
+    pub at: u64,
+    pub kind: ChargeKind,
+    pub plan_id: String,
+    pub credits: i64,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Subscription {
+    pub subscriber: String,
+    pub service_key: String,
+    pub plan_id: String,
+    pub status: SubscriptionStatus,
+    pub period_start: u64,
+    pub period_end: u64,
+    pub requests_used: u64,    // this period, including overage
+    pub overage_requests: u64, // this period, billed at renewal or plan change
+    pub cancel_at_period_end: bool,
+    pub charges: Vec<Charge>,
+}
+
+impl Subscription {
+    pub fn key(subscriber: &str, service_key: &str) -> String {
+        format!("{}:{}", subscriber, service_key)
+    }
+
+    fn record(&mut self, charge: Charge) {
+        self.charges.push(charge);
+        if self.charges.len() > MAX_CHARGES {
+            let excess = self.charges.len() - MAX_CHARGES;
+            self.charges.drain(0..excess);
+        }
+    }
+}
+
+fn debit(
+    sessions: &mut HashMap<String, UserSession>,
+    wallet: &str,
+    credits: u64,
+) -> Result<(), String> {
+    let session = sessions
+        .get_mut(wallet)
+        .ok_or("Subscriber has no credits account")?;
+    if session.credits < credits {
+        return Err(format!(
+            "Insufficient credits: {} needed, {} available",
+            credits, session.credits
+        ));
+    }
+    session.credits -= credits;
+    Ok(())
+}
+
+fn refund(sessions: &mut HashMap<String, UserSession>, wallet: &str, credits: u64) {
+    if let Some(session) = sessions.get_mut(wallet) {
+        session.credits += credits;
+    }
+}
+
+fn overage_charge(plan: &RatePlan, requests: u64) -> u64 {
+    requests * plan.overage_credits_per_request.unwrap_or(0)
+}
+
+/// Start a subscription, charging the first period up front
+pub fn subscribe(
+    subscriptions: &mut HashMap<String, Subscription>,
+    sessions: &mut HashMap<String, UserSession>,
+    subscriber: &str,
+    service_key: &str,
+    plan: &RatePlan,
+    now: u64,
+) -> Result<Subscription, String> {
+    let key = Subscription::key(subscriber, service_key);
+    if subscriptions
+        .get(&key)
+        .is_some_and(|existing| existing.status != SubscriptionStatus::Cancelled)
+    {
+        return Err("Already subscribed; change the plan instead".to_string());
+    }
+
+    debit(sessions, subscriber, plan.monthly_price_credits)?;
+
+    let mut subscription = Subscription {
+        subscriber: subscriber.to_string(),
+        service_key: service_key.to_string(),
+        plan_id: plan.id.clone(),
+        status: SubscriptionStatus::Active,
+        period_start: now,
+        period_end: now + PERIOD_SECS,
+        requests_used: 0,
+        overage_requests: 0,
+        cancel_at_period_end: false,
+        charges: Vec::new(),
+    };
+    subscription.record(Charge {
+        at: now,
+        kind: ChargeKind::Subscription,
+        plan_id: plan.id.clone(),
+        credits: plan.monthly_price_credits as i64,
+        description: format!("{} for the first period", plan.name),
+    });
+    subscriptions.insert(key, subscription.clone());
+    Ok(subscription)
+}
+
+/// Switch plans mid-period: overage so far is settled at the old plan's
+/// price, the unused part of the old plan is credited and the rest of the
+/// period is charged at the new plan's price
+pub fn change_plan(
+    subscription: &mut Subscription,
+    sessions: &mut HashMap<String, UserSession>,
+    old: &RatePlan,
+    new: &RatePlan,
+    now: u64,
+) -> Result<i64, String> {
+    if subscription.status != SubscriptionStatus::Active {
+        return Err("Only active subscriptions can change plans".to_string());
+    }
+
+    let period = subscription
+        .period_end
+        .saturating_sub(subscription.period_start) as f64;
+    let remaining = subscription.period_end.saturating_sub(now) as f64 / period.max(1.0);
+    let credit = (old.monthly_price_credits as f64 * remaining).round() as i64;
+    let charge = (new.monthly_price_credits as f64 * remaining).round() as i64;
+    let overage = overage_charge(old, subscription.overage_requests) as i64;
+    let net = charge - credit + overage;
+
+    if net > 0 {
+        debit(sessions, &subscription.subscriber, net as u64)?;
+    } else {
+        refund(sessions, &subscription.subscriber, net.unsigned_abs());
+    }
+
+    if overage > 0 {
+        subscription.record(Charge {
+            at: now,
+            kind: ChargeKind::Overage,
+            plan_id: old.id.clone(),
+            credits: overage,
+            description: format!("{} requests over quota", subscription.overage_requests),
+        });
+    }
+    subscription.record(Charge {
+        at: now,
+        kind: ChargeKind::Proration,
+        plan_id: new.id.clone(),
+        credits: charge - credit,
+        description: format!(
+            "{} → {} for {:.0}% of the period",
+            old.name,
+            new.name,
+            remaining * 100.0
+        ),
+    });
+
+    // Requests already made count against the new plan's quota; overage
+    // past it is billed from here on
+    subscription.plan_id = new.id.clone();
+    subscription.overage_requests = 0;
+    Ok(net)
+}
+
+/// Close out a finished period: bill its overage, then the next period
+/// unless the subscription was cancelled. A failed charge leaves the
+/// subscription past due and is retried on the next run.
+pub fn renew(
+    subscription: &mut Subscription,
+    sessions: &mut HashMap<String, UserSession>,
+    plan: &RatePlan,
+    now: u64,
+) {
+    if subscription.status == SubscriptionStatus::Cancelled || now < subscription.period_end {
+        return;
+    }
+
+    let overage = overage_charge(plan, subscription.overage_requests);
+    let next_period = if subscription.cancel_at_period_end {
+        0
+    } else {
+        plan.monthly_price_credits
+    };
+
+    if let Err(e) = debit(sessions, &subscription.subscriber, overage + next_period) {
+        if subscription.status != SubscriptionStatus::PastDue {
+            println!(
+                "💳 Subscription {} to {} is past due: {}",
+                subscription.subscriber, subscription.service_key, e
+            );
+        }
+        subscription.status = SubscriptionStatus::PastDue;
+        return;
+    }
+
+    if overage > 0 {
+        subscription.record(Charge {
+            at: now,
+            kind: ChargeKind::Overage,
+            plan_id: plan.id.clone(),
+            credits: overage as i64,
+            description: format!("{} requests over quota", subscription.overage_requests),
+        });
+    }
+
+    if subscription.cancel_at_period_end {
+        subscription.status = SubscriptionStatus::Cancelled;
+        return;
+    }
+
+    subscription.record(Charge {
+        at: now,
+        kind: ChargeKind::Subscription,
+        plan_id: plan.id.clone(),
+        credits: next_period as i64,
+        description: format!("{} renewal", plan.name),
+    });
+    subscription.status = SubscriptionStatus::Active;
+    subscription.period_start = now;
+    subscription.period_end = now + PERIOD_SECS;
+    subscription.requests_used = 0;
+    subscription.overage_requests = 0;
+}
+
+/// Recurring billing, run by the supervisor
+pub async fn bill_due(state: AppState) -> Result<(), String> {
+    let now = chrono::Utc::now().timestamp() as u64;
+    let plans = state.rate_plans.read().await.clone();
+    let mut subscriptions = state.subscriptions.write().await;
+    let mut sessions = state.user_sessions.write().await;
+
+    for subscription in subscriptions.values_mut() {
+        let plan = plans
+            .get(&subscription.service_key)
+            .and_then(|plans| plans.iter().find(|plan| plan.id == subscription.plan_id));
+        match plan {
+            Some(plan) => renew(subscription, &mut sessions, plan, now),
+            // The owner withdrew the plan; let the paid period run out
+            None if now >= subscription.period_end => {
+                subscription.status = SubscriptionStatus::Cancelled
+            }
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+fn refuse(status: StatusCode, error: &str) -> Response {
+    (status, Json(serde_json::json!({ "error": error }))).into_response()
+}
+
+/// Count one call against the plan: Ok((limit, remaining)), or the response
+/// refusing it
+fn count_call(
+    subscription: &mut Subscription,
+    plan: Option<&RatePlan>,
+) -> Option<Result<(u64, u64), Response>> {
+    if subscription.status == SubscriptionStatus::PastDue {
+        return Some(Err(refuse(
+            StatusCode::PAYMENT_REQUIRED,
+            "Subscription is past due; add credits to resume",
+        )));
+    }
+    let plan = match plan {
+        Some(plan) => plan,
+        None => {
+            return Some(Err(refuse(
+                StatusCode::PAYMENT_REQUIRED,
+                "Subscribed plan is no longer offered",
+            )))
+        }
+    };
+
+    let within_quota = subscription.requests_used < plan.included_requests;
+    if !within_quota && plan.overage_credits_per_request.is_none() {
+        return Some(Err(refuse(
+            StatusCode::TOO_MANY_REQUESTS,
+            "Plan quota used up for this period",
+        )));
+    }
+    subscription.requests_used += 1;
+    if !within_quota {
+        subscription.overage_requests += 1;
+    }
+    Some(Ok((
+        plan.included_requests,
+        plan.included_requests
+            .saturating_sub(subscription.requests_used),
+    )))
+}
+
+/// Count calls from subscribers against their plan's quota. Subscribers are
+/// identified by access token only; everyone else pays per call.
+pub async fn enforce_quota(
+    State(state): State<AppState>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let mut segments = request.uri().path().trim_start_matches('/').split('/');
+    let service_key = match (segments.next(), segments.next()) {
+        (Some(wallet), Some(service)) => format!("{}_{}", wallet, service),
+        _ => return next.run(request).await,
+    };
+    let subscriber = match crate::token_wallet(&state, request.headers()).await {
+        Some(subscriber) => subscriber,
+        None => return next.run(request).await,
+    };
+
+    let key = Subscription::key(&subscriber, &service_key);
+    let quota = {
+        let plans = state.rate_plans.read().await;
+        let mut subscriptions = state.subscriptions.write().await;
+        match subscriptions.get_mut(&key) {
+            Some(subscription) if subscription.status != SubscriptionStatus::Cancelled => {
+                let plan = plans
+                    .get(&service_key)
+                    .and_then(|plans| plans.iter().find(|plan| plan.id == subscription.plan_id));
+                count_call(subscription, plan)
+            }
+            _ => None,
+        }
+    };
+
+    let (limit, remaining) = match quota {
+        None => return next.run(request).await,
+        Some(Err(refusal)) => return refusal,
+        Some(Ok(quota)) => quota,
+    };
+
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    headers.insert("x-quota-limit", HeaderValue::from(limit));
+    headers.insert("x-quota-remaining", HeaderValue::from(remaining));
+    response
+}
@@ -0,0 +1,202 @@
+// Named background tasks with watchdogs, restart policies and run stats
+// AGPL-3.0 License
+
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RestartPolicy {
+    Always,                          // keep running after every failure
+    OnFailure { max_failures: u32 }, // stop after this many consecutive failures
+    Never,                           // stop on the first failure
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TaskState {
+    Running,
+    Backoff, // waiting to retry after a failure
+    Stalled, // no heartbeat within the watchdog window
+    Stopped,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskStatus {
+    pub name: String,
+    pub state: TaskState,
+    pub interval_secs: u64,
+    pub watchdog_secs: u64,
+    pub restart_policy: RestartPolicy,
+    pub started_at: u64,
+    pub last_heartbeat: u64,
+    pub last_run_at: Option<u64>,
+    pub last_runtime_ms: Option<u64>,
+    pub max_runtime_ms: u64,
+    pub runs: u64,
+    pub failures: u64,
+    pub consecutive_failures: u32,
+    pub restarts: u64,
+    pub last_error: Option<String>,
+}
+
+#[derive(Clone, Default)]
+pub struct Supervisor {
+    tasks: Arc<RwLock<HashMap<String, TaskStatus>>>,
+}
+
+fn now() -> u64 {
+    chrono::Utc::now().timestamp() as u64
+}
+
+impl Supervisor {
+    /// Run `tick` every `interval`. Each run gets `watchdog` to finish and is
+    /// isolated in its own task, so a hang or panic counts as a failure
+    /// rather than silently killing the loop.
+    pub async fn spawn<F, Fut>(
+        &self,
+        name: &str,
+        state: AppState,
+        interval: Duration,
+        watchdog: Duration,
+        restart_policy: RestartPolicy,
+        tick: F,
+    ) where
+        F: Fn(AppState) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), String>> + Send + 'static,
+    {
+        let started_at = now();
+        self.tasks.write().await.insert(
+            name.to_string(),
+            TaskStatus {
+                name: name.to_string(),
+                state: TaskState::Running,
+                interval_secs: interval.as_secs(),
+                watchdog_secs: watchdog.as_secs(),
+                restart_policy: restart_policy.clone(),
+                started_at,
+                last_heartbeat: started_at,
+                last_run_at: None,
+                last_runtime_ms: None,
+                max_runtime_ms: 0,
+                runs: 0,
+                failures: 0,
+                consecutive_failures: 0,
+                restarts: 0,
+                last_error: None,
+            },
+        );
+
+        println!("🧵 Task {} started (every {:?})", name, interval);
+
+        let tasks = self.tasks.clone();
+        let name = name.to_string();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            let mut backoff = interval;
+
+            loop {
+                ticker.tick().await;
+
+                let started = Instant::now();
+                let run = tokio::spawn(tokio::time::timeout(watchdog, tick(state.clone())));
+                let result = match run.await {
+                    Ok(Ok(result)) => result,
+                    Ok(Err(_)) => Err(format!("watchdog: no completion within {:?}", watchdog)),
+                    Err(e) if e.is_panic() => Err("panicked".to_string()),
+                    Err(e) => Err(format!("aborted: {}", e)),
+                };
+                let runtime_ms = started.elapsed().as_millis() as u64;
+
+                let stop = {
+                    let mut tasks = tasks.write().await;
+                    let status = match tasks.get_mut(&name) {
+                        Some(status) => status,
+                        None => return,
+                    };
+
+                    let finished = now();
+                    status.last_heartbeat = finished;
+                    status.last_run_at = Some(finished);
+                    status.last_runtime_ms = Some(runtime_ms);
+                    status.max_runtime_ms = status.max_runtime_ms.max(runtime_ms);
+                    status.runs += 1;
+
+                    match result {
+                        Ok(()) => {
+                            status.state = TaskState::Running;
+                            status.consecutive_failures = 0;
+                            backoff = interval;
+                            false
+                        }
+                        Err(e) => {
+                            println!("⚠️  Task {} failed: {}", name, e);
+                            status.failures += 1;
+                            status.consecutive_failures += 1;
+                            status.last_error = Some(e);
+
+                            let stop = match status.restart_policy {
+                                RestartPolicy::Always => false,
+                                RestartPolicy::OnFailure { max_failures } => {
+                                    status.consecutive_failures >= max_failures
+                                }
+                                RestartPolicy::Never => true,
+                            };
+                            if stop {
+                                status.state = TaskState::Stopped;
+                            } else {
+                                status.state = TaskState::Backoff;
+                                status.restarts += 1;
+                            }
+                            stop
+                        }
+                    }
+                };
+
+                if stop {
+                    println!("🛑 Task {} stopped by its restart policy", name);
+                    return;
+                }
+
+                // Back off exponentially while failing, on top of the normal interval
+                let failing = tasks
+                    .read()
+                    .await
+                    .get(&name)
+                    .map(|status| status.consecutive_failures > 0)
+                    .unwrap_or(false);
+                if failing {
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(Duration::from_secs(300));
+                }
+            }
+        });
+    }
+
+    /// Current status of every task; tasks whose heartbeat is older than
+    /// their interval plus watchdog are reported as stalled
+    pub async fn statuses(&self) -> Vec<TaskStatus> {
+        let now = now();
+        let mut statuses: Vec<TaskStatus> = self
+            .tasks
+            .read()
+            .await
+            .values()
+            .cloned()
+            .map(|mut status| {
+                let window = status.interval_secs + status.watchdog_secs;
+                if status.state == TaskState::Running && now > status.last_heartbeat + window {
+                    status.state = TaskState::Stalled;
+                }
+                status
+            })
+            .collect();
+
+        statuses.sort_by(|a, b| a.name.cmp(&b.name));
+        statuses
+    }
+}
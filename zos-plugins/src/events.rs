@@ -0,0 +1,140 @@
+// Typed compiler events and how they cross the plugin ABI
+use crate::CompilerEvent;
+use serde::{Deserialize, Serialize};
+
+/// Bumped whenever `PluginEvent` changes shape; plugins built against
+/// another version are refused at the handshake
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
+/// `CompilerEvent::event_type` codes, one per `PluginEvent` variant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[repr(u32)]
+pub enum EventKind {
+    ItemParsed = 1,
+    Diagnostic = 2,
+    BuildStarted = 3,
+    BuildFinished = 4,
+    DeployState = 5,
+    TelemetrySample = 6,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticLevel {
+    Error,
+    Warning,
+    Note,
+    Help,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeployPhase {
+    Pending,
+    Building,
+    Deploying,
+    Live,
+    Failed,
+    RolledBack,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PluginEvent {
+    ItemParsed {
+        file: String,
+        item_name: String,
+        item_kind: String, // fn, struct, enum, impl, macro, ...
+        line: u32,
+    },
+    Diagnostic {
+        level: DiagnosticLevel,
+        message: String,
+        code: Option<String>, // E0308, clippy::..., ...
+        file: Option<String>,
+        line: Option<u32>,
+    },
+    BuildStarted {
+        build_id: String,
+        package: String,
+        profile: String,
+    },
+    BuildFinished {
+        build_id: String,
+        success: bool,
+        duration_ms: u64,
+    },
+    DeployState {
+        deployment_id: String,
+        environment: String,
+        phase: DeployPhase,
+    },
+    TelemetrySample {
+        metric: String,
+        value: f64,
+        timestamp: u64,
+    },
+}
+
+impl PluginEvent {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            PluginEvent::ItemParsed { .. } => EventKind::ItemParsed,
+            PluginEvent::Diagnostic { .. } => EventKind::Diagnostic,
+            PluginEvent::BuildStarted { .. } => EventKind::BuildStarted,
+            PluginEvent::BuildFinished { .. } => EventKind::BuildFinished,
+            PluginEvent::DeployState { .. } => EventKind::DeployState,
+            PluginEvent::TelemetrySample { .. } => EventKind::TelemetrySample,
+        }
+    }
+}
+
+/// Payload format, agreed per plugin in its manifest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[repr(u32)]
+pub enum EventEncoding {
+    #[default]
+    Bincode = 0,
+    Json = 1,
+}
+
+/// An event serialized for one plugin; `as_abi` borrows it as the C view
+#[derive(Debug, Clone)]
+pub struct EncodedEvent {
+    pub kind: EventKind,
+    pub encoding: EventEncoding,
+    pub payload: Vec<u8>,
+}
+
+impl EncodedEvent {
+    pub fn encode(event: &PluginEvent, encoding: EventEncoding) -> Result<Self, String> {
+        let payload = match encoding {
+            EventEncoding::Bincode => bincode::serialize(event).map_err(|e| e.to_string())?,
+            EventEncoding::Json => serde_json::to_vec(event).map_err(|e| e.to_string())?,
+        };
+        Ok(Self {
+            kind: event.kind(),
+            encoding,
+            payload,
+        })
+    }
+
+    pub fn decode(&self) -> Result<PluginEvent, String> {
+        match self.encoding {
+            EventEncoding::Bincode => {
+                bincode::deserialize(&self.payload).map_err(|e| e.to_string())
+            }
+            EventEncoding::Json => serde_json::from_slice(&self.payload).map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Valid only while `self` is alive and unmodified
+    pub fn as_abi(&self) -> CompilerEvent {
+        CompilerEvent {
+            event_type: self.kind as u32,
+            data: self.payload.as_ptr(),
+            size: self.payload.len(),
+        }
+    }
+}
@@ -2,6 +2,14 @@
 use libloading::{Library, Symbol};
 use std::collections::HashMap;
 
+pub mod events;
+pub mod policy;
+
+use events::{EncodedEvent, PluginEvent, EVENT_SCHEMA_VERSION};
+use policy::{Capability, ElevationAttempt, HostApi, HostPolicy, PluginGrant, PluginManifest};
+
+/// ABI view of an event: `event_type` is an `EventKind` code and `data`
+/// points at `size` bytes of the payload, in the plugin's `EventEncoding`
 #[repr(C)]
 pub struct CompilerEvent {
     pub event_type: u32,
@@ -11,56 +19,148 @@ pub struct CompilerEvent {
 
 pub struct PluginDriver {
     plugins: HashMap<String, Library>,
-    stream: Vec<CompilerEvent>,
+    stream: Vec<PluginEvent>,
+    policy: HostPolicy,
+    grants: HashMap<String, PluginGrant>,
+    elevation_log: Vec<ElevationAttempt>,
 }
 
 // Monad operations
 impl PluginDriver {
     pub fn new() -> Self {
+        Self::with_policy(HostPolicy::default())
+    }
+
+    pub fn with_policy(policy: HostPolicy) -> Self {
         Self {
             plugins: HashMap::new(),
             stream: Vec::new(),
+            policy,
+            grants: HashMap::new(),
+            elevation_log: Vec::new(),
         }
     }
 
     // Monadic bind - chain operations on the stream
     pub fn bind<F>(mut self, f: F) -> Self
     where
-        F: Fn(CompilerEvent) -> CompilerEvent,
+        F: Fn(PluginEvent) -> PluginEvent,
     {
         self.stream = self.stream.into_iter().map(f).collect();
         self
     }
 
+    // Drop events from the stream
+    pub fn filter<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&PluginEvent) -> bool,
+    {
+        self.stream.retain(f);
+        self
+    }
+
     // Comonadic extract - get current state
-    pub fn extract(&self) -> &[CompilerEvent] {
+    pub fn extract(&self) -> &[PluginEvent] {
         &self.stream
     }
 
-    // Load .so plugin dynamically
-    pub fn load_plugin(
-        &mut self,
-        name: &str,
-        path: &str,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let lib = unsafe { Library::new(path)? };
-        self.plugins.insert(name.to_string(), lib);
+    // Load .so plugin dynamically, from its manifest; refused if it reaches
+    // above host policy, its audit or its level's capabilities
+    pub fn load_plugin(&mut self, manifest_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let (manifest, dir) = PluginManifest::from_file(manifest_path)?;
+        let (grant, attempts) = policy::evaluate(&manifest, &dir, &self.policy)?;
+        if !attempts.is_empty() {
+            let reasons: Vec<String> = attempts.iter().map(|a| a.reason.clone()).collect();
+            self.elevation_log.extend(attempts);
+            return Err(format!("Plugin {} refused: {}", manifest.name, reasons.join("; ")).into());
+        }
+
+        if manifest.event_schema_version != EVENT_SCHEMA_VERSION {
+            return Err(format!(
+                "Plugin {} speaks event schema v{}, host speaks v{}",
+                manifest.name, manifest.event_schema_version, EVENT_SCHEMA_VERSION
+            )
+            .into());
+        }
+
+        let lib = unsafe { Library::new(dir.join(&manifest.library))? };
+
+        // Handshake: the library itself must agree with its manifest
+        if let Ok(schema) =
+            unsafe { lib.get::<unsafe extern "C" fn() -> u32>(b"zos_plugin_event_schema") }
+        {
+            let version = unsafe { schema() };
+            if version != manifest.event_schema_version {
+                return Err(format!(
+                    "Plugin {} library speaks event schema v{}, manifest says v{}",
+                    manifest.name, version, manifest.event_schema_version
+                )
+                .into());
+            }
+        }
+
+        // Tell the plugin what it was granted, if it wants to know
+        let host = HostApi {
+            security_level: grant.meta.security_level as u32,
+            capabilities: grant.capabilities.iter().map(|c| c.bit()).sum(),
+            event_schema_version: EVENT_SCHEMA_VERSION,
+            event_encoding: grant.event_encoding as u32,
+        };
+        if let Ok(init) =
+            unsafe { lib.get::<unsafe extern "C" fn(*const HostApi)>(b"zos_plugin_init") }
+        {
+            unsafe { init(&host) };
+        }
+
+        self.plugins.insert(manifest.name.clone(), lib);
+        self.grants.insert(manifest.name, grant);
         Ok(())
     }
 
-    // Execute plugin function on stream
+    /// Host functions call this before acting for a plugin; a denial is
+    /// logged as an elevation attempt
+    pub fn require_capability(&mut self, name: &str, capability: Capability) -> Result<(), String> {
+        let grant = self.grants.get(name).ok_or("Plugin not loaded")?;
+        if grant.capabilities.contains(&capability) {
+            return Ok(());
+        }
+        self.elevation_log.push(ElevationAttempt::new(
+            name,
+            format!("{:?}", capability),
+            format!("{:?}", grant.meta.security_level),
+            "host call outside granted capabilities".to_string(),
+        ));
+        Err(format!("Plugin {} may not use {:?}", name, capability))
+    }
+
+    pub fn grant(&self, name: &str) -> Option<&PluginGrant> {
+        self.grants.get(name)
+    }
+
+    pub fn elevation_log(&self) -> &[ElevationAttempt] {
+        &self.elevation_log
+    }
+
+    // Execute plugin function on the events it subscribed to, encoded the
+    // way it asked for
     pub fn execute_plugin(
         &mut self,
         name: &str,
         func: &str,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        if let Some(lib) = self.plugins.get(name) {
-            let func: Symbol<unsafe extern "C" fn(u32, *const u8) -> *mut u8> =
+        if let (Some(lib), Some(grant)) = (self.plugins.get(name), self.grants.get(name)) {
+            let func: Symbol<unsafe extern "C" fn(*const CompilerEvent) -> *mut u8> =
                 unsafe { lib.get(func.as_bytes())? };
 
-            for event in &self.stream {
+            let subscribed = self
+                .stream
+                .iter()
+                .filter(|event| grant.events.is_empty() || grant.events.contains(&event.kind()));
+            for event in subscribed {
+                let encoded = EncodedEvent::encode(event, grant.event_encoding)?;
+                let abi = encoded.as_abi();
                 unsafe {
-                    func(event.event_type, event.data);
+                    func(&abi);
                 }
             }
         }
@@ -68,7 +168,7 @@ impl PluginDriver {
     }
 
     // React to new compiler event
-    pub fn react(mut self, event: CompilerEvent) -> Self {
+    pub fn react(mut self, event: PluginEvent) -> Self {
         self.stream.push(event);
         self
     }
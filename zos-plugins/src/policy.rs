@@ -0,0 +1,292 @@
+// Plugin manifests and the host policy they are checked against
+use crate::events::{EventEncoding, EventKind};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use zos_types::{LMFDBOrbitRef, PluginMeta, SecurityLevel};
+
+/// Host functions a plugin may ask for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    Filesystem,
+    Network,
+    Exec,
+    Syscall,
+}
+
+impl Capability {
+    /// Bit in `HostApi::capabilities`
+    pub fn bit(self) -> u32 {
+        match self {
+            Capability::Filesystem => 1,
+            Capability::Network => 1 << 1,
+            Capability::Exec => 1 << 2,
+            Capability::Syscall => 1 << 3,
+        }
+    }
+
+    /// Lowest security level that may hold this capability
+    pub fn min_level(self) -> SecurityLevel {
+        match self {
+            Capability::Filesystem => SecurityLevel::Controlled,
+            Capability::Network | Capability::Exec => SecurityLevel::Privileged,
+            Capability::Syscall => SecurityLevel::Critical,
+        }
+    }
+}
+
+/// Capabilities a level allows: Safe plugins only see the event stream
+pub fn capabilities_for(level: SecurityLevel) -> Vec<Capability> {
+    [
+        Capability::Filesystem,
+        Capability::Network,
+        Capability::Exec,
+        Capability::Syscall,
+    ]
+    .into_iter()
+    .filter(|capability| capability.min_level() <= level)
+    .collect()
+}
+
+pub fn parse_security_level(level: &str) -> Result<SecurityLevel, String> {
+    match level {
+        "Safe" => Ok(SecurityLevel::Safe),
+        "Controlled" => Ok(SecurityLevel::Controlled),
+        "Privileged" => Ok(SecurityLevel::Privileged),
+        "Critical" => Ok(SecurityLevel::Critical),
+        other => Err(format!("Unknown security level {}", other)),
+    }
+}
+
+/// `<plugin>.manifest.json`, shipped next to the library
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginManifest {
+    pub name: String,
+    pub version: String,
+    pub library: String,        // relative to the manifest
+    pub security_level: String, // Safe, Controlled, Privileged or Critical
+    #[serde(default)]
+    pub capabilities: Vec<Capability>,
+    pub annotations: Option<String>, // zos-analysis --annotate output, relative to the manifest
+    pub event_schema_version: u32,
+    #[serde(default)]
+    pub event_encoding: EventEncoding,
+    #[serde(default)]
+    pub events: Vec<EventKind>, // subscriptions; empty for every event
+}
+
+impl PluginManifest {
+    pub fn from_file(path: &str) -> Result<(Self, PathBuf), String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Plugin manifest {} not readable: {}", path, e))?;
+        let manifest: PluginManifest = serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid plugin manifest {}: {}", path, e))?;
+        let dir = Path::new(path)
+            .parent()
+            .unwrap_or(Path::new("."))
+            .to_path_buf();
+        Ok((manifest, dir))
+    }
+}
+
+/// Which levels this host runs. Plugins above `max_level` load only if
+/// listed in `trusted` at their level or higher.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostPolicy {
+    pub max_level: String,
+    #[serde(default)]
+    pub trusted: HashMap<String, String>, // plugin name -> highest level allowed
+    #[serde(default = "default_require_annotations")]
+    pub require_annotations: bool, // refuse plugins above Safe without audit annotations
+}
+
+fn default_require_annotations() -> bool {
+    true
+}
+
+impl Default for HostPolicy {
+    fn default() -> Self {
+        Self {
+            max_level: "Controlled".to_string(),
+            trusted: HashMap::new(),
+            require_annotations: true,
+        }
+    }
+}
+
+impl HostPolicy {
+    pub fn limit_for(&self, plugin: &str) -> Result<SecurityLevel, String> {
+        let max_level = parse_security_level(&self.max_level)?;
+        match self.trusted.get(plugin) {
+            Some(trusted) => Ok(parse_security_level(trusted)?.max(max_level)),
+            None => Ok(max_level),
+        }
+    }
+}
+
+/// What the security audit found in the plugin's source: the highest level
+/// among its flagged items, and the plugin orbit
+#[derive(Debug, Clone)]
+pub struct AuditSummary {
+    pub required_level: SecurityLevel,
+    pub findings: usize,
+    pub orbit: Option<LMFDBOrbitRef>,
+}
+
+pub fn read_audit(path: &Path) -> Result<AuditSummary, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Audit annotations {} not readable: {}", path.display(), e))?;
+    let annotations: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| format!("Invalid audit annotations {}: {}", path.display(), e))?;
+
+    let mut required_level = SecurityLevel::Safe;
+    let levels = annotations["plugin"]["security_level"]
+        .as_str()
+        .into_iter()
+        .chain(
+            annotations["audit"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|finding| finding["security_level"].as_str()),
+        );
+    for level in levels {
+        required_level = required_level.max(parse_security_level(level)?);
+    }
+
+    let orbit = &annotations["plugin"]["lmfdb_orbit"];
+    let orbit = orbit["orbit_id"].as_str().map(|orbit_id| LMFDBOrbitRef {
+        orbit_id: orbit_id.to_string(),
+        complexity_class: orbit["complexity_class"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+        lmfdb_url: orbit["lmfdb_url"].as_str().unwrap_or_default().to_string(),
+    });
+
+    Ok(AuditSummary {
+        required_level,
+        findings: annotations["audit"].as_array().map_or(0, Vec::len),
+        orbit,
+    })
+}
+
+/// A plugin reaching above what it was granted, at load or at a host call
+#[derive(Debug, Clone, Serialize)]
+pub struct ElevationAttempt {
+    pub plugin: String,
+    pub requested: String,
+    pub granted: String,
+    pub reason: String,
+    pub timestamp: u64,
+}
+
+impl ElevationAttempt {
+    pub fn new(plugin: &str, requested: String, granted: String, reason: String) -> Self {
+        let attempt = Self {
+            plugin: plugin.to_string(),
+            requested,
+            granted,
+            reason,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs()),
+        };
+        eprintln!(
+            "🚨 Plugin elevation attempt: {} requested {} (granted {}): {}",
+            attempt.plugin, attempt.requested, attempt.granted, attempt.reason
+        );
+        attempt
+    }
+}
+
+/// What a loaded plugin was allowed
+#[derive(Debug, Clone)]
+pub struct PluginGrant {
+    pub meta: PluginMeta,
+    pub capabilities: Vec<Capability>,
+    pub event_encoding: EventEncoding,
+    pub events: Vec<EventKind>,
+}
+
+/// Handed to the plugin's `zos_plugin_init`, when it exports one
+#[repr(C)]
+pub struct HostApi {
+    pub security_level: u32,
+    pub capabilities: u32, // `Capability::bit`s granted
+    pub event_schema_version: u32,
+    pub event_encoding: u32, // `EventEncoding` code
+}
+
+/// Check a manifest against the host policy and the audit. Every way a
+/// plugin can reach too high is returned as an elevation attempt; the
+/// plugin is granted only if there are none.
+pub fn evaluate(
+    manifest: &PluginManifest,
+    manifest_dir: &Path,
+    policy: &HostPolicy,
+) -> Result<(PluginGrant, Vec<ElevationAttempt>), String> {
+    let declared = parse_security_level(&manifest.security_level)?;
+    let limit = policy.limit_for(&manifest.name)?;
+    let mut attempts = Vec::new();
+
+    if declared > limit {
+        attempts.push(ElevationAttempt::new(
+            &manifest.name,
+            format!("{:?}", declared),
+            format!("{:?}", limit),
+            "declared level is above host policy".to_string(),
+        ));
+    }
+
+    let audit = match &manifest.annotations {
+        Some(annotations) => Some(read_audit(&manifest_dir.join(annotations))?),
+        None if policy.require_annotations && declared > SecurityLevel::Safe => {
+            return Err(format!(
+                "Plugin {} declares {:?} but ships no audit annotations",
+                manifest.name, declared
+            ));
+        }
+        None => None,
+    };
+    if let Some(audit) = &audit {
+        if audit.required_level > declared {
+            attempts.push(ElevationAttempt::new(
+                &manifest.name,
+                format!("{:?}", audit.required_level),
+                format!("{:?}", declared),
+                format!(
+                    "security audit found {} item(s) needing more than the declared level",
+                    audit.findings
+                ),
+            ));
+        }
+    }
+
+    let allowed = capabilities_for(declared.min(limit));
+    for capability in &manifest.capabilities {
+        if !allowed.contains(capability) {
+            attempts.push(ElevationAttempt::new(
+                &manifest.name,
+                format!("{:?}", capability),
+                format!("{:?}", declared.min(limit)),
+                format!("capability needs {:?}", capability.min_level()),
+            ));
+        }
+    }
+
+    let grant = PluginGrant {
+        meta: PluginMeta {
+            name: manifest.name.clone(),
+            version: manifest.version.clone(),
+            security_level: declared,
+            lmfdb_orbit: audit.and_then(|audit| audit.orbit),
+        },
+        capabilities: manifest.capabilities.clone(),
+        event_encoding: manifest.event_encoding,
+        events: manifest.events.clone(),
+    };
+    Ok((grant, attempts))
+}
@@ -0,0 +1,523 @@
+// ZOS Policy - declarative access decisions
+// AGPL-3.0 License
+//
+// Policies are data: each permits or forbids a set of actions when its
+// conditions hold over the request's principal, resource and context.
+// Evaluation is deny by default, and any matching forbid overrides every
+// permit, so operators can layer restrictions on top of a consumer's
+// built-in policies without editing them.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Effect {
+    Permit,
+    Forbid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+    In,       // attribute is one of the values
+    NotIn,    // attribute is present and none of the values
+    Contains, // attribute (list or string) contains the value
+    Exists,
+    Missing,
+}
+
+/// `attr op value`. The value may be `{"attr": "principal.wallet"}` to
+/// compare against another attribute of the request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Condition {
+    pub attr: String, // dotted path, e.g. "principal.reputation"
+    pub op: Op,
+    #[serde(default)]
+    pub value: Value,
+}
+
+impl Condition {
+    pub fn new(attr: &str, op: Op, value: Value) -> Self {
+        Self {
+            attr: attr.to_string(),
+            op,
+            value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Policy {
+    pub id: String,
+    pub effect: Effect,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub actions: Vec<String>, // exact, "*", or a prefix like "service:*"
+    #[serde(default)]
+    pub when: Vec<Condition>, // all must hold
+    #[serde(default)]
+    pub unless: Vec<Condition>, // none may hold
+}
+
+impl Policy {
+    pub fn permit(id: &str, actions: &[&str]) -> Self {
+        Self::new(id, Effect::Permit, actions)
+    }
+
+    pub fn forbid(id: &str, actions: &[&str]) -> Self {
+        Self::new(id, Effect::Forbid, actions)
+    }
+
+    fn new(id: &str, effect: Effect, actions: &[&str]) -> Self {
+        Self {
+            id: id.to_string(),
+            effect,
+            description: None,
+            actions: actions.iter().map(|action| action.to_string()).collect(),
+            when: Vec::new(),
+            unless: Vec::new(),
+        }
+    }
+
+    pub fn describe(mut self, description: &str) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    pub fn when(mut self, attr: &str, op: Op, value: Value) -> Self {
+        self.when.push(Condition::new(attr, op, value));
+        self
+    }
+
+    pub fn unless(mut self, attr: &str, op: Op, value: Value) -> Self {
+        self.unless.push(Condition::new(attr, op, value));
+        self
+    }
+
+    pub fn covers(&self, action: &str) -> bool {
+        self.actions
+            .iter()
+            .any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => action.starts_with(prefix),
+                None => pattern == action,
+            })
+    }
+
+    pub fn matches(&self, request: &Request) -> bool {
+        self.covers(&request.action)
+            && self.when.iter().all(|condition| request.holds(condition))
+            && !self.unless.iter().any(|condition| request.holds(condition))
+    }
+}
+
+/// What a policy is evaluated against
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Request {
+    pub action: String,
+    #[serde(default)]
+    pub principal: Value,
+    #[serde(default)]
+    pub resource: Value,
+    #[serde(default)]
+    pub context: Value,
+}
+
+impl Request {
+    pub fn new(action: &str) -> Self {
+        Self {
+            action: action.to_string(),
+            ..Self::default()
+        }
+    }
+
+    pub fn principal(mut self, principal: Value) -> Self {
+        self.principal = principal;
+        self
+    }
+
+    pub fn resource(mut self, resource: Value) -> Self {
+        self.resource = resource;
+        self
+    }
+
+    pub fn context(mut self, context: Value) -> Self {
+        self.context = context;
+        self
+    }
+
+    /// The attribute at a dotted path; absent when any step is missing
+    pub fn attr(&self, path: &str) -> Option<&Value> {
+        let mut steps = path.split('.');
+        let mut value = match steps.next()? {
+            "principal" => &self.principal,
+            "resource" => &self.resource,
+            "context" => &self.context,
+            _ => return None,
+        };
+        for step in steps {
+            value = match value {
+                Value::Object(map) => map.get(step)?,
+                Value::Array(items) => items.get(step.parse::<usize>().ok()?)?,
+                _ => return None,
+            };
+        }
+        (!value.is_null()).then_some(value)
+    }
+
+    fn operand<'a>(&'a self, value: &'a Value) -> Option<&'a Value> {
+        match value.as_object().and_then(|map| map.get("attr")) {
+            Some(Value::String(path)) if value.as_object().map(|map| map.len()) == Some(1) => {
+                self.attr(path)
+            }
+            _ => Some(value),
+        }
+    }
+
+    /// A condition over a missing attribute is false, so a permit that
+    /// needs it does not apply and neither does a forbid
+    pub fn holds(&self, condition: &Condition) -> bool {
+        let actual = self.attr(&condition.attr);
+        match condition.op {
+            Op::Exists => return actual.is_some(),
+            Op::Missing => return actual.is_none(),
+            _ => {}
+        }
+        let (Some(actual), Some(expected)) = (actual, self.operand(&condition.value)) else {
+            return false;
+        };
+        match condition.op {
+            Op::Eq => equal(actual, expected),
+            Op::Ne => !equal(actual, expected),
+            Op::Lt => compare(actual, expected).is_some_and(|order| order.is_lt()),
+            Op::Lte => compare(actual, expected).is_some_and(|order| order.is_le()),
+            Op::Gt => compare(actual, expected).is_some_and(|order| order.is_gt()),
+            Op::Gte => compare(actual, expected).is_some_and(|order| order.is_ge()),
+            Op::In => one_of(actual, expected),
+            Op::NotIn => !one_of(actual, expected),
+            Op::Contains => match actual {
+                Value::Array(items) => items.iter().any(|item| equal(item, expected)),
+                Value::String(text) => expected.as_str().is_some_and(|part| text.contains(part)),
+                _ => false,
+            },
+            Op::Exists | Op::Missing => unreachable!(),
+        }
+    }
+}
+
+fn equal(a: &Value, b: &Value) -> bool {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(a), Some(b)) => a == b, // 1 and 1.0 are the same amount
+        _ => a == b,
+    }
+}
+
+fn compare(a: &Value, b: &Value) -> Option<std::cmp::Ordering> {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+fn one_of(actual: &Value, expected: &Value) -> bool {
+    match expected {
+        Value::Array(values) => values.iter().any(|value| equal(actual, value)),
+        value => equal(actual, value),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Decision {
+    pub allowed: bool,
+    pub policies: Vec<String>, // the policies that decided it
+    pub reason: String,
+}
+
+/// An ordered list of policies; serialized as a plain JSON array
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PolicySet {
+    pub policies: Vec<Policy>,
+}
+
+impl PolicySet {
+    pub fn new(policies: Vec<Policy>) -> Self {
+        Self { policies }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let set: Self =
+            serde_json::from_str(json).map_err(|e| format!("Invalid policies: {}", e))?;
+        set.validate()?;
+        Ok(set)
+    }
+
+    /// A policy file, or no policies when the path isn't set
+    pub fn load(path: Option<&str>) -> Result<Self, String> {
+        match path {
+            Some(path) => {
+                let json = std::fs::read_to_string(path)
+                    .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+                Self::from_json(&json).map_err(|e| format!("{}: {}", path, e))
+            }
+            None => Ok(Self::default()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.policies.is_empty()
+    }
+
+    pub fn push(&mut self, policy: Policy) {
+        self.policies.push(policy);
+    }
+
+    /// These policies followed by another set's
+    pub fn with(mut self, other: &PolicySet) -> Self {
+        self.policies.extend(other.policies.iter().cloned());
+        self
+    }
+
+    /// Ids must be unique, every policy must name an action, and attributes
+    /// must be rooted at principal, resource or context
+    pub fn validate(&self) -> Result<(), String> {
+        let mut ids = HashSet::new();
+        for policy in &self.policies {
+            if !ids.insert(policy.id.as_str()) {
+                return Err(format!("Duplicate policy id {}", policy.id));
+            }
+            if policy.actions.is_empty() {
+                return Err(format!("Policy {} names no actions", policy.id));
+            }
+            let mut paths: Vec<&str> = Vec::new();
+            for condition in policy.when.iter().chain(&policy.unless) {
+                paths.push(&condition.attr);
+                if let Some(Value::String(path)) =
+                    condition.value.as_object().and_then(|map| map.get("attr"))
+                {
+                    paths.push(path);
+                }
+            }
+            for path in paths {
+                if !matches!(
+                    path.split('.').next(),
+                    Some("principal" | "resource" | "context")
+                ) {
+                    return Err(format!(
+                        "Policy {}: {} is not under principal, resource or context",
+                        policy.id, path
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Deny by default; any matching forbid overrides every matching permit
+    pub fn evaluate(&self, request: &Request) -> Decision {
+        let matching: Vec<&Policy> = self
+            .policies
+            .iter()
+            .filter(|policy| policy.matches(request))
+            .collect();
+        let ids = |effect: Effect| -> Vec<String> {
+            matching
+                .iter()
+                .filter(|policy| policy.effect == effect)
+                .map(|policy| policy.id.clone())
+                .collect()
+        };
+
+        let forbids = ids(Effect::Forbid);
+        if !forbids.is_empty() {
+            let reason = matching
+                .iter()
+                .find(|policy| policy.effect == Effect::Forbid)
+                .and_then(|policy| policy.description.clone())
+                .unwrap_or_else(|| format!("Forbidden by {}", forbids.join(", ")));
+            return Decision {
+                allowed: false,
+                policies: forbids,
+                reason,
+            };
+        }
+        let permits = ids(Effect::Permit);
+        if permits.is_empty() {
+            return Decision {
+                allowed: false,
+                policies: permits,
+                reason: format!("No policy permits {}", request.action),
+            };
+        }
+        Decision {
+            allowed: true,
+            reason: format!("Permitted by {}", permits.join(", ")),
+            policies: permits,
+        }
+    }
+}
+
+/// Policy tests as data: requests and the decision each should get
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fixture {
+    pub name: String,
+    #[serde(default)]
+    pub policies: Option<PolicySet>, // tested instead of the caller's own set
+    pub cases: Vec<FixtureCase>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixtureCase {
+    pub name: String,
+    pub request: Request,
+    pub expect: Effect,
+    #[serde(default)]
+    pub decided_by: Option<Vec<String>>, // checked only when given
+}
+
+impl Fixture {
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let fixture: Self =
+            serde_json::from_str(json).map_err(|e| format!("Invalid fixture: {}", e))?;
+        if let Some(policies) = &fixture.policies {
+            policies.validate()?;
+        }
+        Ok(fixture)
+    }
+
+    /// Run every case; returns one message per case that failed
+    pub fn run(&self, policies: &PolicySet) -> Vec<String> {
+        let policies = self.policies.as_ref().unwrap_or(policies);
+        let mut failures = Vec::new();
+        for case in &self.cases {
+            let decision = policies.evaluate(&case.request);
+            let expected = case.expect == Effect::Permit;
+            if decision.allowed != expected {
+                failures.push(format!(
+                    "{} / {}: expected {:?}, got {} ({})",
+                    self.name,
+                    case.name,
+                    case.expect,
+                    if decision.allowed { "permit" } else { "forbid" },
+                    decision.reason
+                ));
+            } else if let Some(decided_by) = &case.decided_by {
+                if *decided_by != decision.policies {
+                    failures.push(format!(
+                        "{} / {}: decided by {:?}, expected {:?}",
+                        self.name, case.name, decision.policies, decided_by
+                    ));
+                }
+            }
+        }
+        failures
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const FIXTURES: &[&str] = &[
+        include_str!("../fixtures/bouncer.json"),
+        include_str!("../fixtures/service_calls.json"),
+        include_str!("../fixtures/port_allocation.json"),
+    ];
+
+    #[test]
+    fn fixtures_pass() {
+        for json in FIXTURES {
+            let fixture = Fixture::from_json(json).unwrap();
+            let failures = fixture.run(&PolicySet::default());
+            assert!(failures.is_empty(), "{:#?}", failures);
+        }
+    }
+
+    #[test]
+    fn deny_by_default() {
+        let decision = PolicySet::default().evaluate(&Request::new("service:call"));
+        assert!(!decision.allowed);
+        assert!(decision.policies.is_empty());
+    }
+
+    #[test]
+    fn forbid_overrides_permit() {
+        let set = PolicySet::new(vec![
+            Policy::permit("everyone", &["*"]),
+            Policy::forbid("no-banned", &["service:*"]).when(
+                "principal.banned",
+                Op::Eq,
+                json!(true),
+            ),
+        ]);
+        let banned = Request::new("service:call").principal(json!({"banned": true}));
+        let decision = set.evaluate(&banned);
+        assert!(!decision.allowed);
+        assert_eq!(decision.policies, vec!["no-banned"]);
+        assert!(
+            set.evaluate(&Request::new("service:call").principal(json!({"banned": false})))
+                .allowed
+        );
+    }
+
+    #[test]
+    fn missing_attributes_do_not_match() {
+        let set = PolicySet::new(vec![
+            Policy::permit("reputable", &["group:join"]).when(
+                "principal.reputation",
+                Op::Gte,
+                json!(0.5),
+            ),
+            Policy::forbid("blocked", &["group:join"]).when(
+                "principal.wallet",
+                Op::In,
+                json!(["bad"]),
+            ),
+        ]);
+        assert!(!set.evaluate(&Request::new("group:join")).allowed);
+        assert!(
+            set.evaluate(&Request::new("group:join").principal(json!({"reputation": 1})))
+                .allowed
+        );
+    }
+
+    #[test]
+    fn compares_against_other_attributes() {
+        let set = PolicySet::new(vec![Policy::permit("owner", &["service:manage"]).when(
+            "principal.wallet",
+            Op::Eq,
+            json!({"attr": "resource.owner"}),
+        )]);
+        let request = Request::new("service:manage").resource(json!({"owner": "w1"}));
+        assert!(
+            set.evaluate(&request.clone().principal(json!({"wallet": "w1"})))
+                .allowed
+        );
+        assert!(
+            !set.evaluate(&request.principal(json!({"wallet": "w2"})))
+                .allowed
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_policies() {
+        let duplicate = r#"[{"id": "a", "effect": "permit", "actions": ["*"]},
+                            {"id": "a", "effect": "forbid", "actions": ["*"]}]"#;
+        assert!(PolicySet::from_json(duplicate).is_err());
+        let unrooted = r#"[{"id": "a", "effect": "permit", "actions": ["*"],
+                            "when": [{"attr": "wallet", "op": "eq", "value": "w"}]}]"#;
+        assert!(PolicySet::from_json(unrooted).is_err());
+        let unknown_op = r#"[{"id": "a", "effect": "permit", "actions": ["*"],
+                              "when": [{"attr": "principal.x", "op": "like"}]}]"#;
+        assert!(PolicySet::from_json(unknown_op).is_err());
+    }
+}
@@ -0,0 +1,104 @@
+use crate::receipts::json_response;
+use crate::{HttpResponse, PublicGateway};
+use zos_policy::{Policy, PolicySet, Request};
+
+/// Built-in: every service call; operators narrow it with forbids
+pub fn builtin() -> PolicySet {
+    PolicySet::new(vec![Policy::permit("service-calls", &["service:call"])])
+}
+
+/// Operator policies from ZOS_GATEWAY_POLICY_FILE, read at startup rather
+/// than persisted with the gateway
+pub fn load_operator() -> PolicySet {
+    PolicySet::load(std::env::var("ZOS_GATEWAY_POLICY_FILE").ok().as_deref()).unwrap_or_else(|e| {
+        println!("⚠️  Ignoring operator policies: {}", e);
+        PolicySet::default()
+    })
+}
+
+impl PublicGateway {
+    pub fn effective_policies(&self) -> PolicySet {
+        builtin().with(&self.access_policies)
+    }
+
+    /// The authenticated caller and its tier, the service and its owner. A
+    /// caller that proved nothing is anonymous, whatever wallet it names.
+    fn service_call_request(&self, service_key: &str, caller: Option<&str>) -> Option<Request> {
+        let service = self.service_registry.get(service_key)?;
+        let principal = match caller {
+            Some(wallet) => {
+                let tier = self.commission_system.as_ref()
+                    .and_then(|system| system.earnings_ledger.get(wallet))
+                    .map(|account| format!("{:?}", account.tier));
+                serde_json::json!({ "wallet": wallet, "tier": tier })
+            }
+            None => serde_json::json!({}),
+        };
+        Some(Request::new("service:call")
+            .principal(principal)
+            .resource(serde_json::json!({
+                "service_key": service_key,
+                "service_name": service.service_name,
+                "owner": service.wallet_address,
+                "payment_required": service.payment_required,
+                "auth_required": service.auth_required,
+            }))
+            .context(serde_json::json!({ "sandbox": self.sandbox_mode })))
+    }
+
+    /// 403 when the effective policies refuse the call
+    pub(crate) fn check_access_policies(&self, service_key: &str, caller: Option<&str>) -> Option<HttpResponse> {
+        let request = self.service_call_request(service_key, caller)?;
+        let decision = self.effective_policies().evaluate(&request);
+        if decision.allowed {
+            return None;
+        }
+        json_response(403, &serde_json::json!({
+            "error": decision.reason,
+            "policies": decision.policies,
+        })).ok()
+    }
+
+    /// GET /api/policies
+    pub(crate) fn handle_policies_request(&self) -> Result<HttpResponse, String> {
+        json_response(200, &serde_json::json!({
+            "builtin": builtin(),
+            "operator": self.access_policies,
+        }))
+    }
+
+    /// POST /api/policies/evaluate: the decision a request would get, for
+    /// checking policies before relying on them
+    pub(crate) fn handle_policy_evaluation(&self, body: &[u8]) -> Result<HttpResponse, String> {
+        let request: Request = serde_json::from_slice(body)
+            .map_err(|e| format!("Invalid policy request: {}", e))?;
+        json_response(200, &self.effective_policies().evaluate(&request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PricingTier;
+    use zos_policy::Op;
+
+    #[test]
+    fn test_policies_see_only_a_proven_caller() {
+        let mut gateway = PublicGateway::new("gateway.test");
+        gateway.register_wallet_endpoint("owner1", "owner", vec![4001]).unwrap();
+        gateway.add_service("owner1", "api", 4001, PricingTier::Basic).unwrap();
+        gateway.access_policies = PolicySet::new(vec![
+            Policy::forbid("members-only", &["service:call"])
+                .unless("principal.wallet", Op::Eq, serde_json::json!("member1")),
+        ]);
+
+        assert!(gateway.check_access_policies("owner1_api", None).is_some());
+        assert!(gateway.check_access_policies("owner1_api", Some("stranger")).is_some());
+        assert!(gateway.check_access_policies("owner1_api", Some("member1")).is_none());
+
+        // Naming the member is not being the member
+        let spoofed = std::collections::HashMap::from([("X-Wallet-Address".to_string(), "member1".to_string())]);
+        let refusal = gateway.authorize_service_call("owner1", "api", &spoofed).unwrap().err().unwrap();
+        assert_eq!(refusal.status_code, 403);
+    }
+}
@@ -0,0 +1,198 @@
+use serde::{Deserialize, Serialize};
+use crate::{CommissionPayment, HttpResponse, PublicGateway};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AccountingLedger {
+    pub price_history: HashMap<String, Vec<PricePoint>>, // token -> points, oldest first
+    pub snapshots: HashMap<String, Vec<EarningsSnapshot>>, // wallet -> closed periods
+    pub closed_periods: Vec<(u64, u64)>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PricePoint {
+    pub timestamp: u64,
+    pub usd_price: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EarningsSnapshot {
+    pub wallet_address: String,
+    pub period_start: u64,
+    pub period_end: u64,
+    pub closed_at: u64,
+    pub total_usd: f64,
+    pub usd_by_type: HashMap<String, f64>,
+    pub entries: Vec<SnapshotEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotEntry {
+    pub payment_id: String,
+    pub timestamp: u64,
+    pub commission_type: String,
+    pub token: String,
+    pub amount: f64,
+    pub usd_price: f64,
+    pub usd_value: f64,
+    pub source_transaction: String,
+}
+
+impl PublicGateway {
+    /// Feed the price oracle; points must arrive in time order per token
+    pub fn record_token_price(&mut self, token: &str, usd_price: f64, timestamp: u64) {
+        self.accounting.price_history
+            .entry(token.to_string())
+            .or_default()
+            .push(PricePoint { timestamp, usd_price });
+    }
+
+    /// USD price of a token at a moment: stablecoins are pegged, others use the
+    /// last oracle point at or before `timestamp` (or the first one after it)
+    pub fn usd_price_at(&self, token: &str, timestamp: u64) -> Option<f64> {
+        let pegged = self.payment_processor.supported_tokens.iter()
+            .any(|config| config.symbol == token && config.is_stablecoin);
+        if pegged {
+            return Some(1.0);
+        }
+
+        let points = self.accounting.price_history.get(token)?;
+        points.iter()
+            .rev()
+            .find(|point| point.timestamp <= timestamp)
+            .or_else(|| points.first())
+            .map(|point| point.usd_price)
+    }
+
+    fn snapshot_entry(&self, payment: &CommissionPayment) -> Result<SnapshotEntry, String> {
+        let usd_price = self.usd_price_at(&payment.token, payment.timestamp)
+            .ok_or(format!("No USD price for {} at {}", payment.token, payment.timestamp))?;
+
+        Ok(SnapshotEntry {
+            payment_id: payment.payment_id.clone(),
+            timestamp: payment.timestamp,
+            commission_type: format!("{:?}", payment.commission_type),
+            token: payment.token.clone(),
+            amount: payment.amount,
+            usd_price,
+            usd_value: payment.amount * usd_price,
+            source_transaction: payment.source_transaction.clone(),
+        })
+    }
+
+    /// Close `[period_start, period_end)` for every earning wallet. Each period
+    /// can only be closed once so snapshots stay immutable.
+    pub fn close_accounting_period(&mut self, period_start: u64, period_end: u64) -> Result<usize, String> {
+        if period_end <= period_start {
+            return Err("Period end must be after start".to_string());
+        }
+
+        let overlaps = self.accounting.closed_periods.iter()
+            .any(|(start, end)| period_start < *end && *start < period_end);
+        if overlaps {
+            return Err("Period overlaps an already closed period".to_string());
+        }
+
+        let commission_system = self.commission_system.as_ref()
+            .ok_or("Commission system not initialized")?;
+
+        let closed_at = chrono::Utc::now().timestamp() as u64;
+        let mut snapshots = Vec::new();
+
+        for (wallet_address, payments) in &commission_system.commission_history {
+            let mut entries = Vec::new();
+            for payment in payments.iter()
+                .filter(|p| p.timestamp >= period_start && p.timestamp < period_end) {
+                entries.push(self.snapshot_entry(payment)?);
+            }
+
+            if entries.is_empty() {
+                continue;
+            }
+
+            let mut usd_by_type: HashMap<String, f64> = HashMap::new();
+            for entry in &entries {
+                *usd_by_type.entry(entry.commission_type.clone()).or_insert(0.0) += entry.usd_value;
+            }
+
+            snapshots.push(EarningsSnapshot {
+                wallet_address: wallet_address.clone(),
+                period_start,
+                period_end,
+                closed_at,
+                total_usd: entries.iter().map(|e| e.usd_value).sum(),
+                usd_by_type,
+                entries,
+            });
+        }
+
+        let count = snapshots.len();
+        for snapshot in snapshots {
+            self.accounting.snapshots
+                .entry(snapshot.wallet_address.clone())
+                .or_default()
+                .push(snapshot);
+        }
+        self.accounting.closed_periods.push((period_start, period_end));
+
+        println!("📒 Closed accounting period {}-{} ({} wallets)", period_start, period_end, count);
+        Ok(count)
+    }
+
+    /// One row per commission payment, for accounting and tax tools
+    pub fn export_earnings_csv(&self, wallet_address: &str, from: u64, to: u64) -> String {
+        let mut csv = String::from("date,payment_id,commission_type,token,amount,usd_price,usd_value,source_transaction\n");
+
+        for snapshot in self.accounting.snapshots.get(wallet_address).into_iter().flatten() {
+            for entry in snapshot.entries.iter().filter(|e| e.timestamp >= from && e.timestamp < to) {
+                let date = chrono::DateTime::from_timestamp(entry.timestamp as i64, 0)
+                    .map(|dt| dt.to_rfc3339())
+                    .unwrap_or_default();
+                csv.push_str(&format!("{},{},{},{},{},{:.6},{:.2},{}\n",
+                    date, entry.payment_id, entry.commission_type, entry.token,
+                    entry.amount, entry.usd_price, entry.usd_value, entry.source_transaction));
+            }
+        }
+
+        csv
+    }
+
+    /// Per-period USD totals by commission type
+    pub fn export_period_summary_csv(&self, wallet_address: &str) -> String {
+        let mut csv = String::from("period_start,period_end,commission_type,usd_total\n");
+
+        for snapshot in self.accounting.snapshots.get(wallet_address).into_iter().flatten() {
+            let mut types: Vec<_> = snapshot.usd_by_type.iter().collect();
+            types.sort_by(|a, b| a.0.cmp(b.0));
+            for (commission_type, total) in types {
+                csv.push_str(&format!("{},{},{},{:.2}\n",
+                    snapshot.period_start, snapshot.period_end, commission_type, total));
+            }
+        }
+
+        csv
+    }
+
+    /// `GET /{wallet}/earnings/{tax.csv|summary.csv}?from=&to=`
+    pub fn handle_accounting_export(&self, wallet_address: &str, report: &str,
+                                    query: &HashMap<String, String>) -> Result<HttpResponse, String> {
+        let from = query.get("from").and_then(|v| v.parse().ok()).unwrap_or(0);
+        let to = query.get("to").and_then(|v| v.parse().ok()).unwrap_or(u64::MAX);
+
+        let body = match report {
+            "tax.csv" => self.export_earnings_csv(wallet_address, from, to),
+            "summary.csv" => self.export_period_summary_csv(wallet_address),
+            _ => return Err(format!("Unknown report: {}", report)),
+        };
+
+        Ok(HttpResponse {
+            status_code: 200,
+            headers: HashMap::from([
+                ("Content-Type".to_string(), "text/csv".to_string()),
+                ("Content-Disposition".to_string(),
+                 format!("attachment; filename=\"{}-{}\"", wallet_address, report)),
+            ]),
+            body: body.into_bytes(),
+        })
+    }
+}
@@ -0,0 +1,153 @@
+use serde::Serialize;
+use crate::SwapPool;
+
+/// A swap priced against a pool's reserves, before it is applied
+#[derive(Debug, Clone, Serialize)]
+pub struct SwapQuote {
+    pub input_amount: f64,
+    pub fee: f64,
+    pub output_amount: f64,
+    pub spot_price: f64,      // output per input at the current reserves
+    pub execution_price: f64, // output per input this trade actually gets
+    pub price_impact: f64,    // %, how far the trade moves off the spot price
+}
+
+impl SwapPool {
+    /// (reserve in, reserve out) when swapping from `from_token`. Pools saved
+    /// before reserves were tracked hold their liquidity split evenly.
+    pub fn reserves_for(&self, from_token: &str) -> Option<(f64, f64)> {
+        let (a, b) = if self.reserve_a > 0.0 && self.reserve_b > 0.0 {
+            (self.reserve_a, self.reserve_b)
+        } else {
+            (self.liquidity / 2.0, self.liquidity / 2.0)
+        };
+        if from_token == self.token_a {
+            Some((a, b))
+        } else if from_token == self.token_b {
+            Some((b, a))
+        } else {
+            None
+        }
+    }
+
+    /// Constant product: the fee comes off the input, then
+    /// out = reserve_out * in / (reserve_in + in), so x * y = k holds
+    pub fn quote_swap(&self, from_token: &str, input_amount: f64) -> Result<SwapQuote, String> {
+        if !(input_amount > 0.0 && input_amount.is_finite()) {
+            return Err("Swap amount must be positive".to_string());
+        }
+        let (reserve_in, reserve_out) = self.reserves_for(from_token)
+            .ok_or(format!("Pool {} does not hold {}", self.pool_id, from_token))?;
+        if reserve_in <= 0.0 || reserve_out <= 0.0 {
+            return Err(format!("Pool {} has no liquidity", self.pool_id));
+        }
+
+        let fee = input_amount * self.fee_percentage / 100.0;
+        let amount_in = input_amount - fee;
+        let output_amount = reserve_out * amount_in / (reserve_in + amount_in);
+        if output_amount <= 0.0 {
+            return Err("Swap amount too small to buy anything".to_string());
+        }
+        let spot_price = reserve_out / reserve_in;
+        let execution_price = output_amount / amount_in;
+
+        Ok(SwapQuote {
+            input_amount,
+            fee,
+            output_amount,
+            spot_price,
+            execution_price,
+            price_impact: (1.0 - execution_price / spot_price) * 100.0,
+        })
+    }
+
+    /// Move the reserves by a quoted swap. The fee is routed to burn and
+    /// treasury rather than left in the pool, so only the rest goes in.
+    pub fn apply_swap(&mut self, from_token: &str, quote: &SwapQuote) {
+        let (reserve_in, reserve_out) = match self.reserves_for(from_token) {
+            Some(reserves) => reserves,
+            None => return,
+        };
+        let reserve_in = reserve_in + quote.input_amount - quote.fee;
+        let reserve_out = reserve_out - quote.output_amount;
+        if from_token == self.token_a {
+            self.reserve_a = reserve_in;
+            self.reserve_b = reserve_out;
+        } else {
+            self.reserve_a = reserve_out;
+            self.reserve_b = reserve_in;
+        }
+        self.liquidity = self.reserve_a + self.reserve_b;
+        self.price_impact = quote.price_impact;
+    }
+}
+
+/// Refuse a swap that moves the price further than the caller allows (%)
+pub fn check_slippage(quote: &SwapQuote, slippage_tolerance: f64) -> Result<(), String> {
+    if !(0.0..=100.0).contains(&slippage_tolerance) {
+        return Err("slippage_tolerance is a percentage between 0 and 100".to_string());
+    }
+    if quote.price_impact > slippage_tolerance {
+        return Err(format!("Price impact {:.4}% exceeds slippage tolerance {}%; trade less or raise the tolerance",
+                           quote.price_impact, slippage_tolerance));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(fee_percentage: f64) -> SwapPool {
+        SwapPool {
+            pool_id: "pool1".to_string(),
+            token_a: "SOLFUNMEME".to_string(),
+            token_b: "USDC".to_string(),
+            liquidity: 2_000.0,
+            fee_percentage,
+            price_impact: 0.0,
+            reserve_a: 1_000.0,
+            reserve_b: 1_000.0,
+        }
+    }
+
+    #[test]
+    fn test_zero_and_dust_input() {
+        // A fee that takes the whole input leaves nothing to trade
+        assert!(pool(100.0).quote_swap("SOLFUNMEME", 1.0).is_err());
+
+        let pool = pool(0.3);
+        assert!(pool.quote_swap("SOLFUNMEME", 0.0).is_err());
+        assert!(pool.quote_swap("SOLFUNMEME", -1.0).is_err());
+        assert!(pool.quote_swap("SOLFUNMEME", f64::NAN).is_err());
+        assert!(pool.quote_swap("DOGE", 1.0).is_err());
+
+        // Dust still trades at no better than the spot price, less the fee
+        let quote = pool.quote_swap("SOLFUNMEME", 1e-9).unwrap();
+        assert!(quote.output_amount > 0.0);
+        assert!(quote.output_amount <= quote.spot_price * (quote.input_amount - quote.fee));
+    }
+
+    #[test]
+    fn test_output_never_reaches_the_reserve() {
+        let pool = pool(0.3);
+        for amount in [1.0, 1_000.0, 1e9, 1e15] {
+            let quote = pool.quote_swap("USDC", amount).unwrap();
+            assert!(quote.output_amount < pool.reserve_a, "{} in drained the pool", amount);
+            assert!(quote.price_impact >= 0.0 && quote.price_impact < 100.0);
+        }
+    }
+
+    #[test]
+    fn test_swap_preserves_the_constant_product() {
+        let mut pool = pool(0.3);
+        let k = pool.reserve_a * pool.reserve_b;
+        for (from_token, amount) in [("SOLFUNMEME", 50.0), ("USDC", 120.0), ("SOLFUNMEME", 3.5)] {
+            let quote = pool.quote_swap(from_token, amount).unwrap();
+            pool.apply_swap(from_token, &quote);
+            assert!(((pool.reserve_a * pool.reserve_b) - k).abs() / k < 1e-9);
+            assert!(pool.reserve_a > 0.0 && pool.reserve_b > 0.0);
+        }
+        assert_eq!(pool.liquidity, pool.reserve_a + pool.reserve_b);
+    }
+}
@@ -0,0 +1,272 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::receipts::json_response;
+use crate::screening::is_operator;
+use crate::{HttpResponse, PricingTier, PublicGateway};
+use std::collections::HashMap;
+
+const CHALLENGE_TTL_SECS: u64 = 300;
+const KEY_PREFIX: &str = "zos_";
+
+fn random_hex(bytes: usize) -> String {
+    (0..bytes).map(|_| format!("{:02x}", rand::random::<u8>())).collect()
+}
+
+fn secret_hash(secret: &str) -> String {
+    Sha256::digest(secret.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// A one-time message for a wallet to sign instead of presenting an API key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthChallenge {
+    pub challenge_id: String,
+    pub wallet_address: String,
+    pub message: String,
+    pub expires_at: u64,
+}
+
+/// An API key acting for a wallet. Only the secret's hash is kept; the
+/// key itself is shown once, when issued.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub key_id: String,
+    pub wallet_address: String,
+    pub label: String,
+    #[serde(skip_serializing_if = "String::is_empty", default)]
+    pub secret_hash: String,
+    pub created_at: u64,
+    pub last_used_at: Option<u64>,
+    pub revoked_at: Option<u64>,
+    pub rotated_to: Option<String>, // key that replaced this one
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ApiKeyStore {
+    pub keys: HashMap<String, ApiKey>, // by key_id
+    #[serde(skip)]
+    pub challenges: HashMap<String, AuthChallenge>, // outstanding, by challenge_id
+}
+
+impl ApiKey {
+    /// Without the secret hash, for listing
+    fn public(&self) -> ApiKey {
+        ApiKey { secret_hash: String::new(), ..self.clone() }
+    }
+}
+
+impl ApiKeyStore {
+    pub fn challenge(&mut self, wallet_address: &str, domain: &str, now: u64) -> AuthChallenge {
+        self.challenges.retain(|_, challenge| challenge.expires_at > now);
+        let challenge_id = random_hex(16);
+        let challenge = AuthChallenge {
+            message: format!("{} wants you to manage services as {}\nChallenge: {}\nIssued: {}",
+                             domain, wallet_address, challenge_id, now),
+            challenge_id: challenge_id.clone(),
+            wallet_address: wallet_address.to_string(),
+            expires_at: now + CHALLENGE_TTL_SECS,
+        };
+        self.challenges.insert(challenge_id, challenge.clone());
+        challenge
+    }
+
+    /// Issue a key for a wallet; returns it with the full key to hand out
+    pub fn issue(&mut self, wallet_address: &str, label: &str, now: u64) -> (ApiKey, String) {
+        let key_id = random_hex(8);
+        let secret = random_hex(32);
+        let key = ApiKey {
+            key_id: key_id.clone(),
+            wallet_address: wallet_address.to_string(),
+            label: label.to_string(),
+            secret_hash: secret_hash(&secret),
+            created_at: now,
+            last_used_at: None,
+            revoked_at: None,
+            rotated_to: None,
+        };
+        self.keys.insert(key_id.clone(), key.clone());
+        (key.public(), format!("{}{}.{}", KEY_PREFIX, key_id, secret))
+    }
+
+    /// The wallet a live API key acts for
+    pub fn verify_key(&mut self, api_key: &str, now: u64) -> Result<String, String> {
+        let (key_id, secret) = api_key.strip_prefix(KEY_PREFIX)
+            .and_then(|rest| rest.split_once('.'))
+            .ok_or("Malformed API key")?;
+        let key = self.keys.get_mut(key_id)
+            .filter(|key| key.revoked_at.is_none() && key.secret_hash == secret_hash(secret))
+            .ok_or("Invalid or revoked API key")?;
+        key.last_used_at = Some(now);
+        Ok(key.wallet_address.clone())
+    }
+
+    /// The wallet that signed an outstanding challenge; each challenge works once
+    pub fn verify_challenge(&mut self, wallet_address: &str, challenge_id: &str, signature: &str,
+                            now: u64) -> Result<String, String> {
+        let challenge = self.challenges.remove(challenge_id)
+            .filter(|challenge| challenge.wallet_address == wallet_address && challenge.expires_at > now)
+            .ok_or("Unknown or expired challenge")?;
+        zos_approvals::verify_wallet_signature(wallet_address, &challenge.message, signature)?;
+        Ok(challenge.wallet_address)
+    }
+
+    pub fn revoke(&mut self, wallet_address: &str, key_id: &str, now: u64) -> Result<ApiKey, String> {
+        let key = self.keys.get_mut(key_id)
+            .filter(|key| key.wallet_address == wallet_address)
+            .ok_or("API key not found")?;
+        if key.revoked_at.is_some() {
+            return Err("API key already revoked".to_string());
+        }
+        key.revoked_at = Some(now);
+        Ok(key.public())
+    }
+
+    /// Replace a key with a new one under the same label; the old key stops
+    /// working at once
+    pub fn rotate(&mut self, wallet_address: &str, key_id: &str, now: u64) -> Result<(ApiKey, String), String> {
+        let label = self.revoke(wallet_address, key_id, now)?.label;
+        let (key, api_key) = self.issue(wallet_address, &label, now);
+        if let Some(old) = self.keys.get_mut(key_id) {
+            old.rotated_to = Some(key.key_id.clone());
+        }
+        Ok((key, api_key))
+    }
+
+    pub fn wallet_keys(&self, wallet_address: &str) -> Vec<ApiKey> {
+        let mut keys: Vec<ApiKey> = self.keys.values()
+            .filter(|key| key.wallet_address == wallet_address)
+            .map(ApiKey::public)
+            .collect();
+        keys.sort_by_key(|key| key.created_at);
+        keys
+    }
+}
+
+fn unauthorized(error: &str) -> Result<HttpResponse, String> {
+    let mut response = json_response(401, &serde_json::json!({ "error": error }))?;
+    response.headers.insert("WWW-Authenticate".to_string(), "Bearer realm=\"zos-gateway\"".to_string());
+    Ok(response)
+}
+
+impl PublicGateway {
+    /// The wallet a management request acts for: `Authorization: Bearer
+    /// zos_...` with an issued API key, or X-Wallet-Address with
+    /// X-Auth-Challenge and X-Wallet-Signature over a challenge from
+    /// POST /auth/challenge
+    pub(crate) fn authenticate_wallet(&mut self, headers: &HashMap<String, String>) -> Result<String, String> {
+        let now = chrono::Utc::now().timestamp() as u64;
+        if let Some(api_key) = headers.get("Authorization").and_then(|value| value.strip_prefix("Bearer ")) {
+            return self.api_keys.verify_key(api_key.trim(), now);
+        }
+        match (headers.get("X-Wallet-Address"), headers.get("X-Auth-Challenge"), headers.get("X-Wallet-Signature")) {
+            (Some(wallet_address), Some(challenge_id), Some(signature)) => {
+                self.api_keys.verify_challenge(wallet_address, challenge_id, signature, now)
+            }
+            _ => Err("Authentication required: a bearer API key or a signed challenge".to_string()),
+        }
+    }
+
+    /// /auth/challenge, /auth/keys[/{id}[/rotate]]: wallet sign-in and API
+    /// key issuance, rotation and revocation
+    pub(crate) fn handle_auth_request(&mut self, path: &str, method: &str, headers: &HashMap<String, String>,
+                                      body: &[u8]) -> Result<HttpResponse, String> {
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        let request: serde_json::Value = if body.is_empty() {
+            serde_json::Value::Null
+        } else {
+            serde_json::from_slice(body).map_err(|e| format!("Invalid auth request: {}", e))?
+        };
+        let now = chrono::Utc::now().timestamp() as u64;
+
+        if let ("POST", ["auth", "challenge"]) = (method, segments.as_slice()) {
+            let wallet_address = request["wallet_address"].as_str().ok_or("wallet_address is required")?;
+            let domain = self.domain.clone();
+            return json_response(200, &self.api_keys.challenge(wallet_address, &domain, now));
+        }
+
+        let wallet_address = match self.authenticate_wallet(headers) {
+            Ok(wallet_address) => wallet_address,
+            Err(e) => return unauthorized(&e),
+        };
+        let (status, response) = match (method, segments.as_slice()) {
+            ("GET", ["auth", "keys"]) => (200, serde_json::json!({ "keys": self.api_keys.wallet_keys(&wallet_address) })),
+            ("POST", ["auth", "keys"]) => {
+                let label = request["label"].as_str().unwrap_or("default");
+                let (key, api_key) = self.api_keys.issue(&wallet_address, label, now);
+                println!("🔑 API key {} issued to {}", key.key_id, wallet_address);
+                (201, serde_json::json!({ "key": key, "api_key": api_key }))
+            }
+            ("POST", ["auth", "keys", key_id, "rotate"]) => match self.api_keys.rotate(&wallet_address, key_id, now) {
+                Ok((key, api_key)) => {
+                    println!("🔑 API key {} rotated to {} for {}", key_id, key.key_id, wallet_address);
+                    (200, serde_json::json!({ "key": key, "api_key": api_key, "revoked": key_id }))
+                }
+                Err(e) => (404, serde_json::json!({ "error": e })),
+            },
+            ("DELETE", ["auth", "keys", key_id]) => match self.api_keys.revoke(&wallet_address, key_id, now) {
+                Ok(key) => {
+                    println!("🔑 API key {} revoked by {}", key_id, wallet_address);
+                    (200, serde_json::json!({ "key": key }))
+                }
+                Err(e) => (404, serde_json::json!({ "error": e })),
+            },
+            _ => return Err("Unsupported auth request".to_string()),
+        };
+        json_response(status, &response)
+    }
+
+    /// POST /manage/endpoints and POST /manage/services: register a wallet
+    /// endpoint or add a service to it, as that wallet (or the operator)
+    pub(crate) fn handle_management_request(&mut self, path: &str, method: &str, headers: &HashMap<String, String>,
+                                            body: &[u8]) -> Result<HttpResponse, String> {
+        let request: serde_json::Value = serde_json::from_slice(body)
+            .map_err(|e| format!("Invalid management request: {}", e))?;
+        let wallet_address = request["wallet_address"].as_str().ok_or("wallet_address is required")?;
+
+        if !is_operator(headers) {
+            match self.authenticate_wallet(headers) {
+                Ok(caller) if caller == wallet_address => {}
+                Ok(_) => return json_response(403, &serde_json::json!({ "error": "Credentials are for another wallet" })),
+                Err(e) => return unauthorized(&e),
+            }
+        }
+
+        let result = match (method, path.trim_end_matches('/')) {
+            ("POST", "/manage/endpoints") => {
+                let user_id = request["user_id"].as_str().ok_or("user_id is required")?;
+                let allocated_ports: Vec<u16> = serde_json::from_value(request["allocated_ports"].clone())
+                    .map_err(|e| format!("allocated_ports: {}", e))?;
+                self.register_wallet_endpoint(wallet_address, user_id, allocated_ports)
+                    .map(|url| serde_json::json!({ "endpoint": url }))
+            }
+            ("POST", "/manage/services") => {
+                let service_name = request["service_name"].as_str().ok_or("service_name is required")?;
+                let libp2p_port = request["libp2p_port"].as_u64()
+                    .and_then(|port| u16::try_from(port).ok())
+                    .ok_or("libp2p_port is required")?;
+                let pricing_tier: PricingTier = serde_json::from_value(request["pricing_tier"].clone())
+                    .unwrap_or(PricingTier::Free);
+                self.add_service(wallet_address, service_name, libp2p_port, pricing_tier)
+                    .map(|url| serde_json::json!({ "service": url }))
+            }
+            _ => return Err("Unsupported management request".to_string()),
+        };
+
+        match result {
+            Ok(response) => json_response(201, &response),
+            Err(e) => json_response(409, &serde_json::json!({ "error": e })),
+        }
+    }
+
+    /// POST /{wallet}/register: the older spelling of POST /manage/services,
+    /// with the wallet in the path and the same credentials required
+    pub(crate) fn handle_wallet_register_request(&mut self, wallet_address: &str, headers: &HashMap<String, String>,
+                                                 body: &[u8]) -> Result<HttpResponse, String> {
+        let mut request: serde_json::Value = serde_json::from_slice(body)
+            .map_err(|e| format!("Invalid management request: {}", e))?;
+        request.as_object_mut()
+            .ok_or("A registration must be a JSON object")?
+            .insert("wallet_address".to_string(), serde_json::json!(wallet_address));
+        let body = serde_json::to_vec(&request).map_err(|e| format!("Failed to serialize request: {}", e))?;
+        self.handle_management_request("/manage/services", "POST", headers, &body)
+    }
+}
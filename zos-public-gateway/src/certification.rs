@@ -0,0 +1,265 @@
+use serde::{Deserialize, Serialize};
+use crate::health::{HealthState, OwnerNotification};
+use crate::receipts::json_response;
+use crate::{HttpResponse, PublicGateway};
+use std::collections::{HashMap, VecDeque};
+
+const DAY_SECS: u64 = 86_400;
+const LATENCY_SAMPLES: usize = 1000; // most recent calls the latency check looks at
+
+/// What a service must show to be certified
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CertificationPolicy {
+    pub window_days: u64,
+    pub uptime_sla: f64, // share of health probes that passed over the window
+    pub latency_budget_ms: u64, // p95 of recent calls
+    pub min_latency_samples: usize,
+    pub required_headers: Vec<String>, // on the backend's health check responses
+    pub evaluate_interval_secs: u64,
+}
+
+impl Default for CertificationPolicy {
+    fn default() -> Self {
+        Self {
+            window_days: 30,
+            uptime_sla: 0.995,
+            latency_budget_ms: 500,
+            min_latency_samples: 50,
+            required_headers: vec![
+                "X-Content-Type-Options".to_string(),
+                "X-Frame-Options".to_string(),
+                "Content-Security-Policy".to_string(),
+            ],
+            evaluate_interval_secs: 3600,
+        }
+    }
+}
+
+/// Health probes passed and run on one UTC day
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UptimeDay {
+    pub day: u64, // unix time the day starts
+    pub passed: u32,
+    pub probes: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CertificationCheck {
+    pub name: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+/// Marketplace badge for a service that passed every check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CertificationBadge {
+    pub badge: String,
+    pub awarded_at: u64,
+    pub uptime_pct: f64,
+    pub p95_latency_ms: u64,
+}
+
+/// Evidence gathered for a service's certification, and its current standing
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ServiceCertification {
+    pub uptime: VecDeque<UptimeDay>, // oldest first
+    pub latencies_ms: VecDeque<u64>, // oldest first
+    pub probe_headers: Vec<String>, // header names on the last HTTP probe response
+    pub badge: Option<CertificationBadge>,
+    pub checks: Vec<CertificationCheck>,
+    pub last_evaluated: u64,
+    pub revoked_at: Option<u64>,
+}
+
+impl ServiceCertification {
+    pub(crate) fn record_probe(&mut self, passed: bool, now: u64, window_days: u64) {
+        let day = now - now % DAY_SECS;
+        match self.uptime.back_mut() {
+            Some(today) if today.day == day => {
+                today.probes += 1;
+                today.passed += passed as u32;
+            }
+            _ => self.uptime.push_back(UptimeDay { day, passed: passed as u32, probes: 1 }),
+        }
+        let cutoff = day.saturating_sub(window_days * DAY_SECS);
+        while self.uptime.front().is_some_and(|oldest| oldest.day < cutoff) {
+            self.uptime.pop_front();
+        }
+    }
+
+    pub(crate) fn record_latency(&mut self, latency_ms: u64) {
+        self.latencies_ms.push_back(latency_ms);
+        while self.latencies_ms.len() > LATENCY_SAMPLES {
+            self.latencies_ms.pop_front();
+        }
+    }
+
+    fn p95_latency_ms(&self) -> Option<u64> {
+        let mut sorted: Vec<u64> = self.latencies_ms.iter().copied().collect();
+        sorted.sort_unstable();
+        let index = (sorted.len() * 95).div_ceil(100).checked_sub(1)?;
+        sorted.get(index).copied()
+    }
+
+    /// Uptime over the window, if probes cover all of it
+    fn uptime_over(&self, window_days: u64, now: u64) -> Result<f64, String> {
+        let window_start = (now - now % DAY_SECS).saturating_sub(window_days * DAY_SECS);
+        let covered = self.uptime.front().is_some_and(|oldest| oldest.day <= window_start);
+        if !covered {
+            let days = self.uptime.len();
+            return Err(format!("{} of {} days of health probes", days, window_days));
+        }
+        let (passed, probes) = self.uptime.iter()
+            .filter(|day| day.day >= window_start)
+            .fold((0u64, 0u64), |(passed, probes), day| (passed + day.passed as u64, probes + day.probes as u64));
+        Ok(if probes == 0 { 0.0 } else { passed as f64 / probes as f64 })
+    }
+}
+
+impl PublicGateway {
+    /// Response headers the node saw on a service's last HTTP health probe
+    pub fn record_probe_headers(&mut self, service_key: &str, headers: &HashMap<String, String>) -> Result<(), String> {
+        let service = self.service_registry.get_mut(service_key)
+            .ok_or("Service not found")?;
+        service.certification.probe_headers = headers.keys().map(|name| name.to_ascii_lowercase()).collect();
+        Ok(())
+    }
+
+    fn certification_checks(&self, service_key: &str, now: u64) -> Option<(Vec<CertificationCheck>, f64, u64)> {
+        let service = self.service_registry.get(service_key)?;
+        let policy = &self.certification_policy;
+        let certification = &service.certification;
+        let check = |name: &str, passed: bool, detail: String| CertificationCheck { name: name.to_string(), passed, detail };
+
+        let uptime = certification.uptime_over(policy.window_days, now);
+        let uptime_pct = uptime.as_ref().map(|uptime| uptime * 100.0).unwrap_or(0.0);
+        let p95 = certification.p95_latency_ms();
+        let missing_headers: Vec<&str> = policy.required_headers.iter()
+            .filter(|header| !certification.probe_headers.contains(&header.to_ascii_lowercase()))
+            .map(String::as_str)
+            .collect();
+        let contract = service.contract.as_ref()
+            .filter(|contract| contract.request_schema.is_some() && contract.response_schema.is_some());
+
+        let checks = vec![
+            check("listed", service.health.state != HealthState::Delisted, format!("Health {:?}", service.health.state)),
+            match &uptime {
+                Ok(uptime) => check("uptime_sla", *uptime >= policy.uptime_sla,
+                                    format!("{:.2}% over {} days (needs {:.2}%)", uptime * 100.0, policy.window_days, policy.uptime_sla * 100.0)),
+                Err(coverage) => check("uptime_sla", false, coverage.clone()),
+            },
+            check("schema_contract", contract.is_some(), match contract {
+                Some(_) => "Request and response schemas attached".to_string(),
+                None => "Needs both a request and a response schema".to_string(),
+            }),
+            check("security_headers", missing_headers.is_empty(), if missing_headers.is_empty() {
+                format!("{} present", policy.required_headers.join(", "))
+            } else {
+                format!("Missing on health check responses: {}", missing_headers.join(", "))
+            }),
+            match p95 {
+                Some(p95) if certification.latencies_ms.len() >= policy.min_latency_samples => {
+                    check("latency_budget", p95 <= policy.latency_budget_ms,
+                          format!("p95 {}ms over the last {} calls (budget {}ms)", p95, certification.latencies_ms.len(), policy.latency_budget_ms))
+                }
+                _ => check("latency_budget", false, format!("{} of {} calls measured", certification.latencies_ms.len(), policy.min_latency_samples)),
+            },
+        ];
+        Some((checks, uptime_pct, p95.unwrap_or(0)))
+    }
+
+    /// Run the checks for every service: a badge for those passing them
+    /// all, taken away from any that stopped. Returns a notice for each
+    /// owner whose badge changed.
+    pub fn evaluate_certifications(&mut self, now: u64) -> Vec<OwnerNotification> {
+        let service_keys: Vec<String> = self.service_registry.keys().cloned().collect();
+        let mut notices = Vec::new();
+        for service_key in service_keys {
+            let Some((checks, uptime_pct, p95_latency_ms)) = self.certification_checks(&service_key, now) else { continue };
+            let Some(service) = self.service_registry.get_mut(&service_key) else { continue };
+            let passed = checks.iter().all(|check| check.passed);
+            let failed: Vec<String> = checks.iter().filter(|check| !check.passed).map(|check| check.name.clone()).collect();
+            let certification = &mut service.certification;
+            certification.checks = checks;
+            certification.last_evaluated = now;
+
+            let message = match (&mut certification.badge, passed) {
+                (Some(badge), true) => {
+                    badge.uptime_pct = uptime_pct;
+                    badge.p95_latency_ms = p95_latency_ms;
+                    None
+                }
+                (None, true) => {
+                    certification.badge = Some(CertificationBadge {
+                        badge: "zos-certified".to_string(),
+                        awarded_at: now,
+                        uptime_pct,
+                        p95_latency_ms,
+                    });
+                    certification.revoked_at = None;
+                    Some(format!("{} is now certified", service.service_name))
+                }
+                (Some(_), false) => {
+                    certification.badge = None;
+                    certification.revoked_at = Some(now);
+                    Some(format!("{} lost its certification: {} no longer passing", service.service_name, failed.join(", ")))
+                }
+                (None, false) => None,
+            };
+            if let Some(message) = message {
+                println!("🏅 {}", message);
+                notices.push(OwnerNotification {
+                    wallet_address: service.wallet_address.clone(),
+                    service_name: service.service_name.clone(),
+                    message,
+                    timestamp: now,
+                });
+            }
+        }
+        notices
+    }
+
+    /// GET /certifications (badged services) and
+    /// GET /certifications/{wallet}/{service} (checks, evidence and badge)
+    pub fn handle_certification_request(&self, path: &str, method: &str) -> Result<HttpResponse, String> {
+        if method != "GET" {
+            return Err("Unsupported certification request".to_string());
+        }
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        match segments.as_slice() {
+            ["certifications"] => {
+                let certified: Vec<serde_json::Value> = self.service_registry.values()
+                    .filter_map(|service| service.certification.badge.as_ref().map(|badge| serde_json::json!({
+                        "wallet_address": service.wallet_address,
+                        "service_name": service.service_name,
+                        "badge": badge,
+                    })))
+                    .collect();
+                json_response(200, &serde_json::json!({
+                    "policy": self.certification_policy,
+                    "certified": certified,
+                }))
+            }
+            ["certifications", wallet_address, service_name] => {
+                match self.service_registry.get(&format!("{}_{}", wallet_address, service_name)) {
+                    Some(service) => {
+                        let certification = &service.certification;
+                        json_response(200, &serde_json::json!({
+                            "service_key": format!("{}_{}", wallet_address, service_name),
+                            "badge": certification.badge,
+                            "checks": certification.checks,
+                            "last_evaluated": certification.last_evaluated,
+                            "revoked_at": certification.revoked_at,
+                            "uptime": certification.uptime,
+                            "probe_headers": certification.probe_headers,
+                            "p95_latency_ms": certification.p95_latency_ms(),
+                            "latency_samples": certification.latencies_ms.len(),
+                        }))
+                    }
+                    None => json_response(404, &serde_json::json!({ "error": "Service not found" })),
+                }
+            }
+            _ => Err("Unsupported certification request".to_string()),
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+use crate::receipts::json_response;
+use crate::screening::is_operator;
+use crate::{HttpResponse, PublicGateway};
+use std::collections::HashMap;
+
+/// Per-wallet request counters shared across federated nodes as grow-only
+/// CRDT counters: each node only increments its own slot, merges take the
+/// max per slot, so gossip can arrive late, twice or out of order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterRateLimiter {
+    pub node_id: String,
+    pub enabled: bool,
+    pub partition_timeout_secs: u64, // peers silent this long no longer count
+    pub peers: HashMap<String, u64>, // node_id -> last gossip received
+    pub counters: HashMap<String, HashMap<String, u32>>, // window key -> node_id -> count
+}
+
+impl Default for ClusterRateLimiter {
+    fn default() -> Self {
+        Self {
+            node_id: String::new(),
+            enabled: false,
+            partition_timeout_secs: 30,
+            peers: HashMap::new(),
+            counters: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CounterGossip {
+    pub node_id: String,
+    pub sent_at: u64,
+    pub counters: HashMap<String, HashMap<String, u32>>,
+}
+
+fn window_key(wallet_address: &str, window: &str, start: u64) -> String {
+    format!("{}|{}|{}", wallet_address, window, start)
+}
+
+fn window_start(key: &str) -> Option<(&str, u64)> {
+    let mut parts = key.rsplitn(3, '|');
+    let start = parts.next()?.parse().ok()?;
+    let window = parts.next()?;
+    Some((window, start))
+}
+
+impl ClusterRateLimiter {
+    /// True when at least one peer has gossiped recently. Without that we
+    /// cannot see the rest of the cluster and fall back to local limits.
+    pub fn is_connected(&self, now: u64) -> bool {
+        self.enabled && self.peers.values()
+            .any(|last_seen| now.saturating_sub(*last_seen) <= self.partition_timeout_secs)
+    }
+
+    fn total(&self, key: &str) -> u32 {
+        self.counters.get(key)
+            .map(|slots| slots.values().sum())
+            .unwrap_or(0)
+    }
+
+    /// Cluster-wide (minute, hour) request counts for a wallet
+    pub fn cluster_usage(&self, wallet_address: &str, now: u64) -> (u32, u32) {
+        (self.total(&window_key(wallet_address, "m", now / 60)),
+         self.total(&window_key(wallet_address, "h", now / 3600)))
+    }
+
+    pub fn record_local(&mut self, wallet_address: &str, now: u64) {
+        for key in [window_key(wallet_address, "m", now / 60), window_key(wallet_address, "h", now / 3600)] {
+            *self.counters.entry(key)
+                .or_default()
+                .entry(self.node_id.clone())
+                .or_insert(0) += 1;
+        }
+    }
+
+    pub fn merge(&mut self, gossip: &CounterGossip, now: u64) {
+        if gossip.node_id == self.node_id {
+            return;
+        }
+        self.peers.insert(gossip.node_id.clone(), now);
+
+        for (key, slots) in &gossip.counters {
+            let local = self.counters.entry(key.clone()).or_default();
+            for (node_id, count) in slots {
+                let slot = local.entry(node_id.clone()).or_insert(0);
+                *slot = (*slot).max(*count);
+            }
+        }
+        self.prune(now);
+    }
+
+    /// Drop windows that have closed
+    fn prune(&mut self, now: u64) {
+        self.counters.retain(|key, _| match window_start(key) {
+            Some(("m", start)) => start >= now / 60,
+            Some(("h", start)) => start >= now / 3600,
+            _ => false,
+        });
+    }
+
+    pub fn gossip(&mut self, now: u64) -> CounterGossip {
+        self.prune(now);
+        CounterGossip {
+            node_id: self.node_id.clone(),
+            sent_at: now,
+            counters: self.counters.clone(),
+        }
+    }
+}
+
+impl PublicGateway {
+    pub fn enable_cluster_rate_limits(&mut self, node_id: &str) {
+        self.cluster_limiter.node_id = node_id.to_string();
+        self.cluster_limiter.enabled = true;
+        println!("🌐 Cluster rate limiting enabled as {}", node_id);
+    }
+
+    /// Counters to gossip to peers over the libp2p bridge
+    pub fn rate_limit_gossip(&mut self) -> CounterGossip {
+        self.cluster_limiter.gossip(chrono::Utc::now().timestamp() as u64)
+    }
+
+    /// POST /cluster/rate-limits: merge a trusted peer's counters and answer
+    /// with ours, so one exchange syncs both sides. Peers share the operator key.
+    pub fn handle_rate_limit_gossip(&mut self, headers: &HashMap<String, String>,
+                                    body: &[u8]) -> Result<HttpResponse, String> {
+        if !is_operator(headers) {
+            return json_response(403, &serde_json::json!({ "error": "Needs X-Operator-Key" }));
+        }
+        let gossip: CounterGossip = serde_json::from_slice(body)
+            .map_err(|e| format!("Invalid gossip: {}", e))?;
+        if !self.is_trusted_sync_peer(&gossip.node_id) {
+            return json_response(403, &serde_json::json!({ "error": format!("{} is not a trusted peer", gossip.node_id) }));
+        }
+
+        if !self.cluster_limiter.enabled {
+            return Err("Cluster rate limiting is not enabled".to_string());
+        }
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        self.cluster_limiter.merge(&gossip, now);
+
+        let response_body = serde_json::to_vec(&self.cluster_limiter.gossip(now))
+            .map_err(|e| format!("Failed to serialize gossip: {}", e))?;
+
+        Ok(HttpResponse {
+            status_code: 200,
+            headers: HashMap::from([
+                ("Content-Type".to_string(), "application/json".to_string()),
+            ]),
+            body: response_body,
+        })
+    }
+}
@@ -0,0 +1,202 @@
+use serde::{Deserialize, Serialize};
+use zos_archive::{Archive, ArchiveStore, SegmentMeta, MAX_SEGMENT_RECORDS};
+use crate::receipts::json_response;
+use crate::refunds::MAX_DISPUTE_WINDOW_SECS;
+use crate::screening::is_operator;
+use crate::{CommissionPayment, HttpResponse, PaymentRecord, PublicGateway};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivalConfig {
+    pub retention_days: u64, // records older than this leave the hot store
+    pub interval_secs: u64,
+}
+
+impl ArchivalConfig {
+    /// ZOS_ARCHIVE_RETENTION_DAYS and ZOS_ARCHIVE_INTERVAL_SECS; segments
+    /// go where ZOS_ARCHIVE_LOCATION points
+    pub fn load() -> Self {
+        let env = |name: &str, default: u64| {
+            std::env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+        };
+        Self {
+            retention_days: env("ZOS_ARCHIVE_RETENTION_DAYS", 400).max(1), // past a full tax year
+            interval_secs: env("ZOS_ARCHIVE_INTERVAL_SECS", 86_400).max(60),
+        }
+    }
+}
+
+/// A payment record with the history key it was filed under
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedPayment {
+    pub key: String,
+    pub record: PaymentRecord,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ArchivalReport {
+    pub commissions: usize,
+    pub payments: usize,
+    pub segments: Vec<SegmentMeta>,
+}
+
+/// Records older than `cutoff` in a keyed history, with their keys, oldest first
+fn take_old<T: Clone>(history: &HashMap<String, Vec<T>>, cutoff: u64,
+                      timestamp: impl Fn(&T) -> u64) -> Vec<(String, T)> {
+    let mut old: Vec<(String, T)> = history.iter()
+        .flat_map(|(key, records)| records.iter()
+            .filter(|record| timestamp(record) < cutoff)
+            .map(move |record| (key.clone(), record.clone())))
+        .collect();
+    old.sort_by_key(|(_, record)| timestamp(record));
+    old
+}
+
+impl PublicGateway {
+    /// Move commission and payment records past retention into archive
+    /// segments. Each segment is dropped from the hot store only once the
+    /// archive has it, so a failure part way leaves nothing lost or doubled.
+    /// Records a refund could still claw back stay whatever the retention.
+    pub fn archive_ledgers(&mut self, store: &mut dyn ArchiveStore, now: u64) -> Result<ArchivalReport, String> {
+        let cutoff = now.saturating_sub(self.archival.retention_days * 86_400)
+            .min(now.saturating_sub(MAX_DISPUTE_WINDOW_SECS));
+        let mut report = ArchivalReport::default();
+
+        if let Some(system) = self.commission_system.as_mut() {
+            let old = take_old(&system.commission_history, cutoff, |payment: &CommissionPayment| payment.timestamp);
+            for chunk in old.chunks(MAX_SEGMENT_RECORDS) {
+                let payments: Vec<&CommissionPayment> = chunk.iter().map(|(_, payment)| payment).collect();
+                let meta = system.commission_archive.seal(store, "commissions", &payments,
+                                                          |payment| payment.timestamp, now)?;
+                let sealed: HashSet<&str> = chunk.iter().map(|(_, payment)| payment.payment_id.as_str()).collect();
+                for history in system.commission_history.values_mut() {
+                    history.retain(|kept| !sealed.contains(kept.payment_id.as_str()));
+                }
+                report.commissions += chunk.len();
+                report.segments.push(meta);
+            }
+            system.commission_history.retain(|_, history| !history.is_empty());
+        }
+
+        let processor = &mut self.payment_processor;
+        let old = take_old(&processor.payment_history, cutoff, |payment: &PaymentRecord| payment.timestamp);
+        for chunk in old.chunks(MAX_SEGMENT_RECORDS) {
+            let payments: Vec<ArchivedPayment> = chunk.iter()
+                .map(|(key, record)| ArchivedPayment { key: key.clone(), record: record.clone() })
+                .collect();
+            let meta = processor.payment_archive.seal(store, "payments", &payments,
+                                                      |payment| payment.record.timestamp, now)?;
+            let sealed: HashSet<&str> = chunk.iter().map(|(_, payment)| payment.payment_id.as_str()).collect();
+            for history in processor.payment_history.values_mut() {
+                history.retain(|kept| !sealed.contains(kept.payment_id.as_str()));
+            }
+            report.payments += chunk.len();
+            report.segments.push(meta);
+        }
+        processor.payment_history.retain(|_, history| !history.is_empty());
+
+        Ok(report)
+    }
+
+    fn commission_archive(&self) -> Archive {
+        self.commission_system.as_ref()
+            .map(|system| system.commission_archive.clone())
+            .unwrap_or_default()
+    }
+
+    /// `/archive...`: status, chain verification, slow reads of archived
+    /// records, and an operator-triggered run
+    pub(crate) fn handle_archive_request(&mut self, path: &str, method: &str,
+                                         headers: &HashMap<String, String>) -> Result<HttpResponse, String> {
+        let rest = path.trim_start_matches("/archive").trim_matches('/');
+        let mut store = zos_archive::store_from_env()?;
+        let commissions = self.commission_archive();
+        let payments = self.payment_processor.payment_archive.clone();
+
+        match (method, rest) {
+            ("GET", "") => {
+                let stream = |archive: &Archive| serde_json::json!({
+                    "segments": archive.segments.len(),
+                    "records": archive.records(),
+                    "head_hash": archive.head_hash(),
+                    "oldest": archive.segments.first().map(|segment| segment.from_ts),
+                    "newest": archive.segments.last().map(|segment| segment.to_ts),
+                });
+                json_response(200, &serde_json::json!({
+                    "location": store.location(),
+                    "retention_days": self.archival.retention_days,
+                    "commissions": stream(&commissions),
+                    "payments": stream(&payments),
+                }))
+            }
+            ("GET", "verify") => {
+                let check = |archive: &Archive| match archive.verify(store.as_ref()) {
+                    Ok(segments) => serde_json::json!({ "ok": true, "segments": segments }),
+                    Err(e) => serde_json::json!({ "ok": false, "error": e }),
+                };
+                let (commissions, payments) = (check(&commissions), check(&payments));
+                let status = if commissions["ok"] == true && payments["ok"] == true { 200 } else { 409 };
+                json_response(status, &serde_json::json!({ "commissions": commissions, "payments": payments }))
+            }
+            ("POST", "run") => {
+                if !is_operator(headers) {
+                    return json_response(403, &serde_json::json!({ "error": "Operator key required" }));
+                }
+                let now = chrono::Utc::now().timestamp() as u64;
+                json_response(200, &self.archive_ledgers(store.as_mut(), now)?)
+            }
+            ("GET", rest) => match rest.split_once('/') {
+                // A wallet's archived history is its own, or the operator's
+                Some(("commissions" | "payments", wallet_address)) if !is_operator(headers)
+                    && self.authenticate_wallet(headers).ok().as_deref() != Some(wallet_address) => {
+                    json_response(403, &serde_json::json!({ "error": "Archived records are readable by their wallet or the operator" }))
+                }
+                Some(("commissions", wallet_address)) => {
+                    let found: Vec<CommissionPayment> = commissions.query(store.as_ref(), 0, u64::MAX,
+                        |payment: &CommissionPayment| payment.recipient_wallet == wallet_address)?;
+                    json_response(200, &serde_json::json!({ "wallet_address": wallet_address, "commissions": found }))
+                }
+                Some(("payments", wallet_address)) => {
+                    let found: Vec<ArchivedPayment> = payments.query(store.as_ref(), 0, u64::MAX,
+                        |payment: &ArchivedPayment| payment.record.payer_wallet == wallet_address)?;
+                    let found: Vec<PaymentRecord> = found.into_iter().map(|payment| payment.record).collect();
+                    json_response(200, &serde_json::json!({ "wallet_address": wallet_address, "payments": found }))
+                }
+                _ => json_response(404, &serde_json::json!({ "error": "Unknown archive stream" })),
+            },
+            _ => json_response(405, &serde_json::json!({ "error": "Method not allowed" })),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archived_records_are_read_by_their_wallet_only() {
+        let location = std::env::temp_dir().join(format!("zos-archive-test-{}", std::process::id()));
+        std::env::set_var("ZOS_ARCHIVE_LOCATION", &location);
+        let mut gateway = PublicGateway::new("gateway.test");
+        let now = chrono::Utc::now().timestamp() as u64;
+        let (_, owner_key) = gateway.api_keys.issue("wallet1", "test", now);
+        let (_, other_key) = gateway.api_keys.issue("wallet2", "test", now);
+        let bearer = |key: &str| HashMap::from([("Authorization".to_string(), format!("Bearer {}", key))]);
+        let spoofed = HashMap::from([("X-Wallet-Address".to_string(), "wallet1".to_string())]);
+
+        for stream in ["commissions", "payments"] {
+            let path = format!("/archive/{}/wallet1", stream);
+            let status = |gateway: &mut PublicGateway, headers: &HashMap<String, String>| {
+                gateway.handle_archive_request(&path, "GET", headers).unwrap().status_code
+            };
+            assert_eq!(status(&mut gateway, &HashMap::new()), 403);
+            assert_eq!(status(&mut gateway, &spoofed), 403);
+            assert_eq!(status(&mut gateway, &bearer(&other_key)), 403);
+            assert_eq!(status(&mut gateway, &bearer(&owner_key)), 200);
+        }
+
+        // Stream status stays public
+        assert_eq!(gateway.handle_archive_request("/archive", "GET", &HashMap::new()).unwrap().status_code, 200);
+        let _ = std::fs::remove_dir_all(location);
+    }
+}
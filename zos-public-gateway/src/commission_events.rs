@@ -0,0 +1,474 @@
+use serde::{Deserialize, Serialize};
+use crate::journal::GatewayEvent;
+use crate::receipts::json_response;
+use crate::screening::is_operator;
+use crate::token_amount::TokenAmount;
+use crate::{CommissionSystem, CommissionType, EarningsAccount, EarningsTier, HttpResponse, PublicGateway};
+use std::collections::HashMap;
+
+/// Something that happened to a wallet's earnings. Events are only ever
+/// appended; `EarningsAccount` is what folding them gives.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum CommissionEventKind {
+    /// The account as it stood before events were kept
+    CarriedOver { account: EarningsAccount },
+    Earned {
+        amount_usdc: f64, // 0 when earned in another token
+        #[serde(default = "usdc")]
+        token: String,
+        #[serde(default)]
+        amount: Option<TokenAmount>, // exact; None in events from before multi-token earnings
+        commission_type: CommissionType,
+        source_transaction: String,
+    },
+    TierChanged { from: EarningsTier, to: EarningsTier, referral_count: u32 },
+    /// Referrals counted from the wallet's referral records. Events from
+    /// before this existed left the count to ReferralBonus payments, which
+    /// counted every payment; tier recalculation records the real count.
+    ReferralsCounted { referral_count: u32 },
+    WithdrawalRequested { withdrawal_id: String, amount_usdc: f64 },
+    /// A withdrawal that will not be paid; its amount is available again
+    WithdrawalReleased { withdrawal_id: String, amount_usdc: f64, reason: String },
+    Paid { payout_id: String, amount_usdc: f64, transaction: String },
+    ClawedBack {
+        payment_id: String,
+        amount_usdc: f64,
+        reason: String,
+        #[serde(default = "usdc")]
+        token: String,
+        #[serde(default)]
+        amount: Option<TokenAmount>,
+    },
+}
+
+fn usdc() -> String {
+    "USDC".to_string()
+}
+
+/// Decimals of the tokens accounts had totals for before they were kept per token
+const LEGACY_DECIMALS: [(&str, u8); 2] = [("USDC", 6), ("SOLFUNMEME", 9)];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommissionEvent {
+    pub seq: u64, // across all wallets
+    pub wallet_address: String,
+    pub timestamp: u64,
+    #[serde(flatten)]
+    pub kind: CommissionEventKind,
+}
+
+/// A wallet whose stored account differs from what its events fold to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EarningsDiscrepancy {
+    pub wallet_address: String,
+    pub stored: Option<EarningsAccount>,
+    pub derived: Option<EarningsAccount>,
+}
+
+pub(crate) fn empty_account(wallet_address: &str, timestamp: u64) -> EarningsAccount {
+    EarningsAccount {
+        wallet_address: wallet_address.to_string(),
+        total_earned_usdc: 0.0,
+        total_earned_solfunmeme: 0.0,
+        pending_withdrawals: 0.0,
+        total_withdrawn_usdc: 0.0,
+        lifetime_volume: 0.0,
+        referral_count: 0,
+        tier: EarningsTier::Bronze,
+        last_payout: timestamp,
+        earned_by_token: HashMap::new(),
+    }
+}
+
+/// The exact amount of an event; older events only have the USDC float
+fn exact_amount(token: &str, amount: &Option<TokenAmount>, amount_usdc: f64) -> TokenAmount {
+    amount.unwrap_or_else(|| {
+        let decimals = LEGACY_DECIMALS.iter().find(|(symbol, _)| *symbol == token).map_or(6, |(_, d)| *d);
+        TokenAmount::from_decimal(amount_usdc, decimals).expect("legacy token decimals are in range")
+    })
+}
+
+/// Add to a token's bucket. The float totals are read from the buckets so
+/// they never pick up rounding from repeated float sums.
+fn credit(account: &mut EarningsAccount, token: &str, amount: TokenAmount) {
+    let bucket = account.earned_by_token.entry(token.to_string())
+        .or_insert_with(|| TokenAmount::zero(amount.decimals));
+    if let Some(sum) = bucket.checked_add(amount) {
+        *bucket = sum;
+    } else {
+        eprintln!("⚠️  {} earnings for {} not credited: decimals or range mismatch", token, account.wallet_address);
+    }
+    account.total_earned_usdc = account.earned_by_token.get("USDC").map_or(0.0, |a| a.to_decimal());
+    account.total_earned_solfunmeme = account.earned_by_token.get("SOLFUNMEME").map_or(0.0, |a| a.to_decimal());
+}
+
+/// Apply one event to the account it belongs to
+pub fn apply_event(account: &mut EarningsAccount, event: &CommissionEvent) {
+    match &event.kind {
+        CommissionEventKind::CarriedOver { account: carried } => {
+            *account = carried.clone();
+            if account.earned_by_token.is_empty() {
+                for (token, decimals) in LEGACY_DECIMALS {
+                    let total = if token == "USDC" { carried.total_earned_usdc } else { carried.total_earned_solfunmeme };
+                    if total != 0.0 {
+                        account.earned_by_token.insert(token.to_string(), TokenAmount::from_decimal(total, decimals).expect("legacy token decimals are in range"));
+                    }
+                }
+            }
+        }
+        CommissionEventKind::Earned { amount_usdc, token, amount, .. } => {
+            let exact = exact_amount(token, amount, *amount_usdc);
+            credit(account, token, exact);
+            if token == "USDC" {
+                account.lifetime_volume += exact.to_decimal();
+            }
+            account.last_payout = event.timestamp;
+        }
+        CommissionEventKind::TierChanged { to, .. } => account.tier = to.clone(),
+        CommissionEventKind::ReferralsCounted { referral_count } => account.referral_count = *referral_count,
+        CommissionEventKind::WithdrawalRequested { amount_usdc, .. } => {
+            account.pending_withdrawals += amount_usdc;
+        }
+        CommissionEventKind::WithdrawalReleased { amount_usdc, .. } => {
+            account.pending_withdrawals = (account.pending_withdrawals - amount_usdc).max(0.0);
+        }
+        CommissionEventKind::Paid { amount_usdc, .. } => {
+            account.pending_withdrawals = (account.pending_withdrawals - amount_usdc).max(0.0);
+            account.total_withdrawn_usdc += amount_usdc;
+        }
+        // Lifetime volume stays gross; only the balance is reduced
+        CommissionEventKind::ClawedBack { amount_usdc, token, amount, .. } => {
+            let exact = exact_amount(token, amount, *amount_usdc);
+            credit(account, token, TokenAmount { base_units: -exact.base_units, decimals: exact.decimals });
+        }
+    }
+}
+
+/// The account a wallet's events fold to, counting those at or before
+/// `until` when given. None if it has no events by then.
+pub fn fold_events<'a>(wallet_address: &str, events: impl IntoIterator<Item = &'a CommissionEvent>,
+                       until: Option<u64>) -> Option<EarningsAccount> {
+    events.into_iter()
+        .filter(|event| until.is_none_or(|until| event.timestamp <= until))
+        .fold(None, |account: Option<EarningsAccount>, event| {
+            let mut account = account.unwrap_or_else(|| empty_account(wallet_address, event.timestamp));
+            apply_event(&mut account, event);
+            Some(account)
+        })
+}
+
+impl CommissionSystem {
+    /// Append an event and apply it to the wallet's account. This is the only
+    /// way account balances change.
+    pub fn record_event(&mut self, wallet_address: &str, kind: CommissionEventKind) -> &EarningsAccount {
+        let timestamp = chrono::Utc::now().timestamp() as u64;
+        let event = CommissionEvent {
+            seq: self.event_seq,
+            wallet_address: wallet_address.to_string(),
+            timestamp,
+            kind,
+        };
+        self.event_seq += 1;
+
+        let account = self.earnings_ledger
+            .entry(wallet_address.to_string())
+            .or_insert_with(|| empty_account(wallet_address, timestamp));
+        apply_event(account, &event);
+        self.journal_outbox.push(GatewayEvent::Earnings { record: event.clone() });
+        self.events.entry(wallet_address.to_string()).or_default().push(event);
+        account
+    }
+
+    /// Accounts stored before events were kept become a CarriedOver event,
+    /// so every account can be replayed. Returns how many were carried over.
+    pub fn carry_over_legacy_accounts(&mut self) -> usize {
+        let legacy: Vec<EarningsAccount> = self.earnings_ledger.values()
+            .filter(|account| !self.events.contains_key(&account.wallet_address))
+            .cloned()
+            .collect();
+        for account in &legacy {
+            let wallet_address = account.wallet_address.clone();
+            self.record_event(&wallet_address, CommissionEventKind::CarriedOver { account: account.clone() });
+        }
+        legacy.len()
+    }
+
+    /// Every wallet whose stored account and event fold disagree
+    pub fn audit_earnings(&self) -> Vec<EarningsDiscrepancy> {
+        let mut wallets: Vec<&String> = self.earnings_ledger.keys().chain(self.events.keys()).collect();
+        wallets.sort();
+        wallets.dedup();
+
+        wallets.into_iter()
+            .filter_map(|wallet_address| {
+                let stored = self.earnings_ledger.get(wallet_address).cloned();
+                let derived = fold_events(wallet_address, self.events.get(wallet_address).into_iter().flatten(), None);
+                let same = serde_json::to_value(&stored).ok() == serde_json::to_value(&derived).ok();
+                (!same).then(|| EarningsDiscrepancy { wallet_address: wallet_address.clone(), stored, derived })
+            })
+            .collect()
+    }
+
+    /// Replace every stored account with its event fold. Returns the
+    /// discrepancies that were corrected.
+    pub fn replay_earnings(&mut self) -> Vec<EarningsDiscrepancy> {
+        let discrepancies = self.audit_earnings();
+        self.earnings_ledger = self.events.iter()
+            .filter_map(|(wallet_address, events)| {
+                fold_events(wallet_address, events, None).map(|account| (wallet_address.clone(), account))
+            })
+            .collect();
+        discrepancies
+    }
+
+    /// A wallet's account as it stood at `timestamp`
+    pub fn earnings_at(&self, wallet_address: &str, timestamp: u64) -> Option<EarningsAccount> {
+        fold_events(wallet_address, self.events.get(wallet_address).into_iter().flatten(), Some(timestamp))
+    }
+}
+
+impl PublicGateway {
+    /// Take back a commission paid in error. The payment stays in the
+    /// history; a ClawedBack event reverses what is left of it, once.
+    pub fn claw_back_commission(&mut self, payment_id: &str, reason: &str) -> Result<CommissionEvent, String> {
+        self.claw_back_share(payment_id, 1.0, reason)
+    }
+
+    /// Take back `share` of a commission payment, as when part of the payment
+    /// it was earned on is refunded. Never takes back more than is left of it
+    /// after earlier clawbacks.
+    pub(crate) fn claw_back_share(&mut self, payment_id: &str, share: f64, reason: &str) -> Result<CommissionEvent, String> {
+        let commission_system = self.commission_system.as_mut()
+            .ok_or("Commission system not initialized")?;
+        let payment = commission_system.commission_history.values()
+            .flatten()
+            .find(|payment| payment.payment_id == payment_id)
+            .cloned()
+            .ok_or("Commission payment not found")?;
+
+        let paid = exact_amount(&payment.token, &payment.exact, payment.amount);
+        let clawed: i64 = commission_system.events.get(&payment.recipient_wallet).into_iter().flatten()
+            .filter_map(|event| match &event.kind {
+                CommissionEventKind::ClawedBack { payment_id: id, amount_usdc, token, amount, .. } if id == payment_id =>
+                    Some(exact_amount(token, amount, *amount_usdc).base_units),
+                _ => None,
+            })
+            .sum();
+        let left = paid.base_units - clawed;
+        let base_units = ((paid.base_units as f64 * share.clamp(0.0, 1.0)).round() as i64).min(left);
+        if base_units <= 0 {
+            return Err("Commission payment already clawed back".to_string());
+        }
+
+        let amount = TokenAmount { base_units, decimals: paid.decimals };
+        commission_system.record_event(&payment.recipient_wallet, CommissionEventKind::ClawedBack {
+            payment_id: payment_id.to_string(),
+            amount_usdc: if payment.token == "USDC" { amount.to_decimal() } else { 0.0 },
+            reason: reason.to_string(),
+            token: payment.token.clone(),
+            amount: Some(amount),
+        });
+        println!("↩️  Clawed back {} {} from {} ({})", amount, payment.token, payment.recipient_wallet, reason);
+
+        commission_system.events.get(&payment.recipient_wallet)
+            .and_then(|events| events.last())
+            .cloned()
+            .ok_or("Clawback was not recorded".to_string())
+    }
+
+    /// GET /{wallet}/earnings/events, GET /{wallet}/earnings/at/{unix}
+    pub(crate) fn handle_earnings_events_request(&self, wallet_address: &str, path: &str) -> Result<HttpResponse, String> {
+        let commission_system = self.commission_system.as_ref()
+            .ok_or("Commission system not initialized")?;
+
+        if let Some(at) = path.trim_end_matches('/').rsplit_once("/earnings/at/").map(|(_, at)| at) {
+            let timestamp = at.parse::<u64>().map_err(|_| "Timestamp must be unix seconds".to_string())?;
+            return match commission_system.earnings_at(wallet_address, timestamp) {
+                Some(account) => json_response(200, &serde_json::json!({ "at": timestamp, "account": account })),
+                None => json_response(404, &serde_json::json!({ "error": "No earnings by then" })),
+            };
+        }
+
+        json_response(200, &serde_json::json!({
+            "wallet_address": wallet_address,
+            "events": commission_system.events.get(wallet_address),
+        }))
+    }
+
+    /// Operator tools over the event log: GET /commission/events/audit,
+    /// POST /commission/events/replay, POST /commission/clawbacks
+    pub(crate) fn handle_commission_events_request(&mut self, path: &str, method: &str,
+                                                   headers: &HashMap<String, String>,
+                                                   body: &[u8]) -> Result<HttpResponse, String> {
+        if !is_operator(headers) {
+            return json_response(403, &serde_json::json!({ "error": "Needs X-Operator-Key" }));
+        }
+
+        match (method, path.trim_end_matches('/')) {
+            ("GET", "/commission/events/audit") => {
+                let commission_system = self.commission_system.as_ref()
+                    .ok_or("Commission system not initialized")?;
+                json_response(200, &serde_json::json!({ "discrepancies": commission_system.audit_earnings() }))
+            }
+            ("POST", "/commission/events/replay") => {
+                let commission_system = self.commission_system.as_mut()
+                    .ok_or("Commission system not initialized")?;
+                let corrected = commission_system.replay_earnings();
+                if !corrected.is_empty() {
+                    println!("⚠️  Replay corrected {} earnings accounts", corrected.len());
+                }
+                json_response(200, &serde_json::json!({ "corrected": corrected }))
+            }
+            ("POST", "/commission/clawbacks") => {
+                let request: serde_json::Value = serde_json::from_slice(body)
+                    .map_err(|e| format!("Invalid clawback request: {}", e))?;
+                let payment_id = request["payment_id"].as_str().ok_or("payment_id is required")?;
+                let reason = request["reason"].as_str().ok_or("reason is required")?;
+                match self.claw_back_commission(payment_id, reason) {
+                    Ok(event) => json_response(200, &serde_json::json!({ "event": event })),
+                    Err(e) => json_response(409, &serde_json::json!({ "error": e })),
+                }
+            }
+            _ => Err("Unsupported commission events request".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WALLET: &str = "EarnerWallet111111111111111111111111111111";
+
+    fn commission_system() -> CommissionSystem {
+        let mut gateway = PublicGateway::new("gateway.test");
+        gateway.initialize_commission_system();
+        gateway.commission_system.take().unwrap()
+    }
+
+    fn earned(token: &str, amount_usdc: f64, amount: Option<TokenAmount>) -> CommissionEventKind {
+        CommissionEventKind::Earned {
+            amount_usdc,
+            token: token.to_string(),
+            amount,
+            commission_type: CommissionType::ServiceFee,
+            source_transaction: "pay_1".to_string(),
+        }
+    }
+
+    fn json(account: &Option<EarningsAccount>) -> serde_json::Value {
+        serde_json::to_value(account).unwrap()
+    }
+
+    #[test]
+    fn test_record_event_matches_fold_events() {
+        let mut system = commission_system();
+        let kinds = vec![
+            earned("USDC", 1.5, Some(TokenAmount { base_units: 1_500_000, decimals: 6 })),
+            earned("USDC", 0.25, None), // from before exact amounts
+            earned("SOLFUNMEME", 0.0, Some(TokenAmount { base_units: 3_000_000_000, decimals: 9 })),
+            CommissionEventKind::TierChanged { from: EarningsTier::Bronze, to: EarningsTier::Silver, referral_count: 5 },
+            CommissionEventKind::ReferralsCounted { referral_count: 6 },
+            CommissionEventKind::WithdrawalRequested { withdrawal_id: "w1".to_string(), amount_usdc: 1.0 },
+            CommissionEventKind::WithdrawalReleased { withdrawal_id: "w1".to_string(), amount_usdc: 1.0, reason: "rejected".to_string() },
+            CommissionEventKind::WithdrawalRequested { withdrawal_id: "w2".to_string(), amount_usdc: 0.5 },
+            CommissionEventKind::Paid { payout_id: "p1".to_string(), amount_usdc: 0.5, transaction: "tx".to_string() },
+            CommissionEventKind::ClawedBack {
+                payment_id: "comm_1".to_string(),
+                amount_usdc: 0.75,
+                reason: "refund".to_string(),
+                token: "USDC".to_string(),
+                amount: Some(TokenAmount { base_units: 750_000, decimals: 6 }),
+            },
+        ];
+        for kind in kinds {
+            system.record_event(WALLET, kind);
+        }
+
+        let stored = system.earnings_ledger.get(WALLET).cloned();
+        let folded = fold_events(WALLET, &system.events[WALLET], None);
+        assert_eq!(json(&stored), json(&folded));
+        assert!(system.audit_earnings().is_empty());
+
+        let account = stored.unwrap();
+        assert_eq!(account.earned_by_token["USDC"], TokenAmount { base_units: 1_000_000, decimals: 6 });
+        assert_eq!(account.earned_by_token["SOLFUNMEME"], TokenAmount { base_units: 3_000_000_000, decimals: 9 });
+        assert_eq!(account.total_earned_usdc, 1.0);
+        assert_eq!(account.lifetime_volume, 1.75); // gross of the clawback
+        assert!(matches!(account.tier, EarningsTier::Silver));
+        assert_eq!(account.referral_count, 6);
+        assert_eq!(account.pending_withdrawals, 0.0);
+        assert_eq!(account.total_withdrawn_usdc, 0.5);
+
+        // A prefix folds to the account as it stood; no events by `until`, no account
+        let first_only = fold_events(WALLET, &system.events[WALLET][..1], None).unwrap();
+        assert_eq!(first_only.total_earned_usdc, 1.5);
+        assert!(fold_events(WALLET, &system.events[WALLET], Some(0)).is_none());
+    }
+
+    #[test]
+    fn test_carry_over_legacy_accounts_round_trips() {
+        let mut system = commission_system();
+        let mut legacy = empty_account(WALLET, 1_600_000_000);
+        legacy.total_earned_usdc = 12.5;
+        legacy.total_earned_solfunmeme = 4.0;
+        legacy.total_withdrawn_usdc = 2.0;
+        legacy.lifetime_volume = 40.0;
+        legacy.referral_count = 3;
+        legacy.tier = EarningsTier::Gold;
+        system.earnings_ledger.insert(WALLET.to_string(), legacy.clone());
+
+        // An account with no events does not replay yet
+        assert_eq!(system.audit_earnings().len(), 1);
+
+        assert_eq!(system.carry_over_legacy_accounts(), 1);
+        assert_eq!(system.carry_over_legacy_accounts(), 0);
+        assert!(system.audit_earnings().is_empty());
+        assert!(system.replay_earnings().is_empty());
+
+        let account = &system.earnings_ledger[WALLET];
+        assert_eq!(account.total_earned_usdc, 12.5);
+        assert_eq!(account.total_earned_solfunmeme, 4.0);
+        assert_eq!(account.earned_by_token["USDC"], TokenAmount { base_units: 12_500_000, decimals: 6 });
+        assert_eq!(account.earned_by_token["SOLFUNMEME"], TokenAmount { base_units: 4_000_000_000, decimals: 9 });
+        assert_eq!(account.total_withdrawn_usdc, 2.0);
+        assert_eq!(account.referral_count, 3);
+        assert!(matches!(account.tier, EarningsTier::Gold));
+        assert_eq!(account.last_payout, legacy.last_payout);
+
+        // New events build on the carried-over balance
+        system.record_event(WALLET, earned("USDC", 1.0, None));
+        assert_eq!(system.earnings_ledger[WALLET].total_earned_usdc, 13.5);
+        assert!(system.audit_earnings().is_empty());
+    }
+
+    #[test]
+    fn test_audit_earnings_flags_a_tampered_account() {
+        let mut system = commission_system();
+        system.record_event(WALLET, earned("USDC", 2.0, None));
+        system.record_event("OtherWallet", earned("USDC", 1.0, None));
+        let honest = system.earnings_ledger[WALLET].clone();
+
+        let account = system.earnings_ledger.get_mut(WALLET).unwrap();
+        account.total_earned_usdc = 200.0;
+        account.earned_by_token.insert("USDC".to_string(), TokenAmount { base_units: 200_000_000, decimals: 6 });
+
+        let discrepancies = system.audit_earnings();
+        assert_eq!(discrepancies.len(), 1);
+        assert_eq!(discrepancies[0].wallet_address, WALLET);
+        assert_eq!(discrepancies[0].stored.as_ref().unwrap().total_earned_usdc, 200.0);
+        assert_eq!(discrepancies[0].derived.as_ref().unwrap().total_earned_usdc, 2.0);
+
+        // Replay corrects it from the events
+        assert_eq!(system.replay_earnings().len(), 1);
+        assert_eq!(json(&Some(system.earnings_ledger[WALLET].clone())), json(&Some(honest)));
+        assert!(system.audit_earnings().is_empty());
+
+        // An account with no events behind it is flagged too
+        system.earnings_ledger.insert("Forged".to_string(), empty_account("Forged", 0));
+        let discrepancies = system.audit_earnings();
+        assert_eq!(discrepancies.len(), 1);
+        assert!(discrepancies[0].derived.is_none());
+    }
+}
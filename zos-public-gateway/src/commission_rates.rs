@@ -0,0 +1,455 @@
+use serde::{Deserialize, Serialize};
+use crate::receipts::json_response;
+use crate::screening::is_operator;
+use crate::{CommissionPayment, CommissionRates, CommissionType, HttpResponse, PublicGateway};
+use std::collections::HashMap;
+
+const TIERS: [&str; 4] = ["Bronze", "Silver", "Gold", "Platinum"];
+
+impl Default for CommissionRates {
+    fn default() -> Self {
+        Self {
+            swap_commission_percentage: 20.0,    // 20% of swap fees
+            referral_commission_percentage: 10.0, // 10% of referee's fees
+            service_commission_percentage: 5.0,   // 5% of service payments
+            tier_multipliers: HashMap::from([
+                ("Bronze".to_string(), 1.0),
+                ("Silver".to_string(), 1.2),
+                ("Gold".to_string(), 1.5),
+                ("Platinum".to_string(), 2.0),
+            ]),
+        }
+    }
+}
+
+/// Bounds every rate change is checked against
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateCaps {
+    pub max_percentage: f64,      // any single commission rate
+    pub max_tier_multiplier: f64,
+    pub min_notice_secs: u64,     // how far ahead a change must be scheduled
+}
+
+impl Default for RateCaps {
+    fn default() -> Self {
+        Self {
+            max_percentage: 50.0,
+            max_tier_multiplier: 3.0,
+            min_notice_secs: 86_400,
+        }
+    }
+}
+
+/// A set of rates and when it takes over. Changes are never edited or
+/// removed, so any past payment can be checked against the rates it was
+/// paid under.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateChange {
+    pub change_id: String,
+    pub rates: CommissionRates,
+    pub effective_at: u64,
+    pub scheduled_at: u64,
+    pub proposal_id: Option<String>, // governance proposal that approved it; None for the initial rates
+    pub cancelled_at: Option<u64>,
+}
+
+/// A service owner's own rates for commissions on their service. Plans are
+/// never edited either; a new one takes over from its effective date.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommissionPlan {
+    pub plan_id: String,
+    pub service_key: String,
+    pub rates: CommissionRates,
+    pub effective_at: u64,
+    pub created_at: u64,
+    pub cancelled_at: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommissionPlanRequest {
+    rates: CommissionRates,
+    effective_at: Option<u64>, // now when absent; never in the past
+}
+
+#[derive(Debug, Deserialize)]
+struct ScheduleRatesRequest {
+    rates: CommissionRates,
+    effective_at: u64,
+    proposal_id: String,
+}
+
+/// Rate changes need `X-Governance-Key` matching ZOS_GOVERNANCE_KEY (held by
+/// whoever executes passed proposals); with no key configured rates are
+/// read-only over HTTP
+fn is_governance(headers: &HashMap<String, String>) -> bool {
+    match std::env::var("ZOS_GOVERNANCE_KEY") {
+        Ok(expected) if !expected.is_empty() => headers.get("X-Governance-Key") == Some(&expected),
+        _ => false,
+    }
+}
+
+/// Caps on each rate, plus the sum check: swap commission, the largest
+/// referral payout and the burn/treasury shares all come out of the same fee
+pub fn validate_rates(rates: &CommissionRates, caps: &RateCaps, fee_shares_percentage: f64) -> Result<(), String> {
+    let percentages = [
+        ("swap_commission_percentage", rates.swap_commission_percentage),
+        ("referral_commission_percentage", rates.referral_commission_percentage),
+        ("service_commission_percentage", rates.service_commission_percentage),
+    ];
+    for (name, value) in percentages {
+        if !value.is_finite() || value < 0.0 || value > caps.max_percentage {
+            return Err(format!("{} must be between 0 and {}", name, caps.max_percentage));
+        }
+    }
+
+    for tier in TIERS {
+        let multiplier = rates.tier_multipliers.get(tier)
+            .ok_or_else(|| format!("Missing tier multiplier for {}", tier))?;
+        if !multiplier.is_finite() || *multiplier < 1.0 || *multiplier > caps.max_tier_multiplier {
+            return Err(format!("{} multiplier must be between 1 and {}", tier, caps.max_tier_multiplier));
+        }
+    }
+    if let Some(tier) = rates.tier_multipliers.keys().find(|tier| !TIERS.contains(&tier.as_str())) {
+        return Err(format!("Unknown tier {}", tier));
+    }
+
+    let max_multiplier = rates.tier_multipliers.values().cloned().fold(1.0, f64::max);
+    let fee_share = rates.swap_commission_percentage
+        + rates.referral_commission_percentage * max_multiplier
+        + fee_shares_percentage;
+    if fee_share > 100.0 {
+        return Err(format!("Swap and referral commissions plus burn/treasury shares take {:.2}% of a fee", fee_share));
+    }
+
+    Ok(())
+}
+
+impl PublicGateway {
+    /// The rates that were (or will be) in force at `timestamp`. Before the
+    /// first recorded change that's the first rates; with no history at all
+    /// (state from before rates were scheduled) the live ones.
+    pub fn commission_rates_at(&self, timestamp: u64) -> Result<CommissionRates, String> {
+        let commission_system = self.commission_system.as_ref()
+            .ok_or("Commission system not initialized")?;
+
+        let live = commission_system.rate_changes.iter()
+            .filter(|change| change.cancelled_at.is_none());
+        let in_force = live.clone()
+            .filter(|change| change.effective_at <= timestamp)
+            .max_by_key(|change| (change.effective_at, change.scheduled_at))
+            .or_else(|| live.min_by_key(|change| (change.effective_at, change.scheduled_at)));
+
+        Ok(in_force.map(|change| change.rates.clone())
+            .unwrap_or_else(|| commission_system.commission_rates.clone()))
+    }
+
+    /// The plan a service had in force at `timestamp`, if any
+    pub fn commission_plan_at(&self, service_key: &str, timestamp: u64) -> Option<&CommissionPlan> {
+        self.commission_system.as_ref()?
+            .commission_plans.get(service_key)?
+            .iter()
+            .filter(|plan| plan.cancelled_at.is_none() && plan.effective_at <= timestamp)
+            .max_by_key(|plan| (plan.effective_at, plan.created_at))
+    }
+
+    /// The rates commissions on a service were (or will be) paid at: its
+    /// owner's plan when one was in force, otherwise the global rates
+    pub fn commission_rates_for(&self, service_key: &str, timestamp: u64) -> Result<CommissionRates, String> {
+        match self.commission_plan_at(service_key, timestamp) {
+            Some(plan) => Ok(plan.rates.clone()),
+            None => self.commission_rates_at(timestamp),
+        }
+    }
+
+    /// Register an owner's rates for a service. Plans are held to the same
+    /// caps and fee total as global rates, and can't start in the past, so
+    /// commissions already paid keep the rates they were paid at.
+    pub fn schedule_commission_plan(&mut self, service_key: &str, rates: CommissionRates,
+                                    effective_at: Option<u64>) -> Result<CommissionPlan, String> {
+        if !self.service_registry.contains_key(service_key) {
+            return Err("Service not found".to_string());
+        }
+        let now = chrono::Utc::now().timestamp() as u64;
+        let effective_at = effective_at.unwrap_or(now);
+        if effective_at < now {
+            return Err(format!("effective_at must not be before now ({})", now));
+        }
+        let config = &self.fee_routing.config;
+        let fee_shares = config.burn_percentage.max(0.0) + config.treasury_percentage.max(0.0);
+
+        let commission_system = self.commission_system.as_mut()
+            .ok_or("Commission system not initialized")?;
+        validate_rates(&rates, &commission_system.rate_caps, fee_shares)?;
+
+        let plans = commission_system.commission_plans.entry(service_key.to_string()).or_default();
+        let plan = CommissionPlan {
+            plan_id: format!("plan_{}_{}", now, plans.len()),
+            service_key: service_key.to_string(),
+            rates,
+            effective_at,
+            created_at: now,
+            cancelled_at: None,
+        };
+        plans.push(plan.clone());
+
+        println!("📐 Commission plan {} for {} effective {}", plan.plan_id, service_key, effective_at);
+        Ok(plan)
+    }
+
+    /// Withdraw a plan that hasn't taken effect yet
+    pub fn cancel_commission_plan(&mut self, service_key: &str, plan_id: &str) -> Result<CommissionPlan, String> {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let commission_system = self.commission_system.as_mut()
+            .ok_or("Commission system not initialized")?;
+
+        let plan = commission_system.commission_plans.get_mut(service_key)
+            .and_then(|plans| plans.iter_mut().find(|plan| plan.plan_id == plan_id))
+            .ok_or("Commission plan not found")?;
+        if plan.cancelled_at.is_some() {
+            return Err("Commission plan already cancelled".to_string());
+        }
+        if plan.effective_at <= now {
+            return Err("Commission plan is already in force; schedule a new one instead".to_string());
+        }
+        plan.cancelled_at = Some(now);
+        Ok(plan.clone())
+    }
+
+    /// GET, POST /{wallet}/{service}/commission-plan and
+    /// DELETE /{wallet}/{service}/commission-plan/{plan_id}, for the service owner
+    /// (or an operator)
+    pub(crate) fn handle_commission_plan_request(&mut self, wallet_address: &str, service_name: &str,
+                                                 path: &str, method: &str,
+                                                 headers: &HashMap<String, String>,
+                                                 body: &[u8]) -> Result<HttpResponse, String> {
+        if !is_operator(headers) {
+            match self.authenticate_wallet(headers) {
+                Ok(caller) if caller == wallet_address => {}
+                Ok(_) => return json_response(403, &serde_json::json!({ "error": "Only the service owner can set commission plans" })),
+                Err(e) => return json_response(401, &serde_json::json!({ "error": e })),
+            }
+        }
+        let service_key = format!("{}_{}", wallet_address, service_name);
+        let rest: Vec<&str> = path.trim_matches('/').split('/').skip(3).collect();
+        let now = chrono::Utc::now().timestamp() as u64;
+
+        match (method, rest.as_slice()) {
+            ("GET", []) => {
+                let plans = self.commission_system.as_ref()
+                    .and_then(|system| system.commission_plans.get(&service_key))
+                    .cloned()
+                    .unwrap_or_default();
+                json_response(200, &serde_json::json!({
+                    "service": service_key,
+                    "current": self.commission_rates_for(&service_key, now)?,
+                    "plan_in_force": self.commission_plan_at(&service_key, now).map(|plan| &plan.plan_id),
+                    "scheduled": plans.iter()
+                        .filter(|plan| plan.cancelled_at.is_none() && plan.effective_at > now)
+                        .collect::<Vec<_>>(),
+                    "history": plans,
+                }))
+            }
+            ("POST", []) => {
+                let request: CommissionPlanRequest = serde_json::from_slice(body)
+                    .map_err(|e| format!("Invalid commission plan: {}", e))?;
+                match self.schedule_commission_plan(&service_key, request.rates, request.effective_at) {
+                    Ok(plan) => json_response(201, &plan),
+                    Err(e) => json_response(400, &serde_json::json!({ "error": e })),
+                }
+            }
+            ("DELETE", [plan_id]) => match self.cancel_commission_plan(&service_key, plan_id) {
+                Ok(plan) => json_response(200, &plan),
+                Err(e) => json_response(409, &serde_json::json!({ "error": e })),
+            },
+            _ => Err("Unsupported commission plan request".to_string()),
+        }
+    }
+
+    /// Make the rates in force now the live ones; run before paying commission
+    pub(crate) fn apply_due_rate_changes(&mut self) {
+        let now = chrono::Utc::now().timestamp() as u64;
+        if let Ok(rates) = self.commission_rates_at(now) {
+            if let Some(commission_system) = self.commission_system.as_mut() {
+                commission_system.commission_rates = rates;
+            }
+        }
+    }
+
+    /// Schedule new rates, approved by a governance proposal
+    pub fn schedule_commission_rates(&mut self, rates: CommissionRates, effective_at: u64,
+                                     proposal_id: &str) -> Result<RateChange, String> {
+        if proposal_id.is_empty() {
+            return Err("proposal_id is required".to_string());
+        }
+        let now = chrono::Utc::now().timestamp() as u64;
+        let config = &self.fee_routing.config;
+        let fee_shares = config.burn_percentage.max(0.0) + config.treasury_percentage.max(0.0);
+
+        let commission_system = self.commission_system.as_mut()
+            .ok_or("Commission system not initialized")?;
+        validate_rates(&rates, &commission_system.rate_caps, fee_shares)?;
+
+        let earliest = now + commission_system.rate_caps.min_notice_secs;
+        if effective_at < earliest {
+            return Err(format!("Rate changes need {}s notice: effective_at must be at least {}",
+                               commission_system.rate_caps.min_notice_secs, earliest));
+        }
+        if commission_system.rate_changes.iter()
+            .any(|change| change.proposal_id.as_deref() == Some(proposal_id) && change.cancelled_at.is_none()) {
+            return Err(format!("Proposal {} already scheduled a rate change", proposal_id));
+        }
+
+        let change = RateChange {
+            change_id: format!("rates_{}_{}", now, commission_system.rate_changes.len()),
+            rates,
+            effective_at,
+            scheduled_at: now,
+            proposal_id: Some(proposal_id.to_string()),
+            cancelled_at: None,
+        };
+        commission_system.rate_changes.push(change.clone());
+
+        println!("📐 Commission rates {} scheduled for {} (proposal {})", change.change_id, effective_at, proposal_id);
+        Ok(change)
+    }
+
+    /// Withdraw a change that hasn't taken effect yet
+    pub fn cancel_commission_rates(&mut self, change_id: &str) -> Result<RateChange, String> {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let commission_system = self.commission_system.as_mut()
+            .ok_or("Commission system not initialized")?;
+
+        let change = commission_system.rate_changes.iter_mut()
+            .find(|change| change.change_id == change_id)
+            .ok_or("Rate change not found")?;
+        if change.cancelled_at.is_some() {
+            return Err("Rate change already cancelled".to_string());
+        }
+        if change.effective_at <= now {
+            return Err("Rate change is already in force".to_string());
+        }
+        change.cancelled_at = Some(now);
+        Ok(change.clone())
+    }
+
+    /// Check a past payment against the rates in force when it was made
+    pub fn audit_commission_payment(&self, payment_id: &str) -> Result<serde_json::Value, String> {
+        let commission_system = self.commission_system.as_ref()
+            .ok_or("Commission system not initialized")?;
+        let payment: &CommissionPayment = commission_system.commission_history.values()
+            .flatten()
+            .find(|payment| payment.payment_id == payment_id)
+            .ok_or("Commission payment not found")?;
+
+        let rates = match &payment.service_key {
+            Some(service_key) => self.commission_rates_for(service_key, payment.timestamp)?,
+            None => self.commission_rates_at(payment.timestamp)?,
+        };
+        let plan = payment.service_key.as_deref()
+            .and_then(|service_key| self.commission_plan_at(service_key, payment.timestamp))
+            .map(|plan| plan.plan_id.clone());
+        let percentage = match payment.commission_type {
+            CommissionType::SwapFee => Some(rates.swap_commission_percentage),
+            CommissionType::ReferralBonus => Some(rates.referral_commission_percentage),
+            CommissionType::ServiceFee => Some(rates.service_commission_percentage),
+            CommissionType::VolumeBonus => None, // fixed milestone amounts, not a rate
+            CommissionType::EdgeCacheHits => None, // fixed per-hit reward
+        };
+
+        // Referral payouts carry the recipient's tier multiplier, which any
+        // tier in force at the time may account for
+        let (expected, consistent) = match (percentage, payment.base_amount) {
+            (Some(percentage), Some(base)) => {
+                let expected = base * percentage / 100.0;
+                let multipliers: Vec<f64> = match payment.commission_type {
+                    CommissionType::ReferralBonus => rates.tier_multipliers.values().cloned().collect(),
+                    _ => vec![1.0],
+                };
+                let consistent = multipliers.iter()
+                    .any(|multiplier| (expected * multiplier - payment.amount).abs() < 1e-6);
+                (Some(expected), Some(consistent))
+            }
+            _ => (None, None),
+        };
+
+        Ok(serde_json::json!({
+            "payment": payment,
+            "rates_in_force": rates,
+            "commission_plan": plan,
+            "percentage": percentage,
+            "expected_before_multiplier": expected,
+            "consistent": consistent,
+        }))
+    }
+
+    /// GET /commission/rates, /commission/rates/history, /commission/rates/at/{timestamp};
+    /// POST /commission/rates and DELETE /commission/rates/{change_id} (governance);
+    /// GET /commission/audit/{payment_id} (the recipient or governance)
+    pub fn handle_commission_rates_request(&mut self, path: &str, method: &str,
+                                           headers: &HashMap<String, String>,
+                                           body: &[u8]) -> Result<HttpResponse, String> {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let path = path.trim_end_matches('/');
+
+        if let Some(payment_id) = path.strip_prefix("/commission/audit/") {
+            let audit = self.audit_commission_payment(payment_id)?;
+            let recipient = audit["payment"]["recipient_wallet"].as_str().unwrap_or("");
+            if !is_governance(headers) {
+                match self.authenticate_wallet(headers) {
+                    Ok(caller) if caller == recipient => {}
+                    Ok(_) => return json_response(403, &serde_json::json!({ "error": "Only the recipient can audit this payment" })),
+                    Err(e) => return json_response(401, &serde_json::json!({ "error": e })),
+                }
+            }
+            return json_response(200, &audit);
+        }
+
+        match (method, path) {
+            ("GET", "/commission/rates") => {
+                let commission_system = self.commission_system.as_ref()
+                    .ok_or("Commission system not initialized")?;
+                json_response(200, &serde_json::json!({
+                    "current": self.commission_rates_at(now)?,
+                    "caps": commission_system.rate_caps,
+                    "scheduled": commission_system.rate_changes.iter()
+                        .filter(|change| change.cancelled_at.is_none() && change.effective_at > now)
+                        .collect::<Vec<_>>(),
+                }))
+            }
+            ("GET", "/commission/rates/history") => {
+                let commission_system = self.commission_system.as_ref()
+                    .ok_or("Commission system not initialized")?;
+                json_response(200, &serde_json::json!({ "changes": commission_system.rate_changes }))
+            }
+            ("POST", "/commission/rates") => {
+                if !is_governance(headers) {
+                    return json_response(403, &serde_json::json!({ "error": "Rate changes need X-Governance-Key" }));
+                }
+                let request: ScheduleRatesRequest = serde_json::from_slice(body)
+                    .map_err(|e| format!("Invalid rate change: {}", e))?;
+                match self.schedule_commission_rates(request.rates, request.effective_at, &request.proposal_id) {
+                    Ok(change) => json_response(201, &change),
+                    Err(e) => json_response(400, &serde_json::json!({ "error": e })),
+                }
+            }
+            ("GET", _) if path.starts_with("/commission/rates/at/") => {
+                let timestamp: u64 = path.trim_start_matches("/commission/rates/at/").parse()
+                    .map_err(|_| "Timestamp must be unix seconds".to_string())?;
+                json_response(200, &serde_json::json!({
+                    "timestamp": timestamp,
+                    "rates": self.commission_rates_at(timestamp)?,
+                }))
+            }
+            ("DELETE", _) if path.starts_with("/commission/rates/") => {
+                if !is_governance(headers) {
+                    return json_response(403, &serde_json::json!({ "error": "Rate changes need X-Governance-Key" }));
+                }
+                match self.cancel_commission_rates(path.trim_start_matches("/commission/rates/")) {
+                    Ok(change) => json_response(200, &change),
+                    Err(e) => json_response(409, &serde_json::json!({ "error": e })),
+                }
+            }
+            _ => Err("Unsupported commission rates request".to_string()),
+        }
+    }
+}
@@ -0,0 +1,356 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::{HttpResponse, PublicGateway};
+use std::collections::{HashMap, VecDeque};
+
+const RECENT_VIOLATIONS: usize = 50;
+
+/// Keywords that change what validates but that this validator does not
+/// implement; schemas using them are refused rather than half-checked
+const UNSUPPORTED_KEYWORDS: [&str; 10] = [
+    "$ref", "$dynamicRef", "pattern", "patternProperties", "if", "dependentRequired",
+    "dependentSchemas", "unevaluatedProperties", "unevaluatedItems", "prefixItems",
+];
+
+/// JSON Schemas an owner attached to a service. Requests that do not match
+/// are refused with 422; responses are only ever checked, never altered.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceContract {
+    pub request_schema: Option<Value>,
+    pub response_schema: Option<Value>,
+    pub monitor_responses: bool,
+    #[serde(default)]
+    pub stats: ContractStats,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ContractStats {
+    pub requests_checked: u64,
+    pub requests_rejected: u64,
+    pub responses_checked: u64,
+    pub response_violations: u64,
+    pub recent: VecDeque<ContractViolation>,
+}
+
+impl ContractStats {
+    pub fn request_violation_rate(&self) -> f64 {
+        if self.requests_checked == 0 { 0.0 } else { self.requests_rejected as f64 / self.requests_checked as f64 }
+    }
+
+    pub fn response_violation_rate(&self) -> f64 {
+        if self.responses_checked == 0 { 0.0 } else { self.response_violations as f64 / self.responses_checked as f64 }
+    }
+
+    fn remember(&mut self, direction: &str, violations: Vec<Violation>) {
+        self.recent.push_back(ContractViolation {
+            direction: direction.to_string(),
+            timestamp: chrono::Utc::now().timestamp() as u64,
+            violations,
+        });
+        while self.recent.len() > RECENT_VIOLATIONS {
+            self.recent.pop_front();
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContractViolation {
+    pub direction: String, // "request" or "response"
+    pub timestamp: u64,
+    pub violations: Vec<Violation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Violation {
+    pub pointer: String, // RFC 6901 JSON pointer into the body, "" for the root
+    pub message: String,
+}
+
+/// Refuse schemas the validator cannot enforce faithfully
+pub fn check_schema(schema: &Value) -> Result<(), String> {
+    match schema {
+        Value::Bool(_) => Ok(()),
+        Value::Object(map) => {
+            if let Some(keyword) = UNSUPPORTED_KEYWORDS.iter().find(|keyword| map.contains_key(**keyword)) {
+                return Err(format!("Schema keyword {} is not supported", keyword));
+            }
+            for (keyword, value) in map {
+                match keyword.as_str() {
+                    "properties" => {
+                        for property in value.as_object().ok_or("properties must be an object")?.values() {
+                            check_schema(property)?;
+                        }
+                    }
+                    "items" | "additionalProperties" | "not" => check_schema(value)?,
+                    "allOf" | "anyOf" | "oneOf" => {
+                        for branch in value.as_array().ok_or(format!("{} must be an array", keyword))? {
+                            check_schema(branch)?;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Ok(())
+        }
+        _ => Err("A schema must be an object or a boolean".to_string()),
+    }
+}
+
+fn escape_pointer(segment: &str) -> String {
+    segment.replace('~', "~0").replace('/', "~1")
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn matches_type(value: &Value, expected: &str) -> bool {
+    let actual = type_name(value);
+    actual == expected
+        || (expected == "number" && actual == "integer")
+        || (expected == "integer" && value.as_f64().is_some_and(|n| n.fract() == 0.0))
+}
+
+/// Check `value` against `schema`, collecting every violation with the
+/// JSON pointer where it occurred
+pub fn validate(schema: &Value, value: &Value, pointer: &str, violations: &mut Vec<Violation>) {
+    let mut fail = |message: String| violations.push(Violation { pointer: pointer.to_string(), message });
+
+    let schema = match schema {
+        Value::Bool(true) => return,
+        Value::Bool(false) => return fail("No value is allowed here".to_string()),
+        Value::Object(schema) => schema,
+        _ => return,
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let allowed: Vec<&str> = match expected {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.is_empty() && !allowed.iter().any(|name| matches_type(value, name)) {
+            // Nothing below makes sense for the wrong type
+            return fail(format!("Expected {}, got {}", allowed.join(" or "), type_name(value)));
+        }
+    }
+
+    if let Some(expected) = schema.get("const") {
+        if value != expected {
+            fail(format!("Must equal {}", expected));
+        }
+    }
+    if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+        if !options.contains(value) {
+            fail(format!("Must be one of {}", Value::Array(options.clone())));
+        }
+    }
+
+    if let Some(n) = value.as_f64() {
+        let bound = |keyword: &str| schema.get(keyword).and_then(Value::as_f64);
+        if let Some(min) = bound("minimum").filter(|min| n < *min) {
+            fail(format!("Must be at least {}", min));
+        }
+        if let Some(max) = bound("maximum").filter(|max| n > *max) {
+            fail(format!("Must be at most {}", max));
+        }
+        if let Some(min) = bound("exclusiveMinimum").filter(|min| n <= *min) {
+            fail(format!("Must be greater than {}", min));
+        }
+        if let Some(max) = bound("exclusiveMaximum").filter(|max| n >= *max) {
+            fail(format!("Must be less than {}", max));
+        }
+    }
+
+    if let Some(s) = value.as_str() {
+        let length = s.chars().count() as u64;
+        if let Some(min) = schema.get("minLength").and_then(Value::as_u64).filter(|min| length < *min) {
+            fail(format!("Must be at least {} characters", min));
+        }
+        if let Some(max) = schema.get("maxLength").and_then(Value::as_u64).filter(|max| length > *max) {
+            fail(format!("Must be at most {} characters", max));
+        }
+    }
+
+    if let Some(items) = value.as_array() {
+        let count = items.len() as u64;
+        if let Some(min) = schema.get("minItems").and_then(Value::as_u64).filter(|min| count < *min) {
+            fail(format!("Must have at least {} items", min));
+        }
+        if let Some(max) = schema.get("maxItems").and_then(Value::as_u64).filter(|max| count > *max) {
+            fail(format!("Must have at most {} items", max));
+        }
+        if let Some(item_schema) = schema.get("items") {
+            for (i, item) in items.iter().enumerate() {
+                validate(item_schema, item, &format!("{}/{}", pointer, i), violations);
+            }
+        }
+    }
+
+    if let Some(object) = value.as_object() {
+        let properties = schema.get("properties").and_then(Value::as_object);
+
+        if let Some(required) = schema.get("required").and_then(Value::as_array) {
+            for name in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(name) {
+                    violations.push(Violation {
+                        pointer: format!("{}/{}", pointer, escape_pointer(name)),
+                        message: "Required property is missing".to_string(),
+                    });
+                }
+            }
+        }
+
+        for (name, property) in object {
+            let property_pointer = format!("{}/{}", pointer, escape_pointer(name));
+            match properties.and_then(|properties| properties.get(name)) {
+                Some(property_schema) => validate(property_schema, property, &property_pointer, violations),
+                None => match schema.get("additionalProperties") {
+                    Some(Value::Bool(false)) => violations.push(Violation {
+                        pointer: property_pointer,
+                        message: "Property is not allowed".to_string(),
+                    }),
+                    Some(additional) => validate(additional, property, &property_pointer, violations),
+                    None => {}
+                },
+            }
+        }
+    }
+
+    let branch_passes = |branch: &Value| {
+        let mut branch_violations = Vec::new();
+        validate(branch, value, pointer, &mut branch_violations);
+        branch_violations.is_empty()
+    };
+    if let Some(branches) = schema.get("allOf").and_then(Value::as_array) {
+        for branch in branches {
+            validate(branch, value, pointer, violations);
+        }
+    }
+    if let Some(branches) = schema.get("anyOf").and_then(Value::as_array) {
+        if !branches.iter().any(branch_passes) {
+            violations.push(Violation { pointer: pointer.to_string(), message: "Matches none of anyOf".to_string() });
+        }
+    }
+    if let Some(branches) = schema.get("oneOf").and_then(Value::as_array) {
+        let passing = branches.iter().filter(|branch| branch_passes(branch)).count();
+        if passing != 1 {
+            violations.push(Violation {
+                pointer: pointer.to_string(),
+                message: format!("Must match exactly one of oneOf, matched {}", passing),
+            });
+        }
+    }
+    if let Some(negated) = schema.get("not") {
+        if branch_passes(negated) {
+            violations.push(Violation { pointer: pointer.to_string(), message: "Must not match the not schema".to_string() });
+        }
+    }
+}
+
+/// Violations of `body` against `schema`; a body that is not JSON is one
+/// violation at the root
+pub fn validate_body(schema: &Value, body: &[u8]) -> Vec<Violation> {
+    let value: Value = match serde_json::from_slice(body) {
+        Ok(value) => value,
+        Err(e) => return vec![Violation { pointer: String::new(), message: format!("Body is not valid JSON: {}", e) }],
+    };
+
+    let mut violations = Vec::new();
+    validate(schema, &value, "", &mut violations);
+    violations
+}
+
+impl PublicGateway {
+    /// Attach, replace or (with None) remove a service's contract. Counters
+    /// restart when the contract changes.
+    pub fn set_service_contract(&mut self, service_key: &str, contract: Option<ServiceContract>) -> Result<(), String> {
+        if let Some(contract) = &contract {
+            for schema in [&contract.request_schema, &contract.response_schema].into_iter().flatten() {
+                check_schema(schema)?;
+            }
+        }
+
+        let service = self.service_registry.get_mut(service_key)
+            .ok_or("Service not found")?;
+        service.contract = contract.map(|contract| ServiceContract { stats: ContractStats::default(), ..contract });
+        Ok(())
+    }
+
+    /// 422 with pointer paths when the request body breaks the contract
+    pub(crate) fn check_request_contract(&mut self, service_key: &str, body: &[u8]) -> Option<HttpResponse> {
+        let contract = self.service_registry.get_mut(service_key)?.contract.as_mut()?;
+        let schema = contract.request_schema.as_ref()?;
+
+        let violations = validate_body(schema, body);
+        contract.stats.requests_checked += 1;
+        if violations.is_empty() {
+            return None;
+        }
+        contract.stats.requests_rejected += 1;
+        contract.stats.remember("request", violations.clone());
+
+        let response_body = serde_json::json!({
+            "error": "Request does not match the service contract",
+            "violations": violations
+        });
+        Some(HttpResponse {
+            status_code: 422,
+            headers: HashMap::from([
+                ("Content-Type".to_string(), "application/json".to_string()),
+            ]),
+            body: serde_json::to_vec(&response_body).unwrap_or_default(),
+        })
+    }
+
+    /// Monitoring only: count responses that break the contract
+    pub(crate) fn monitor_response_contract(&mut self, service_key: &str, body: &[u8]) {
+        let contract = match self.service_registry.get_mut(service_key).and_then(|s| s.contract.as_mut()) {
+            Some(contract) if contract.monitor_responses => contract,
+            _ => return,
+        };
+        let schema = match &contract.response_schema {
+            Some(schema) => schema,
+            None => return,
+        };
+
+        let violations = validate_body(schema, body);
+        contract.stats.responses_checked += 1;
+        if !violations.is_empty() {
+            contract.stats.response_violations += 1;
+            contract.stats.remember("response", violations);
+        }
+    }
+
+    /// GET /{wallet}/{service}/contract — schemas and violation rates
+    pub fn handle_contract_request(&self, wallet_address: &str, service_name: &str) -> Result<HttpResponse, String> {
+        let service_key = format!("{}_{}", wallet_address, service_name);
+        let service = self.service_registry.get(&service_key)
+            .ok_or("Service not found")?;
+        let contract = service.contract.as_ref()
+            .ok_or("Service has no contract")?;
+
+        let response_body = serde_json::to_vec(&serde_json::json!({
+            "service": service_key,
+            "contract": contract,
+            "request_violation_rate": contract.stats.request_violation_rate(),
+            "response_violation_rate": contract.stats.response_violation_rate()
+        })).map_err(|e| format!("Failed to serialize response: {}", e))?;
+
+        Ok(HttpResponse {
+            status_code: 200,
+            headers: HashMap::from([
+                ("Content-Type".to_string(), "application/json".to_string()),
+            ]),
+            body: response_body,
+        })
+    }
+}
@@ -0,0 +1,287 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::receipts::json_response;
+use crate::screening::is_operator;
+use crate::{HttpResponse, PublicGateway};
+use std::collections::HashMap;
+
+/// Request headers browsers may always send cross-origin
+const SAFELISTED_HEADERS: [&str; 3] = ["accept", "accept-language", "content-language"];
+
+/// Which browser origins may call a service, and with what. Applies while
+/// the service's `cors_enabled` is on; with it off, no origin may.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CorsPolicy {
+    pub allowed_origins: Vec<String>, // "*", "https://app.example" or "https://*.example.com" (subdomains only)
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>, // request headers beyond the CORS-safelisted ones
+    pub exposed_headers: Vec<String>, // response headers page scripts may read
+    pub max_age_secs: Option<u64>,    // how long browsers may cache a preflight
+    pub allow_credentials: bool,      // cookies and HTTP auth; needs listed origins, not "*"
+}
+
+impl Default for CorsPolicy {
+    /// Any origin, without credentials: what every service got before policies
+    fn default() -> Self {
+        let strings = |values: &[&str]| values.iter().map(|value| value.to_string()).collect();
+        Self {
+            allowed_origins: strings(&["*"]),
+            allowed_methods: strings(&["GET", "POST", "PUT", "DELETE"]),
+            allowed_headers: strings(&[
+                "Content-Type", "Authorization", "X-Payment-Token", "X-Wallet-Address", "X-Estimate-Id",
+                "X-Coupon-Code", "X-Game-Session", "Traceparent",
+            ]),
+            exposed_headers: strings(&[
+                "X-Receipt-Id", "X-Charge-USDC", "X-Estimate-Id", "X-Coupon-Code", "X-Discount-USDC",
+                "X-Experiment-Variant", "Traceparent",
+            ]),
+            max_age_secs: Some(600),
+            allow_credentials: false,
+        }
+    }
+}
+
+fn valid_origin(origin: &str) -> bool {
+    let Some(host) = origin.strip_prefix("https://").or_else(|| origin.strip_prefix("http://")) else {
+        return false;
+    };
+    let host = host.strip_prefix("*.").unwrap_or(host);
+    !host.is_empty() && !host.contains(['/', '*', ' ', '?', '#'])
+}
+
+impl CorsPolicy {
+    /// Refuse policies browsers would reject, and normalize the rest:
+    /// origins lowercased without a trailing slash, methods uppercased
+    pub fn validate(&mut self) -> Result<(), String> {
+        for origin in self.allowed_origins.iter_mut() {
+            *origin = origin.trim().trim_end_matches('/').to_ascii_lowercase();
+            if origin != "*" && !valid_origin(origin) {
+                return Err(format!("Invalid origin {}: expected \"*\" or scheme://host[:port]", origin));
+            }
+        }
+        if self.allow_credentials && self.allowed_origins.iter().any(|origin| origin == "*") {
+            return Err("allow_credentials needs listed origins; browsers refuse credentials with \"*\"".to_string());
+        }
+        for method in self.allowed_methods.iter_mut() {
+            *method = method.trim().to_ascii_uppercase();
+            if method.is_empty() || !method.chars().all(|c| c.is_ascii_alphabetic()) {
+                return Err(format!("Invalid method {}", method));
+            }
+        }
+        for header in self.allowed_headers.iter().chain(&self.exposed_headers) {
+            if header.is_empty() || !header.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+                return Err(format!("Invalid header name {}", header));
+            }
+        }
+        Ok(())
+    }
+
+    fn any_origin(&self) -> bool {
+        self.allowed_origins.iter().any(|origin| origin == "*")
+    }
+
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        let origin = origin.trim_end_matches('/').to_ascii_lowercase();
+        self.allowed_origins.iter().any(|allowed| {
+            if allowed == "*" || *allowed == origin {
+                return true;
+            }
+            // https://*.example.com: same scheme, any subdomain
+            match allowed.split_once("://*.") {
+                Some((scheme, domain)) => origin.strip_prefix(scheme)
+                    .and_then(|rest| rest.strip_prefix("://"))
+                    .is_some_and(|host| host.ends_with(&format!(".{}", domain))),
+                None => false,
+            }
+        })
+    }
+
+    /// The origin to answer with: "*" when any origin may call without
+    /// credentials, otherwise the caller's own origin if it's allowed
+    fn allow_origin_value(&self, origin: Option<&str>) -> Option<String> {
+        if self.any_origin() && !self.allow_credentials {
+            return Some("*".to_string());
+        }
+        origin.filter(|origin| self.allows_origin(origin)).map(str::to_string)
+    }
+
+    /// CORS headers for an actual (non-preflight) response
+    pub fn response_headers(&self, origin: Option<&str>) -> HashMap<String, String> {
+        let mut headers = HashMap::new();
+        let allow_origin = self.allow_origin_value(origin);
+        if allow_origin.as_deref() != Some("*") {
+            headers.insert("Vary".to_string(), "Origin".to_string()); // the answer depends on the caller
+        }
+        let Some(allow_origin) = allow_origin else { return headers };
+        headers.insert("Access-Control-Allow-Origin".to_string(), allow_origin);
+        if self.allow_credentials {
+            headers.insert("Access-Control-Allow-Credentials".to_string(), "true".to_string());
+        }
+        if !self.exposed_headers.is_empty() {
+            headers.insert("Access-Control-Expose-Headers".to_string(), self.exposed_headers.join(", "));
+        }
+        headers
+    }
+
+    /// Answer an OPTIONS request: a preflight when it carries Origin and
+    /// Access-Control-Request-Method, otherwise just the allowed methods
+    pub fn preflight(&self, headers: &HashMap<String, String>) -> Result<HttpResponse, String> {
+        let mut allow = self.allowed_methods.clone();
+        allow.push("OPTIONS".to_string());
+        let allow = allow.join(", ");
+        let plain = |status_code: u16, extra: HashMap<String, String>| {
+            let mut response_headers = HashMap::from([("Allow".to_string(), allow.clone())]);
+            response_headers.extend(extra);
+            HttpResponse { status_code, headers: response_headers, body: Vec::new() }
+        };
+
+        let origin = headers.get("Origin").map(String::as_str);
+        let (Some(origin), Some(method)) = (origin, headers.get("Access-Control-Request-Method")) else {
+            return Ok(plain(204, HashMap::new()));
+        };
+        let vary = HashMap::from([(
+            "Vary".to_string(),
+            "Origin, Access-Control-Request-Method, Access-Control-Request-Headers".to_string(),
+        )]);
+        let refuse = |error: String| -> Result<HttpResponse, String> {
+            let mut response = json_response(403, &serde_json::json!({ "error": error }))?;
+            response.headers.extend(vary.clone());
+            Ok(response)
+        };
+
+        let Some(allow_origin) = self.allow_origin_value(Some(origin)) else {
+            return refuse(format!("Origin {} may not call this service", origin));
+        };
+        let method = method.trim().to_ascii_uppercase();
+        if !self.allowed_methods.contains(&method) {
+            return refuse(format!("Method {} is not allowed; allowed: {}", method, self.allowed_methods.join(", ")));
+        }
+        let requested: Vec<&str> = headers.get("Access-Control-Request-Headers")
+            .map(|requested| requested.split(',').map(str::trim).filter(|header| !header.is_empty()).collect())
+            .unwrap_or_default();
+        let refused: Vec<&str> = requested.iter()
+            .copied()
+            .filter(|header| {
+                let header = header.to_ascii_lowercase();
+                !SAFELISTED_HEADERS.contains(&header.as_str())
+                    && !self.allowed_headers.iter().any(|allowed| allowed.eq_ignore_ascii_case(&header))
+            })
+            .collect();
+        if !refused.is_empty() {
+            return refuse(format!("Headers not allowed: {}", refused.join(", ")));
+        }
+
+        let mut response_headers = vary.clone();
+        response_headers.insert("Access-Control-Allow-Origin".to_string(), allow_origin);
+        response_headers.insert("Access-Control-Allow-Methods".to_string(), self.allowed_methods.join(", "));
+        if !requested.is_empty() {
+            response_headers.insert("Access-Control-Allow-Headers".to_string(), requested.join(", "));
+        }
+        if let Some(max_age_secs) = self.max_age_secs {
+            response_headers.insert("Access-Control-Max-Age".to_string(), max_age_secs.to_string());
+        }
+        if self.allow_credentials {
+            response_headers.insert("Access-Control-Allow-Credentials".to_string(), "true".to_string());
+        }
+        Ok(plain(204, response_headers))
+    }
+}
+
+impl PublicGateway {
+    /// The policy for a path: the service's for /{wallet}/{service}/...,
+    /// the default for the gateway's own routes. None when the service has
+    /// CORS turned off.
+    pub fn cors_policy_for(&self, path: &str) -> Option<CorsPolicy> {
+        let mut parts = path.trim_start_matches('/').split('/');
+        if let (Some(wallet_address), Some(service_name)) = (parts.next(), parts.next()) {
+            if let Some(service) = self.service_registry.get(&format!("{}_{}", wallet_address, service_name)) {
+                return service.cors_enabled.then(|| service.cors.clone());
+            }
+        }
+        Some(CorsPolicy::default())
+    }
+
+    /// CORS headers for a response to `path`, from the caller's Origin
+    pub fn cors_response_headers(&self, path: &str, headers: &HashMap<String, String>) -> HashMap<String, String> {
+        self.cors_policy_for(path)
+            .map(|policy| policy.response_headers(headers.get("Origin").map(String::as_str)))
+            .unwrap_or_default()
+    }
+
+    /// Replace whatever CORS headers a handler set with the path's policy
+    pub(crate) fn apply_cors(&self, path: &str, headers: &HashMap<String, String>, response: &mut HttpResponse) {
+        response.headers.retain(|name, _| !name.starts_with("Access-Control-"));
+        response.headers.extend(self.cors_response_headers(path, headers));
+    }
+
+    /// OPTIONS on any path: answered from its policy, before rate limits,
+    /// payment or authentication, none of which a preflight carries
+    pub(crate) fn handle_preflight(&self, path: &str, headers: &HashMap<String, String>) -> Result<HttpResponse, String> {
+        match self.cors_policy_for(path) {
+            Some(policy) => policy.preflight(headers),
+            None if headers.contains_key("Access-Control-Request-Method") => {
+                json_response(403, &serde_json::json!({ "error": "Cross-origin calls to this service are turned off" }))
+            }
+            None => Ok(HttpResponse { status_code: 204, headers: HashMap::new(), body: Vec::new() }),
+        }
+    }
+
+    pub fn set_cors_policy(&mut self, service_key: &str, enabled: bool, mut policy: CorsPolicy) -> Result<(), String> {
+        policy.validate()?;
+        let service = self.service_registry.get_mut(service_key).ok_or("Service not found")?;
+        service.cors_enabled = enabled;
+        service.cors = policy;
+        Ok(())
+    }
+
+    /// GET and PUT /{wallet}/{service}/cors: the service's policy, as the
+    /// owner (or operator) for changes
+    pub(crate) fn handle_cors_request(&mut self, wallet_address: &str, service_name: &str, method: &str,
+                                      headers: &HashMap<String, String>, body: &[u8]) -> Result<HttpResponse, String> {
+        let service_key = format!("{}_{}", wallet_address, service_name);
+        let service = self.service_registry.get(&service_key).ok_or("Service not found")?;
+        let enabled = service.cors_enabled;
+        match method {
+            "GET" => json_response(200, &serde_json::json!({
+                "service": service_key,
+                "enabled": enabled,
+                "policy": service.cors,
+            })),
+            "PUT" => {
+                if !is_operator(headers) {
+                    match self.authenticate_wallet(headers) {
+                        Ok(caller) if caller == wallet_address => {}
+                        Ok(_) => return json_response(403, &serde_json::json!({ "error": "Only the service owner can set its CORS policy" })),
+                        Err(e) => return json_response(401, &serde_json::json!({ "error": e })),
+                    }
+                }
+                // The policy's fields replace it; "enabled" alone only flips the switch
+                let mut update: Value = serde_json::from_slice(body)
+                    .map_err(|e| format!("Invalid CORS policy: {}", e))?;
+                let fields = update.as_object_mut().ok_or("A CORS policy must be an object")?;
+                let enabled = match fields.remove("enabled") {
+                    Some(Value::Bool(enabled)) => enabled,
+                    Some(_) => return Err("enabled must be true or false".to_string()),
+                    None => enabled,
+                };
+                let policy = if fields.is_empty() {
+                    self.service_registry[&service_key].cors.clone()
+                } else {
+                    serde_json::from_value(update).map_err(|e| format!("Invalid CORS policy: {}", e))?
+                };
+                if let Err(e) = self.set_cors_policy(&service_key, enabled, policy) {
+                    return json_response(422, &serde_json::json!({ "error": e }));
+                }
+                println!("🌍 CORS policy updated for {}", service_key);
+                let service = &self.service_registry[&service_key];
+                json_response(200, &serde_json::json!({
+                    "service": service_key,
+                    "enabled": service.cors_enabled,
+                    "policy": service.cors,
+                }))
+            }
+            _ => Err("Unsupported CORS request".to_string()),
+        }
+    }
+}
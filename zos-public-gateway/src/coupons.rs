@@ -0,0 +1,455 @@
+use serde::{Deserialize, Serialize};
+use crate::receipts::json_response;
+use crate::{HttpResponse, PublicGateway, ReferralRecord, ReferralStatus};
+use std::collections::HashMap;
+
+const MAX_REDEMPTION_LOG: usize = 10_000;
+
+/// Coupons by code, and a log of recent redemptions for analytics
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CouponBook {
+    pub coupons: HashMap<String, Coupon>,
+    pub redemptions: Vec<CouponRedemption>, // newest last, capped
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Discount {
+    Percentage(f64), // 0-100, of the call's charge
+    FixedUsdc(f64),  // off the call's charge, never below zero
+}
+
+/// Who funds the discount: a service owner for their own services, or the
+/// platform for any service
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum CouponIssuer {
+    Platform,
+    ServiceOwner(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Coupon {
+    pub code: String,
+    pub issuer: CouponIssuer,
+    pub discount: Discount,
+    pub max_redemptions: Option<u32>,  // across all wallets
+    pub max_per_wallet: Option<u32>,
+    pub expires_at: Option<u64>,
+    pub eligible_services: Vec<String>, // service keys; empty: all of the issuer's services
+    pub referral_link_id: Option<String>, // promo conversions attributed to this link
+    pub disabled: bool,
+    pub created_at: u64,
+    pub redemption_count: u32,
+    pub redemptions_by_wallet: HashMap<String, u32>,
+    pub discount_total_usdc: f64,
+    pub conversions: u32, // wallets whose first referral came through this coupon
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CouponRequest {
+    pub code: String,
+    pub discount: Discount,
+    #[serde(default)]
+    pub max_redemptions: Option<u32>,
+    #[serde(default)]
+    pub max_per_wallet: Option<u32>,
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+    #[serde(default)]
+    pub eligible_services: Vec<String>,
+    #[serde(default)]
+    pub referral_link_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CouponRedemption {
+    pub code: String,
+    pub wallet: String,
+    pub service_key: String,
+    pub list_price_usdc: f64,
+    pub discount_usdc: f64,
+    pub timestamp: u64,
+}
+
+impl Coupon {
+    fn covers(&self, service_key: &str) -> bool {
+        if !self.eligible_services.is_empty() {
+            return self.eligible_services.iter().any(|key| key == service_key);
+        }
+        match &self.issuer {
+            CouponIssuer::Platform => true,
+            CouponIssuer::ServiceOwner(owner) => service_key.starts_with(&format!("{}_", owner)),
+        }
+    }
+
+    /// Why this coupon can't be used by `wallet` on `service_key` right now
+    fn refusal(&self, service_key: &str, wallet: &str, now: u64) -> Option<&'static str> {
+        if self.disabled {
+            return Some("Coupon has been withdrawn");
+        }
+        if self.expires_at.is_some_and(|expires_at| now > expires_at) {
+            return Some("Coupon has expired");
+        }
+        if !self.covers(service_key) {
+            return Some("Coupon is not valid for this service");
+        }
+        if self.max_redemptions.is_some_and(|max| self.redemption_count >= max) {
+            return Some("Coupon has been fully redeemed");
+        }
+        let used = self.redemptions_by_wallet.get(wallet).copied().unwrap_or(0);
+        if self.max_per_wallet.is_some_and(|max| used >= max) {
+            return Some("Coupon already used the maximum times by this wallet");
+        }
+        None
+    }
+
+    /// Same terms with nothing redeemed; the sandbox has no referral links
+    pub(crate) fn for_sandbox(&self) -> Coupon {
+        Coupon {
+            referral_link_id: None,
+            redemption_count: 0,
+            redemptions_by_wallet: HashMap::new(),
+            discount_total_usdc: 0.0,
+            conversions: 0,
+            ..self.clone()
+        }
+    }
+
+    fn discount_for(&self, amount_usdc: f64) -> f64 {
+        match self.discount {
+            Discount::Percentage(percent) => amount_usdc * percent.clamp(0.0, 100.0) / 100.0,
+            Discount::FixedUsdc(usdc) => usdc.max(0.0).min(amount_usdc),
+        }
+    }
+}
+
+fn normalize_code(code: &str) -> String {
+    code.trim().to_uppercase()
+}
+
+impl PublicGateway {
+    pub fn create_coupon(&mut self, issuer: CouponIssuer, request: CouponRequest) -> Result<Coupon, String> {
+        let code = normalize_code(&request.code);
+        if code.len() < 4 || !code.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err("Coupon codes are at least 4 letters, digits, '-' or '_'".to_string());
+        }
+        if self.coupons.coupons.contains_key(&code) {
+            return Err("Coupon code already exists".to_string());
+        }
+        match request.discount {
+            Discount::Percentage(percent) if percent.is_nan() || percent <= 0.0 || percent > 100.0 =>
+                return Err("Percentage discount must be in (0, 100]".to_string()),
+            Discount::FixedUsdc(usdc) if usdc.is_nan() || usdc <= 0.0 =>
+                return Err("Fixed discount must be positive".to_string()),
+            _ => {}
+        }
+
+        // Owners discount their own services only
+        if let CouponIssuer::ServiceOwner(owner) = &issuer {
+            let prefix = format!("{}_", owner);
+            if let Some(key) = request.eligible_services.iter().find(|key| !key.starts_with(&prefix)) {
+                return Err(format!("{} is not one of your services", key));
+            }
+        }
+        if let Some(key) = request.eligible_services.iter().find(|key| !self.service_registry.contains_key(*key)) {
+            return Err(format!("Unknown service {}", key));
+        }
+        if let Some(link_id) = &request.referral_link_id {
+            let link = self.commission_system.as_ref()
+                .and_then(|commission_system| commission_system.referral_links.get(link_id))
+                .ok_or("Unknown referral link")?;
+            if let CouponIssuer::ServiceOwner(owner) = &issuer {
+                if &link.referrer_wallet != owner {
+                    return Err("Referral link belongs to another wallet".to_string());
+                }
+            }
+        }
+
+        let coupon = Coupon {
+            code: code.clone(),
+            issuer,
+            discount: request.discount,
+            max_redemptions: request.max_redemptions,
+            max_per_wallet: request.max_per_wallet,
+            expires_at: request.expires_at,
+            eligible_services: request.eligible_services,
+            referral_link_id: request.referral_link_id,
+            disabled: false,
+            created_at: chrono::Utc::now().timestamp() as u64,
+            redemption_count: 0,
+            redemptions_by_wallet: HashMap::new(),
+            discount_total_usdc: 0.0,
+            conversions: 0,
+        };
+        self.coupons.coupons.insert(code.clone(), coupon.clone());
+
+        println!("🎟️  Coupon {} created", code);
+        Ok(coupon)
+    }
+
+    /// Refuse a call up front if its `X-Coupon-Code` can't be used, so a bad
+    /// code never turns into a full-price charge. Per-wallet limits count
+    /// the authenticated caller, not a wallet the request merely names.
+    pub(crate) fn check_coupon(&self, service_key: &str, caller: Option<&str>,
+                               headers: &HashMap<String, String>) -> Result<(), String> {
+        let code = match headers.get("X-Coupon-Code") {
+            Some(code) => normalize_code(code),
+            None => return Ok(()),
+        };
+        let wallet = caller
+            .ok_or("Coupons need an authenticated wallet: a bearer API key or a signed challenge")?;
+        let coupon = self.coupons.coupons.get(&code)
+            .ok_or("Unknown coupon code")?;
+        match coupon.refusal(service_key, wallet, chrono::Utc::now().timestamp() as u64) {
+            Some(reason) => Err(reason.to_string()),
+            None => Ok(()),
+        }
+    }
+
+    /// Apply `X-Coupon-Code` to a call's charge at payment time. Returns the
+    /// discounted amount and the redemption, if a coupon was used.
+    pub(crate) fn redeem_coupon(&mut self, service_key: &str, caller: Option<&str>, headers: &HashMap<String, String>,
+                                amount_usdc: f64) -> Result<(f64, Option<CouponRedemption>), String> {
+        self.check_coupon(service_key, caller, headers)?;
+        let (code, wallet) = match (headers.get("X-Coupon-Code"), caller) {
+            (Some(code), Some(wallet)) => (normalize_code(code), wallet.to_string()),
+            _ => return Ok((amount_usdc, None)),
+        };
+
+        let coupon = self.coupons.coupons.get_mut(&code)
+            .ok_or("Unknown coupon code")?;
+        let discount = coupon.discount_for(amount_usdc);
+        let first_use = !coupon.redemptions_by_wallet.contains_key(&wallet);
+        coupon.redemption_count += 1;
+        *coupon.redemptions_by_wallet.entry(wallet.clone()).or_insert(0) += 1;
+        coupon.discount_total_usdc += discount;
+        let referral_link_id = coupon.referral_link_id.clone();
+
+        if first_use {
+            if let Some(link_id) = referral_link_id {
+                if self.attribute_promo_conversion(&link_id, &wallet) {
+                    if let Some(coupon) = self.coupons.coupons.get_mut(&code) {
+                        coupon.conversions += 1;
+                    }
+                }
+            }
+        }
+
+        let redemption = CouponRedemption {
+            code,
+            wallet,
+            service_key: service_key.to_string(),
+            list_price_usdc: amount_usdc,
+            discount_usdc: discount,
+            timestamp: chrono::Utc::now().timestamp() as u64,
+        };
+        self.coupons.redemptions.push(redemption.clone());
+        if self.coupons.redemptions.len() > MAX_REDEMPTION_LOG {
+            let excess = self.coupons.redemptions.len() - MAX_REDEMPTION_LOG;
+            self.coupons.redemptions.drain(0..excess);
+        }
+
+        Ok((amount_usdc - discount, Some(redemption)))
+    }
+
+    /// A wallet's first coupon use counts as a conversion for the coupon's
+    /// referral link, unless the wallet was already referred to its owner
+    fn attribute_promo_conversion(&mut self, link_id: &str, wallet: &str) -> bool {
+        let commission_system = match self.commission_system.as_mut() {
+            Some(commission_system) => commission_system,
+            None => return false,
+        };
+        let link = match commission_system.referral_links.get_mut(link_id) {
+            Some(link) => link,
+            None => return false,
+        };
+
+        let referrer_wallet = link.referrer_wallet.clone();
+        let referral_key = format!("{}_{}", referrer_wallet, wallet);
+        if commission_system.referral_tracking.contains_key(&referral_key) {
+            return false;
+        }
+
+        link.conversion_count += 1;
+        link.promo_conversions += 1;
+        commission_system.insert_referral(referral_key, ReferralRecord {
+            referrer_wallet: referrer_wallet.clone(),
+            referee_wallet: wallet.to_string(),
+            referral_code: link_id.to_string(),
+            first_transaction_at: chrono::Utc::now().timestamp() as u64,
+            total_volume: 0.0,
+            total_commissions_earned: 0.0,
+            status: ReferralStatus::Active,
+        });
+        if let Err(e) = self.recalculate_tier(&referrer_wallet) {
+            eprintln!("⚠️  Tier recalculation for {} failed: {}", referrer_wallet, e);
+        }
+        true
+    }
+
+    pub fn coupon_stats(&self, code: &str) -> Option<serde_json::Value> {
+        let coupon = self.coupons.coupons.get(code)?;
+        let mut by_service: HashMap<&str, (u32, f64)> = HashMap::new();
+        for redemption in self.coupons.redemptions.iter().filter(|r| r.code == coupon.code) {
+            let entry = by_service.entry(&redemption.service_key).or_insert((0, 0.0));
+            entry.0 += 1;
+            entry.1 += redemption.discount_usdc;
+        }
+
+        Some(serde_json::json!({
+            "coupon": coupon,
+            "redemptions": coupon.redemption_count,
+            "unique_wallets": coupon.redemptions_by_wallet.len(),
+            "discount_total_usdc": coupon.discount_total_usdc,
+            "conversions": coupon.conversions,
+            "conversion_rate": if coupon.redemptions_by_wallet.is_empty() { 0.0 } else {
+                coupon.conversions as f64 / coupon.redemptions_by_wallet.len() as f64 * 100.0
+            },
+            "recent_by_service": by_service.iter().map(|(service_key, (count, discount))| serde_json::json!({
+                "service_key": service_key,
+                "redemptions": count,
+                "discount_usdc": discount
+            })).collect::<Vec<_>>()
+        }))
+    }
+
+    /// Caller allowed to manage coupons: the platform key, else the wallet
+    /// behind a bearer API key or signed challenge
+    fn coupon_caller(&mut self, headers: &HashMap<String, String>) -> Result<CouponIssuer, String> {
+        let platform_key = std::env::var("ZOS_PLATFORM_COUPON_KEY").ok().filter(|key| !key.is_empty());
+        if let (Some(expected), Some(given)) = (platform_key, headers.get("X-Platform-Key")) {
+            return if &expected == given {
+                Ok(CouponIssuer::Platform)
+            } else {
+                Err("Invalid platform key".to_string())
+            };
+        }
+        self.authenticate_wallet(headers).map(CouponIssuer::ServiceOwner)
+    }
+
+    /// POST /coupons, GET /coupons/{code}, GET /coupons/{code}/stats,
+    /// DELETE /coupons/{code}
+    pub fn handle_coupon_request(&mut self, path: &str, method: &str,
+                                 headers: &HashMap<String, String>,
+                                 body: &[u8]) -> Result<HttpResponse, String> {
+        let rest = path.trim_start_matches("/coupons").trim_matches('/');
+        let (code, action) = match rest.split_once('/') {
+            Some((code, action)) => (normalize_code(code), action),
+            None => (normalize_code(rest), ""),
+        };
+
+        match (method, code.as_str(), action) {
+            ("POST", "", "") => {
+                let request: CouponRequest = serde_json::from_slice(body)
+                    .map_err(|e| format!("Invalid coupon: {}", e))?;
+                let caller = match self.coupon_caller(headers) {
+                    Ok(caller) => caller,
+                    Err(e) => return json_response(401, &serde_json::json!({ "error": e })),
+                };
+                match self.create_coupon(caller, request) {
+                    Ok(coupon) => json_response(201, &coupon),
+                    Err(e) => json_response(400, &serde_json::json!({ "error": e })),
+                }
+            }
+            // Public: what a code is worth, without who used it
+            ("GET", code, "") => match self.coupons.coupons.get(code) {
+                Some(coupon) => json_response(200, &serde_json::json!({
+                    "code": coupon.code,
+                    "discount": coupon.discount,
+                    "expires_at": coupon.expires_at,
+                    "eligible_services": coupon.eligible_services,
+                    "valid": !coupon.disabled
+                        && coupon.expires_at.is_none_or(|expires_at| chrono::Utc::now().timestamp() as u64 <= expires_at)
+                        && coupon.max_redemptions.is_none_or(|max| coupon.redemption_count < max),
+                })),
+                None => json_response(404, &serde_json::json!({ "error": "Unknown coupon code" })),
+            },
+            ("GET", code, "stats") | ("DELETE", code, "") => {
+                let caller = match self.coupon_caller(headers) {
+                    Ok(caller) => caller,
+                    Err(e) => return json_response(401, &serde_json::json!({ "error": e })),
+                };
+                let coupon = match self.coupons.coupons.get_mut(code) {
+                    Some(coupon) => coupon,
+                    None => return json_response(404, &serde_json::json!({ "error": "Unknown coupon code" })),
+                };
+                if caller != CouponIssuer::Platform && caller != coupon.issuer {
+                    return json_response(403, &serde_json::json!({ "error": "Not your coupon" }));
+                }
+                if method == "DELETE" {
+                    coupon.disabled = true;
+                    return json_response(200, &serde_json::json!({ "code": code, "disabled": true }));
+                }
+                json_response(200, &self.coupon_stats(code))
+            }
+            _ => Err("Unsupported coupon request".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PricingTier;
+
+    fn gateway() -> PublicGateway {
+        let mut gateway = PublicGateway::new("gateway.test");
+        gateway.initialize_commission_system();
+        gateway.register_wallet_endpoint("owner1", "owner", vec![4001]).unwrap();
+        gateway.add_service("owner1", "api", 4001, PricingTier::Basic).unwrap();
+        gateway
+    }
+
+    fn signed_in(gateway: &mut PublicGateway, wallet: &str) -> HashMap<String, String> {
+        let (_, api_key) = gateway.api_keys.issue(wallet, "test", chrono::Utc::now().timestamp() as u64);
+        HashMap::from([("Authorization".to_string(), format!("Bearer {}", api_key))])
+    }
+
+    fn create(gateway: &mut PublicGateway, headers: &HashMap<String, String>) -> u16 {
+        let body = br#"{"code":"LAUNCH20","discount":{"Percentage":20},"max_per_wallet":1}"#;
+        gateway.handle_coupon_request("/coupons", "POST", headers, body).unwrap().status_code
+    }
+
+    #[test]
+    fn test_naming_a_wallet_does_not_make_you_its_owner() {
+        let mut gateway = gateway();
+        let spoofed = HashMap::from([("X-Wallet-Address".to_string(), "owner1".to_string())]);
+        assert_eq!(create(&mut gateway, &spoofed), 401);
+        assert!(gateway.coupons.coupons.is_empty());
+
+        let owner = signed_in(&mut gateway, "owner1");
+        assert_eq!(create(&mut gateway, &owner), 201);
+        assert_eq!(gateway.coupons.coupons["LAUNCH20"].issuer, CouponIssuer::ServiceOwner("owner1".to_string()));
+
+        // Stats and withdrawal are the owner's too
+        let response = gateway.handle_coupon_request("/coupons/LAUNCH20", "DELETE", &spoofed, &[]).unwrap();
+        assert_eq!(response.status_code, 401);
+        let stranger = signed_in(&mut gateway, "stranger");
+        let response = gateway.handle_coupon_request("/coupons/LAUNCH20", "DELETE", &stranger, &[]).unwrap();
+        assert_eq!(response.status_code, 403);
+        assert!(!gateway.coupons.coupons["LAUNCH20"].disabled);
+    }
+
+    #[test]
+    fn test_per_wallet_limits_count_the_authenticated_caller() {
+        let mut gateway = gateway();
+        let owner = signed_in(&mut gateway, "owner1");
+        assert_eq!(create(&mut gateway, &owner), 201);
+        let headers = HashMap::from([
+            ("X-Coupon-Code".to_string(), "launch20".to_string()),
+            ("X-Wallet-Address".to_string(), "someone-else".to_string()),
+        ]);
+
+        // No proof of who is calling: no discount
+        assert!(gateway.redeem_coupon("owner1_api", None, &headers, 1.0).is_err());
+
+        let (charged, redemption) = gateway.redeem_coupon("owner1_api", Some("buyer1"), &headers, 1.0).unwrap();
+        assert!((charged - 0.8).abs() < 1e-9);
+        assert_eq!(redemption.unwrap().wallet, "buyer1");
+
+        // A different X-Wallet-Address does not reset the caller's allowance
+        let mut renamed = headers.clone();
+        renamed.insert("X-Wallet-Address".to_string(), "fresh-wallet".to_string());
+        assert!(gateway.redeem_coupon("owner1_api", Some("buyer1"), &renamed, 1.0).is_err());
+        assert!(gateway.redeem_coupon("owner1_api", Some("buyer2"), &renamed, 1.0).is_ok());
+    }
+}
@@ -0,0 +1,246 @@
+use crate::commission_events::CommissionEventKind;
+use crate::receipts::json_response;
+use crate::screening::is_operator;
+use crate::{HttpResponse, PublicGateway};
+use std::collections::HashMap;
+
+impl PublicGateway {
+    /// Everything the gateway holds about a wallet, for `/api/v1/export/{wallet}`
+    /// on the node, which fetches it from GET /{wallet}/export
+    pub fn export_wallet_data(&self, wallet_address: &str) -> serde_json::Value {
+        let payments = self.payment_processor.payment_history
+            .values()
+            .flatten()
+            .filter(|payment| payment.payer_wallet == wallet_address)
+            .collect::<Vec<_>>();
+
+        let services = self.service_registry.values()
+            .filter(|service| service.wallet_address == wallet_address)
+            .collect::<Vec<_>>();
+        let plan_prefix = format!("{}_", wallet_address);
+
+        let (earnings, events, commissions, withdrawals, payouts, referral_links, referrals, plans) = match &self.commission_system {
+            Some(system) => (
+                serde_json::json!(system.earnings_ledger.get(wallet_address)),
+                serde_json::json!(system.events.get(wallet_address)),
+                serde_json::json!(system.commission_history.get(wallet_address)),
+                serde_json::json!(system.withdrawals.values()
+                    .filter(|w| w.wallet_address == wallet_address)
+                    .collect::<Vec<_>>()),
+                serde_json::json!(system.payout_history.get(wallet_address)),
+                serde_json::json!(system.referral_links.values()
+                    .filter(|link| link.referrer_wallet == wallet_address)
+                    .collect::<Vec<_>>()),
+                serde_json::json!(system.referral_tracking.values()
+                    .filter(|r| r.referrer_wallet == wallet_address || r.referee_wallet == wallet_address)
+                    .collect::<Vec<_>>()),
+                serde_json::json!(system.commission_plans.iter()
+                    .filter(|(service_key, _)| service_key.starts_with(&plan_prefix))
+                    .collect::<std::collections::HashMap<_, _>>()),
+            ),
+            None => Default::default(),
+        };
+
+        serde_json::json!({
+            "endpoint": self.wallet_endpoints.get(wallet_address),
+            "services": services,
+            "payments": payments,
+            "earnings": earnings,
+            "earnings_events": events,
+            "commission_history": commissions,
+            "withdrawals": withdrawals,
+            "payouts": payouts,
+            "referral_links": referral_links,
+            "referrals": referrals,
+            "commission_plans": plans,
+            "consumer_usage": self.consumer_usage.wallets.get(wallet_address),
+            "metered_usage": self.usage_meter.wallets.get(wallet_address),
+            "api_keys": self.api_keys.wallet_keys(wallet_address)
+        })
+    }
+
+    /// Replace a wallet with `pseudonym` across ledger records and drop its live
+    /// endpoints. Amounts, ids and timestamps are untouched so totals still
+    /// reconcile. Returns the number of records changed.
+    pub fn anonymize_wallet(&mut self, wallet_address: &str, pseudonym: &str) -> usize {
+        let mut changed = 0;
+
+        if self.wallet_endpoints.remove(wallet_address).is_some() {
+            changed += 1;
+        }
+
+        // Keys only ever act for the wallet, so they go rather than move
+        let before = self.api_keys.keys.len();
+        self.api_keys.keys.retain(|_, key| key.wallet_address != wallet_address);
+        changed += before - self.api_keys.keys.len();
+
+        let before = self.service_registry.len();
+        self.service_registry.retain(|_, service| service.wallet_address != wallet_address);
+        changed += before - self.service_registry.len();
+
+        for experiment in self.experiments.experiments.values_mut() {
+            if let Some(variant) = experiment.seen.remove(wallet_address) {
+                experiment.seen.insert(pseudonym.to_string(), variant);
+                changed += 1;
+            }
+        }
+
+        if let Some(days) = self.consumer_usage.wallets.remove(wallet_address) {
+            changed += days.len();
+            self.consumer_usage.wallets.insert(pseudonym.to_string(), days);
+        }
+
+        if let Some(buckets) = self.usage_meter.wallets.remove(wallet_address) {
+            changed += buckets.len();
+            self.usage_meter.wallets.insert(pseudonym.to_string(), buckets);
+        }
+
+        for payment in self.payment_processor.payment_history.values_mut().flatten() {
+            if payment.payer_wallet == wallet_address {
+                payment.payer_wallet = pseudonym.to_string();
+                changed += 1;
+            }
+        }
+
+        if let Some(system) = self.commission_system.as_mut() {
+            if let Some(mut account) = system.earnings_ledger.remove(wallet_address) {
+                account.wallet_address = pseudonym.to_string();
+                system.earnings_ledger.insert(pseudonym.to_string(), account);
+                changed += 1;
+            }
+
+            // Erasure outranks append-only: events keep their amounts, not the wallet
+            if let Some(mut events) = system.events.remove(wallet_address) {
+                for event in &mut events {
+                    event.wallet_address = pseudonym.to_string();
+                    if let CommissionEventKind::CarriedOver { account } = &mut event.kind {
+                        account.wallet_address = pseudonym.to_string();
+                    }
+                }
+                changed += events.len();
+                system.events.insert(pseudonym.to_string(), events);
+            }
+
+            if let Some(mut payments) = system.commission_history.remove(wallet_address) {
+                for payment in &mut payments {
+                    payment.recipient_wallet = pseudonym.to_string();
+                }
+                changed += payments.len();
+                system.commission_history.insert(pseudonym.to_string(), payments);
+            }
+
+            for withdrawal in system.withdrawals.values_mut() {
+                if withdrawal.wallet_address == wallet_address {
+                    withdrawal.wallet_address = pseudonym.to_string();
+                    changed += 1;
+                }
+            }
+            for payout in system.payouts.values_mut() {
+                if payout.wallet_address == wallet_address {
+                    payout.wallet_address = pseudonym.to_string();
+                    changed += 1;
+                }
+            }
+            if let Some(mut records) = system.payout_history.remove(wallet_address) {
+                for record in &mut records {
+                    record.wallet_address = pseudonym.to_string();
+                }
+                changed += records.len();
+                system.payout_history.insert(pseudonym.to_string(), records);
+            }
+
+            for link in system.referral_links.values_mut() {
+                if link.referrer_wallet == wallet_address {
+                    link.referrer_wallet = pseudonym.to_string();
+                    changed += 1;
+                }
+            }
+
+            for link in system.short_links.values_mut() {
+                if link.owner_wallet == wallet_address {
+                    link.owner_wallet = pseudonym.to_string();
+                    link.disabled = true;
+                    changed += 1;
+                }
+            }
+
+            // Plans stay for auditing past payments, under the pseudonym's service keys
+            let prefix = format!("{}_", wallet_address);
+            let rekey = |service_key: &str| service_key.strip_prefix(&prefix)
+                .map(|service| format!("{}_{}", pseudonym, service));
+            let plan_keys = system.commission_plans.keys()
+                .filter(|service_key| service_key.starts_with(&prefix))
+                .cloned()
+                .collect::<Vec<_>>();
+            for key in plan_keys {
+                if let (Some(mut plans), Some(new_key)) = (system.commission_plans.remove(&key), rekey(&key)) {
+                    for plan in &mut plans {
+                        plan.service_key = new_key.clone();
+                    }
+                    changed += plans.len();
+                    system.commission_plans.insert(new_key, plans);
+                }
+            }
+            for payment in system.commission_history.values_mut().flatten() {
+                if let Some(new_key) = payment.service_key.as_deref().and_then(&rekey) {
+                    payment.service_key = Some(new_key);
+                    changed += 1;
+                }
+            }
+
+            let referral_keys = system.referral_tracking.iter()
+                .filter(|(_, r)| r.referrer_wallet == wallet_address || r.referee_wallet == wallet_address)
+                .map(|(key, _)| key.clone())
+                .collect::<Vec<_>>();
+
+            for key in referral_keys {
+                if let Some(mut record) = system.referral_tracking.remove(&key) {
+                    if record.referrer_wallet == wallet_address {
+                        record.referrer_wallet = pseudonym.to_string();
+                    }
+                    if record.referee_wallet == wallet_address {
+                        record.referee_wallet = pseudonym.to_string();
+                    }
+                    let new_key = format!("{}_{}", record.referrer_wallet, record.referee_wallet);
+                    system.referral_tracking.insert(new_key, record);
+                    changed += 1;
+                }
+            }
+            system.rebuild_referee_index();
+        }
+
+        self.flush_journal();
+        changed += self.journal.anonymize(wallet_address, pseudonym);
+
+        println!("🗑️  Gateway anonymized {} records → {}", changed, pseudonym);
+
+        changed
+    }
+
+    /// GET /{wallet}/export and POST /{wallet}/erase ({"pseudonym"}), as the
+    /// wallet or an operator (the node does both on the wallet's behalf)
+    pub(crate) fn handle_wallet_data_request(&mut self, wallet_address: &str, action: &str, method: &str,
+                                             headers: &HashMap<String, String>, body: &[u8]) -> Result<HttpResponse, String> {
+        if !is_operator(headers) {
+            match self.authenticate_wallet(headers) {
+                Ok(caller) if caller == wallet_address => {}
+                Ok(_) => return json_response(403, &serde_json::json!({ "error": "Only the wallet itself can export or erase its data" })),
+                Err(e) => return json_response(401, &serde_json::json!({ "error": e })),
+            }
+        }
+
+        match (method, action) {
+            ("GET", "export") => json_response(200, &self.export_wallet_data(wallet_address)),
+            ("POST", "erase") => {
+                let request: serde_json::Value = serde_json::from_slice(body)
+                    .map_err(|e| format!("Invalid erasure request: {}", e))?;
+                let pseudonym = request["pseudonym"].as_str()
+                    .filter(|pseudonym| !pseudonym.is_empty() && *pseudonym != wallet_address)
+                    .ok_or("pseudonym is required")?;
+                let anonymized = self.anonymize_wallet(wallet_address, pseudonym);
+                json_response(200, &serde_json::json!({ "pseudonym": pseudonym, "anonymized_records": anonymized }))
+            }
+            _ => Err("Unsupported wallet data request".to_string()),
+        }
+    }
+}
@@ -0,0 +1,513 @@
+use serde::{Deserialize, Serialize};
+use ed25519_dalek::{Signature, Signer, Verifier, VerifyingKey};
+use sha2::{Digest, Sha256};
+use crate::receipts::{from_hex, json_response, to_hex};
+use crate::screening::is_operator;
+use crate::{CommissionType, HttpResponse, PublicGateway};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+/// Cache-only federation members. Edges serve public, free GET responses
+/// and static assets the gateway lists in a signed manifest; they never
+/// see paid traffic. An operator approves each one, and the gateway
+/// redirects callers to them; only hits it routed are paid, as commissions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EdgeCache {
+    pub nodes: HashMap<String, EdgeNode>,
+    pub entries: HashMap<String, ManifestEntry>, // by path
+    pub manifest_ttl_secs: u64, // edges refetch the manifest this often
+    pub reward_per_1k_hits_usdc: f64,
+    pub max_hits_per_sec: u64, // per node; hits above this are not paid
+    pub report_retention: usize,
+    pub reports: VecDeque<AcceptedReport>,
+}
+
+impl Default for EdgeCache {
+    fn default() -> Self {
+        Self {
+            nodes: HashMap::new(),
+            entries: HashMap::new(),
+            manifest_ttl_secs: 300,
+            reward_per_1k_hits_usdc: 0.05,
+            max_hits_per_sec: 200,
+            report_retention: 1000,
+            reports: VecDeque::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum EdgeStatus {
+    Pending, // joined, waiting for an operator
+    Active,
+    Suspended,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EdgeNode {
+    pub node_id: String,
+    pub operator_wallet: String, // where rewards go
+    pub public_key: String,      // hex ed25519 key the node signs reports with
+    pub region: String,
+    #[serde(default)]
+    pub base_url: String, // where the gateway redirects callers, e.g. https://edge1.example
+    pub status: EdgeStatus,
+    pub joined_at: u64,
+    pub reported_until: u64, // end of the last accepted report period
+    pub hits: u64,
+    pub misses: u64,
+    pub bytes_served: u64,
+    pub rewarded_usdc: f64,
+    #[serde(default)]
+    pub routed: BTreeMap<String, u64>, // path -> hits the gateway sent here, not yet paid
+}
+
+impl EdgeNode {
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
+}
+
+/// One cacheable response: edges may serve a body only if it hashes to
+/// `content_hash`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub path: String,
+    pub content_hash: String, // sha256 hex of the body
+    pub content_type: String,
+    pub max_age_secs: u64,
+    pub published_at: u64,
+}
+
+/// The signature covers the JSON of the manifest with `signature` set to ""
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheManifest {
+    pub issued_at: u64,
+    pub expires_at: u64,
+    pub entries: Vec<ManifestEntry>, // sorted by path
+    pub gateway_public_key: String,
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct JoinRequest {
+    pub node_id: String,
+    pub operator_wallet: String,
+    pub public_key: String,
+    pub region: String,
+    pub base_url: String,
+    pub proof: String, // signature over "zos-edge-join:{node_id}:{operator_wallet}"
+}
+
+/// Signed by the edge over its JSON with `signature` set to ""
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HitReport {
+    pub node_id: String,
+    pub period_start: u64,
+    pub period_end: u64,
+    pub hits: BTreeMap<String, u64>, // path -> hits
+    pub misses: u64,
+    pub bytes_served: u64,
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AcceptedReport {
+    pub node_id: String,
+    pub period_start: u64,
+    pub period_end: u64,
+    pub reported_hits: u64,
+    pub paid_hits: u64, // reported hits the gateway had routed to the node
+    pub reward_usdc: f64,
+    pub accepted_at: u64,
+}
+
+pub fn content_hash(body: &[u8]) -> String {
+    to_hex(&Sha256::digest(body))
+}
+
+fn verifying_key(public_key: &str) -> Result<VerifyingKey, String> {
+    let bytes: [u8; 32] = from_hex(public_key)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or("Public key must be 32 bytes of hex")?;
+    VerifyingKey::from_bytes(&bytes).map_err(|e| format!("Invalid public key: {}", e))
+}
+
+fn verify(public_key: &str, payload: &[u8], signature: &str) -> Result<(), String> {
+    let signature: [u8; 64] = from_hex(signature)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or("Signature must be 64 bytes of hex")?;
+    verifying_key(public_key)?
+        .verify(payload, &Signature::from_bytes(&signature))
+        .map_err(|_| "Signature does not match".to_string())
+}
+
+fn manifest_payload(manifest: &CacheManifest) -> Result<Vec<u8>, String> {
+    let mut unsigned = manifest.clone();
+    unsigned.signature = String::new();
+    serde_json::to_vec(&unsigned).map_err(|e| format!("Failed to serialize manifest: {}", e))
+}
+
+/// What an edge checks before trusting a manifest: the gateway key it
+/// learned from /receipts/public-key, not the one the manifest names
+pub fn verify_manifest(public_key: &str, manifest: &CacheManifest) -> Result<(), String> {
+    verify(public_key, &manifest_payload(manifest)?, &manifest.signature)
+}
+
+fn report_payload(report: &HitReport) -> Result<Vec<u8>, String> {
+    let mut unsigned = report.clone();
+    unsigned.signature = String::new();
+    serde_json::to_vec(&unsigned).map_err(|e| format!("Failed to serialize report: {}", e))
+}
+
+impl PublicGateway {
+    /// List a response edges may serve for `max_age_secs`
+    pub fn publish_cacheable(&mut self, path: &str, body: &[u8], content_type: &str, max_age_secs: u64) {
+        self.edge_cache.entries.insert(path.to_string(), ManifestEntry {
+            path: path.to_string(),
+            content_hash: content_hash(body),
+            content_type: content_type.to_string(),
+            max_age_secs,
+            published_at: chrono::Utc::now().timestamp() as u64,
+        });
+    }
+
+    /// The current entries, signed with the gateway's receipt key
+    pub fn cache_manifest(&self) -> Result<CacheManifest, String> {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let mut entries: Vec<ManifestEntry> = self.edge_cache.entries.values()
+            .filter(|entry| entry.published_at + entry.max_age_secs > now)
+            .cloned()
+            .collect();
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let key = self.receipt_key();
+        let mut manifest = CacheManifest {
+            issued_at: now,
+            expires_at: now + self.edge_cache.manifest_ttl_secs,
+            entries,
+            gateway_public_key: to_hex(key.verifying_key().as_bytes()),
+            signature: String::new(),
+        };
+        manifest.signature = to_hex(&key.sign(&manifest_payload(&manifest)?).to_bytes());
+        Ok(manifest)
+    }
+
+    /// Ask to join; the node waits as Pending until an operator approves it
+    pub fn join_edge(&mut self, request: JoinRequest) -> Result<EdgeNode, String> {
+        if request.node_id.is_empty() || request.operator_wallet.is_empty() {
+            return Err("node_id and operator_wallet are required".to_string());
+        }
+        if !request.base_url.starts_with("https://") && !request.base_url.starts_with("http://") {
+            return Err("base_url must be an http(s) URL".to_string());
+        }
+        if self.compliance.blocklist.contains_key(&request.operator_wallet) {
+            return Err("Operator wallet is blocklisted".to_string());
+        }
+        let proof = format!("zos-edge-join:{}:{}", request.node_id, request.operator_wallet);
+        verify(&request.public_key, proof.as_bytes(), &request.proof)
+            .map_err(|e| format!("Join proof rejected: {}", e))?;
+        if let Some(existing) = self.edge_cache.nodes.get(&request.node_id) {
+            if existing.public_key != request.public_key {
+                return Err("node_id is taken by another key".to_string());
+            }
+        }
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        let node = self.edge_cache.nodes.entry(request.node_id.clone()).or_insert(EdgeNode {
+            node_id: request.node_id.clone(),
+            operator_wallet: request.operator_wallet.clone(),
+            public_key: request.public_key.clone(),
+            region: request.region.clone(),
+            base_url: String::new(),
+            status: EdgeStatus::Pending,
+            joined_at: now,
+            reported_until: now,
+            hits: 0,
+            misses: 0,
+            bytes_served: 0,
+            rewarded_usdc: 0.0,
+            routed: BTreeMap::new(),
+        });
+        node.region = request.region;
+        node.base_url = request.base_url.trim_end_matches('/').to_string();
+        println!("🌍 Edge node {} asked to join from {} ({:?})", node.node_id, node.region, node.status);
+        Ok(node.clone())
+    }
+
+    /// An operator lets a pending or suspended node serve, from now on
+    pub fn approve_edge(&mut self, node_id: &str) -> Result<EdgeNode, String> {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let node = self.edge_cache.nodes.get_mut(node_id).ok_or("Unknown edge node")?;
+        if node.status != EdgeStatus::Active {
+            node.status = EdgeStatus::Active;
+            node.reported_until = node.reported_until.max(now);
+        }
+        println!("🌍 Edge node {} approved", node_id);
+        Ok(node.clone())
+    }
+
+    /// Pick an active edge for a listed, fresh path and count the hit for
+    /// it; returns where to redirect the caller
+    pub fn route_to_edge(&mut self, path: &str) -> Option<String> {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let entry = self.edge_cache.entries.get(path)?;
+        if entry.published_at + entry.max_age_secs <= now {
+            return None;
+        }
+        // The node with the fewest unpaid hits, so load spreads
+        let node = self.edge_cache.nodes.values_mut()
+            .filter(|node| node.status == EdgeStatus::Active && !node.base_url.is_empty())
+            .min_by(|a, b| (a.routed.values().sum::<u64>(), &a.node_id).cmp(&(b.routed.values().sum::<u64>(), &b.node_id)))?;
+        *node.routed.entry(path.to_string()).or_insert(0) += 1;
+        Some(format!("{}{}", node.base_url, path))
+    }
+
+    /// Check a signed hit report and pay the operator for the hits the
+    /// gateway routed to the node: listed paths, within the rate cap, in a
+    /// period not reported before. Reported hits beyond those earn nothing.
+    pub fn accept_hit_report(&mut self, report: HitReport) -> Result<AcceptedReport, String> {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let node = self.edge_cache.nodes.get(&report.node_id)
+            .ok_or("Unknown edge node")?;
+        if node.status != EdgeStatus::Active {
+            return Err("Edge node is suspended".to_string());
+        }
+        verify(&node.public_key, &report_payload(&report)?, &report.signature)
+            .map_err(|e| format!("Report signature rejected: {}", e))?;
+        if report.period_start < node.reported_until {
+            return Err(format!("Period overlaps one already reported (until {})", node.reported_until));
+        }
+        if report.period_end <= report.period_start || report.period_end > now {
+            return Err("Report period must be in the past and non-empty".to_string());
+        }
+
+        let reported_hits: u64 = report.hits.values().sum();
+        let mut cap = (report.period_end - report.period_start).saturating_mul(self.edge_cache.max_hits_per_sec);
+        let mut paid: BTreeMap<String, u64> = BTreeMap::new();
+        for (path, hits) in &report.hits {
+            if !self.edge_cache.entries.contains_key(path) {
+                continue;
+            }
+            let routed = node.routed.get(path).copied().unwrap_or(0);
+            let hits = (*hits).min(routed).min(cap);
+            if hits > 0 {
+                cap -= hits;
+                paid.insert(path.clone(), hits);
+            }
+        }
+        let paid_hits: u64 = paid.values().sum();
+        let reward_usdc = paid_hits as f64 / 1000.0 * self.edge_cache.reward_per_1k_hits_usdc;
+        let operator = node.operator_wallet.clone();
+
+        if reward_usdc > 0.0 {
+            let source = format!("edge_{}_{}", report.node_id, report.period_end);
+            self.pay_commission(&operator, reward_usdc, CommissionType::EdgeCacheHits, &source, None)?;
+        }
+
+        let node = self.edge_cache.nodes.get_mut(&report.node_id)
+            .ok_or("Unknown edge node")?;
+        node.reported_until = report.period_end;
+        for (path, hits) in &paid {
+            if let Some(routed) = node.routed.get_mut(path) {
+                *routed -= hits;
+            }
+        }
+        node.routed.retain(|_, routed| *routed > 0);
+        node.hits += reported_hits;
+        node.misses += report.misses;
+        node.bytes_served += report.bytes_served;
+        node.rewarded_usdc += reward_usdc;
+
+        let accepted = AcceptedReport {
+            node_id: report.node_id,
+            period_start: report.period_start,
+            period_end: report.period_end,
+            reported_hits,
+            paid_hits,
+            reward_usdc,
+            accepted_at: now,
+        };
+        self.edge_cache.reports.push_back(accepted.clone());
+        while self.edge_cache.reports.len() > self.edge_cache.report_retention {
+            self.edge_cache.reports.pop_front();
+        }
+        Ok(accepted)
+    }
+
+    /// GET /edge/manifest, GET|POST /edge/nodes, POST /edge/nodes/{id}/approve
+    /// and DELETE /edge/nodes/{id} (operators), POST /edge/reports
+    pub fn handle_edge_request(&mut self, path: &str, method: &str,
+                               headers: &HashMap<String, String>,
+                               body: &[u8]) -> Result<HttpResponse, String> {
+        match (method, path.trim_start_matches("/edge/").trim_end_matches('/')) {
+            ("GET", "manifest") => json_response(200, &self.cache_manifest()?),
+            ("GET", "nodes") => {
+                let mut nodes: Vec<serde_json::Value> = self.edge_cache.nodes.values()
+                    .map(|node| serde_json::json!({
+                        "node_id": node.node_id,
+                        "region": node.region,
+                        "status": node.status,
+                        "hit_rate": node.hit_rate(),
+                        "hits": node.hits,
+                        "bytes_served": node.bytes_served,
+                        "rewarded_usdc": node.rewarded_usdc,
+                    }))
+                    .collect();
+                nodes.sort_by(|a, b| a["node_id"].as_str().cmp(&b["node_id"].as_str()));
+                json_response(200, &serde_json::json!({ "nodes": nodes }))
+            }
+            ("POST", "nodes") => {
+                let request: JoinRequest = serde_json::from_slice(body)
+                    .map_err(|e| format!("Invalid join request: {}", e))?;
+                match self.join_edge(request) {
+                    Ok(node) => json_response(202, &node),
+                    Err(e) => json_response(400, &serde_json::json!({ "error": e })),
+                }
+            }
+            ("POST", rest) if rest.starts_with("nodes/") && rest.ends_with("/approve") => {
+                if !is_operator(headers) {
+                    return json_response(403, &serde_json::json!({ "error": "Operator key required" }));
+                }
+                match self.approve_edge(rest.trim_start_matches("nodes/").trim_end_matches("/approve")) {
+                    Ok(node) => json_response(200, &node),
+                    Err(e) => json_response(404, &serde_json::json!({ "error": e })),
+                }
+            }
+            ("DELETE", rest) if rest.starts_with("nodes/") => {
+                if !is_operator(headers) {
+                    return json_response(403, &serde_json::json!({ "error": "Operator key required" }));
+                }
+                match self.edge_cache.nodes.get_mut(rest.trim_start_matches("nodes/")) {
+                    Some(node) => {
+                        node.status = EdgeStatus::Suspended;
+                        json_response(200, node)
+                    }
+                    None => json_response(404, &serde_json::json!({ "error": "Unknown edge node" })),
+                }
+            }
+            ("POST", "reports") => {
+                let report: HitReport = serde_json::from_slice(body)
+                    .map_err(|e| format!("Invalid hit report: {}", e))?;
+                match self.accept_hit_report(report) {
+                    Ok(accepted) => json_response(200, &accepted),
+                    Err(e) => json_response(400, &serde_json::json!({ "error": e })),
+                }
+            }
+            _ => Err("Unsupported edge request".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::SigningKey;
+
+    const OPERATOR_WALLET: &str = "EdgeOperatorWallet11111111111111111111111";
+    const PATH: &str = "/alice/docs/index";
+
+    fn join(gateway: &mut PublicGateway, node_id: &str, key: &SigningKey) -> Result<EdgeNode, String> {
+        let proof = format!("zos-edge-join:{}:{}", node_id, OPERATOR_WALLET);
+        gateway.join_edge(JoinRequest {
+            node_id: node_id.to_string(),
+            operator_wallet: OPERATOR_WALLET.to_string(),
+            public_key: to_hex(key.verifying_key().as_bytes()),
+            region: "eu".to_string(),
+            base_url: format!("https://{}.example/", node_id),
+            proof: to_hex(&key.sign(proof.as_bytes()).to_bytes()),
+        })
+    }
+
+    /// A signed report of `hits` on PATH over the last minute
+    fn report(gateway: &mut PublicGateway, node_id: &str, key: &SigningKey, hits: u64) -> Result<AcceptedReport, String> {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let node = gateway.edge_cache.nodes.get_mut(node_id).unwrap();
+        node.reported_until = node.reported_until.min(now - 60);
+        let mut report = HitReport {
+            node_id: node_id.to_string(),
+            period_start: node.reported_until,
+            period_end: now,
+            hits: BTreeMap::from([(PATH.to_string(), hits)]),
+            misses: 0,
+            bytes_served: 0,
+            signature: String::new(),
+        };
+        report.signature = to_hex(&key.sign(&report_payload(&report).unwrap()).to_bytes());
+        gateway.accept_hit_report(report)
+    }
+
+    fn gateway() -> PublicGateway {
+        let mut gateway = PublicGateway::new("gateway.test");
+        gateway.initialize_commission_system();
+        gateway.publish_cacheable(PATH, b"{}", "application/json", 600);
+        gateway
+    }
+
+    #[test]
+    fn test_joining_needs_an_operator() {
+        let mut gateway = gateway();
+        let key = SigningKey::from_bytes(&[7; 32]);
+        assert_eq!(join(&mut gateway, "edge1", &key).unwrap().status, EdgeStatus::Pending);
+
+        // Pending nodes get no traffic and their reports are refused
+        assert_eq!(gateway.route_to_edge(PATH), None);
+        assert!(report(&mut gateway, "edge1", &key, 10).is_err());
+
+        let response = gateway.handle_edge_request("/edge/nodes/edge1/approve", "POST", &HashMap::new(), b"").unwrap();
+        assert_eq!(response.status_code, 403);
+        gateway.approve_edge("edge1").unwrap();
+        assert_eq!(gateway.route_to_edge(PATH), Some(format!("https://edge1.example{}", PATH)));
+    }
+
+    #[test]
+    fn test_spoofed_report_earns_nothing() {
+        let mut gateway = gateway();
+        let key = SigningKey::from_bytes(&[8; 32]);
+        join(&mut gateway, "sybil", &key).unwrap();
+        gateway.approve_edge("sybil").unwrap();
+
+        // Properly signed, listed path, within the rate cap, but never routed
+        let accepted = report(&mut gateway, "sybil", &key, 12_000).unwrap();
+        assert_eq!(accepted.reported_hits, 12_000);
+        assert_eq!(accepted.paid_hits, 0);
+        assert_eq!(accepted.reward_usdc, 0.0);
+        assert_eq!(gateway.edge_cache.nodes["sybil"].rewarded_usdc, 0.0);
+        assert!(!gateway.commission_system.as_ref().unwrap().earnings_ledger.contains_key(OPERATOR_WALLET));
+    }
+
+    #[test]
+    fn test_only_routed_hits_are_paid() {
+        let mut gateway = gateway();
+        let key = SigningKey::from_bytes(&[9; 32]);
+        join(&mut gateway, "edge1", &key).unwrap();
+        gateway.approve_edge("edge1").unwrap();
+        for _ in 0..3 {
+            gateway.route_to_edge(PATH).unwrap();
+        }
+
+        // Over-reporting is paid the 3 routed hits, once
+        let accepted = report(&mut gateway, "edge1", &key, 500).unwrap();
+        assert_eq!(accepted.paid_hits, 3);
+        assert!((accepted.reward_usdc - 3.0 / 1000.0 * 0.05).abs() < 1e-12);
+        assert!(gateway.edge_cache.nodes["edge1"].routed.is_empty());
+
+        assert_eq!(report(&mut gateway, "edge1", &key, 500).unwrap().paid_hits, 0);
+    }
+
+    #[test]
+    fn test_reports_signed_by_another_key_are_refused() {
+        let mut gateway = gateway();
+        let key = SigningKey::from_bytes(&[10; 32]);
+        join(&mut gateway, "edge1", &key).unwrap();
+        gateway.approve_edge("edge1").unwrap();
+        gateway.route_to_edge(PATH).unwrap();
+
+        let forged = report(&mut gateway, "edge1", &SigningKey::from_bytes(&[11; 32]), 1);
+        assert!(forged.unwrap_err().contains("signature"));
+        // Nor can another key take over the node id
+        assert!(join(&mut gateway, "edge1", &SigningKey::from_bytes(&[11; 32])).is_err());
+    }
+}
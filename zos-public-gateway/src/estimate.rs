@@ -0,0 +1,252 @@
+use serde::{Deserialize, Serialize};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use crate::{HttpResponse, PricingConfig, PublicGateway};
+use std::collections::HashMap;
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostEstimator {
+    pub validity_secs: u64,
+    pub tier_discounts: HashMap<String, f64>, // EarningsTier name -> discount %
+    pub issued: HashMap<String, SignedEstimate>, // estimate_id -> estimate, until used or expired
+}
+
+impl Default for CostEstimator {
+    fn default() -> Self {
+        Self {
+            validity_secs: 120,
+            tier_discounts: HashMap::from([
+                ("Bronze".to_string(), 0.0),
+                ("Silver".to_string(), 2.0),
+                ("Gold".to_string(), 5.0),
+                ("Platinum".to_string(), 10.0),
+            ]),
+            issued: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EstimateRequest {
+    #[serde(default)]
+    pub payload_mb: f64,
+    #[serde(default)]
+    pub duration_secs: f64,
+    #[serde(default = "default_requests")]
+    pub requests: u32,
+}
+
+fn default_requests() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedEstimate {
+    pub estimate_id: String,
+    pub service_key: String,
+    pub consumer_wallet: String,
+    pub payload_mb: f64,
+    pub duration_secs: f64,
+    pub requests: u32,
+    pub subtotal_usdc: f64,
+    pub bulk_discount_percentage: f64,
+    pub tier_discount_percentage: f64,
+    pub total_usdc: f64,
+    pub issued_at: u64,
+    pub valid_until: u64,
+    pub signature: String,
+}
+
+pub fn signing_key() -> String {
+    std::env::var("ZOS_ESTIMATE_SIGNING_KEY").unwrap_or_else(|_| "zos-dev-estimate-key".to_string())
+}
+
+pub fn sign_estimate(key: &str, estimate: &SignedEstimate) -> Result<String, String> {
+    let mut unsigned = estimate.clone();
+    unsigned.signature = String::new();
+
+    let payload = serde_json::to_vec(&unsigned)
+        .map_err(|e| format!("Failed to serialize estimate: {}", e))?;
+
+    let mut mac = HmacSha256::new_from_slice(key.as_bytes())
+        .map_err(|e| format!("Invalid signing key: {}", e))?;
+    mac.update(&payload);
+
+    Ok(mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+pub fn verify_estimate(key: &str, estimate: &SignedEstimate) -> bool {
+    sign_estimate(key, estimate)
+        .map(|expected| expected == estimate.signature)
+        .unwrap_or(false)
+}
+
+/// Usage-based charge before discounts
+pub(crate) fn usage_subtotal(pricing: &PricingConfig, request: &EstimateRequest) -> f64 {
+    let per_request = pricing.per_request_price
+        + pricing.per_mb_price * request.payload_mb
+        + pricing.per_second_price * request.duration_secs;
+
+    pricing.base_price_usdc + per_request * request.requests as f64
+}
+
+/// Largest bulk discount the declared request count qualifies for
+pub(crate) fn bulk_discount(pricing: &PricingConfig, requests: u32) -> f64 {
+    pricing.bulk_discounts.iter()
+        .filter(|discount| requests >= discount.min_requests)
+        .map(|discount| discount.discount_percentage)
+        .fold(0.0, f64::max)
+}
+
+impl PublicGateway {
+    /// POST /{wallet}/{service}/estimate
+    pub fn handle_estimate_request(&mut self, wallet_address: &str, service_name: &str,
+                                   headers: &HashMap<String, String>, body: &[u8]) -> Result<HttpResponse, String> {
+        let request: EstimateRequest = serde_json::from_slice(body)
+            .map_err(|e| format!("Invalid estimate request: {}", e))?;
+
+        if request.payload_mb < 0.0 || request.duration_secs < 0.0 || request.requests == 0 {
+            return Err("Payload size and duration must be non-negative, requests at least 1".to_string());
+        }
+
+        let service_key = format!("{}_{}", wallet_address, service_name);
+        let service = self.service_registry.get(&service_key)
+            .ok_or("Service not found")?;
+
+        let subtotal = usage_subtotal(&service.pricing, &request);
+        let bulk = bulk_discount(&service.pricing, request.requests);
+
+        // Tier discounts go to the wallet that proves it; anyone else is quoted list price
+        let consumer_wallet = self.authenticate_wallet(headers).unwrap_or_default();
+        let tier_discount = match self.commission_system.as_ref()
+            .and_then(|system| system.earnings_ledger.get(&consumer_wallet)) {
+            Some(account) => self.cost_estimator.tier_discounts
+                .get(&format!("{:?}", account.tier))
+                .copied()
+                .unwrap_or(0.0),
+            None => 0.0,
+        };
+        let total = subtotal * (1.0 - bulk / 100.0) * (1.0 - tier_discount / 100.0);
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        let mut estimate = SignedEstimate {
+            estimate_id: format!("est_{}_{}", now, self.cost_estimator.issued.len()),
+            service_key,
+            consumer_wallet,
+            payload_mb: request.payload_mb,
+            duration_secs: request.duration_secs,
+            requests: request.requests,
+            subtotal_usdc: subtotal,
+            bulk_discount_percentage: bulk,
+            tier_discount_percentage: tier_discount,
+            total_usdc: (total * 1_000_000.0).round() / 1_000_000.0, // USDC has 6 decimals
+            issued_at: now,
+            valid_until: now + self.cost_estimator.validity_secs,
+            signature: String::new(),
+        };
+        estimate.signature = sign_estimate(&signing_key(), &estimate)?;
+
+        self.cost_estimator.issued.retain(|_, issued| issued.valid_until >= now);
+        self.cost_estimator.issued.insert(estimate.estimate_id.clone(), estimate.clone());
+
+        let response_body = serde_json::to_vec(&estimate)
+            .map_err(|e| format!("Failed to serialize estimate: {}", e))?;
+
+        Ok(HttpResponse {
+            status_code: 200,
+            headers: HashMap::from([
+                ("Content-Type".to_string(), "application/json".to_string()),
+                ("Cache-Control".to_string(), "no-store".to_string()),
+            ]),
+            body: response_body,
+        })
+    }
+
+    /// Redeem an estimate quoted in `X-Estimate-Id`; the quoted total is the
+    /// charge for this call. Each estimate can be used once, by the
+    /// authenticated wallet it was quoted to.
+    pub(crate) fn redeem_estimate(&mut self, service_key: &str, caller: Option<&str>,
+                                  headers: &HashMap<String, String>) -> Result<Option<SignedEstimate>, String> {
+        let estimate_id = match headers.get("X-Estimate-Id") {
+            Some(estimate_id) => estimate_id,
+            None => return Ok(None),
+        };
+
+        let estimate = self.cost_estimator.issued.get(estimate_id)
+            .ok_or("Unknown or already used estimate")?;
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        if now > estimate.valid_until {
+            return Err("Estimate expired; request a new one".to_string());
+        }
+        if estimate.service_key != service_key {
+            return Err("Estimate was issued for a different service".to_string());
+        }
+        if estimate.consumer_wallet != caller.unwrap_or("") {
+            return Err("Estimate was issued to a different wallet".to_string());
+        }
+        if !verify_estimate(&signing_key(), estimate) {
+            return Err("Estimate signature is invalid".to_string());
+        }
+
+        Ok(self.cost_estimator.issued.remove(estimate_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commission_events::empty_account;
+    use crate::{EarningsTier, PricingTier};
+
+    const BODY: &[u8] = br#"{"payload_mb": 1.0, "duration_secs": 1.0, "requests": 1}"#;
+
+    fn gateway_with_gold_consumer() -> PublicGateway {
+        let mut gateway = PublicGateway::new("gateway.test");
+        gateway.register_wallet_endpoint("owner1", "owner", vec![4001]).unwrap();
+        gateway.add_service("owner1", "api", 4001, PricingTier::Basic).unwrap();
+        gateway.initialize_commission_system();
+        let mut account = empty_account("gold1", 0);
+        account.tier = EarningsTier::Gold;
+        gateway.commission_system.as_mut().unwrap().earnings_ledger.insert("gold1".to_string(), account);
+        gateway
+    }
+
+    fn estimate(gateway: &mut PublicGateway, headers: &HashMap<String, String>) -> SignedEstimate {
+        let response = gateway.handle_estimate_request("owner1", "api", headers, BODY).unwrap();
+        assert_eq!(response.status_code, 200);
+        serde_json::from_slice(&response.body).unwrap()
+    }
+
+    #[test]
+    fn test_claimed_wallet_header_gets_list_price() {
+        let mut gateway = gateway_with_gold_consumer();
+        let spoofed = HashMap::from([("X-Wallet-Address".to_string(), "gold1".to_string())]);
+
+        let quote = estimate(&mut gateway, &spoofed);
+        assert_eq!(quote.tier_discount_percentage, 0.0);
+        assert_eq!(quote.consumer_wallet, "");
+        assert_eq!(quote.total_usdc, quote.subtotal_usdc);
+    }
+
+    #[test]
+    fn test_authenticated_wallet_gets_tier_discount_and_only_it_redeems() {
+        let mut gateway = gateway_with_gold_consumer();
+        let (_, key) = gateway.api_keys.issue("gold1", "test", chrono::Utc::now().timestamp() as u64);
+        let headers = HashMap::from([("Authorization".to_string(), format!("Bearer {}", key))]);
+
+        let quote = estimate(&mut gateway, &headers);
+        assert_eq!(quote.tier_discount_percentage, 5.0);
+        assert_eq!(quote.consumer_wallet, "gold1");
+        assert!(quote.total_usdc < quote.subtotal_usdc);
+
+        let redeem = HashMap::from([("X-Estimate-Id".to_string(), quote.estimate_id.clone())]);
+        assert!(gateway.redeem_estimate("owner1_api", None, &redeem).is_err());
+        assert!(gateway.redeem_estimate("owner1_api", Some("other"), &redeem).is_err());
+        let redeemed = gateway.redeem_estimate("owner1_api", Some("gold1"), &redeem).unwrap();
+        assert_eq!(redeemed.unwrap().estimate_id, quote.estimate_id);
+        assert!(gateway.redeem_estimate("owner1_api", Some("gold1"), &redeem).is_err()); // single use
+    }
+}
@@ -0,0 +1,296 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::receipts::json_response;
+use crate::screening::is_operator;
+use crate::{HttpResponse, PricingConfig, PublicGateway};
+use std::collections::HashMap;
+
+/// Consumer cohorts a split can be given for. Cohorts come from things that
+/// rarely change, so a consumer stays in one variant: no wallet, a wallet,
+/// or a referrer's earnings tier. "*" is every consumer without a split of
+/// their own.
+pub const COHORTS: [&str; 7] = ["*", "anonymous", "wallet", "bronze", "silver", "gold", "platinum"];
+const MAX_VARIANTS: usize = 8;
+
+/// One arm of an experiment. Unset fields fall back to the service's own.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Variant {
+    pub name: String,
+    pub libp2p_port: Option<u16>, // its own backend
+    pub pricing: Option<PricingConfig>,
+    #[serde(default)]
+    pub stats: HashMap<String, VariantStats>, // by cohort
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VariantStats {
+    pub consumers: u64, // first calls seen in this variant
+    pub calls: u64,
+    pub errors: u64,
+    pub conversions: u64, // calls that ended in a charge
+    pub latency_ms_total: u64,
+    pub latency_ms_max: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Experiment {
+    pub experiment_id: String,
+    pub service_key: String,
+    pub name: String,
+    pub variants: Vec<Variant>,
+    pub splits: HashMap<String, Vec<f64>>, // cohort -> percentage per variant
+    pub started_at: u64,
+    pub stopped_at: Option<u64>,
+    #[serde(default)]
+    pub seen: HashMap<String, usize>, // consumer -> variant, for counting consumers
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExperimentRegistry {
+    pub experiments: HashMap<String, Experiment>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExperimentRequest {
+    pub name: String,
+    pub variants: Vec<Variant>,
+    pub splits: HashMap<String, Vec<f64>>,
+}
+
+/// The variant a call was routed to, for recording its outcome
+#[derive(Debug, Clone)]
+pub struct Assignment {
+    pub experiment_id: String,
+    pub variant: usize,
+    pub variant_name: String,
+    pub cohort: String,
+    pub consumer: String,
+    pub libp2p_port: Option<u16>,
+    pub pricing: Option<PricingConfig>,
+}
+
+fn validate(request: &ExperimentRequest) -> Result<(), String> {
+    if request.variants.len() < 2 || request.variants.len() > MAX_VARIANTS {
+        return Err(format!("An experiment needs 2 to {} variants", MAX_VARIANTS));
+    }
+    let mut names: Vec<&str> = request.variants.iter().map(|v| v.name.as_str()).collect();
+    names.sort();
+    names.dedup();
+    if names.len() != request.variants.len() || names.iter().any(|name| name.is_empty()) {
+        return Err("Variant names must be unique and not empty".to_string());
+    }
+    if !request.splits.contains_key("*") {
+        return Err("A \"*\" split is required for consumers in no other cohort".to_string());
+    }
+    for (cohort, split) in &request.splits {
+        if !COHORTS.contains(&cohort.as_str()) {
+            return Err(format!("Unknown cohort {}; expected one of {:?}", cohort, COHORTS));
+        }
+        if split.len() != request.variants.len() || split.iter().any(|p| !(0.0..=100.0).contains(p)) {
+            return Err(format!("Split for {} needs a percentage per variant", cohort));
+        }
+        if (split.iter().sum::<f64>() - 100.0).abs() > 0.01 {
+            return Err(format!("Split for {} must add up to 100", cohort));
+        }
+    }
+    Ok(())
+}
+
+/// Stable bucket in [0, 100) for a consumer in an experiment
+fn bucket(experiment_id: &str, consumer: &str) -> f64 {
+    let digest = Sha256::digest(format!("{}:{}", experiment_id, consumer).as_bytes());
+    let n = u64::from_be_bytes(digest[..8].try_into().unwrap_or([0; 8]));
+    (n % 10_000) as f64 / 100.0
+}
+
+fn pick_variant(split: &[f64], bucket: f64) -> usize {
+    let mut cumulative = 0.0;
+    for (index, percentage) in split.iter().enumerate() {
+        cumulative += percentage;
+        if bucket < cumulative {
+            return index;
+        }
+    }
+    split.len().saturating_sub(1)
+}
+
+impl PublicGateway {
+    fn consumer_cohort(&self, headers: &HashMap<String, String>) -> (String, String) {
+        let Some(wallet) = headers.get("X-Wallet-Address") else {
+            let consumer = headers.get("X-Forwarded-For").cloned().unwrap_or_else(|| "anonymous".to_string());
+            return ("anonymous".to_string(), consumer);
+        };
+        let cohort = self.commission_system.as_ref()
+            .and_then(|system| system.earnings_ledger.get(wallet))
+            .map(|account| format!("{:?}", account.tier).to_lowercase())
+            .unwrap_or_else(|| "wallet".to_string());
+        (cohort, wallet.clone())
+    }
+
+    /// The running experiment's variant for this caller, if the service has one
+    pub(crate) fn assign_variant(&self, service_key: &str, headers: &HashMap<String, String>) -> Option<Assignment> {
+        let experiment = self.experiments.experiments.values()
+            .find(|e| e.service_key == service_key && e.stopped_at.is_none())?;
+        let (cohort, consumer) = self.consumer_cohort(headers);
+        let split = experiment.splits.get(&cohort).or_else(|| experiment.splits.get("*"))?;
+        let variant = pick_variant(split, bucket(&experiment.experiment_id, &consumer));
+        let chosen = experiment.variants.get(variant)?;
+
+        Some(Assignment {
+            experiment_id: experiment.experiment_id.clone(),
+            variant,
+            variant_name: chosen.name.clone(),
+            cohort,
+            consumer,
+            libp2p_port: chosen.libp2p_port,
+            pricing: chosen.pricing.clone(),
+        })
+    }
+
+    /// Count a routed call against its variant and cohort
+    pub(crate) fn record_variant_call(&mut self, assignment: &Assignment, latency_ms: u64, ok: bool, converted: bool) {
+        if self.sandbox_mode {
+            return;
+        }
+        let Some(experiment) = self.experiments.experiments.get_mut(&assignment.experiment_id) else { return };
+        let first_call = !experiment.seen.contains_key(&assignment.consumer);
+        if first_call {
+            experiment.seen.insert(assignment.consumer.clone(), assignment.variant);
+        }
+        let Some(variant) = experiment.variants.get_mut(assignment.variant) else { return };
+
+        let stats = variant.stats.entry(assignment.cohort.clone()).or_default();
+        stats.consumers += first_call as u64;
+        stats.calls += 1;
+        stats.errors += !ok as u64;
+        stats.conversions += converted as u64;
+        stats.latency_ms_total += latency_ms;
+        stats.latency_ms_max = stats.latency_ms_max.max(latency_ms);
+    }
+
+    pub fn start_experiment(&mut self, service_key: &str, request: ExperimentRequest) -> Result<Experiment, String> {
+        if !self.service_registry.contains_key(service_key) {
+            return Err("Service not found".to_string());
+        }
+        validate(&request)?;
+        if self.experiments.experiments.values().any(|e| e.service_key == service_key && e.stopped_at.is_none()) {
+            return Err("Stop the running experiment before starting another".to_string());
+        }
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        let experiment = Experiment {
+            experiment_id: format!("exp_{}_{}", now, self.experiments.experiments.len()),
+            service_key: service_key.to_string(),
+            name: request.name,
+            variants: request.variants.into_iter()
+                .map(|variant| Variant { stats: HashMap::new(), ..variant })
+                .collect(),
+            splits: request.splits,
+            started_at: now,
+            stopped_at: None,
+            seen: HashMap::new(),
+        };
+        self.experiments.experiments.insert(experiment.experiment_id.clone(), experiment.clone());
+        println!("🧪 Experiment {} started on {} ({} variants)", experiment.experiment_id, service_key,
+                 experiment.variants.len());
+        Ok(experiment)
+    }
+
+    /// Per variant and cohort: calls, conversion rate, error rate and latency
+    pub fn experiment_results(&self, experiment_id: &str) -> Option<serde_json::Value> {
+        let experiment = self.experiments.experiments.get(experiment_id)?;
+        let rate = |n: u64, d: u64| if d > 0 { n as f64 / d as f64 } else { 0.0 };
+        let summary = |stats: &VariantStats| serde_json::json!({
+            "consumers": stats.consumers,
+            "calls": stats.calls,
+            "conversions": stats.conversions,
+            "conversion_rate": rate(stats.conversions, stats.calls),
+            "error_rate": rate(stats.errors, stats.calls),
+            "latency_ms_mean": rate(stats.latency_ms_total, stats.calls),
+            "latency_ms_max": stats.latency_ms_max,
+        });
+
+        let variants = experiment.variants.iter().map(|variant| {
+            let mut total = VariantStats::default();
+            for stats in variant.stats.values() {
+                total.consumers += stats.consumers;
+                total.calls += stats.calls;
+                total.errors += stats.errors;
+                total.conversions += stats.conversions;
+                total.latency_ms_total += stats.latency_ms_total;
+                total.latency_ms_max = total.latency_ms_max.max(stats.latency_ms_max);
+            }
+            serde_json::json!({
+                "name": variant.name,
+                "libp2p_port": variant.libp2p_port,
+                "pricing": variant.pricing,
+                "overall": summary(&total),
+                "by_cohort": variant.stats.iter()
+                    .map(|(cohort, stats)| (cohort.clone(), summary(stats)))
+                    .collect::<serde_json::Map<_, _>>(),
+            })
+        }).collect::<Vec<_>>();
+
+        Some(serde_json::json!({
+            "experiment_id": experiment.experiment_id,
+            "name": experiment.name,
+            "splits": experiment.splits,
+            "started_at": experiment.started_at,
+            "stopped_at": experiment.stopped_at,
+            "variants": variants,
+        }))
+    }
+
+    /// /{wallet}/{service}/experiments[/{id}[/stop]], for the service owner
+    /// (or an operator)
+    pub(crate) fn handle_experiment_request(&mut self, wallet_address: &str, service_name: &str, path: &str,
+                                            method: &str, headers: &HashMap<String, String>,
+                                            body: &[u8]) -> Result<HttpResponse, String> {
+        if !is_operator(headers) {
+            match self.authenticate_wallet(headers) {
+                Ok(caller) if caller == wallet_address => {}
+                Ok(_) => return json_response(403, &serde_json::json!({ "error": "Only the service owner can run experiments" })),
+                Err(e) => return json_response(401, &serde_json::json!({ "error": e })),
+            }
+        }
+        let service_key = format!("{}_{}", wallet_address, service_name);
+        let rest: Vec<&str> = path.trim_matches('/').split('/').skip(3).collect();
+
+        match (method, rest.as_slice()) {
+            ("GET", []) => {
+                let mut experiments = self.experiments.experiments.values()
+                    .filter(|e| e.service_key == service_key)
+                    .collect::<Vec<_>>();
+                experiments.sort_by_key(|e| std::cmp::Reverse(e.started_at));
+                let results = experiments.iter()
+                    .filter_map(|e| self.experiment_results(&e.experiment_id))
+                    .collect::<Vec<_>>();
+                json_response(200, &serde_json::json!({ "experiments": results }))
+            }
+            ("POST", []) => {
+                let request: ExperimentRequest = serde_json::from_slice(body)
+                    .map_err(|e| format!("Invalid experiment: {}", e))?;
+                match self.start_experiment(&service_key, request) {
+                    Ok(experiment) => json_response(201, &serde_json::json!({ "experiment": experiment })),
+                    Err(e) => json_response(400, &serde_json::json!({ "error": e })),
+                }
+            }
+            ("GET", [experiment_id]) => {
+                match self.experiment_results(experiment_id)
+                    .filter(|_| self.experiments.experiments[*experiment_id].service_key == service_key) {
+                    Some(results) => json_response(200, &results),
+                    None => json_response(404, &serde_json::json!({ "error": "Experiment not found" })),
+                }
+            }
+            ("POST", [experiment_id, "stop"]) => {
+                let experiment = self.experiments.experiments.get_mut(*experiment_id)
+                    .filter(|e| e.service_key == service_key)
+                    .ok_or("Experiment not found")?;
+                experiment.stopped_at.get_or_insert(chrono::Utc::now().timestamp() as u64);
+                let results = self.experiment_results(experiment_id);
+                json_response(200, &serde_json::json!({ "experiment": results }))
+            }
+            _ => Err("Unsupported experiment request".to_string()),
+        }
+    }
+}
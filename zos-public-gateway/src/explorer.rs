@@ -0,0 +1,242 @@
+use serde::{Deserialize, Serialize};
+use crate::receipts::json_response;
+use crate::{HttpResponse, PublicGateway};
+use std::collections::{BTreeMap, HashMap};
+
+/// One UTC day of gateway traffic, summed over every wallet
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DailyVolume {
+    pub calls: u64,
+    pub paid_calls: u64,
+    pub volume_usdc: f64,
+    pub swaps: u64,
+    pub swap_volume: HashMap<String, f64>, // input token -> amount swapped
+}
+
+/// Aggregates behind the public explorer. Only totals are kept: nothing
+/// here says which wallet called, paid or swapped.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EconomyStats {
+    pub retention_days: usize,
+    pub top_services: usize,
+    pub min_calls_listed: u64, // quieter services stay out of the top list
+    pub days: BTreeMap<String, DailyVolume>, // "YYYY-MM-DD"
+    pub service_calls: HashMap<String, u64>, // service key -> lifetime calls
+}
+
+impl Default for EconomyStats {
+    fn default() -> Self {
+        Self {
+            retention_days: 90,
+            top_services: 20,
+            min_calls_listed: 10,
+            days: BTreeMap::new(),
+            service_calls: HashMap::new(),
+        }
+    }
+}
+
+impl EconomyStats {
+    fn today(&mut self) -> &mut DailyVolume {
+        let day = chrono::Utc::now().format("%Y-%m-%d").to_string();
+        if !self.days.contains_key(&day) {
+            while self.days.len() >= self.retention_days.max(1) {
+                self.days.pop_first();
+            }
+        }
+        self.days.entry(day).or_default()
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+impl PublicGateway {
+    /// Count a call the service answered; `charged_usdc` is the receipt
+    /// amount for paid calls. Sandbox traffic is left out.
+    pub(crate) fn record_call(&mut self, service_key: &str, charged_usdc: Option<f64>) {
+        if self.sandbox_mode {
+            return;
+        }
+        *self.economy.service_calls.entry(service_key.to_string()).or_insert(0) += 1;
+        let today = self.economy.today();
+        today.calls += 1;
+        if let Some(amount) = charged_usdc {
+            today.paid_calls += 1;
+            today.volume_usdc += amount;
+        }
+    }
+
+    pub(crate) fn record_swap(&mut self, token: &str, amount: f64) {
+        if self.sandbox_mode {
+            return;
+        }
+        self.metrics.record_swap(token, amount);
+        let today = self.economy.today();
+        today.swaps += 1;
+        *today.swap_volume.entry(token.to_string()).or_insert(0.0) += amount;
+    }
+
+    /// Busiest services by lifetime calls, by name only
+    fn top_services(&self) -> Vec<serde_json::Value> {
+        let mut ranked: Vec<(&String, &u64)> = self.economy.service_calls.iter()
+            .filter(|(service_key, calls)| **calls >= self.economy.min_calls_listed
+                && self.service_registry.contains_key(*service_key)
+                && !self.is_delisted(service_key))
+            .collect();
+        ranked.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+
+        ranked.into_iter()
+            .take(self.economy.top_services)
+            .filter_map(|(service_key, calls)| {
+                let service = self.service_registry.get(service_key)?;
+                Some(serde_json::json!({
+                    "service_name": service.service_name,
+                    "calls": calls,
+                    "payment_required": service.payment_required,
+                }))
+            })
+            .collect()
+    }
+
+    /// Commission paid out, summed by type and token
+    pub(crate) fn commission_totals(&self) -> BTreeMap<String, BTreeMap<String, f64>> {
+        let mut totals: BTreeMap<String, BTreeMap<String, f64>> = BTreeMap::new();
+        let payments = self.commission_system.iter()
+            .flat_map(|system| system.commission_history.values())
+            .flatten();
+        for payment in payments {
+            *totals.entry(format!("{:?}", payment.commission_type)).or_default()
+                .entry(payment.token.clone()).or_insert(0.0) += payment.amount;
+        }
+        totals
+    }
+
+    /// Per token: burned, sent to the treasury, swapped and pooled
+    fn token_circulation(&self) -> BTreeMap<String, serde_json::Value> {
+        let mut tokens: Vec<String> = self.payment_processor.supported_tokens.iter()
+            .map(|token| token.symbol.clone())
+            .collect();
+        tokens.extend(self.fee_routing.burned_total.keys().cloned());
+        tokens.extend(self.fee_routing.treasury_total.keys().cloned());
+
+        let mut swapped: HashMap<&str, f64> = HashMap::new();
+        for day in self.economy.days.values() {
+            for (token, amount) in &day.swap_volume {
+                *swapped.entry(token.as_str()).or_insert(0.0) += amount;
+            }
+        }
+        tokens.extend(swapped.keys().map(|token| token.to_string()));
+
+        tokens.into_iter()
+            .map(|token| {
+                let pooled: f64 = self.payment_processor.swap_pools.values()
+                    .filter(|pool| pool.token_a == token || pool.token_b == token)
+                    .filter_map(|pool| pool.reserves_for(&token).map(|(reserve, _)| reserve))
+                    .sum();
+                let circulation = serde_json::json!({
+                    "burned": self.fee_routing.burned_total.get(&token).copied().unwrap_or(0.0),
+                    "treasury": self.fee_routing.treasury_total.get(&token).copied().unwrap_or(0.0),
+                    "swapped": swapped.get(token.as_str()).copied().unwrap_or(0.0),
+                    "pool_liquidity": pooled,
+                });
+                (token, circulation)
+            })
+            .collect()
+    }
+
+    fn explorer_summary(&self) -> serde_json::Value {
+        let listed = self.service_registry.keys()
+            .filter(|service_key| !self.is_delisted(service_key))
+            .count();
+        let calls: u64 = self.economy.days.values().map(|day| day.calls).sum();
+        let volume: f64 = self.economy.days.values().map(|day| day.volume_usdc).sum();
+
+        serde_json::json!({
+            "total_services": self.service_registry.len(),
+            "listed_services": listed,
+            "window_days": self.economy.days.len(),
+            "calls": calls,
+            "volume_usdc": volume,
+            "commission_totals": self.commission_totals(),
+            "token_circulation": self.token_circulation(),
+        })
+    }
+
+    fn explorer_page(&self) -> String {
+        let summary = self.explorer_summary();
+        let mut page = String::from("<!DOCTYPE html><html><head><meta charset=\"utf-8\">\
+            <title>ZOS Gateway Explorer</title></head><body><h1>ZOS Gateway Explorer</h1>");
+
+        page.push_str(&format!("<p>{} services ({} listed) &middot; {} calls &middot; {:.2} USDC over the last {} days</p>",
+            summary["total_services"], summary["listed_services"], summary["calls"],
+            summary["volume_usdc"].as_f64().unwrap_or(0.0), summary["window_days"]));
+
+        page.push_str("<h2>Daily volume</h2><table><tr><th>Day</th><th>Calls</th><th>Paid calls</th>\
+            <th>Volume (USDC)</th><th>Swaps</th></tr>");
+        for (day, volume) in self.economy.days.iter().rev() {
+            page.push_str(&format!("<tr><td>{}</td><td>{}</td><td>{}</td><td>{:.2}</td><td>{}</td></tr>",
+                day, volume.calls, volume.paid_calls, volume.volume_usdc, volume.swaps));
+        }
+        page.push_str("</table>");
+
+        page.push_str("<h2>Top services</h2><table><tr><th>Service</th><th>Calls</th></tr>");
+        for service in self.top_services() {
+            page.push_str(&format!("<tr><td>{}</td><td>{}</td></tr>",
+                escape_html(service["service_name"].as_str().unwrap_or("")), service["calls"]));
+        }
+        page.push_str("</table>");
+
+        page.push_str("<h2>Commissions</h2><table><tr><th>Type</th><th>Token</th><th>Total</th></tr>");
+        for (commission_type, by_token) in self.commission_totals() {
+            for (token, amount) in by_token {
+                page.push_str(&format!("<tr><td>{}</td><td>{}</td><td>{:.2}</td></tr>",
+                    commission_type, escape_html(&token), amount));
+            }
+        }
+        page.push_str("</table>");
+
+        page.push_str("<h2>Token circulation</h2><table><tr><th>Token</th><th>Burned</th>\
+            <th>Treasury</th><th>Swapped</th><th>Pool liquidity</th></tr>");
+        for (token, circulation) in self.token_circulation() {
+            page.push_str(&format!("<tr><td>{}</td><td>{:.2}</td><td>{:.2}</td><td>{:.2}</td><td>{:.2}</td></tr>",
+                escape_html(&token),
+                circulation["burned"].as_f64().unwrap_or(0.0),
+                circulation["treasury"].as_f64().unwrap_or(0.0),
+                circulation["swapped"].as_f64().unwrap_or(0.0),
+                circulation["pool_liquidity"].as_f64().unwrap_or(0.0)));
+        }
+        page.push_str("</table></body></html>");
+        page
+    }
+
+    /// GET /explorer (HTML), /explorer/stats, /explorer/volume, /explorer/services.
+    /// Unauthenticated and read-only.
+    pub fn handle_explorer_request(&self, path: &str, method: &str) -> Result<HttpResponse, String> {
+        if method != "GET" {
+            return Err("Explorer is read-only".to_string());
+        }
+
+        match path.trim_end_matches('/') {
+            "/explorer" => Ok(HttpResponse {
+                status_code: 200,
+                headers: HashMap::from([
+                    ("Content-Type".to_string(), "text/html; charset=utf-8".to_string()),
+                    ("Access-Control-Allow-Origin".to_string(), "*".to_string()),
+                ]),
+                body: self.explorer_page().into_bytes(),
+            }),
+            "/explorer/stats" => json_response(200, &self.explorer_summary()),
+            "/explorer/volume" => json_response(200, &serde_json::json!({
+                "retention_days": self.economy.retention_days,
+                "days": self.economy.days,
+            })),
+            "/explorer/services" => json_response(200, &serde_json::json!({
+                "min_calls_listed": self.economy.min_calls_listed,
+                "services": self.top_services(),
+            })),
+            _ => Err("Unsupported explorer request".to_string()),
+        }
+    }
+}
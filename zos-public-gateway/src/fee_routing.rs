@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+use crate::receipts::json_response;
+use crate::{HttpResponse, PublicGateway};
+use std::collections::{HashMap, VecDeque};
+
+/// Share of every gateway fee taken out before the rest is paid on: burned
+/// (fees in the burn token only) or sent to the community treasury
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeeRouting {
+    pub burn_percentage: f64,
+    pub treasury_percentage: f64,
+    pub burn_token: String,       // fees in other tokens are never burned
+    pub treasury_account: String, // community ledger account credited
+    pub history_retention: usize,
+}
+
+impl Default for FeeRouting {
+    fn default() -> Self {
+        Self {
+            burn_percentage: 10.0,
+            treasury_percentage: 10.0,
+            burn_token: "SOLFUNMEME".to_string(),
+            treasury_account: "community_treasury".to_string(),
+            history_retention: 10_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FeeDestination {
+    Burn,
+    Treasury,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutedFee {
+    pub id: String, // also the community ledger's transfer/burn id
+    pub destination: FeeDestination,
+    pub token: String,
+    pub amount: f64,
+    pub base_units: u64, // amount in the token's smallest unit, as the ledger keeps it
+    pub source: String,  // transaction the fee came from
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FeeRoutingLedger {
+    pub config: FeeRouting,
+    pub history: VecDeque<RoutedFee>,
+    pub burned_total: HashMap<String, f64>,
+    pub treasury_total: HashMap<String, f64>,
+    pub undelivered: Vec<RoutedFee>, // not yet applied to the community ledger
+    pub routed_total: u64,           // sequence for ids
+}
+
+impl PublicGateway {
+    /// Take the burn and treasury shares out of a fee
+    pub(crate) fn route_fee(&mut self, token: &str, fee: f64, source: &str) {
+        if fee <= 0.0 {
+            return;
+        }
+        let decimals = self.payment_processor.supported_tokens.iter()
+            .find(|config| config.symbol == token)
+            .map(|config| config.decimals)
+            .unwrap_or(6);
+
+        let config = &self.fee_routing.config;
+        let burn = if token == config.burn_token {
+            fee * config.burn_percentage.clamp(0.0, 100.0) / 100.0
+        } else {
+            0.0
+        };
+        let treasury = (fee * config.treasury_percentage.clamp(0.0, 100.0) / 100.0).min(fee - burn);
+
+        for (destination, amount) in [(FeeDestination::Burn, burn), (FeeDestination::Treasury, treasury)] {
+            let base_units = (amount * 10f64.powi(decimals as i32)).floor() as u64;
+            if base_units == 0 {
+                continue;
+            }
+
+            let ledger = &mut self.fee_routing;
+            let routed = RoutedFee {
+                id: format!("fee_{}_{}", chrono::Utc::now().timestamp(), ledger.routed_total),
+                destination: destination.clone(),
+                token: token.to_string(),
+                amount,
+                base_units,
+                source: source.to_string(),
+                timestamp: chrono::Utc::now().timestamp() as u64,
+            };
+            ledger.routed_total += 1;
+
+            let totals = match destination {
+                FeeDestination::Burn => &mut ledger.burned_total,
+                FeeDestination::Treasury => &mut ledger.treasury_total,
+            };
+            *totals.entry(token.to_string()).or_insert(0.0) += amount;
+
+            // Sandbox fees are fake money and never reach the community ledger
+            if !self.sandbox_mode {
+                ledger.undelivered.push(routed.clone());
+            }
+            ledger.history.push_back(routed);
+            while ledger.history.len() > ledger.config.history_retention {
+                ledger.history.pop_front();
+            }
+        }
+    }
+
+    /// Hand routed fees to the host for the community ledger
+    /// (`deposit_to_treasury` / `record_burn`, keyed by id so retries are
+    /// harmless)
+    pub fn take_undelivered_fees(&mut self) -> Vec<RoutedFee> {
+        std::mem::take(&mut self.fee_routing.undelivered)
+    }
+
+    /// GET /treasury, GET /treasury/burns
+    pub fn handle_treasury_request(&self, path: &str, method: &str) -> Result<HttpResponse, String> {
+        let ledger = &self.fee_routing;
+        match (method, path.trim_end_matches('/')) {
+            ("GET", "/treasury") => json_response(200, &serde_json::json!({
+                "fee_routing": ledger.config,
+                "burned_total": ledger.burned_total,
+                "treasury_total": ledger.treasury_total,
+                "treasury_account": ledger.config.treasury_account,
+                "undelivered": ledger.undelivered.len(),
+            })),
+            ("GET", "/treasury/burns") => json_response(200, &serde_json::json!({
+                "burn_token": ledger.config.burn_token,
+                "burned_total": ledger.burned_total,
+                "burns": ledger.history.iter().rev()
+                    .filter(|routed| routed.destination == FeeDestination::Burn)
+                    .collect::<Vec<_>>(),
+            })),
+            _ => Err("Unsupported treasury request".to_string()),
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use serde::{Deserialize, Serialize};
+use crate::{HttpResponse, PublicGateway};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum HealthCheckKind {
+    Http { path: String },
+    Libp2pPing,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthCheck {
+    pub kind: HealthCheckKind,
+    pub interval_secs: u64,
+    pub timeout_secs: u64,
+    pub failure_threshold: u32, // consecutive failures before delisting
+}
+
+impl Default for HealthCheck {
+    fn default() -> Self {
+        Self {
+            kind: HealthCheckKind::Http { path: "/health".to_string() },
+            interval_secs: 30,
+            timeout_secs: 5,
+            failure_threshold: 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum HealthState {
+    Unknown,
+    Healthy,
+    Failing,  // below the threshold, still listed
+    Delisted, // requests get 503 until a probe succeeds
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceHealth {
+    pub state: HealthState,
+    pub consecutive_failures: u32,
+    pub last_checked: u64,
+    pub last_success: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+impl Default for ServiceHealth {
+    fn default() -> Self {
+        Self {
+            state: HealthState::Unknown,
+            consecutive_failures: 0,
+            last_checked: 0,
+            last_success: None,
+            last_error: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OwnerNotification {
+    pub wallet_address: String,
+    pub service_name: String,
+    pub message: String,
+    pub timestamp: u64,
+}
+
+/// A probe the host should run now: service key, check definition and target
+#[derive(Debug, Clone, Serialize)]
+pub struct ProbeTarget {
+    pub service_key: String,
+    pub check: HealthCheck,
+    pub target: String,
+}
+
+impl PublicGateway {
+    pub fn set_health_check(&mut self, service_key: &str, check: HealthCheck) -> Result<(), String> {
+        let service = self.service_registry.get_mut(service_key)
+            .ok_or("Service not found")?;
+        service.health_check = check;
+        Ok(())
+    }
+
+    /// Services whose check interval has elapsed. The gateway has no network
+    /// client of its own, so the node runs these and reports back.
+    pub fn due_probes(&self, now: u64) -> Vec<ProbeTarget> {
+        self.service_registry.iter()
+            .filter(|(_, service)| now >= service.health.last_checked + service.health_check.interval_secs)
+            .map(|(key, service)| ProbeTarget {
+                service_key: key.clone(),
+                check: service.health_check.clone(),
+                target: match &service.health_check.kind {
+                    HealthCheckKind::Http { path } => format!("http://127.0.0.1:{}{}", service.libp2p_port, path),
+                    HealthCheckKind::Libp2pPing => format!("/ip4/127.0.0.1/tcp/{}", service.libp2p_port),
+                },
+            })
+            .collect()
+    }
+
+    /// Apply a probe result. Returns a notification for the owner when the
+    /// service is delisted or recovers.
+    pub fn record_health_probe(&mut self, service_key: &str, success: bool,
+                               error: Option<String>) -> Result<Option<OwnerNotification>, String> {
+        let service = self.service_registry.get_mut(service_key)
+            .ok_or("Service not found")?;
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        service.certification.record_probe(success, now, self.certification_policy.window_days);
+        let health = &mut service.health;
+        let was_delisted = health.state == HealthState::Delisted;
+        health.last_checked = now;
+
+        let message = if success {
+            health.consecutive_failures = 0;
+            health.last_success = Some(now);
+            health.last_error = None;
+            health.state = HealthState::Healthy;
+
+            if was_delisted {
+                Some(format!("{} recovered and is listed again", service.service_name))
+            } else {
+                None
+            }
+        } else {
+            health.consecutive_failures += 1;
+            health.last_error = error;
+
+            if health.consecutive_failures >= service.health_check.failure_threshold {
+                health.state = HealthState::Delisted;
+                if was_delisted {
+                    None
+                } else {
+                    // A delisted service loses its badge now rather than at the next evaluation
+                    let decertified = service.certification.badge.take().is_some();
+                    if decertified {
+                        service.certification.revoked_at = Some(now);
+                    }
+                    Some(format!("{} delisted after {} failed health checks: {}{}",
+                        service.service_name, health.consecutive_failures,
+                        health.last_error.as_deref().unwrap_or("no response"),
+                        if decertified { " (certification revoked)" } else { "" }))
+                }
+            } else {
+                health.state = HealthState::Failing;
+                None
+            }
+        };
+
+        Ok(message.map(|message| {
+            println!("🩺 {}", message);
+            OwnerNotification {
+                wallet_address: service.wallet_address.clone(),
+                service_name: service.service_name.clone(),
+                message,
+                timestamp: now,
+            }
+        }))
+    }
+
+    pub fn is_delisted(&self, service_key: &str) -> bool {
+        self.service_registry.get(service_key)
+            .map(|service| service.health.state == HealthState::Delisted)
+            .unwrap_or(false)
+    }
+
+    /// Listed services with their health, for the marketplace and dashboard
+    pub fn marketplace_listing(&self) -> Vec<serde_json::Value> {
+        self.service_registry.values()
+            .filter(|service| service.health.state != HealthState::Delisted)
+            .map(|service| serde_json::json!({
+                "wallet_address": service.wallet_address,
+                "service_name": service.service_name,
+                "http_path": service.http_path,
+                "pricing": service.pricing,
+                "health": service.health,
+                "certification": service.certification.badge
+            }))
+            .collect()
+    }
+
+    pub fn service_unavailable_response(&self, service_key: &str) -> HttpResponse {
+        let health = self.service_registry.get(service_key).map(|service| &service.health);
+        let body = serde_json::json!({
+            "error": "Service unavailable",
+            "health": health
+        });
+
+        HttpResponse {
+            status_code: 503,
+            headers: HashMap::from([
+                ("Content-Type".to_string(), "application/json".to_string()),
+                ("Retry-After".to_string(), "60".to_string()),
+            ]),
+            body: serde_json::to_vec(&body).unwrap_or_default(),
+        }
+    }
+}
@@ -0,0 +1,343 @@
+use axum::body::{Body, Bytes};
+use axum::extract::rejection::QueryRejection;
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{any, get};
+use axum::Router;
+use crate::storage::{DurableGateway, GatewayStore};
+use crate::streaming::{StreamFramer, StreamSummary, StreamingCall};
+use crate::{EarningsQuery, HttpResponse, PublicGateway};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// A gateway the router can drive: the plain one, or one that logs every
+/// request to its store
+pub trait HttpGateway: Send + 'static {
+    fn handle(&mut self, path: &str, method: &str, headers: &HashMap<String, String>,
+              body: &[u8]) -> Result<HttpResponse, String>;
+    fn gateway(&self) -> &PublicGateway;
+    fn gateway_mut(&mut self) -> &mut PublicGateway;
+    /// Record changes made outside a request, where the gateway is durable
+    fn commit(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+impl HttpGateway for PublicGateway {
+    fn handle(&mut self, path: &str, method: &str, headers: &HashMap<String, String>,
+              body: &[u8]) -> Result<HttpResponse, String> {
+        self.handle_http_request(path, method, headers, body)
+    }
+
+    fn gateway(&self) -> &PublicGateway {
+        self
+    }
+
+    fn gateway_mut(&mut self) -> &mut PublicGateway {
+        self
+    }
+}
+
+impl<S: GatewayStore + Send + 'static> HttpGateway for DurableGateway<S> {
+    fn handle(&mut self, path: &str, method: &str, headers: &HashMap<String, String>,
+              body: &[u8]) -> Result<HttpResponse, String> {
+        self.handle_http_request(path, method, headers, body)
+    }
+
+    fn gateway(&self) -> &PublicGateway {
+        &self.gateway
+    }
+
+    fn gateway_mut(&mut self) -> &mut PublicGateway {
+        &mut self.gateway
+    }
+
+    fn commit(&mut self) -> Result<(), String> {
+        DurableGateway::commit(self).map(|_| ())
+    }
+}
+
+pub type SharedGateway<G> = Arc<Mutex<G>>;
+
+/// Frames a streamed response may have waiting for a slow caller before the
+/// service's stream is no longer read
+const STREAM_BUFFER_FRAMES: usize = 16;
+
+/// Routes for mounting the gateway in an axum server, e.g.
+/// `app.nest("/gateway", zos_public_gateway::http_router::router(gateway))`.
+/// Earnings dashboards and accounting exports are answered here; everything
+/// else, CORS preflights included, goes to `handle_http_request`, which does
+/// its own routing.
+pub fn router<G: HttpGateway>(gateway: SharedGateway<G>) -> Router {
+    Router::new()
+        .route("/:wallet/earnings", get(earnings_dashboard::<G>).options(dispatch::<G>))
+        .route("/:wallet/earnings/tax.csv", get(accounting_export::<G>).options(dispatch::<G>))
+        .route("/:wallet/earnings/summary.csv", get(accounting_export::<G>).options(dispatch::<G>))
+        .route("/:wallet/:service/swap", any(dispatch::<G>))
+        .route("/:wallet/:service/quote", any(dispatch::<G>))
+        .route("/:wallet", any(dispatch::<G>))
+        .route("/:wallet/:service", any(dispatch::<G>))
+        .route("/:wallet/:service/*rest", any(dispatch::<G>))
+        .fallback(dispatch::<G>)
+        .with_state(gateway)
+}
+
+/// Sweep expired swap quotes on the cache's interval, for as long as the
+/// server runs. Inserts also sweep when one is due; this keeps an idle
+/// cache from holding stale quotes.
+pub fn spawn_quote_sweeper<G: HttpGateway>(gateway: SharedGateway<G>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            let interval = match gateway.lock() {
+                Ok(mut gateway) => {
+                    let gateway = gateway.gateway_mut();
+                    let swept = gateway.sweep_quote_cache();
+                    if swept > 0 {
+                        println!("🧹 Swept {} expired quotes", swept);
+                    }
+                    gateway.payment_processor.quote_cache.config.sweep_interval_secs
+                }
+                Err(_) => return,
+            };
+            tokio::time::sleep(std::time::Duration::from_secs(interval)).await;
+        }
+    })
+}
+
+/// Re-run the certification checks on the policy's interval, awarding
+/// badges and revoking those of services that regressed
+pub fn spawn_certifier<G: HttpGateway>(gateway: SharedGateway<G>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            let interval = match gateway.lock() {
+                Ok(gateway) => gateway.gateway().certification_policy.evaluate_interval_secs,
+                Err(_) => return,
+            };
+            tokio::time::sleep(std::time::Duration::from_secs(interval)).await;
+
+            let Ok(mut gateway) = gateway.lock() else { return };
+            let now = chrono::Utc::now().timestamp() as u64;
+            gateway.gateway_mut().evaluate_certifications(now);
+            if let Err(e) = gateway.commit() {
+                println!("⚠️  Recording certifications failed: {}", e);
+            }
+        }
+    })
+}
+
+/// Re-derive every tier from the referral records on start and then hourly,
+/// so demotions land even when no referral changes through the API
+pub fn spawn_tier_recalculator<G: HttpGateway>(gateway: SharedGateway<G>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            {
+                let Ok(mut gateway) = gateway.lock() else { return };
+                match gateway.gateway_mut().recalculate_all_tiers() {
+                    Ok(changes) if !changes.is_empty() => println!("🏅 Tier recalculation changed {} tiers", changes.len()),
+                    Ok(_) => {}
+                    Err(e) => println!("⚠️  Tier recalculation failed: {}", e),
+                }
+                // Recounted referrals are worth keeping even when no tier moved
+                if let Err(e) = gateway.commit() {
+                    println!("⚠️  Recording tier recalculation failed: {}", e);
+                }
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(crate::tiers::TIER_RECALC_INTERVAL_SECS)).await;
+        }
+    })
+}
+
+/// Move ledger records past retention to cold storage on the archival
+/// interval. Runs off the async workers since segments may go to S3; the
+/// gateway is locked while a run seals and drops records.
+pub fn spawn_archiver<G: HttpGateway>(gateway: SharedGateway<G>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            let interval = match gateway.lock() {
+                Ok(gateway) => gateway.gateway().archival.interval_secs,
+                Err(_) => return,
+            };
+            tokio::time::sleep(std::time::Duration::from_secs(interval)).await;
+
+            let gateway = gateway.clone();
+            let run = tokio::task::spawn_blocking(move || -> Result<(), String> {
+                let mut store = zos_archive::store_from_env()?;
+                let mut gateway = gateway.lock().map_err(|_| "Gateway lock poisoned".to_string())?;
+                let now = chrono::Utc::now().timestamp() as u64;
+                let report = gateway.gateway_mut().archive_ledgers(store.as_mut(), now);
+                gateway.commit()?;
+                let report = report?;
+                if !report.segments.is_empty() {
+                    println!("🧊 Archived {} commissions and {} payments in {} segments to {}",
+                             report.commissions, report.payments, report.segments.len(), store.location());
+                }
+                Ok(())
+            }).await;
+            match run {
+                Ok(Err(e)) => println!("⚠️  Archival failed: {}", e),
+                Err(_) => return,
+                Ok(Ok(())) => {}
+            }
+        }
+    })
+}
+
+/// `x-wallet-address` → `X-Wallet-Address`, the spelling the gateway looks up
+fn canonical_header_name(name: &HeaderName) -> String {
+    name.as_str()
+        .split('-')
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn gateway_headers(headers: &HeaderMap) -> HashMap<String, String> {
+    headers.iter()
+        .filter_map(|(name, value)| {
+            value.to_str().ok().map(|value| (canonical_header_name(name), value.to_string()))
+        })
+        .collect()
+}
+
+fn error_response(status: StatusCode, error: &str) -> Response {
+    (status, axum::Json(serde_json::json!({ "error": error }))).into_response()
+}
+
+fn into_response(result: Result<HttpResponse, String>) -> Response {
+    let response = match result {
+        Ok(response) => response,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, &e),
+    };
+
+    let status = StatusCode::from_u16(response.status_code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    let mut reply = Response::new(Body::from(response.body));
+    *reply.status_mut() = status;
+    for (name, value) in response.headers {
+        if let (Ok(name), Ok(value)) = (HeaderName::try_from(name), HeaderValue::try_from(value)) {
+            reply.headers_mut().insert(name, value);
+        }
+    }
+    reply
+}
+
+/// The gateway is synchronous; calls hold the lock only while it handles them
+#[allow(clippy::result_large_err)]
+fn with_gateway<G: HttpGateway, T>(gateway: &SharedGateway<G>, f: impl FnOnce(&mut G) -> T) -> Result<T, Response> {
+    match gateway.lock() {
+        Ok(mut gateway) => Ok(f(&mut gateway)),
+        Err(_) => Err(error_response(StatusCode::INTERNAL_SERVER_ERROR, "Gateway state is poisoned")),
+    }
+}
+
+async fn dispatch<G: HttpGateway>(State(gateway): State<SharedGateway<G>>, method: Method, uri: Uri,
+                                  headers: HeaderMap, body: Bytes) -> Response {
+    let headers = gateway_headers(&headers);
+    // Streamed calls come back open, to be read outside the lock
+    let opened = with_gateway(&gateway, |gateway| {
+        if !gateway.gateway().streams_response(uri.path(), &headers) {
+            return Err(gateway.handle(uri.path(), method.as_str(), &headers, &body));
+        }
+        let opened = gateway.gateway_mut().open_stream(uri.path(), method.as_str(), &headers, &body);
+        if let Err(e) = gateway.commit() {
+            println!("⚠️  Recording a streamed call failed: {}", e);
+        }
+        match opened {
+            Ok(Ok(call)) => Ok(call),
+            Ok(Err(response)) => Err(Ok(response)),
+            Err(e) => Err(Err(e)),
+        }
+    });
+    match opened {
+        Ok(Ok(call)) => stream_response(gateway, call),
+        Ok(Err(result)) => into_response(result),
+        Err(response) => response,
+    }
+}
+
+/// Send a streamed response without buffering it or holding the gateway: a
+/// blocking task reads the service's chunks into a small channel that the
+/// body drains, stopping if the caller goes away, then meters the call
+fn stream_response<G: HttpGateway>(gateway: SharedGateway<G>, call: StreamingCall) -> Response {
+    let StreamingCall { service_key, format, response_headers, chunks, span, sampled, caller } = call;
+    let (sender, mut receiver) = tokio::sync::mpsc::channel::<Result<Vec<u8>, String>>(STREAM_BUFFER_FRAMES);
+
+    tokio::task::spawn_blocking(move || {
+        let started = std::time::Instant::now();
+        let mut framer = StreamFramer::new(format);
+        let mut summary = StreamSummary::default();
+        for chunk in chunks {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    summary.error = Some(e);
+                    break;
+                }
+            };
+            summary.first_chunk_ms.get_or_insert(started.elapsed().as_millis() as u64);
+            summary.bytes += chunk.len();
+            let Some(frame) = framer.frame(&chunk) else { continue };
+            if sender.blocking_send(Ok(frame)).is_err() {
+                summary.error = Some("Caller disconnected".to_string());
+                break;
+            }
+        }
+        match framer.finish(summary.error.as_deref()) {
+            Ok(Some(frame)) => { let _ = sender.blocking_send(Ok(frame)); }
+            Ok(None) => {}
+            Err(e) => { let _ = sender.blocking_send(Err(e)); }
+        }
+        summary.elapsed_ms = started.elapsed().as_millis() as u64;
+
+        let Ok(mut gateway) = gateway.lock() else { return };
+        gateway.gateway_mut().finish_stream(&service_key, caller.as_deref(), span, sampled, &summary);
+        if let Err(e) = gateway.commit() {
+            println!("⚠️  Recording a streamed call failed: {}", e);
+        }
+    });
+
+    let frames = futures_util::stream::poll_fn(move |cx| receiver.poll_recv(cx));
+    let mut reply = Response::new(Body::from_stream(frames));
+    for (name, value) in response_headers {
+        if let (Ok(name), Ok(value)) = (HeaderName::try_from(name), HeaderValue::try_from(value)) {
+            reply.headers_mut().insert(name, value);
+        }
+    }
+    reply
+}
+
+async fn earnings_dashboard<G: HttpGateway>(State(gateway): State<SharedGateway<G>>,
+                                            Path(wallet): Path<String>,
+                                            query: Result<Query<EarningsQuery>, QueryRejection>) -> Response {
+    let query = match query {
+        Ok(Query(query)) => query,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, &e.body_text()),
+    };
+    if let Err(e) = query.bounds() {
+        return error_response(StatusCode::BAD_REQUEST, &e);
+    }
+    let dashboard = match with_gateway(&gateway, |gateway| gateway.gateway().get_earnings_dashboard(&wallet, &query)) {
+        Ok(dashboard) => dashboard,
+        Err(response) => return response,
+    };
+    match dashboard {
+        Ok(json) => ([("Content-Type", "application/json")], json).into_response(),
+        Err(e) => error_response(StatusCode::NOT_FOUND, &e),
+    }
+}
+
+async fn accounting_export<G: HttpGateway>(State(gateway): State<SharedGateway<G>>, uri: Uri,
+                                           Path(wallet): Path<String>,
+                                           Query(query): Query<HashMap<String, String>>) -> Response {
+    let report = uri.path().rsplit('/').next().unwrap_or("").to_string();
+    match with_gateway(&gateway, |gateway| gateway.gateway().handle_accounting_export(&wallet, &report, &query)) {
+        Ok(result) => into_response(result),
+        Err(response) => response,
+    }
+}
@@ -0,0 +1,405 @@
+use serde::{Deserialize, Serialize};
+use crate::commission_events::{apply_event, empty_account, CommissionEvent, CommissionEventKind, EarningsDiscrepancy};
+use crate::receipts::json_response;
+use crate::refunds::Refund;
+use crate::screening::is_operator;
+use crate::tiers::{tier_threshold, TierChangeEvent};
+use crate::{CommissionPayment, CommissionSystem, HttpResponse, PaymentRecord, PricingTier, PublicGateway,
+            ReferralLink, ReferralRecord, ReferralStatus};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+
+/// Entries a page of GET /journal/after/{seq} holds
+const PAGE_SIZE: usize = 500;
+
+/// Something that happened at the gateway. Entries are only ever appended;
+/// the commission state can be rebuilt from them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum GatewayEvent {
+    /// Commission state as it stood when the journal started on a gateway
+    /// that already had some
+    CarriedOver { commission_system: Box<CommissionSystem> },
+    WalletRegistered { wallet_address: String, user_id: String },
+    ServiceRegistered { wallet_address: String, service_name: String, libp2p_port: u16, pricing_tier: PricingTier },
+    PaymentVerified { wallet_address: String, service_name: String, verified: bool },
+    PaymentRecorded { payment: PaymentRecord },
+    PaymentRefunded { payment_id: String, refund: Refund },
+    SwapExecuted {
+        wallet_address: String,
+        service_name: String,
+        transaction_id: String,
+        from_token: String,
+        to_token: String,
+        input_amount: f64,
+        output_amount: f64,
+        fee: f64,
+    },
+    ReferralLinkCreated { link: ReferralLink },
+    ReferralTracked { referral_key: String, record: ReferralRecord },
+    ReferralStatusChanged { referral_key: String, status: ReferralStatus },
+    CommissionPaid { payment: CommissionPayment },
+    /// An earnings event, as appended to the wallet's event log
+    Earnings { record: CommissionEvent },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub seq: u64, // from 1, without gaps
+    pub at: u64,
+    #[serde(flatten)]
+    pub event: GatewayEvent,
+}
+
+/// The journal, in memory and, with ZOS_GATEWAY_JOURNAL set, appended to
+/// that file as JSON lines so it outlives the state it can rebuild
+#[derive(Debug, Clone, Default)]
+pub struct GatewayJournal {
+    pub entries: Vec<JournalEntry>,
+    path: Option<PathBuf>,
+}
+
+impl GatewayJournal {
+    /// ZOS_GATEWAY_JOURNAL, reloading what it already holds
+    pub fn load() -> Self {
+        let path = match std::env::var("ZOS_GATEWAY_JOURNAL") {
+            Ok(path) if !path.is_empty() => PathBuf::from(path),
+            _ => return Self::default(),
+        };
+
+        let mut entries = Vec::new();
+        if let Ok(file) = std::fs::File::open(&path) {
+            for (number, line) in std::io::BufReader::new(file).lines().enumerate() {
+                let parsed = line.map_err(|e| e.to_string())
+                    .and_then(|line| serde_json::from_str::<JournalEntry>(&line).map_err(|e| e.to_string()));
+                match parsed {
+                    Ok(entry) => entries.push(entry),
+                    // A torn last write; entries after it would have gaps
+                    Err(e) => {
+                        println!("⚠️  Gateway journal {} stops at line {}: {}", path.display(), number + 1, e);
+                        break;
+                    }
+                }
+            }
+            println!("📒 Gateway journal: {} entries from {}", entries.len(), path.display());
+        }
+        Self { entries, path: Some(path) }
+    }
+
+    pub fn last_seq(&self) -> u64 {
+        self.entries.last().map_or(0, |entry| entry.seq)
+    }
+
+    pub fn append(&mut self, event: GatewayEvent) {
+        let entry = JournalEntry {
+            seq: self.last_seq() + 1,
+            at: chrono::Utc::now().timestamp() as u64,
+            event,
+        };
+        if let Some(path) = &self.path {
+            let written = serde_json::to_string(&entry).map_err(|e| e.to_string()).and_then(|line| {
+                std::fs::OpenOptions::new().create(true).append(true).open(path)
+                    .and_then(|mut file| writeln!(file, "{}", line))
+                    .map_err(|e| e.to_string())
+            });
+            if let Err(e) = written {
+                println!("⚠️  Gateway journal entry {} not written to {}: {}", entry.seq, path.display(), e);
+            }
+        }
+        self.entries.push(entry);
+    }
+
+    /// Replace a wallet with its pseudonym in every entry and rewrite the
+    /// file: erasure outranks append-only here as in the event logs
+    pub fn anonymize(&mut self, wallet_address: &str, pseudonym: &str) -> usize {
+        let mut changed = 0;
+        for entry in &mut self.entries {
+            let Ok(mut value) = serde_json::to_value(&*entry) else { continue };
+            let replaced = anonymize_value(&mut value, wallet_address, pseudonym, false);
+            if replaced > 0 {
+                match serde_json::from_value(value) {
+                    Ok(anonymized) => *entry = anonymized,
+                    Err(e) => println!("⚠️  Journal entry {} not anonymized: {}", entry.seq, e),
+                }
+                changed += 1;
+            }
+        }
+
+        if let (Some(path), true) = (&self.path, changed > 0) {
+            let lines: Result<Vec<String>, _> = self.entries.iter().map(serde_json::to_string).collect();
+            let temp = path.with_extension("anonymizing");
+            let written = lines.map_err(|e| e.to_string()).and_then(|lines| {
+                std::fs::write(&temp, lines.join("\n") + "\n")
+                    .and_then(|_| std::fs::rename(&temp, path))
+                    .map_err(|e| e.to_string())
+            });
+            if let Err(e) = written {
+                println!("⚠️  Gateway journal {} not rewritten: {}", path.display(), e);
+            }
+        }
+        changed
+    }
+
+    /// Entries after `seq`, a page at a time
+    pub fn after(&self, seq: u64) -> &[JournalEntry] {
+        let start = self.entries.partition_point(|entry| entry.seq <= seq);
+        &self.entries[start..(start + PAGE_SIZE).min(self.entries.len())]
+    }
+}
+
+/// Swap the wallet for its pseudonym wherever a string is exactly it, and
+/// in the `{referrer}_{referee}` keys of referrals
+fn anonymize_value(value: &mut Value, wallet_address: &str, pseudonym: &str, referral_key: bool) -> usize {
+    let rename = |text: &mut String, referral_key: bool| -> usize {
+        if text == wallet_address {
+            *text = pseudonym.to_string();
+            return 1;
+        }
+        if referral_key {
+            let parts: Vec<&str> = text.splitn(2, '_').collect();
+            if parts.contains(&wallet_address) {
+                *text = parts.iter().map(|part| if *part == wallet_address { pseudonym } else { part })
+                    .collect::<Vec<_>>().join("_");
+                return 1;
+            }
+        }
+        0
+    };
+
+    match value {
+        Value::String(text) => rename(text, referral_key),
+        Value::Array(values) => values.iter_mut()
+            .map(|value| anonymize_value(value, wallet_address, pseudonym, false))
+            .sum(),
+        Value::Object(fields) => {
+            // In referral_tracking keys are referral keys; in referee_index, values
+            let mut changed = 0;
+            for (mut name, mut field) in std::mem::take(fields) {
+                changed += rename(&mut name, referral_key);
+                let holds_referral_keys = referral_key
+                    || matches!(name.as_str(), "referral_key" | "referral_tracking" | "referee_index");
+                changed += anonymize_value(&mut field, wallet_address, pseudonym, holds_referral_keys);
+                fields.insert(name, field);
+            }
+            changed
+        }
+        _ => 0,
+    }
+}
+
+/// Rebuild commission state from journal entries. Earnings accounts and
+/// their event logs, commission payments, referral links and referrals,
+/// tier history and claimed milestones come from the journal; rates,
+/// milestones, payout schedules and the withdrawal queue come from `base`,
+/// unless the journal starts from a carried-over state.
+pub fn replay_commission_system(base: &CommissionSystem, entries: &[JournalEntry]) -> CommissionSystem {
+    let mut system = base.clone();
+    system.earnings_ledger.clear();
+    system.events.clear();
+    system.event_seq = 0;
+    system.commission_history.clear();
+    system.referral_links.clear();
+    system.referral_tracking.clear();
+    system.referee_index.clear();
+    system.tier_events.clear();
+    system.claimed_milestones.clear();
+
+    for entry in entries {
+        match &entry.event {
+            GatewayEvent::CarriedOver { commission_system } => system = (**commission_system).clone(),
+            GatewayEvent::ReferralLinkCreated { link } => {
+                system.referral_links.insert(link.link_id.clone(), link.clone());
+            }
+            GatewayEvent::ReferralTracked { referral_key, record } => {
+                if let Some(link) = system.referral_links.get_mut(&record.referral_code) {
+                    link.conversion_count += 1;
+                }
+                system.insert_referral(referral_key.clone(), record.clone());
+            }
+            GatewayEvent::ReferralStatusChanged { referral_key, status } => {
+                if let Some(referral) = system.referral_tracking.get_mut(referral_key) {
+                    referral.status = status.clone();
+                }
+            }
+            GatewayEvent::CommissionPaid { payment } => {
+                system.commission_history.entry(payment.recipient_wallet.clone()).or_default().push(payment.clone());
+            }
+            GatewayEvent::Earnings { record } => {
+                let wallet_address = &record.wallet_address;
+                if let CommissionEventKind::TierChanged { from, to, referral_count } = &record.kind {
+                    system.tier_events.push(TierChangeEvent {
+                        wallet_address: wallet_address.clone(),
+                        from: from.clone(),
+                        to: to.clone(),
+                        referral_count: *referral_count,
+                        timestamp: record.timestamp,
+                    });
+                    let claimed = system.claimed_milestones.entry(wallet_address.clone()).or_default();
+                    for milestone in system.milestones.iter().filter(|m| tier_threshold(&m.tier) <= tier_threshold(to)) {
+                        if !claimed.contains(&milestone.milestone_id) {
+                            claimed.push(milestone.milestone_id.clone());
+                        }
+                    }
+                }
+                let account = system.earnings_ledger.entry(wallet_address.clone())
+                    .or_insert_with(|| empty_account(wallet_address, record.timestamp));
+                apply_event(account, record);
+                system.events.entry(wallet_address.clone()).or_default().push(record.clone());
+                system.event_seq = system.event_seq.max(record.seq + 1);
+            }
+            _ => {}
+        }
+    }
+    system.journal_outbox.clear();
+    system
+}
+
+/// Where the stored commission state and a replay of the journal disagree
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct JournalAudit {
+    pub entries: usize,
+    pub accounts: Vec<EarningsDiscrepancy>,
+    pub payments_not_journaled: Vec<String>, // stored commission payments the journal never saw
+    pub payments_not_stored: usize,          // journaled ones no longer stored, archived ones included
+    pub referrals: Vec<String>,              // referral keys missing on one side or with another status
+}
+
+impl JournalAudit {
+    pub fn is_clean(&self) -> bool {
+        self.accounts.is_empty() && self.payments_not_journaled.is_empty() && self.referrals.is_empty()
+    }
+}
+
+fn audit(stored: &CommissionSystem, replayed: &CommissionSystem, entries: usize) -> JournalAudit {
+    let wallets: BTreeSet<&String> = stored.earnings_ledger.keys().chain(replayed.earnings_ledger.keys()).collect();
+    let accounts = wallets.into_iter()
+        .filter_map(|wallet_address| {
+            let stored = stored.earnings_ledger.get(wallet_address).cloned();
+            let derived = replayed.earnings_ledger.get(wallet_address).cloned();
+            let same = serde_json::to_value(&stored).ok() == serde_json::to_value(&derived).ok();
+            (!same).then(|| EarningsDiscrepancy { wallet_address: wallet_address.clone(), stored, derived })
+        })
+        .collect();
+
+    let payment_ids = |system: &CommissionSystem| -> BTreeSet<String> {
+        system.commission_history.values().flatten().map(|payment| payment.payment_id.clone()).collect()
+    };
+    let (stored_ids, replayed_ids) = (payment_ids(stored), payment_ids(replayed));
+
+    let keys: BTreeSet<&String> = stored.referral_tracking.keys().chain(replayed.referral_tracking.keys()).collect();
+    let referrals = keys.into_iter()
+        .filter(|key| {
+            let status = |system: &CommissionSystem| system.referral_tracking.get(*key)
+                .map(|referral| format!("{:?}", referral.status));
+            status(stored) != status(replayed)
+        })
+        .cloned()
+        .collect();
+
+    JournalAudit {
+        entries,
+        accounts,
+        payments_not_journaled: stored_ids.difference(&replayed_ids).cloned().collect(),
+        payments_not_stored: replayed_ids.difference(&stored_ids).count(),
+        referrals,
+    }
+}
+
+impl PublicGateway {
+    /// Append to the journal, after any earnings events and referrals the
+    /// commission system recorded since the last entry, so the order holds
+    pub(crate) fn journal(&mut self, event: GatewayEvent) {
+        self.flush_journal();
+        if !self.sandbox_mode {
+            self.journal.append(event);
+        }
+    }
+
+    /// Move what the commission system recorded into the journal
+    pub fn flush_journal(&mut self) {
+        let Some(system) = self.commission_system.as_mut() else { return };
+        let recorded = std::mem::take(&mut system.journal_outbox);
+        if self.sandbox_mode {
+            return;
+        }
+        for event in recorded {
+            self.journal.append(event);
+        }
+    }
+
+    /// Start an empty journal from restored commission state, which it
+    /// could not otherwise rebuild
+    pub(crate) fn carry_over_into_journal(&mut self) {
+        if self.sandbox_mode || !self.journal.entries.is_empty() {
+            return;
+        }
+        if let Some(system) = self.commission_system.as_mut() {
+            system.journal_outbox.clear(); // already part of the state carried over
+            let commission_system = Box::new(system.clone());
+            self.journal.append(GatewayEvent::CarriedOver { commission_system });
+        }
+    }
+
+    /// Compare the stored commission state with a replay of the journal
+    pub fn audit_journal(&mut self) -> Result<JournalAudit, String> {
+        self.flush_journal();
+        let stored = self.commission_system.as_ref().ok_or("Commission system not initialized")?;
+        let replayed = replay_commission_system(stored, &self.journal.entries);
+        Ok(audit(stored, &replayed, self.journal.entries.len()))
+    }
+
+    /// Replace the commission state with a replay of the journal, for
+    /// recovery after the state was lost or damaged. Returns what changed.
+    pub fn restore_from_journal(&mut self) -> Result<JournalAudit, String> {
+        self.flush_journal();
+        if self.commission_system.is_none() {
+            self.initialize_commission_system();
+        }
+        let stored = self.commission_system.as_ref().ok_or("Commission system not initialized")?;
+        let replayed = replay_commission_system(stored, &self.journal.entries);
+        let changes = audit(stored, &replayed, self.journal.entries.len());
+        self.commission_system = Some(replayed);
+        println!("📒 Commission state restored from {} journal entries", changes.entries);
+        Ok(changes)
+    }
+
+    /// GET /journal, GET /journal/after/{seq}, GET /journal/audit,
+    /// POST /journal/restore; operators only
+    pub(crate) fn handle_journal_request(&mut self, path: &str, method: &str,
+                                         headers: &HashMap<String, String>) -> Result<HttpResponse, String> {
+        if !is_operator(headers) {
+            return json_response(403, &serde_json::json!({ "error": "Needs X-Operator-Key" }));
+        }
+        self.flush_journal();
+
+        let path = path.trim_end_matches('/');
+        match (method, path) {
+            ("GET", "/journal") => {
+                let entries = &self.journal.entries;
+                json_response(200, &serde_json::json!({
+                    "last_seq": self.journal.last_seq(),
+                    "entries": &entries[entries.len().saturating_sub(100)..],
+                }))
+            }
+            ("GET", "/journal/audit") => {
+                let audit = self.audit_journal()?;
+                json_response(200, &serde_json::json!({ "clean": audit.is_clean(), "audit": audit }))
+            }
+            ("POST", "/journal/restore") => {
+                let changes = self.restore_from_journal()?;
+                json_response(200, &serde_json::json!({ "changed": changes }))
+            }
+            ("GET", _) if path.starts_with("/journal/after/") => {
+                let seq = path["/journal/after/".len()..].parse::<u64>()
+                    .map_err(|_| "Sequence must be a number".to_string())?;
+                json_response(200, &serde_json::json!({
+                    "last_seq": self.journal.last_seq(),
+                    "entries": self.journal.after(seq),
+                }))
+            }
+            _ => Err("Unsupported journal request".to_string()),
+        }
+    }
+}
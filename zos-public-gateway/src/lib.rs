@@ -1,13 +1,165 @@
 use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 
+pub mod access_policy;
+pub mod accounting;
+pub mod amm;
+pub mod api_keys;
+pub mod certification;
+pub mod cluster_limits;
+pub mod cold_storage;
+pub mod commission_events;
+pub mod commission_rates;
+pub mod contracts;
+pub mod cors;
+pub mod coupons;
+pub mod data_export;
+pub mod edge_cache;
+pub mod estimate;
+pub mod experiments;
+pub mod explorer;
+pub mod fee_routing;
+pub mod health;
+pub mod http_router;
+pub mod journal;
+pub mod metering;
+pub mod metrics;
+pub mod mirror;
+pub mod nft_gate;
+pub mod openapi;
+pub mod passthrough;
+pub mod payout_approvals;
+pub mod qr;
+pub mod quote_cache;
+pub mod rate_limits;
+pub mod receipts;
+pub mod recommendations;
+pub mod refunds;
+pub mod sandbox;
+pub mod screening;
+pub mod scheduler;
+pub mod session_routes;
+pub mod short_links;
+pub mod state_sync;
+pub mod storage;
+pub mod streaming;
+pub mod tiers;
+pub mod token_amount;
+pub mod trace_context;
+pub mod transforms;
+pub mod withdrawals;
+
+use accounting::AccountingLedger;
+use api_keys::ApiKeyStore;
+use certification::{CertificationPolicy, ServiceCertification};
+use cold_storage::ArchivalConfig;
+use commission_events::{CommissionEvent, CommissionEventKind};
+use cluster_limits::ClusterRateLimiter;
+use commission_rates::{CommissionPlan, RateCaps, RateChange};
+use contracts::ServiceContract;
+use cors::CorsPolicy;
+use coupons::CouponBook;
+use edge_cache::EdgeCache;
+use experiments::ExperimentRegistry;
+use estimate::CostEstimator;
+use explorer::EconomyStats;
+use fee_routing::FeeRoutingLedger;
+use health::{HealthCheck, ServiceHealth};
+use journal::{GatewayEvent, GatewayJournal};
+use metering::UsageMeter;
+use metrics::GatewayMetrics;
+use mirror::MirrorConfig;
+use nft_gate::{NftGating, NftRequirement};
+use passthrough::{AuthorizedCall, BodyMode};
+use quote_cache::QuoteCacheStore;
+use rate_limits::UsageStats;
+use receipts::ReceiptLedger;
+use recommendations::ConsumerUsage;
+use refunds::{Refund, RefundPolicy};
+use sandbox::Sandbox;
+use screening::{ScreeningPurpose, WalletCompliance};
+use scheduler::RequestScheduler;
+use session_routes::SessionRoutes;
+use short_links::ShortLink;
+use state_sync::StateSync;
+use streaming::ResponseMode;
+use tiers::{GrantedReward, MilestoneReward, TierChangeEvent};
+use token_amount::TokenAmount;
+use trace_context::{OpenSpan, SpanExporter, TraceParent};
+use transforms::ServiceTransforms;
+use withdrawals::{Payout, PayoutRecord, PayoutSchedule, Withdrawal};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommissionSystem {
     pub referral_tracking: HashMap<String, ReferralRecord>,
     pub commission_rates: CommissionRates,
-    pub earnings_ledger: HashMap<String, EarningsAccount>,
+    pub earnings_ledger: HashMap<String, EarningsAccount>, // fold of `events`; never edited directly
     pub referral_links: HashMap<String, ReferralLink>,
     pub commission_history: HashMap<String, Vec<CommissionPayment>>,
+    #[serde(default)]
+    pub short_links: HashMap<String, ShortLink>,
+    #[serde(default)]
+    pub tier_events: Vec<TierChangeEvent>,
+    #[serde(default = "tiers::default_milestones")]
+    pub milestones: Vec<MilestoneReward>,
+    #[serde(default)]
+    pub claimed_milestones: HashMap<String, Vec<String>>, // wallet -> milestone ids
+    #[serde(default)]
+    pub granted_rewards: Vec<GrantedReward>,
+    #[serde(default)]
+    pub rate_changes: Vec<RateChange>, // every rate set ever scheduled, oldest first
+    #[serde(default)]
+    pub rate_caps: RateCaps,
+    #[serde(default)]
+    pub commission_plans: HashMap<String, Vec<CommissionPlan>>, // service key -> owner's plans, oldest first
+    #[serde(default = "withdrawals::default_payout_schedules")]
+    pub payout_schedules: HashMap<String, PayoutSchedule>, // tier name -> schedule
+    #[serde(default)]
+    pub withdrawals: HashMap<String, Withdrawal>, // by withdrawal id
+    #[serde(default)]
+    pub payouts: HashMap<String, Payout>, // handed to the host, not yet confirmed
+    #[serde(default)]
+    pub payout_history: HashMap<String, Vec<PayoutRecord>>, // wallet -> completed payouts
+    #[serde(default)]
+    pub payout_seq: u64, // sequence for payout ids
+    #[serde(default)]
+    pub events: HashMap<String, Vec<CommissionEvent>>, // wallet -> append-only earnings events
+    #[serde(default)]
+    pub event_seq: u64,
+    #[serde(default)]
+    pub referee_index: HashMap<String, String>, // referee wallet -> referral_tracking key
+    #[serde(default)]
+    pub commission_archive: zos_archive::Archive, // segments holding commission_history past retention
+    #[serde(default)]
+    pub payout_approvals: zos_approvals::ApprovalBook, // co-signing of payouts over the threshold
+    #[serde(skip)]
+    pub(crate) journal_outbox: Vec<GatewayEvent>, // recorded here, moved to the gateway journal in order
+}
+
+impl CommissionSystem {
+    /// Track a referral and index it by referee; a referee keeps the first
+    /// referrer that signed them up
+    pub fn insert_referral(&mut self, referral_key: String, record: ReferralRecord) {
+        self.referee_index.entry(record.referee_wallet.clone())
+            .or_insert_with(|| referral_key.clone());
+        self.journal_outbox.push(GatewayEvent::ReferralTracked { referral_key: referral_key.clone(), record: record.clone() });
+        self.referral_tracking.insert(referral_key, record);
+    }
+
+    /// Rebuild `referee_index` from `referral_tracking`, earliest referral
+    /// first, for state saved before it existed or after keys were rewritten
+    pub fn rebuild_referee_index(&mut self) {
+        let mut referrals: Vec<(&String, &ReferralRecord)> = self.referral_tracking.iter().collect();
+        referrals.sort_by(|(a_key, a), (b_key, b)| {
+            a.first_transaction_at.cmp(&b.first_transaction_at).then_with(|| a_key.cmp(b_key))
+        });
+
+        let mut index = HashMap::new();
+        for (key, record) in referrals {
+            index.entry(record.referee_wallet.clone()).or_insert_with(|| key.clone());
+        }
+        self.referee_index = index;
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,10 +187,14 @@ pub struct EarningsAccount {
     pub total_earned_usdc: f64,
     pub total_earned_solfunmeme: f64,
     pub pending_withdrawals: f64,
+    #[serde(default)]
+    pub total_withdrawn_usdc: f64,
     pub lifetime_volume: f64,
     pub referral_count: u32,
     pub tier: EarningsTier,
     pub last_payout: u64,
+    #[serde(default)]
+    pub earned_by_token: HashMap<String, TokenAmount>, // exact; the totals above are read from these
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -50,6 +206,8 @@ pub struct ReferralLink {
     pub click_count: u32,
     pub conversion_count: u32,
     pub created_at: u64,
+    #[serde(default)]
+    pub promo_conversions: u32, // of conversion_count, first uses of a coupon tied to this link
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -61,16 +219,23 @@ pub struct CommissionPayment {
     pub commission_type: CommissionType,
     pub source_transaction: String,
     pub timestamp: u64,
+    #[serde(default)]
+    pub base_amount: Option<f64>, // what the commission rate was applied to; None for fixed bonuses
+    #[serde(default)]
+    pub exact: Option<TokenAmount>, // `amount` in base units of `token`
+    #[serde(default)]
+    pub service_key: Option<String>, // service whose commission plan priced it, if any
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ReferralStatus {
     Active,
-    Inactive,
-    Suspended,
-    Graduated, // No longer needs referrer
+    Inactive,  // Stops counting toward the referrer's tier
+    Suspended, // Stops counting toward the referrer's tier
+    Graduated, // No longer needs referrer; still counts
 }
 
+/// Set by distinct Active or Graduated referrals, so it can go down as well as up
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum EarningsTier {
     Bronze,   // 0-10 referrals
@@ -79,32 +244,106 @@ pub enum EarningsTier {
     Platinum, // 201+ referrals
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CommissionType {
     SwapFee,
     ReferralBonus,
     ServiceFee,
     VolumeBonus,
+    EdgeCacheHits, // edge nodes paid per verified cache hit
+}
+
+const DEFAULT_PAGE_SIZE: usize = 20;
+const MAX_PAGE_SIZE: usize = 100;
+
+/// Query parameters of the earnings dashboard's payment listing
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct EarningsQuery {
+    pub from: Option<u64>, // unix seconds, inclusive
+    pub to: Option<u64>,
+    pub page: Option<usize>, // from 1
+    pub page_size: Option<usize>,
+    pub commission_type: Option<CommissionType>,
+}
+
+impl EarningsQuery {
+    /// (from, to, page, page_size) with defaults filled in, or why the query
+    /// is malformed
+    pub fn bounds(&self) -> Result<(u64, u64, usize, usize), String> {
+        let from = self.from.unwrap_or(0);
+        let to = self.to.unwrap_or(u64::MAX);
+        if from > to {
+            return Err("from must not be after to".to_string());
+        }
+        let page = self.page.unwrap_or(1);
+        if page == 0 {
+            return Err("page starts at 1".to_string());
+        }
+        let page_size = self.page_size.unwrap_or(DEFAULT_PAGE_SIZE);
+        if !(1..=MAX_PAGE_SIZE).contains(&page_size) {
+            return Err(format!("page_size must be between 1 and {}", MAX_PAGE_SIZE));
+        }
+        Ok((from, to, page, page_size))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pagination {
+    pub page: usize,
+    pub page_size: usize,
+    pub total_items: usize,
+    pub total_pages: usize,
+    pub has_previous: bool,
+    pub has_next: bool,
+}
+
+/// One page of commission payments, with totals over every page
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentPage {
+    pub payments: Vec<CommissionPayment>,
+    pub pagination: Pagination,
+    pub from: Option<u64>,
+    pub to: Option<u64>,
+    pub commission_type: Option<CommissionType>,
+    pub totals_by_token: std::collections::BTreeMap<String, f64>,
 }
 
 impl PublicGateway {
     pub fn initialize_commission_system(&mut self) {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let rates = CommissionRates::default();
         self.commission_system = Some(CommissionSystem {
             referral_tracking: HashMap::new(),
-            commission_rates: CommissionRates {
-                swap_commission_percentage: 20.0,    // 20% of swap fees
-                referral_commission_percentage: 10.0, // 10% of referee's fees
-                service_commission_percentage: 5.0,   // 5% of service payments
-                tier_multipliers: HashMap::from([
-                    ("Bronze".to_string(), 1.0),
-                    ("Silver".to_string(), 1.2),
-                    ("Gold".to_string(), 1.5),
-                    ("Platinum".to_string(), 2.0),
-                ]),
-            },
+            commission_rates: rates.clone(),
             earnings_ledger: HashMap::new(),
             referral_links: HashMap::new(),
             commission_history: HashMap::new(),
+            short_links: HashMap::new(),
+            tier_events: Vec::new(),
+            milestones: tiers::default_milestones(),
+            claimed_milestones: HashMap::new(),
+            granted_rewards: Vec::new(),
+            rate_changes: vec![RateChange {
+                change_id: "rates_initial".to_string(),
+                rates,
+                effective_at: now,
+                scheduled_at: now,
+                proposal_id: None,
+                cancelled_at: None,
+            }],
+            rate_caps: RateCaps::default(),
+            commission_plans: HashMap::new(),
+            payout_schedules: withdrawals::default_payout_schedules(),
+            withdrawals: HashMap::new(),
+            payouts: HashMap::new(),
+            payout_history: HashMap::new(),
+            payout_seq: 0,
+            events: HashMap::new(),
+            event_seq: 0,
+            referee_index: HashMap::new(),
+            commission_archive: zos_archive::Archive::default(),
+            payout_approvals: zos_approvals::ApprovalBook::default(),
+            journal_outbox: Vec::new(),
         });
     }
 
@@ -121,20 +360,23 @@ impl PublicGateway {
             click_count: 0,
             conversion_count: 0,
             created_at: chrono::Utc::now().timestamp() as u64,
+            promo_conversions: 0,
         };
 
         let commission_system = self.commission_system.as_mut()
             .ok_or("Commission system not initialized")?;
 
-        commission_system.referral_links.insert(link_id.clone(), referral_link);
+        commission_system.referral_links.insert(link_id.clone(), referral_link.clone());
+        self.journal(GatewayEvent::ReferralLinkCreated { link: referral_link });
 
-        // Generate referral URL
+        // Generate referral URL, handed out as a short link
         let referral_url = format!("https://{}/{}?ref={}",
                                   self.domain, service_endpoint, link_id);
+        let short_url = self.create_short_link(&link_id, referrer_wallet, &referral_url)?;
 
-        println!("🔗 Referral link created: {} → {}", &referrer_wallet[..8], referral_url);
+        println!("🔗 Referral link created: {} → {} ({})", &referrer_wallet[..8], short_url, referral_url);
 
-        Ok(referral_url)
+        Ok(short_url)
     }
 
     pub fn track_referral(&mut self, referral_code: &str, referee_wallet: &str) -> Result<(), String> {
@@ -146,13 +388,16 @@ impl PublicGateway {
             .ok_or("Invalid referral code")?;
 
         referral_link.click_count += 1;
+        let referrer_wallet = referral_link.referrer_wallet.clone();
 
         // Check if this is a new referral
-        let referral_key = format!("{}_{}", referral_link.referrer_wallet, referee_wallet);
+        let referral_key = format!("{}_{}", referrer_wallet, referee_wallet);
 
         if !commission_system.referral_tracking.contains_key(&referral_key) {
+            referral_link.conversion_count += 1;
+
             let referral_record = ReferralRecord {
-                referrer_wallet: referral_link.referrer_wallet.clone(),
+                referrer_wallet: referrer_wallet.clone(),
                 referee_wallet: referee_wallet.to_string(),
                 referral_code: referral_code.to_string(),
                 first_transaction_at: chrono::Utc::now().timestamp() as u64,
@@ -161,71 +406,96 @@ impl PublicGateway {
                 status: ReferralStatus::Active,
             };
 
-            commission_system.referral_tracking.insert(referral_key, referral_record);
-            referral_link.conversion_count += 1;
+            commission_system.insert_referral(referral_key, referral_record);
 
-            // Update referrer's earnings account
-            self.update_earnings_account(&referral_link.referrer_wallet, 0.0, CommissionType::ReferralBonus)?;
+            // The new referee may move the referrer up a tier
+            self.recalculate_tier(&referrer_wallet)?;
 
             println!("👥 New referral tracked: {} → {}",
-                     &referral_link.referrer_wallet[..8], &referee_wallet[..8]);
+                     &referrer_wallet[..8], &referee_wallet[..8]);
         }
 
         Ok(())
     }
 
+    /// Pay the commissions on a transaction; amounts are in `token`, which
+    /// the commissions are paid in
     pub fn calculate_and_pay_commissions(&mut self, transaction_type: &str,
-                                       transaction_amount: f64, fee_amount: f64,
+                                       transaction_amount: f64, fee_amount: f64, token: &str,
                                        payer_wallet: &str, service_endpoint: &str) -> Result<(), String> {
+        self.pay_transaction_commissions(transaction_type, transaction_type, transaction_amount, fee_amount, token,
+                                         payer_wallet, service_endpoint)
+    }
 
-        let commission_system = self.commission_system.as_mut()
-            .ok_or("Commission system not initialized")?;
+    /// As `calculate_and_pay_commissions`, with the commissions recorded
+    /// against `source_transaction` so a refund of it can find them
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn pay_transaction_commissions(&mut self, transaction_type: &str, source_transaction: &str,
+                                              transaction_amount: f64, fee_amount: f64, token: &str,
+                                              payer_wallet: &str, service_endpoint: &str) -> Result<(), String> {
+
+        // Scheduled rate changes take over once their effective date passes
+        self.apply_due_rate_changes();
+
+        // The service's own plan when it has one in force, else the global rates
+        let rates = self.commission_rates_for(service_endpoint, chrono::Utc::now().timestamp() as u64)?;
+        let service_key = Some(service_endpoint);
+        let service_owner = self.service_registry.get(service_endpoint)
+            .map(|service| service.wallet_address.clone());
 
         // 1. Pay service endpoint owner (swap commission)
-        if let Some(service) = self.service_registry.get(service_endpoint) {
-            let swap_commission = fee_amount * commission_system.commission_rates.swap_commission_percentage / 100.0;
+        if let Some(owner) = &service_owner {
+            let swap_commission = fee_amount * rates.swap_commission_percentage / 100.0;
 
-            self.pay_commission(&service.wallet_address, swap_commission,
-                              CommissionType::SwapFee, transaction_type)?;
+            self.pay_commission_in(owner, token, swap_commission,
+                                 CommissionType::SwapFee, source_transaction, Some(fee_amount), service_key)?;
         }
 
-        // 2. Pay referrer commission (if payer was referred)
-        let referral_key_pattern = format!("_{}", payer_wallet);
+        // 2. Pay referrer commission (if payer was referred), found through
+        // the referee index rather than by scanning every referral
+        let referral = self.commission_system.as_ref()
+            .and_then(|system| {
+                let key = system.referee_index.get(payer_wallet)?;
+                let referral = system.referral_tracking.get(key)?;
+                matches!(referral.status, ReferralStatus::Active)
+                    .then(|| (key.clone(), referral.referrer_wallet.clone()))
+            });
 
-        for (key, referral) in &mut commission_system.referral_tracking {
-            if key.ends_with(&referral_key_pattern) && matches!(referral.status, ReferralStatus::Active) {
-                let referral_commission = fee_amount * commission_system.commission_rates.referral_commission_percentage / 100.0;
+        if let Some((referral_key, referrer_wallet)) = referral {
+            let referral_commission = fee_amount * rates.referral_commission_percentage / 100.0;
 
-                // Apply tier multiplier
-                let earnings_account = commission_system.earnings_ledger
-                    .get(&referral.referrer_wallet)
-                    .cloned()
-                    .unwrap_or_else(|| self.create_default_earnings_account(&referral.referrer_wallet));
+            // Apply tier multiplier
+            let tier = self.commission_system.as_ref()
+                .and_then(|system| system.earnings_ledger.get(&referrer_wallet))
+                .map(|account| account.tier.clone())
+                .unwrap_or(EarningsTier::Bronze);
 
-                let tier_multiplier = commission_system.commission_rates.tier_multipliers
-                    .get(&format!("{:?}", earnings_account.tier))
-                    .unwrap_or(&1.0);
+            let tier_multiplier = rates.tier_multipliers
+                .get(&format!("{:?}", tier))
+                .copied()
+                .unwrap_or(1.0);
 
-                let final_commission = referral_commission * tier_multiplier;
+            let final_commission = referral_commission * tier_multiplier;
 
-                self.pay_commission(&referral.referrer_wallet, final_commission,
-                                  CommissionType::ReferralBonus, transaction_type)?;
+            self.pay_commission_in(&referrer_wallet, token, final_commission,
+                                 CommissionType::ReferralBonus, source_transaction, Some(fee_amount), service_key)?;
 
-                // Update referral stats
+            // Update referral stats
+            if let Some(referral) = self.commission_system.as_mut()
+                .and_then(|system| system.referral_tracking.get_mut(&referral_key)) {
                 referral.total_volume += transaction_amount;
                 referral.total_commissions_earned += final_commission;
-
-                break;
             }
         }
 
         // 3. Pay service usage commission (if different from swap)
         if transaction_type == "service_call" {
-            if let Some(service) = self.service_registry.get(service_endpoint) {
-                let service_commission = transaction_amount * commission_system.commission_rates.service_commission_percentage / 100.0;
+            if let Some(owner) = &service_owner {
+                let service_commission = transaction_amount * rates.service_commission_percentage / 100.0;
 
-                self.pay_commission(&service.wallet_address, service_commission,
-                                  CommissionType::ServiceFee, transaction_type)?;
+                self.pay_commission_in(owner, token, service_commission,
+                                     CommissionType::ServiceFee, source_transaction, Some(transaction_amount),
+                                     service_key)?;
             }
         }
 
@@ -233,73 +503,72 @@ impl PublicGateway {
     }
 
     fn pay_commission(&mut self, recipient_wallet: &str, amount: f64,
-                     commission_type: CommissionType, source_tx: &str) -> Result<(), String> {
+                     commission_type: CommissionType, source_tx: &str,
+                     base_amount: Option<f64>) -> Result<(), String> {
+        self.pay_commission_in(recipient_wallet, "USDC", amount, commission_type, source_tx, base_amount, None)
+    }
 
-        let commission_system = self.commission_system.as_mut()
-            .ok_or("Commission system not initialized")?;
+    /// Pay a commission in `token`, rounded once to the token's base units so
+    /// the wallet's per-token earnings add up exactly
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn pay_commission_in(&mut self, recipient_wallet: &str, token: &str, amount: f64,
+                                    commission_type: CommissionType, source_tx: &str,
+                                    base_amount: Option<f64>, service_key: Option<&str>) -> Result<(), String> {
+        let decimals = self.token_decimals(token)
+            .ok_or(format!("Unsupported commission token {}", token))?;
+        let exact = TokenAmount::from_decimal(amount, decimals)?;
 
         // Update earnings account
-        self.update_earnings_account(recipient_wallet, amount, commission_type.clone())?;
+        self.update_earnings_account(recipient_wallet, token, exact, commission_type.clone(), source_tx)?;
+        let earned_seq = self.commission_system.as_ref().map_or(0, |system| system.event_seq.saturating_sub(1));
 
-        // Record commission payment
+        // Record commission payment; the event seq keeps ids apart when one
+        // transaction pays a wallet twice in the same second
         let payment = CommissionPayment {
-            payment_id: format!("comm_{}_{}", recipient_wallet, chrono::Utc::now().timestamp()),
+            payment_id: format!("comm_{}_{}_{}", recipient_wallet, chrono::Utc::now().timestamp(), earned_seq),
             recipient_wallet: recipient_wallet.to_string(),
-            amount,
-            token: "USDC".to_string(), // Default to USDC
+            amount: exact.to_decimal(),
+            token: token.to_string(),
             commission_type,
             source_transaction: source_tx.to_string(),
             timestamp: chrono::Utc::now().timestamp() as u64,
+            base_amount,
+            exact: Some(exact),
+            service_key: service_key.map(str::to_string),
         };
 
-        commission_system.commission_history
+        self.commission_system.as_mut()
+            .ok_or("Commission system not initialized")?
+            .commission_history
             .entry(recipient_wallet.to_string())
-            .or_insert_with(Vec::new)
-            .push(payment);
+            .or_default()
+            .push(payment.clone());
+        self.journal(GatewayEvent::CommissionPaid { payment });
 
-        println!("💰 Commission paid: {} USDC to {}", amount, &recipient_wallet[..8]);
+        println!("💰 Commission paid: {} {} to {}", exact, token, &recipient_wallet[..8]);
 
         Ok(())
     }
 
-    fn update_earnings_account(&mut self, wallet_address: &str, amount: f64,
-                              commission_type: CommissionType) -> Result<(), String> {
+    fn update_earnings_account(&mut self, wallet_address: &str, token: &str, amount: TokenAmount,
+                              commission_type: CommissionType, source: &str) -> Result<(), String> {
 
         let commission_system = self.commission_system.as_mut()
             .ok_or("Commission system not initialized")?;
 
-        let account = commission_system.earnings_ledger
-            .entry(wallet_address.to_string())
-            .or_insert_with(|| self.create_default_earnings_account(wallet_address));
-
-        // Update earnings
-        account.total_earned_usdc += amount;
-        account.lifetime_volume += amount;
-
-        // Update referral count and tier
-        if matches!(commission_type, CommissionType::ReferralBonus) {
-            account.referral_count += 1;
-            account.tier = self.calculate_earnings_tier(account.referral_count);
-        }
-
-        account.last_payout = chrono::Utc::now().timestamp() as u64;
+        // Balances only change through the event log; tiers follow the
+        // referral records, not payments (see `recalculate_tier`)
+        commission_system.record_event(wallet_address, CommissionEventKind::Earned {
+            amount_usdc: if token == "USDC" { amount.to_decimal() } else { 0.0 },
+            token: token.to_string(),
+            amount: Some(amount),
+            commission_type,
+            source_transaction: source.to_string(),
+        });
 
         Ok(())
     }
 
-    fn create_default_earnings_account(&self, wallet_address: &str) -> EarningsAccount {
-        EarningsAccount {
-            wallet_address: wallet_address.to_string(),
-            total_earned_usdc: 0.0,
-            total_earned_solfunmeme: 0.0,
-            pending_withdrawals: 0.0,
-            lifetime_volume: 0.0,
-            referral_count: 0,
-            tier: EarningsTier::Bronze,
-            last_payout: chrono::Utc::now().timestamp() as u64,
-        }
-    }
-
     fn calculate_earnings_tier(&self, referral_count: u32) -> EarningsTier {
         match referral_count {
             0..=10 => EarningsTier::Bronze,
@@ -309,17 +578,52 @@ impl PublicGateway {
         }
     }
 
-    pub fn get_earnings_dashboard(&self, wallet_address: &str) -> Result<String, String> {
+    /// A wallet's commission payments in the query's range and type, newest
+    /// first, one page at a time
+    pub fn commission_payments_page(&self, wallet_address: &str, query: &EarningsQuery) -> Result<PaymentPage, String> {
+        let commission_system = self.commission_system.as_ref()
+            .ok_or("Commission system not initialized")?;
+        let (from, to, page, page_size) = query.bounds()?;
+
+        let matching: Vec<&CommissionPayment> = commission_system.commission_history
+            .get(wallet_address)
+            .map(|payments| payments.iter().rev()
+                .filter(|payment| payment.timestamp >= from && payment.timestamp <= to)
+                .filter(|payment| query.commission_type.as_ref().is_none_or(|kind| payment.commission_type == *kind))
+                .collect())
+            .unwrap_or_default();
+        let mut totals_by_token = std::collections::BTreeMap::new();
+        for payment in &matching {
+            *totals_by_token.entry(payment.token.clone()).or_insert(0.0) += payment.amount;
+        }
+
+        let total_items = matching.len();
+        let total_pages = total_items.div_ceil(page_size);
+        Ok(PaymentPage {
+            payments: matching.into_iter().skip((page - 1) * page_size).take(page_size).cloned().collect(),
+            pagination: Pagination {
+                page,
+                page_size,
+                total_items,
+                total_pages,
+                has_previous: page > 1,
+                has_next: page < total_pages,
+            },
+            from: query.from,
+            to: query.to,
+            commission_type: query.commission_type.clone(),
+            totals_by_token,
+        })
+    }
+
+    pub fn get_earnings_dashboard(&self, wallet_address: &str, query: &EarningsQuery) -> Result<String, String> {
         let commission_system = self.commission_system.as_ref()
             .ok_or("Commission system not initialized")?;
 
         let account = commission_system.earnings_ledger.get(wallet_address)
             .ok_or("Earnings account not found")?;
 
-        let recent_payments = commission_system.commission_history
-            .get(wallet_address)
-            .map(|payments| payments.iter().rev().take(10).collect::<Vec<_>>())
-            .unwrap_or_default();
+        let payments = self.commission_payments_page(wallet_address, query)?;
 
         let referral_links = commission_system.referral_links.values()
             .filter(|link| link.referrer_wallet == wallet_address)
@@ -331,7 +635,10 @@ impl PublicGateway {
                 "total_earned_usdc": account.total_earned_usdc,
                 "total_earned_solfunmeme": account.total_earned_solfunmeme,
                 "pending_withdrawals": account.pending_withdrawals,
-                "lifetime_volume": account.lifetime_volume
+                "lifetime_volume": account.lifetime_volume,
+                "by_token": account.earned_by_token.iter()
+                    .map(|(token, amount)| (token.clone(), serde_json::json!(amount.to_string())))
+                    .collect::<serde_json::Map<_, _>>()
             },
             "referrals": {
                 "total_referrals": account.referral_count,
@@ -339,16 +646,34 @@ impl PublicGateway {
                 "tier_multiplier": commission_system.commission_rates.tier_multipliers
                     .get(&format!("{:?}", account.tier)).unwrap_or(&1.0)
             },
+            "tier_progress": self.tier_progress(wallet_address).ok(),
+            "recommendations": self.usage_recommendations(wallet_address),
             "referral_links": referral_links.iter().map(|link| serde_json::json!({
                 "link_id": link.link_id,
                 "service_endpoint": link.service_endpoint,
                 "clicks": link.click_count,
                 "conversions": link.conversion_count,
+                "promo_conversions": link.promo_conversions,
                 "conversion_rate": if link.click_count > 0 {
                     link.conversion_count as f64 / link.click_count as f64 * 100.0
                 } else { 0.0 }
             })).collect::<Vec<_>>(),
-            "recent_payments": recent_payments,
+            "payments": payments,
+            "short_links": commission_system.short_links.values()
+                .filter(|link| link.owner_wallet == wallet_address)
+                .map(|link| serde_json::json!({
+                    "code": link.code,
+                    "clicks": link.clicks,
+                    "daily_clicks": link.daily_clicks,
+                    "disabled": link.disabled,
+                    "expires_at": link.expires_at
+                })).collect::<Vec<_>>(),
+            "service_health": self.service_registry.values()
+                .filter(|service| service.wallet_address == wallet_address)
+                .map(|service| serde_json::json!({
+                    "service_name": service.service_name,
+                    "health": service.health
+                })).collect::<Vec<_>>(),
             "commission_rates": {
                 "swap_commission": commission_system.commission_rates.swap_commission_percentage,
                 "referral_commission": commission_system.commission_rates.referral_commission_percentage,
@@ -370,16 +695,58 @@ pub struct PublicGateway {
     pub libp2p_bridge: LibP2PBridge,
     pub rate_limiter: RateLimiter,
     pub commission_system: Option<CommissionSystem>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PublicGateway {
-    pub domain: String,
-    pub wallet_endpoints: HashMap<String, WalletEndpoint>,
-    pub service_registry: HashMap<String, ServiceEndpoint>,
-    pub payment_processor: PaymentProcessor,
-    pub libp2p_bridge: LibP2PBridge,
-    pub rate_limiter: RateLimiter,
+    #[serde(default)]
+    pub request_scheduler: RequestScheduler,
+    #[serde(default)]
+    pub accounting: AccountingLedger,
+    #[serde(default)]
+    pub cost_estimator: CostEstimator,
+    #[serde(default)]
+    pub cluster_limiter: ClusterRateLimiter,
+    #[serde(default)]
+    pub state_sync: StateSync, // service registry and referral links shared with trusted peers
+    #[serde(default)]
+    pub receipts: ReceiptLedger,
+    #[serde(default)]
+    pub session_routes: SessionRoutes,
+    #[serde(default)]
+    pub traces: SpanExporter,
+    #[serde(default)]
+    pub sandbox: Sandbox,
+    #[serde(default)]
+    pub sandbox_mode: bool, // this gateway is a sandbox: fake money, test ledger
+    #[serde(default)]
+    pub coupons: CouponBook,
+    #[serde(default)]
+    pub fee_routing: FeeRoutingLedger,
+    #[serde(default)]
+    pub compliance: WalletCompliance,
+    #[serde(default)]
+    pub nft_gating: NftGating,
+    #[serde(default)]
+    pub economy: EconomyStats, // aggregates for the public explorer
+    #[serde(default)]
+    pub edge_cache: EdgeCache,
+    #[serde(default)]
+    pub experiments: ExperimentRegistry,
+    #[serde(default)]
+    pub consumer_usage: ConsumerUsage, // per-wallet history behind recommendations
+    #[serde(default)]
+    pub usage_meter: UsageMeter, // hourly usage per wallet and service, for invoices
+    #[serde(skip, default = "ArchivalConfig::load")]
+    pub archival: ArchivalConfig,
+    #[serde(skip, default = "access_policy::load_operator")]
+    pub access_policies: zos_policy::PolicySet, // operator policies on service calls
+    #[serde(skip, default = "payout_approvals::load_policy")]
+    pub payout_approval_policy: zos_approvals::ApprovalPolicy,
+    #[serde(default)]
+    pub api_keys: ApiKeyStore, // credentials for managing endpoints and services
+    #[serde(default)]
+    pub certification_policy: CertificationPolicy,
+    #[serde(skip)]
+    pub metrics: GatewayMetrics, // Prometheus counters since start
+    #[serde(skip, default = "GatewayJournal::load")]
+    pub journal: GatewayJournal, // append-only record the commission state can be rebuilt from
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -401,16 +768,45 @@ pub struct ServiceEndpoint {
     pub http_path: String,
     pub pricing: PricingConfig,
     pub payment_required: bool,
-    pub cors_enabled: bool,
+    pub cors_enabled: bool, // off: no origin may call it from a browser
+    #[serde(default)]
+    pub cors: CorsPolicy, // which origins may, while on
     pub auth_required: bool,
+    #[serde(default)]
+    pub health_check: HealthCheck,
+    #[serde(default)]
+    pub health: ServiceHealth,
+    #[serde(default)]
+    pub mirror: Option<MirrorConfig>,
+    #[serde(default)]
+    pub body_mode: BodyMode,
+    #[serde(default)]
+    pub contract: Option<ServiceContract>,
+    #[serde(default)]
+    pub nft_gate: Vec<NftRequirement>, // callers must hold all of these
+    #[serde(default)]
+    pub edge_cache_secs: Option<u64>, // GET responses edges may serve; free, public services only
+    #[serde(default)]
+    pub certification: ServiceCertification,
+    #[serde(default)]
+    pub response_mode: ResponseMode,
+    #[serde(default)]
+    pub transforms: ServiceTransforms, // owner scripts rewriting requests and responses
+    #[serde(default)]
+    pub refund_policy: RefundPolicy, // how long payments to it can be refunded or charged back
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PaymentProcessor {
     pub supported_tokens: Vec<TokenConfig>,
     pub swap_pools: HashMap<String, SwapPool>,
-    pub payment_history: HashMap<String, Vec<PaymentRecord>>,
-    pub quote_cache: HashMap<String, QuoteCache>,
+    pub payment_history: HashMap<String, Vec<PaymentRecord>>, // payer -> payments
+    #[serde(default)]
+    pub payment_seq: u64,
+    #[serde(default)]
+    pub payment_archive: zos_archive::Archive, // segments holding payment_history past retention
+    #[serde(skip)]
+    pub quote_cache: QuoteCacheStore,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -453,9 +849,13 @@ pub struct SwapPool {
     pub pool_id: String,
     pub token_a: String,
     pub token_b: String,
-    pub liquidity: f64,
+    pub liquidity: f64, // reserve_a + reserve_b
     pub fee_percentage: f64,
-    pub price_impact: f64,
+    pub price_impact: f64, // % of the last swap
+    #[serde(default)]
+    pub reserve_a: f64,
+    #[serde(default)]
+    pub reserve_b: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -467,6 +867,8 @@ pub struct PaymentRecord {
     pub service_endpoint: String,
     pub timestamp: u64,
     pub status: PaymentStatus,
+    #[serde(default)]
+    pub refunds: Vec<Refund>, // Refunded once these add up to the amount
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -546,14 +948,6 @@ pub struct RateLimiter {
     pub current_usage: HashMap<String, UsageStats>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct UsageStats {
-    pub requests_this_minute: u32,
-    pub requests_this_hour: u32,
-    pub bandwidth_used_mb: f64,
-    pub last_reset: u64,
-}
-
 impl PublicGateway {
     pub fn new(domain: &str) -> Self {
         Self {
@@ -579,7 +973,9 @@ impl PublicGateway {
                 ],
                 swap_pools: HashMap::new(),
                 payment_history: HashMap::new(),
-                quote_cache: HashMap::new(),
+                payment_seq: 0,
+                payment_archive: zos_archive::Archive::default(),
+                quote_cache: QuoteCacheStore::default(),
             },
             libp2p_bridge: LibP2PBridge {
                 peer_connections: HashMap::new(),
@@ -590,6 +986,7 @@ impl PublicGateway {
                     connection_timeout: 30000,
                 },
             },
+            commission_system: None,
             rate_limiter: RateLimiter {
                 global_limits: RateLimit {
                     requests_per_minute: 1000,
@@ -599,12 +996,40 @@ impl PublicGateway {
                 per_wallet_limits: HashMap::new(),
                 current_usage: HashMap::new(),
             },
+            request_scheduler: RequestScheduler::default(),
+            accounting: AccountingLedger::default(),
+            cost_estimator: CostEstimator::default(),
+            cluster_limiter: ClusterRateLimiter::default(),
+            state_sync: StateSync::default(),
+            receipts: ReceiptLedger::default(),
+            session_routes: SessionRoutes::default(),
+            traces: SpanExporter::default(),
+            sandbox: Sandbox::default(),
+            sandbox_mode: false,
+            coupons: CouponBook::default(),
+            fee_routing: FeeRoutingLedger::default(),
+            compliance: WalletCompliance::default(),
+            nft_gating: NftGating::default(),
+            economy: EconomyStats::default(),
+            edge_cache: EdgeCache::default(),
+            experiments: ExperimentRegistry::default(),
+            consumer_usage: ConsumerUsage::default(),
+            usage_meter: UsageMeter::default(),
+            archival: ArchivalConfig::load(),
+            access_policies: access_policy::load_operator(),
+            payout_approval_policy: payout_approvals::load_policy(),
+            api_keys: ApiKeyStore::default(),
+            certification_policy: CertificationPolicy::default(),
+            metrics: GatewayMetrics::default(),
+            journal: GatewayJournal::load(),
         }
     }
 
     pub fn register_wallet_endpoint(&mut self, wallet_address: &str, user_id: &str,
                                   allocated_ports: Vec<u16>) -> Result<String, String> {
 
+        self.screen_wallet(wallet_address, ScreeningPurpose::EndpointRegistration)?;
+
         let endpoint = WalletEndpoint {
             wallet_address: wallet_address.to_string(),
             user_id: user_id.to_string(),
@@ -620,6 +1045,10 @@ impl PublicGateway {
         };
 
         self.wallet_endpoints.insert(wallet_address.to_string(), endpoint);
+        self.journal(GatewayEvent::WalletRegistered {
+            wallet_address: wallet_address.to_string(),
+            user_id: user_id.to_string(),
+        });
 
         let public_url = format!("https://{}/{}", self.domain, wallet_address);
 
@@ -685,13 +1114,25 @@ impl PublicGateway {
             pricing,
             payment_required: !matches!(pricing_tier, PricingTier::Free),
             cors_enabled: true,
+            cors: CorsPolicy::default(),
             auth_required: false,
+            health_check: HealthCheck::default(),
+            health: ServiceHealth::default(),
+            mirror: None,
+            body_mode: BodyMode::Buffered,
+            contract: None,
+            nft_gate: Vec::new(),
+            edge_cache_secs: None,
+            certification: ServiceCertification::default(),
+            response_mode: ResponseMode::Buffered,
+            transforms: ServiceTransforms::default(),
+            refund_policy: RefundPolicy::default(),
         };
 
         let service_config = ServiceConfig {
             service_name: service_name.to_string(),
             port: libp2p_port,
-            pricing_tier,
+            pricing_tier: pricing_tier.clone(),
             payment_required: service_endpoint.payment_required,
             description: format!("Service {} on port {}", service_name, libp2p_port),
             api_spec: None,
@@ -701,6 +1142,12 @@ impl PublicGateway {
 
         let service_key = format!("{}_{}", wallet_address, service_name);
         self.service_registry.insert(service_key, service_endpoint);
+        self.journal(GatewayEvent::ServiceRegistered {
+            wallet_address: wallet_address.to_string(),
+            service_name: service_name.to_string(),
+            libp2p_port,
+            pricing_tier,
+        });
 
         let service_url = format!("https://{}/{}/{}", self.domain, wallet_address, service_name);
 
@@ -708,9 +1155,220 @@ impl PublicGateway {
         Ok(service_url)
     }
 
+    /// Handle a call as one gateway span in the caller's trace (or a new
+    /// one); the response carries `traceparent` so clients can look it up
     pub fn handle_http_request(&mut self, path: &str, method: &str,
                               headers: &HashMap<String, String>,
                               body: &[u8]) -> Result<HttpResponse, String> {
+        // CORS preflights carry no credentials; answered from the path's policy alone
+        if method == "OPTIONS" {
+            return self.handle_preflight(path, headers);
+        }
+
+        // Integrators testing against fake money
+        if !self.sandbox_mode && sandbox::is_sandbox_request(headers) {
+            return self.handle_sandbox_request(path, method, headers, body);
+        }
+
+        let parent = TraceParent::from_headers(headers);
+        let trace = parent.as_ref().map(TraceParent::child).unwrap_or_else(TraceParent::root);
+        let span = OpenSpan::start(&trace, parent.as_ref(), &format!("{} {}", method, path), "gateway");
+
+        let result = self.route_http_request(path, method, headers, body, &trace);
+
+        let span = match &result {
+            Ok(response) => span.finish(Some(response.status_code), None),
+            Err(e) => span.finish(None, Some(e.clone())),
+        };
+        self.traces.record(span, trace.sampled);
+        self.record_service_request(path, result.as_ref().map_or(400, |response| response.status_code));
+        self.flush_journal();
+
+        result.map(|mut response| {
+            response.headers.insert("traceparent".to_string(), trace.header_value());
+            self.apply_cors(path, headers, &mut response);
+            response
+        })
+    }
+
+    fn route_http_request(&mut self, path: &str, method: &str,
+                          headers: &HashMap<String, String>,
+                          body: &[u8], trace: &TraceParent) -> Result<HttpResponse, String> {
+
+        if path == "/quote/stats" && method == "GET" {
+            return self.handle_quote_stats_request();
+        }
+
+        // Prometheus scrapes
+        if path == "/metrics" && method == "GET" {
+            return self.handle_metrics_request(headers);
+        }
+
+        if path == "/openapi.json" && method == "GET" {
+            let mut response = receipts::json_response(200, &openapi::generate_openapi(&self.domain))?;
+            response.headers.insert("Access-Control-Allow-Origin".to_string(), "*".to_string());
+            return Ok(response);
+        }
+
+        // Plan, bulk discount and tier suggestions from a consumer's usage
+        if let Some(wallet_address) = path.strip_prefix("/api/recommendations/") {
+            if method == "GET" {
+                return self.handle_recommendations_request(wallet_address, headers);
+            }
+        }
+
+        // Built-in and operator access policies, and dry runs against them
+        if path == "/api/policies" && method == "GET" {
+            return self.handle_policies_request();
+        }
+        if path == "/api/policies/evaluate" && method == "POST" {
+            return self.handle_policy_evaluation(body);
+        }
+
+        // Peer nodes syncing rate limit counters
+        if path == "/cluster/rate-limits" && method == "POST" {
+            return self.handle_rate_limit_gossip(headers, body);
+        }
+
+        // Trusted peer nodes syncing service registry and referral links
+        if path == "/cluster/state" && method == "POST" {
+            return self.handle_state_gossip(headers, body);
+        }
+
+        // Draining nodes announcing where their game sessions went
+        if path == "/cluster/game-sessions" && method == "POST" {
+            return self.handle_session_migration(headers, body);
+        }
+
+        // Game session handed off to another node: send the client after it
+        if let Some(redirect) = self.session_redirect(path, headers) {
+            return Ok(redirect);
+        }
+
+        // Receipt public key, lookup and verification
+        if path.starts_with("/receipts/") {
+            return self.handle_receipt_request(path, method, body);
+        }
+
+        // Operator-managed wallet blocklist
+        if path == "/compliance/blocklist" || path.starts_with("/compliance/blocklist/") {
+            return self.handle_compliance_request(path, method, headers, body);
+        }
+
+        // Earnings payouts, screened before they are queued
+        if method == "POST" && path.ends_with("/earnings/withdraw") {
+            let wallet_address = path.trim_start_matches('/').split('/').next().unwrap_or("");
+            return self.handle_withdrawal_request(wallet_address, headers, body);
+        }
+        if method == "GET" {
+            if let Some((wallet_address, id)) = wallet_route(path, "earnings/withdrawals") {
+                if !id.contains('/') {
+                    return self.handle_withdrawal_status(wallet_address, path);
+                }
+            }
+        }
+
+        // Metered usage and the monthly invoices priced from it
+        if method == "GET" {
+            if let Some((wallet_address, period)) = wallet_route(path, "invoices") {
+                if !period.is_empty() && !period.contains('/') {
+                    return self.handle_invoice_request(wallet_address, period, headers);
+                }
+            }
+            if let Some((wallet_address, "")) = wallet_route(path, "usage") {
+                return self.handle_usage_request(wallet_address, headers);
+            }
+        }
+
+        if method == "GET" && (path.ends_with("/earnings/events") || path.contains("/earnings/at/")) {
+            let wallet_address = path.trim_start_matches('/').split('/').next().unwrap_or("");
+            return self.handle_earnings_events_request(wallet_address, path);
+        }
+
+        // Wallet sign-in and API keys, required to change the service registry
+        if path.starts_with("/auth/") {
+            return self.handle_auth_request(path, method, headers, body);
+        }
+        if path.starts_with("/manage/") {
+            return self.handle_management_request(path, method, headers, body);
+        }
+
+        // Commission and payment records past retention, in cold storage
+        if path == "/archive" || path.starts_with("/archive/") {
+            return self.handle_archive_request(path, method, headers);
+        }
+
+        // Operator payout runs over the withdrawal queue
+        if path == "/payouts" || path.starts_with("/payouts/") {
+            return self.handle_payout_request(path, method, headers, body);
+        }
+
+        // Admin wallets co-signing payouts over the approval threshold
+        if path == "/approvals" || path.starts_with("/approvals/") {
+            return self.handle_approval_request(path, method, headers, body);
+        }
+
+        // Badges for services passing the certification checks
+        if path == "/certifications" || path.starts_with("/certifications/") {
+            return self.handle_certification_request(path, method);
+        }
+
+        // Fee burns and treasury transfers
+        if path == "/treasury" || path.starts_with("/treasury/") {
+            return self.handle_treasury_request(path, method);
+        }
+
+        // Tier recalculation and referral status, which tiers are derived from
+        if path.starts_with("/commission/tiers/") || path.starts_with("/commission/referrals/") {
+            return self.handle_tier_request(path, method, headers, body);
+        }
+
+        // Earnings event log: audit, replay, clawbacks
+        if path.starts_with("/commission/events/") || path == "/commission/clawbacks" {
+            return self.handle_commission_events_request(path, method, headers, body);
+        }
+
+        // Commission rates: schedule, history, audits
+        if path.starts_with("/commission/") {
+            return self.handle_commission_rates_request(path, method, headers, body);
+        }
+
+        // Append-only journal of gateway events: paging, audit, restore
+        if path == "/journal" || path.starts_with("/journal/") {
+            return self.handle_journal_request(path, method, headers);
+        }
+
+        // Payment records, and refunds and chargebacks of them
+        if path.starts_with("/payments/") {
+            return self.handle_payment_request(path, method, headers, body);
+        }
+
+        // Public, read-only economy explorer
+        if path == "/explorer" || path.starts_with("/explorer/") {
+            return self.handle_explorer_request(path, method);
+        }
+
+        // Cache-only edge nodes: signed manifest, joins, hit reports
+        if path.starts_with("/edge/") {
+            return self.handle_edge_request(path, method, headers, body);
+        }
+
+        // Promo codes: creation, lookup, owner analytics
+        if path == "/coupons" || path.starts_with("/coupons/") {
+            return self.handle_coupon_request(path, method, headers, body);
+        }
+
+        // A wallet's own data: the export the node archives, and erasure
+        if let Some((wallet_address, action @ ("export" | "erase"))) = path.trim_start_matches('/').split_once('/') {
+            return self.handle_wallet_data_request(wallet_address, action, method, headers, body);
+        }
+
+        // Older spelling of POST /manage/services
+        if let Some(wallet_address) = path.strip_suffix("/register").and_then(|rest| rest.strip_prefix('/')) {
+            if method == "POST" && !wallet_address.contains('/') {
+                return self.handle_wallet_register_request(wallet_address, headers, body);
+            }
+        }
 
         // Parse path: /{wallet}/{service} or /{wallet}/{service}/swap or /{wallet}/{service}/quote
         let path_parts: Vec<&str> = path.trim_start_matches('/').split('/').collect();
@@ -723,64 +1381,232 @@ impl PublicGateway {
         let service_name = path_parts[1];
         let action = path_parts.get(2).unwrap_or(&"");
 
+        // Owner-run A/B experiments and their results
+        if *action == "experiments" {
+            return self.handle_experiment_request(wallet_address, service_name, path, method, headers, body);
+        }
+
+        // Which browser origins may call the service
+        if *action == "cors" {
+            return self.handle_cors_request(wallet_address, service_name, method, headers, body);
+        }
+
+        // The owner's transform scripts: versions, uploads and activation
+        if *action == "transforms" {
+            return self.handle_transform_request(wallet_address, service_name, path, method, headers, body);
+        }
+
+        // How long the service's payments stay open to refunds and chargebacks
+        if *action == "refund-policy" {
+            return self.handle_refund_policy_request(wallet_address, service_name, method, headers, body);
+        }
+
+        // The owner's commission rates for this service
+        if *action == "commission-plan" {
+            return self.handle_commission_plan_request(wallet_address, service_name, path, method, headers, body);
+        }
+
         // Handle special endpoints
-        match action {
-            "swap" => return self.handle_swap_request(wallet_address, service_name, body),
+        match *action {
+            "swap" => return self.handle_swap_request(wallet_address, service_name, headers, body),
             "quote" => return self.handle_quote_request(wallet_address, service_name, body),
+            "estimate" => return self.handle_estimate_request(wallet_address, service_name, headers, body),
+            "contract" if method == "GET" => return self.handle_contract_request(wallet_address, service_name),
             _ => {}
         }
 
-        // Check rate limits
-        self.check_rate_limits(wallet_address)?;
+        // Bodies that break the owner's request schema never reach the
+        // service, and are neither charged nor count against an estimate
+        let service_key = format!("{}_{}", wallet_address, service_name);
+        if let Some(rejection) = self.check_request_contract(&service_key, body) {
+            return Ok(rejection);
+        }
+
+        // Rate limits, delisting, payment and estimate; shared with pass-through calls
+        let AuthorizedCall { caller, estimate } = match self.authorize_service_call(wallet_address, service_name, headers)? {
+            Ok(call) => call,
+            Err(response) => return Ok(response),
+        };
 
         // Find service
-        let service_key = format!("{}_{}", wallet_address, service_name);
         let service = self.service_registry.get(&service_key)
             .ok_or("Service not found")?;
+        let payment_required = service.payment_required;
 
-        // Check payment requirement
-        if service.payment_required {
-            let payment_header = headers.get("X-Payment-Token")
-                .ok_or("Payment required. Include X-Payment-Token header")?;
+        // A running experiment may send this caller to another backend or price
+        let assignment = self.assign_variant(&service_key, headers);
+        let mut service = service.clone();
+        if let Some(port) = assignment.as_ref().and_then(|a| a.libp2p_port) {
+            service.libp2p_port = port;
+        }
+        let pricing = assignment.as_ref()
+            .and_then(|a| a.pricing.clone())
+            .unwrap_or_else(|| service.pricing.clone());
+        let edge_cache_secs = service.edge_cache_secs
+            .filter(|_| method == "GET" && !service.payment_required && !service.auth_required);
+
+        // A fresh copy at an edge is served from there; the redirect is the
+        // hit the edge is paid for
+        if edge_cache_secs.is_some() {
+            if let Some(location) = self.route_to_edge(path) {
+                return Ok(HttpResponse {
+                    status_code: 307,
+                    headers: HashMap::from([("Location".to_string(), location)]),
+                    body: Vec::new(),
+                });
+            }
+        }
+
+        // The owner's request script rewrites what the service receives
+        let forwarded = match self.transform_request(&service_key, method, path, headers, body) {
+            Ok(forwarded) => forwarded,
+            Err(response) => return Ok(response),
+        };
+        let (forwarded_headers, forwarded_body) = match &forwarded {
+            Some(message) => (message.headers.clone(), message.body.as_slice()),
+            None => (transforms::forwarded_headers(headers), body),
+        };
 
-            self.verify_payment(payment_header, &service.pricing)?;
+        // Forward to libp2p service, as the next hop in the trace
+        let hop = trace.child();
+        let hop_span = OpenSpan::start(&hop, Some(trace), "libp2p.forward", "libp2p");
+        let started = std::time::Instant::now();
+        let response = self.forward_to_libp2p(&service, method, &forwarded_headers, forwarded_body, &hop);
+        let latency_ms = started.elapsed().as_millis() as u64;
+        let hop_span = match &response {
+            Ok(_) => hop_span.finish(None, None),
+            Err(e) => hop_span.finish(None, Some(e.clone())),
+        };
+        self.traces.record(hop_span, hop.sampled);
+        if let (Some(assignment), Err(_)) = (&assignment, &response) {
+            self.record_variant_call(assignment, latency_ms, false, false);
+        }
+        let response = response?;
+
+        // and its response script what the caller gets back
+        let (response, script_headers) = match self.transform_response(&service_key, method, path, &response) {
+            Ok(Some(message)) => (message.body, message.headers),
+            Ok(None) => (response, HashMap::new()),
+            Err(response) => return Ok(response),
+        };
+        self.monitor_response_contract(&service_key, &response);
+        if let Some(service) = self.service_registry.get_mut(&service_key) {
+            service.certification.record_latency(latency_ms);
         }
 
-        // Forward to libp2p service
-        let response = self.forward_to_libp2p(service, method, body)?;
+        // Free, public GETs can be answered by edges until the next change
+        if let Some(max_age_secs) = edge_cache_secs {
+            self.publish_cacheable(path, &response, "application/json", max_age_secs);
+        }
+
+        // Signed receipt for the charge, once the service has answered
+        let receipt = if payment_required {
+            let amount = receipts::call_charge(&pricing, body.len(), estimate.as_ref());
+            let (amount, redemption) = self.redeem_coupon(&service_key, caller.as_deref(), headers, amount)?;
+            let hash = receipts::request_hash(method, path, body);
+            let estimate_id = estimate.as_ref().map(|estimate| estimate.estimate_id.clone());
+            Some((self.issue_receipt(&service_key, headers, amount, hash, estimate_id)?, redemption))
+        } else {
+            None
+        };
+        let charged = receipt.as_ref().map(|(receipt, _)| receipt.amount_usdc);
+        self.record_call(&service_key, charged);
+        self.record_consumer_usage(&service_key, caller.as_deref(), body.len() + response.len(), charged);
+        self.meter_usage(&service_key, caller.as_deref(), body.len() + response.len(), latency_ms as f64 / 1000.0);
+        self.record_bandwidth(wallet_address, response.len());
+        if let Some(assignment) = &assignment {
+            self.record_variant_call(assignment, latency_ms, true, receipt.is_some());
+        }
+
+        // Shadow a share of traffic to staging, outside billing and the response
+        self.mirror_request(&service_key, method, headers, body);
+
+        let mut response_headers = HashMap::from([
+            ("Content-Type".to_string(), "application/json".to_string()),
+        ]);
+        response_headers.extend(script_headers);
+        if let Some(estimate) = estimate {
+            response_headers.insert("X-Estimate-Id".to_string(), estimate.estimate_id);
+            response_headers.insert("X-Charge-USDC".to_string(), format!("{:.6}", estimate.total_usdc));
+        }
+        if let Some(assignment) = assignment {
+            response_headers.insert("X-Experiment-Variant".to_string(), assignment.variant_name);
+        }
+        if let Some((receipt, redemption)) = receipt {
+            response_headers.insert("X-Receipt-Id".to_string(), receipt.receipt_id);
+            if let Some(redemption) = redemption {
+                response_headers.insert("X-Coupon-Code".to_string(), redemption.code);
+                response_headers.insert("X-Discount-USDC".to_string(), format!("{:.6}", redemption.discount_usdc));
+            }
+        }
 
         Ok(HttpResponse {
             status_code: 200,
-            headers: HashMap::from([
-                ("Content-Type".to_string(), "application/json".to_string()),
-                ("Access-Control-Allow-Origin".to_string(), "*".to_string()),
-            ]),
+            headers: response_headers,
             body: response,
         })
     }
 
+    /// Execute a swap against the shared pool reserves. Only an authenticated
+    /// wallet that has paid in the input moves them; `/quote` prices without.
     pub fn handle_swap_request(&mut self, wallet_address: &str, service_name: &str,
-                              body: &[u8]) -> Result<HttpResponse, String> {
+                              headers: &HashMap<String, String>, body: &[u8]) -> Result<HttpResponse, String> {
 
         let swap_request: SwapRequest = serde_json::from_slice(body)
             .map_err(|e| format!("Invalid swap request: {}", e))?;
 
-        // Find best swap pool
+        let swapper = match self.authenticate_wallet(headers) {
+            Ok(swapper) => swapper,
+            Err(e) => return receipts::json_response(401, &serde_json::json!({ "error": e })),
+        };
+        let pricing = self.service_registry.get(&format!("{}_{}", wallet_address, service_name))
+            .map(|service| service.pricing.clone())
+            .ok_or("Service not found")?;
+        let settled = match headers.get("X-Payment-Token") {
+            Some(payment_token) => self.verify_payment(payment_token, &pricing),
+            None => Err("Swaps settle the input first. Include X-Payment-Token header".to_string()),
+        };
+        self.metrics.record_payment_verification(settled.is_ok());
+        if let Err(e) = settled {
+            return receipts::json_response(402, &serde_json::json!({ "error": e }));
+        }
+
+        // Price against the deepest pool's reserves, within the caller's tolerance
         let pool = self.find_best_swap_pool(&swap_request.from_token, &swap_request.to_token)?;
+        let quote = pool.quote_swap(&swap_request.from_token, swap_request.amount)?;
+        amm::check_slippage(&quote, swap_request.slippage_tolerance)?;
+        let pool_id = pool.pool_id.clone();
 
-        // Calculate swap
-        let output_amount = self.calculate_swap_output(&pool, swap_request.amount)?;
+        if let Some(pool) = self.payment_processor.swap_pools.get_mut(&pool_id) {
+            pool.apply_swap(&swap_request.from_token, &quote);
+        }
 
-        // Execute swap (simplified)
         let swap_result = SwapResult {
             transaction_id: format!("tx_{}", chrono::Utc::now().timestamp()),
             input_amount: swap_request.amount,
-            output_amount,
-            price_impact: pool.price_impact,
-            fee: swap_request.amount * pool.fee_percentage / 100.0,
+            output_amount: quote.output_amount,
+            price_impact: quote.price_impact,
+            fee: quote.fee,
             status: "completed".to_string(),
         };
 
+        println!("🔄 Swap {}: {} {} → {} {} for {}", swap_result.transaction_id, swap_request.amount,
+                 swap_request.from_token, swap_result.output_amount, swap_request.to_token, swapper);
+
+        // Burn and treasury shares of the swap fee
+        self.route_fee(&swap_request.from_token, swap_result.fee, &swap_result.transaction_id);
+        self.record_swap(&swap_request.from_token, swap_request.amount);
+        self.journal(GatewayEvent::SwapExecuted {
+            wallet_address: wallet_address.to_string(),
+            service_name: service_name.to_string(),
+            transaction_id: swap_result.transaction_id.clone(),
+            from_token: swap_request.from_token.clone(),
+            to_token: swap_request.to_token.clone(),
+            input_amount: swap_result.input_amount,
+            output_amount: swap_result.output_amount,
+            fee: swap_result.fee,
+        });
+
         let response_body = serde_json::to_vec(&swap_result)
             .map_err(|e| format!("Failed to serialize response: {}", e))?;
 
@@ -793,7 +1619,7 @@ impl PublicGateway {
         })
     }
 
-    pub fn handle_quote_request(&mut self, wallet_address: &str, service_name: &str,
+    pub fn handle_quote_request(&mut self, wallet_address: &str, _service_name: &str,
                                body: &[u8]) -> Result<HttpResponse, String> {
 
         let quote_request: QuoteRequest = serde_json::from_slice(body)
@@ -804,37 +1630,36 @@ impl PublicGateway {
                                quote_request.from_token, quote_request.to_token,
                                quote_request.amount, wallet_address);
 
-        if let Some(cached_quote) = self.payment_processor.quote_cache.get(&cache_key) {
-            if cached_quote.expires_at > chrono::Utc::now().timestamp() as u64 {
-                let response_body = serde_json::to_vec(cached_quote)
-                    .map_err(|e| format!("Failed to serialize cached quote: {}", e))?;
+        let now = chrono::Utc::now().timestamp() as u64;
+        if let Some(cached_quote) = self.payment_processor.quote_cache.get(&cache_key, now) {
+            let response_body = serde_json::to_vec(&cached_quote)
+                .map_err(|e| format!("Failed to serialize cached quote: {}", e))?;
 
-                return Ok(HttpResponse {
-                    status_code: 200,
-                    headers: HashMap::from([
-                        ("Content-Type".to_string(), "application/json".to_string()),
-                        ("X-Cache".to_string(), "HIT".to_string()),
-                    ]),
-                    body: response_body,
-                });
-            }
+            return Ok(HttpResponse {
+                status_code: 200,
+                headers: HashMap::from([
+                    ("Content-Type".to_string(), "application/json".to_string()),
+                    ("X-Cache".to_string(), "HIT".to_string()),
+                ]),
+                body: response_body,
+            });
         }
 
         // Calculate fresh quote
         let pool = self.find_best_swap_pool(&quote_request.from_token, &quote_request.to_token)?;
-        let output_amount = self.calculate_swap_output(&pool, quote_request.amount)?;
+        let swap = pool.quote_swap(&quote_request.from_token, quote_request.amount)?;
 
         let quote = QuoteCache {
             from_token: quote_request.from_token.clone(),
             to_token: quote_request.to_token.clone(),
             amount: quote_request.amount,
-            quoted_price: output_amount,
-            expires_at: chrono::Utc::now().timestamp() as u64 + 30, // 30 second expiry
-            slippage: pool.price_impact,
+            quoted_price: swap.output_amount,
+            expires_at: now + self.payment_processor.quote_cache.config.ttl_secs,
+            slippage: swap.price_impact,
         };
 
         // Cache the quote
-        self.payment_processor.quote_cache.insert(cache_key, quote.clone());
+        self.payment_processor.quote_cache.insert(cache_key, quote.clone(), now);
 
         let response_body = serde_json::to_vec(&quote)
             .map_err(|e| format!("Failed to serialize quote: {}", e))?;
@@ -849,49 +1674,12 @@ impl PublicGateway {
         })
     }
 
-    fn check_rate_limits(&mut self, wallet_address: &str) -> Result<(), String> {
-        let current_time = chrono::Utc::now().timestamp() as u64;
-
-        let usage = self.rate_limiter.current_usage
-            .entry(wallet_address.to_string())
-            .or_insert(UsageStats {
-                requests_this_minute: 0,
-                requests_this_hour: 0,
-                bandwidth_used_mb: 0.0,
-                last_reset: current_time,
-            });
-
-        // Reset counters if needed
-        if current_time - usage.last_reset > 60 {
-            usage.requests_this_minute = 0;
-            usage.last_reset = current_time;
-        }
-
-        if current_time - usage.last_reset > 3600 {
-            usage.requests_this_hour = 0;
+    fn verify_payment(&self, payment_token: &str, _pricing: &PricingConfig) -> Result<(), String> {
+        // Sandbox payments are simulated; any token pays
+        if self.sandbox_mode {
+            return if payment_token.is_empty() { Err("Invalid payment token".to_string()) } else { Ok(()) };
         }
 
-        // Check limits
-        let limits = self.rate_limiter.per_wallet_limits
-            .get(wallet_address)
-            .unwrap_or(&self.rate_limiter.global_limits);
-
-        if usage.requests_this_minute >= limits.requests_per_minute {
-            return Err("Rate limit exceeded: too many requests per minute".to_string());
-        }
-
-        if usage.requests_this_hour >= limits.requests_per_hour {
-            return Err("Rate limit exceeded: too many requests per hour".to_string());
-        }
-
-        // Increment counters
-        usage.requests_this_minute += 1;
-        usage.requests_this_hour += 1;
-
-        Ok(())
-    }
-
-    fn verify_payment(&self, payment_token: &str, pricing: &PricingConfig) -> Result<(), String> {
         // Simplified payment verification
         // In real implementation, would verify blockchain transaction
         if payment_token.starts_with("pay_") && payment_token.len() > 10 {
@@ -901,13 +1689,17 @@ impl PublicGateway {
         }
     }
 
-    fn forward_to_libp2p(&self, service: &ServiceEndpoint, method: &str, body: &[u8]) -> Result<Vec<u8>, String> {
+    fn forward_to_libp2p(&self, service: &ServiceEndpoint, method: &str, headers: &HashMap<String, String>,
+                         _body: &[u8], trace: &TraceParent) -> Result<Vec<u8>, String> {
         // Simplified libp2p forwarding
-        // In real implementation, would use libp2p client to forward request
+        // In real implementation, would use libp2p client to forward request,
+        // with `traceparent` in the envelope for the node to continue the trace
         let response = serde_json::json!({
             "service": service.service_name,
             "port": service.libp2p_port,
             "method": method,
+            "headers": headers,
+            "traceparent": trace.header_value(),
             "response": "Service response from libp2p",
             "timestamp": chrono::Utc::now().to_rfc3339()
         });
@@ -917,24 +1709,16 @@ impl PublicGateway {
     }
 
     fn find_best_swap_pool(&self, from_token: &str, to_token: &str) -> Result<&SwapPool, String> {
-        // Find pool with best liquidity and lowest fees
+        // Deepest pool for the pair: the least price impact
         self.payment_processor.swap_pools
             .values()
-            .find(|pool| {
+            .filter(|pool| {
                 (pool.token_a == from_token && pool.token_b == to_token) ||
                 (pool.token_a == to_token && pool.token_b == from_token)
             })
+            .max_by(|a, b| a.liquidity.total_cmp(&b.liquidity))
             .ok_or("No swap pool found for token pair".to_string())
     }
-
-    fn calculate_swap_output(&self, pool: &SwapPool, input_amount: f64) -> Result<f64, String> {
-        // Simplified AMM calculation
-        let fee = input_amount * pool.fee_percentage / 100.0;
-        let amount_after_fee = input_amount - fee;
-        let output = amount_after_fee * 0.98; // 2% slippage
-
-        Ok(output)
-    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -969,6 +1753,20 @@ pub struct QuoteRequest {
     pub amount: f64,
 }
 
+/// The wallet and what follows `route` in `/{wallet}/{route}[/{rest}]`.
+/// Matched segment by segment, so a service's own paths that merely
+/// contain the route still reach the service.
+fn wallet_route<'a>(path: &'a str, route: &str) -> Option<(&'a str, &'a str)> {
+    let (wallet_address, tail) = path.strip_prefix('/')?.split_once('/')?;
+    if wallet_address.is_empty() {
+        return None;
+    }
+    match tail.trim_end_matches('/').strip_prefix(route)? {
+        "" => Some((wallet_address, "")),
+        rest => rest.strip_prefix('/').map(|rest| (wallet_address, rest)),
+    }
+}
+
 // Example usage and routing
 pub fn create_gateway_routes() -> String {
     r#"
@@ -976,32 +1774,250 @@ pub fn create_gateway_routes() -> String {
 
 Base URL: https://node1.solfunmeme.com
 
+API Description:
+  GET  /openapi.json                → OpenAPI 3.0 document for every route below, to generate clients from
+
 Wallet Endpoints:
   GET  /{wallet}                    → Wallet info and services
-  POST /{wallet}/register           → Register new service
+  POST /{wallet}/register           → Register new service ({"service_name", "libp2p_port", "pricing_tier"};
+                                      credentials as for POST /manage/services)
+  GET  /{wallet}/export             → Payments, earnings, referrals, withdrawals, usage and API keys held
+                                      for the wallet (the wallet's credentials, or X-Operator-Key)
+  POST /{wallet}/erase              → Replace the wallet with {"pseudonym"} across the ledger and drop its
+                                      endpoints, services and keys (as for export)
+
+Service Management (as the wallet: `Authorization: Bearer zos_...`, or X-Wallet-Address, X-Auth-Challenge and
+X-Wallet-Signature over a challenge's message; X-Operator-Key for any wallet):
+  POST   /auth/challenge            → One-time message for {"wallet_address"} to sign (valid 5 minutes)
+  POST   /auth/keys                 → Issue an API key ({"label"}); the key is shown only in this response
+  GET    /auth/keys                 → The wallet's keys: labels, last use, revoked and rotated
+  POST   /auth/keys/{id}/rotate     → New key under the same label; the old one stops working at once
+  DELETE /auth/keys/{id}            → Revoke a key
+  POST   /manage/endpoints          → Register {"wallet_address", "user_id", "allocated_ports"}
+  POST   /manage/services           → Add {"wallet_address", "service_name", "libp2p_port", "pricing_tier"}
 
 Service Endpoints:
   GET    /{wallet}/{service}        → Call service (GET)
   POST   /{wallet}/{service}        → Call service (POST)
   PUT    /{wallet}/{service}        → Call service (PUT)
   DELETE /{wallet}/{service}        → Call service (DELETE)
+  *      /{wallet}/{service}/...    → Pass-through services: bodies streamed to the backend unparsed
+
+Certification Endpoints (re-evaluated hourly; marketplace listings carry the badge):
+  GET  /certifications              → Certified services and the policy: listed, uptime SLA over 30 days, request
+                                      and response schemas, security headers on health checks, p95 budget
+  GET  /certifications/{wallet}/{service} → Each check's result, uptime by day, latency and the badge
+                                      (lost on the first failing evaluation, or at once when delisted)
 
 Payment Endpoints:
-  POST /{wallet}/{service}/swap     → Swap tokens
-  GET  /{wallet}/{service}/quote    → Get swap quote
+  POST /{wallet}/{service}/swap     → Swap tokens against the deepest pool's reserves (x*y=k); refused when
+                                      the price impact exceeds slippage_tolerance (%). Needs an API key or
+                                      wallet signature and X-Payment-Token for the input
+  GET  /{wallet}/{service}/quote    → Get swap quote (cached for ZOS_QUOTE_TTL_SECS, at most ZOS_QUOTE_CACHE_MAX)
+  GET  /quote/stats                 → Quote cache size, hit rate, expired/swept/evicted counts
   POST /{wallet}/{service}/pay      → Process payment
+  POST /{wallet}/{service}/estimate → Signed price for declared payload_mb/duration_secs/requests (tier discount needs an API key or wallet signature)
+  GET  /{wallet}/{service}/contract → Request/response JSON Schemas and violation rates
+                                      (bodies breaking the request schema get 422 with JSON pointers)
+
+Experiment Endpoints (owner or operator, authenticated as under Service Management):
+  GET  /{wallet}/{service}/experiments           → Experiments with results per variant and cohort
+  POST /{wallet}/{service}/experiments           → Start one ({"name", "variants": [{"name", "libp2p_port",
+                                                   "pricing"}], "splits": {"*": [50, 50], "gold": [...]}});
+                                                   callers are bucketed by wallet, answers carry
+                                                   X-Experiment-Variant
+  GET  /{wallet}/{service}/experiments/{id}      → Calls, conversion and error rates, latency per variant
+  POST /{wallet}/{service}/experiments/{id}/stop → Stop routing to variants; results are kept
+
+Commission Plan Endpoints (owner or operator, authenticated as under Service Management):
+  GET    /{wallet}/{service}/commission-plan        → Rates in force for the service, scheduled plans, history
+  POST   /{wallet}/{service}/commission-plan        → Register rates ({"rates", "effective_at"}); checked against
+                                                      the global caps, never effective in the past
+  DELETE /{wallet}/{service}/commission-plan/{id}   → Cancel a plan that isn't in force yet
+
+CORS (preflight OPTIONS requests are answered from the policy, on every route; the gateway's own
+routes allow any origin without credentials):
+  GET  /{wallet}/{service}/cors     → {"enabled", "policy"}: allowed origins, methods and headers, exposed
+                                      headers, max_age_secs, allow_credentials
+  PUT  /{wallet}/{service}/cors     → Set them, as the owner like Service Management; "enabled": false refuses
+                                      every origin
+
+Transform Endpoints (as the owner, like Service Management; scripts get at most 20000 steps and 2 MiB per call):
+  GET  /{wallet}/{service}/transforms          → Versions, the active one, runs, failures and limits
+  POST /{wallet}/{service}/transforms          → Upload {"request_script", "response_script", "samples"}; stored
+                                                 as a new version only once every sample gives its expected output
+  POST /{wallet}/{service}/transforms/{v}/activate → Rewrite calls with version v (a failing script answers 502)
+  POST /{wallet}/{service}/transforms/deactivate   → Stop rewriting; versions are kept
+
+Access Policies (service calls are evaluated as action "service:call"):
+  GET  /api/policies                → Built-in policies and the operator's, from ZOS_GATEWAY_POLICY_FILE
+  POST /api/policies/evaluate       → Decision for {"action", "principal", "resource", "context"} without
+                                      making the call: allowed, deciding policies and reason
+
+Cold Storage (records older than ZOS_ARCHIVE_RETENTION_DAYS, in hash-linked gzip segments at
+ZOS_ARCHIVE_LOCATION, a directory or s3://bucket/prefix):
+  GET  /archive                          → Segments, records and head hash per stream
+  GET  /archive/verify                   → Check every segment against its hash and its link to the one before
+  GET  /archive/commissions/{wallet}     → Archived commission payments (reads segments: slower than the hot store;
+                                           the wallet's API key or signature, or X-Operator-Key)
+  GET  /archive/payments/{wallet}        → Archived payment records (same)
+  POST /archive/run                      → Archive now instead of on the daily timer (X-Operator-Key)
+
+Short Links:
+  GET  /r/{code}                    → Redirect to referral URL (410 if expired/disabled)
+  GET  /r/{code}/qr.png             → QR code (PNG)
+  GET  /r/{code}/qr.svg             → QR code (SVG)
+
+Accounting Endpoints:
+  GET  /{wallet}/earnings             → Earnings dashboard: balances (exact, by token), tier, referral links, recent payments,
+                                        recommendations
+  GET  /api/recommendations/{wallet}  → Last 30 days of calls, bandwidth and spend per service, with suggested
+                                        cheaper plans, bulk discounts and tier upgrades (monthly savings, largest first;
+                                        the wallet's API key or signature, or X-Operator-Key)
+  GET  /{wallet}/usage                → Calls, bytes and compute seconds per service: last 48 hourly buckets
+                                        and month to date (the wallet's API key or signed challenge, or X-Operator-Key;
+                                        likewise for invoices)
+  GET  /{wallet}/invoices/{YYYY-MM}   → Monthly invoice from metered usage at each service's pricing: base price,
+                                        per request/MB/second, less the bulk discount the month's calls reach
+  GET  /{wallet}/earnings/tax.csv     → Commission payments with USD value (?from=&to=)
+  GET  /{wallet}/earnings/summary.csv → Closed-period totals by commission type
+  POST /{wallet}/earnings/withdraw    → Queue a withdrawal ({"amount_usdc": ...}) as the wallet (API key or
+                                        signed challenge); the wallet is screened first and the amount
+                                        must meet its tier's minimum
+  GET  /{wallet}/earnings/withdrawals → Balance, tier payout schedule, next payout, withdrawals, payouts
+  GET  /{wallet}/earnings/withdrawals/{id} → One withdrawal and its status
+  GET  /{wallet}/earnings/events      → Append-only earnings events (earned, referrals counted, tier changed,
+                                        withdrawals, paid, clawed back) the account is folded from
+  GET  /{wallet}/earnings/at/{unix}   → The account replayed up to a moment
+
+Earnings Event Endpoints (need X-Operator-Key):
+  GET  /commission/events/audit      → Wallets whose stored account differs from their event fold
+  POST /commission/events/replay     → Rebuild every account from its events; returns what changed
+  POST /commission/clawbacks         → Reverse a commission payment ({"payment_id", "reason"})
+  POST /commission/tiers/recalculate → Re-derive every tier from distinct active referrals (also runs
+                                       hourly); returns promotions and demotions
+  PUT  /commission/referrals/{referrer}/{referee} → Set a referral's status ({"status": "Active" |
+                                       "Inactive" | "Suspended" | "Graduated"}); the referrer's tier follows
+
+Journal Endpoints (need X-Operator-Key; ZOS_GATEWAY_JOURNAL names the JSON lines file it is appended to):
+  GET  /journal                      → Latest 100 entries: wallets and services registered, payments verified,
+                                       recorded and refunded, swaps, referrals, commissions paid, earnings events
+  GET  /journal/after/{seq}          → Up to 500 entries after seq, to read the whole journal in order
+  GET  /journal/audit                → Where the stored commission state and a replay of the journal disagree
+  POST /journal/restore              → Replace earnings, commission payments, referrals and tier history with
+                                       the replay, for disaster recovery; returns what changed
+
+Payout Endpoints (need X-Operator-Key):
+  GET  /payouts                      → Payouts sent to the host but not confirmed, payouts awaiting approval, and tier schedules
+  POST /payouts/batch                → Batch due withdrawals into one payout per wallet
+  POST /payouts/{id}/complete        → Record a sent payout ({"transaction": ...}) in the ledger
+  POST /payouts/{id}/fail            → Fail a payout ({"error": ...}); funds return to the balance
+
+Refund Endpoints (as the payer, the service's owner or an operator, like Service Management; within the
+service's dispute window, and each commission the payment paid is clawed back by the refunded share):
+  GET  /payments/{id}                → Payment, its refunds and the amount refunded so far
+  POST /payments/{id}/refund         → Refund ({"reason", "amount"}; all that is left when amount is omitted), as
+                                       the service's owner or an operator; refunded in full, it is Refunded
+  POST /payments/{id}/chargeback     → Record a chargeback the same way (X-Operator-Key)
+  GET  /{wallet}/{service}/refund-policy → {"dispute_window_secs"} (30 days unless set)
+  PUT  /{wallet}/{service}/refund-policy → Set it, as the owner; at most 180 days
+
+Payout Approvals (payouts over ZOS_PAYOUT_APPROVAL_THRESHOLD_USDC wait for ZOS_PAYOUT_APPROVALS_REQUIRED
+of the ZOS_PAYOUT_APPROVERS wallets; unsigned requests expire after ZOS_PAYOUT_APPROVAL_EXPIRY_SECS):
+  GET  /approvals                    → Approval policy and pending requests (X-Operator-Key)
+  GET  /approvals/{id}               → Request, the messages to sign, and its audit trail (X-Operator-Key)
+  POST /approvals/{id}/approve       → Co-sign ({"approver", "signature"}: base58 ed25519 over the approve message)
+  POST /approvals/{id}/decline       → Decline ({"approver", "signature", "reason"}); rejected once approval is
+                                       out of reach, and the payout fails back to the balance
+
+Coupon Endpoints:
+  POST   /coupons                   → Create a promo code (owner: API key or signed challenge, own services only;
+                                      platform: X-Platform-Key). Body: code, discount {"Percentage": 20}
+                                      or {"FixedUsdc": 0.5}, max_redemptions, max_per_wallet,
+                                      expires_at, eligible_services, referral_link_id
+  GET    /coupons/{code}            → Discount, eligible services and whether it can still be used
+  GET    /coupons/{code}/stats      → Redemptions, unique wallets, discount given, promo conversions
+  DELETE /coupons/{code}            → Withdraw a coupon
+
+Compliance Endpoints (changes need X-Operator-Key):
+  GET    /compliance/blocklist          → Blocklisted wallets and the screening provider in use
+  GET    /compliance/blocklist/{wallet} → One entry
+  POST   /compliance/blocklist          → Block a wallet ({"wallet_address", "reason"})
+  DELETE /compliance/blocklist/{wallet} → Unblock; cached screening verdicts are dropped
+                                          (registration and withdrawals are screened: blocklist,
+                                          then the WalletScreening provider, cached per wallet)
+
+Commission Rate Endpoints (changes need X-Governance-Key and an approved proposal_id):
+  GET    /commission/rates              → Rates in force, caps and changes scheduled ahead
+  GET    /commission/rates/history      → Every rate change, including cancelled ones
+  GET    /commission/rates/at/{unix}    → Rates in force at a moment, past or future
+  POST   /commission/rates              → Schedule new rates ({"rates", "effective_at", "proposal_id"});
+                                          each rate is capped, swap + referral (at the top tier
+                                          multiplier) + burn/treasury shares must fit in one fee,
+                                          and changes need min_notice_secs notice
+  DELETE /commission/rates/{change_id}  → Cancel a change that isn't in force yet
+  GET    /commission/audit/{payment_id} → Check a payment against the rates in force when it was made
+                                          (the recipient's API key or signed challenge, or X-Governance-Key)
+
+Treasury Endpoints:
+  GET  /treasury                    → Fee routing (burn_percentage of burn_token fees, treasury_percentage
+                                      of all fees to the community treasury) and routed totals
+  GET  /treasury/burns              → Burn history, newest first
+                                      (balances and spending proposals live in the community ledger)
+
+Monitoring Endpoints (open, or Authorization: Bearer ZOS_METRICS_TOKEN when set):
+  GET  /metrics                     → Prometheus counters: requests per service and status, payment
+                                      verifications, swap volume, commission totals, rate limit
+                                      rejections, quote cache hits and size
+
+Explorer Endpoints (public, read-only, aggregates only: no wallets):
+  GET  /explorer                    → HTML overview of the tables below
+  GET  /explorer/stats              → Service counts, calls and volume, commission totals by type and
+                                      token, token circulation (burned, treasury, swapped, pooled)
+  GET  /explorer/volume             → Calls, paid calls, USDC volume and swaps per UTC day
+  GET  /explorer/services           → Top services by calls, by name (quiet services not listed)
+
+Receipt Endpoints:
+  GET  /receipts/public-key         → Gateway ed25519 key that signs usage receipts
+  GET  /receipts/{receipt_id}       → Fetch a recent receipt (X-Receipt-Id on paid responses)
+  POST /receipts/verify             → Check a stored receipt against this gateway's key
+
+Sandbox (X-ZOS-Sandbox: true, or the sandbox port):
+  *    any route above              → Same services; simulated payments, fake swap pools,
+                                      test commission ledger, receipts signed with the sandbox key
+  GET  /sandbox/status              → Last and next wipe (state is wiped daily)
+
+Cluster Endpoints:
+  POST /cluster/rate-limits         → Exchange per-wallet rate limit counters with a trusted peer
+                                      (X-Operator-Key)
+  POST /cluster/state               → Exchange service registry and referral link updates with a
+                                      trusted peer (X-Operator-Key; last writer wins)
+  POST /cluster/game-sessions       → Draining node announces where its game sessions moved
+                                      (trusted peer, X-Operator-Key)
 
 Headers:
   X-Payment-Token: pay_abc123...    → Payment authorization
-  X-Wallet-Address: 0x123...        → Caller wallet
+  X-Wallet-Address: 0x123...        → Caller wallet; with X-Auth-Challenge and X-Wallet-Signature (or a bearer
+                                      API key instead) it is proven, and NFT-gated services check its holdings
+  X-Estimate-Id: est_...            → Charge the quoted estimate (single use, until valid_until)
+  X-Coupon-Code: LAUNCH20           → Discount paid calls (needs an API key or signed challenge); invalid codes
+                                      refuse the call before it is forwarded
+  X-Game-Session: session_...       → Game session; 307 to its new node after a migration
+  X-ZOS-Sandbox: true               → Route the call to the sandbox
+  traceparent: 00-{trace}-{span}-01 → W3C trace context, continued through libp2p to the node
+                                      and backend; echoed on responses (view: node /api/v1/traces/{trace})
   Content-Type: application/json    → Request format
 
 HTTP Status Codes:
   200 OK                           → Success
+  307 Temporary Redirect           → Game session moved to another node (see Location)
   402 Payment Required             → Need payment
-  429 Too Many Requests            → Rate limited
+  403 Forbidden                    → Caller wallet lacks the NFTs the service is gated on
+  429 Too Many Requests            → Over requests per minute/hour or bandwidth (see Retry-After)
   404 Not Found                    → Service not found
   500 Internal Server Error        → Server error
+  503 Service Unavailable          → Delisted, service queue full (see Retry-After), or NFT
+                                     holdings could not be verified
 
 Example Requests:
 
@@ -1016,6 +2032,61 @@ curl https://node1.solfunmeme.com/0xABC.../ai-service \
 
 # Swap tokens
 curl https://node1.solfunmeme.com/0xABC.../ai-service/swap \
+  -H "Authorization: Bearer zos_..." \
+  -H "X-Payment-Token: pay_xyz789..." \
   -d '{"from_token":"SOLFUNMEME","to_token":"USDC","amount":100,"slippage_tolerance":0.5}'
 "#.to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wallet_routes_match_whole_segments() {
+        assert_eq!(wallet_route("/w1/earnings/withdrawals", "earnings/withdrawals"), Some(("w1", "")));
+        assert_eq!(wallet_route("/w1/earnings/withdrawals/", "earnings/withdrawals"), Some(("w1", "")));
+        assert_eq!(wallet_route("/w1/earnings/withdrawals/wd_1", "earnings/withdrawals"), Some(("w1", "wd_1")));
+
+        // A service's own paths are not the gateway's
+        assert_eq!(wallet_route("/w1/api/earnings/withdrawals", "earnings/withdrawals"), None);
+        assert_eq!(wallet_route("/w1/earnings/withdrawals-export", "earnings/withdrawals"), None);
+        assert_eq!(wallet_route("//earnings/withdrawals", "earnings/withdrawals"), None);
+    }
+
+    #[test]
+    fn test_swaps_need_an_authenticated_settled_caller() {
+        let mut gateway = PublicGateway::new("gateway.test");
+        gateway.register_wallet_endpoint("owner1", "owner", vec![4001]).unwrap();
+        gateway.add_service("owner1", "api", 4001, PricingTier::Basic).unwrap();
+        gateway.payment_processor.swap_pools.insert("pool1".to_string(), SwapPool {
+            pool_id: "pool1".to_string(),
+            token_a: "SOLFUNMEME".to_string(),
+            token_b: "USDC".to_string(),
+            liquidity: 2_000.0,
+            fee_percentage: 0.3,
+            price_impact: 0.0,
+            reserve_a: 1_000.0,
+            reserve_b: 1_000.0,
+        });
+        let body = br#"{"from_token":"SOLFUNMEME","to_token":"USDC","amount":10,"slippage_tolerance":5}"#;
+        let reserves = |gateway: &PublicGateway| {
+            let pool = &gateway.payment_processor.swap_pools["pool1"];
+            (pool.reserve_a, pool.reserve_b)
+        };
+
+        let spoofed = HashMap::from([("X-Wallet-Address".to_string(), "trader".to_string()),
+                                     ("X-Payment-Token".to_string(), "pay_0123456789".to_string())]);
+        assert_eq!(gateway.handle_swap_request("owner1", "api", &spoofed, body).unwrap().status_code, 401);
+
+        let (_, key) = gateway.api_keys.issue("trader", "test", chrono::Utc::now().timestamp() as u64);
+        let mut headers = HashMap::from([("Authorization".to_string(), format!("Bearer {}", key))]);
+        assert_eq!(gateway.handle_swap_request("owner1", "api", &headers, body).unwrap().status_code, 402);
+        assert_eq!(reserves(&gateway), (1_000.0, 1_000.0));
+
+        headers.insert("X-Payment-Token".to_string(), "pay_0123456789".to_string());
+        assert_eq!(gateway.handle_swap_request("owner1", "api", &headers, body).unwrap().status_code, 200);
+        let (reserve_a, reserve_b) = reserves(&gateway);
+        assert!(reserve_a > 1_000.0 && reserve_b < 1_000.0);
+    }
+}
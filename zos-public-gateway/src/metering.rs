@@ -0,0 +1,318 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use chrono::{Datelike, NaiveDate, TimeZone, Utc};
+use crate::estimate::bulk_discount;
+use crate::receipts::json_response;
+use crate::screening::is_operator;
+use crate::{HttpResponse, PricingConfig, PublicGateway};
+use std::collections::{BTreeMap, HashMap};
+
+const HOUR_SECS: u64 = 3600;
+
+/// One hour of one consumer's calls to one service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageBucket {
+    pub hour: u64, // unix time the hour starts
+    pub service_key: String,
+    pub requests: u64,
+    pub bytes: u64,
+    pub compute_secs: f64, // time the backend spent answering
+}
+
+/// Metered usage per wallet per service in hourly buckets, the basis for
+/// monthly invoices
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageMeter {
+    pub retention_days: u64, // long enough to reissue last year's invoices
+    pub wallets: HashMap<String, Vec<UsageBucket>>,
+}
+
+impl Default for UsageMeter {
+    fn default() -> Self {
+        Self {
+            retention_days: 400,
+            wallets: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UsageTotals {
+    pub requests: u64,
+    pub bytes: u64,
+    pub compute_secs: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InvoiceLine {
+    pub service_key: String,
+    pub requests: u64,
+    pub bandwidth_mb: f64,
+    pub compute_secs: f64,
+    pub subtotal_usdc: f64,
+    pub bulk_discount_percentage: f64,
+    pub discount_usdc: f64,
+    pub total_usdc: f64,
+    pub priced: bool, // false when the service is gone and its pricing with it
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Invoice {
+    pub invoice_id: String,
+    pub wallet_address: String,
+    pub period: String, // "YYYY-MM"
+    pub period_start: u64,
+    pub period_end: u64,
+    pub lines: Vec<InvoiceLine>,
+    pub subtotal_usdc: f64,
+    pub discount_usdc: f64,
+    pub total_usdc: f64,
+    pub closed: bool, // the month is over, so the invoice won't change
+    pub generated_at: u64,
+}
+
+fn round_usdc(amount: f64) -> f64 {
+    (amount * 1_000_000.0).round() / 1_000_000.0 // USDC has 6 decimals
+}
+
+/// Start and end (exclusive) of a "YYYY-MM" month, in unix seconds
+pub fn month_bounds(period: &str) -> Result<(u64, u64), String> {
+    let start = NaiveDate::parse_from_str(&format!("{}-01", period), "%Y-%m-%d")
+        .map_err(|_| format!("Invalid billing period {}; expected YYYY-MM", period))?;
+    let end = if start.month() == 12 {
+        NaiveDate::from_ymd_opt(start.year() + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(start.year(), start.month() + 1, 1)
+    }.ok_or("Billing period out of range")?;
+    let timestamp = |date: NaiveDate| Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap_or_default()).timestamp().max(0) as u64;
+    Ok((timestamp(start), timestamp(end)))
+}
+
+/// Usage priced at list: the base price once for the month, then per
+/// request, MB and compute second; the bulk discount the month's request
+/// count reaches comes off the whole line
+pub fn price_line(service_key: &str, usage: &UsageTotals, pricing: Option<&PricingConfig>) -> InvoiceLine {
+    let bandwidth_mb = usage.bytes as f64 / (1024.0 * 1024.0);
+    let (subtotal, discount_percentage) = match pricing {
+        Some(pricing) => {
+            let subtotal = pricing.base_price_usdc
+                + pricing.per_request_price * usage.requests as f64
+                + pricing.per_mb_price * bandwidth_mb
+                + pricing.per_second_price * usage.compute_secs;
+            (subtotal, bulk_discount(pricing, usage.requests.min(u32::MAX as u64) as u32))
+        }
+        None => (0.0, 0.0),
+    };
+    let discount = subtotal * discount_percentage / 100.0;
+    InvoiceLine {
+        service_key: service_key.to_string(),
+        requests: usage.requests,
+        bandwidth_mb,
+        compute_secs: usage.compute_secs,
+        subtotal_usdc: round_usdc(subtotal),
+        bulk_discount_percentage: discount_percentage,
+        discount_usdc: round_usdc(discount),
+        total_usdc: round_usdc(subtotal - discount),
+        priced: pricing.is_some(),
+    }
+}
+
+impl UsageMeter {
+    pub fn record(&mut self, wallet_address: &str, service_key: &str, bytes: usize, compute_secs: f64, now: u64) {
+        let hour = now - now % HOUR_SECS;
+        let buckets = self.wallets.entry(wallet_address.to_string()).or_default();
+        if !buckets.iter().any(|bucket| bucket.hour == hour) {
+            let cutoff = now.saturating_sub(self.retention_days * 86_400);
+            buckets.retain(|bucket| bucket.hour >= cutoff);
+        }
+
+        let index = match buckets.iter().rposition(|bucket| bucket.hour == hour && bucket.service_key == service_key) {
+            Some(index) => index,
+            None => {
+                buckets.push(UsageBucket {
+                    hour,
+                    service_key: service_key.to_string(),
+                    requests: 0,
+                    bytes: 0,
+                    compute_secs: 0.0,
+                });
+                buckets.len() - 1
+            }
+        };
+        let bucket = &mut buckets[index];
+        bucket.requests += 1;
+        bucket.bytes += bytes as u64;
+        bucket.compute_secs += compute_secs;
+    }
+
+    /// Buckets starting in [from, to)
+    pub fn buckets(&self, wallet_address: &str, from: u64, to: u64) -> Vec<&UsageBucket> {
+        self.wallets.get(wallet_address).into_iter().flatten()
+            .filter(|bucket| bucket.hour >= from && bucket.hour < to)
+            .collect()
+    }
+
+    /// Totals per service over [from, to)
+    pub fn totals(&self, wallet_address: &str, from: u64, to: u64) -> BTreeMap<String, UsageTotals> {
+        let mut totals: BTreeMap<String, UsageTotals> = BTreeMap::new();
+        for bucket in self.buckets(wallet_address, from, to) {
+            let service = totals.entry(bucket.service_key.clone()).or_default();
+            service.requests += bucket.requests;
+            service.bytes += bucket.bytes;
+            service.compute_secs += bucket.compute_secs;
+        }
+        totals
+    }
+}
+
+impl PublicGateway {
+    /// Meter a call the service answered for the authenticated caller; a
+    /// wallet merely named in X-Wallet-Address is never billed. Sandbox and
+    /// anonymous traffic are left out.
+    pub(crate) fn meter_usage(&mut self, service_key: &str, caller: Option<&str>,
+                              bytes: usize, compute_secs: f64) {
+        if self.sandbox_mode {
+            return;
+        }
+        if let Some(wallet) = caller.filter(|wallet| !wallet.is_empty()) {
+            let now = Utc::now().timestamp() as u64;
+            self.usage_meter.record(wallet, service_key, bytes, compute_secs, now);
+        }
+    }
+
+    /// The month's usage for a wallet, priced with each service's current
+    /// pricing and bulk discounts
+    pub fn generate_invoice(&self, wallet_address: &str, period: &str) -> Result<Invoice, String> {
+        let (period_start, period_end) = month_bounds(period)?;
+        let lines: Vec<InvoiceLine> = self.usage_meter.totals(wallet_address, period_start, period_end).iter()
+            .map(|(service_key, usage)| match self.service_registry.get(service_key) {
+                Some(service) if service.payment_required => price_line(service_key, usage, Some(&service.pricing)),
+                // Free services are on the invoice, just at no charge
+                Some(_) => InvoiceLine { priced: true, ..price_line(service_key, usage, None) },
+                None => price_line(service_key, usage, None),
+            })
+            .collect();
+
+        let sum = |amount: fn(&InvoiceLine) -> f64| round_usdc(lines.iter().map(amount).sum());
+        let invoice_id = Sha256::digest(format!("{}:{}", wallet_address, period).as_bytes())
+            .iter().take(8).map(|b| format!("{:02x}", b)).collect::<String>();
+        let now = Utc::now().timestamp() as u64;
+        Ok(Invoice {
+            invoice_id: format!("inv_{}", invoice_id),
+            wallet_address: wallet_address.to_string(),
+            period: period.to_string(),
+            period_start,
+            period_end,
+            subtotal_usdc: sum(|line| line.subtotal_usdc),
+            discount_usdc: sum(|line| line.discount_usdc),
+            total_usdc: sum(|line| line.total_usdc),
+            lines,
+            closed: now >= period_end,
+            generated_at: now,
+        })
+    }
+
+    /// A wallet's usage and invoices are read by the wallet itself or the
+    /// operator; anything else gets the refusal to send
+    fn refuse_usage_read(&mut self, wallet_address: &str, headers: &HashMap<String, String>) -> Option<HttpResponse> {
+        if is_operator(headers) {
+            return None;
+        }
+        match self.authenticate_wallet(headers) {
+            Ok(caller) if caller == wallet_address => None,
+            Ok(_) => json_response(403, &serde_json::json!({ "error": "Usage is visible to the wallet itself only" })).ok(),
+            Err(e) => json_response(401, &serde_json::json!({ "error": e })).ok(),
+        }
+    }
+
+    /// GET /{wallet}/usage: the last 48 hourly buckets and month to date
+    pub(crate) fn handle_usage_request(&mut self, wallet_address: &str,
+                                       headers: &HashMap<String, String>) -> Result<HttpResponse, String> {
+        if let Some(refusal) = self.refuse_usage_read(wallet_address, headers) {
+            return Ok(refusal);
+        }
+        let now = Utc::now().timestamp() as u64;
+        let period = Utc::now().format("%Y-%m").to_string();
+        let (month_start, _) = month_bounds(&period)?;
+        json_response(200, &serde_json::json!({
+            "wallet_address": wallet_address,
+            "hourly": self.usage_meter.buckets(wallet_address, now.saturating_sub(48 * HOUR_SECS), u64::MAX),
+            "month_to_date": self.usage_meter.totals(wallet_address, month_start, u64::MAX),
+            "period": period,
+        }))
+    }
+
+    /// GET /{wallet}/invoices/{YYYY-MM}
+    pub(crate) fn handle_invoice_request(&mut self, wallet_address: &str, period: &str,
+                                         headers: &HashMap<String, String>) -> Result<HttpResponse, String> {
+        if let Some(refusal) = self.refuse_usage_read(wallet_address, headers) {
+            return Ok(refusal);
+        }
+        json_response(200, &self.generate_invoice(wallet_address, period)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PricingTier;
+
+    fn gateway() -> PublicGateway {
+        let mut gateway = PublicGateway::new("gateway.test");
+        gateway.register_wallet_endpoint("owner1", "owner", vec![4001]).unwrap();
+        gateway.add_service("owner1", "api", 4001, PricingTier::Basic).unwrap();
+        gateway.service_registry.get_mut("owner1_api").unwrap().payment_required = false;
+        gateway
+    }
+
+    fn signed_in(gateway: &mut PublicGateway, wallet: &str) -> HashMap<String, String> {
+        let (_, api_key) = gateway.api_keys.issue(wallet, "test", Utc::now().timestamp() as u64);
+        HashMap::from([("Authorization".to_string(), format!("Bearer {}", api_key))])
+    }
+
+    fn json(response: &HttpResponse) -> serde_json::Value {
+        serde_json::from_slice(&response.body).unwrap()
+    }
+
+    #[test]
+    fn test_usage_is_billed_to_the_authenticated_caller() {
+        let mut gateway = gateway();
+        let spoofed = HashMap::from([("X-Wallet-Address".to_string(), "victim".to_string())]);
+        gateway.handle_http_request("/owner1/api", "GET", &spoofed, &[]).unwrap();
+        assert!(gateway.usage_meter.totals("victim", 0, u64::MAX).is_empty());
+
+        let mut caller = signed_in(&mut gateway, "buyer1");
+        caller.insert("X-Wallet-Address".to_string(), "victim".to_string());
+        gateway.handle_http_request("/owner1/api", "GET", &caller, &[]).unwrap();
+        assert!(gateway.usage_meter.totals("victim", 0, u64::MAX).is_empty());
+        assert_eq!(gateway.usage_meter.totals("buyer1", 0, u64::MAX)["owner1_api"].requests, 1);
+    }
+
+    #[test]
+    fn test_usage_and_invoices_are_the_wallets_own() {
+        let mut gateway = gateway();
+        let none = HashMap::new();
+        let response = gateway.handle_http_request("/buyer1/usage", "GET", &none, &[]).unwrap();
+        assert_eq!(response.status_code, 401);
+
+        let stranger = signed_in(&mut gateway, "stranger");
+        let response = gateway.handle_http_request("/buyer1/invoices/2026-01", "GET", &stranger, &[]).unwrap();
+        assert_eq!(response.status_code, 403);
+
+        let buyer = signed_in(&mut gateway, "buyer1");
+        let response = gateway.handle_http_request("/buyer1/usage", "GET", &buyer, &[]).unwrap();
+        assert_eq!(response.status_code, 200);
+        let response = gateway.handle_http_request("/buyer1/invoices/2026-01", "GET", &buyer, &[]).unwrap();
+        assert_eq!(json(&response)["wallet_address"], "buyer1");
+    }
+
+    #[test]
+    fn test_service_paths_named_like_accounting_reach_the_service() {
+        let mut gateway = gateway();
+        let none = HashMap::new();
+        for path in ["/owner1/api/invoices/2026-01", "/owner1/api/usage", "/owner1/api/v1/usage"] {
+            let response = gateway.handle_http_request(path, "GET", &none, &[]).unwrap();
+            assert_eq!(json(&response)["response"], "Service response from libp2p", "{}", path);
+        }
+    }
+}
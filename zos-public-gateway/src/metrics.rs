@@ -0,0 +1,169 @@
+// Prometheus metrics: counters since the gateway started, in the text
+// exposition format, so operators can scrape /metrics into Grafana
+// AGPL-3.0 License
+
+use crate::receipts::json_response;
+use crate::{HttpResponse, PublicGateway};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+/// Counters kept in memory only; Prometheus handles the reset on restart
+#[derive(Debug, Clone, Default)]
+pub struct GatewayMetrics {
+    pub requests: BTreeMap<(String, String, u16), u64>, // (wallet, service, status) -> requests
+    pub payment_verifications: BTreeMap<&'static str, u64>, // "ok" | "failed"
+    pub swaps: BTreeMap<String, u64>, // input token -> swaps
+    pub swap_volume: BTreeMap<String, f64>, // input token -> amount swapped
+    pub rate_limit_rejections: BTreeMap<&'static str, u64>, // limit -> 429s
+}
+
+impl GatewayMetrics {
+    pub fn record_request(&mut self, wallet_address: &str, service_name: &str, status: u16) {
+        *self.requests.entry((wallet_address.to_string(), service_name.to_string(), status)).or_insert(0) += 1;
+    }
+
+    pub fn record_payment_verification(&mut self, verified: bool) {
+        *self.payment_verifications.entry(if verified { "ok" } else { "failed" }).or_insert(0) += 1;
+    }
+
+    pub fn record_swap(&mut self, token: &str, amount: f64) {
+        *self.swaps.entry(token.to_string()).or_insert(0) += 1;
+        *self.swap_volume.entry(token.to_string()).or_insert(0.0) += amount;
+    }
+
+    pub fn record_rate_limit_rejection(&mut self, limit: &'static str) {
+        *self.rate_limit_rejections.entry(limit).or_insert(0) += 1;
+    }
+}
+
+/// Label values escaped as the exposition format wants them
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// One metric family in text exposition format 0.0.4
+struct Family<'a> {
+    out: &'a mut String,
+    name: &'static str,
+}
+
+impl<'a> Family<'a> {
+    fn new(out: &'a mut String, name: &'static str, kind: &str, help: &str) -> Self {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} {}", name, kind);
+        Self { out, name }
+    }
+
+    fn sample(&mut self, labels: &[(&str, &str)], value: f64) {
+        let _ = write!(self.out, "{}", self.name);
+        if !labels.is_empty() {
+            let labels: Vec<String> = labels.iter()
+                .map(|(name, value)| format!("{}=\"{}\"", name, escape_label(value)))
+                .collect();
+            let _ = write!(self.out, "{{{}}}", labels.join(","));
+        }
+        let _ = writeln!(self.out, " {}", value);
+    }
+}
+
+/// `/{wallet}/{service}...` paths naming a registered service
+pub(crate) fn service_of<'a>(gateway: &PublicGateway, path: &'a str) -> Option<(&'a str, &'a str)> {
+    let mut parts = path.trim_start_matches('/').split('/');
+    let (wallet_address, service_name) = (parts.next()?, parts.next()?);
+    gateway.service_registry.contains_key(&format!("{}_{}", wallet_address, service_name))
+        .then_some((wallet_address, service_name))
+}
+
+/// Scrapes need `Authorization: Bearer` with ZOS_METRICS_TOKEN when one is
+/// configured; without one the endpoint is open, for private networks
+fn is_scraper(headers: &HashMap<String, String>) -> bool {
+    match std::env::var("ZOS_METRICS_TOKEN") {
+        Ok(expected) if !expected.is_empty() => headers.get("Authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|token| token == expected),
+        _ => true,
+    }
+}
+
+impl PublicGateway {
+    /// Count a request against the service its path names, if any
+    pub(crate) fn record_service_request(&mut self, path: &str, status: u16) {
+        if let Some((wallet_address, service_name)) = service_of(self, path) {
+            self.metrics.record_request(wallet_address, service_name, status);
+        }
+    }
+
+    /// Every counter, plus the quote cache and commission totals read from
+    /// the state that already keeps them
+    pub fn render_metrics(&self) -> String {
+        let mut out = String::new();
+        let metrics = &self.metrics;
+
+        let mut family = Family::new(&mut out, "zos_gateway_requests_total", "counter",
+                                     "Requests to each service, by the status the gateway answered with");
+        for ((wallet_address, service_name, status), count) in &metrics.requests {
+            family.sample(&[("wallet", wallet_address), ("service", service_name), ("status", &status.to_string())], *count as f64);
+        }
+
+        let mut family = Family::new(&mut out, "zos_gateway_payment_verifications_total", "counter",
+                                     "Payment tokens checked for paid service calls, by result");
+        for result in ["ok", "failed"] {
+            family.sample(&[("result", result)], metrics.payment_verifications.get(result).copied().unwrap_or(0) as f64);
+        }
+
+        let mut family = Family::new(&mut out, "zos_gateway_swaps_total", "counter", "Swaps executed, by input token");
+        for (token, count) in &metrics.swaps {
+            family.sample(&[("token", token)], *count as f64);
+        }
+        let mut family = Family::new(&mut out, "zos_gateway_swap_volume_total", "counter", "Amount swapped, by input token");
+        for (token, amount) in &metrics.swap_volume {
+            family.sample(&[("token", token)], *amount);
+        }
+
+        let mut family = Family::new(&mut out, "zos_gateway_commission_paid_total", "counter",
+                                     "Commission paid out, by commission type and token");
+        for (commission_type, by_token) in self.commission_totals() {
+            for (token, amount) in by_token {
+                family.sample(&[("type", &commission_type), ("token", &token)], amount);
+            }
+        }
+
+        let mut family = Family::new(&mut out, "zos_gateway_rate_limit_rejections_total", "counter",
+                                     "Calls refused with 429, by the limit they went over");
+        for (limit, count) in &metrics.rate_limit_rejections {
+            family.sample(&[("limit", limit)], *count as f64);
+        }
+
+        let cache = &self.payment_processor.quote_cache;
+        let stats = &cache.stats;
+        for (name, help, value) in [
+            ("zos_gateway_quote_cache_hits_total", "Quotes answered from the cache", stats.hits),
+            ("zos_gateway_quote_cache_misses_total", "Quotes computed because none was cached", stats.misses),
+            ("zos_gateway_quote_cache_expired_total", "Cached quotes found stale on lookup", stats.expired),
+            ("zos_gateway_quote_cache_evicted_total", "Live quotes removed because the cache was full", stats.evicted),
+        ] {
+            Family::new(&mut out, name, "counter", help).sample(&[], value as f64);
+        }
+        Family::new(&mut out, "zos_gateway_quote_cache_entries", "gauge", "Quotes in the cache")
+            .sample(&[], cache.len() as f64);
+
+        Family::new(&mut out, "zos_gateway_services", "gauge", "Registered services")
+            .sample(&[], self.service_registry.len() as f64);
+
+        out
+    }
+
+    pub(crate) fn handle_metrics_request(&self, headers: &HashMap<String, String>) -> Result<HttpResponse, String> {
+        if !is_scraper(headers) {
+            return json_response(401, &serde_json::json!({ "error": "Needs Authorization: Bearer with the metrics token" }));
+        }
+        Ok(HttpResponse {
+            status_code: 200,
+            headers: HashMap::from([
+                ("Content-Type".to_string(), "text/plain; version=0.0.4; charset=utf-8".to_string()),
+                ("Cache-Control".to_string(), "no-store".to_string()),
+            ]),
+            body: self.render_metrics().into_bytes(),
+        })
+    }
+}
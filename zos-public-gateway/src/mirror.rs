@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+use crate::PublicGateway;
+use std::collections::HashMap;
+
+/// Copy a share of production traffic to a staging endpoint. Mirrored calls
+/// are never billed and their responses are discarded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MirrorConfig {
+    pub staging_endpoint: String, // libp2p multiaddr or http URL of the staging service
+    pub percentage: f64,
+    pub scrub_body: bool,
+    pub scrub_fields: Vec<String>, // empty = scrub every string value
+    #[serde(default)]
+    pub stats: MirrorStats,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MirrorStats {
+    pub requests_seen: u64,
+    pub mirrored: u64,
+    pub mirror_errors: u64,
+}
+
+impl MirrorConfig {
+    /// Deterministic sampling: mirror whenever the running count crosses the
+    /// next whole multiple of the percentage, so 10% is exactly every tenth call.
+    fn should_mirror(&mut self) -> bool {
+        let before = (self.stats.requests_seen as f64 * self.percentage / 100.0).floor();
+        self.stats.requests_seen += 1;
+        let after = (self.stats.requests_seen as f64 * self.percentage / 100.0).floor();
+        after > before
+    }
+}
+
+fn scrub_value(value: &mut serde_json::Value, fields: &[String]) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, field) in map.iter_mut() {
+                if fields.iter().any(|f| f == key) {
+                    *field = serde_json::Value::String("[scrubbed]".to_string());
+                } else {
+                    scrub_value(field, fields);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                scrub_value(item, fields);
+            }
+        }
+        serde_json::Value::String(s) if fields.is_empty() => {
+            *s = "[scrubbed]".to_string();
+        }
+        _ => {}
+    }
+}
+
+pub fn scrub_body(body: &[u8], fields: &[String]) -> Vec<u8> {
+    match serde_json::from_slice::<serde_json::Value>(body) {
+        Ok(mut value) => {
+            scrub_value(&mut value, fields);
+            serde_json::to_vec(&value).unwrap_or_default()
+        }
+        Err(_) => Vec::new(), // opaque bodies are dropped rather than leaked
+    }
+}
+
+impl PublicGateway {
+    pub fn set_traffic_mirror(&mut self, service_key: &str, config: Option<MirrorConfig>) -> Result<(), String> {
+        if let Some(config) = &config {
+            if !(0.0..=100.0).contains(&config.percentage) {
+                return Err("Mirror percentage must be between 0 and 100".to_string());
+            }
+            if config.staging_endpoint.is_empty() {
+                return Err("Staging endpoint required".to_string());
+            }
+        }
+
+        let service = self.service_registry.get_mut(service_key)
+            .ok_or("Service not found")?;
+        service.mirror = config;
+
+        Ok(())
+    }
+
+    /// Called after the production response is built; never affects it
+    pub(crate) fn mirror_request(&mut self, service_key: &str, method: &str,
+                                 headers: &HashMap<String, String>, body: &[u8]) {
+        let mirror = match self.service_registry.get_mut(service_key).and_then(|s| s.mirror.as_mut()) {
+            Some(mirror) => mirror,
+            None => return,
+        };
+
+        if !mirror.should_mirror() {
+            return;
+        }
+
+        let mut mirrored_headers = headers.clone();
+        mirrored_headers.remove("X-Payment-Token");
+        mirrored_headers.insert("X-Zos-Mirror".to_string(), "1".to_string());
+
+        let mirrored_body = if mirror.scrub_body {
+            scrub_body(body, &mirror.scrub_fields)
+        } else {
+            body.to_vec()
+        };
+
+        // Simplified libp2p forwarding, as for production calls
+        let sent = serde_json::to_vec(&serde_json::json!({
+            "mirror_of": service_key,
+            "target": mirror.staging_endpoint,
+            "method": method,
+            "headers": mirrored_headers,
+            "body_bytes": mirrored_body.len()
+        }));
+
+        match sent {
+            Ok(_) => mirror.stats.mirrored += 1,
+            Err(_) => mirror.stats.mirror_errors += 1,
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use serde::{Deserialize, Serialize};
+use crate::receipts::json_response;
+use crate::{HttpResponse, PublicGateway};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Hold at least `min_count` NFTs from a Metaplex verified collection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NftRequirement {
+    pub collection_address: String, // collection mint
+    pub min_count: u32,
+}
+
+/// On-chain holdings lookup (an RPC or indexer). Deployments plug their own
+/// in with `set_balance_oracle`.
+pub trait BalanceOracle: Send + Sync {
+    fn provider(&self) -> &str;
+    fn nft_count(&self, wallet_address: &str, collection_address: &str) -> Result<u32, String>;
+}
+
+/// Default: holdings can't be looked up, so gated services turn everyone away
+pub struct NoBalanceOracle;
+
+impl BalanceOracle for NoBalanceOracle {
+    fn provider(&self) -> &str {
+        "none"
+    }
+
+    fn nft_count(&self, _wallet_address: &str, _collection_address: &str) -> Result<u32, String> {
+        Err("No balance oracle configured".to_string())
+    }
+}
+
+#[derive(Clone)]
+pub struct BalanceOracleHook(pub Arc<dyn BalanceOracle>);
+
+impl Default for BalanceOracleHook {
+    fn default() -> Self {
+        Self(Arc::new(NoBalanceOracle))
+    }
+}
+
+impl std::fmt::Debug for BalanceOracleHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "BalanceOracleHook({})", self.0.provider())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedNftCount {
+    pub count: u32,
+    pub checked_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NftGating {
+    pub cache_ttl_secs: u64,
+    pub cache: HashMap<String, CachedNftCount>, // "{collection}:{wallet}" -> holdings
+    #[serde(skip)]
+    pub oracle: BalanceOracleHook,
+}
+
+impl Default for NftGating {
+    fn default() -> Self {
+        Self {
+            cache_ttl_secs: 300,
+            cache: HashMap::new(),
+            oracle: BalanceOracleHook::default(),
+        }
+    }
+}
+
+impl PublicGateway {
+    pub fn set_balance_oracle(&mut self, oracle: Arc<dyn BalanceOracle>) {
+        println!("🖼️  Balance oracle: {}", oracle.provider());
+        self.nft_gating.oracle = BalanceOracleHook(oracle);
+        self.nft_gating.cache.clear();
+    }
+
+    /// Gate a service on NFT ownership; an empty list opens it again
+    pub fn set_nft_gate(&mut self, service_key: &str, requirements: Vec<NftRequirement>) -> Result<(), String> {
+        if requirements.iter().any(|requirement| requirement.collection_address.is_empty()) {
+            return Err("collection_address is required".to_string());
+        }
+
+        let service = self.service_registry.get_mut(service_key)
+            .ok_or("Service not found")?;
+        service.nft_gate = requirements;
+        Ok(())
+    }
+
+    /// Holdings of one collection, cached; oracle errors are not cached
+    pub fn nft_count(&mut self, wallet_address: &str, collection_address: &str) -> Result<u32, String> {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let key = format!("{}:{}", collection_address, wallet_address);
+        if let Some(cached) = self.nft_gating.cache.get(&key)
+            .filter(|cached| now.saturating_sub(cached.checked_at) < self.nft_gating.cache_ttl_secs) {
+            return Ok(cached.count);
+        }
+
+        let count = self.nft_gating.oracle.0.nft_count(wallet_address, collection_address)?;
+        self.nft_gating.cache.insert(key, CachedNftCount { count, checked_at: now });
+        Ok(count)
+    }
+
+    /// 403 unless the authenticated caller meets every NFT requirement on
+    /// the service, 401 without one, 503 if holdings can't be looked up.
+    /// Naming a holder's wallet in X-Wallet-Address proves nothing.
+    pub(crate) fn check_nft_gate(&mut self, service_key: &str, caller: Option<&str>) -> Option<HttpResponse> {
+        let requirements = self.service_registry.get(service_key)?.nft_gate.clone();
+        if requirements.is_empty() {
+            return None;
+        }
+
+        let Some(caller) = caller else {
+            return json_response(401, &serde_json::json!({
+                "error": "This service is NFT-gated. Sign in as the holding wallet: a bearer API key or a signed challenge",
+                "requirements": requirements,
+            })).ok();
+        };
+
+        for requirement in &requirements {
+            let (status, refusal) = match self.nft_count(caller, &requirement.collection_address) {
+                Ok(count) if count >= requirement.min_count => continue,
+                Ok(count) => (403, format!("Holds {} of {} required NFTs from collection {}",
+                                           count, requirement.min_count, requirement.collection_address)),
+                Err(e) => (503, format!("Could not verify NFT holdings: {}", e)),
+            };
+            return json_response(status, &serde_json::json!({
+                "error": refusal,
+                "requirements": requirements,
+            })).ok();
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PricingTier;
+
+    /// Only `holder` holds the collection
+    struct OneHolder;
+
+    impl BalanceOracle for OneHolder {
+        fn provider(&self) -> &str {
+            "test"
+        }
+
+        fn nft_count(&self, wallet_address: &str, _collection_address: &str) -> Result<u32, String> {
+            Ok(if wallet_address == "holder" { 1 } else { 0 })
+        }
+    }
+
+    fn gated() -> PublicGateway {
+        let mut gateway = PublicGateway::new("gateway.test");
+        gateway.register_wallet_endpoint("owner1", "owner", vec![4001]).unwrap();
+        gateway.add_service("owner1", "api", 4001, PricingTier::Basic).unwrap();
+        gateway.service_registry.get_mut("owner1_api").unwrap().payment_required = false;
+        gateway.set_nft_gate("owner1_api", vec![NftRequirement {
+            collection_address: "collection1".to_string(),
+            min_count: 1,
+        }]).unwrap();
+        gateway.set_balance_oracle(Arc::new(OneHolder));
+        gateway
+    }
+
+    #[test]
+    fn test_naming_a_holder_does_not_pass_the_gate() {
+        let mut gateway = gated();
+        let headers = HashMap::from([("X-Wallet-Address".to_string(), "holder".to_string())]);
+        let refusal = gateway.authorize_service_call("owner1", "api", &headers).unwrap().err().unwrap();
+        assert_eq!(refusal.status_code, 401);
+    }
+
+    #[test]
+    fn test_the_gate_checks_the_authenticated_caller() {
+        let mut gateway = gated();
+        assert!(gateway.check_nft_gate("owner1_api", Some("holder")).is_none());
+        assert_eq!(gateway.check_nft_gate("owner1_api", Some("stranger")).unwrap().status_code, 403);
+
+        let (_, api_key) = gateway.api_keys.issue("holder", "test", chrono::Utc::now().timestamp() as u64);
+        let headers = HashMap::from([("Authorization".to_string(), format!("Bearer {}", api_key))]);
+        assert!(gateway.authorize_service_call("owner1", "api", &headers).unwrap().is_ok());
+    }
+}
@@ -0,0 +1,731 @@
+use serde_json::{json, Map, Value};
+
+/// (method, path, tag, summary, request schema, response schema, success
+/// status, security schemes). Schemas name entries of `components`; None
+/// is a free-form JSON object.
+type Route = (&'static str, &'static str, &'static str, &'static str,
+              Option<&'static str>, Option<&'static str>, u16, &'static [&'static str]);
+
+const WALLET: &[&str] = &["WalletAddress"];
+const MANAGE: &[&str] = &["ApiKey", "WalletChallenge", "OperatorKey"];
+const SIGNED: &[&str] = &["ApiKey", "WalletChallenge"];
+const RECIPIENT: &[&str] = &["ApiKey", "WalletChallenge", "GovernanceKey"];
+const OPERATOR: &[&str] = &["OperatorKey"];
+const GOVERNANCE: &[&str] = &["GovernanceKey"];
+const PAID: &[&str] = &["PaymentToken"];
+const METRICS: &[&str] = &["MetricsToken"];
+const PUBLIC: &[&str] = &[];
+
+const ROUTES: &[Route] = &[
+    ("get", "/{wallet}", "Wallets", "Wallet info and services", None, None, 200, PUBLIC),
+    ("get", "/{wallet}/export", "Wallets", "Everything the gateway holds about the wallet", None, None, 200, MANAGE),
+    ("post", "/{wallet}/erase", "Wallets", "Replace the wallet with a pseudonym across the ledger", Some("ErasureRequest"), None, 200, MANAGE),
+    ("post", "/{wallet}/register", "Wallets", "Register a new service", Some("RegisterServiceRequest"), None, 201, MANAGE),
+
+    ("post", "/auth/challenge", "Service Management", "One-time message for a wallet to sign (valid 5 minutes)", Some("ChallengeRequest"), Some("AuthChallenge"), 200, PUBLIC),
+    ("get", "/auth/keys", "Service Management", "The wallet's API keys", None, None, 200, MANAGE),
+    ("post", "/auth/keys", "Service Management", "Issue an API key; the key is shown only in this response", None, None, 201, MANAGE),
+    ("post", "/auth/keys/{id}/rotate", "Service Management", "New key under the same label; the old one stops working at once", None, None, 200, MANAGE),
+    ("delete", "/auth/keys/{id}", "Service Management", "Revoke a key", None, None, 200, MANAGE),
+    ("post", "/manage/endpoints", "Service Management", "Register a wallet endpoint", Some("RegisterEndpointRequest"), None, 201, MANAGE),
+    ("post", "/manage/services", "Service Management", "Add a service to a wallet endpoint", Some("AddServiceRequest"), None, 201, MANAGE),
+
+    ("get", "/{wallet}/{service}", "Services", "Call a service", None, None, 200, PAID),
+    ("post", "/{wallet}/{service}", "Services", "Call a service", None, None, 200, PAID),
+    ("put", "/{wallet}/{service}", "Services", "Call a service", None, None, 200, PAID),
+    ("delete", "/{wallet}/{service}", "Services", "Call a service", None, None, 200, PAID),
+    ("get", "/{wallet}/{service}/contract", "Services", "Request and response JSON Schemas and violation rates", None, None, 200, PUBLIC),
+    ("get", "/certifications", "Certification", "Certified services and the certification policy", None, None, 200, PUBLIC),
+    ("get", "/certifications/{wallet}/{service}", "Certification", "Each check's result, uptime by day, latency and the badge", None, None, 200, PUBLIC),
+
+    ("post", "/{wallet}/{service}/swap", "Payments", "Swap tokens against the deepest pool's reserves; the input is paid with X-Payment-Token", Some("SwapRequest"), Some("SwapResult"), 200, SIGNED),
+    ("post", "/{wallet}/{service}/quote", "Payments", "Swap quote, cached until expires_at (GET with the same body is accepted)", Some("QuoteRequest"), Some("Quote"), 200, PUBLIC),
+    ("get", "/quote/stats", "Payments", "Quote cache size, hit rate, expired, swept and evicted counts", None, None, 200, PUBLIC),
+    ("post", "/{wallet}/{service}/estimate", "Payments", "Signed price for a declared payload, duration and request count; tier discount for an authenticated wallet", None, None, 200, SIGNED),
+
+    ("get", "/{wallet}/{service}/experiments", "Experiments", "Experiments with results per variant and cohort", None, None, 200, MANAGE),
+    ("post", "/{wallet}/{service}/experiments", "Experiments", "Start an experiment", None, None, 200, MANAGE),
+    ("get", "/{wallet}/{service}/experiments/{id}", "Experiments", "Calls, conversion and error rates, latency per variant", None, None, 200, MANAGE),
+    ("post", "/{wallet}/{service}/experiments/{id}/stop", "Experiments", "Stop routing to variants; results are kept", None, None, 200, MANAGE),
+
+    ("get", "/{wallet}/{service}/commission-plan", "Commission Plans", "Rates in force for the service, scheduled plans and history", None, None, 200, MANAGE),
+    ("post", "/{wallet}/{service}/commission-plan", "Commission Plans", "Register the service's own rates", Some("ScheduleRatesRequest"), None, 200, MANAGE),
+    ("delete", "/{wallet}/{service}/commission-plan/{id}", "Commission Plans", "Cancel a plan that isn't in force yet", None, None, 200, MANAGE),
+
+    ("get", "/{wallet}/{service}/cors", "CORS", "Whether browsers may call the service, and from which origins", None, None, 200, PUBLIC),
+    ("put", "/{wallet}/{service}/cors", "CORS", "Set the service's CORS policy; enabled turns cross-origin calls on or off", Some("CorsPolicy"), None, 200, MANAGE),
+
+    ("get", "/{wallet}/{service}/transforms", "Transforms", "Transform versions, the active one, run stats and limits", None, None, 200, MANAGE),
+    ("post", "/{wallet}/{service}/transforms", "Transforms", "Upload scripts as a new version, stored once every sample passes", Some("TransformUpload"), None, 201, MANAGE),
+    ("post", "/{wallet}/{service}/transforms/{version}/activate", "Transforms", "Run this version on every call", None, None, 200, MANAGE),
+    ("post", "/{wallet}/{service}/transforms/deactivate", "Transforms", "Stop transforming calls; versions are kept", None, None, 200, MANAGE),
+
+    ("get", "/api/policies", "Access Policies", "Built-in and operator access policies", None, None, 200, PUBLIC),
+    ("post", "/api/policies/evaluate", "Access Policies", "Decision for an action, principal, resource and context", None, None, 200, PUBLIC),
+
+    ("get", "/archive", "Archive", "Segments, records and head hash per stream", None, None, 200, PUBLIC),
+    ("get", "/archive/verify", "Archive", "Check every segment against its hash and its link to the one before", None, None, 200, PUBLIC),
+    ("get", "/archive/commissions/{wallet}", "Archive", "Archived commission payments", None, Some("CommissionPaymentList"), 200, MANAGE),
+    ("get", "/archive/payments/{wallet}", "Archive", "Archived payment records", None, None, 200, MANAGE),
+    ("post", "/archive/run", "Archive", "Archive now instead of on the daily timer", None, None, 200, OPERATOR),
+
+    ("get", "/r/{code}", "Short Links", "Redirect to the referral URL", None, None, 302, PUBLIC),
+    ("get", "/r/{code}/qr.png", "Short Links", "QR code (PNG)", None, None, 200, PUBLIC),
+    ("get", "/r/{code}/qr.svg", "Short Links", "QR code (SVG)", None, None, 200, PUBLIC),
+
+    ("get", "/{wallet}/earnings", "Accounting", "Earnings dashboard: balances, tier, referral links and a page of commission payments", None, Some("EarningsDashboard"), 200, PUBLIC),
+    ("get", "/api/recommendations/{wallet}", "Accounting", "Usage over the last 30 days with suggested plans", None, None, 200, MANAGE),
+    ("get", "/{wallet}/usage", "Accounting", "Calls, bytes and compute seconds per service, hourly", None, None, 200, MANAGE),
+    ("get", "/{wallet}/invoices/{month}", "Accounting", "Monthly invoice (month as YYYY-MM) from metered usage", None, None, 200, MANAGE),
+    ("get", "/{wallet}/earnings/tax.csv", "Accounting", "Commission payments with USD value", None, None, 200, PUBLIC),
+    ("get", "/{wallet}/earnings/summary.csv", "Accounting", "Closed-period totals by commission type", None, None, 200, PUBLIC),
+    ("post", "/{wallet}/earnings/withdraw", "Accounting", "Queue a withdrawal; the wallet is screened", Some("WithdrawRequest"), None, 200, SIGNED),
+    ("get", "/{wallet}/earnings/withdrawals", "Accounting", "Balance, payout schedule, withdrawals and payouts", None, None, 200, WALLET),
+    ("get", "/{wallet}/earnings/withdrawals/{id}", "Accounting", "One withdrawal and its status", None, None, 200, WALLET),
+    ("get", "/{wallet}/earnings/events", "Accounting", "Append-only earnings events", None, None, 200, WALLET),
+    ("get", "/{wallet}/earnings/at/{unix}", "Accounting", "The earnings account replayed up to a moment", None, Some("EarningsAt"), 200, WALLET),
+
+    ("get", "/commission/events/audit", "Commissions", "Wallets whose stored account differs from their event fold", None, None, 200, OPERATOR),
+    ("post", "/commission/events/replay", "Commissions", "Rebuild every account from its events", None, None, 200, OPERATOR),
+    ("post", "/commission/clawbacks", "Commissions", "Reverse a commission payment", Some("ClawbackRequest"), None, 200, OPERATOR),
+    ("post", "/commission/tiers/recalculate", "Commissions", "Re-derive every tier from distinct active referrals", None, Some("TierChangeList"), 200, OPERATOR),
+    ("put", "/commission/referrals/{referrer}/{referee}", "Commissions", "Set a referral's status; the referrer's tier follows", Some("ReferralStatusRequest"), None, 200, OPERATOR),
+    ("get", "/commission/rates", "Commissions", "Rates in force, caps and changes scheduled ahead", None, None, 200, PUBLIC),
+    ("get", "/commission/rates/history", "Commissions", "Every rate change, including cancelled ones", None, Some("RateChangeList"), 200, PUBLIC),
+    ("get", "/commission/rates/at/{unix}", "Commissions", "Rates in force at a moment, past or future", None, None, 200, PUBLIC),
+    ("post", "/commission/rates", "Commissions", "Schedule new rates approved by a governance proposal", Some("ScheduleRatesRequest"), Some("RateChange"), 201, GOVERNANCE),
+    ("delete", "/commission/rates/{change_id}", "Commissions", "Cancel a change that isn't in force yet", None, Some("RateChange"), 200, GOVERNANCE),
+    ("get", "/commission/audit/{payment_id}", "Commissions", "Check a payment against the rates in force when it was made", None, None, 200, RECIPIENT),
+
+    ("get", "/payments/{id}", "Refunds", "Payment, its refunds and the amount refunded so far", None, Some("PaymentRefunds"), 200, MANAGE),
+    ("post", "/payments/{id}/refund", "Refunds", "Refund part or all of a payment within the dispute window, clawing back its commissions pro rata", Some("RefundRequest"), None, 200, MANAGE),
+    ("post", "/payments/{id}/chargeback", "Refunds", "Record a chargeback, clawing back its commissions pro rata", Some("RefundRequest"), None, 200, OPERATOR),
+    ("get", "/{wallet}/{service}/refund-policy", "Refunds", "How long the service's payments can be refunded or charged back", None, Some("RefundPolicy"), 200, PUBLIC),
+    ("put", "/{wallet}/{service}/refund-policy", "Refunds", "Set the service's dispute window", Some("RefundPolicy"), Some("RefundPolicy"), 200, MANAGE),
+
+    ("get", "/journal", "Journal", "Latest journal entries", None, Some("JournalPage"), 200, OPERATOR),
+    ("get", "/journal/after/{seq}", "Journal", "Up to 500 journal entries after seq", None, Some("JournalPage"), 200, OPERATOR),
+    ("get", "/journal/audit", "Journal", "Where the stored commission state and a replay of the journal disagree", None, None, 200, OPERATOR),
+    ("post", "/journal/restore", "Journal", "Rebuild the commission state from the journal", None, None, 200, OPERATOR),
+
+    ("get", "/payouts", "Payouts", "Unconfirmed payouts, payouts awaiting approval and tier schedules", None, None, 200, OPERATOR),
+    ("post", "/payouts/batch", "Payouts", "Batch due withdrawals into one payout per wallet", None, None, 200, OPERATOR),
+    ("post", "/payouts/{id}/complete", "Payouts", "Record a sent payout in the ledger", None, None, 200, OPERATOR),
+    ("post", "/payouts/{id}/fail", "Payouts", "Fail a payout; funds return to the balance", None, None, 200, OPERATOR),
+    ("get", "/approvals", "Payouts", "Approval policy and pending requests", None, None, 200, OPERATOR),
+    ("get", "/approvals/{id}", "Payouts", "Approval request, the messages to sign and its audit trail", None, None, 200, OPERATOR),
+    ("post", "/approvals/{id}/approve", "Payouts", "Co-sign with an approver wallet's signature over the approve message", Some("ApprovalSignature"), None, 200, PUBLIC),
+    ("post", "/approvals/{id}/decline", "Payouts", "Decline with an approver wallet's signature over the decline message", Some("ApprovalSignature"), None, 200, PUBLIC),
+
+    ("post", "/coupons", "Coupons", "Create a promo code", None, None, 200, SIGNED),
+    ("get", "/coupons/{code}", "Coupons", "Discount, eligible services and whether it can still be used", None, None, 200, PUBLIC),
+    ("get", "/coupons/{code}/stats", "Coupons", "Redemptions, unique wallets, discount given and promo conversions", None, None, 200, SIGNED),
+    ("delete", "/coupons/{code}", "Coupons", "Withdraw a coupon", None, None, 200, SIGNED),
+
+    ("get", "/compliance/blocklist", "Compliance", "Blocklisted wallets and the screening provider in use", None, None, 200, PUBLIC),
+    ("get", "/compliance/blocklist/{wallet}", "Compliance", "One blocklist entry", None, None, 200, PUBLIC),
+    ("post", "/compliance/blocklist", "Compliance", "Block a wallet", None, None, 200, OPERATOR),
+    ("delete", "/compliance/blocklist/{wallet}", "Compliance", "Unblock a wallet", None, None, 200, OPERATOR),
+
+    ("get", "/treasury", "Treasury", "Fee routing and routed totals", None, None, 200, PUBLIC),
+    ("get", "/treasury/burns", "Treasury", "Burn history, newest first", None, None, 200, PUBLIC),
+
+    ("get", "/explorer", "Explorer", "HTML overview of the explorer tables", None, None, 200, PUBLIC),
+    ("get", "/explorer/stats", "Explorer", "Service counts, calls, volume, commission totals and token circulation", None, None, 200, PUBLIC),
+    ("get", "/metrics", "Monitoring", "Prometheus counters: requests, payment verifications, swap volume, commission, rate limit rejections, quote cache", None, Some("MetricsText"), 200, METRICS),
+    ("get", "/explorer/volume", "Explorer", "Calls, paid calls, USDC volume and swaps per UTC day", None, None, 200, PUBLIC),
+    ("get", "/explorer/services", "Explorer", "Top services by calls", None, None, 200, PUBLIC),
+
+    ("get", "/receipts/public-key", "Receipts", "Gateway ed25519 key that signs usage receipts", None, None, 200, PUBLIC),
+    ("get", "/receipts/{receipt_id}", "Receipts", "A recent usage receipt", None, Some("UsageReceipt"), 200, PUBLIC),
+    ("post", "/receipts/verify", "Receipts", "Check a stored receipt against this gateway's key", Some("UsageReceipt"), None, 200, PUBLIC),
+
+    ("get", "/sandbox/status", "Sandbox", "Last and next sandbox wipe", None, None, 200, PUBLIC),
+    ("post", "/cluster/rate-limits", "Cluster", "Exchange per-wallet rate limit counters with a trusted peer", None, None, 200, OPERATOR),
+    ("post", "/cluster/state", "Cluster", "Exchange service registry and referral link updates with a trusted peer", Some("StateGossip"), Some("StateGossip"), 200, OPERATOR),
+    ("post", "/cluster/game-sessions", "Cluster", "Draining node announces where its game sessions moved", None, None, 200, OPERATOR),
+    ("get", "/openapi.json", "Meta", "This document", None, None, 200, PUBLIC),
+];
+
+fn schema_ref(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+fn body_schema(schema: Option<&str>) -> Value {
+    schema.map(schema_ref).unwrap_or_else(|| json!({ "type": "object" }))
+}
+
+fn path_parameters(path: &str) -> Vec<Value> {
+    path.split('/')
+        .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+        .map(|name| json!({
+            "name": name,
+            "in": "path",
+            "required": true,
+            "schema": if name == "unix" || name == "seq" { json!({ "type": "integer", "format": "int64" }) } else { json!({ "type": "string" }) },
+        }))
+        .collect()
+}
+
+/// Query parameters of the operations that take any
+fn query_parameters(path: &str) -> Vec<Value> {
+    let parameter = |name: &str, description: &str, schema: Value| json!({
+        "name": name,
+        "in": "query",
+        "required": false,
+        "description": description,
+        "schema": schema,
+    });
+    match path {
+        "/{wallet}/earnings" => vec![
+            parameter("from", "Payments at or after this unix time", json!({ "type": "integer", "format": "int64" })),
+            parameter("to", "Payments at or before this unix time", json!({ "type": "integer", "format": "int64" })),
+            parameter("page", "Page number, from 1", json!({ "type": "integer", "minimum": 1, "default": 1 })),
+            parameter("page_size", "Payments per page", json!({ "type": "integer", "minimum": 1, "maximum": 100, "default": 20 })),
+            parameter("commission_type", "Only payments of this type", schema_ref("CommissionType")),
+        ],
+        "/{wallet}/earnings/tax.csv" => vec![
+            parameter("from", "Payments at or after this unix time", json!({ "type": "integer", "format": "int64" })),
+            parameter("to", "Payments at or before this unix time", json!({ "type": "integer", "format": "int64" })),
+        ],
+        _ => Vec::new(),
+    }
+}
+
+fn operation(route: &Route) -> Value {
+    let (method, path, tag, summary, request, response, status, security) = *route;
+    let content_type = if path.ends_with(".csv") {
+        "text/csv"
+    } else if path.ends_with(".png") {
+        "image/png"
+    } else if path.ends_with(".svg") {
+        "image/svg+xml"
+    } else if path == "/explorer" {
+        "text/html"
+    } else if path == "/metrics" {
+        "text/plain"
+    } else {
+        "application/json"
+    };
+
+    let mut responses = Map::new();
+    responses.insert(status.to_string(), json!({
+        "description": summary,
+        "content": { content_type: { "schema": body_schema(response) } },
+    }));
+    let error = |description: &str| json!({
+        "description": description,
+        "content": { "application/json": { "schema": schema_ref("Error") } },
+    });
+    responses.insert("400".to_string(), error("Malformed request, or the gateway refused it"));
+    if !security.is_empty() {
+        responses.insert("401".to_string(), error("Missing or invalid credentials"));
+        responses.insert("403".to_string(), error("Credentials do not allow this"));
+    }
+    if tag == "Services" {
+        responses.insert("402".to_string(), error("Payment required"));
+        responses.insert("429".to_string(), json!({
+            "description": "Over the wallet's rate or bandwidth limit",
+            "headers": { "Retry-After": { "schema": { "type": "integer" } } },
+            "content": { "application/json": { "schema": schema_ref("Error") } },
+        }));
+        responses.insert("503".to_string(), error("Service delisted or its queue is full"));
+    }
+
+    let mut operation = json!({
+        "tags": [tag],
+        "summary": summary,
+        "operationId": operation_id(method, path),
+        "parameters": path_parameters(path).into_iter().chain(query_parameters(path)).collect::<Vec<_>>(),
+        "responses": responses,
+        "security": security.iter().map(|scheme| json!({ *scheme: [] })).collect::<Vec<_>>(),
+    });
+    if let Some(request) = request {
+        operation["requestBody"] = json!({
+            "required": true,
+            "content": { "application/json": { "schema": schema_ref(request) } },
+        });
+    }
+    operation
+}
+
+/// `post /{wallet}/{service}/swap` → `postWalletServiceSwap`
+fn operation_id(method: &str, path: &str) -> String {
+    let words = path.split(|c: char| !c.is_ascii_alphanumeric()).filter(|word| !word.is_empty());
+    std::iter::once(method.to_string())
+        .chain(words.map(|word| {
+            let mut chars = word.chars();
+            chars.next().map(|first| first.to_ascii_uppercase().to_string() + chars.as_str()).unwrap_or_default()
+        }))
+        .collect()
+}
+
+fn schemas() -> Value {
+    let mut schemas = json!({
+        "Error": {
+            "type": "object",
+            "required": ["error"],
+            "properties": { "error": { "type": "string" } },
+        },
+        "MetricsText": {
+            "type": "string",
+            "description": "Prometheus text exposition format 0.0.4",
+            "example": "# TYPE zos_gateway_quote_cache_hits_total counter\nzos_gateway_quote_cache_hits_total 42\n",
+        },
+        "SwapRequest": {
+            "type": "object",
+            "required": ["from_token", "to_token", "amount", "slippage_tolerance"],
+            "properties": {
+                "from_token": { "type": "string", "example": "SOLFUNMEME" },
+                "to_token": { "type": "string", "example": "USDC" },
+                "amount": { "type": "number", "format": "double" },
+                "slippage_tolerance": { "type": "number", "format": "double", "description": "Largest price impact accepted, in %" },
+            },
+        },
+        "SwapResult": {
+            "type": "object",
+            "required": ["transaction_id", "input_amount", "output_amount", "price_impact", "fee", "status"],
+            "properties": {
+                "transaction_id": { "type": "string" },
+                "input_amount": { "type": "number", "format": "double" },
+                "output_amount": { "type": "number", "format": "double" },
+                "price_impact": { "type": "number", "format": "double", "description": "%" },
+                "fee": { "type": "number", "format": "double" },
+                "status": { "type": "string" },
+            },
+        },
+        "QuoteRequest": {
+            "type": "object",
+            "required": ["from_token", "to_token", "amount"],
+            "properties": {
+                "from_token": { "type": "string" },
+                "to_token": { "type": "string" },
+                "amount": { "type": "number", "format": "double" },
+            },
+        },
+        "Quote": {
+            "type": "object",
+            "required": ["from_token", "to_token", "amount", "quoted_price", "expires_at", "slippage"],
+            "properties": {
+                "from_token": { "type": "string" },
+                "to_token": { "type": "string" },
+                "amount": { "type": "number", "format": "double" },
+                "quoted_price": { "type": "number", "format": "double", "description": "Output amount for the whole input" },
+                "expires_at": { "type": "integer", "format": "int64" },
+                "slippage": { "type": "number", "format": "double", "description": "Price impact, %" },
+            },
+        },
+        "CommissionType": {
+            "type": "string",
+            "enum": ["SwapFee", "ReferralBonus", "ServiceFee", "VolumeBonus", "EdgeCacheHits"],
+        },
+        "TokenAmount": {
+            "type": "object",
+            "required": ["base_units", "decimals"],
+            "properties": {
+                "base_units": { "type": "integer", "format": "int64" },
+                "decimals": { "type": "integer", "minimum": 0, "maximum": 255 },
+            },
+        },
+        "CommissionRates": {
+            "type": "object",
+            "required": ["swap_commission_percentage", "referral_commission_percentage",
+                         "service_commission_percentage", "tier_multipliers"],
+            "properties": {
+                "swap_commission_percentage": { "type": "number", "format": "double" },
+                "referral_commission_percentage": { "type": "number", "format": "double" },
+                "service_commission_percentage": { "type": "number", "format": "double" },
+                "tier_multipliers": { "type": "object", "additionalProperties": { "type": "number", "format": "double" } },
+            },
+        },
+        "RateChange": {
+            "type": "object",
+            "required": ["change_id", "rates", "effective_at", "scheduled_at"],
+            "properties": {
+                "change_id": { "type": "string" },
+                "rates": schema_ref("CommissionRates"),
+                "effective_at": { "type": "integer", "format": "int64" },
+                "scheduled_at": { "type": "integer", "format": "int64" },
+                "proposal_id": { "type": "string", "nullable": true },
+                "cancelled_at": { "type": "integer", "format": "int64", "nullable": true },
+            },
+        },
+        "RateChangeList": {
+            "type": "object",
+            "properties": { "changes": { "type": "array", "items": schema_ref("RateChange") } },
+        },
+        "CorsPolicy": {
+            "type": "object",
+            "properties": {
+                "enabled": { "type": "boolean" },
+                "allowed_origins": { "type": "array", "items": { "type": "string" },
+                                     "description": "\"*\", scheme://host[:port], or scheme://*.domain for its subdomains" },
+                "allowed_methods": { "type": "array", "items": { "type": "string" } },
+                "allowed_headers": { "type": "array", "items": { "type": "string" } },
+                "exposed_headers": { "type": "array", "items": { "type": "string" } },
+                "max_age_secs": { "type": "integer", "format": "int64", "nullable": true },
+                "allow_credentials": { "type": "boolean", "description": "Needs listed origins, not \"*\"" },
+            },
+        },
+        "TransformUpload": {
+            "type": "object",
+            "required": ["samples"],
+            "properties": {
+                "request_script": { "type": "string", "maxLength": crate::transforms::MAX_SCRIPT_BYTES },
+                "response_script": { "type": "string", "maxLength": crate::transforms::MAX_SCRIPT_BYTES },
+                "samples": {
+                    "type": "array",
+                    "description": "Messages each script must transform within its limits, with the expected output",
+                    "items": {
+                        "type": "object",
+                        "required": ["direction"],
+                        "properties": {
+                            "direction": { "type": "string", "enum": ["request", "response"] },
+                            "method": { "type": "string" },
+                            "path": { "type": "string" },
+                            "headers": { "type": "object", "additionalProperties": { "type": "string" } },
+                            "body": {},
+                            "expect_headers": { "type": "object", "additionalProperties": { "type": "string" } },
+                            "expect_body": {},
+                        },
+                    },
+                },
+            },
+        },
+        "ScheduleRatesRequest": {
+            "type": "object",
+            "required": ["rates", "effective_at"],
+            "properties": {
+                "rates": schema_ref("CommissionRates"),
+                "effective_at": { "type": "integer", "format": "int64" },
+                "proposal_id": { "type": "string", "description": "Approved governance proposal; network rates only" },
+            },
+        },
+        "CommissionPayment": {
+            "type": "object",
+            "required": ["payment_id", "recipient_wallet", "amount", "token", "commission_type",
+                         "source_transaction", "timestamp"],
+            "properties": {
+                "payment_id": { "type": "string" },
+                "recipient_wallet": { "type": "string" },
+                "amount": { "type": "number", "format": "double" },
+                "token": { "type": "string" },
+                "commission_type": schema_ref("CommissionType"),
+                "source_transaction": { "type": "string" },
+                "timestamp": { "type": "integer", "format": "int64" },
+                "base_amount": { "type": "number", "format": "double", "nullable": true },
+                "exact": { "allOf": [schema_ref("TokenAmount")], "nullable": true },
+                "service_key": { "type": "string", "nullable": true },
+            },
+        },
+        "Pagination": {
+            "type": "object",
+            "required": ["page", "page_size", "total_items", "total_pages", "has_previous", "has_next"],
+            "properties": {
+                "page": { "type": "integer" },
+                "page_size": { "type": "integer" },
+                "total_items": { "type": "integer" },
+                "total_pages": { "type": "integer" },
+                "has_previous": { "type": "boolean" },
+                "has_next": { "type": "boolean" },
+            },
+        },
+        "PaymentPage": {
+            "type": "object",
+            "required": ["payments", "pagination", "totals_by_token"],
+            "properties": {
+                "payments": { "type": "array", "items": schema_ref("CommissionPayment") },
+                "pagination": schema_ref("Pagination"),
+                "from": { "type": "integer", "format": "int64", "nullable": true },
+                "to": { "type": "integer", "format": "int64", "nullable": true },
+                "commission_type": { "allOf": [schema_ref("CommissionType")], "nullable": true },
+                "totals_by_token": {
+                    "type": "object",
+                    "description": "Sum of the matching payments on every page",
+                    "additionalProperties": { "type": "number", "format": "double" },
+                },
+            },
+        },
+        "EarningsDashboard": {
+            "type": "object",
+            "properties": {
+                "wallet_address": { "type": "string" },
+                "earnings": { "type": "object" },
+                "referrals": { "type": "object" },
+                "tier_progress": { "type": "object", "nullable": true },
+                "recommendations": { "type": "array", "items": { "type": "object" } },
+                "referral_links": { "type": "array", "items": { "type": "object" } },
+                "payments": schema_ref("PaymentPage"),
+                "short_links": { "type": "array", "items": { "type": "object" } },
+                "service_health": { "type": "array", "items": { "type": "object" } },
+                "commission_rates": { "type": "object" },
+            },
+        },
+        "CommissionPaymentList": {
+            "type": "object",
+            "properties": {
+                "wallet_address": { "type": "string" },
+                "commissions": { "type": "array", "items": schema_ref("CommissionPayment") },
+            },
+        },
+        "EarningsAccount": {
+            "type": "object",
+            "properties": {
+                "wallet_address": { "type": "string" },
+                "total_earned_usdc": { "type": "number", "format": "double" },
+                "total_earned_solfunmeme": { "type": "number", "format": "double" },
+                "pending_withdrawals": { "type": "number", "format": "double" },
+                "total_withdrawn_usdc": { "type": "number", "format": "double" },
+                "lifetime_volume": { "type": "number", "format": "double" },
+                "referral_count": { "type": "integer" },
+                "tier": { "type": "string", "enum": ["Bronze", "Silver", "Gold", "Platinum"] },
+                "last_payout": { "type": "integer", "format": "int64" },
+                "earned_by_token": { "type": "object", "additionalProperties": schema_ref("TokenAmount") },
+            },
+        },
+        "EarningsAt": {
+            "type": "object",
+            "properties": {
+                "at": { "type": "integer", "format": "int64" },
+                "account": schema_ref("EarningsAccount"),
+            },
+        },
+        "WithdrawRequest": {
+            "type": "object",
+            "required": ["amount_usdc"],
+            "properties": { "amount_usdc": { "type": "number", "format": "double" } },
+        },
+        "ClawbackRequest": {
+            "type": "object",
+            "required": ["payment_id", "reason"],
+            "properties": { "payment_id": { "type": "string" }, "reason": { "type": "string" } },
+        },
+        "ApprovalSignature": {
+            "type": "object",
+            "required": ["approver", "signature"],
+            "properties": {
+                "approver": { "type": "string" },
+                "signature": { "type": "string", "description": "base58 ed25519 signature over the message from GET /approvals/{id}" },
+                "reason": { "type": "string" },
+            },
+        },
+        "UsageReceipt": {
+            "type": "object",
+            "required": ["receipt_id", "service_key", "consumer_wallet", "amount_usdc", "timestamp",
+                         "request_hash", "gateway_public_key", "signature"],
+            "properties": {
+                "receipt_id": { "type": "string" },
+                "service_key": { "type": "string" },
+                "consumer_wallet": { "type": "string" },
+                "amount_usdc": { "type": "number", "format": "double" },
+                "timestamp": { "type": "integer", "format": "int64" },
+                "request_hash": { "type": "string" },
+                "estimate_id": { "type": "string", "nullable": true },
+                "gateway_public_key": { "type": "string" },
+                "signature": { "type": "string" },
+            },
+        },
+        "ChallengeRequest": {
+            "type": "object",
+            "required": ["wallet_address"],
+            "properties": { "wallet_address": { "type": "string" } },
+        },
+        "AuthChallenge": {
+            "type": "object",
+            "properties": {
+                "challenge_id": { "type": "string" },
+                "wallet_address": { "type": "string" },
+                "message": { "type": "string" },
+                "expires_at": { "type": "integer", "format": "int64" },
+            },
+        },
+        "PricingTier": {
+            "type": "string",
+            "enum": ["Free", "Basic", "Premium", "Enterprise"],
+        },
+        "RegisterEndpointRequest": {
+            "type": "object",
+            "required": ["wallet_address", "user_id", "allocated_ports"],
+            "properties": {
+                "wallet_address": { "type": "string" },
+                "user_id": { "type": "string" },
+                "allocated_ports": { "type": "array", "items": { "type": "integer", "minimum": 0, "maximum": 65535 } },
+            },
+        },
+        "AddServiceRequest": {
+            "type": "object",
+            "required": ["wallet_address", "service_name", "libp2p_port"],
+            "properties": {
+                "wallet_address": { "type": "string" },
+                "service_name": { "type": "string" },
+                "libp2p_port": { "type": "integer", "minimum": 0, "maximum": 65535 },
+                "pricing_tier": schema_ref("PricingTier"),
+            },
+        },
+    });
+
+    // Added apart from the rest, which already fill json!'s recursion limit
+    schemas["ErasureRequest"] = json!({
+        "type": "object",
+        "required": ["pseudonym"],
+        "properties": { "pseudonym": { "type": "string", "description": "What the wallet becomes in ledger records" } },
+    });
+    schemas["RegisterServiceRequest"] = json!({
+        "type": "object",
+        "required": ["service_name", "libp2p_port"],
+        "properties": {
+            "service_name": { "type": "string" },
+            "libp2p_port": { "type": "integer", "minimum": 0, "maximum": 65535 },
+            "pricing_tier": schema_ref("PricingTier"),
+        },
+    });
+    schemas["ReferralStatusRequest"] = json!({
+        "type": "object",
+        "required": ["status"],
+        "properties": {
+            "status": { "type": "string", "enum": ["Active", "Inactive", "Suspended", "Graduated"],
+                        "description": "Inactive and Suspended referrals stop counting toward the referrer's tier" },
+        },
+    });
+    schemas["RefundPolicy"] = json!({
+        "type": "object",
+        "properties": { "dispute_window_secs": { "type": "integer", "minimum": 0, "maximum": 15552000 } },
+    });
+    schemas["RefundRequest"] = json!({
+        "type": "object",
+        "required": ["reason"],
+        "properties": {
+            "reason": { "type": "string" },
+            "amount": { "type": "number", "format": "double", "description": "In the payment's token; all that is left when omitted" },
+        },
+    });
+    schemas["PaymentRecord"] = json!({
+        "type": "object",
+        "properties": {
+            "payment_id": { "type": "string" },
+            "payer_wallet": { "type": "string" },
+            "amount": { "type": "number", "format": "double" },
+            "token": { "type": "string" },
+            "service_endpoint": { "type": "string" },
+            "timestamp": { "type": "integer" },
+            "status": { "type": "string", "enum": ["Pending", "Confirmed", "Failed", "Refunded"] },
+            "refunds": { "type": "array", "items": {
+                "type": "object",
+                "properties": {
+                    "refund_id": { "type": "string" },
+                    "kind": { "type": "string", "enum": ["Refund", "Chargeback"] },
+                    "amount": { "type": "number", "format": "double" },
+                    "reason": { "type": "string" },
+                    "refunded_at": { "type": "integer" },
+                    "clawed_back": { "type": "array", "items": { "type": "string" } },
+                },
+            } },
+        },
+    });
+    schemas["PaymentRefunds"] = json!({
+        "type": "object",
+        "properties": {
+            "payment": schema_ref("PaymentRecord"),
+            "refunded_amount": { "type": "number", "format": "double" },
+        },
+    });
+    schemas["JournalPage"] = json!({
+        "type": "object",
+        "properties": {
+            "last_seq": { "type": "integer" },
+            "entries": { "type": "array", "items": {
+                "type": "object",
+                "required": ["seq", "at", "event"],
+                "properties": {
+                    "seq": { "type": "integer" },
+                    "at": { "type": "integer" },
+                    "event": { "type": "string", "enum": [
+                        "carried_over", "wallet_registered", "service_registered", "payment_verified",
+                        "payment_recorded", "payment_refunded", "swap_executed", "referral_link_created",
+                        "referral_tracked", "referral_status_changed", "commission_paid", "earnings",
+                    ] },
+                },
+                "additionalProperties": true,
+            } },
+        },
+    });
+    let registers = json!({
+        "type": "object",
+        "additionalProperties": {
+            "type": "object",
+            "required": ["stamp"],
+            "properties": {
+                "stamp": {
+                    "type": "object",
+                    "properties": { "at_ms": { "type": "integer" }, "node_id": { "type": "string" } },
+                },
+                "value": { "type": "object", "nullable": true, "description": "null once deleted" },
+            },
+        },
+    });
+    schemas["StateGossip"] = json!({
+        "type": "object",
+        "required": ["node_id", "sent_at", "services", "referral_links", "short_links"],
+        "properties": {
+            "node_id": { "type": "string", "description": "Must be a trusted sync peer of the receiving node" },
+            "sent_at": { "type": "integer" },
+            "services": registers,
+            "referral_links": registers,
+            "short_links": registers,
+        },
+    });
+    schemas["TierChangeList"] = json!({
+        "type": "object",
+        "properties": {
+            "changes": { "type": "array", "items": {
+                "type": "object",
+                "properties": {
+                    "wallet_address": { "type": "string" },
+                    "from": { "type": "string" },
+                    "to": { "type": "string" },
+                    "referral_count": { "type": "integer" },
+                    "timestamp": { "type": "integer" },
+                },
+            } },
+        },
+    });
+    schemas
+}
+
+/// OpenAPI 3.0 document for every gateway route, for integrators to
+/// generate clients from. Served at /openapi.json.
+pub fn generate_openapi(domain: &str) -> Value {
+    let mut paths = Map::new();
+    for route in ROUTES {
+        let item = paths.entry(route.1).or_insert_with(|| json!({}));
+        item[route.0] = operation(route);
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "ZOS Public Gateway",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Wallet-addressed services, token swaps, payments and commissions. \
+                            Errors are {\"error\": \"...\"}; add X-ZOS-Sandbox: true to any call to use the sandbox.",
+            "license": { "name": "AGPL-3.0" },
+        },
+        "servers": [{ "url": format!("https://{}", domain) }],
+        "paths": paths,
+        "components": {
+            "schemas": schemas(),
+            "securitySchemes": {
+                "WalletAddress": { "type": "apiKey", "in": "header", "name": "X-Wallet-Address" },
+                "PaymentToken": { "type": "apiKey", "in": "header", "name": "X-Payment-Token" },
+                "ApiKey": { "type": "http", "scheme": "bearer", "description": "zos_{key_id}.{secret} from POST /auth/keys" },
+                "WalletChallenge": {
+                    "type": "apiKey", "in": "header", "name": "X-Wallet-Signature",
+                    "description": "Signature over a POST /auth/challenge message, with X-Wallet-Address and X-Auth-Challenge",
+                },
+                "OperatorKey": { "type": "apiKey", "in": "header", "name": "X-Operator-Key" },
+                "GovernanceKey": { "type": "apiKey", "in": "header", "name": "X-Governance-Key" },
+                "MetricsToken": { "type": "http", "scheme": "bearer", "description": "ZOS_METRICS_TOKEN; /metrics is open when it is unset" },
+            },
+        },
+    })
+}
@@ -0,0 +1,175 @@
+use serde::{Deserialize, Serialize};
+use crate::estimate::SignedEstimate;
+use crate::journal::GatewayEvent;
+use crate::receipts;
+use crate::trace_context::{OpenSpan, TraceParent};
+use crate::{HttpResponse, PublicGateway};
+use std::collections::HashMap;
+
+/// How the gateway treats a service's request and response bodies
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum BodyMode {
+    #[default]
+    Buffered,    // body handed to handle_http_request, available to mirroring and queueing
+    PassThrough, // gateway only looks at headers; the node streams the body to the backend
+}
+
+/// A call the gateway has let through: who made it, if they proved it,
+/// and the estimate it is charged at
+pub(crate) struct AuthorizedCall {
+    pub caller: Option<String>, // wallet behind a bearer API key or signed challenge
+    pub estimate: Option<SignedEstimate>,
+}
+
+/// Where a pass-through call goes once the gateway has authorized it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyTarget {
+    pub service_key: String,
+    pub libp2p_port: u16,
+    pub rest_path: String, // path after /{wallet}/{service}
+    pub response_headers: HashMap<String, String>, // to add to the backend's response
+    pub traceparent: String, // gateway's span, for the node to continue the trace under
+}
+
+impl PublicGateway {
+    pub fn set_body_mode(&mut self, service_key: &str, mode: BodyMode) -> Result<(), String> {
+        self.service_registry.get_mut(service_key)
+            .ok_or("Service not found")?
+            .body_mode = mode;
+        Ok(())
+    }
+
+    /// Rate limit, delisting, payment and estimate checks; everything a call
+    /// needs except the body. Ok(Err(response)) is a response to send as-is.
+    /// The caller is authenticated here, once per call, since a signed
+    /// challenge works only once.
+    pub(crate) fn authorize_service_call(&mut self, wallet_address: &str, service_name: &str,
+                                         headers: &HashMap<String, String>)
+                                         -> Result<Result<AuthorizedCall, HttpResponse>, String> {
+        if let Some(refusal) = self.check_rate_limits(wallet_address) {
+            return Ok(Err(refusal));
+        }
+        let caller = self.authenticate_wallet(headers).ok();
+
+        let service_key = format!("{}_{}", wallet_address, service_name);
+        if let Some(refusal) = self.check_nft_gate(&service_key, caller.as_deref()) {
+            return Ok(Err(refusal));
+        }
+        if let Some(refusal) = self.check_access_policies(&service_key, caller.as_deref()) {
+            return Ok(Err(refusal));
+        }
+
+        let service = self.service_registry.get(&service_key)
+            .ok_or("Service not found")?;
+
+        // Delisted after sustained health check failures
+        if self.is_delisted(&service_key) {
+            return Ok(Err(self.service_unavailable_response(&service_key)));
+        }
+
+        if service.payment_required {
+            let payment_header = headers.get("X-Payment-Token")
+                .ok_or("Payment required. Include X-Payment-Token header")?;
+
+            let verified = self.verify_payment(payment_header, &service.pricing);
+            self.metrics.record_payment_verification(verified.is_ok());
+            self.journal(GatewayEvent::PaymentVerified {
+                wallet_address: wallet_address.to_string(),
+                service_name: service_name.to_string(),
+                verified: verified.is_ok(),
+            });
+            verified?;
+            self.check_coupon(&service_key, caller.as_deref(), headers)?;
+        }
+
+        // Honor a signed estimate as the price for this call
+        let estimate = self.redeem_estimate(&service_key, caller.as_deref(), headers)?;
+        Ok(Ok(AuthorizedCall { caller, estimate }))
+    }
+
+    /// Authorize a call to a pass-through service from its path and headers
+    /// alone, so the node can stream the body to the backend without the
+    /// gateway buffering or parsing it. Mirroring needs the body and does not
+    /// apply to pass-through calls.
+    pub fn authorize_passthrough(&mut self, path: &str, headers: &HashMap<String, String>)
+                                 -> Result<Result<ProxyTarget, HttpResponse>, String> {
+        let mut parts = path.trim_start_matches('/').splitn(3, '/');
+        let (wallet_address, service_name) = match (parts.next(), parts.next()) {
+            (Some(wallet), Some(service)) if !wallet.is_empty() && !service.is_empty() => (wallet, service),
+            _ => return Err("Invalid path format. Expected: /{wallet}/{service}".to_string()),
+        };
+        let rest_path = format!("/{}", parts.next().unwrap_or(""));
+
+        if let Some(redirect) = self.session_redirect(path, headers) {
+            return Ok(Err(redirect));
+        }
+
+        // The gateway's part of a pass-through call is authorization only
+        let parent = TraceParent::from_headers(headers);
+        let trace = parent.as_ref().map(TraceParent::child).unwrap_or_else(TraceParent::root);
+        let span = OpenSpan::start(&trace, parent.as_ref(), &format!("authorize {}", path), "gateway");
+
+        let service_key = format!("{}_{}", wallet_address, service_name);
+        let service = self.service_registry.get(&service_key)
+            .ok_or("Service not found")?;
+        if service.body_mode != BodyMode::PassThrough {
+            return Err("Service is not in pass-through mode".to_string());
+        }
+        let libp2p_port = service.libp2p_port;
+        let payment_required = service.payment_required;
+        let pricing = service.pricing.clone();
+
+        let authorized = self.authorize_service_call(wallet_address, service_name, headers);
+        let span = match &authorized {
+            Ok(Ok(_)) => span.finish(None, None),
+            Ok(Err(response)) => span.finish(Some(response.status_code), None),
+            Err(e) => span.finish(None, Some(e.clone())),
+        };
+        self.traces.record(span, trace.sampled);
+        self.metrics.record_request(wallet_address, service_name, match &authorized {
+            Ok(Ok(_)) => 200,
+            Ok(Err(response)) => response.status_code,
+            Err(_) => 400,
+        });
+        let AuthorizedCall { caller, estimate } = match authorized? {
+            Ok(call) => call,
+            Err(response) => return Ok(Err(response)),
+        };
+
+        let mut response_headers = self.cors_response_headers(path, headers);
+        response_headers.insert("traceparent".to_string(), trace.header_value());
+        if let Some(estimate) = &estimate {
+            response_headers.insert("X-Estimate-Id".to_string(), estimate.estimate_id.clone());
+            response_headers.insert("X-Charge-USDC".to_string(), format!("{:.6}", estimate.total_usdc));
+        }
+
+        // The body never passes through the gateway, so the receipt covers
+        // the path and is charged at list price for an empty body
+        let charged = if payment_required {
+            let amount = receipts::call_charge(&pricing, 0, estimate.as_ref());
+            let (amount, redemption) = self.redeem_coupon(&service_key, caller.as_deref(), headers, amount)?;
+            let hash = receipts::request_hash("*", path, &[]);
+            let estimate_id = estimate.as_ref().map(|estimate| estimate.estimate_id.clone());
+            let receipt = self.issue_receipt(&service_key, headers, amount, hash, estimate_id)?;
+            response_headers.insert("X-Receipt-Id".to_string(), receipt.receipt_id);
+            if let Some(redemption) = redemption {
+                response_headers.insert("X-Coupon-Code".to_string(), redemption.code);
+                response_headers.insert("X-Discount-USDC".to_string(), format!("{:.6}", redemption.discount_usdc));
+            }
+            Some(receipt.amount_usdc)
+        } else {
+            None
+        };
+        self.record_call(&service_key, charged);
+        self.record_consumer_usage(&service_key, caller.as_deref(), 0, charged); // bytes go straight to the backend
+        self.meter_usage(&service_key, caller.as_deref(), 0, 0.0); // and so does the time spent answering
+
+        Ok(Ok(ProxyTarget {
+            service_key,
+            libp2p_port,
+            rest_path,
+            response_headers,
+            traceparent: trace.header_value(),
+        }))
+    }
+}
@@ -0,0 +1,150 @@
+use zos_approvals::{ApprovalNotice, ApprovalPolicy, ApprovalStatus};
+use crate::receipts::json_response;
+use crate::screening::is_operator;
+use crate::withdrawals::Payout;
+use crate::{HttpResponse, PublicGateway};
+use std::collections::HashMap;
+
+/// ZOS_PAYOUT_APPROVERS (comma separated admin wallets),
+/// ZOS_PAYOUT_APPROVALS_REQUIRED, ZOS_PAYOUT_APPROVAL_THRESHOLD_USDC and
+/// ZOS_PAYOUT_APPROVAL_EXPIRY_SECS. Without approvers every payout goes
+/// straight to the host.
+pub fn load_policy() -> ApprovalPolicy {
+    let env = |name: &str| std::env::var(name).ok();
+    let approvers: Vec<String> = env("ZOS_PAYOUT_APPROVERS").unwrap_or_default()
+        .split(',')
+        .map(|wallet| wallet.trim().to_string())
+        .filter(|wallet| !wallet.is_empty())
+        .collect();
+    let required = env("ZOS_PAYOUT_APPROVALS_REQUIRED").and_then(|v| v.parse().ok())
+        .unwrap_or(approvers.len().min(2))
+        .clamp(1, approvers.len().max(1));
+    ApprovalPolicy {
+        required,
+        threshold: env("ZOS_PAYOUT_APPROVAL_THRESHOLD_USDC").and_then(|v| v.parse().ok()).unwrap_or(1000.0),
+        token_thresholds: HashMap::new(),
+        expiry_secs: env("ZOS_PAYOUT_APPROVAL_EXPIRY_SECS").and_then(|v| v.parse().ok()).unwrap_or(2 * 86400).max(60),
+        approvers,
+    }
+}
+
+impl PublicGateway {
+    /// Hold a new payout for co-signing if it is over the approval threshold;
+    /// true when it was held
+    pub(crate) fn hold_for_approval(&mut self, payout: &mut Payout, now: u64) -> Result<bool, String> {
+        if !self.payout_approval_policy.requires_approval("USDC", payout.amount_usdc) {
+            return Ok(false);
+        }
+        let commission_system = self.commission_system.as_mut()
+            .ok_or("Commission system not initialized")?;
+        let summary = format!("Payout of {:.6} USDC to {}", payout.amount_usdc, payout.wallet_address);
+        let action = commission_system.payout_approvals.request(&self.payout_approval_policy, "payout", &payout.payout_id,
+                                                               "USDC", payout.amount_usdc, &summary, "payout_batch", now)?;
+        payout.approval_id = Some(action.action_id.clone());
+        payout.awaiting_approval = true;
+        println!("✍️  {} held for {} of {} approvers ({})", summary, action.required, action.approvers.len(), action.action_id);
+        Ok(true)
+    }
+
+    /// Held payouts whose approvals are in go to the host; ones declined or
+    /// left to expire fail, returning the funds to the wallet's balance
+    pub(crate) fn release_approved_payouts(&mut self, now: u64) -> Result<Vec<Payout>, String> {
+        let commission_system = self.commission_system.as_mut()
+            .ok_or("Commission system not initialized")?;
+        commission_system.payout_approvals.expire(now);
+
+        let mut released = Vec::new();
+        let mut refused = Vec::new();
+        let held: Vec<String> = commission_system.payouts.values()
+            .filter(|payout| payout.awaiting_approval)
+            .map(|payout| payout.payout_id.clone())
+            .collect();
+        for payout_id in held {
+            let Some(payout) = commission_system.payouts.get_mut(&payout_id) else { continue };
+            let approval_id = payout.approval_id.clone().unwrap_or_default();
+            match commission_system.payout_approvals.actions.get(&approval_id).map(|action| action.status) {
+                Some(ApprovalStatus::Pending) => {}
+                Some(ApprovalStatus::Approved) => {
+                    payout.awaiting_approval = false;
+                    released.push(payout.clone());
+                    commission_system.payout_approvals.mark_executed(&approval_id, "payout_batch", now)?;
+                }
+                status => refused.push((payout_id, format!("Payout approval {}", match status {
+                    Some(status) => format!("{:?}", status).to_lowercase(),
+                    None => "missing".to_string(),
+                }))),
+            }
+        }
+
+        for (payout_id, error) in refused {
+            self.fail_payout(&payout_id, &error)?;
+        }
+        Ok(released)
+    }
+
+    /// Approval requests, signatures and outcomes for the approvers' channel
+    /// (the Telegram bot); each is handed out once
+    pub fn take_approval_notices(&mut self) -> Vec<ApprovalNotice> {
+        self.commission_system.as_mut()
+            .map(|commission_system| commission_system.payout_approvals.take_notices())
+            .unwrap_or_default()
+    }
+
+    /// GET /approvals, GET /approvals/{id} (operator);
+    /// POST /approvals/{id}/approve and /approvals/{id}/decline with
+    /// {"approver", "signature", "reason"}, signed by an approver wallet
+    pub(crate) fn handle_approval_request(&mut self, path: &str, method: &str, headers: &HashMap<String, String>,
+                                          body: &[u8]) -> Result<HttpResponse, String> {
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        let now = chrono::Utc::now().timestamp() as u64;
+        let policy = self.payout_approval_policy.clone();
+        let commission_system = self.commission_system.as_mut()
+            .ok_or("Commission system not initialized")?;
+        let book = &mut commission_system.payout_approvals;
+
+        match (method, segments.as_slice()) {
+            ("GET", ["approvals", rest @ ..]) if rest.len() <= 1 => {
+                if !is_operator(headers) {
+                    return json_response(403, &serde_json::json!({ "error": "Approvals need X-Operator-Key" }));
+                }
+                book.expire(now);
+                match rest.first() {
+                    None => json_response(200, &serde_json::json!({
+                        "policy": policy,
+                        "pending": book.pending(),
+                    })),
+                    Some(action_id) => match book.actions.get(*action_id) {
+                        Some(action) => json_response(200, &serde_json::json!({
+                            "action": action,
+                            "approve_message": action.signing_message(true),
+                            "decline_message": action.signing_message(false),
+                            "audit": book.audit_trail(action_id),
+                        })),
+                        None => json_response(404, &serde_json::json!({ "error": "Approval request not found" })),
+                    },
+                }
+            }
+            ("POST", ["approvals", action_id, decision @ ("approve" | "decline")]) => {
+                let request: serde_json::Value = serde_json::from_slice(body)
+                    .map_err(|e| format!("Invalid approval: {}", e))?;
+                let approver = request["approver"].as_str().ok_or("approver is required")?;
+                let signature = request["signature"].as_str().ok_or("signature is required")?;
+                let reason = request["reason"].as_str().map(str::to_string);
+
+                match book.sign(action_id, approver, signature, *decision == "approve", reason, now) {
+                    Ok(action) => {
+                        println!("✍️  {} {}d {} ({} of {})", approver, decision, action.action_id,
+                                 action.approvals.len(), action.required);
+                        json_response(200, &serde_json::json!({
+                            "action": action,
+                            // Approved payouts go to the host with the next batch
+                            "released_with_next_batch": action.status == ApprovalStatus::Approved,
+                        }))
+                    }
+                    Err(e) => json_response(409, &serde_json::json!({ "error": e })),
+                }
+            }
+            _ => Err("Unsupported approval request".to_string()),
+        }
+    }
+}
@@ -0,0 +1,405 @@
+//! Minimal QR code encoder for short links: byte mode, error correction
+//! level M, versions 1-6 (up to 106 bytes), rendered as SVG or PNG.
+
+/// (total codewords, ecc codewords per block, block count) for versions 1-6 at level M
+const VERSIONS_M: [(usize, usize, usize); 6] = [
+    (26, 10, 1),
+    (44, 16, 1),
+    (70, 26, 1),
+    (100, 18, 2),
+    (134, 24, 2),
+    (172, 16, 4),
+];
+
+pub struct QrCode {
+    pub size: usize,
+    modules: Vec<Vec<bool>>,
+    is_function: Vec<Vec<bool>>,
+}
+
+impl QrCode {
+    pub fn encode(text: &str) -> Result<Self, String> {
+        let data = text.as_bytes();
+
+        let version = (1..=VERSIONS_M.len())
+            .find(|&v| {
+                let (total, ecc, blocks) = VERSIONS_M[v - 1];
+                (total - ecc * blocks) * 8 >= 4 + 8 + data.len() * 8
+            })
+            .ok_or(format!("Text too long for QR code: {} bytes (max 106)", data.len()))?;
+
+        let (total, ecc_len, block_count) = VERSIONS_M[version - 1];
+        let data_capacity = total - ecc_len * block_count;
+
+        // Byte mode segment, terminator and padding
+        let mut bits = BitBuffer::default();
+        bits.push(0b0100, 4);
+        bits.push(data.len() as u32, 8);
+        for &byte in data {
+            bits.push(byte as u32, 8);
+        }
+        let terminator = (data_capacity * 8 - bits.len()).min(4);
+        bits.push(0, terminator);
+        bits.push(0, (8 - bits.len() % 8) % 8);
+        for pad in [0xEC, 0x11].iter().cycle() {
+            if bits.len() >= data_capacity * 8 {
+                break;
+            }
+            bits.push(*pad, 8);
+        }
+
+        let codewords = interleave(&bits.to_bytes(), ecc_len, block_count);
+
+        let size = version * 4 + 17;
+        let mut qr = QrCode {
+            size,
+            modules: vec![vec![false; size]; size],
+            is_function: vec![vec![false; size]; size],
+        };
+        qr.draw_function_patterns(version);
+        qr.draw_codewords(&codewords);
+
+        let mask = (0..8)
+            .min_by_key(|&mask| {
+                qr.apply_mask(mask);
+                qr.draw_format_bits(mask);
+                let penalty = qr.penalty();
+                qr.apply_mask(mask); // XOR again to undo
+                penalty
+            })
+            .unwrap_or(0);
+        qr.apply_mask(mask);
+        qr.draw_format_bits(mask);
+
+        Ok(qr)
+    }
+
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.modules[y][x]
+    }
+
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y][x] = dark;
+        self.is_function[y][x] = true;
+    }
+
+    fn draw_function_patterns(&mut self, version: usize) {
+        for i in 0..self.size {
+            self.set_function(6, i, i % 2 == 0);
+            self.set_function(i, 6, i % 2 == 0);
+        }
+
+        let far = self.size as isize - 4;
+        for (cx, cy) in [(3, 3), (far, 3), (3, far)] {
+            for dy in -4..=4isize {
+                for dx in -4..=4isize {
+                    let (x, y) = (cx + dx, cy + dy);
+                    if (0..self.size as isize).contains(&x) && (0..self.size as isize).contains(&y) {
+                        let dist = dx.abs().max(dy.abs());
+                        self.set_function(x as usize, y as usize, dist != 2 && dist != 4);
+                    }
+                }
+            }
+        }
+
+        // Versions 2-6 have a single alignment pattern near the bottom right
+        if version >= 2 {
+            let center = self.size - 7;
+            for dy in -2..=2isize {
+                for dx in -2..=2isize {
+                    let dark = dx.abs().max(dy.abs()) != 1;
+                    self.set_function((center as isize + dx) as usize, (center as isize + dy) as usize, dark);
+                }
+            }
+        }
+
+        // Reserve the format areas; real bits are drawn after masking
+        self.draw_format_bits(0);
+    }
+
+    fn draw_format_bits(&mut self, mask: u32) {
+        // Level M is 0b00
+        let data = mask;
+        let mut rem = data;
+        for _ in 0..10 {
+            rem = (rem << 1) ^ ((rem >> 9) * 0x537);
+        }
+        let bits = ((data << 10) | rem) ^ 0x5412;
+        let bit = |i: usize| (bits >> i) & 1 != 0;
+
+        for i in 0..=5 {
+            self.set_function(8, i, bit(i));
+        }
+        self.set_function(8, 7, bit(6));
+        self.set_function(8, 8, bit(7));
+        self.set_function(7, 8, bit(8));
+        for i in 9..15 {
+            self.set_function(14 - i, 8, bit(i));
+        }
+
+        let size = self.size;
+        for i in 0..8 {
+            self.set_function(size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set_function(8, size - 15 + i, bit(i));
+        }
+        self.set_function(8, size - 8, true);
+    }
+
+    fn draw_codewords(&mut self, codewords: &[u8]) {
+        let total_bits = codewords.len() * 8;
+        let mut i = 0;
+        let mut right = self.size as isize - 1;
+
+        while right >= 1 {
+            if right == 6 {
+                right = 5;
+            }
+            for vert in 0..self.size {
+                for j in 0..2 {
+                    let x = (right - j) as usize;
+                    let upward = (right + 1) & 2 == 0;
+                    let y = if upward { self.size - 1 - vert } else { vert };
+                    if !self.is_function[y][x] && i < total_bits {
+                        self.modules[y][x] = (codewords[i / 8] >> (7 - i % 8)) & 1 != 0;
+                        i += 1;
+                    }
+                }
+            }
+            right -= 2;
+        }
+    }
+
+    fn apply_mask(&mut self, mask: u32) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let invert = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                if invert && !self.is_function[y][x] {
+                    self.modules[y][x] = !self.modules[y][x];
+                }
+            }
+        }
+    }
+
+    /// Runs, 2x2 blocks and dark/light balance (finder-like pattern rule omitted)
+    fn penalty(&self) -> usize {
+        let size = self.size;
+        let mut penalty = 0;
+
+        for horizontal in [true, false] {
+            for a in 0..size {
+                let mut run = 1;
+                for b in 1..size {
+                    let (cur, prev) = if horizontal {
+                        (self.modules[a][b], self.modules[a][b - 1])
+                    } else {
+                        (self.modules[b][a], self.modules[b - 1][a])
+                    };
+                    if cur == prev {
+                        run += 1;
+                        if run == 5 {
+                            penalty += 3;
+                        } else if run > 5 {
+                            penalty += 1;
+                        }
+                    } else {
+                        run = 1;
+                    }
+                }
+            }
+        }
+
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let c = self.modules[y][x];
+                if c == self.modules[y][x + 1] && c == self.modules[y + 1][x] && c == self.modules[y + 1][x + 1] {
+                    penalty += 3;
+                }
+            }
+        }
+
+        let dark = self.modules.iter().flatten().filter(|&&m| m).count();
+        let percent = dark * 100 / (size * size);
+        penalty += (percent.abs_diff(50) / 5) * 10;
+
+        penalty
+    }
+
+    pub fn to_svg(&self, module_px: usize) -> String {
+        let quiet = 4;
+        let dim = self.size + quiet * 2;
+        let mut path = String::new();
+
+        for y in 0..self.size {
+            for x in 0..self.size {
+                if self.modules[y][x] {
+                    path.push_str(&format!("M{},{}h1v1h-1z", x + quiet, y + quiet));
+                }
+            }
+        }
+
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {dim} {dim}\" width=\"{px}\" height=\"{px}\" shape-rendering=\"crispEdges\">\
+<rect width=\"100%\" height=\"100%\" fill=\"#fff\"/><path d=\"{path}\" fill=\"#000\"/></svg>",
+            dim = dim,
+            px = dim * module_px,
+            path = path
+        )
+    }
+
+    /// 8-bit grayscale PNG with stored (uncompressed) deflate blocks
+    pub fn to_png(&self, module_px: usize) -> Vec<u8> {
+        let quiet = 4;
+        let width = (self.size + quiet * 2) * module_px;
+
+        let mut raw = Vec::with_capacity((width + 1) * width);
+        for py in 0..width {
+            raw.push(0); // filter: none
+            for px in 0..width {
+                let (mx, my) = (px / module_px, py / module_px);
+                let dark = mx >= quiet && my >= quiet && mx < self.size + quiet && my < self.size + quiet
+                    && self.modules[my - quiet][mx - quiet];
+                raw.push(if dark { 0 } else { 255 });
+            }
+        }
+
+        let mut zlib = vec![0x78, 0x01];
+        let chunks: Vec<&[u8]> = raw.chunks(65535).collect();
+        for (i, chunk) in chunks.iter().enumerate() {
+            zlib.push(if i + 1 == chunks.len() { 1 } else { 0 });
+            let len = chunk.len() as u16;
+            zlib.extend_from_slice(&len.to_le_bytes());
+            zlib.extend_from_slice(&(!len).to_le_bytes());
+            zlib.extend_from_slice(chunk);
+        }
+        zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+        let mut ihdr = Vec::new();
+        ihdr.extend_from_slice(&(width as u32).to_be_bytes());
+        ihdr.extend_from_slice(&(width as u32).to_be_bytes());
+        ihdr.extend_from_slice(&[8, 0, 0, 0, 0]);
+
+        let mut png = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+        for (kind, data) in [(b"IHDR", ihdr), (b"IDAT", zlib), (b"IEND", Vec::new())] {
+            png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            let mut crc_input = kind.to_vec();
+            crc_input.extend_from_slice(&data);
+            png.extend_from_slice(&crc_input);
+            png.extend_from_slice(&crc32(&crc_input).to_be_bytes());
+        }
+
+        png
+    }
+}
+
+#[derive(Default)]
+struct BitBuffer {
+    bits: Vec<bool>,
+}
+
+impl BitBuffer {
+    fn push(&mut self, value: u32, count: usize) {
+        for i in (0..count).rev() {
+            self.bits.push((value >> i) & 1 != 0);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.bits.len()
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        self.bits
+            .chunks(8)
+            .map(|byte| byte.iter().fold(0u8, |acc, &bit| (acc << 1) | bit as u8))
+            .collect()
+    }
+}
+
+/// Split data into equal blocks, append Reed-Solomon ecc, and interleave
+fn interleave(data: &[u8], ecc_len: usize, block_count: usize) -> Vec<u8> {
+    let divisor = rs_divisor(ecc_len);
+    let block_len = data.len() / block_count;
+    let blocks: Vec<&[u8]> = data.chunks(block_len).collect();
+    let eccs: Vec<Vec<u8>> = blocks.iter().map(|block| rs_remainder(block, &divisor)).collect();
+
+    let mut result = Vec::new();
+    for i in 0..block_len {
+        for block in &blocks {
+            result.push(block[i]);
+        }
+    }
+    for i in 0..ecc_len {
+        for ecc in &eccs {
+            result.push(ecc[i]);
+        }
+    }
+    result
+}
+
+fn gf_mul(x: u8, y: u8) -> u8 {
+    let mut z: u32 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x11D);
+        z ^= ((y as u32 >> i) & 1) * x as u32;
+    }
+    z as u8
+}
+
+fn rs_divisor(degree: usize) -> Vec<u8> {
+    let mut result = vec![0u8; degree];
+    result[degree - 1] = 1;
+    let mut root = 1u8;
+    for _ in 0..degree {
+        for j in 0..degree {
+            result[j] = gf_mul(result[j], root);
+            if j + 1 < degree {
+                result[j] ^= result[j + 1];
+            }
+        }
+        root = gf_mul(root, 0x02);
+    }
+    result
+}
+
+fn rs_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut result = vec![0u8; divisor.len()];
+    for &byte in data {
+        let factor = byte ^ result.remove(0);
+        result.push(0);
+        for (r, &d) in result.iter_mut().zip(divisor) {
+            *r ^= gf_mul(d, factor);
+        }
+    }
+    result
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
@@ -0,0 +1,240 @@
+use serde::{Deserialize, Serialize};
+use crate::receipts::json_response;
+use crate::{HttpResponse, PublicGateway, QuoteCache};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuoteCacheConfig {
+    pub max_entries: usize,
+    pub ttl_secs: u64,
+    pub sweep_interval_secs: u64,
+}
+
+impl QuoteCacheConfig {
+    /// ZOS_QUOTE_CACHE_MAX, ZOS_QUOTE_TTL_SECS and ZOS_QUOTE_SWEEP_SECS
+    pub fn load() -> Self {
+        let env = |name: &str, default: u64| {
+            std::env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+        };
+        Self {
+            max_entries: env("ZOS_QUOTE_CACHE_MAX", 10_000).max(1) as usize,
+            ttl_secs: env("ZOS_QUOTE_TTL_SECS", 30).max(1),
+            sweep_interval_secs: env("ZOS_QUOTE_SWEEP_SECS", 60).max(1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct QuoteCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub expired: u64, // found stale on lookup
+    pub swept: u64,   // removed stale by a sweep
+    pub evicted: u64, // removed live because the cache was full
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    quote: QuoteCache,
+    used: u64, // recency tick
+}
+
+/// Quotes by request, bounded: stale quotes go first, then the least
+/// recently used. Quotes only live for seconds, so they aren't persisted.
+#[derive(Debug, Clone)]
+pub struct QuoteCacheStore {
+    entries: HashMap<String, Entry>,
+    recency: BTreeMap<u64, String>, // tick -> key, oldest first
+    tick: u64,
+    last_sweep: u64,
+    pub config: QuoteCacheConfig,
+    pub stats: QuoteCacheStats,
+}
+
+impl Default for QuoteCacheStore {
+    fn default() -> Self {
+        Self::new(QuoteCacheConfig::load())
+    }
+}
+
+impl QuoteCacheStore {
+    pub fn new(config: QuoteCacheConfig) -> Self {
+        Self {
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            tick: 0,
+            last_sweep: 0,
+            config,
+            stats: QuoteCacheStats::default(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn touch(&mut self, key: &str) {
+        self.tick += 1;
+        if let Some(entry) = self.entries.get_mut(key) {
+            self.recency.remove(&entry.used);
+            entry.used = self.tick;
+            self.recency.insert(self.tick, key.to_string());
+        }
+    }
+
+    fn remove(&mut self, key: &str) -> Option<QuoteCache> {
+        let entry = self.entries.remove(key)?;
+        self.recency.remove(&entry.used);
+        Some(entry.quote)
+    }
+
+    /// A quote still valid at `now`; stale ones are dropped on the way
+    pub fn get(&mut self, key: &str, now: u64) -> Option<QuoteCache> {
+        match self.entries.get(key) {
+            Some(entry) if entry.quote.expires_at > now => {
+                let quote = entry.quote.clone();
+                self.touch(key);
+                self.stats.hits += 1;
+                Some(quote)
+            }
+            Some(_) => {
+                self.remove(key);
+                self.stats.expired += 1;
+                self.stats.misses += 1;
+                None
+            }
+            None => {
+                self.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    pub fn insert(&mut self, key: String, quote: QuoteCache, now: u64) {
+        if now >= self.last_sweep + self.config.sweep_interval_secs {
+            self.sweep(now);
+        }
+        self.remove(&key);
+        if self.entries.len() >= self.config.max_entries {
+            // Full: reclaim stale quotes before giving up live ones
+            self.sweep(now);
+        }
+        while self.entries.len() >= self.config.max_entries {
+            let Some((_, oldest)) = self.recency.pop_first() else { break };
+            self.entries.remove(&oldest);
+            self.stats.evicted += 1;
+        }
+
+        self.tick += 1;
+        self.recency.insert(self.tick, key.clone());
+        self.entries.insert(key, Entry { quote, used: self.tick });
+    }
+
+    /// Drop every quote expired at `now`; returns how many
+    pub fn sweep(&mut self, now: u64) -> usize {
+        let stale: Vec<String> = self.entries.iter()
+            .filter(|(_, entry)| entry.quote.expires_at <= now)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &stale {
+            self.remove(key);
+        }
+        self.stats.swept += stale.len() as u64;
+        self.last_sweep = now;
+        stale.len()
+    }
+
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.stats.hits + self.stats.misses;
+        if lookups > 0 { self.stats.hits as f64 / lookups as f64 } else { 0.0 }
+    }
+}
+
+impl PublicGateway {
+    /// Sweep expired quotes; for hosts to call on a timer
+    pub fn sweep_quote_cache(&mut self) -> usize {
+        let now = chrono::Utc::now().timestamp() as u64;
+        self.payment_processor.quote_cache.sweep(now)
+    }
+
+    /// GET /quote/stats
+    pub(crate) fn handle_quote_stats_request(&self) -> Result<HttpResponse, String> {
+        let cache = &self.payment_processor.quote_cache;
+        json_response(200, &serde_json::json!({
+            "size": cache.len(),
+            "max_entries": cache.config.max_entries,
+            "ttl_secs": cache.config.ttl_secs,
+            "hit_rate": cache.hit_rate(),
+            "stats": cache.stats,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(max_entries: usize) -> QuoteCacheStore {
+        QuoteCacheStore::new(QuoteCacheConfig { max_entries, ttl_secs: 30, sweep_interval_secs: 60 })
+    }
+
+    fn quote(expires_at: u64) -> QuoteCache {
+        QuoteCache {
+            from_token: "SOLFUNMEME".to_string(),
+            to_token: "USDC".to_string(),
+            amount: 100.0,
+            quoted_price: 0.5,
+            expires_at,
+            slippage: 0.1,
+        }
+    }
+
+    #[test]
+    fn test_stale_quotes_miss_and_are_dropped() {
+        let mut cache = cache(10);
+        cache.insert("q1".to_string(), quote(130), 100);
+        assert!(cache.get("q1", 129).is_some());
+        assert!(cache.get("q1", 130).is_none());
+        assert!(cache.is_empty());
+        assert!(cache.get("q2", 130).is_none());
+        assert_eq!((cache.stats.hits, cache.stats.misses, cache.stats.expired), (1, 2, 1));
+        assert_eq!(cache.hit_rate(), 1.0 / 3.0);
+    }
+
+    #[test]
+    fn test_full_cache_sweeps_stale_before_evicting_least_recently_used() {
+        let mut cache = cache(3);
+        cache.insert("stale".to_string(), quote(105), 100);
+        cache.insert("old".to_string(), quote(200), 100);
+        cache.insert("recent".to_string(), quote(200), 100);
+
+        // Full: the stale quote goes first, however recently used
+        assert!(cache.get("stale", 104).is_some());
+        cache.insert("new1".to_string(), quote(200), 110);
+        assert_eq!(cache.stats.swept, 1);
+        assert_eq!(cache.stats.evicted, 0);
+
+        // Then the least recently used live one
+        assert!(cache.get("old", 111).is_some());
+        cache.insert("new2".to_string(), quote(200), 112);
+        assert_eq!(cache.stats.evicted, 1);
+        assert_eq!(cache.len(), 3);
+        assert!(cache.get("recent", 113).is_none());
+        assert!(cache.get("old", 113).is_some());
+    }
+
+    #[test]
+    fn test_reinserting_a_key_replaces_it() {
+        let mut cache = cache(2);
+        cache.insert("q1".to_string(), quote(130), 100);
+        cache.insert("q1".to_string(), quote(160), 120);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get("q1", 140).unwrap().expires_at, 160);
+        assert_eq!(cache.sweep(160), 1);
+        assert!(cache.is_empty());
+    }
+}
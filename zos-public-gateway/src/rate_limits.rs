@@ -0,0 +1,252 @@
+use serde::{Deserialize, Serialize};
+use crate::{HttpResponse, PublicGateway, RateLimit};
+use std::collections::{HashMap, VecDeque};
+
+const MINUTE_SECS: u64 = 60;
+const HOUR_SECS: u64 = 3600;
+const BANDWIDTH_WINDOW_SECS: u64 = 10; // bandwidth is averaged over this many seconds
+
+/// A wallet's recent traffic as sliding windows: every request time over
+/// the last hour, and every response size over the bandwidth window
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UsageStats {
+    pub requests: VecDeque<u64>, // oldest first
+    pub transfers: VecDeque<(u64, u64)>, // (time, bytes), oldest first
+}
+
+/// Which limit a call ran into and when it may try again
+#[derive(Debug, Clone, Serialize)]
+pub struct RateLimitExceeded {
+    pub limit: &'static str,
+    pub allowed: f64,
+    pub current: f64,
+    pub retry_after_secs: u64,
+}
+
+/// Seconds until enough of the oldest entries leave `window` to bring the
+/// in-window amount under `allowed`
+fn retry_after<'a>(entries: impl Iterator<Item = (&'a u64, f64)>, mut current: f64,
+                   allowed: f64, window: u64, now: u64) -> u64 {
+    for (time, amount) in entries {
+        current -= amount;
+        if current < allowed {
+            return (time + window).saturating_sub(now).max(1);
+        }
+    }
+    1
+}
+
+impl UsageStats {
+    fn prune(&mut self, now: u64) {
+        while self.requests.front().is_some_and(|time| time + HOUR_SECS <= now) {
+            self.requests.pop_front();
+        }
+        while self.transfers.front().is_some_and(|(time, _)| time + BANDWIDTH_WINDOW_SECS <= now) {
+            self.transfers.pop_front();
+        }
+    }
+
+    fn requests_within(&self, window: u64, now: u64) -> impl Iterator<Item = &u64> {
+        self.requests.iter().filter(move |time| *time + window > now)
+    }
+
+    /// Requests in the minute and hour up to `now`
+    pub fn request_counts(&self, now: u64) -> (u32, u32) {
+        (self.requests_within(MINUTE_SECS, now).count() as u32,
+         self.requests_within(HOUR_SECS, now).count() as u32)
+    }
+
+    /// Average response bandwidth over the window up to `now`, in megabits per second
+    pub fn bandwidth_mbps(&self, now: u64) -> f64 {
+        let bytes: u64 = self.transfers.iter()
+            .filter(|(time, _)| time + BANDWIDTH_WINDOW_SECS > now)
+            .map(|(_, bytes)| bytes)
+            .sum();
+        bytes as f64 * 8.0 / 1_000_000.0 / BANDWIDTH_WINDOW_SECS as f64
+    }
+
+    /// The first limit another request would break, if any
+    pub fn check(&self, limits: &RateLimit, now: u64) -> Option<RateLimitExceeded> {
+        let (minute, hour) = self.request_counts(now);
+        for (limit, count, allowed, window) in [
+            ("requests_per_minute", minute, limits.requests_per_minute, MINUTE_SECS),
+            ("requests_per_hour", hour, limits.requests_per_hour, HOUR_SECS),
+        ] {
+            if count >= allowed {
+                let oldest = self.requests_within(window, now).map(|time| (time, 1.0));
+                return Some(RateLimitExceeded {
+                    limit,
+                    allowed: allowed as f64,
+                    current: count as f64,
+                    retry_after_secs: retry_after(oldest, count as f64, allowed as f64, window, now),
+                });
+            }
+        }
+
+        let mbps = self.bandwidth_mbps(now);
+        if mbps >= limits.bandwidth_limit_mbps {
+            let per_byte = 8.0 / 1_000_000.0 / BANDWIDTH_WINDOW_SECS as f64;
+            let oldest = self.transfers.iter()
+                .filter(|(time, _)| time + BANDWIDTH_WINDOW_SECS > now)
+                .map(|(time, bytes)| (time, *bytes as f64 * per_byte));
+            return Some(RateLimitExceeded {
+                limit: "bandwidth_limit_mbps",
+                allowed: limits.bandwidth_limit_mbps,
+                current: mbps,
+                retry_after_secs: retry_after(oldest, mbps, limits.bandwidth_limit_mbps, BANDWIDTH_WINDOW_SECS, now),
+            });
+        }
+        None
+    }
+}
+
+impl RateLimitExceeded {
+    pub fn response(&self) -> HttpResponse {
+        let body = serde_json::json!({
+            "error": format!("Rate limit exceeded: {}", self.limit),
+            "limit": self.limit,
+            "allowed": self.allowed,
+            "current": self.current,
+            "retry_after_secs": self.retry_after_secs,
+        });
+
+        HttpResponse {
+            status_code: 429,
+            headers: HashMap::from([
+                ("Content-Type".to_string(), "application/json".to_string()),
+                ("Retry-After".to_string(), self.retry_after_secs.to_string()),
+            ]),
+            body: serde_json::to_vec(&body).unwrap_or_default(),
+        }
+    }
+}
+
+impl PublicGateway {
+    fn wallet_limits(&self, wallet_address: &str) -> &RateLimit {
+        self.rate_limiter.per_wallet_limits
+            .get(wallet_address)
+            .unwrap_or(&self.rate_limiter.global_limits)
+    }
+
+    /// Count a call against the wallet's limits, or a 429 with Retry-After
+    /// when it would go over. Request counts are cluster-wide while peers are
+    /// reachable, this node's own sliding windows when partitioned;
+    /// bandwidth is always this node's.
+    pub(crate) fn check_rate_limits(&mut self, wallet_address: &str) -> Option<HttpResponse> {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let limits = self.wallet_limits(wallet_address).clone();
+        let usage = self.rate_limiter.current_usage
+            .entry(wallet_address.to_string())
+            .or_default();
+        usage.prune(now);
+
+        let exceeded = if self.cluster_limiter.is_connected(now) {
+            // Cluster counters are fixed windows; they free up when the window turns
+            let (minute, hour) = self.cluster_limiter.cluster_usage(wallet_address, now);
+            if minute >= limits.requests_per_minute {
+                Some(RateLimitExceeded {
+                    limit: "requests_per_minute",
+                    allowed: limits.requests_per_minute as f64,
+                    current: minute as f64,
+                    retry_after_secs: MINUTE_SECS - now % MINUTE_SECS,
+                })
+            } else if hour >= limits.requests_per_hour {
+                Some(RateLimitExceeded {
+                    limit: "requests_per_hour",
+                    allowed: limits.requests_per_hour as f64,
+                    current: hour as f64,
+                    retry_after_secs: HOUR_SECS - now % HOUR_SECS,
+                })
+            } else {
+                let bandwidth = RateLimit { requests_per_minute: u32::MAX, requests_per_hour: u32::MAX, ..limits };
+                usage.check(&bandwidth, now)
+            }
+        } else {
+            usage.check(&limits, now)
+        };
+        if let Some(exceeded) = exceeded {
+            println!("🚦 {} over {} (retry in {}s)", wallet_address, exceeded.limit, exceeded.retry_after_secs);
+            self.metrics.record_rate_limit_rejection(exceeded.limit);
+            return Some(exceeded.response());
+        }
+
+        usage.requests.push_back(now);
+        if self.cluster_limiter.enabled {
+            self.cluster_limiter.record_local(wallet_address, now);
+        }
+        None
+    }
+
+    /// Count bytes sent back for a wallet's service against its bandwidth limit
+    pub(crate) fn record_bandwidth(&mut self, wallet_address: &str, bytes: usize) {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let usage = self.rate_limiter.current_usage
+            .entry(wallet_address.to_string())
+            .or_default();
+        usage.prune(now);
+        usage.transfers.push_back((now, bytes as u64));
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(requests_per_minute: u32, requests_per_hour: u32) -> RateLimit {
+        RateLimit { requests_per_minute, requests_per_hour, bandwidth_limit_mbps: 1.0 }
+    }
+
+    #[test]
+    fn test_request_windows_roll_over() {
+        let mut usage = UsageStats::default();
+        let start = 1_000_000;
+        usage.requests.extend([start, start + 10, start + 20]);
+
+        let per_minute = limits(3, 100);
+        let exceeded = usage.check(&per_minute, start + 30).unwrap();
+        assert_eq!(exceeded.limit, "requests_per_minute");
+        assert_eq!(exceeded.retry_after_secs, 30); // the first request leaves the minute at start + 60
+
+        // Once the oldest request is a minute old it no longer counts
+        assert_eq!(usage.request_counts(start + 60), (2, 3));
+        assert!(usage.check(&per_minute, start + 60).is_none());
+
+        // The hour window holds them until each is an hour old, then prune drops them
+        let per_hour = limits(100, 3);
+        assert_eq!(usage.check(&per_hour, start + 3599).unwrap().limit, "requests_per_hour");
+        assert!(usage.check(&per_hour, start + 3600).is_none());
+        usage.prune(start + 3620);
+        assert!(usage.requests.is_empty());
+    }
+
+    #[test]
+    fn test_bandwidth_window_rolls_over() {
+        let mut usage = UsageStats::default();
+        usage.transfers.push_back((500, 2_000_000)); // 16 Mbit over a 10 s window: 1.6 Mbps
+        assert_eq!(usage.check(&limits(100, 100), 505).unwrap().limit, "bandwidth_limit_mbps");
+        assert!(usage.check(&limits(100, 100), 510).is_none());
+    }
+
+    #[test]
+    fn test_per_wallet_limits_override_global() {
+        let mut gateway = PublicGateway::new("gateway.test");
+        gateway.rate_limiter.global_limits = limits(2, 100);
+        gateway.rate_limiter.per_wallet_limits.insert("premium".to_string(), limits(5, 100));
+
+        for _ in 0..2 {
+            assert!(gateway.check_rate_limits("basic").is_none());
+        }
+        let refused = gateway.check_rate_limits("basic").unwrap();
+        assert_eq!(refused.status_code, 429);
+        assert!(refused.headers.contains_key("Retry-After"));
+
+        // Each wallet has its own window, and premium its own limit
+        for _ in 0..5 {
+            assert!(gateway.check_rate_limits("premium").is_none());
+        }
+        assert!(gateway.check_rate_limits("premium").is_some());
+        assert_eq!(gateway.rate_limiter.current_usage["basic"].requests.len(), 2);
+    }
+}
@@ -0,0 +1,196 @@
+use serde::{Deserialize, Serialize};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use sha2::{Digest, Sha256};
+use crate::estimate::{usage_subtotal, EstimateRequest, SignedEstimate};
+use crate::{HttpResponse, PricingConfig, PublicGateway};
+use std::collections::{HashMap, VecDeque};
+
+/// Receipts issued for paid calls, kept so consumers can fetch one again by
+/// id. The receipt itself is the evidence; the ledger is only a convenience.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReceiptLedger {
+    pub retention: usize, // most recent receipts kept for GET /receipts/{id}
+    pub issued: VecDeque<UsageReceipt>,
+    #[serde(default)]
+    pub issued_total: u64, // sequence for receipt ids
+}
+
+impl Default for ReceiptLedger {
+    fn default() -> Self {
+        Self {
+            retention: 10_000,
+            issued: VecDeque::new(),
+            issued_total: 0,
+        }
+    }
+}
+
+/// Signed statement of one charge. The signature covers the JSON of the
+/// receipt with `signature` set to "", fields in declaration order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageReceipt {
+    pub receipt_id: String,
+    pub service_key: String,
+    pub consumer_wallet: String,
+    pub amount_usdc: f64,
+    pub timestamp: u64,
+    pub request_hash: String, // sha256 hex of "METHOD path\n" and the body; pass-through: "* path\n" only
+    pub estimate_id: Option<String>,
+    pub gateway_public_key: String, // hex ed25519 key that signed this receipt
+    pub signature: String, // hex ed25519 signature
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub(crate) fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// ZOS_RECEIPT_SIGNING_KEY (hex seed), else a fixed development key
+pub fn receipt_signing_key() -> SigningKey {
+    let seed: [u8; 32] = std::env::var("ZOS_RECEIPT_SIGNING_KEY")
+        .ok()
+        .and_then(|hex| from_hex(hex.trim()))
+        .and_then(|bytes| bytes.try_into().ok())
+        .unwrap_or_else(|| Sha256::digest(b"zos-dev-receipt-key").into());
+    SigningKey::from_bytes(&seed)
+}
+
+/// Signs sandbox receipts. Deliberately well known: a sandbox receipt must
+/// never verify against the real gateway key.
+pub fn sandbox_signing_key() -> SigningKey {
+    SigningKey::from_bytes(&Sha256::digest(b"zos-sandbox-receipt-key").into())
+}
+
+pub fn gateway_public_key() -> String {
+    to_hex(receipt_signing_key().verifying_key().as_bytes())
+}
+
+pub fn request_hash(method: &str, path: &str, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(format!("{} {}\n", method, path).as_bytes());
+    hasher.update(body);
+    to_hex(&hasher.finalize())
+}
+
+/// What a call was charged: the redeemed estimate's total, else list price
+/// for one request of this body size
+pub fn call_charge(pricing: &PricingConfig, body_len: usize, estimate: Option<&SignedEstimate>) -> f64 {
+    match estimate {
+        Some(estimate) => estimate.total_usdc,
+        None => usage_subtotal(pricing, &EstimateRequest {
+            payload_mb: body_len as f64 / (1024.0 * 1024.0),
+            duration_secs: 0.0,
+            requests: 1,
+        }),
+    }
+}
+
+fn signing_payload(receipt: &UsageReceipt) -> Result<Vec<u8>, String> {
+    let mut unsigned = receipt.clone();
+    unsigned.signature = String::new();
+    serde_json::to_vec(&unsigned)
+        .map_err(|e| format!("Failed to serialize receipt: {}", e))
+}
+
+pub fn sign_receipt(key: &SigningKey, receipt: &UsageReceipt) -> Result<String, String> {
+    Ok(to_hex(&key.sign(&signing_payload(receipt)?).to_bytes()))
+}
+
+/// Check a receipt against a gateway public key (hex) obtained out of band,
+/// not against the key the receipt claims for itself
+pub fn verify_receipt(public_key: &str, receipt: &UsageReceipt) -> Result<(), String> {
+    let key_bytes: [u8; 32] = from_hex(public_key)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or("Public key must be 32 bytes of hex")?;
+    let verifying_key = VerifyingKey::from_bytes(&key_bytes)
+        .map_err(|e| format!("Invalid public key: {}", e))?;
+
+    let signature_bytes: [u8; 64] = from_hex(&receipt.signature)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or("Signature must be 64 bytes of hex")?;
+
+    verifying_key
+        .verify(&signing_payload(receipt)?, &Signature::from_bytes(&signature_bytes))
+        .map_err(|_| "Receipt signature does not match".to_string())
+}
+
+pub(crate) fn json_response(status_code: u16, value: &impl Serialize) -> Result<HttpResponse, String> {
+    let body = serde_json::to_vec(value)
+        .map_err(|e| format!("Failed to serialize response: {}", e))?;
+
+    Ok(HttpResponse {
+        status_code,
+        headers: HashMap::from([
+            ("Content-Type".to_string(), "application/json".to_string()),
+        ]),
+        body,
+    })
+}
+
+impl PublicGateway {
+    /// Sign and record a receipt for a charge that has just been taken
+    pub(crate) fn issue_receipt(&mut self, service_key: &str, headers: &HashMap<String, String>,
+                                amount_usdc: f64, request_hash: String,
+                                estimate_id: Option<String>) -> Result<UsageReceipt, String> {
+        let key = self.receipt_key();
+        let now = chrono::Utc::now().timestamp() as u64;
+
+        let mut receipt = UsageReceipt {
+            receipt_id: format!("{}rcpt_{}_{}", if self.sandbox_mode { "sandbox_" } else { "" },
+                                now, self.receipts.issued_total),
+            service_key: service_key.to_string(),
+            consumer_wallet: headers.get("X-Wallet-Address").cloned().unwrap_or_default(),
+            amount_usdc: (amount_usdc * 1_000_000.0).round() / 1_000_000.0, // USDC has 6 decimals
+            timestamp: now,
+            request_hash,
+            estimate_id,
+            gateway_public_key: to_hex(key.verifying_key().as_bytes()),
+            signature: String::new(),
+        };
+        receipt.signature = sign_receipt(&key, &receipt)?;
+
+        self.receipts.issued_total += 1;
+        self.receipts.issued.push_back(receipt.clone());
+        while self.receipts.issued.len() > self.receipts.retention {
+            self.receipts.issued.pop_front();
+        }
+
+        Ok(receipt)
+    }
+
+    pub(crate) fn receipt_key(&self) -> SigningKey {
+        if self.sandbox_mode { sandbox_signing_key() } else { receipt_signing_key() }
+    }
+
+    /// GET /receipts/public-key, GET /receipts/{id}, POST /receipts/verify
+    pub fn handle_receipt_request(&self, path: &str, method: &str, body: &[u8]) -> Result<HttpResponse, String> {
+        match (method, path.trim_start_matches("/receipts/")) {
+            ("GET", "public-key") => json_response(200, &serde_json::json!({
+                "algorithm": "ed25519",
+                "public_key": to_hex(self.receipt_key().verifying_key().as_bytes()),
+            })),
+            ("POST", "verify") => {
+                let receipt: UsageReceipt = serde_json::from_slice(body)
+                    .map_err(|e| format!("Invalid receipt: {}", e))?;
+                let result = verify_receipt(&to_hex(self.receipt_key().verifying_key().as_bytes()), &receipt);
+                json_response(200, &serde_json::json!({
+                    "valid": result.is_ok(),
+                    "error": result.err(),
+                }))
+            }
+            ("GET", receipt_id) => match self.receipts.issued.iter().find(|r| r.receipt_id == receipt_id) {
+                Some(receipt) => json_response(200, receipt),
+                None => json_response(404, &serde_json::json!({
+                    "error": "Receipt not found or past retention; use your stored copy",
+                })),
+            },
+            _ => Err("Unsupported receipt request".to_string()),
+        }
+    }
+}
@@ -0,0 +1,276 @@
+use serde::{Deserialize, Serialize};
+use crate::estimate::{bulk_discount, usage_subtotal, EstimateRequest};
+use crate::receipts::json_response;
+use crate::screening::is_operator;
+use crate::tiers::{next_tier, tier_threshold};
+use crate::{EarningsTier, HttpResponse, PublicGateway};
+use std::collections::HashMap;
+
+/// One UTC day of a consumer's calls to one service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageDay {
+    pub day: String, // "YYYY-MM-DD"
+    pub service_key: String,
+    pub requests: u64,
+    pub bandwidth_mb: f64,
+    pub spend_usdc: f64,
+}
+
+/// Per-consumer usage history, daily and per service, behind the plan and
+/// tier recommendations
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsumerUsage {
+    pub retention_days: i64,
+    pub window_days: i64, // recommendations look at this much history
+    pub min_savings_usdc: f64, // smaller savings aren't worth suggesting
+    pub wallets: HashMap<String, Vec<UsageDay>>,
+}
+
+impl Default for ConsumerUsage {
+    fn default() -> Self {
+        Self {
+            retention_days: 90,
+            window_days: 30,
+            min_savings_usdc: 0.01,
+            wallets: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecommendationKind {
+    CheaperPlan,  // the same backend is offered for less
+    BulkDiscount, // volume qualifies, or nearly, for a bulk discount
+    TierUpgrade,  // referrals to a tier whose discount pays for itself
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recommendation {
+    pub kind: RecommendationKind,
+    pub service_key: Option<String>,
+    pub message: String,
+    pub monthly_savings_usdc: f64, // at the usage seen over the window
+}
+
+/// Usage of one service over the window
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ServiceUsage {
+    pub requests: u64,
+    pub bandwidth_mb: f64,
+    pub spend_usdc: f64,
+}
+
+fn day_string(days_ago: i64) -> String {
+    (chrono::Utc::now() - chrono::Duration::days(days_ago)).format("%Y-%m-%d").to_string()
+}
+
+fn round_usdc(amount: f64) -> f64 {
+    (amount * 1_000_000.0).round() / 1_000_000.0 // USDC has 6 decimals
+}
+
+impl ConsumerUsage {
+    pub fn record(&mut self, wallet_address: &str, service_key: &str, bytes: usize, charged_usdc: Option<f64>) {
+        let today = day_string(0);
+        let days = self.wallets.entry(wallet_address.to_string()).or_default();
+        if !days.iter().any(|day| day.day == today) {
+            let cutoff = day_string(self.retention_days);
+            days.retain(|day| day.day > cutoff);
+        }
+
+        let index = match days.iter().position(|day| day.day == today && day.service_key == service_key) {
+            Some(index) => index,
+            None => {
+                days.push(UsageDay {
+                    day: today,
+                    service_key: service_key.to_string(),
+                    requests: 0,
+                    bandwidth_mb: 0.0,
+                    spend_usdc: 0.0,
+                });
+                days.len() - 1
+            }
+        };
+        let day = &mut days[index];
+        day.requests += 1;
+        day.bandwidth_mb += bytes as f64 / (1024.0 * 1024.0);
+        day.spend_usdc += charged_usdc.unwrap_or(0.0);
+    }
+
+    /// Totals per service over the last `window_days`
+    pub fn window(&self, wallet_address: &str) -> HashMap<String, ServiceUsage> {
+        let cutoff = day_string(self.window_days);
+        let mut usage: HashMap<String, ServiceUsage> = HashMap::new();
+        for day in self.wallets.get(wallet_address).into_iter().flatten().filter(|day| day.day > cutoff) {
+            let service = usage.entry(day.service_key.clone()).or_default();
+            service.requests += day.requests;
+            service.bandwidth_mb += day.bandwidth_mb;
+            service.spend_usdc += day.spend_usdc;
+        }
+        usage
+    }
+}
+
+impl PublicGateway {
+    /// Count a call an authenticated consumer made; sandbox and anonymous
+    /// traffic are left out
+    pub(crate) fn record_consumer_usage(&mut self, service_key: &str, caller: Option<&str>,
+                                        bytes: usize, charged_usdc: Option<f64>) {
+        if self.sandbox_mode {
+            return;
+        }
+        if let Some(wallet) = caller.filter(|wallet| !wallet.is_empty()) {
+            self.consumer_usage.record(wallet, service_key, bytes, charged_usdc);
+        }
+    }
+
+    /// Another plan for the same backend, by the same owner, that would have
+    /// cost less for this usage
+    fn cheaper_plan(&self, service_key: &str, usage: &ServiceUsage, scale: f64) -> Option<Recommendation> {
+        let service = self.service_registry.get(service_key)?;
+        let per_call = EstimateRequest {
+            payload_mb: usage.bandwidth_mb / usage.requests.max(1) as f64,
+            duration_secs: 0.0,
+            requests: 1,
+        };
+
+        let (alternative, cost) = self.service_registry.iter()
+            .filter(|(key, alt)| *key != service_key
+                && alt.wallet_address == service.wallet_address
+                && alt.libp2p_port == service.libp2p_port
+                && alt.nft_gate.is_empty())
+            .map(|(key, alt)| {
+                let cost = if alt.payment_required {
+                    usage_subtotal(&alt.pricing, &per_call) * usage.requests as f64
+                } else {
+                    0.0
+                };
+                (key, cost)
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))?;
+
+        let savings = round_usdc((usage.spend_usdc - cost) * scale);
+        (savings >= self.consumer_usage.min_savings_usdc).then(|| Recommendation {
+            kind: RecommendationKind::CheaperPlan,
+            service_key: Some(service_key.to_string()),
+            message: format!("{} serves the same backend; your last {} calls would have cost {:.6} USDC there instead of {:.6}",
+                             alternative, usage.requests, cost, usage.spend_usdc),
+            monthly_savings_usdc: savings,
+        })
+    }
+
+    /// Bulk discount the monthly volume qualifies for through a batched
+    /// estimate, or the next one when the volume is within a fifth of it
+    fn bulk_discount_eligibility(&self, service_key: &str, usage: &ServiceUsage, scale: f64) -> Option<Recommendation> {
+        let pricing = &self.service_registry.get(service_key)?.pricing;
+        let monthly_requests = (usage.requests as f64 * scale) as u32;
+        let monthly_spend = usage.spend_usdc * scale;
+
+        let qualifies = bulk_discount(pricing, monthly_requests);
+        if qualifies > 0.0 {
+            let savings = round_usdc(monthly_spend * qualifies / 100.0);
+            return (savings >= self.consumer_usage.min_savings_usdc).then(|| Recommendation {
+                kind: RecommendationKind::BulkDiscount,
+                service_key: Some(service_key.to_string()),
+                message: format!("~{} calls a month qualifies for {}% off: declare them in one POST /{}/estimate with \"requests\"",
+                                 monthly_requests, qualifies, service_key.replacen('_', "/", 1)),
+                monthly_savings_usdc: savings,
+            });
+        }
+
+        let next = pricing.bulk_discounts.iter()
+            .filter(|discount| discount.min_requests > monthly_requests
+                && monthly_requests as f64 >= discount.min_requests as f64 * 0.8)
+            .min_by_key(|discount| discount.min_requests)?;
+        let savings = round_usdc(monthly_spend * next.discount_percentage / 100.0);
+        (savings >= self.consumer_usage.min_savings_usdc).then(|| Recommendation {
+            kind: RecommendationKind::BulkDiscount,
+            service_key: Some(service_key.to_string()),
+            message: format!("{} more calls a month would reach {}% off ({} requests per estimate)",
+                             next.min_requests - monthly_requests, next.discount_percentage, next.min_requests),
+            monthly_savings_usdc: savings,
+        })
+    }
+
+    /// The next earnings tier's estimate discount against the referrals it takes
+    fn tier_upgrade(&self, wallet_address: &str, monthly_spend: f64) -> Option<Recommendation> {
+        let account = self.commission_system.as_ref()
+            .and_then(|system| system.earnings_ledger.get(wallet_address));
+        let tier = account.map(|account| account.tier.clone()).unwrap_or(EarningsTier::Bronze);
+        let referral_count = account.map(|account| account.referral_count).unwrap_or(0);
+        let next = next_tier(&tier)?;
+
+        let discount = |tier: &EarningsTier| self.cost_estimator.tier_discounts
+            .get(&format!("{:?}", tier)).copied().unwrap_or(0.0);
+        let gain = discount(&next) - discount(&tier);
+        let savings = round_usdc(monthly_spend * gain / 100.0);
+        if gain <= 0.0 || savings < self.consumer_usage.min_savings_usdc {
+            return None;
+        }
+
+        let referrals_needed = tier_threshold(&next).saturating_sub(referral_count).max(1);
+        Some(Recommendation {
+            kind: RecommendationKind::TierUpgrade,
+            service_key: None,
+            message: format!("{} more referrals reach {:?}: {}% more off estimates, {:.6} USDC a month per referral at your spend",
+                             referrals_needed, next, gain, savings / referrals_needed as f64),
+            monthly_savings_usdc: savings,
+        })
+    }
+
+    /// Recommendations from the consumer's usage, largest savings first
+    pub fn usage_recommendations(&self, wallet_address: &str) -> Vec<Recommendation> {
+        let usage = self.consumer_usage.window(wallet_address);
+        let scale = 30.0 / self.consumer_usage.window_days.max(1) as f64; // to a month
+
+        let mut recommendations: Vec<Recommendation> = usage.iter()
+            .filter(|(_, usage)| usage.spend_usdc > 0.0)
+            .flat_map(|(service_key, usage)| [
+                self.cheaper_plan(service_key, usage, scale),
+                self.bulk_discount_eligibility(service_key, usage, scale),
+            ])
+            .flatten()
+            .collect();
+        let monthly_spend = usage.values().map(|usage| usage.spend_usdc).sum::<f64>() * scale;
+        recommendations.extend(self.tier_upgrade(wallet_address, monthly_spend));
+
+        recommendations.sort_by(|a, b| b.monthly_savings_usdc.total_cmp(&a.monthly_savings_usdc));
+        recommendations
+    }
+
+    /// GET /api/recommendations/{wallet}
+    /// GET /api/recommendations/{wallet}: a consumer's usage is theirs, or the operator's
+    pub fn handle_recommendations_request(&mut self, wallet_address: &str,
+                                          headers: &HashMap<String, String>) -> Result<HttpResponse, String> {
+        if !is_operator(headers) && self.authenticate_wallet(headers).ok().as_deref() != Some(wallet_address) {
+            return json_response(403, &serde_json::json!({ "error": "Usage is readable by its wallet or the operator" }));
+        }
+        let usage = self.consumer_usage.window(wallet_address);
+        json_response(200, &serde_json::json!({
+            "wallet_address": wallet_address,
+            "window_days": self.consumer_usage.window_days,
+            "usage": usage,
+            "recommendations": self.usage_recommendations(wallet_address),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usage_is_recorded_and_read_for_the_authenticated_wallet() {
+        let mut gateway = PublicGateway::new("gateway.test");
+        gateway.record_consumer_usage("owner1_api", None, 100, Some(0.01));
+        gateway.record_consumer_usage("owner1_api", Some("consumer1"), 100, Some(0.01));
+        assert_eq!(gateway.consumer_usage.window("consumer1")["owner1_api"].requests, 1);
+
+        let (_, key) = gateway.api_keys.issue("consumer1", "test", chrono::Utc::now().timestamp() as u64);
+        let owner = HashMap::from([("Authorization".to_string(), format!("Bearer {}", key))]);
+        let spoofed = HashMap::from([("X-Wallet-Address".to_string(), "consumer1".to_string())]);
+        assert_eq!(gateway.handle_recommendations_request("consumer1", &spoofed).unwrap().status_code, 403);
+        assert_eq!(gateway.handle_recommendations_request("consumer2", &owner).unwrap().status_code, 403);
+        assert_eq!(gateway.handle_recommendations_request("consumer1", &owner).unwrap().status_code, 200);
+    }
+}
@@ -0,0 +1,413 @@
+use serde::{Deserialize, Serialize};
+use crate::receipts::json_response;
+use crate::screening::is_operator;
+use crate::journal::GatewayEvent;
+use crate::{HttpResponse, PaymentRecord, PaymentStatus, PublicGateway};
+use std::collections::HashMap;
+
+const DAY_SECS: u64 = 86_400;
+
+/// Slack for float sums of refunds against the payment amount
+const DUST: f64 = 1e-9;
+
+/// Longest dispute window a service may set
+pub const MAX_DISPUTE_WINDOW_SECS: u64 = 180 * DAY_SECS;
+
+/// How long after a payment it can still be refunded or charged back
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RefundPolicy {
+    pub dispute_window_secs: u64,
+}
+
+impl Default for RefundPolicy {
+    fn default() -> Self {
+        Self { dispute_window_secs: 30 * DAY_SECS }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RefundKind {
+    Refund,     // the service owner gives the money back
+    Chargeback, // the payer disputed it with their payment provider; recorded by an operator
+}
+
+/// Part or all of a payment given back, and the commissions reversed with it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Refund {
+    pub refund_id: String,
+    pub kind: RefundKind,
+    pub amount: f64, // in the payment's token
+    pub reason: String,
+    pub refunded_at: u64,
+    pub clawed_back: Vec<String>, // commission payment ids, each reversed by the refunded share
+}
+
+impl PaymentRecord {
+    pub fn refunded_amount(&self) -> f64 {
+        self.refunds.iter().fold(0.0, |total, refund| total + refund.amount)
+    }
+}
+
+impl PublicGateway {
+    /// Record a confirmed payment to a service and pay its commissions
+    /// against the payment, so a refund can take back the same share of them
+    pub fn record_payment(&mut self, payer_wallet: &str, service_key: &str, token: &str,
+                          amount: f64, fee_amount: f64) -> Result<PaymentRecord, String> {
+        if !self.service_registry.contains_key(service_key) {
+            return Err("Service not found".to_string());
+        }
+        if amount.is_nan() || amount <= 0.0 {
+            return Err("Payment amount must be positive".to_string());
+        }
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        let processor = &mut self.payment_processor;
+        processor.payment_seq += 1;
+        let record = PaymentRecord {
+            payment_id: format!("pay_{}_{}", now, processor.payment_seq),
+            payer_wallet: payer_wallet.to_string(),
+            amount,
+            token: token.to_string(),
+            service_endpoint: service_key.to_string(),
+            timestamp: now,
+            status: PaymentStatus::Confirmed,
+            refunds: Vec::new(),
+        };
+        processor.payment_history.entry(payer_wallet.to_string()).or_default().push(record.clone());
+        self.journal(GatewayEvent::PaymentRecorded { payment: record.clone() });
+
+        if self.commission_system.is_some() {
+            self.pay_transaction_commissions("service_call", &record.payment_id, amount, fee_amount, token,
+                                             payer_wallet, service_key)?;
+        }
+        Ok(record)
+    }
+
+    pub fn find_payment(&self, payment_id: &str) -> Option<&PaymentRecord> {
+        self.payment_processor.payment_history.values()
+            .flatten()
+            .find(|payment| payment.payment_id == payment_id)
+    }
+
+    fn find_payment_mut(&mut self, payment_id: &str) -> Option<&mut PaymentRecord> {
+        self.payment_processor.payment_history.values_mut()
+            .flatten()
+            .find(|payment| payment.payment_id == payment_id)
+    }
+
+    /// Refund or charge back `amount` of a payment (what is left of it when
+    /// None), within the service's dispute window. Every commission paid on
+    /// the payment is clawed back pro rata; a payment refunded in full is
+    /// marked Refunded.
+    pub fn initiate_refund(&mut self, payment_id: &str, amount: Option<f64>, kind: RefundKind,
+                           reason: &str) -> Result<Refund, String> {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let payment = self.find_payment(payment_id).ok_or("Payment not found")?;
+        if !matches!(payment.status, PaymentStatus::Confirmed) {
+            return Err(format!("Only confirmed payments can be refunded; this one is {:?}", payment.status));
+        }
+
+        let window = self.service_registry.get(&payment.service_endpoint)
+            .map(|service| service.refund_policy.dispute_window_secs)
+            .unwrap_or_else(|| RefundPolicy::default().dispute_window_secs);
+        if now.saturating_sub(payment.timestamp) > window {
+            return Err(format!("The dispute window closed {}s after the payment", window));
+        }
+
+        let left = payment.amount - payment.refunded_amount();
+        let amount = amount.unwrap_or(left);
+        if amount.is_nan() || amount <= 0.0 || amount > left + DUST {
+            return Err(format!("Refund must be more than 0 and at most the {} {} left", left, payment.token));
+        }
+        let share = amount / payment.amount;
+        let refund_id = format!("{}_refund_{}", payment_id, payment.refunds.len() + 1);
+
+        // Each earner gives back the refunded share of what the payment paid them
+        let commission_ids: Vec<String> = self.commission_system.as_ref()
+            .map(|system| system.commission_history.values()
+                .flatten()
+                .filter(|commission| commission.source_transaction == payment_id)
+                .map(|commission| commission.payment_id.clone())
+                .collect())
+            .unwrap_or_default();
+        let mut clawed_back = Vec::new();
+        for commission_id in commission_ids {
+            match self.claw_back_share(&commission_id, share, &format!("{:?} {}: {}", kind, refund_id, reason)) {
+                Ok(_) => clawed_back.push(commission_id),
+                Err(e) => println!("⚠️  Commission {} not clawed back for {}: {}", commission_id, refund_id, e),
+            }
+        }
+
+        let refund = Refund {
+            refund_id,
+            kind,
+            amount,
+            reason: reason.to_string(),
+            refunded_at: now,
+            clawed_back,
+        };
+        let payment = self.find_payment_mut(payment_id).ok_or("Payment not found")?;
+        payment.refunds.push(refund.clone());
+        if payment.amount - payment.refunded_amount() <= DUST {
+            payment.status = PaymentStatus::Refunded;
+        }
+        println!("💸 {:?} of {} {} on {} ({} commissions clawed back)",
+                 refund.kind, amount, payment.token, payment_id, refund.clawed_back.len());
+        self.journal(GatewayEvent::PaymentRefunded { payment_id: payment_id.to_string(), refund: refund.clone() });
+
+        Ok(refund)
+    }
+
+    pub fn set_refund_policy(&mut self, service_key: &str, policy: RefundPolicy) -> Result<(), String> {
+        if policy.dispute_window_secs > MAX_DISPUTE_WINDOW_SECS {
+            return Err(format!("dispute_window_secs can be at most {}", MAX_DISPUTE_WINDOW_SECS));
+        }
+        self.service_registry.get_mut(service_key)
+            .ok_or("Service not found")?
+            .refund_policy = policy;
+        Ok(())
+    }
+
+    /// GET /payments/{id}, POST /payments/{id}/refund (the service's owner
+    /// or an operator), POST /payments/{id}/chargeback (operators only)
+    pub(crate) fn handle_payment_request(&mut self, path: &str, method: &str,
+                                         headers: &HashMap<String, String>, body: &[u8]) -> Result<HttpResponse, String> {
+        let parts: Vec<&str> = path.trim_matches('/').split('/').collect();
+        let payment_id = match parts.get(1) {
+            Some(payment_id) => *payment_id,
+            None => return Err("Unsupported payment request".to_string()),
+        };
+        let (payer, owner) = match self.find_payment(payment_id) {
+            Some(payment) => (payment.payer_wallet.clone(),
+                              self.service_registry.get(&payment.service_endpoint).map(|service| service.wallet_address.clone())),
+            None => return json_response(404, &serde_json::json!({ "error": "Payment not found" })),
+        };
+
+        // Operators may do anything; the service's owner may look and refund;
+        // the payer may only look
+        let operator = is_operator(headers);
+        let caller = if operator { None } else {
+            match self.authenticate_wallet(headers) {
+                Ok(caller) => Some(caller),
+                Err(e) => return json_response(401, &serde_json::json!({ "error": e })),
+            }
+        };
+        let is_owner = caller.is_some() && caller == owner;
+
+        match (method, parts.get(2).copied()) {
+            ("GET", None) => {
+                if !operator && !is_owner && caller.as_deref() != Some(payer.as_str()) {
+                    return json_response(403, &serde_json::json!({ "error": "Only the payer or the service's owner can see this payment" }));
+                }
+                let payment = self.find_payment(payment_id).ok_or("Payment not found")?;
+                json_response(200, &serde_json::json!({
+                    "payment": payment,
+                    "refunded_amount": payment.refunded_amount(),
+                }))
+            }
+            ("POST", Some(action @ ("refund" | "chargeback"))) => {
+                let kind = if action == "refund" { RefundKind::Refund } else { RefundKind::Chargeback };
+                let allowed = operator || (kind == RefundKind::Refund && is_owner);
+                if !allowed {
+                    let error = if kind == RefundKind::Refund { "Only the service's owner can refund" } else { "Needs X-Operator-Key" };
+                    return json_response(403, &serde_json::json!({ "error": error }));
+                }
+                let request: serde_json::Value = serde_json::from_slice(body)
+                    .map_err(|e| format!("Invalid refund request: {}", e))?;
+                let reason = request["reason"].as_str().ok_or("reason is required")?;
+                let amount = match &request["amount"] {
+                    serde_json::Value::Null => None,
+                    amount => Some(amount.as_f64().ok_or("amount must be a number")?),
+                };
+                match self.initiate_refund(payment_id, amount, kind, reason) {
+                    Ok(refund) => {
+                        let payment = self.find_payment(payment_id).ok_or("Payment not found")?;
+                        json_response(200, &serde_json::json!({ "refund": refund, "payment": payment }))
+                    }
+                    Err(e) => json_response(409, &serde_json::json!({ "error": e })),
+                }
+            }
+            _ => Err("Unsupported payment request".to_string()),
+        }
+    }
+
+    /// GET/PUT /{wallet}/{service}/refund-policy; setting it is for the
+    /// owner, like the rest of Service Management
+    pub(crate) fn handle_refund_policy_request(&mut self, wallet_address: &str, service_name: &str, method: &str,
+                                               headers: &HashMap<String, String>, body: &[u8]) -> Result<HttpResponse, String> {
+        let service_key = format!("{}_{}", wallet_address, service_name);
+        let service = self.service_registry.get(&service_key).ok_or("Service not found")?;
+        match method {
+            "GET" => json_response(200, &serde_json::json!({
+                "service": service_key,
+                "policy": service.refund_policy,
+            })),
+            "PUT" => {
+                if !is_operator(headers) {
+                    match self.authenticate_wallet(headers) {
+                        Ok(caller) if caller == wallet_address => {}
+                        Ok(_) => return json_response(403, &serde_json::json!({ "error": "Only the service owner can set its refund policy" })),
+                        Err(e) => return json_response(401, &serde_json::json!({ "error": e })),
+                    }
+                }
+                let policy: RefundPolicy = serde_json::from_slice(body)
+                    .map_err(|e| format!("Invalid refund policy: {}", e))?;
+                if let Err(e) = self.set_refund_policy(&service_key, policy) {
+                    return json_response(422, &serde_json::json!({ "error": e }));
+                }
+                println!("💸 Refund policy updated for {}", service_key);
+                json_response(200, &serde_json::json!({
+                    "service": service_key,
+                    "policy": self.service_registry[&service_key].refund_policy,
+                }))
+            }
+            _ => Err("Unsupported refund policy request".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token_amount::TokenAmount;
+    use crate::PricingTier;
+    use zos_archive::ArchiveStore;
+
+    const OWNER: &str = "OwnerWallet1111111111111111111111111111111";
+    const PAYER: &str = "PayerWallet1111111111111111111111111111111";
+    const SERVICE: &str = "OwnerWallet1111111111111111111111111111111_api";
+
+    /// A 100 USDC payment with a 10 USDC fee. At the default rates the
+    /// owner earns 2 (20% of the fee) and 5 (5% of the payment).
+    fn paid_gateway() -> (PublicGateway, String) {
+        let mut gateway = PublicGateway::new("gateway.test");
+        gateway.initialize_commission_system();
+        gateway.register_wallet_endpoint(OWNER, "owner", vec![4001]).unwrap();
+        gateway.add_service(OWNER, "api", 4001, PricingTier::Basic).unwrap();
+        let payment = gateway.record_payment(PAYER, SERVICE, "USDC", 100.0, 10.0).unwrap();
+        (gateway, payment.payment_id)
+    }
+
+    fn owner_earned(gateway: &PublicGateway) -> TokenAmount {
+        gateway.commission_system.as_ref().unwrap().earnings_ledger[OWNER].earned_by_token["USDC"]
+    }
+
+    fn usdc(amount: f64) -> TokenAmount {
+        TokenAmount::from_decimal(amount, 6).unwrap()
+    }
+
+    /// Make the payment and its commissions `secs` older
+    fn age(gateway: &mut PublicGateway, secs: u64) {
+        for payment in gateway.payment_processor.payment_history.values_mut().flatten() {
+            payment.timestamp -= secs;
+        }
+        for commission in gateway.commission_system.as_mut().unwrap().commission_history.values_mut().flatten() {
+            commission.timestamp -= secs;
+        }
+    }
+
+    #[derive(Default)]
+    struct MemoryStore(HashMap<String, Vec<u8>>);
+
+    impl ArchiveStore for MemoryStore {
+        fn put(&mut self, key: &str, bytes: &[u8]) -> Result<(), String> {
+            self.0.insert(key.to_string(), bytes.to_vec());
+            Ok(())
+        }
+
+        fn get(&self, key: &str) -> Result<Vec<u8>, String> {
+            self.0.get(key).cloned().ok_or(format!("No segment {}", key))
+        }
+
+        fn location(&self) -> String {
+            "memory".to_string()
+        }
+    }
+
+    #[test]
+    fn test_partial_refund_claws_back_pro_rata() {
+        let (mut gateway, payment_id) = paid_gateway();
+        assert_eq!(owner_earned(&gateway), usdc(7.0));
+
+        let refund = gateway.initiate_refund(&payment_id, Some(25.0), RefundKind::Refund, "partial outage").unwrap();
+        assert_eq!(refund.amount, 25.0);
+        assert_eq!(refund.clawed_back.len(), 2);
+        assert_eq!(owner_earned(&gateway), usdc(5.25));
+
+        let payment = gateway.find_payment(&payment_id).unwrap();
+        assert!(matches!(payment.status, PaymentStatus::Confirmed));
+        assert_eq!(payment.refunded_amount(), 25.0);
+    }
+
+    #[test]
+    fn test_repeated_partial_refunds() {
+        let (mut gateway, payment_id) = paid_gateway();
+
+        gateway.initiate_refund(&payment_id, Some(30.0), RefundKind::Refund, "first").unwrap();
+        let second = gateway.initiate_refund(&payment_id, Some(30.0), RefundKind::Chargeback, "second").unwrap();
+        assert_eq!(second.refund_id, format!("{}_refund_2", payment_id));
+        assert_eq!(owner_earned(&gateway), usdc(2.8));
+
+        // No more than what is left
+        assert!(gateway.initiate_refund(&payment_id, Some(40.5), RefundKind::Refund, "too much").is_err());
+        assert!(gateway.initiate_refund(&payment_id, Some(0.0), RefundKind::Refund, "nothing").is_err());
+
+        let rest = gateway.initiate_refund(&payment_id, None, RefundKind::Refund, "the rest").unwrap();
+        assert!((rest.amount - 40.0).abs() < 1e-9);
+        assert_eq!(owner_earned(&gateway), usdc(0.0));
+        assert_eq!(gateway.find_payment(&payment_id).unwrap().refunds.len(), 3);
+    }
+
+    #[test]
+    fn test_full_refund_marks_payment_refunded() {
+        let (mut gateway, payment_id) = paid_gateway();
+
+        let refund = gateway.initiate_refund(&payment_id, None, RefundKind::Refund, "cancelled").unwrap();
+        assert_eq!(refund.amount, 100.0);
+        let payment = gateway.find_payment(&payment_id).unwrap();
+        assert!(matches!(payment.status, PaymentStatus::Refunded));
+        assert_eq!(owner_earned(&gateway), usdc(0.0));
+
+        let again = gateway.initiate_refund(&payment_id, Some(1.0), RefundKind::Chargeback, "again");
+        assert!(again.unwrap_err().contains("Only confirmed payments"));
+    }
+
+    #[test]
+    fn test_dispute_window() {
+        let (mut gateway, payment_id) = paid_gateway();
+        age(&mut gateway, 31 * DAY_SECS);
+
+        let closed = gateway.initiate_refund(&payment_id, None, RefundKind::Refund, "late");
+        assert!(closed.unwrap_err().contains("dispute window"));
+        assert_eq!(owner_earned(&gateway), usdc(7.0));
+
+        // The service may keep it open longer, up to the maximum
+        assert!(gateway.set_refund_policy(SERVICE, RefundPolicy { dispute_window_secs: MAX_DISPUTE_WINDOW_SECS + 1 }).is_err());
+        gateway.set_refund_policy(SERVICE, RefundPolicy { dispute_window_secs: 60 * DAY_SECS }).unwrap();
+        gateway.initiate_refund(&payment_id, None, RefundKind::Refund, "late").unwrap();
+        assert_eq!(owner_earned(&gateway), usdc(0.0));
+    }
+
+    #[test]
+    fn test_archival_keeps_records_inside_the_dispute_window() {
+        let (mut gateway, payment_id) = paid_gateway();
+        gateway.set_refund_policy(SERVICE, RefundPolicy { dispute_window_secs: MAX_DISPUTE_WINDOW_SECS }).unwrap();
+        gateway.archival.retention_days = 1;
+        age(&mut gateway, 100 * DAY_SECS);
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        let mut store = MemoryStore::default();
+        let report = gateway.archive_ledgers(&mut store, now).unwrap();
+        assert_eq!((report.commissions, report.payments), (0, 0));
+
+        let refund = gateway.initiate_refund(&payment_id, None, RefundKind::Chargeback, "disputed").unwrap();
+        assert_eq!(refund.clawed_back.len(), 2);
+        assert_eq!(owner_earned(&gateway), usdc(0.0));
+
+        // Once past the longest window they go
+        age(&mut gateway, 81 * DAY_SECS);
+        let report = gateway.archive_ledgers(&mut store, now).unwrap();
+        assert_eq!((report.commissions, report.payments), (2, 1));
+    }
+}
@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+use crate::journal::GatewayJournal;
+use crate::{HttpResponse, PublicGateway, SwapPool};
+use std::collections::HashMap;
+
+pub const SANDBOX_HEADER: &str = "X-ZOS-Sandbox";
+
+/// Pools every sandbox starts with; liquidity is deep so test swaps never
+/// run dry
+const SANDBOX_POOLS: [(&str, &str, f64); 2] = [
+    ("USDC", "SOLFUNMEME", 0.3),
+    ("USDC", "SOL", 0.25),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SandboxConfig {
+    pub enabled: bool,
+    pub port: Option<u16>, // host serves handle_sandbox_request here too
+    pub wipe_interval_secs: u64,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            port: None,
+            wipe_interval_secs: 86400,
+        }
+    }
+}
+
+/// A throwaway copy of the gateway for integrators: the same services, but
+/// simulated payments, fake swap pools, a test commission ledger and
+/// receipts signed with a key nobody should trust. Wiped on a timer.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Sandbox {
+    pub config: SandboxConfig,
+    pub last_wiped: u64,
+    pub wipes: u64,
+    #[serde(skip)]
+    environment: Option<Box<PublicGateway>>,
+}
+
+/// `X-ZOS-Sandbox: true`, in any header case
+pub fn is_sandbox_request(headers: &HashMap<String, String>) -> bool {
+    headers.iter().any(|(name, value)| {
+        name.eq_ignore_ascii_case(SANDBOX_HEADER) && value.trim().eq_ignore_ascii_case("true")
+    })
+}
+
+impl PublicGateway {
+    fn build_sandbox(&self) -> PublicGateway {
+        let mut sandbox = PublicGateway::new(&format!("sandbox.{}", self.domain));
+        sandbox.sandbox_mode = true;
+        sandbox.journal = GatewayJournal::default(); // test traffic stays out of the real journal
+        sandbox.wallet_endpoints = self.wallet_endpoints.clone();
+        sandbox.service_registry = self.service_registry.clone();
+        for service in sandbox.service_registry.values_mut() {
+            // Sandbox traffic must not reach staging either
+            service.mirror = None;
+        }
+
+        for (token_a, token_b, fee_percentage) in SANDBOX_POOLS {
+            let pool_id = format!("sandbox_{}_{}", token_a, token_b).to_lowercase();
+            sandbox.payment_processor.swap_pools.insert(pool_id.clone(), SwapPool {
+                pool_id,
+                token_a: token_a.to_string(),
+                token_b: token_b.to_string(),
+                liquidity: 1_000_000_000.0,
+                fee_percentage,
+                price_impact: 0.0,
+                reserve_a: 500_000_000.0,
+                reserve_b: 500_000_000.0,
+            });
+        }
+        sandbox.initialize_commission_system();
+        sandbox.coupons.coupons = self.coupons.coupons.iter()
+            .map(|(code, coupon)| (code.clone(), coupon.for_sandbox()))
+            .collect();
+        sandbox
+    }
+
+    /// Throw away all sandbox state and start again from production's services
+    pub fn wipe_sandbox(&mut self) {
+        let now = chrono::Utc::now().timestamp() as u64;
+        self.sandbox.environment = Some(Box::new(self.build_sandbox()));
+        self.sandbox.last_wiped = now;
+        self.sandbox.wipes += 1;
+        println!("🧪 Sandbox wiped ({} so far)", self.sandbox.wipes);
+    }
+
+    /// Serve a call against the sandbox; also the handler for the sandbox port
+    pub fn handle_sandbox_request(&mut self, path: &str, method: &str,
+                                  headers: &HashMap<String, String>,
+                                  body: &[u8]) -> Result<HttpResponse, String> {
+        if !self.sandbox.config.enabled {
+            return Err("Sandbox is disabled on this gateway".to_string());
+        }
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        let due = now.saturating_sub(self.sandbox.last_wiped) >= self.sandbox.config.wipe_interval_secs;
+        if self.sandbox.environment.is_none() || due {
+            self.wipe_sandbox();
+        }
+
+        if path == "/sandbox/status" {
+            let body = serde_json::to_vec(&serde_json::json!({
+                "sandbox": true,
+                "last_wiped": self.sandbox.last_wiped,
+                "next_wipe": self.sandbox.last_wiped + self.sandbox.config.wipe_interval_secs,
+                "wipes": self.sandbox.wipes
+            })).map_err(|e| format!("Failed to serialize response: {}", e))?;
+            return Ok(HttpResponse {
+                status_code: 200,
+                headers: HashMap::from([
+                    ("Content-Type".to_string(), "application/json".to_string()),
+                    (SANDBOX_HEADER.to_string(), "true".to_string()),
+                ]),
+                body,
+            });
+        }
+
+        // Services registered since the last wipe show up right away
+        let missing: Vec<_> = self.service_registry.iter()
+            .filter(|(key, _)| !self.sandbox.environment.as_ref()
+                .is_some_and(|sandbox| sandbox.service_registry.contains_key(*key)))
+            .map(|(key, service)| (key.clone(), service.clone()))
+            .collect();
+
+        let sandbox = self.sandbox.environment.as_mut()
+            .ok_or("Sandbox is not available")?;
+        for (key, mut service) in missing {
+            service.mirror = None;
+            sandbox.service_registry.insert(key, service);
+        }
+        // NFT gates read real holdings; only payments are simulated
+        sandbox.nft_gating.oracle = self.nft_gating.oracle.clone();
+        // Callers prove who they are with their real API keys
+        sandbox.api_keys.keys = self.api_keys.keys.clone();
+
+        sandbox.handle_http_request(path, method, headers, body).map(|mut response| {
+            response.headers.insert(SANDBOX_HEADER.to_string(), "true".to_string());
+            response
+        })
+    }
+}
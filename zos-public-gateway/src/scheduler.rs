@@ -0,0 +1,181 @@
+use serde::{Deserialize, Serialize};
+use crate::{EarningsTier, HttpResponse, PublicGateway};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestScheduler {
+    pub queue_capacity: usize,              // per service
+    pub tier_weights: HashMap<String, f64>, // EarningsTier name -> share weight
+    pub queues: HashMap<String, ServiceQueue>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ServiceQueue {
+    pub pending: Vec<QueuedRequest>,
+    pub virtual_time: f64,
+    pub last_finish: HashMap<String, f64>, // consumer wallet -> last finish tag
+    pub metrics: QueueMetrics,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QueueMetrics {
+    pub enqueued: u64,
+    pub dispatched: u64,
+    pub rejected: u64,
+    pub max_depth: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedRequest {
+    pub request_id: String,
+    pub consumer_wallet: String,
+    pub path: String,
+    pub method: String,
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+    pub enqueued_at: u64,
+    pub finish_tag: f64,
+}
+
+impl Default for RequestScheduler {
+    fn default() -> Self {
+        Self {
+            queue_capacity: 100,
+            tier_weights: HashMap::from([
+                ("Bronze".to_string(), 1.0),
+                ("Silver".to_string(), 2.0),
+                ("Gold".to_string(), 4.0),
+                ("Platinum".to_string(), 8.0),
+            ]),
+            queues: HashMap::new(),
+        }
+    }
+}
+
+impl ServiceQueue {
+    /// Weighted fair queueing: each consumer's requests get finish tags spaced
+    /// by 1/weight, and the lowest tag is served first, so a noisy wallet only
+    /// delays its own backlog.
+    fn push(&mut self, mut request: QueuedRequest, weight: f64) {
+        let start = self.last_finish.get(&request.consumer_wallet)
+            .copied()
+            .unwrap_or(0.0)
+            .max(self.virtual_time);
+        request.finish_tag = start + 1.0 / weight.max(0.01);
+
+        self.last_finish.insert(request.consumer_wallet.clone(), request.finish_tag);
+        self.pending.push(request);
+        self.metrics.enqueued += 1;
+        self.metrics.max_depth = self.metrics.max_depth.max(self.pending.len());
+    }
+
+    fn pop(&mut self) -> Option<QueuedRequest> {
+        let next = self.pending.iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.finish_tag.total_cmp(&b.finish_tag))
+            .map(|(index, _)| index)?;
+
+        let request = self.pending.remove(next);
+        self.virtual_time = request.finish_tag;
+        self.metrics.dispatched += 1;
+
+        if self.pending.is_empty() {
+            self.last_finish.clear();
+        }
+
+        Some(request)
+    }
+}
+
+impl PublicGateway {
+    fn consumer_weight(&self, consumer_wallet: &str) -> f64 {
+        let tier = self.commission_system.as_ref()
+            .and_then(|system| system.earnings_ledger.get(consumer_wallet))
+            .map(|account| account.tier.clone())
+            .unwrap_or(EarningsTier::Bronze);
+
+        self.request_scheduler.tier_weights
+            .get(&format!("{:?}", tier))
+            .copied()
+            .unwrap_or(1.0)
+    }
+
+    /// Queue a service call. Returns the request id, or a 503 response with
+    /// Retry-After when the service queue is full.
+    pub fn enqueue_request(&mut self, path: &str, method: &str,
+                           headers: &HashMap<String, String>,
+                           body: &[u8]) -> Result<String, HttpResponse> {
+        let path_parts: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+        let service_key = format!("{}_{}", path_parts[0], path_parts.get(1).unwrap_or(&""));
+        let consumer_wallet = headers.get("X-Wallet-Address")
+            .cloned()
+            .unwrap_or_else(|| "anonymous".to_string());
+
+        let weight = self.consumer_weight(&consumer_wallet);
+        let capacity = self.request_scheduler.queue_capacity;
+        let queue = self.request_scheduler.queues.entry(service_key.clone()).or_default();
+
+        if queue.pending.len() >= capacity {
+            queue.metrics.rejected += 1;
+            println!("🚦 Queue full for {} ({} pending)", service_key, queue.pending.len());
+
+            // Rough drain estimate: one second per ten queued requests
+            let retry_after = (queue.pending.len() / 10).max(1);
+            return Err(HttpResponse {
+                status_code: 503,
+                headers: HashMap::from([
+                    ("Content-Type".to_string(), "application/json".to_string()),
+                    ("Retry-After".to_string(), retry_after.to_string()),
+                ]),
+                body: serde_json::to_vec(&serde_json::json!({
+                    "error": "Service queue full",
+                    "queue_depth": queue.pending.len()
+                })).unwrap_or_default(),
+            });
+        }
+
+        let now = chrono::Utc::now();
+        let request_id = format!("req_{}_{}", now.timestamp_nanos_opt().unwrap_or(0), queue.metrics.enqueued);
+
+        queue.push(QueuedRequest {
+            request_id: request_id.clone(),
+            consumer_wallet,
+            path: path.to_string(),
+            method: method.to_string(),
+            headers: headers.clone(),
+            body: body.to_vec(),
+            enqueued_at: now.timestamp() as u64,
+            finish_tag: 0.0,
+        }, weight);
+
+        Ok(request_id)
+    }
+
+    /// Run the next request for a service in fair order
+    pub fn dispatch_next(&mut self, service_key: &str) -> Option<(String, Result<HttpResponse, String>)> {
+        let request = self.request_scheduler.queues.get_mut(service_key)?.pop()?;
+        let response = self.handle_http_request(&request.path, &request.method, &request.headers, &request.body);
+
+        Some((request.request_id, response))
+    }
+
+    pub fn queue_metrics(&self) -> serde_json::Value {
+        let services = self.request_scheduler.queues.iter()
+            .map(|(service_key, queue)| {
+                let mut per_consumer: HashMap<&str, usize> = HashMap::new();
+                for request in &queue.pending {
+                    *per_consumer.entry(request.consumer_wallet.as_str()).or_insert(0) += 1;
+                }
+
+                (service_key.clone(), serde_json::json!({
+                    "depth": queue.pending.len(),
+                    "capacity": self.request_scheduler.queue_capacity,
+                    "per_consumer_depth": per_consumer,
+                    "metrics": queue.metrics
+                }))
+            })
+            .collect::<serde_json::Map<_, _>>();
+
+        serde_json::Value::Object(services)
+    }
+}
@@ -0,0 +1,206 @@
+use serde::{Deserialize, Serialize};
+use crate::receipts::json_response;
+use crate::{HttpResponse, PublicGateway};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ScreeningPurpose {
+    EndpointRegistration,
+    Withdrawal,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ScreeningVerdict {
+    Clear,
+    Blocked(String), // reason, shown to the wallet
+}
+
+/// Compliance check against an external provider (sanctions lists, chain
+/// analytics). Deployments plug their own in with `set_wallet_screening`.
+pub trait WalletScreening: Send + Sync {
+    fn provider(&self) -> &str;
+    fn screen(&self, wallet_address: &str, purpose: ScreeningPurpose) -> Result<ScreeningVerdict, String>;
+}
+
+/// Default: every wallet is clear; only the local blocklist applies
+pub struct NoScreening;
+
+impl WalletScreening for NoScreening {
+    fn provider(&self) -> &str {
+        "none"
+    }
+
+    fn screen(&self, _wallet_address: &str, _purpose: ScreeningPurpose) -> Result<ScreeningVerdict, String> {
+        Ok(ScreeningVerdict::Clear)
+    }
+}
+
+#[derive(Clone)]
+pub struct ScreeningHook(pub Arc<dyn WalletScreening>);
+
+impl Default for ScreeningHook {
+    fn default() -> Self {
+        Self(Arc::new(NoScreening))
+    }
+}
+
+impl std::fmt::Debug for ScreeningHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ScreeningHook({})", self.0.provider())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlocklistEntry {
+    pub wallet_address: String,
+    pub reason: String,
+    pub added_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedScreening {
+    pub provider: String,
+    pub verdict: ScreeningVerdict,
+    pub screened_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletCompliance {
+    pub blocklist: HashMap<String, BlocklistEntry>,
+    pub cache_ttl_secs: u64,
+    pub fail_closed: bool, // provider errors block instead of letting the wallet through
+    pub cache: HashMap<String, CachedScreening>, // "{purpose:?}:{wallet}" -> last verdict
+    #[serde(skip)]
+    pub hook: ScreeningHook,
+}
+
+impl Default for WalletCompliance {
+    fn default() -> Self {
+        Self {
+            blocklist: HashMap::new(),
+            cache_ttl_secs: 24 * 3600,
+            fail_closed: true,
+            cache: HashMap::new(),
+            hook: ScreeningHook::default(),
+        }
+    }
+}
+
+fn cache_key(wallet_address: &str, purpose: ScreeningPurpose) -> String {
+    format!("{:?}:{}", purpose, wallet_address)
+}
+
+/// Blocklist changes need `X-Operator-Key` matching ZOS_OPERATOR_KEY; with
+/// no key configured the blocklist is read-only over HTTP
+pub(crate) fn is_operator(headers: &HashMap<String, String>) -> bool {
+    match std::env::var("ZOS_OPERATOR_KEY") {
+        Ok(expected) if !expected.is_empty() => headers.get("X-Operator-Key") == Some(&expected),
+        _ => false,
+    }
+}
+
+impl PublicGateway {
+    pub fn set_wallet_screening(&mut self, screening: Arc<dyn WalletScreening>) {
+        println!("🛂 Wallet screening provider: {}", screening.provider());
+        self.compliance.hook = ScreeningHook(screening);
+        self.compliance.cache.clear();
+    }
+
+    /// Blocklist first, then the provider's cached or fresh verdict
+    pub fn screen_wallet(&mut self, wallet_address: &str, purpose: ScreeningPurpose) -> Result<(), String> {
+        if let Some(entry) = self.compliance.blocklist.get(wallet_address) {
+            return Err(format!("Wallet is blocked: {}", entry.reason));
+        }
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        let key = cache_key(wallet_address, purpose);
+        let provider = self.compliance.hook.0.provider().to_string();
+        let cached = self.compliance.cache.get(&key)
+            .filter(|cached| cached.provider == provider)
+            .filter(|cached| now.saturating_sub(cached.screened_at) < self.compliance.cache_ttl_secs);
+
+        let verdict = match cached {
+            Some(cached) => cached.verdict.clone(),
+            None => match self.compliance.hook.0.screen(wallet_address, purpose) {
+                Ok(verdict) => {
+                    self.compliance.cache.insert(key, CachedScreening {
+                        provider,
+                        verdict: verdict.clone(),
+                        screened_at: now,
+                    });
+                    verdict
+                }
+                // Errors are not cached; the next attempt asks again
+                Err(e) if self.compliance.fail_closed => return Err(format!("Wallet screening unavailable: {}", e)),
+                Err(e) => {
+                    println!("⚠️  Wallet screening failed open for {}: {}", wallet_address, e);
+                    ScreeningVerdict::Clear
+                }
+            },
+        };
+
+        match verdict {
+            ScreeningVerdict::Clear => Ok(()),
+            ScreeningVerdict::Blocked(reason) => Err(format!("Wallet failed screening: {}", reason)),
+        }
+    }
+
+    pub fn block_wallet(&mut self, wallet_address: &str, reason: &str) {
+        self.compliance.blocklist.insert(wallet_address.to_string(), BlocklistEntry {
+            wallet_address: wallet_address.to_string(),
+            reason: reason.to_string(),
+            added_at: chrono::Utc::now().timestamp() as u64,
+        });
+        println!("⛔ Wallet {} blocked: {}", wallet_address, reason);
+    }
+
+    /// Remove a wallet from the blocklist; its cached verdicts go too so the
+    /// provider is asked again
+    pub fn unblock_wallet(&mut self, wallet_address: &str) -> bool {
+        self.compliance.cache.retain(|key, _| !key.ends_with(&format!(":{}", wallet_address)));
+        self.compliance.blocklist.remove(wallet_address).is_some()
+    }
+
+    /// GET/POST /compliance/blocklist, DELETE /compliance/blocklist/{wallet}
+    pub fn handle_compliance_request(&mut self, path: &str, method: &str,
+                                     headers: &HashMap<String, String>,
+                                     body: &[u8]) -> Result<HttpResponse, String> {
+        let rest = path.trim_start_matches("/compliance/blocklist").trim_matches('/');
+        if method != "GET" && !is_operator(headers) {
+            return json_response(403, &serde_json::json!({ "error": "Operator key required" }));
+        }
+
+        match (method, rest) {
+            ("GET", "") => {
+                let mut entries: Vec<_> = self.compliance.blocklist.values().collect();
+                entries.sort_by_key(|entry| entry.added_at);
+                json_response(200, &serde_json::json!({
+                    "provider": self.compliance.hook.0.provider(),
+                    "blocklist": entries,
+                }))
+            }
+            ("GET", wallet_address) => match self.compliance.blocklist.get(wallet_address) {
+                Some(entry) => json_response(200, entry),
+                None => json_response(404, &serde_json::json!({ "error": "Wallet is not blocklisted" })),
+            },
+            ("POST", "") => {
+                let entry: serde_json::Value = serde_json::from_slice(body)
+                    .map_err(|e| format!("Invalid blocklist entry: {}", e))?;
+                let wallet_address = entry["wallet_address"].as_str()
+                    .ok_or("wallet_address is required")?;
+                let reason = entry["reason"].as_str().unwrap_or("Blocked by operator");
+                self.block_wallet(wallet_address, reason);
+                json_response(201, &self.compliance.blocklist[wallet_address])
+            }
+            ("DELETE", wallet_address) if !wallet_address.is_empty() => {
+                let removed = self.unblock_wallet(wallet_address);
+                json_response(if removed { 200 } else { 404 }, &serde_json::json!({
+                    "wallet_address": wallet_address,
+                    "removed": removed,
+                }))
+            }
+            _ => Err("Unsupported compliance request".to_string()),
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+use crate::receipts::json_response;
+use crate::screening::is_operator;
+use crate::{HttpResponse, PublicGateway};
+use std::collections::HashMap;
+
+/// Where game sessions went after their node was drained, so calls that
+/// still carry the old session id follow it to the new node
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionRoutes {
+    pub ttl_secs: u64, // forget a route once the session has long moved on
+    pub routes: HashMap<String, SessionRoute>, // session_id -> route
+}
+
+impl Default for SessionRoutes {
+    fn default() -> Self {
+        Self {
+            ttl_secs: 86400,
+            routes: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionRoute {
+    pub from_node: String,
+    pub to_node: String, // base URL of the node now hosting the session
+    pub migrated_at: u64,
+}
+
+/// Sent by a draining node once the target accepted its sessions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionMigration {
+    pub handoff_id: String,
+    pub from_node: String,
+    pub to_node: String,
+    pub session_ids: Vec<String>,
+}
+
+impl PublicGateway {
+    /// POST /cluster/game-sessions, from a trusted peer holding the
+    /// operator key: clients are redirected wherever it says
+    pub fn handle_session_migration(&mut self, headers: &HashMap<String, String>,
+                                    body: &[u8]) -> Result<HttpResponse, String> {
+        if !is_operator(headers) {
+            return json_response(403, &serde_json::json!({ "error": "Needs X-Operator-Key" }));
+        }
+        let migration: SessionMigration = serde_json::from_slice(body)
+            .map_err(|e| format!("Invalid session migration: {}", e))?;
+        if !self.is_trusted_sync_peer(&migration.from_node) {
+            return json_response(403, &serde_json::json!({ "error": format!("{} is not a trusted peer", migration.from_node) }));
+        }
+
+        if !(migration.to_node.starts_with("https://") || migration.to_node.starts_with("http://")) {
+            return Err("Migration target must be the node's http(s) base URL".to_string());
+        }
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        let ttl = self.session_routes.ttl_secs;
+        self.session_routes.routes.retain(|_, route| now.saturating_sub(route.migrated_at) <= ttl);
+
+        for session_id in &migration.session_ids {
+            self.session_routes.routes.insert(session_id.clone(), SessionRoute {
+                from_node: migration.from_node.clone(),
+                to_node: migration.to_node.clone(),
+                migrated_at: now,
+            });
+        }
+
+        println!("🚚 {} game sessions now routed to {} ({})",
+                 migration.session_ids.len(), migration.to_node, migration.handoff_id);
+
+        let response_body = serde_json::to_vec(&serde_json::json!({
+            "handoff_id": migration.handoff_id,
+            "routed": migration.session_ids.len()
+        })).map_err(|e| format!("Failed to serialize response: {}", e))?;
+
+        Ok(HttpResponse {
+            status_code: 200,
+            headers: HashMap::from([
+                ("Content-Type".to_string(), "application/json".to_string()),
+            ]),
+            body: response_body,
+        })
+    }
+
+    /// 307 to the session's new node, keeping method and body, when the
+    /// call names a migrated session in `X-Game-Session`
+    pub fn session_redirect(&self, path: &str, headers: &HashMap<String, String>) -> Option<HttpResponse> {
+        let session_id = headers.get("X-Game-Session")?;
+        let route = self.session_routes.routes.get(session_id)?;
+
+        Some(HttpResponse {
+            status_code: 307,
+            headers: HashMap::from([
+                ("Location".to_string(), format!("{}{}", route.to_node.trim_end_matches('/'), path)),
+                ("X-Game-Session".to_string(), session_id.clone()),
+                ("Cache-Control".to_string(), "no-store".to_string()),
+            ]),
+            body: Vec::new(),
+        })
+    }
+}
@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+use crate::qr::QrCode;
+use crate::{HttpResponse, PublicGateway};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+const CODE_ALPHABET: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShortLink {
+    pub code: String,
+    pub link_id: String, // referral link this code resolves to
+    pub owner_wallet: String,
+    pub target_url: String,
+    pub created_at: u64,
+    pub expires_at: Option<u64>,
+    pub disabled: bool,
+    pub clicks: u32,
+    pub daily_clicks: HashMap<String, u32>, // YYYY-MM-DD -> clicks
+}
+
+impl PublicGateway {
+    pub(crate) fn create_short_link(&mut self, link_id: &str, owner_wallet: &str,
+                                    target_url: &str) -> Result<String, String> {
+        let commission_system = self.commission_system.as_mut()
+            .ok_or("Commission system not initialized")?;
+
+        let mut hasher = DefaultHasher::new();
+        link_id.hash(&mut hasher);
+        chrono::Utc::now().timestamp_nanos_opt().hash(&mut hasher);
+        let mut seed = hasher.finish();
+
+        let code = loop {
+            let code: String = (0..7)
+                .map(|i| CODE_ALPHABET[((seed >> (i * 6)) % 62) as usize] as char)
+                .collect();
+            if !commission_system.short_links.contains_key(&code) {
+                break code;
+            }
+            seed = seed.rotate_left(7).wrapping_add(1);
+        };
+
+        commission_system.short_links.insert(code.clone(), ShortLink {
+            code: code.clone(),
+            link_id: link_id.to_string(),
+            owner_wallet: owner_wallet.to_string(),
+            target_url: target_url.to_string(),
+            created_at: chrono::Utc::now().timestamp() as u64,
+            expires_at: None,
+            disabled: false,
+            clicks: 0,
+            daily_clicks: HashMap::new(),
+        });
+
+        Ok(format!("https://{}/r/{}", self.domain, code))
+    }
+
+    fn owned_short_link(&mut self, code: &str, owner_wallet: &str) -> Result<&mut ShortLink, String> {
+        let link = self.commission_system.as_mut()
+            .ok_or("Commission system not initialized")?
+            .short_links.get_mut(code)
+            .ok_or("Short link not found")?;
+
+        if link.owner_wallet != owner_wallet {
+            return Err("Short link belongs to another wallet".to_string());
+        }
+
+        Ok(link)
+    }
+
+    pub fn set_short_link_expiry(&mut self, code: &str, owner_wallet: &str,
+                                 expires_at: Option<u64>) -> Result<(), String> {
+        self.owned_short_link(code, owner_wallet)?.expires_at = expires_at;
+        Ok(())
+    }
+
+    pub fn set_short_link_disabled(&mut self, code: &str, owner_wallet: &str,
+                                   disabled: bool) -> Result<(), String> {
+        self.owned_short_link(code, owner_wallet)?.disabled = disabled;
+        Ok(())
+    }
+
+    /// `GET /r/{code}` → 302 to the referral URL, 404 if unknown, 410 if
+    /// expired or disabled. Every resolved click is counted per day.
+    pub fn handle_short_link(&mut self, code: &str) -> HttpResponse {
+        let now = chrono::Utc::now();
+        let link = self.commission_system.as_mut()
+            .and_then(|system| system.short_links.get_mut(code));
+
+        let (status_code, location) = match link {
+            None => (404, None),
+            Some(link) if link.disabled => (410, None),
+            Some(link) if link.expires_at.map(|t| now.timestamp() as u64 >= t).unwrap_or(false) => (410, None),
+            Some(link) => {
+                link.clicks += 1;
+                *link.daily_clicks.entry(now.format("%Y-%m-%d").to_string()).or_insert(0) += 1;
+                (302, Some(link.target_url.clone()))
+            }
+        };
+
+        let mut headers = HashMap::new();
+        if let Some(location) = location {
+            headers.insert("Location".to_string(), location);
+        }
+
+        HttpResponse { status_code, headers, body: Vec::new() }
+    }
+
+    /// Click analytics for the owner's dashboard
+    pub fn short_link_stats(&self, owner_wallet: &str) -> Vec<&ShortLink> {
+        self.commission_system.as_ref()
+            .map(|system| system.short_links.values()
+                .filter(|link| link.owner_wallet == owner_wallet)
+                .collect())
+            .unwrap_or_default()
+    }
+
+    /// `GET /r/{code}/qr.png` or `/r/{code}/qr.svg`
+    pub fn short_link_qr(&self, code: &str, format: &str) -> Result<HttpResponse, String> {
+        let exists = self.commission_system.as_ref()
+            .map(|system| system.short_links.contains_key(code))
+            .unwrap_or(false);
+        if !exists {
+            return Err("Short link not found".to_string());
+        }
+
+        let qr = QrCode::encode(&format!("https://{}/r/{}", self.domain, code))?;
+        let (content_type, body) = match format {
+            "png" => ("image/png", qr.to_png(8)),
+            "svg" => ("image/svg+xml", qr.to_svg(8).into_bytes()),
+            _ => return Err(format!("Unsupported QR format: {}", format)),
+        };
+
+        Ok(HttpResponse {
+            status_code: 200,
+            headers: HashMap::from([
+                ("Content-Type".to_string(), content_type.to_string()),
+                ("Cache-Control".to_string(), "public, max-age=86400".to_string()),
+            ]),
+            body,
+        })
+    }
+}
@@ -0,0 +1,428 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::journal::GatewayEvent;
+use crate::receipts::json_response;
+use crate::screening::is_operator;
+use crate::short_links::ShortLink;
+use crate::{HttpResponse, PeerConnection, ProtocolHandler, PublicGateway, ReferralLink, ServiceEndpoint};
+use std::collections::{BTreeMap, HashMap};
+
+/// Protocol a peer in the libp2p bridge must speak to be trusted with our
+/// service registry and referral links
+pub const SYNC_PROTOCOL: &str = "/zos/state-sync/1.0.0";
+
+/// When and where a value was last written. Later `at_ms` wins; the node id
+/// breaks ties, so every node settles on the same value.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Stamp {
+    pub at_ms: u64,
+    pub node_id: String,
+}
+
+/// Last-writer-wins register for one service, referral link or short link
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Register {
+    pub stamp: Stamp,
+    pub value: Option<Value>, // None: deleted, kept so peers delete it too
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Service,
+    ReferralLink,
+    ShortLink,
+}
+
+/// Service registry and referral links replicated between trusted nodes.
+/// Local changes are stamped when they are first seen by a gossip exchange;
+/// per-node fields (health, certification, click and conversion counts)
+/// are left out and stay each node's own.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StateSync {
+    pub node_id: String,
+    pub enabled: bool,
+    pub clock: u64, // highest stamp written or seen, in ms
+    pub services: BTreeMap<String, Register>, // service key -> register
+    pub referral_links: BTreeMap<String, Register>, // link id -> register
+    pub short_links: BTreeMap<String, Register>, // code -> register
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateGossip {
+    pub node_id: String,
+    pub sent_at: u64,
+    pub services: BTreeMap<String, Register>,
+    pub referral_links: BTreeMap<String, Register>,
+    pub short_links: BTreeMap<String, Register>,
+}
+
+fn service_value(service: &ServiceEndpoint) -> Value {
+    let mut service = service.clone();
+    service.health = Default::default();
+    service.certification = Default::default();
+    serde_json::to_value(service).unwrap_or(Value::Null)
+}
+
+fn referral_link_value(link: &ReferralLink) -> Value {
+    let mut link = link.clone();
+    link.click_count = 0;
+    link.conversion_count = 0;
+    link.promo_conversions = 0;
+    serde_json::to_value(link).unwrap_or(Value::Null)
+}
+
+fn short_link_value(link: &ShortLink) -> Value {
+    let mut link = link.clone();
+    link.clicks = 0;
+    link.daily_clicks.clear();
+    serde_json::to_value(link).unwrap_or(Value::Null)
+}
+
+impl StateSync {
+    fn registers(&self, kind: Kind) -> &BTreeMap<String, Register> {
+        match kind {
+            Kind::Service => &self.services,
+            Kind::ReferralLink => &self.referral_links,
+            Kind::ShortLink => &self.short_links,
+        }
+    }
+
+    fn registers_mut(&mut self, kind: Kind) -> &mut BTreeMap<String, Register> {
+        match kind {
+            Kind::Service => &mut self.services,
+            Kind::ReferralLink => &mut self.referral_links,
+            Kind::ShortLink => &mut self.short_links,
+        }
+    }
+
+    /// A stamp later than any written or seen, even if our clock is behind
+    fn tick(&mut self) -> Stamp {
+        let now_ms = chrono::Utc::now().timestamp_millis() as u64;
+        self.clock = now_ms.max(self.clock + 1);
+        Stamp { at_ms: self.clock, node_id: self.node_id.clone() }
+    }
+
+    /// Stamp whatever changed locally since the last exchange, including
+    /// deletions. Returns how many registers changed.
+    fn refresh(&mut self, kind: Kind, local: BTreeMap<String, Value>) -> usize {
+        let registers = self.registers(kind);
+        let mut changed: Vec<(String, Option<Value>)> = local.iter()
+            .filter(|(key, value)| registers.get(*key).and_then(|register| register.value.as_ref()) != Some(*value))
+            .map(|(key, value)| (key.clone(), Some(value.clone())))
+            .collect();
+        changed.extend(registers.iter()
+            .filter(|(key, register)| register.value.is_some() && !local.contains_key(*key))
+            .map(|(key, _)| (key.clone(), None)));
+
+        let count = changed.len();
+        for (key, value) in changed {
+            let stamp = self.tick();
+            self.registers_mut(kind).insert(key, Register { stamp, value });
+        }
+        count
+    }
+
+    /// Peer registers newer than ours, to be applied before they are accepted
+    fn newer(&self, kind: Kind, incoming: &BTreeMap<String, Register>) -> Vec<(String, Register)> {
+        let registers = self.registers(kind);
+        incoming.iter()
+            .filter(|(key, register)| registers.get(*key).is_none_or(|local| register.stamp > local.stamp))
+            .map(|(key, register)| (key.clone(), register.clone()))
+            .collect()
+    }
+
+    fn accept(&mut self, kind: Kind, key: String, register: Register) {
+        self.clock = self.clock.max(register.stamp.at_ms);
+        self.registers_mut(kind).insert(key, register);
+    }
+}
+
+impl PublicGateway {
+    pub fn enable_state_sync(&mut self, node_id: &str) {
+        self.state_sync.node_id = node_id.to_string();
+        self.state_sync.enabled = true;
+        self.libp2p_bridge.protocol_handlers.insert(SYNC_PROTOCOL.to_string(), ProtocolHandler {
+            protocol: SYNC_PROTOCOL.to_string(),
+            handler_type: "state_sync".to_string(),
+            port_mapping: 0,
+        });
+        println!("🔄 State sync enabled as {}", node_id);
+    }
+
+    /// Trust a peer node with our registry and accept its updates to ours,
+    /// its game session migrations and its rate limit counters
+    pub fn trust_sync_peer(&mut self, peer_id: &str, multiaddr: &str) {
+        let peer = self.libp2p_bridge.peer_connections.entry(peer_id.to_string())
+            .or_insert_with(|| PeerConnection {
+                peer_id: peer_id.to_string(),
+                multiaddr: multiaddr.to_string(),
+                protocols: Vec::new(),
+                last_seen: 0,
+            });
+        peer.multiaddr = multiaddr.to_string();
+        if !peer.protocols.iter().any(|protocol| protocol == SYNC_PROTOCOL) {
+            peer.protocols.push(SYNC_PROTOCOL.to_string());
+        }
+        println!("🔄 Trusting {} ({}) for state sync", peer_id, multiaddr);
+    }
+
+    pub fn is_trusted_sync_peer(&self, peer_id: &str) -> bool {
+        self.libp2p_bridge.peer_connections.get(peer_id)
+            .is_some_and(|peer| peer.protocols.iter().any(|protocol| protocol == SYNC_PROTOCOL))
+    }
+
+    fn refresh_state_sync(&mut self) {
+        let services = self.service_registry.iter()
+            .map(|(key, service)| (key.clone(), service_value(service)))
+            .collect();
+        let mut changed = self.state_sync.refresh(Kind::Service, services);
+
+        // Without a commission system there are no links to compare, and
+        // comparing would read as every link having been deleted
+        if let Some(system) = &self.commission_system {
+            let links = system.referral_links.iter()
+                .map(|(link_id, link)| (link_id.clone(), referral_link_value(link)))
+                .collect();
+            let short_links = system.short_links.iter()
+                .map(|(code, link)| (code.clone(), short_link_value(link)))
+                .collect();
+            changed += self.state_sync.refresh(Kind::ReferralLink, links);
+            changed += self.state_sync.refresh(Kind::ShortLink, short_links);
+        }
+        if changed > 0 {
+            println!("🔄 {} local registry changes stamped for sync", changed);
+        }
+    }
+
+    /// Our registers, to gossip to trusted peers over the libp2p bridge
+    pub fn state_gossip(&mut self) -> Result<StateGossip, String> {
+        if !self.state_sync.enabled {
+            return Err("State sync is not enabled".to_string());
+        }
+        self.refresh_state_sync();
+        let sync = &self.state_sync;
+        Ok(StateGossip {
+            node_id: sync.node_id.clone(),
+            sent_at: chrono::Utc::now().timestamp() as u64,
+            services: sync.services.clone(),
+            referral_links: sync.referral_links.clone(),
+            short_links: sync.short_links.clone(),
+        })
+    }
+
+    /// Apply a trusted peer's newer registers. Returns how many were applied.
+    pub fn merge_state_gossip(&mut self, gossip: &StateGossip) -> Result<usize, String> {
+        if !self.state_sync.enabled {
+            return Err("State sync is not enabled".to_string());
+        }
+        if gossip.node_id == self.state_sync.node_id {
+            return Ok(0);
+        }
+        if !self.is_trusted_sync_peer(&gossip.node_id) {
+            return Err(format!("{} is not a trusted sync peer", gossip.node_id));
+        }
+        if let Some(peer) = self.libp2p_bridge.peer_connections.get_mut(&gossip.node_id) {
+            peer.last_seen = chrono::Utc::now().timestamp() as u64;
+        }
+
+        // Stamp our own unsent changes first, so a later local edit is not
+        // overwritten by an older remote one
+        self.refresh_state_sync();
+
+        let mut applied = 0;
+        for (key, register) in self.state_sync.newer(Kind::Service, &gossip.services) {
+            match self.apply_service(&key, register.value.as_ref()) {
+                Ok(value) => {
+                    self.state_sync.accept(Kind::Service, key, Register { stamp: register.stamp, value });
+                    applied += 1;
+                }
+                Err(e) => println!("⚠️  Service {} from {} not applied: {}", key, gossip.node_id, e),
+            }
+        }
+
+        if self.commission_system.is_some() {
+            for (link_id, register) in self.state_sync.newer(Kind::ReferralLink, &gossip.referral_links) {
+                match self.apply_referral_link(&link_id, register.value.as_ref()) {
+                    Ok(value) => {
+                        self.state_sync.accept(Kind::ReferralLink, link_id, Register { stamp: register.stamp, value });
+                        applied += 1;
+                    }
+                    Err(e) => println!("⚠️  Referral link {} from {} not applied: {}", link_id, gossip.node_id, e),
+                }
+            }
+            for (code, register) in self.state_sync.newer(Kind::ShortLink, &gossip.short_links) {
+                match self.apply_short_link(&code, register.value.as_ref()) {
+                    Ok(value) => {
+                        self.state_sync.accept(Kind::ShortLink, code, Register { stamp: register.stamp, value });
+                        applied += 1;
+                    }
+                    Err(e) => println!("⚠️  Short link {} from {} not applied: {}", code, gossip.node_id, e),
+                }
+            }
+        } else if !gossip.referral_links.is_empty() {
+            println!("⚠️  Referral links from {} skipped: commission system not initialized", gossip.node_id);
+        }
+
+        if applied > 0 {
+            println!("🔄 Applied {} registry updates from {}", applied, gossip.node_id);
+        }
+        Ok(applied)
+    }
+
+    /// Returns the value as we now hold it, for the register
+    fn apply_service(&mut self, service_key: &str, value: Option<&Value>) -> Result<Option<Value>, String> {
+        let Some(value) = value else {
+            self.service_registry.remove(service_key);
+            return Ok(None);
+        };
+        let mut service: ServiceEndpoint = serde_json::from_value(value.clone())
+            .map_err(|e| format!("Invalid service: {}", e))?;
+        if let Some(local) = self.service_registry.get(service_key) {
+            service.health = local.health.clone();
+            service.certification = local.certification.clone();
+        }
+        let value = service_value(&service);
+        self.service_registry.insert(service_key.to_string(), service);
+        Ok(Some(value))
+    }
+
+    fn apply_referral_link(&mut self, link_id: &str, value: Option<&Value>) -> Result<Option<Value>, String> {
+        let system = self.commission_system.as_mut().ok_or("Commission system not initialized")?;
+        let Some(value) = value else {
+            system.referral_links.remove(link_id);
+            return Ok(None);
+        };
+        let mut link: ReferralLink = serde_json::from_value(value.clone())
+            .map_err(|e| format!("Invalid referral link: {}", e))?;
+        if let Some(local) = system.referral_links.get(link_id) {
+            link.click_count = local.click_count;
+            link.conversion_count = local.conversion_count;
+            link.promo_conversions = local.promo_conversions;
+        }
+        system.referral_links.insert(link_id.to_string(), link.clone());
+        let value = referral_link_value(&link);
+        // Replay rebuilds referral links from the journal
+        self.journal(GatewayEvent::ReferralLinkCreated { link });
+        Ok(Some(value))
+    }
+
+    fn apply_short_link(&mut self, code: &str, value: Option<&Value>) -> Result<Option<Value>, String> {
+        let system = self.commission_system.as_mut().ok_or("Commission system not initialized")?;
+        let Some(value) = value else {
+            system.short_links.remove(code);
+            return Ok(None);
+        };
+        let mut link: ShortLink = serde_json::from_value(value.clone())
+            .map_err(|e| format!("Invalid short link: {}", e))?;
+        if let Some(local) = system.short_links.get(code) {
+            link.clicks = local.clicks;
+            link.daily_clicks = local.daily_clicks.clone();
+        }
+        let value = short_link_value(&link);
+        system.short_links.insert(code.to_string(), link);
+        Ok(Some(value))
+    }
+
+    /// POST /cluster/state: merge a trusted peer's registers and answer with
+    /// ours, so one exchange syncs both sides. Peers share the operator key.
+    pub(crate) fn handle_state_gossip(&mut self, headers: &HashMap<String, String>,
+                                      body: &[u8]) -> Result<HttpResponse, String> {
+        if !is_operator(headers) {
+            return json_response(403, &serde_json::json!({ "error": "Needs X-Operator-Key" }));
+        }
+        let gossip: StateGossip = serde_json::from_slice(body)
+            .map_err(|e| format!("Invalid gossip: {}", e))?;
+        if !self.is_trusted_sync_peer(&gossip.node_id) {
+            return json_response(403, &serde_json::json!({ "error": format!("{} is not a trusted sync peer", gossip.node_id) }));
+        }
+
+        if let Err(e) = self.merge_state_gossip(&gossip) {
+            return json_response(409, &serde_json::json!({ "error": e }));
+        }
+        json_response(200, &self.state_gossip()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PricingTier;
+
+    fn node(node_id: &str, peer_id: &str) -> PublicGateway {
+        let mut gateway = PublicGateway::new("gateway.test");
+        gateway.enable_state_sync(node_id);
+        gateway.trust_sync_peer(peer_id, "/ip4/127.0.0.1/tcp/4001");
+        gateway
+    }
+
+    fn exchange(from: &mut PublicGateway, to: &mut PublicGateway) -> usize {
+        let gossip = from.state_gossip().unwrap();
+        to.merge_state_gossip(&gossip).unwrap()
+    }
+
+    #[test]
+    fn test_later_writes_win_and_deletions_replicate() {
+        let (mut a, mut b) = (node("node-a", "node-b"), node("node-b", "node-a"));
+        a.register_wallet_endpoint("owner1", "owner", vec![4001]).unwrap();
+        a.add_service("owner1", "api", 4001, PricingTier::Basic).unwrap();
+        assert_eq!(exchange(&mut a, &mut b), 1);
+        assert!(b.service_registry.contains_key("owner1_api"));
+
+        // Both edit; b's edit is stamped after a's, so it wins on both nodes
+        a.service_registry.get_mut("owner1_api").unwrap().payment_required = false;
+        a.state_gossip().unwrap();
+        b.service_registry.get_mut("owner1_api").unwrap().libp2p_port = 4002;
+        let from_b = b.state_gossip().unwrap();
+        let from_a = a.state_gossip().unwrap();
+        assert_eq!(a.merge_state_gossip(&from_b).unwrap(), 1);
+        assert_eq!(b.merge_state_gossip(&from_a).unwrap(), 0);
+        for node in [&a, &b] {
+            let service = &node.service_registry["owner1_api"];
+            assert_eq!(service.libp2p_port, 4002);
+            assert!(service.payment_required);
+        }
+
+        // A deletion travels as a tombstone and is not resurrected by stale gossip
+        let stale = a.state_gossip().unwrap();
+        b.service_registry.remove("owner1_api");
+        assert_eq!(exchange(&mut b, &mut a), 1);
+        assert!(!a.service_registry.contains_key("owner1_api"));
+        assert!(a.state_sync.services["owner1_api"].value.is_none());
+        assert_eq!(b.merge_state_gossip(&stale).unwrap(), 0);
+        assert!(!b.service_registry.contains_key("owner1_api"));
+    }
+
+    #[test]
+    fn test_untrusted_and_own_gossip_are_not_merged() {
+        let (mut a, mut b) = (node("node-a", "node-b"), node("node-b", "node-a"));
+        let mut stranger = node("node-x", "node-a");
+        stranger.register_wallet_endpoint("owner1", "owner", vec![4001]).unwrap();
+        stranger.add_service("owner1", "api", 4001, PricingTier::Basic).unwrap();
+        assert!(b.merge_state_gossip(&stranger.state_gossip().unwrap()).is_err());
+
+        let own = a.state_gossip().unwrap();
+        assert_eq!(a.merge_state_gossip(&own).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_links_wait_for_a_commission_system() {
+        let (mut a, mut b) = (node("node-a", "node-b"), node("node-b", "node-a"));
+        a.initialize_commission_system();
+        a.create_referral_link("referrer1", "owner1/api", HashMap::new()).unwrap();
+        let link_id = a.commission_system.as_ref().unwrap().referral_links.keys().next().unwrap().clone();
+
+        // Without a commission system b skips the links and records nothing for them
+        assert_eq!(exchange(&mut a, &mut b), 0);
+        assert!(b.state_sync.referral_links.is_empty() && b.state_sync.short_links.is_empty());
+
+        // ...and its gossip does not read as a deletion of a's links
+        assert_eq!(exchange(&mut b, &mut a), 0);
+        assert!(a.commission_system.as_ref().unwrap().referral_links.contains_key(&link_id));
+
+        // Once it has one, the next exchange brings the link and its short link over
+        b.initialize_commission_system();
+        assert_eq!(exchange(&mut a, &mut b), 2);
+        assert!(b.commission_system.as_ref().unwrap().referral_links.contains_key(&link_id));
+    }
+}
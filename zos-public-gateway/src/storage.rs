@@ -0,0 +1,432 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use crate::{CommissionSystem, HttpResponse, PaymentProcessor, PublicGateway};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// Object levels the WAL diffs into (component / field / key) before it
+/// writes a whole value; deep enough that one payment is a handful of entries
+const DIFF_DEPTH: usize = 3;
+
+/// The part of the gateway that must survive a restart: money owed, paid
+/// and promised
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersistedState {
+    pub commission_system: Option<CommissionSystem>,
+    pub payment_processor: PaymentProcessor,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum WalOp {
+    Set { path: Vec<String>, value: Value },
+    Remove { path: Vec<String> },
+}
+
+/// Everything one request changed. Written as one checksummed line, so a
+/// crash mid-payment leaves either the whole payment or none of it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalRecord {
+    pub seq: u64,
+    pub at: u64,
+    pub ops: Vec<WalOp>,
+}
+
+/// Where the gateway keeps its snapshot and write-ahead log
+pub trait GatewayStore {
+    /// Latest snapshot and the sequence number it includes
+    fn load_snapshot(&self) -> Result<Option<(u64, Value)>, String>;
+    /// Records after `seq`, in order; a torn last record is dropped
+    fn read_wal(&mut self, after_seq: u64) -> Result<Vec<WalRecord>, String>;
+    /// Durable when it returns
+    fn append(&mut self, record: &WalRecord) -> Result<(), String>;
+    /// Replace the snapshot, then forget the records it covers
+    fn write_snapshot(&mut self, seq: u64, state: &Value) -> Result<(), String>;
+}
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotFile {
+    seq: u64,
+    state: Value,
+}
+
+#[derive(Serialize, Deserialize)]
+struct WalLine {
+    checksum: String, // sha256 hex of the record's JSON
+    record: WalRecord,
+}
+
+fn checksum(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Snapshot and WAL as files in one directory: `snapshot.json`, replaced
+/// atomically, and `wal.jsonl`, one fsynced record per line
+#[derive(Debug)]
+pub struct FileStore {
+    dir: PathBuf,
+}
+
+impl FileStore {
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, String> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        Ok(Self { dir })
+    }
+
+    /// ZOS_GATEWAY_STATE_DIR, else `gateway-state` under the working directory
+    pub fn from_env() -> Result<Self, String> {
+        Self::open(std::env::var("ZOS_GATEWAY_STATE_DIR").unwrap_or_else(|_| "gateway-state".to_string()))
+    }
+
+    fn snapshot_path(&self) -> PathBuf {
+        self.dir.join("snapshot.json")
+    }
+
+    fn wal_path(&self) -> PathBuf {
+        self.dir.join("wal.jsonl")
+    }
+}
+
+impl GatewayStore for FileStore {
+    fn load_snapshot(&self) -> Result<Option<(u64, Value)>, String> {
+        let bytes = match fs::read(self.snapshot_path()) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("Failed to read snapshot: {}", e)),
+        };
+        let snapshot: SnapshotFile = serde_json::from_slice(&bytes)
+            .map_err(|e| format!("Corrupt snapshot: {}", e))?;
+        Ok(Some((snapshot.seq, snapshot.state)))
+    }
+
+    fn read_wal(&mut self, after_seq: u64) -> Result<Vec<WalRecord>, String> {
+        let file = match File::open(self.wal_path()) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("Failed to open WAL: {}", e)),
+        };
+
+        let mut records = Vec::new();
+        let mut good_bytes = 0u64;
+        let mut torn = false;
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|e| format!("Failed to read WAL: {}", e))?;
+            let parsed = serde_json::from_str::<WalLine>(&line).ok().filter(|entry| {
+                serde_json::to_vec(&entry.record).is_ok_and(|bytes| checksum(&bytes) == entry.checksum)
+            });
+            let Some(entry) = parsed else {
+                // Only the last append can be partial; nothing after it was acknowledged
+                torn = true;
+                break;
+            };
+            good_bytes += line.len() as u64 + 1;
+            if entry.record.seq > after_seq {
+                records.push(entry.record);
+            }
+        }
+
+        if torn {
+            println!("⚠️  Dropping torn WAL tail after {} bytes", good_bytes);
+            OpenOptions::new()
+                .write(true)
+                .open(self.wal_path())
+                .and_then(|file| file.set_len(good_bytes))
+                .map_err(|e| format!("Failed to truncate WAL: {}", e))?;
+        }
+        Ok(records)
+    }
+
+    fn append(&mut self, record: &WalRecord) -> Result<(), String> {
+        let bytes = serde_json::to_vec(record)
+            .map_err(|e| format!("Failed to serialize WAL record: {}", e))?;
+        let line = serde_json::to_string(&WalLine { checksum: checksum(&bytes), record: record.clone() })
+            .map_err(|e| format!("Failed to serialize WAL record: {}", e))?;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.wal_path())
+            .map_err(|e| format!("Failed to open WAL: {}", e))?;
+        file.write_all(format!("{}\n", line).as_bytes())
+            .and_then(|_| file.sync_data())
+            .map_err(|e| format!("Failed to append to WAL: {}", e))
+    }
+
+    fn write_snapshot(&mut self, seq: u64, state: &Value) -> Result<(), String> {
+        let bytes = serde_json::to_vec(&SnapshotFile { seq, state: state.clone() })
+            .map_err(|e| format!("Failed to serialize snapshot: {}", e))?;
+        let tmp = self.dir.join("snapshot.json.tmp");
+        File::create(&tmp)
+            .and_then(|mut file| file.write_all(&bytes).and_then(|_| file.sync_all()))
+            .and_then(|_| fs::rename(&tmp, self.snapshot_path()))
+            .map_err(|e| format!("Failed to write snapshot: {}", e))?;
+
+        // Records up to `seq` are in the snapshot now; a crash before this
+        // truncate only means they are skipped on replay
+        File::create(self.wal_path())
+            .and_then(|file| file.sync_all())
+            .map_err(|e| format!("Failed to truncate WAL: {}", e))
+    }
+}
+
+fn diff(before: &Value, after: &Value, path: &mut Vec<String>, ops: &mut Vec<WalOp>) {
+    match (before, after) {
+        (Value::Object(old), Value::Object(new)) if path.len() < DIFF_DEPTH => {
+            for (key, value) in new {
+                path.push(key.clone());
+                match old.get(key) {
+                    Some(previous) => diff(previous, value, path, ops),
+                    None => ops.push(WalOp::Set { path: path.clone(), value: value.clone() }),
+                }
+                path.pop();
+            }
+            for key in old.keys().filter(|key| !new.contains_key(*key)) {
+                path.push(key.clone());
+                ops.push(WalOp::Remove { path: path.clone() });
+                path.pop();
+            }
+        }
+        _ if before != after => ops.push(WalOp::Set { path: path.clone(), value: after.clone() }),
+        _ => {}
+    }
+}
+
+fn apply(state: &mut Value, op: &WalOp) {
+    let (path, value) = match op {
+        WalOp::Set { path, value } => (path, Some(value)),
+        WalOp::Remove { path } => (path, None),
+    };
+    let Some((last, parents)) = path.split_last() else {
+        if let Some(value) = value {
+            *state = value.clone();
+        }
+        return;
+    };
+
+    let mut node = state;
+    for key in parents {
+        if !node.is_object() {
+            *node = Value::Object(Map::new());
+        }
+        let Value::Object(object) = node else { return };
+        node = object.entry(key.clone()).or_insert(Value::Null);
+    }
+    if !node.is_object() {
+        *node = Value::Object(Map::new());
+    }
+    let Value::Object(object) = node else { return };
+    match value {
+        Some(value) => {
+            object.insert(last.clone(), value.clone());
+        }
+        None => {
+            object.remove(last);
+        }
+    }
+}
+
+/// A gateway whose commissions, earnings, referrals and payments are logged
+/// to a `GatewayStore` after every request and snapshotted periodically
+pub struct DurableGateway<S: GatewayStore> {
+    pub gateway: PublicGateway,
+    store: S,
+    seq: u64,
+    persisted: Value, // state as of the last WAL record
+    records_since_snapshot: u64,
+    pub snapshot_every: u64, // WAL records between snapshots
+}
+
+impl PublicGateway {
+    pub fn persisted_state(&self) -> PersistedState {
+        PersistedState {
+            commission_system: self.commission_system.clone(),
+            payment_processor: self.payment_processor.clone(),
+        }
+    }
+
+    pub fn restore_persisted_state(&mut self, state: PersistedState) {
+        self.commission_system = state.commission_system;
+        self.payment_processor = state.payment_processor;
+        if let Some(commission_system) = self.commission_system.as_mut() {
+            commission_system.rebuild_referee_index();
+            let carried = commission_system.carry_over_legacy_accounts();
+            if carried > 0 {
+                println!("📜 Carried {} earnings accounts over into the event log", carried);
+            }
+        }
+        self.carry_over_into_journal();
+    }
+}
+
+impl<S: GatewayStore> DurableGateway<S> {
+    /// Rebuild the gateway from the snapshot plus every WAL record after it
+    pub fn open(mut store: S, domain: &str) -> Result<Self, String> {
+        let mut gateway = PublicGateway::new(domain);
+        let (mut seq, mut state) = match store.load_snapshot()? {
+            Some((seq, state)) => (seq, state),
+            None => (0, serde_json::to_value(gateway.persisted_state())
+                .map_err(|e| format!("Failed to serialize gateway state: {}", e))?),
+        };
+
+        let records = store.read_wal(seq)?;
+        let replayed = records.len();
+        for record in records {
+            for op in &record.ops {
+                apply(&mut state, op);
+            }
+            seq = record.seq;
+        }
+
+        let restored: PersistedState = serde_json::from_value(state.clone())
+            .map_err(|e| format!("Stored gateway state does not load: {}", e))?;
+        gateway.restore_persisted_state(restored);
+        println!("💾 Gateway state restored at seq {} ({} WAL records replayed)", seq, replayed);
+
+        Ok(Self {
+            gateway,
+            store,
+            seq,
+            persisted: state,
+            records_since_snapshot: replayed as u64,
+            snapshot_every: 1000,
+        })
+    }
+
+    /// Handle the request, then log what it changed before answering. If
+    /// the log write fails the caller gets an error, not an unrecorded success.
+    pub fn handle_http_request(&mut self, path: &str, method: &str,
+                               headers: &HashMap<String, String>,
+                               body: &[u8]) -> Result<HttpResponse, String> {
+        let result = self.gateway.handle_http_request(path, method, headers, body);
+        self.commit()?;
+        result
+    }
+
+    /// Log changes made to the gateway since the last commit; call after
+    /// mutating it outside `handle_http_request`. Returns the ops written.
+    pub fn commit(&mut self) -> Result<usize, String> {
+        self.gateway.flush_journal();
+        let state = serde_json::to_value(self.gateway.persisted_state())
+            .map_err(|e| format!("Failed to serialize gateway state: {}", e))?;
+        let mut ops = Vec::new();
+        diff(&self.persisted, &state, &mut Vec::new(), &mut ops);
+        if ops.is_empty() {
+            return Ok(0);
+        }
+
+        let record = WalRecord {
+            seq: self.seq + 1,
+            at: chrono::Utc::now().timestamp() as u64,
+            ops,
+        };
+        self.store.append(&record)?;
+        self.seq = record.seq;
+        self.persisted = state;
+        self.records_since_snapshot += 1;
+
+        if self.records_since_snapshot >= self.snapshot_every {
+            self.snapshot()?;
+        }
+        Ok(record.ops.len())
+    }
+
+    /// Write a snapshot of the committed state and start a fresh WAL
+    pub fn snapshot(&mut self) -> Result<(), String> {
+        self.store.write_snapshot(self.seq, &self.persisted)?;
+        self.records_since_snapshot = 0;
+        println!("💾 Gateway snapshot at seq {}", self.seq);
+        Ok(())
+    }
+
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("zos-gateway-store-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_diff_and_apply_round_trip() {
+        let before = serde_json::json!({ "a": { "b": { "c": 1, "d": [1, 2] }, "gone": true }, "x": 1 });
+        let after = serde_json::json!({ "a": { "b": { "c": 2, "d": [1, 2], "e": "new" } }, "x": 1, "y": null });
+        let mut ops = Vec::new();
+        diff(&before, &after, &mut Vec::new(), &mut ops);
+        assert_eq!(ops.len(), 4); // c, e, y set; gone removed
+
+        let mut replayed = before.clone();
+        for op in &ops {
+            apply(&mut replayed, op);
+        }
+        assert_eq!(replayed, after);
+    }
+
+    #[test]
+    fn test_wal_replays_after_the_snapshot_and_drops_a_torn_tail() {
+        let dir = scratch("torn");
+        let mut store = FileStore::open(&dir).unwrap();
+        let record = |seq| WalRecord {
+            seq,
+            at: 0,
+            ops: vec![WalOp::Set { path: vec!["n".to_string()], value: serde_json::json!(seq) }],
+        };
+        for seq in 1..=3 {
+            store.append(&record(seq)).unwrap();
+        }
+        assert_eq!(store.read_wal(1).unwrap().iter().map(|r| r.seq).collect::<Vec<_>>(), vec![2, 3]);
+
+        // A crash mid-append leaves half a line; it is dropped and the file cut back
+        let mut wal = OpenOptions::new().append(true).open(store.wal_path()).unwrap();
+        wal.write_all(b"{\"checksum\":\"00\",\"rec").unwrap();
+        assert_eq!(store.read_wal(0).unwrap().len(), 3);
+        let size = fs::metadata(store.wal_path()).unwrap().len();
+        store.append(&record(4)).unwrap();
+        assert_eq!(store.read_wal(0).unwrap().len(), 4);
+        assert!(fs::metadata(store.wal_path()).unwrap().len() > size);
+
+        // A snapshot covers the records and empties the WAL
+        store.write_snapshot(4, &serde_json::json!({ "n": 4 })).unwrap();
+        assert_eq!(store.load_snapshot().unwrap(), Some((4, serde_json::json!({ "n": 4 }))));
+        assert!(store.read_wal(0).unwrap().is_empty());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_wal_stays_bounded_by_snapshots_and_state_survives_reopen() {
+        let dir = scratch("reopen");
+        let mut durable = DurableGateway::open(FileStore::open(&dir).unwrap(), "gateway.test").unwrap();
+        durable.snapshot_every = 3;
+        assert_eq!(durable.commit().unwrap(), 0); // nothing changed yet
+
+        durable.gateway.initialize_commission_system();
+        assert!(durable.commit().unwrap() > 0);
+        for n in 0..4 {
+            durable.gateway.commission_system.as_mut().unwrap().event_seq = n + 1;
+            durable.commit().unwrap();
+        }
+        assert_eq!(durable.seq(), 5);
+
+        // Only records after the last snapshot remain to replay
+        let mut store = FileStore::open(&dir).unwrap();
+        let (snapshot_seq, _) = store.load_snapshot().unwrap().unwrap();
+        assert_eq!(snapshot_seq, 3);
+        assert_eq!(store.read_wal(snapshot_seq).unwrap().len(), 2);
+
+        let reopened = DurableGateway::open(store, "gateway.test").unwrap();
+        assert_eq!(reopened.seq(), 5);
+        assert_eq!(reopened.gateway.commission_system.as_ref().unwrap().event_seq, 4);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,297 @@
+use serde::{Deserialize, Serialize};
+use crate::passthrough::AuthorizedCall;
+use crate::receipts;
+use crate::sandbox;
+use crate::trace_context::{OpenSpan, TraceParent};
+use crate::transforms;
+use crate::{HttpResponse, PublicGateway, ServiceEndpoint};
+use std::collections::HashMap;
+
+/// Path segments after /{wallet}/{service} that the gateway answers itself
+const RESERVED_ACTIONS: [&str; 8] = [
+    "swap", "quote", "estimate", "contract", "experiments", "commission-plan", "transforms", "cors",
+];
+
+/// How a service's responses reach the caller
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ResponseMode {
+    #[default]
+    Buffered, // whole body from forward_to_libp2p; contract-checked, mirrored and edge-cacheable
+    Streamed, // chunks forwarded as the service produces them; none of the above, nor response transforms
+}
+
+/// A service's response as it is produced. Reading blocks on the libp2p
+/// stream, so it happens off the async workers.
+pub type ChunkStream = Box<dyn Iterator<Item = Result<Vec<u8>, String>> + Send>;
+
+/// Service key, response headers and body of a stream the node opened
+type OpenedStream = (String, Option<String>, HashMap<String, String>, ChunkStream);
+
+/// Wire format of a streamed response
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StreamFormat {
+    Chunked,     // the service's bytes as-is, with chunked transfer encoding
+    EventStream, // SSE: a `data` event per chunk, then an `end` or `error` event
+}
+
+impl StreamFormat {
+    /// SSE for callers that accept it, e.g. browsers using EventSource
+    pub fn for_request(headers: &HashMap<String, String>) -> Self {
+        match headers.get("Accept") {
+            Some(accept) if accept.contains("text/event-stream") => StreamFormat::EventStream,
+            _ => StreamFormat::Chunked,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            StreamFormat::Chunked => "application/octet-stream",
+            StreamFormat::EventStream => "text/event-stream",
+        }
+    }
+}
+
+/// Turns a service's chunks into frames of the wire format. SSE carries
+/// text, so a UTF-8 character split across chunks is held until it's whole.
+pub struct StreamFramer {
+    format: StreamFormat,
+    partial: Vec<u8>,
+}
+
+impl StreamFramer {
+    pub fn new(format: StreamFormat) -> Self {
+        Self { format, partial: Vec::new() }
+    }
+
+    /// The frame to send for a chunk; None while there's nothing whole to send
+    pub fn frame(&mut self, chunk: &[u8]) -> Option<Vec<u8>> {
+        match self.format {
+            StreamFormat::Chunked => (!chunk.is_empty()).then(|| chunk.to_vec()),
+            StreamFormat::EventStream => {
+                self.partial.extend_from_slice(chunk);
+                let whole = match std::str::from_utf8(&self.partial) {
+                    Ok(text) => text.len(),
+                    Err(e) if e.error_len().is_none() => e.valid_up_to(), // incomplete character at the end
+                    Err(_) => self.partial.len(), // not text; sent lossily
+                };
+                if whole == 0 {
+                    return None;
+                }
+                let rest = self.partial.split_off(whole);
+                let text = String::from_utf8_lossy(&std::mem::replace(&mut self.partial, rest)).into_owned();
+                Some(sse_event(None, &text))
+            }
+        }
+    }
+
+    /// The closing frame. A chunked response can only report a failure by
+    /// ending early, so its error comes back as Err for the body to abort with.
+    pub fn finish(&mut self, error: Option<&str>) -> Result<Option<Vec<u8>>, String> {
+        match (self.format, error) {
+            (StreamFormat::Chunked, None) => Ok(None),
+            (StreamFormat::Chunked, Some(error)) => Err(error.to_string()),
+            (StreamFormat::EventStream, error) => {
+                let mut frame = std::mem::take(&mut self.partial);
+                if !frame.is_empty() {
+                    frame = sse_event(None, &String::from_utf8_lossy(&frame));
+                }
+                match error {
+                    None => frame.extend(sse_event(Some("end"), "")),
+                    Some(error) => frame.extend(sse_event(Some("error"), error)),
+                }
+                Ok(Some(frame))
+            }
+        }
+    }
+}
+
+/// One SSE event; each line of the payload gets its own `data` field
+fn sse_event(event: Option<&str>, data: &str) -> Vec<u8> {
+    let mut frame = String::new();
+    if let Some(event) = event {
+        frame.push_str(&format!("event: {}\n", event));
+    }
+    for line in data.split('\n') {
+        frame.push_str(&format!("data: {}\n", line.trim_end_matches('\r')));
+    }
+    frame.push('\n');
+    frame.into_bytes()
+}
+
+/// An authorized and charged call whose response is still to be read
+pub struct StreamingCall {
+    pub service_key: String,
+    pub format: StreamFormat,
+    pub response_headers: HashMap<String, String>, // sent before the first chunk
+    pub chunks: ChunkStream,
+    pub span: OpenSpan,
+    pub sampled: bool,
+    pub caller: Option<String>, // authenticated as the stream opened, metered once it ends
+}
+
+/// What went over a finished stream, for `finish_stream`
+#[derive(Debug, Clone, Default)]
+pub struct StreamSummary {
+    pub bytes: usize,
+    pub first_chunk_ms: Option<u64>,
+    pub elapsed_ms: u64,
+    pub error: Option<String>, // the service failed or the caller went away
+}
+
+impl PublicGateway {
+    pub fn set_response_mode(&mut self, service_key: &str, mode: ResponseMode) -> Result<(), String> {
+        self.service_registry.get_mut(service_key)
+            .ok_or("Service not found")?
+            .response_mode = mode;
+        Ok(())
+    }
+
+    /// Whether a request is a call to a service that streams its responses;
+    /// those go through `open_stream` instead of `handle_http_request`
+    pub fn streams_response(&self, path: &str, headers: &HashMap<String, String>) -> bool {
+        if !self.sandbox_mode && sandbox::is_sandbox_request(headers) {
+            return false;
+        }
+        let path_parts: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+        if path_parts.len() < 2 || path_parts.get(2).is_some_and(|action| RESERVED_ACTIONS.contains(action)) {
+            return false;
+        }
+        let service_key = format!("{}_{}", path_parts[0], path_parts[1]);
+        self.service_registry.get(&service_key)
+            .is_some_and(|service| service.response_mode == ResponseMode::Streamed)
+    }
+
+    /// Authorize and charge a call to a streaming service, then open its
+    /// response stream. The charge covers the request body, as for buffered
+    /// calls; the response is metered by `finish_stream` once it has been sent.
+    /// Ok(Err(response)) is a response to send as-is.
+    pub fn open_stream(&mut self, path: &str, method: &str, headers: &HashMap<String, String>,
+                       body: &[u8]) -> Result<Result<StreamingCall, HttpResponse>, String> {
+        if let Some(redirect) = self.session_redirect(path, headers) {
+            return Ok(Err(redirect));
+        }
+
+        let parent = TraceParent::from_headers(headers);
+        let trace = parent.as_ref().map(TraceParent::child).unwrap_or_else(TraceParent::root);
+        let span = OpenSpan::start(&trace, parent.as_ref(), &format!("{} {}", method, path), "gateway");
+
+        let opened = self.open_service_stream(path, method, headers, body, &trace);
+        self.record_service_request(path, match &opened {
+            Ok(Ok(_)) => 200,
+            Ok(Err(response)) => response.status_code,
+            Err(_) => 400,
+        });
+        match opened {
+            Ok(Ok((service_key, caller, mut response_headers, chunks))) => {
+                let format = StreamFormat::for_request(headers);
+                response_headers.insert("Content-Type".to_string(), format.content_type().to_string());
+                response_headers.insert("Cache-Control".to_string(), "no-cache".to_string());
+                response_headers.insert("X-Accel-Buffering".to_string(), "no".to_string()); // nginx would otherwise buffer it all
+                response_headers.insert("traceparent".to_string(), trace.header_value());
+                Ok(Ok(StreamingCall { service_key, format, response_headers, chunks, span, sampled: trace.sampled, caller }))
+            }
+            Ok(Err(response)) => {
+                self.traces.record(span.finish(Some(response.status_code), None), trace.sampled);
+                Ok(Err(response))
+            }
+            Err(e) => {
+                self.traces.record(span.finish(None, Some(e.clone())), trace.sampled);
+                Err(e)
+            }
+        }
+    }
+
+    fn open_service_stream(&mut self, path: &str, method: &str, headers: &HashMap<String, String>,
+                           body: &[u8], trace: &TraceParent)
+                           -> Result<Result<OpenedStream, HttpResponse>, String> {
+        let path_parts: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+        let (wallet_address, service_name) = match path_parts.as_slice() {
+            [wallet, service, ..] => (*wallet, *service),
+            _ => return Err("Invalid path format. Expected: /{wallet}/{service}".to_string()),
+        };
+
+        let service_key = format!("{}_{}", wallet_address, service_name);
+        if let Some(rejection) = self.check_request_contract(&service_key, body) {
+            return Ok(Err(rejection));
+        }
+        let AuthorizedCall { caller, estimate } = match self.authorize_service_call(wallet_address, service_name, headers)? {
+            Ok(call) => call,
+            Err(response) => return Ok(Err(response)),
+        };
+
+        let service = self.service_registry.get(&service_key)
+            .ok_or("Service not found")?
+            .clone();
+
+        let forwarded = match self.transform_request(&service_key, method, path, headers, body) {
+            Ok(forwarded) => forwarded,
+            Err(response) => return Ok(Err(response)),
+        };
+        let (forwarded_headers, forwarded_body) = match &forwarded {
+            Some(message) => (message.headers.clone(), message.body.as_slice()),
+            None => (transforms::forwarded_headers(headers), body),
+        };
+
+        // Nothing is charged unless the service accepts the call
+        let hop = trace.child();
+        let hop_span = OpenSpan::start(&hop, Some(trace), "libp2p.stream", "libp2p");
+        let chunks = self.stream_from_libp2p(&service, method, &forwarded_headers, forwarded_body, &hop);
+        let hop_span = match &chunks {
+            Ok(_) => hop_span.finish(None, None),
+            Err(e) => hop_span.finish(None, Some(e.clone())),
+        };
+        self.traces.record(hop_span, hop.sampled);
+        let chunks = chunks?;
+
+        let mut response_headers = self.cors_response_headers(path, headers);
+        if let Some(estimate) = &estimate {
+            response_headers.insert("X-Estimate-Id".to_string(), estimate.estimate_id.clone());
+            response_headers.insert("X-Charge-USDC".to_string(), format!("{:.6}", estimate.total_usdc));
+        }
+        let charged = if service.payment_required {
+            let amount = receipts::call_charge(&service.pricing, body.len(), estimate.as_ref());
+            let (amount, redemption) = self.redeem_coupon(&service_key, caller.as_deref(), headers, amount)?;
+            let hash = receipts::request_hash(method, path, body);
+            let estimate_id = estimate.as_ref().map(|estimate| estimate.estimate_id.clone());
+            let receipt = self.issue_receipt(&service_key, headers, amount, hash, estimate_id)?;
+            response_headers.insert("X-Receipt-Id".to_string(), receipt.receipt_id);
+            if let Some(redemption) = redemption {
+                response_headers.insert("X-Coupon-Code".to_string(), redemption.code);
+                response_headers.insert("X-Discount-USDC".to_string(), format!("{:.6}", redemption.discount_usdc));
+            }
+            Some(receipt.amount_usdc)
+        } else {
+            None
+        };
+        self.record_call(&service_key, charged);
+        self.record_consumer_usage(&service_key, caller.as_deref(), body.len(), charged);
+
+        Ok(Ok((service_key, caller, response_headers, chunks)))
+    }
+
+    /// Meter a stream once it has ended: the bytes sent count toward usage
+    /// and bandwidth, and the time to the first chunk toward certification
+    pub fn finish_stream(&mut self, call_service_key: &str, caller: Option<&str>,
+                         span: OpenSpan, sampled: bool, summary: &StreamSummary) {
+        self.traces.record(span.finish(Some(200), summary.error.clone()), sampled);
+        self.meter_usage(call_service_key, caller, summary.bytes, summary.elapsed_ms as f64 / 1000.0);
+        let Some(service) = self.service_registry.get_mut(call_service_key) else { return };
+        if let Some(first_chunk_ms) = summary.first_chunk_ms {
+            service.certification.record_latency(first_chunk_ms);
+        }
+        let wallet_address = service.wallet_address.clone();
+        self.record_bandwidth(&wallet_address, summary.bytes);
+    }
+
+    fn stream_from_libp2p(&self, service: &ServiceEndpoint, method: &str, headers: &HashMap<String, String>,
+                          body: &[u8], trace: &TraceParent) -> Result<ChunkStream, String> {
+        // Simplified libp2p streaming
+        // In real implementation, would open a libp2p stream to the service
+        // and yield each frame as the node reads it
+        let response = self.forward_to_libp2p(service, method, headers, body, trace)?;
+        let chunks: Vec<Result<Vec<u8>, String>> = response.chunks(256)
+            .map(|chunk| Ok(chunk.to_vec()))
+            .collect();
+        Ok(Box::new(chunks.into_iter()))
+    }
+}
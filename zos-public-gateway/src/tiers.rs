@@ -0,0 +1,310 @@
+use serde::{Deserialize, Serialize};
+use crate::commission_events::CommissionEventKind;
+use crate::journal::GatewayEvent;
+use crate::receipts::json_response;
+use crate::screening::is_operator;
+use crate::{CommissionSystem, CommissionType, EarningsTier, HttpResponse, PublicGateway, ReferralStatus};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// How often the periodic recalculation re-derives every tier
+pub const TIER_RECALC_INTERVAL_SECS: u64 = 3600;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TierChangeEvent {
+    pub wallet_address: String,
+    pub from: EarningsTier,
+    pub to: EarningsTier,
+    pub referral_count: u32,
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MilestoneReward {
+    pub milestone_id: String,
+    pub tier: EarningsTier, // granted the first time a wallet reaches this tier
+    pub bonus_commission_usdc: f64,
+    pub credit_grant: u64,
+    pub badge: Option<String>, // shared with the retro games achievements
+}
+
+/// Credits and badges owed to a wallet, drained by the node and applied to
+/// the credit ledger and achievements
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GrantedReward {
+    pub wallet_address: String,
+    pub milestone_id: String,
+    pub credit_grant: u64,
+    pub badge: Option<String>,
+    pub granted_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TierProgress {
+    pub current_tier: EarningsTier,
+    pub next_tier: Option<EarningsTier>,
+    pub referral_count: u32,
+    pub referrals_needed: u32,
+    pub percent: f64,
+}
+
+pub fn default_milestones() -> Vec<MilestoneReward> {
+    vec![
+        MilestoneReward {
+            milestone_id: "silver_referrer".to_string(),
+            tier: EarningsTier::Silver,
+            bonus_commission_usdc: 5.0,
+            credit_grant: 500,
+            badge: Some("🥈 Silver Referrer".to_string()),
+        },
+        MilestoneReward {
+            milestone_id: "gold_referrer".to_string(),
+            tier: EarningsTier::Gold,
+            bonus_commission_usdc: 25.0,
+            credit_grant: 2000,
+            badge: Some("🥇 Gold Referrer".to_string()),
+        },
+        MilestoneReward {
+            milestone_id: "platinum_referrer".to_string(),
+            tier: EarningsTier::Platinum,
+            bonus_commission_usdc: 100.0,
+            credit_grant: 10000,
+            badge: Some("💎 Platinum Referrer".to_string()),
+        },
+    ]
+}
+
+/// Referral count at which a tier starts, matching `calculate_earnings_tier`
+pub(crate) fn tier_threshold(tier: &EarningsTier) -> u32 {
+    match tier {
+        EarningsTier::Bronze => 0,
+        EarningsTier::Silver => 11,
+        EarningsTier::Gold => 51,
+        EarningsTier::Platinum => 201,
+    }
+}
+
+/// Graduated referees were referred successfully and keep counting; Inactive
+/// and Suspended ones stop counting until they are Active again
+fn counts_toward_tier(status: &ReferralStatus) -> bool {
+    matches!(status, ReferralStatus::Active | ReferralStatus::Graduated)
+}
+
+pub(crate) fn next_tier(tier: &EarningsTier) -> Option<EarningsTier> {
+    match tier {
+        EarningsTier::Bronze => Some(EarningsTier::Silver),
+        EarningsTier::Silver => Some(EarningsTier::Gold),
+        EarningsTier::Gold => Some(EarningsTier::Platinum),
+        EarningsTier::Platinum => None,
+    }
+}
+
+impl CommissionSystem {
+    /// Distinct referees counting toward the referrer's tier
+    pub fn active_referral_count(&self, referrer_wallet: &str) -> u32 {
+        self.referral_tracking.values()
+            .filter(|referral| referral.referrer_wallet == referrer_wallet && counts_toward_tier(&referral.status))
+            .map(|referral| referral.referee_wallet.as_str())
+            .collect::<HashSet<_>>()
+            .len() as u32
+    }
+}
+
+impl PublicGateway {
+    /// Derive a wallet's referral count and tier from its referral records,
+    /// promoting or demoting it. Demotion grants nothing, and milestones
+    /// already claimed aren't granted again on the way back up.
+    pub fn recalculate_tier(&mut self, wallet_address: &str) -> Result<Option<TierChangeEvent>, String> {
+        let commission_system = self.commission_system.as_mut()
+            .ok_or("Commission system not initialized")?;
+        let referral_count = commission_system.active_referral_count(wallet_address);
+        let (tier, counted) = commission_system.earnings_ledger.get(wallet_address)
+            .map_or((EarningsTier::Bronze, 0), |account| (account.tier.clone(), account.referral_count));
+
+        if counted != referral_count {
+            commission_system.record_event(wallet_address, CommissionEventKind::ReferralsCounted { referral_count });
+        }
+
+        let new_tier = self.calculate_earnings_tier(referral_count);
+        if std::mem::discriminant(&new_tier) == std::mem::discriminant(&tier) {
+            return Ok(None);
+        }
+
+        self.commission_system.as_mut()
+            .ok_or("Commission system not initialized")?
+            .record_event(wallet_address, CommissionEventKind::TierChanged {
+                from: tier.clone(),
+                to: new_tier.clone(),
+                referral_count,
+            });
+        self.on_tier_change(wallet_address, tier, new_tier, referral_count)?;
+
+        Ok(self.commission_system.as_ref()
+            .and_then(|system| system.tier_events.iter().rev()
+                .find(|event| event.wallet_address == wallet_address))
+            .cloned())
+    }
+
+    /// Recalculate every wallet with an earnings account or a referral,
+    /// returning the tier changes. Run periodically, it also corrects counts
+    /// inflated before tiers were derived from referral records.
+    pub fn recalculate_all_tiers(&mut self) -> Result<Vec<TierChangeEvent>, String> {
+        let commission_system = self.commission_system.as_ref()
+            .ok_or("Commission system not initialized")?;
+        let wallets: BTreeSet<String> = commission_system.earnings_ledger.keys().cloned()
+            .chain(commission_system.referral_tracking.values().map(|referral| referral.referrer_wallet.clone()))
+            .collect();
+
+        let mut changes = Vec::new();
+        for wallet_address in wallets {
+            if let Some(change) = self.recalculate_tier(&wallet_address)? {
+                changes.push(change);
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Change a referral's status and recalculate its referrer's tier
+    pub fn set_referral_status(&mut self, referrer_wallet: &str, referee_wallet: &str,
+                               status: ReferralStatus) -> Result<Option<TierChangeEvent>, String> {
+        let referral = self.commission_system.as_mut()
+            .ok_or("Commission system not initialized")?
+            .referral_tracking.get_mut(&format!("{}_{}", referrer_wallet, referee_wallet))
+            .ok_or("Referral not found")?;
+
+        println!("👥 Referral {} → {}: {:?} → {:?}",
+                 &referrer_wallet[..8.min(referrer_wallet.len())], &referee_wallet[..8.min(referee_wallet.len())],
+                 referral.status, status);
+        referral.status = status.clone();
+        self.journal(GatewayEvent::ReferralStatusChanged {
+            referral_key: format!("{}_{}", referrer_wallet, referee_wallet),
+            status,
+        });
+
+        self.recalculate_tier(referrer_wallet)
+    }
+
+    /// Operator tier maintenance: recalculate now, or change a referral's status
+    pub(crate) fn handle_tier_request(&mut self, path: &str, method: &str,
+                                      headers: &HashMap<String, String>, body: &[u8]) -> Result<HttpResponse, String> {
+        if !is_operator(headers) {
+            return json_response(403, &serde_json::json!({ "error": "Needs X-Operator-Key" }));
+        }
+
+        let path = path.trim_end_matches('/');
+        if method == "POST" && path == "/commission/tiers/recalculate" {
+            let changes = self.recalculate_all_tiers()?;
+            return json_response(200, &serde_json::json!({ "changes": changes }));
+        }
+
+        let parts: Vec<&str> = path.trim_start_matches("/commission/referrals/").split('/').collect();
+        match (method, parts.as_slice()) {
+            ("PUT", [referrer_wallet, referee_wallet]) if path.starts_with("/commission/referrals/") => {
+                let request: serde_json::Value = serde_json::from_slice(body)
+                    .map_err(|e| format!("Invalid referral status request: {}", e))?;
+                let status: ReferralStatus = serde_json::from_value(request["status"].clone())
+                    .map_err(|_| "status must be Active, Inactive, Suspended or Graduated")?;
+                match self.set_referral_status(referrer_wallet, referee_wallet, status) {
+                    Ok(change) => json_response(200, &serde_json::json!({ "tier_change": change })),
+                    Err(e) => json_response(404, &serde_json::json!({ "error": e })),
+                }
+            }
+            _ => Err("Unsupported tier request".to_string()),
+        }
+    }
+
+    /// Record the event and grant any milestone rewards for the new tier
+    pub(crate) fn on_tier_change(&mut self, wallet_address: &str, from: EarningsTier,
+                                 to: EarningsTier, referral_count: u32) -> Result<(), String> {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let commission_system = self.commission_system.as_mut()
+            .ok_or("Commission system not initialized")?;
+
+        println!("🏅 Tier change: {} {:?} → {:?}", &wallet_address[..8.min(wallet_address.len())], from, to);
+
+        commission_system.tier_events.push(TierChangeEvent {
+            wallet_address: wallet_address.to_string(),
+            from,
+            to: to.clone(),
+            referral_count,
+            timestamp: now,
+        });
+
+        let claimed = commission_system.claimed_milestones
+            .entry(wallet_address.to_string())
+            .or_default();
+
+        let due: Vec<MilestoneReward> = commission_system.milestones.iter()
+            .filter(|m| tier_threshold(&m.tier) <= tier_threshold(&to))
+            .filter(|m| !claimed.contains(&m.milestone_id))
+            .cloned()
+            .collect();
+
+        for milestone in &due {
+            claimed.push(milestone.milestone_id.clone());
+            commission_system.granted_rewards.push(GrantedReward {
+                wallet_address: wallet_address.to_string(),
+                milestone_id: milestone.milestone_id.clone(),
+                credit_grant: milestone.credit_grant,
+                badge: milestone.badge.clone(),
+                granted_at: now,
+            });
+        }
+
+        for milestone in due {
+            if milestone.bonus_commission_usdc > 0.0 {
+                self.pay_commission(wallet_address, milestone.bonus_commission_usdc,
+                                    CommissionType::VolumeBonus, &format!("milestone_{}", milestone.milestone_id), None)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn tier_progress(&self, wallet_address: &str) -> Result<TierProgress, String> {
+        let account = self.commission_system.as_ref()
+            .ok_or("Commission system not initialized")?
+            .earnings_ledger.get(wallet_address)
+            .ok_or("Earnings account not found")?;
+
+        let next = next_tier(&account.tier);
+        let (referrals_needed, percent) = match &next {
+            Some(next) => {
+                let start = tier_threshold(&account.tier);
+                let target = tier_threshold(next);
+                let done = account.referral_count.saturating_sub(start);
+                (target.saturating_sub(account.referral_count),
+                 done as f64 / (target - start) as f64 * 100.0)
+            }
+            None => (0, 100.0),
+        };
+
+        Ok(TierProgress {
+            current_tier: account.tier.clone(),
+            next_tier: next,
+            referral_count: account.referral_count,
+            referrals_needed,
+            percent,
+        })
+    }
+
+    pub fn set_milestones(&mut self, milestones: Vec<MilestoneReward>) -> Result<(), String> {
+        self.commission_system.as_mut()
+            .ok_or("Commission system not initialized")?
+            .milestones = milestones;
+        Ok(())
+    }
+
+    pub fn drain_granted_rewards(&mut self) -> Vec<GrantedReward> {
+        self.commission_system.as_mut()
+            .map(|system| std::mem::take(&mut system.granted_rewards))
+            .unwrap_or_default()
+    }
+
+    pub fn tier_events_since(&self, since: u64) -> Vec<&TierChangeEvent> {
+        self.commission_system.as_ref()
+            .map(|system| system.tier_events.iter()
+                .filter(|event| event.timestamp >= since)
+                .collect())
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,175 @@
+use serde::{Deserialize, Serialize};
+use crate::PublicGateway;
+use std::fmt;
+
+/// An amount of a token in its smallest unit, with the token's decimals.
+/// Sums of these are exact; floats are only for display and pricing maths.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenAmount {
+    pub base_units: i64, // negative after a clawback of more than was earned
+    pub decimals: u8,
+}
+
+/// Most decimals a token may have: 10^18 still fits the i64 base units
+pub const MAX_DECIMALS: u8 = 18;
+
+fn check_decimals(decimals: u8) -> Result<(), String> {
+    if decimals > MAX_DECIMALS {
+        return Err(format!("At most {} token decimals are supported, not {}", MAX_DECIMALS, decimals));
+    }
+    Ok(())
+}
+
+impl TokenAmount {
+    pub fn zero(decimals: u8) -> Self {
+        Self { base_units: 0, decimals }
+    }
+
+    /// Round a computed amount (a fee share, say) to the nearest base unit
+    pub fn from_decimal(amount: f64, decimals: u8) -> Result<Self, String> {
+        check_decimals(decimals)?;
+        Ok(Self {
+            base_units: (amount * 10f64.powi(decimals as i32)).round() as i64,
+            decimals,
+        })
+    }
+
+    /// Parse "12.345" exactly; more fractional digits than the token has are refused
+    pub fn parse(amount: &str, decimals: u8) -> Result<Self, String> {
+        check_decimals(decimals)?;
+        let (negative, digits) = match amount.trim().strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, amount.trim()),
+        };
+        let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        if whole.is_empty() && fraction.is_empty()
+            || !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit()) {
+            return Err(format!("Invalid amount: {}", amount));
+        }
+        if fraction.len() > decimals as usize {
+            return Err(format!("At most {} decimal places", decimals));
+        }
+
+        let scale = 10i64.pow(decimals as u32);
+        let whole: i64 = if whole.is_empty() { 0 } else {
+            whole.parse().map_err(|_| format!("Amount too large: {}", amount))?
+        };
+        let fraction: i64 = format!("{:0<width$}", fraction, width = decimals as usize)
+            .parse()
+            .unwrap_or(0);
+        let base_units = whole.checked_mul(scale)
+            .and_then(|units| units.checked_add(fraction))
+            .ok_or(format!("Amount too large: {}", amount))?;
+        Ok(Self { base_units: if negative { -base_units } else { base_units }, decimals })
+    }
+
+    pub fn to_decimal(self) -> f64 {
+        self.base_units as f64 / 10f64.powi(self.decimals as i32)
+    }
+
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        if self.decimals != other.decimals {
+            return None;
+        }
+        Some(Self { base_units: self.base_units.checked_add(other.base_units)?, decimals: self.decimals })
+    }
+
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        self.checked_add(Self { base_units: other.base_units.checked_neg()?, decimals: other.decimals })
+    }
+}
+
+impl fmt::Display for TokenAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.base_units < 0 { "-" } else { "" };
+        let units = self.base_units.unsigned_abs();
+        if self.decimals == 0 {
+            return write!(f, "{}{}", sign, units);
+        }
+        // Past u64's range every amount is below one whole token
+        let (whole, fraction) = match 10u64.checked_pow(self.decimals as u32) {
+            Some(scale) => (units / scale, units % scale),
+            None => (0, units),
+        };
+        write!(f, "{}{}.{:0width$}", sign, whole, fraction, width = self.decimals as usize)
+    }
+}
+
+impl PublicGateway {
+    /// Decimals of a supported token
+    pub fn token_decimals(&self, symbol: &str) -> Option<u8> {
+        self.payment_processor.supported_tokens.iter()
+            .find(|config| config.symbol == symbol)
+            .map(|config| config.decimals)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn amount(base_units: i64, decimals: u8) -> TokenAmount {
+        TokenAmount { base_units, decimals }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(TokenAmount::parse("12.345", 6), Ok(amount(12_345_000, 6)));
+        assert_eq!(TokenAmount::parse(" 7 ", 6), Ok(amount(7_000_000, 6)));
+        assert_eq!(TokenAmount::parse(".5", 6), Ok(amount(500_000, 6)));
+        assert_eq!(TokenAmount::parse("5.", 6), Ok(amount(5_000_000, 6)));
+        assert_eq!(TokenAmount::parse("-1.5", 9), Ok(amount(-1_500_000_000, 9)));
+        assert_eq!(TokenAmount::parse("1", 18), Ok(amount(1_000_000_000_000_000_000, 18)));
+
+        for invalid in ["", ".", "-", "abc", "1.2.3", "--1", "1e6"] {
+            assert!(TokenAmount::parse(invalid, 6).is_err(), "{:?} parsed", invalid);
+        }
+        assert!(TokenAmount::parse("1.2345678", 6).is_err());
+        assert!(TokenAmount::parse("10", 18).is_err());
+        assert!(TokenAmount::parse("99999999999999999999", 0).is_err());
+    }
+
+    #[test]
+    fn test_decimals_past_i64_are_refused() {
+        assert!(TokenAmount::parse("1", 19).is_err());
+        assert!(TokenAmount::parse("0", u8::MAX).is_err());
+        assert!(TokenAmount::from_decimal(1.0, 19).is_err());
+        assert!(TokenAmount::from_decimal(1.0, 18).is_ok());
+    }
+
+    #[test]
+    fn test_from_decimal_rounds_to_nearest_base_unit() {
+        assert_eq!(TokenAmount::from_decimal(1.23456789, 6), Ok(amount(1_234_568, 6)));
+        assert_eq!(TokenAmount::from_decimal(1.2345641, 6), Ok(amount(1_234_564, 6)));
+        assert_eq!(TokenAmount::from_decimal(2.5, 0), Ok(amount(3, 0)));
+        assert_eq!(TokenAmount::from_decimal(-2.5, 0), Ok(amount(-3, 0)));
+        assert_eq!(TokenAmount::from_decimal(0.1 + 0.2, 6), Ok(amount(300_000, 6)));
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(amount(12_345_000, 6).to_string(), "12.345000");
+        assert_eq!(amount(-1_500_000, 6).to_string(), "-1.500000");
+        assert_eq!(amount(-5, 6).to_string(), "-0.000005");
+        assert_eq!(amount(42, 0).to_string(), "42");
+        assert_eq!(amount(i64::MIN, 0).to_string(), "-9223372036854775808");
+        assert_eq!(amount(5, 20).to_string(), "0.00000000000000000005");
+
+        // Display and parse agree
+        let parsed = TokenAmount::parse("-0.000123456", 9).unwrap();
+        assert_eq!(TokenAmount::parse(&parsed.to_string(), 9), Ok(parsed));
+    }
+
+    #[test]
+    fn test_checked_add_and_sub() {
+        assert_eq!(amount(1_500_000, 6).checked_add(amount(250_000, 6)), Some(amount(1_750_000, 6)));
+        assert_eq!(amount(1_500_000, 6).checked_sub(amount(2_000_000, 6)), Some(amount(-500_000, 6)));
+        assert_eq!(amount(i64::MAX, 6).checked_add(amount(1, 6)), None);
+        assert_eq!(amount(0, 6).checked_sub(amount(i64::MIN, 6)), None);
+
+        // 1 USDC and 1 SOL-style unit are not the same quantity
+        assert_eq!(amount(1_000_000, 6).checked_add(amount(1_000_000_000, 9)), None);
+        assert_eq!(amount(1_000_000, 6).checked_sub(amount(1, 9)), None);
+        assert_eq!(TokenAmount::zero(6).checked_add(TokenAmount::zero(9)), None);
+    }
+}
@@ -0,0 +1,153 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+/// The W3C `traceparent` header: version 00, trace id, parent span id, flags
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceParent {
+    pub trace_id: String, // 32 lowercase hex
+    pub span_id: String, // 16 lowercase hex
+    pub sampled: bool,
+}
+
+fn random_hex(bytes: usize) -> String {
+    (0..bytes).map(|_| format!("{:02x}", rand::random::<u8>())).collect()
+}
+
+fn is_hex_id(value: &str, len: usize) -> bool {
+    value.len() == len
+        && value.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+        && value.bytes().any(|b| b != b'0')
+}
+
+impl TraceParent {
+    pub fn root() -> Self {
+        Self {
+            trace_id: random_hex(16),
+            span_id: random_hex(8),
+            sampled: true,
+        }
+    }
+
+    /// Invalid headers are ignored, as the spec asks, and a new trace starts
+    pub fn parse(header: &str) -> Option<Self> {
+        let mut fields = header.trim().split('-');
+        let (version, trace_id, span_id, flags) = (fields.next()?, fields.next()?, fields.next()?, fields.next()?);
+        if version != "00" || fields.next().is_some() {
+            return None;
+        }
+        if !is_hex_id(trace_id, 32) || !is_hex_id(span_id, 16) || flags.len() != 2 {
+            return None;
+        }
+        let flags = u8::from_str_radix(flags, 16).ok()?;
+
+        Some(Self {
+            trace_id: trace_id.to_string(),
+            span_id: span_id.to_string(),
+            sampled: flags & 1 == 1,
+        })
+    }
+
+    /// Header names arrive in whatever case the client sent
+    pub fn from_headers(headers: &HashMap<String, String>) -> Option<Self> {
+        headers.iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("traceparent"))
+            .and_then(|(_, value)| Self::parse(value))
+    }
+
+    /// Same trace, new span under this one
+    pub fn child(&self) -> Self {
+        Self {
+            trace_id: self.trace_id.clone(),
+            span_id: random_hex(8),
+            sampled: self.sampled,
+        }
+    }
+
+    pub fn header_value(&self) -> String {
+        format!("00-{}-{}-{}", self.trace_id, self.span_id, if self.sampled { "01" } else { "00" })
+    }
+}
+
+/// Same shape the node's span collector (POST /api/v1/traces/spans) accepts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Span {
+    pub trace_id: String,
+    pub span_id: String,
+    pub parent_span_id: Option<String>,
+    pub name: String,
+    pub component: String, // gateway, libp2p, node, backend
+    pub start_us: u64,
+    pub duration_us: u64,
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    #[serde(default)]
+    pub attributes: HashMap<String, String>,
+}
+
+/// A span being timed; `finish` stamps the duration
+pub struct OpenSpan {
+    pub span: Span,
+    started: std::time::Instant,
+}
+
+impl OpenSpan {
+    pub fn start(context: &TraceParent, parent: Option<&TraceParent>, name: &str, component: &str) -> Self {
+        Self {
+            span: Span {
+                trace_id: context.trace_id.clone(),
+                span_id: context.span_id.clone(),
+                parent_span_id: parent.map(|parent| parent.span_id.clone()),
+                name: name.to_string(),
+                component: component.to_string(),
+                start_us: chrono::Utc::now().timestamp_micros() as u64,
+                duration_us: 0,
+                status: None,
+                error: None,
+                attributes: HashMap::new(),
+            },
+            started: std::time::Instant::now(),
+        }
+    }
+
+    pub fn finish(mut self, status: Option<u16>, error: Option<String>) -> Span {
+        self.span.duration_us = self.started.elapsed().as_micros() as u64;
+        self.span.status = status;
+        self.span.error = error;
+        self.span
+    }
+}
+
+/// Finished spans waiting to be shipped to a node's span collector. The
+/// gateway keeps no trace history itself; the node's /api/v1/traces/{id} viewer
+/// stitches the gateway's spans together with its own.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpanExporter {
+    pub max_pending: usize, // oldest spans are dropped if nobody drains
+    pub pending: VecDeque<Span>,
+}
+
+impl Default for SpanExporter {
+    fn default() -> Self {
+        Self {
+            max_pending: 10_000,
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+impl SpanExporter {
+    pub fn record(&mut self, span: Span, sampled: bool) {
+        if !sampled {
+            return;
+        }
+        self.pending.push_back(span);
+        while self.pending.len() > self.max_pending {
+            self.pending.pop_front();
+        }
+    }
+
+    /// Spans to POST to the node's /api/v1/traces/spans
+    pub fn drain_spans(&mut self) -> Vec<Span> {
+        self.pending.drain(..).collect()
+    }
+}
@@ -0,0 +1,970 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::screening::is_operator;
+use crate::receipts::json_response;
+use crate::{HttpResponse, PublicGateway};
+use std::collections::{HashMap, VecDeque};
+
+/// Script source an owner may attach, per direction
+pub const MAX_SCRIPT_BYTES: usize = 8 * 1024;
+const MAX_STATEMENTS: usize = 200;
+const MAX_PATH_DEPTH: usize = 32;
+/// Evaluation steps one run may take: one per statement and expression, and
+/// one per value node or 64 bytes of text produced
+pub const MAX_STEPS: u64 = 20_000;
+/// Bytes of values one run may produce, body included
+pub const MAX_MEMORY_BYTES: usize = 2 * 1024 * 1024;
+const MAX_VERSIONS: usize = 20;
+const RECENT_FAILURES: usize = 20;
+
+/// Headers the gateway manages; scripts may read them but not set them
+const PROTECTED_HEADERS: [&str; 11] = [
+    "Access-Control-", "Content-Length", "Transfer-Encoding", "Traceparent", "X-Receipt-", "X-Charge-",
+    "X-Estimate-", "X-Coupon-", "X-Discount-", "X-Experiment-", "X-Wallet-",
+];
+
+/// Caller credentials meant for the gateway, never forwarded to a service
+const CREDENTIAL_HEADERS: [&str; 7] = [
+    "Authorization", "X-Payment-Token", "X-Wallet-Signature", "X-Auth-Challenge", "X-Operator-Key",
+    "X-Platform-Key", "X-Governance-Key",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Request,  // rewrites what the service receives: forwarded headers and body
+    Response, // rewrites what the caller gets; starts with no headers, what it sets is added to the reply
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Target {
+    Header(String),
+    Body(Vec<Segment>), // empty for the whole body
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Func {
+    Concat,
+    Lower,
+    Upper,
+    Trim,
+    Default,
+    String,
+    Number,
+    Len,
+    Now,
+}
+
+impl Func {
+    fn parse(name: &str) -> Option<(Self, usize, usize)> {
+        // (function, fewest arguments, most arguments)
+        Some(match name {
+            "concat" => (Func::Concat, 1, 16),
+            "lower" => (Func::Lower, 1, 1),
+            "upper" => (Func::Upper, 1, 1),
+            "trim" => (Func::Trim, 1, 1),
+            "default" => (Func::Default, 2, 2),
+            "string" => (Func::String, 1, 1),
+            "number" => (Func::Number, 1, 1),
+            "len" => (Func::Len, 1, 1),
+            "now" => (Func::Now, 0, 0),
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Literal(Value),
+    Read(Target),
+    Method,
+    Path,
+    Call(Func, Vec<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Condition {
+    Equal(Expr, Expr),
+    NotEqual(Expr, Expr),
+    Exists(Target),
+    Missing(Target),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Statement {
+    Set(Target, Expr),
+    Remove(Target),
+    Move(Target, Target),
+}
+
+#[derive(Debug, Clone)]
+struct Line {
+    number: usize,
+    statement: Statement,
+    condition: Option<Condition>,
+}
+
+/// A parsed transform script. One statement per line, `#` starts a comment:
+///
+/// ```text
+/// set header.X-Api-Version = "2"
+/// set body.user_id = body.user.id
+/// move body.fullName -> body.name
+/// remove body.debug if header.X-Env != "staging"
+/// set body.label = concat(upper(body.kind), ": ", default(body.title, "untitled"))
+/// ```
+///
+/// Values are read from `body.<path>` (array items by index), `header.<Name>`,
+/// `method` and `path`; `set` creates missing objects along its path, and
+/// setting a header to null removes it. Functions: concat, lower, upper,
+/// trim, default, string, number, len, now. A statement may end with
+/// `if <a> == <b>`, `if <a> != <b>`, `if exists <ref>` or `if missing <ref>`.
+/// There are no loops; runs are still bounded by MAX_STEPS and MAX_MEMORY_BYTES.
+#[derive(Debug, Clone)]
+pub struct Script {
+    lines: Vec<Line>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Num(serde_json::Number),
+    Sym(&'static str),
+}
+
+fn tokenize(line: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        match c {
+            ' ' | '\t' => i += 1,
+            '#' => break,
+            '"' => {
+                let mut text = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err("Unterminated string".to_string()),
+                        Some('"') => break,
+                        Some('\\') => {
+                            text.push(match chars.get(i + 1) {
+                                Some('n') => '\n',
+                                Some('t') => '\t',
+                                Some('"') => '"',
+                                Some('\\') => '\\',
+                                _ => return Err("Unknown escape in string".to_string()),
+                            });
+                            i += 2;
+                        }
+                        Some(c) => {
+                            text.push(*c);
+                            i += 1;
+                        }
+                    }
+                }
+                tokens.push(Token::Str(text));
+                i += 1;
+            }
+            '(' | ')' | ',' => {
+                tokens.push(Token::Sym(match c { '(' => "(", ')' => ")", _ => "," }));
+                i += 1;
+            }
+            '=' if next == Some('=') => { tokens.push(Token::Sym("==")); i += 2; }
+            '=' => { tokens.push(Token::Sym("=")); i += 1; }
+            '!' if next == Some('=') => { tokens.push(Token::Sym("!=")); i += 2; }
+            '-' if next == Some('>') => { tokens.push(Token::Sym("->")); i += 2; }
+            c if c.is_ascii_digit() || (c == '-' && next.is_some_and(|n| n.is_ascii_digit())) => {
+                let start = i;
+                i += 1;
+                while i < chars.len() && (chars[i].is_ascii_digit() || matches!(chars[i], '.' | 'e' | 'E')) {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                match serde_json::from_str::<Value>(&text) {
+                    Ok(Value::Number(number)) => tokens.push(Token::Num(number)),
+                    _ => return Err(format!("Invalid number {}", text)),
+                }
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len()
+                    && (chars[i].is_ascii_alphanumeric() || matches!(chars[i], '_' | '.' | '-'))
+                    && !(chars[i] == '-' && chars.get(i + 1) == Some(&'>')) {
+                    i += 1;
+                }
+                tokens.push(Token::Word(chars[start..i].iter().collect()));
+            }
+            c => return Err(format!("Unexpected character '{}'", c)),
+        }
+    }
+    Ok(tokens)
+}
+
+/// `x-api-key` → `X-Api-Key`, the spelling the router hands the gateway
+fn canonical_header(name: &str) -> String {
+    name.split('-')
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + &chars.as_str().to_ascii_lowercase(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn parse_target(word: &str) -> Result<Target, String> {
+    if let Some(name) = word.strip_prefix("header.") {
+        if name.is_empty() || name.contains('.') {
+            return Err(format!("Invalid header reference {}", word));
+        }
+        return Ok(Target::Header(canonical_header(name)));
+    }
+    let rest = match word.strip_prefix("body") {
+        Some("") => return Ok(Target::Body(Vec::new())),
+        Some(rest) => rest.strip_prefix('.').ok_or(format!("Unknown reference {}", word))?,
+        None => return Err(format!("Unknown reference {}", word)),
+    };
+    let segments: Vec<Segment> = rest.split('.')
+        .map(|segment| match segment.parse::<usize>() {
+            Ok(index) => Ok(Segment::Index(index)),
+            Err(_) if !segment.is_empty() => Ok(Segment::Key(segment.to_string())),
+            Err(_) => Err(format!("Empty field in {}", word)),
+        })
+        .collect::<Result<_, _>>()?;
+    if segments.len() > MAX_PATH_DEPTH {
+        return Err(format!("{} is nested deeper than {} fields", word, MAX_PATH_DEPTH));
+    }
+    Ok(Target::Body(segments))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), String> {
+        match self.next() {
+            Some(Token::Sym(found)) if found == symbol => Ok(()),
+            _ => Err(format!("Expected '{}'", symbol)),
+        }
+    }
+
+    fn target(&mut self) -> Result<Target, String> {
+        match self.next() {
+            Some(Token::Word(word)) => parse_target(&word),
+            _ => Err("Expected body.<path> or header.<Name>".to_string()),
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Str(text)) => Ok(Expr::Literal(Value::String(text))),
+            Some(Token::Num(number)) => Ok(Expr::Literal(Value::Number(number))),
+            Some(Token::Word(word)) => match word.as_str() {
+                "true" => Ok(Expr::Literal(Value::Bool(true))),
+                "false" => Ok(Expr::Literal(Value::Bool(false))),
+                "null" => Ok(Expr::Literal(Value::Null)),
+                "method" => Ok(Expr::Method),
+                "path" => Ok(Expr::Path),
+                name if self.peek() == Some(&Token::Sym("(")) => {
+                    let (func, fewest, most) = Func::parse(name).ok_or(format!("Unknown function {}", name))?;
+                    self.expect("(")?;
+                    let mut args = Vec::new();
+                    if self.peek() != Some(&Token::Sym(")")) {
+                        loop {
+                            args.push(self.expr()?);
+                            if self.peek() != Some(&Token::Sym(",")) {
+                                break;
+                            }
+                            self.next();
+                        }
+                    }
+                    self.expect(")")?;
+                    if args.len() < fewest || args.len() > most {
+                        return Err(format!("{} takes {} to {} arguments", name, fewest, most));
+                    }
+                    Ok(Expr::Call(func, args))
+                }
+                _ => parse_target(&word).map(Expr::Read),
+            },
+            _ => Err("Expected a value".to_string()),
+        }
+    }
+
+    fn condition(&mut self) -> Result<Condition, String> {
+        match self.peek() {
+            Some(Token::Word(word)) if word == "exists" => { self.next(); Ok(Condition::Exists(self.target()?)) }
+            Some(Token::Word(word)) if word == "missing" => { self.next(); Ok(Condition::Missing(self.target()?)) }
+            _ => {
+                let left = self.expr()?;
+                match self.next() {
+                    Some(Token::Sym("==")) => Ok(Condition::Equal(left, self.expr()?)),
+                    Some(Token::Sym("!=")) => Ok(Condition::NotEqual(left, self.expr()?)),
+                    _ => Err("Expected == or != in condition".to_string()),
+                }
+            }
+        }
+    }
+
+    fn line(&mut self, direction: Direction) -> Result<(Statement, Option<Condition>), String> {
+        let statement = match self.next() {
+            Some(Token::Word(word)) if word == "set" => {
+                let target = self.writable(direction)?;
+                self.expect("=")?;
+                Statement::Set(target, self.expr()?)
+            }
+            Some(Token::Word(word)) if word == "remove" => Statement::Remove(self.writable(direction)?),
+            Some(Token::Word(word)) if word == "move" => {
+                let from = self.writable(direction)?;
+                self.expect("->")?;
+                Statement::Move(from, self.writable(direction)?)
+            }
+            _ => return Err("Expected set, remove or move".to_string()),
+        };
+        let condition = match self.next() {
+            None => None,
+            Some(Token::Word(word)) if word == "if" => Some(self.condition()?),
+            Some(_) => return Err("Unexpected text after statement".to_string()),
+        };
+        if self.next().is_some() {
+            return Err("Unexpected text after condition".to_string());
+        }
+        Ok((statement, condition))
+    }
+
+    fn writable(&mut self, direction: Direction) -> Result<Target, String> {
+        let target = self.target()?;
+        if let Target::Header(name) = &target {
+            if PROTECTED_HEADERS.iter().any(|protected| name.starts_with(protected)) {
+                return Err(format!("{} is set by the gateway", name));
+            }
+            if direction == Direction::Request && CREDENTIAL_HEADERS.contains(&name.as_str()) {
+                return Err(format!("{} is a credential and is never forwarded", name));
+            }
+        }
+        Ok(target)
+    }
+}
+
+impl Script {
+    pub fn parse(source: &str, direction: Direction) -> Result<Self, String> {
+        if source.len() > MAX_SCRIPT_BYTES {
+            return Err(format!("Scripts are limited to {} bytes", MAX_SCRIPT_BYTES));
+        }
+        let mut lines = Vec::new();
+        for (index, text) in source.lines().enumerate() {
+            let tokens = tokenize(text).map_err(|e| format!("line {}: {}", index + 1, e))?;
+            if tokens.is_empty() {
+                continue;
+            }
+            let mut parser = Parser { tokens, position: 0 };
+            let (statement, condition) = parser.line(direction)
+                .map_err(|e| format!("line {}: {}", index + 1, e))?;
+            lines.push(Line { number: index + 1, statement, condition });
+        }
+        if lines.is_empty() {
+            return Err("Script has no statements".to_string());
+        }
+        if lines.len() > MAX_STATEMENTS {
+            return Err(format!("Scripts are limited to {} statements", MAX_STATEMENTS));
+        }
+        Ok(Self { lines })
+    }
+
+    /// Run against one message. Fails, changing nothing, on a runtime error
+    /// or when the run exceeds its step or memory budget.
+    pub fn run(&self, method: &str, path: &str, message: Message) -> Result<(Message, RunUsage), String> {
+        let mut run = Run {
+            method,
+            path,
+            headers: message.headers,
+            raw_body: message.body,
+            body: None,
+            body_changed: false,
+            usage: RunUsage::default(),
+        };
+        for line in &self.lines {
+            run.line(line).map_err(|e| format!("line {}: {}", line.number, e))?;
+        }
+
+        let body = match run.body.take().filter(|_| run.body_changed) {
+            Some(Value::Null) => Vec::new(),
+            Some(body) => {
+                let bytes = serde_json::to_vec(&body).map_err(|e| format!("Failed to serialize body: {}", e))?;
+                run.usage.memory_bytes += bytes.len();
+                if run.usage.memory_bytes > MAX_MEMORY_BYTES {
+                    return Err(format!("Script used more than {} bytes", MAX_MEMORY_BYTES));
+                }
+                bytes
+            }
+            None => run.raw_body, // unchanged; passed on byte for byte
+        };
+        Ok((Message { headers: run.headers, body }, run.usage))
+    }
+}
+
+/// What a script sees and rewrites
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Message {
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunUsage {
+    pub steps: u64,
+    pub memory_bytes: usize,
+}
+
+struct Run<'a> {
+    method: &'a str,
+    path: &'a str,
+    headers: HashMap<String, String>,
+    raw_body: Vec<u8>,
+    body: Option<Value>, // parsed on first use
+    body_changed: bool,
+    usage: RunUsage,
+}
+
+fn text_of(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn lookup<'v>(mut value: &'v Value, segments: &[Segment]) -> Option<&'v Value> {
+    for segment in segments {
+        value = match (segment, value) {
+            (Segment::Key(key), Value::Object(map)) => map.get(key)?,
+            (Segment::Index(index), Value::Array(items)) => items.get(*index)?,
+            (Segment::Index(index), Value::Object(map)) => map.get(&index.to_string())?,
+            _ => return None,
+        };
+    }
+    Some(value)
+}
+
+impl Run<'_> {
+    fn step(&mut self, steps: u64) -> Result<(), String> {
+        self.usage.steps += steps;
+        if self.usage.steps > MAX_STEPS {
+            return Err(format!("Script ran out of steps ({})", MAX_STEPS));
+        }
+        Ok(())
+    }
+
+    /// Account for a value the run produced: a step per node and per 64
+    /// bytes of text, and its size against the memory budget
+    fn charge(&mut self, value: &Value) -> Result<(), String> {
+        let mut pending = vec![value];
+        while let Some(value) = pending.pop() {
+            let bytes = match value {
+                Value::String(text) => text.len(),
+                Value::Array(items) => { pending.extend(items); 8 }
+                Value::Object(map) => {
+                    pending.extend(map.values());
+                    map.keys().map(String::len).sum::<usize>() + 8
+                }
+                _ => 8,
+            };
+            self.step(1 + bytes as u64 / 64)?;
+            self.usage.memory_bytes += bytes;
+            if self.usage.memory_bytes > MAX_MEMORY_BYTES {
+                return Err(format!("Script used more than {} bytes", MAX_MEMORY_BYTES));
+            }
+        }
+        Ok(())
+    }
+
+    fn body(&mut self) -> Result<&mut Value, String> {
+        if self.body.is_none() {
+            let parsed = if self.raw_body.is_empty() {
+                Value::Null
+            } else {
+                serde_json::from_slice(&self.raw_body).map_err(|_| "Body is not JSON".to_string())?
+            };
+            self.charge(&parsed)?;
+            self.body = Some(parsed);
+        }
+        Ok(self.body.get_or_insert(Value::Null))
+    }
+
+    fn read(&mut self, target: &Target) -> Result<Option<Value>, String> {
+        let value = match target {
+            Target::Header(name) => self.headers.get(name).cloned().map(Value::String),
+            Target::Body(segments) => lookup(self.body()?, segments).cloned(),
+        };
+        if let Some(value) = &value {
+            self.charge(value)?;
+        }
+        Ok(value)
+    }
+
+    fn eval(&mut self, expr: &Expr) -> Result<Value, String> {
+        self.step(1)?;
+        let value = match expr {
+            Expr::Literal(value) => value.clone(),
+            Expr::Read(target) => return Ok(self.read(target)?.unwrap_or(Value::Null)),
+            Expr::Method => Value::String(self.method.to_string()),
+            Expr::Path => Value::String(self.path.to_string()),
+            Expr::Call(func, args) => {
+                let args = args.iter().map(|arg| self.eval(arg)).collect::<Result<Vec<_>, _>>()?;
+                match func {
+                    Func::Concat => Value::String(args.iter().map(text_of).collect()),
+                    Func::Lower => Value::String(text_of(&args[0]).to_lowercase()),
+                    Func::Upper => Value::String(text_of(&args[0]).to_uppercase()),
+                    Func::Trim => Value::String(text_of(&args[0]).trim().to_string()),
+                    Func::Default => match &args[0] {
+                        Value::Null => args[1].clone(),
+                        value => value.clone(),
+                    },
+                    Func::String => Value::String(text_of(&args[0])),
+                    Func::Number => match &args[0] {
+                        Value::Null | Value::Number(_) => args[0].clone(),
+                        Value::String(text) => match serde_json::from_str::<Value>(text.trim()) {
+                            Ok(Value::Number(number)) => Value::Number(number),
+                            _ => return Err(format!("\"{}\" is not a number", text)),
+                        },
+                        Value::Bool(flag) => Value::from(*flag as u8),
+                        _ => return Err("number() needs a string, number or bool".to_string()),
+                    },
+                    Func::Len => Value::from(match &args[0] {
+                        Value::Null => 0,
+                        Value::String(text) => text.chars().count(),
+                        Value::Array(items) => items.len(),
+                        Value::Object(map) => map.len(),
+                        _ => return Err("len() needs a string, array or object".to_string()),
+                    }),
+                    Func::Now => Value::from(chrono::Utc::now().timestamp()),
+                }
+            }
+        };
+        self.charge(&value)?;
+        Ok(value)
+    }
+
+    fn test(&mut self, condition: &Condition) -> Result<bool, String> {
+        Ok(match condition {
+            Condition::Equal(left, right) => self.eval(left)? == self.eval(right)?,
+            Condition::NotEqual(left, right) => self.eval(left)? != self.eval(right)?,
+            Condition::Exists(target) => self.read(target)?.is_some(),
+            Condition::Missing(target) => self.read(target)?.is_none(),
+        })
+    }
+
+    fn line(&mut self, line: &Line) -> Result<(), String> {
+        self.step(1)?;
+        if let Some(condition) = &line.condition {
+            if !self.test(condition)? {
+                return Ok(());
+            }
+        }
+        match &line.statement {
+            Statement::Set(target, expr) => {
+                let value = self.eval(expr)?;
+                self.write(target, value)
+            }
+            Statement::Remove(target) => self.remove(target).map(|_| ()),
+            Statement::Move(from, to) => match self.remove(from)? {
+                Some(value) => self.write(to, value),
+                None => Ok(()),
+            },
+        }
+    }
+
+    fn write(&mut self, target: &Target, value: Value) -> Result<(), String> {
+        match target {
+            Target::Header(name) => {
+                if value.is_null() {
+                    self.headers.remove(name);
+                    return Ok(());
+                }
+                let text = text_of(&value);
+                if text.contains(['\r', '\n']) {
+                    return Err(format!("{} would contain a line break", name));
+                }
+                self.headers.insert(name.clone(), text);
+                Ok(())
+            }
+            Target::Body(segments) => {
+                let mut slot = self.body()?;
+                for (depth, segment) in segments.iter().enumerate() {
+                    if slot.is_null() {
+                        *slot = Value::Object(serde_json::Map::new());
+                    }
+                    slot = match (segment, slot) {
+                        (Segment::Key(key), Value::Object(map)) => map.entry(key.clone()).or_insert(Value::Null),
+                        (Segment::Index(index), Value::Object(map)) => map.entry(index.to_string()).or_insert(Value::Null),
+                        (Segment::Index(index), Value::Array(items)) if *index <= items.len() => {
+                            if *index == items.len() {
+                                items.push(Value::Null);
+                            }
+                            &mut items[*index]
+                        }
+                        (Segment::Index(index), Value::Array(_)) => {
+                            return Err(format!("Index {} is past the end of the array", index));
+                        }
+                        _ => return Err(format!("{} is not an object or array", path_text(&segments[..depth]))),
+                    };
+                }
+                *slot = value;
+                self.body_changed = true;
+                Ok(())
+            }
+        }
+    }
+
+    fn remove(&mut self, target: &Target) -> Result<Option<Value>, String> {
+        match target {
+            Target::Header(name) => Ok(self.headers.remove(name).map(Value::String)),
+            Target::Body(segments) => {
+                let Some((last, parents)) = segments.split_last() else {
+                    self.body_changed = true;
+                    return Ok(Some(std::mem::take(self.body()?)));
+                };
+                let body = self.body()?;
+                let removed = match (lookup_mut(body, parents), last) {
+                    (Some(Value::Object(map)), Segment::Key(key)) => map.remove(key),
+                    (Some(Value::Object(map)), Segment::Index(index)) => map.remove(&index.to_string()),
+                    (Some(Value::Array(items)), Segment::Index(index)) if *index < items.len() => Some(items.remove(*index)),
+                    _ => None,
+                };
+                self.body_changed |= removed.is_some();
+                Ok(removed)
+            }
+        }
+    }
+}
+
+fn path_text(segments: &[Segment]) -> String {
+    segments.iter().fold("body".to_string(), |text, segment| match segment {
+        Segment::Key(key) => format!("{}.{}", text, key),
+        Segment::Index(index) => format!("{}.{}", text, index),
+    })
+}
+
+fn lookup_mut<'v>(mut value: &'v mut Value, segments: &[Segment]) -> Option<&'v mut Value> {
+    for segment in segments {
+        value = match (segment, value) {
+            (Segment::Key(key), Value::Object(map)) => map.get_mut(key)?,
+            (Segment::Index(index), Value::Array(items)) => items.get_mut(*index)?,
+            (Segment::Index(index), Value::Object(map)) => map.get_mut(&index.to_string())?,
+            _ => return None,
+        };
+    }
+    Some(value)
+}
+
+/// The caller's headers as a service may see them: without the gateway's credentials
+pub fn forwarded_headers(headers: &HashMap<String, String>) -> HashMap<String, String> {
+    headers.iter()
+        .filter(|(name, _)| !CREDENTIAL_HEADERS.contains(&name.as_str()))
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect()
+}
+
+/// Transform scripts an owner attached to a service. Uploads become new
+/// versions once they pass validation; one version at a time is active.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ServiceTransforms {
+    pub versions: Vec<TransformVersion>, // oldest first
+    pub active_version: Option<u32>,
+    #[serde(default)]
+    pub stats: TransformStats,
+}
+
+impl ServiceTransforms {
+    pub fn active(&self) -> Option<&TransformVersion> {
+        let active = self.active_version?;
+        self.versions.iter().find(|version| version.version == active)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransformVersion {
+    pub version: u32,
+    pub request_script: Option<String>,
+    pub response_script: Option<String>,
+    pub created_at: u64,
+    pub activated_at: Option<u64>, // most recent activation
+    pub validation: Vec<SampleResult>, // the sample runs it passed
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TransformStats {
+    pub request_runs: u64,
+    pub response_runs: u64,
+    pub failures: u64,
+    pub peak_steps: u64,
+    pub recent_failures: VecDeque<TransformFailure>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransformFailure {
+    pub version: u32,
+    pub direction: Direction,
+    pub error: String,
+    pub at: u64,
+}
+
+/// A message to run a script against before it can be activated, with what
+/// it should produce
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransformSample {
+    pub direction: Direction,
+    #[serde(default = "default_sample_method")]
+    pub method: String,
+    #[serde(default)]
+    pub path: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default)]
+    pub body: Value, // null for an empty body
+    #[serde(default)]
+    pub expect_headers: Option<HashMap<String, String>>, // each must be present with this value
+    #[serde(default)]
+    pub expect_body: Option<Value>,
+}
+
+fn default_sample_method() -> String {
+    "POST".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SampleResult {
+    pub direction: Direction,
+    pub headers: HashMap<String, String>,
+    pub body: Value,
+    pub usage: RunUsage,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TransformUpload {
+    #[serde(default)]
+    pub request_script: Option<String>,
+    #[serde(default)]
+    pub response_script: Option<String>,
+    pub samples: Vec<TransformSample>,
+}
+
+/// Parse both scripts and run each against its samples within the run
+/// limits; a version is only stored when every sample passes
+pub fn validate(upload: &TransformUpload) -> Result<Vec<SampleResult>, String> {
+    let mut results = Vec::new();
+    for (direction, source) in [(Direction::Request, &upload.request_script), (Direction::Response, &upload.response_script)] {
+        let Some(source) = source else { continue };
+        let script = Script::parse(source, direction)
+            .map_err(|e| format!("{:?} script: {}", direction, e))?;
+        let samples: Vec<&TransformSample> = upload.samples.iter()
+            .filter(|sample| sample.direction == direction)
+            .collect();
+        if samples.is_empty() {
+            return Err(format!("{:?} script needs at least one sample to validate against", direction));
+        }
+        for (index, sample) in samples.into_iter().enumerate() {
+            let name = format!("{:?} sample {}", direction, index + 1);
+            let message = Message {
+                headers: sample.headers.iter().map(|(name, value)| (canonical_header(name), value.clone())).collect(),
+                body: if sample.body.is_null() { Vec::new() } else { serde_json::to_vec(&sample.body).unwrap_or_default() },
+            };
+            let (output, usage) = script.run(&sample.method, &sample.path, message)
+                .map_err(|e| format!("{}: {}", name, e))?;
+            let body = if output.body.is_empty() {
+                Value::Null
+            } else {
+                serde_json::from_slice(&output.body).unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&output.body).into_owned()))
+            };
+            if let Some(expected) = &sample.expect_body {
+                if *expected != body {
+                    return Err(format!("{}: body is {} but {} was expected", name, body, expected));
+                }
+            }
+            for (header, expected) in sample.expect_headers.iter().flatten() {
+                let header = canonical_header(header);
+                if output.headers.get(&header) != Some(expected) {
+                    return Err(format!("{}: {} is {:?} but \"{}\" was expected", name, header,
+                                       output.headers.get(&header), expected));
+                }
+            }
+            results.push(SampleResult { direction, headers: output.headers, body, usage });
+        }
+    }
+    if results.is_empty() {
+        return Err("Provide a request_script, a response_script or both".to_string());
+    }
+    Ok(results)
+}
+
+impl PublicGateway {
+    /// Run the active request script: the headers and body the service
+    /// receives. Err is the response to send instead (the script failed).
+    pub(crate) fn transform_request(&mut self, service_key: &str, method: &str, path: &str,
+                                    headers: &HashMap<String, String>, body: &[u8])
+                                    -> Result<Option<Message>, HttpResponse> {
+        self.run_transform(service_key, Direction::Request, method, path, Message {
+            headers: forwarded_headers(headers),
+            body: body.to_vec(),
+        })
+    }
+
+    /// Run the active response script on a service's answer: the headers to
+    /// add to the reply and the body the caller gets
+    pub(crate) fn transform_response(&mut self, service_key: &str, method: &str, path: &str,
+                                     body: &[u8]) -> Result<Option<Message>, HttpResponse> {
+        self.run_transform(service_key, Direction::Response, method, path, Message {
+            headers: HashMap::new(),
+            body: body.to_vec(),
+        })
+    }
+
+    fn run_transform(&mut self, service_key: &str, direction: Direction, method: &str, path: &str,
+                     message: Message) -> Result<Option<Message>, HttpResponse> {
+        let Some(service) = self.service_registry.get_mut(service_key) else { return Ok(None) };
+        let transforms = &mut service.transforms;
+        let Some(active) = transforms.active() else { return Ok(None) };
+        let version = active.version;
+        let source = match direction {
+            Direction::Request => &active.request_script,
+            Direction::Response => &active.response_script,
+        };
+        let Some(source) = source else { return Ok(None) };
+
+        let result = Script::parse(source, direction)
+            .and_then(|script| script.run(method, path, message));
+        match direction {
+            Direction::Request => transforms.stats.request_runs += 1,
+            Direction::Response => transforms.stats.response_runs += 1,
+        }
+        match result {
+            Ok((output, usage)) => {
+                transforms.stats.peak_steps = transforms.stats.peak_steps.max(usage.steps);
+                Ok(Some(output))
+            }
+            Err(e) => {
+                transforms.stats.failures += 1;
+                transforms.stats.recent_failures.push_back(TransformFailure {
+                    version,
+                    direction,
+                    error: e.clone(),
+                    at: chrono::Utc::now().timestamp() as u64,
+                });
+                while transforms.stats.recent_failures.len() > RECENT_FAILURES {
+                    transforms.stats.recent_failures.pop_front();
+                }
+                println!("⚠️  {:?} transform v{} failed for {}: {}", direction, version, service_key, e);
+                Err(json_response(502, &serde_json::json!({
+                    "error": format!("{:?} transform failed: {}", direction, e),
+                    "transform_version": version,
+                })).unwrap_or_else(|_| HttpResponse { status_code: 502, headers: HashMap::new(), body: Vec::new() }))
+            }
+        }
+    }
+
+    /// /{wallet}/{service}/transforms[/...]: the owner's (or operator's)
+    /// transform versions, uploads and activation
+    pub(crate) fn handle_transform_request(&mut self, wallet_address: &str, service_name: &str, path: &str,
+                                           method: &str, headers: &HashMap<String, String>,
+                                           body: &[u8]) -> Result<HttpResponse, String> {
+        if !is_operator(headers) {
+            match self.authenticate_wallet(headers) {
+                Ok(caller) if caller == wallet_address => {}
+                Ok(_) => return json_response(403, &serde_json::json!({ "error": "Only the service owner can manage transforms" })),
+                Err(e) => return json_response(401, &serde_json::json!({ "error": e })),
+            }
+        }
+        let service_key = format!("{}_{}", wallet_address, service_name);
+        let streamed = self.service_registry.get(&service_key)
+            .ok_or("Service not found")?
+            .response_mode == crate::streaming::ResponseMode::Streamed;
+        let transforms = &mut self.service_registry.get_mut(&service_key).ok_or("Service not found")?.transforms;
+        let rest: Vec<&str> = path.trim_matches('/').split('/').skip(3).collect();
+        let now = chrono::Utc::now().timestamp() as u64;
+
+        match (method, rest.as_slice()) {
+            ("GET", []) => json_response(200, &serde_json::json!({
+                "service": service_key,
+                "active_version": transforms.active_version,
+                "versions": transforms.versions,
+                "stats": transforms.stats,
+                "limits": {
+                    "max_script_bytes": MAX_SCRIPT_BYTES,
+                    "max_statements": MAX_STATEMENTS,
+                    "max_steps": MAX_STEPS,
+                    "max_memory_bytes": MAX_MEMORY_BYTES,
+                },
+            })),
+            ("POST", []) => {
+                let upload: TransformUpload = serde_json::from_slice(body)
+                    .map_err(|e| format!("Invalid transform upload: {}", e))?;
+                let validation = match validate(&upload) {
+                    Ok(validation) => validation,
+                    Err(e) => return json_response(422, &serde_json::json!({ "error": e })),
+                };
+                let version = transforms.versions.last().map(|version| version.version + 1).unwrap_or(1);
+                transforms.versions.push(TransformVersion {
+                    version,
+                    request_script: upload.request_script,
+                    response_script: upload.response_script,
+                    created_at: now,
+                    activated_at: None,
+                    validation,
+                });
+                // Drop the oldest versions past the limit, never the active one
+                while transforms.versions.len() > MAX_VERSIONS {
+                    let active = transforms.active_version;
+                    match transforms.versions.iter().position(|version| Some(version.version) != active) {
+                        Some(index) => { transforms.versions.remove(index); }
+                        None => break,
+                    }
+                }
+                println!("🧩 Transform v{} validated for {}", version, service_key);
+                json_response(201, &serde_json::json!({ "version": transforms.versions.last() }))
+            }
+            ("POST", [version, "activate"]) => {
+                let version: u32 = version.parse().map_err(|_| "Invalid version".to_string())?;
+                let Some(found) = transforms.versions.iter_mut().find(|found| found.version == version) else {
+                    return json_response(404, &serde_json::json!({ "error": "Transform version not found" }));
+                };
+                if streamed && found.response_script.is_some() {
+                    return json_response(409, &serde_json::json!({
+                        "error": "Responses of this service are streamed and can't be transformed"
+                    }));
+                }
+                found.activated_at = Some(now);
+                transforms.active_version = Some(version);
+                println!("🧩 Transform v{} active for {}", version, service_key);
+                json_response(200, &serde_json::json!({ "active_version": version }))
+            }
+            ("POST", ["deactivate"]) => {
+                let previous = transforms.active_version.take();
+                json_response(200, &serde_json::json!({ "active_version": null, "deactivated": previous }))
+            }
+            _ => Err("Unsupported transform request".to_string()),
+        }
+    }
+}
@@ -0,0 +1,385 @@
+use serde::{Deserialize, Serialize};
+use crate::commission_events::CommissionEventKind;
+use crate::receipts::json_response;
+use crate::screening::{is_operator, ScreeningPurpose};
+use crate::{EarningsTier, HttpResponse, PublicGateway};
+use std::collections::HashMap;
+
+/// How often a tier is paid out and the smallest withdrawal it may queue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PayoutSchedule {
+    pub minimum_usdc: f64,
+    pub interval_secs: u64, // between payouts to the same wallet
+}
+
+/// Keyed by tier name, like `tier_multipliers`
+pub fn default_payout_schedules() -> HashMap<String, PayoutSchedule> {
+    [
+        ("Bronze", 25.0, 7 * 86400),
+        ("Silver", 10.0, 3 * 86400),
+        ("Gold", 5.0, 86400),
+        ("Platinum", 1.0, 3600),
+    ]
+    .into_iter()
+    .map(|(tier, minimum_usdc, interval_secs)| {
+        (tier.to_string(), PayoutSchedule { minimum_usdc, interval_secs })
+    })
+    .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum WithdrawalStatus {
+    Queued,     // waiting for the wallet's next payout window
+    Processing, // part of a payout handed to the host
+    Paid,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Withdrawal {
+    pub withdrawal_id: String,
+    pub wallet_address: String,
+    pub amount_usdc: f64,
+    pub requested_at: u64,
+    pub status: WithdrawalStatus,
+    pub payout_id: Option<String>,
+    pub error: Option<String>,
+}
+
+/// A wallet's due withdrawals, batched into one transfer for the host to send
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Payout {
+    pub payout_id: String,
+    pub wallet_address: String,
+    pub amount_usdc: f64,
+    pub withdrawal_ids: Vec<String>,
+    pub created_at: u64,
+    #[serde(default)]
+    pub approval_id: Option<String>, // co-signing request, for payouts over the approval threshold
+    #[serde(default)]
+    pub awaiting_approval: bool, // held back from the host until approved
+}
+
+/// Ledger entry for a payout the host confirmed sent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PayoutRecord {
+    pub payout_id: String,
+    pub wallet_address: String,
+    pub amount_usdc: f64,
+    pub token: String,
+    pub withdrawal_ids: Vec<String>,
+    pub transaction: String, // on-chain signature
+    pub paid_at: u64,
+}
+
+impl PublicGateway {
+    fn payout_schedule(&self, tier: &EarningsTier) -> Result<PayoutSchedule, String> {
+        let commission_system = self.commission_system.as_ref()
+            .ok_or("Commission system not initialized")?;
+        commission_system.payout_schedules.get(&format!("{:?}", tier))
+            .cloned()
+            .ok_or(format!("No payout schedule for tier {:?}", tier))
+    }
+
+    /// When the wallet's next payout may go out: one interval after its last
+    /// one, or now if it was never paid
+    pub fn next_payout_at(&self, wallet_address: &str) -> Result<u64, String> {
+        let commission_system = self.commission_system.as_ref()
+            .ok_or("Commission system not initialized")?;
+        let account = commission_system.earnings_ledger.get(wallet_address)
+            .ok_or("Earnings account not found")?;
+        let schedule = self.payout_schedule(&account.tier)?;
+
+        Ok(commission_system.payout_history.get(wallet_address)
+            .and_then(|records| records.iter().map(|record| record.paid_at).max())
+            .map(|last| last + schedule.interval_secs)
+            .unwrap_or(0))
+    }
+
+    /// Queue a withdrawal of earnings, once the wallet passes screening. It
+    /// is paid in the wallet's next payout window.
+    pub fn request_withdrawal(&mut self, wallet_address: &str, amount_usdc: f64) -> Result<Withdrawal, String> {
+        if amount_usdc.is_nan() || amount_usdc <= 0.0 {
+            return Err("Withdrawal amount must be positive".to_string());
+        }
+        self.screen_wallet(wallet_address, ScreeningPurpose::Withdrawal)?;
+
+        let tier = self.commission_system.as_ref()
+            .ok_or("Commission system not initialized")?
+            .earnings_ledger.get(wallet_address)
+            .ok_or("Earnings account not found")?
+            .tier.clone();
+        let schedule = self.payout_schedule(&tier)?;
+        if amount_usdc < schedule.minimum_usdc {
+            return Err(format!("{:?} tier withdrawals start at {:.6} USDC", tier, schedule.minimum_usdc));
+        }
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        let commission_system = self.commission_system.as_mut()
+            .ok_or("Commission system not initialized")?;
+        let account = commission_system.earnings_ledger.get(wallet_address)
+            .ok_or("Earnings account not found")?;
+
+        let available = account.total_earned_usdc - account.pending_withdrawals - account.total_withdrawn_usdc;
+        if amount_usdc > available {
+            return Err(format!("Only {:.6} USDC available to withdraw", available));
+        }
+
+        let withdrawal = Withdrawal {
+            withdrawal_id: format!("wd_{}_{}", now, commission_system.withdrawals.len()),
+            wallet_address: wallet_address.to_string(),
+            amount_usdc,
+            requested_at: now,
+            status: WithdrawalStatus::Queued,
+            payout_id: None,
+            error: None,
+        };
+        commission_system.record_event(wallet_address, CommissionEventKind::WithdrawalRequested {
+            withdrawal_id: withdrawal.withdrawal_id.clone(),
+            amount_usdc,
+        });
+        commission_system.withdrawals.insert(withdrawal.withdrawal_id.clone(), withdrawal.clone());
+
+        println!("🏧 Withdrawal of {:.6} USDC requested by {}", amount_usdc, wallet_address);
+        Ok(withdrawal)
+    }
+
+    /// Batch every queued withdrawal whose wallet is due into one payout per
+    /// wallet. Wallets are screened again; a blocked one has its withdrawals
+    /// failed and the funds returned to its balance. Payouts over the approval
+    /// threshold are held until approvers co-sign them, and go out with the
+    /// first batch after.
+    pub fn process_withdrawal_batch(&mut self, now: u64) -> Result<Vec<Payout>, String> {
+        let mut payouts = self.release_approved_payouts(now)?;
+        let commission_system = self.commission_system.as_ref()
+            .ok_or("Commission system not initialized")?;
+
+        let mut queued: HashMap<String, Vec<String>> = HashMap::new();
+        for withdrawal in commission_system.withdrawals.values()
+            .filter(|w| w.status == WithdrawalStatus::Queued) {
+            queued.entry(withdrawal.wallet_address.clone())
+                .or_default()
+                .push(withdrawal.withdrawal_id.clone());
+        }
+
+        for (wallet_address, mut withdrawal_ids) in queued {
+            if self.next_payout_at(&wallet_address)? > now {
+                continue;
+            }
+            if let Err(e) = self.screen_wallet(&wallet_address, ScreeningPurpose::Withdrawal) {
+                self.release_withdrawals(&withdrawal_ids, &e)?;
+                continue;
+            }
+
+            withdrawal_ids.sort();
+            let commission_system = self.commission_system.as_mut()
+                .ok_or("Commission system not initialized")?;
+            let payout_id = format!("payout_{}_{}", now, commission_system.payout_seq);
+            commission_system.payout_seq += 1;
+
+            let mut amount_usdc = 0.0;
+            for id in &withdrawal_ids {
+                if let Some(withdrawal) = commission_system.withdrawals.get_mut(id) {
+                    withdrawal.status = WithdrawalStatus::Processing;
+                    withdrawal.payout_id = Some(payout_id.clone());
+                    amount_usdc += withdrawal.amount_usdc;
+                }
+            }
+
+            let mut payout = Payout {
+                payout_id: payout_id.clone(),
+                wallet_address,
+                amount_usdc,
+                withdrawal_ids,
+                created_at: now,
+                approval_id: None,
+                awaiting_approval: false,
+            };
+            let held = self.hold_for_approval(&mut payout, now)?;
+            self.commission_system.as_mut()
+                .ok_or("Commission system not initialized")?
+                .payouts.insert(payout_id, payout.clone());
+            if !held {
+                payouts.push(payout);
+            }
+        }
+
+        if !payouts.is_empty() {
+            println!("🏧 Payout batch: {} payouts, {:.6} USDC", payouts.len(),
+                     payouts.iter().map(|p| p.amount_usdc).sum::<f64>());
+        }
+        Ok(payouts)
+    }
+
+    /// Return withdrawals' funds to the wallet's balance and mark them failed
+    fn release_withdrawals(&mut self, withdrawal_ids: &[String], error: &str) -> Result<(), String> {
+        let commission_system = self.commission_system.as_mut()
+            .ok_or("Commission system not initialized")?;
+        for id in withdrawal_ids {
+            let Some(withdrawal) = commission_system.withdrawals.get_mut(id) else { continue };
+            withdrawal.status = WithdrawalStatus::Failed;
+            withdrawal.error = Some(error.to_string());
+            let (wallet_address, amount_usdc) = (withdrawal.wallet_address.clone(), withdrawal.amount_usdc);
+            commission_system.record_event(&wallet_address, CommissionEventKind::WithdrawalReleased {
+                withdrawal_id: id.clone(),
+                amount_usdc,
+                reason: error.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// The host sent the payout: record it in the ledger and settle the
+    /// wallet's balance
+    pub fn complete_payout(&mut self, payout_id: &str, transaction: &str) -> Result<PayoutRecord, String> {
+        let commission_system = self.commission_system.as_mut()
+            .ok_or("Commission system not initialized")?;
+        if commission_system.payouts.get(payout_id).is_some_and(|payout| payout.awaiting_approval) {
+            return Err("Payout is awaiting approval and was never sent".to_string());
+        }
+        let payout = commission_system.payouts.remove(payout_id)
+            .ok_or("Payout not found")?;
+
+        commission_system.record_event(&payout.wallet_address, CommissionEventKind::Paid {
+            payout_id: payout.payout_id.clone(),
+            amount_usdc: payout.amount_usdc,
+            transaction: transaction.to_string(),
+        });
+        for id in &payout.withdrawal_ids {
+            if let Some(withdrawal) = commission_system.withdrawals.get_mut(id) {
+                withdrawal.status = WithdrawalStatus::Paid;
+            }
+        }
+
+        let record = PayoutRecord {
+            payout_id: payout.payout_id,
+            wallet_address: payout.wallet_address.clone(),
+            amount_usdc: payout.amount_usdc,
+            token: "USDC".to_string(),
+            withdrawal_ids: payout.withdrawal_ids,
+            transaction: transaction.to_string(),
+            paid_at: chrono::Utc::now().timestamp() as u64,
+        };
+        commission_system.payout_history
+            .entry(payout.wallet_address)
+            .or_default()
+            .push(record.clone());
+
+        println!("🏧 Payout {} sent: {:.6} USDC ({})", record.payout_id, record.amount_usdc, transaction);
+        Ok(record)
+    }
+
+    /// The host could not send the payout; its withdrawals fail and the funds
+    /// go back to the wallet's balance
+    pub fn fail_payout(&mut self, payout_id: &str, error: &str) -> Result<Payout, String> {
+        let payout = self.commission_system.as_mut()
+            .ok_or("Commission system not initialized")?
+            .payouts.remove(payout_id)
+            .ok_or("Payout not found")?;
+        self.release_withdrawals(&payout.withdrawal_ids, error)?;
+        println!("⚠️  Payout {} failed: {}", payout_id, error);
+        Ok(payout)
+    }
+
+    /// POST /{wallet}/earnings/withdraw
+    pub(crate) fn handle_withdrawal_request(&mut self, wallet_address: &str, headers: &HashMap<String, String>,
+                                            body: &[u8]) -> Result<HttpResponse, String> {
+        match self.authenticate_wallet(headers) {
+            Ok(caller) if caller == wallet_address => {}
+            Ok(_) => return json_response(403, &serde_json::json!({ "error": "Withdrawals must come from the wallet itself" })),
+            Err(e) => return json_response(401, &serde_json::json!({ "error": e })),
+        }
+        let request: serde_json::Value = serde_json::from_slice(body)
+            .map_err(|e| format!("Invalid withdrawal request: {}", e))?;
+        let amount_usdc = request["amount_usdc"].as_f64()
+            .ok_or("amount_usdc is required")?;
+
+        let (status_code, response) = match self.request_withdrawal(wallet_address, amount_usdc) {
+            Ok(withdrawal) => (202, serde_json::json!({
+                "withdrawal": withdrawal,
+                "next_payout_at": self.next_payout_at(wallet_address)?,
+            })),
+            Err(e) => (403, serde_json::json!({ "error": e })),
+        };
+        json_response(status_code, &response)
+    }
+
+    /// GET /{wallet}/earnings/withdrawals[/{id}]
+    pub(crate) fn handle_withdrawal_status(&self, wallet_address: &str, path: &str) -> Result<HttpResponse, String> {
+        let commission_system = self.commission_system.as_ref()
+            .ok_or("Commission system not initialized")?;
+        let account = commission_system.earnings_ledger.get(wallet_address)
+            .ok_or("Earnings account not found")?;
+
+        let withdrawal_id = path.trim_end_matches('/').rsplit('/').next().unwrap_or("");
+        if withdrawal_id != "withdrawals" {
+            return match commission_system.withdrawals.get(withdrawal_id)
+                .filter(|w| w.wallet_address == wallet_address) {
+                Some(withdrawal) => json_response(200, &serde_json::json!({ "withdrawal": withdrawal })),
+                None => json_response(404, &serde_json::json!({ "error": "Withdrawal not found" })),
+            };
+        }
+
+        let mut withdrawals = commission_system.withdrawals.values()
+            .filter(|w| w.wallet_address == wallet_address)
+            .collect::<Vec<_>>();
+        withdrawals.sort_by_key(|w| std::cmp::Reverse(w.requested_at));
+
+        json_response(200, &serde_json::json!({
+            "wallet_address": wallet_address,
+            "available_usdc": account.total_earned_usdc - account.pending_withdrawals - account.total_withdrawn_usdc,
+            "pending_withdrawals": account.pending_withdrawals,
+            "total_withdrawn_usdc": account.total_withdrawn_usdc,
+            "tier": account.tier,
+            "schedule": self.payout_schedule(&account.tier)?,
+            "next_payout_at": self.next_payout_at(wallet_address)?,
+            "withdrawals": withdrawals,
+            "payouts": commission_system.payout_history.get(wallet_address),
+        }))
+    }
+
+    /// Operator payout runs: GET /payouts, POST /payouts/batch,
+    /// POST /payouts/{id}/complete, POST /payouts/{id}/fail
+    pub(crate) fn handle_payout_request(&mut self, path: &str, method: &str, headers: &HashMap<String, String>,
+                                        body: &[u8]) -> Result<HttpResponse, String> {
+        if !is_operator(headers) {
+            return json_response(403, &serde_json::json!({ "error": "Payouts need X-Operator-Key" }));
+        }
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        let request: serde_json::Value = if body.is_empty() {
+            serde_json::Value::Null
+        } else {
+            serde_json::from_slice(body).map_err(|e| format!("Invalid payout request: {}", e))?
+        };
+
+        let result = match (method, segments.as_slice()) {
+            ("GET", ["payouts"]) => {
+                let commission_system = self.commission_system.as_ref()
+                    .ok_or("Commission system not initialized")?;
+                return json_response(200, &serde_json::json!({
+                    "in_flight": commission_system.payouts.values().filter(|p| !p.awaiting_approval).collect::<Vec<_>>(),
+                    "awaiting_approval": commission_system.payouts.values().filter(|p| p.awaiting_approval).collect::<Vec<_>>(),
+                    "schedules": commission_system.payout_schedules,
+                }));
+            }
+            ("POST", ["payouts", "batch"]) => self.process_withdrawal_batch(chrono::Utc::now().timestamp() as u64)
+                .map(|payouts| serde_json::json!({ "payouts": payouts })),
+            ("POST", ["payouts", payout_id, "complete"]) => {
+                let transaction = request["transaction"].as_str().ok_or("transaction is required")?;
+                self.complete_payout(payout_id, transaction)
+                    .map(|record| serde_json::json!({ "payout": record }))
+            }
+            ("POST", ["payouts", payout_id, "fail"]) => {
+                let error = request["error"].as_str().unwrap_or("Payout failed");
+                self.fail_payout(payout_id, error)
+                    .map(|payout| serde_json::json!({ "payout": payout }))
+            }
+            _ => return Err("Unsupported payout request".to_string()),
+        };
+
+        match result {
+            Ok(response) => json_response(200, &response),
+            Err(e) => json_response(409, &serde_json::json!({ "error": e })),
+        }
+    }
+}
@@ -1,6 +1,19 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+pub mod migration;
+pub mod moderation;
+pub mod replay;
+pub mod rooms;
+pub mod scores;
+pub mod wasm_games;
+
+use moderation::{Direction, ModerationPipeline, Verdict};
+use replay::Replay;
+use rooms::{ChatRoom, PresenceTimeouts};
+use scores::FlaggedScore;
+use wasm_games::{AuthorPayout, SandboxLimits, UserGame};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RetroAIServices {
     pub door_games: HashMap<String, DoorGame>,
@@ -8,6 +21,26 @@ pub struct RetroAIServices {
     pub game_sessions: HashMap<String, GameSession>,
     pub high_scores: HashMap<String, Vec<HighScore>>,
     pub user_stats: HashMap<String, UserGameStats>,
+    #[serde(default)]
+    pub moderation: ModerationPipeline,
+    #[serde(default)]
+    pub replays: HashMap<String, Replay>, // session_id -> recorded turns
+    #[serde(default)]
+    pub draining: bool, // no new games while sessions are handed off
+    #[serde(default)]
+    pub migrated_sessions: HashMap<String, String>, // session_id -> node now hosting it
+    #[serde(default)]
+    pub user_games: HashMap<String, UserGame>, // game_id -> WASM door game
+    #[serde(default)]
+    pub sandbox_limits: SandboxLimits, // ceiling for every user game
+    #[serde(default)]
+    pub pending_author_payouts: Vec<AuthorPayout>,
+    #[serde(default)]
+    pub rooms: HashMap<String, ChatRoom>, // room_id -> multi-user lounge room
+    #[serde(default)]
+    pub presence_timeouts: PresenceTimeouts,
+    #[serde(default)]
+    pub score_reviews: Vec<FlaggedScore>, // implausible scores awaiting an admin
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,6 +78,8 @@ pub struct GameSession {
     pub started_at: u64,
     pub last_action: u64,
     pub ai_companion: Option<String>,
+    #[serde(default)]
+    pub group_id: Option<String>, // chat group, selects the moderation policy
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -53,6 +88,12 @@ pub struct HighScore {
     pub score: u64,
     pub achieved_at: u64,
     pub game_data: serde_json::Value,
+    #[serde(default)]
+    pub session_id: Option<String>, // the replay it was validated against
+    #[serde(default)]
+    pub turns: u32,
+    #[serde(default)]
+    pub signature: Option<String>, // HMAC over the record, see `scores::sign_score`
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -71,7 +112,9 @@ pub enum GameCategory {
     Simulation,
     Puzzle,
     Social,
-    AI_Chat,
+    #[serde(rename = "AI_Chat")]
+    AiChat,
+    Community, // user-authored WASM games
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -81,6 +124,13 @@ pub struct GameCommand {
     pub cost_credits: u64,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpResponse {
+    pub status_code: u16,
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResponsePattern {
     pub trigger_words: Vec<String>,
@@ -88,6 +138,12 @@ pub struct ResponsePattern {
     pub mood_modifier: f32,
 }
 
+impl Default for RetroAIServices {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl RetroAIServices {
     pub fn new() -> Self {
         let mut services = Self {
@@ -96,6 +152,16 @@ impl RetroAIServices {
             game_sessions: HashMap::new(),
             high_scores: HashMap::new(),
             user_stats: HashMap::new(),
+            moderation: ModerationPipeline::default(),
+            replays: HashMap::new(),
+            draining: false,
+            migrated_sessions: HashMap::new(),
+            user_games: HashMap::new(),
+            sandbox_limits: SandboxLimits::default(),
+            pending_author_payouts: Vec::new(),
+            rooms: HashMap::new(),
+            presence_timeouts: PresenceTimeouts::default(),
+            score_reviews: Vec::new(),
         };
 
         services.initialize_classic_games();
@@ -201,7 +267,7 @@ impl RetroAIServices {
                 game_id: "ai_lounge".to_string(),
                 name: "AI Chat Lounge".to_string(),
                 description: "Hang out with retro AI personalities from the 80s".to_string(),
-                category: GameCategory::AI_Chat,
+                category: GameCategory::AiChat,
                 max_players: 20,
                 credits_per_turn: 1,
                 ai_personality: Some("valley_girl".to_string()),
@@ -445,6 +511,10 @@ impl RetroAIServices {
     }
 
     pub fn start_game(&mut self, user_id: &str, game_id: &str) -> Result<String, String> {
+        if self.draining {
+            return Err("Node is draining for deployment; start the game on another node".to_string());
+        }
+
         let game = self.door_games.get(game_id).ok_or("Game not found")?;
 
         let session_id = format!("session_{}_{}", user_id, chrono::Utc::now().timestamp());
@@ -459,8 +529,11 @@ impl RetroAIServices {
             started_at: chrono::Utc::now().timestamp() as u64,
             last_action: chrono::Utc::now().timestamp() as u64,
             ai_companion: game.ai_personality.clone(),
+            group_id: None,
         };
 
+        self.replays
+            .insert(session_id.clone(), Replay::for_session(&session));
         self.game_sessions.insert(session_id.clone(), session);
 
         // Get AI greeting if personality exists
@@ -490,9 +563,13 @@ impl RetroAIServices {
         command: &str,
         args: &str,
     ) -> Result<String, String> {
+        if let Some(node) = self.migrated_to(session_id) {
+            return Err(format!("Session migrated to {}", node));
+        }
+
         let session = self
             .game_sessions
-            .get_mut(session_id)
+            .get(session_id)
             .ok_or("Session not found")?;
 
         let game = self
@@ -506,29 +583,62 @@ impl RetroAIServices {
             .iter()
             .find(|c| c.command == command)
             .ok_or("Invalid command")?;
+        let cost_credits = game_command.cost_credits;
+
+        // Take the session out while its game runs, since some games need
+        // the rest of the services mutably; it goes back whatever happens
+        let mut session = self
+            .game_sessions
+            .remove(session_id)
+            .ok_or("Session not found")?;
+        let turn = self.run_game_command(&mut session, command, args, cost_credits);
+        self.game_sessions.insert(session_id.to_string(), session);
+        let (result, ai_response) = turn?;
 
+        let output = format!("{}\n{}", result, ai_response);
+        self.record_frame(session_id, command, args, &output);
+        self.route_author_payout(session_id, cost_credits);
+
+        Ok(output)
+    }
+
+    /// Play one command of a session's game and update its counters;
+    /// returns the game's output and any AI companion's response
+    fn run_game_command(
+        &mut self,
+        session: &mut GameSession,
+        command: &str,
+        args: &str,
+        cost_credits: u64,
+    ) -> Result<(String, String), String> {
         // Execute command based on game type
         let result = match session.game_id.as_str() {
             "tradewars2035" => self.execute_tradewars_command(session, command, args),
             "lord2035" => self.execute_lord_command(session, command, args),
             "ai_lounge" => self.execute_ai_chat_command(session, command, args),
             "quantum_puzzle" => self.execute_puzzle_command(session, command, args),
+            game_id if self.is_user_game(game_id) => {
+                self.execute_user_game_command(session, command, args)
+            }
             _ => Ok("Command executed.".to_string()),
         }?;
 
         // Update session
         session.turns_taken += 1;
-        session.credits_spent += game_command.cost_credits;
+        session.credits_spent += cost_credits;
         session.last_action = chrono::Utc::now().timestamp() as u64;
 
-        // Add AI response if personality exists
-        let ai_response = if let Some(ai_id) = &session.ai_companion {
+        // Add AI response if personality exists; the lounge moderates and
+        // appends its own
+        let ai_response = if session.game_id == "ai_lounge" {
+            String::new()
+        } else if let Some(ai_id) = &session.ai_companion {
             self.generate_ai_response(ai_id, &result, args)
         } else {
             String::new()
         };
 
-        Ok(format!("{}\n{}", result, ai_response))
+        Ok((result, ai_response))
     }
 
     fn execute_tradewars_command(
@@ -581,23 +691,59 @@ impl RetroAIServices {
     }
 
     fn execute_ai_chat_command(
-        &self,
+        &mut self,
         session: &mut GameSession,
         command: &str,
         args: &str,
     ) -> Result<String, String> {
-        match command {
-            "talk" => Ok(format!("You say: '{}'", args)),
+        let group_id = session.group_id.as_deref();
+
+        // Blocked input fails the command, so no credits are spent on it
+        let args = match self
+            .moderation
+            .moderate(&session.user_id, group_id, Direction::UserInput, args)
+        {
+            Verdict::Allow(text) | Verdict::Masked(text) => text,
+            Verdict::Blocked(reason) => return Err(reason),
+        };
+
+        let result = match command {
+            "talk" => format!("You say: '{}'", args),
             "compliment" => {
                 let friendship = session.game_state["friendship_level"].as_u64().unwrap_or(0);
                 session.game_state["friendship_level"] =
                     serde_json::Value::Number((friendship + 1).into());
-                Ok("Your compliment makes them smile! Friendship increased.".to_string())
+                "Your compliment makes them smile! Friendship increased.".to_string()
             }
-            "ask" => Ok(format!("You ask: '{}'", args)),
-            "joke" => Ok("You tell a joke. They laugh heartily!".to_string()),
-            _ => Ok("Unknown command".to_string()),
-        }
+            "ask" => format!("You ask: '{}'", args),
+            "joke" => "You tell a joke. They laugh heartily!".to_string(),
+            _ => "Unknown command".to_string(),
+        };
+
+        let ai_response = match &session.ai_companion {
+            Some(ai_id) => self.generate_ai_response(ai_id, &result, &args),
+            None => String::new(),
+        };
+        let ai_response = match self.moderation.moderate(
+            &session.user_id,
+            group_id,
+            Direction::AiOutput,
+            &ai_response,
+        ) {
+            Verdict::Allow(text) | Verdict::Masked(text) => text,
+            Verdict::Blocked(notice) => format!("\n{}", notice),
+        };
+
+        Ok(format!("{}{}", result, ai_response))
+    }
+
+    /// Put a session in a chat group, whose moderation policy then applies
+    pub fn join_group(&mut self, session_id: &str, group_id: &str) -> Result<(), String> {
+        self.game_sessions
+            .get_mut(session_id)
+            .ok_or("Session not found")?
+            .group_id = Some(group_id.to_string());
+        Ok(())
     }
 
     fn execute_puzzle_command(
@@ -669,4 +815,97 @@ impl RetroAIServices {
             .map(|scores| scores.iter().collect())
             .unwrap_or_default()
     }
+
+    /// Add a badge earned elsewhere (e.g. gateway tier milestones) to the
+    /// user's achievements. Returns false if they already had it.
+    pub fn award_badge(&mut self, user_id: &str, badge: &str) -> bool {
+        let stats = self
+            .user_stats
+            .entry(user_id.to_string())
+            .or_insert_with(|| UserGameStats {
+                total_games_played: 0,
+                total_credits_spent: 0,
+                favorite_game: String::new(),
+                achievements: Vec::new(),
+                current_streak: 0,
+            });
+
+        if stats.achievements.iter().any(|a| a == badge) {
+            return false;
+        }
+
+        stats.achievements.push(badge.to_string());
+        println!("🏆 Badge awarded to {}: {}", user_id, badge);
+        true
+    }
+
+    /// Game saves, replays, scores and stats for a user, for `/api/v1/export/{wallet}`
+    pub fn export_user_data(&self, user_id: &str) -> serde_json::Value {
+        let sessions: Vec<&GameSession> = self
+            .game_sessions
+            .values()
+            .filter(|session| session.user_id == user_id)
+            .collect();
+
+        let high_scores: HashMap<&String, Vec<&HighScore>> = self
+            .high_scores
+            .iter()
+            .map(|(game_id, scores)| {
+                let own: Vec<&HighScore> = scores.iter().filter(|s| s.user_id == user_id).collect();
+                (game_id, own)
+            })
+            .filter(|(_, scores)| !scores.is_empty())
+            .collect();
+
+        let replays: Vec<&Replay> = self
+            .replays
+            .values()
+            .filter(|replay| replay.user_id == user_id)
+            .collect();
+
+        serde_json::json!({
+            "game_sessions": sessions,
+            "replays": replays,
+            "high_scores": high_scores,
+            "stats": self.user_stats.get(user_id)
+        })
+    }
+
+    /// Drop a user's saves, replays and stats; high scores stay on the board under `pseudonym`
+    pub fn anonymize_user(&mut self, user_id: &str, pseudonym: &str) -> usize {
+        let before = self.game_sessions.len();
+        self.game_sessions
+            .retain(|_, session| session.user_id != user_id);
+        let mut changed = before - self.game_sessions.len();
+
+        let before = self.replays.len();
+        self.replays.retain(|_, replay| replay.user_id != user_id);
+        changed += before - self.replays.len();
+
+        // Renamed scores are re-signed so they still verify
+        let key = scores::signing_key();
+        let boards = self
+            .high_scores
+            .iter_mut()
+            .flat_map(|(game_id, board)| board.iter_mut().map(move |score| (game_id.as_str(), score)));
+        let reviews = self
+            .score_reviews
+            .iter_mut()
+            .map(|review| (review.game_id.as_str(), &mut review.score));
+        for (game_id, score) in boards.chain(reviews) {
+            if score.user_id == user_id {
+                score.user_id = pseudonym.to_string();
+                if score.signature.is_some() {
+                    score.signature = scores::sign_score(&key, game_id, score).ok();
+                }
+                changed += 1;
+            }
+        }
+
+        if self.user_stats.remove(user_id).is_some() {
+            changed += 1;
+        }
+
+        changed
+    }
 }
@@ -0,0 +1,118 @@
+use crate::replay::Replay;
+use crate::{GameSession, RetroAIServices};
+use serde::{Deserialize, Serialize};
+
+/// A session with its replay, as sent to the node taking it over
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigratedSession {
+    pub session: GameSession,
+    pub replay: Option<Replay>,
+}
+
+/// Sessions handed from a draining node to another over the federation channel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionHandoff {
+    pub handoff_id: String,
+    pub source_node: String,
+    pub target_node: String, // base URL clients are redirected to
+    pub sessions: Vec<MigratedSession>,
+    pub sent_at: u64,
+}
+
+impl SessionHandoff {
+    pub fn session_ids(&self) -> Vec<String> {
+        self.sessions
+            .iter()
+            .map(|migrated| migrated.session.session_id.clone())
+            .collect()
+    }
+}
+
+impl RetroAIServices {
+    /// Stop accepting new games and package every active session for
+    /// `target_node`. Sessions leave this node; calls for them are answered
+    /// with `migrated_to` so the gateway can redirect the client.
+    pub fn begin_drain(&mut self, source_node: &str, target_node: &str) -> SessionHandoff {
+        self.draining = true;
+
+        let session_ids: Vec<String> = self.game_sessions.keys().cloned().collect();
+        let sessions = session_ids
+            .iter()
+            .filter_map(|session_id| {
+                let session = self.game_sessions.remove(session_id)?;
+                self.migrated_sessions
+                    .insert(session_id.clone(), target_node.to_string());
+                Some(MigratedSession {
+                    replay: self.replays.remove(session_id),
+                    session,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        println!(
+            "🚚 Draining {}: {} game sessions handed to {}",
+            source_node,
+            sessions.len(),
+            target_node
+        );
+
+        SessionHandoff {
+            handoff_id: format!("handoff_{}_{}", source_node, now),
+            source_node: source_node.to_string(),
+            target_node: target_node.to_string(),
+            sessions,
+            sent_at: now,
+        }
+    }
+
+    /// Take over sessions from a draining node. Sessions whose id already
+    /// exists here are refused and returned in the error.
+    pub fn accept_handoff(&mut self, handoff: SessionHandoff) -> Result<Vec<String>, String> {
+        let conflicts: Vec<&str> = handoff
+            .sessions
+            .iter()
+            .map(|migrated| migrated.session.session_id.as_str())
+            .filter(|session_id| self.game_sessions.contains_key(*session_id))
+            .collect();
+        if !conflicts.is_empty() {
+            return Err(format!("Sessions already hosted here: {}", conflicts.join(", ")));
+        }
+
+        let session_ids = handoff.session_ids();
+        for migrated in handoff.sessions {
+            let session_id = migrated.session.session_id.clone();
+            if let Some(replay) = migrated.replay {
+                self.replays.insert(session_id.clone(), replay);
+            }
+            // A session coming back to a node that once handed it off
+            self.migrated_sessions.remove(&session_id);
+            self.game_sessions.insert(session_id, migrated.session);
+        }
+
+        println!(
+            "📥 Accepted {} game sessions from {}",
+            session_ids.len(),
+            handoff.source_node
+        );
+        Ok(session_ids)
+    }
+
+    /// Put a rejected handoff's sessions back so they are not lost
+    pub fn cancel_handoff(&mut self, handoff: SessionHandoff) {
+        for migrated in handoff.sessions {
+            let session_id = migrated.session.session_id.clone();
+            if let Some(replay) = migrated.replay {
+                self.replays.insert(session_id.clone(), replay);
+            }
+            self.migrated_sessions.remove(&session_id);
+            self.game_sessions.insert(session_id, migrated.session);
+        }
+        self.draining = false;
+    }
+
+    /// Node now hosting a session that was handed off from here
+    pub fn migrated_to(&self, session_id: &str) -> Option<&str> {
+        self.migrated_sessions.get(session_id).map(String::as_str)
+    }
+}
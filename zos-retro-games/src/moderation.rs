@@ -0,0 +1,256 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Direction {
+    UserInput,
+    AiOutput,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RulePattern {
+    Keyword(String), // case-insensitive substring
+    Regex(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModerationRule {
+    pub rule_id: String,
+    pub pattern: RulePattern,
+    pub severity: Severity,
+}
+
+/// What a group tolerates. Anything below `mask_at` passes untouched.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupPolicy {
+    pub mask_at: Severity,  // matched text replaced with ***
+    pub block_at: Severity, // message rejected
+    pub strike_at: Severity, // user input at this level earns a strike
+    pub strikes_before_escalation: u32,
+    pub strike_decay_secs: u64, // strikes reset after this long without a new one
+    pub reputation_penalty: f32,
+}
+
+impl Default for GroupPolicy {
+    fn default() -> Self {
+        Self {
+            mask_at: Severity::Low,
+            block_at: Severity::High,
+            strike_at: Severity::Medium,
+            strikes_before_escalation: 3,
+            strike_decay_secs: 86400,
+            reputation_penalty: 10.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModerationHit {
+    pub source: String, // rule id, or "classifier"
+    pub severity: Severity,
+    pub matched: Option<String>, // text to mask; None flags the whole message
+}
+
+/// Model-based second opinion, run after the keyword and regex rules
+pub trait ModerationClassifier: Send + Sync {
+    fn classify(&self, text: &str, direction: Direction) -> Option<ModerationHit>;
+}
+
+#[derive(Clone)]
+pub struct ClassifierHandle(pub Arc<dyn ModerationClassifier>);
+
+impl std::fmt::Debug for ClassifierHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ClassifierHandle")
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StrikeRecord {
+    pub strikes: u32,
+    pub last_strike_at: u64,
+    pub escalations: u32,
+}
+
+/// Sent to the reputation engine (the Telegram bot's reputation scores) by
+/// whoever drains `pending_escalations`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReputationEscalation {
+    pub user_id: String,
+    pub group_id: Option<String>,
+    pub strikes: u32,
+    pub penalty: f32,
+    pub reason: String,
+    pub escalated_at: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    Allow(String),
+    Masked(String),
+    Blocked(String), // reason shown to the user
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModerationPipeline {
+    pub rules: Vec<ModerationRule>,
+    pub default_policy: GroupPolicy,
+    pub group_policies: HashMap<String, GroupPolicy>,
+    pub strikes: HashMap<String, StrikeRecord>, // user_id -> strikes
+    pub pending_escalations: Vec<ReputationEscalation>,
+    #[serde(skip)]
+    compiled: HashMap<String, Regex>, // rule_id -> compiled pattern, rebuilt on demand
+    #[serde(skip)]
+    classifier: Option<ClassifierHandle>,
+}
+
+impl ModerationPipeline {
+    pub fn add_rule(&mut self, rule_id: &str, pattern: RulePattern, severity: Severity) -> Result<(), String> {
+        if let RulePattern::Regex(source) = &pattern {
+            let regex = Regex::new(&format!("(?i){}", source))
+                .map_err(|e| format!("Invalid moderation pattern {}: {}", rule_id, e))?;
+            self.compiled.insert(rule_id.to_string(), regex);
+        }
+
+        self.rules.retain(|rule| rule.rule_id != rule_id);
+        self.rules.push(ModerationRule {
+            rule_id: rule_id.to_string(),
+            pattern,
+            severity,
+        });
+        Ok(())
+    }
+
+    pub fn set_group_policy(&mut self, group_id: &str, policy: GroupPolicy) {
+        self.group_policies.insert(group_id.to_string(), policy);
+    }
+
+    pub fn set_classifier(&mut self, classifier: Arc<dyn ModerationClassifier>) {
+        self.classifier = Some(ClassifierHandle(classifier));
+    }
+
+    fn policy(&self, group_id: Option<&str>) -> &GroupPolicy {
+        group_id
+            .and_then(|group| self.group_policies.get(group))
+            .unwrap_or(&self.default_policy)
+    }
+
+    fn scan(&mut self, text: &str, direction: Direction) -> Vec<ModerationHit> {
+        let lowered = text.to_lowercase();
+        let mut hits = Vec::new();
+
+        for rule in &self.rules {
+            let matched = match &rule.pattern {
+                RulePattern::Keyword(keyword) => lowered
+                    .find(&keyword.to_lowercase())
+                    .map(|start| text.get(start..start + keyword.len()).unwrap_or(keyword).to_string()),
+                RulePattern::Regex(source) => {
+                    // Rules loaded from saved state arrive without their compiled form
+                    if !self.compiled.contains_key(&rule.rule_id) {
+                        match Regex::new(&format!("(?i){}", source)) {
+                            Ok(regex) => {
+                                self.compiled.insert(rule.rule_id.clone(), regex);
+                            }
+                            Err(_) => continue,
+                        }
+                    }
+                    self.compiled[&rule.rule_id]
+                        .find(text)
+                        .map(|m| m.as_str().to_string())
+                }
+            };
+
+            if let Some(matched) = matched {
+                hits.push(ModerationHit {
+                    source: rule.rule_id.clone(),
+                    severity: rule.severity,
+                    matched: Some(matched),
+                });
+            }
+        }
+
+        if let Some(classifier) = &self.classifier {
+            hits.extend(classifier.0.classify(text, direction));
+        }
+
+        hits
+    }
+
+    /// Run `text` through the rules and classifier and apply the group's
+    /// policy. User input can earn the sender a strike; AI output is only
+    /// masked or withheld.
+    pub fn moderate(&mut self, user_id: &str, group_id: Option<&str>, direction: Direction, text: &str) -> Verdict {
+        let hits = self.scan(text, direction);
+        let worst = match hits.iter().map(|hit| hit.severity).max() {
+            Some(worst) => worst,
+            None => return Verdict::Allow(text.to_string()),
+        };
+        let policy = self.policy(group_id).clone();
+
+        if direction == Direction::UserInput && worst >= policy.strike_at {
+            self.record_strike(user_id, group_id, &policy, &hits);
+        }
+
+        if worst >= policy.block_at {
+            let reason = match direction {
+                Direction::UserInput => "Message blocked by the lounge moderation policy.",
+                Direction::AiOutput => "[reply withheld by moderation]",
+            };
+            return Verdict::Blocked(reason.to_string());
+        }
+
+        if worst >= policy.mask_at {
+            let mut masked = text.to_string();
+            for hit in hits.iter().filter(|hit| hit.severity >= policy.mask_at) {
+                match &hit.matched {
+                    Some(matched) => masked = masked.replace(matched.as_str(), &"*".repeat(matched.chars().count())),
+                    None => return Verdict::Blocked("[message withheld by moderation]".to_string()),
+                }
+            }
+            return Verdict::Masked(masked);
+        }
+
+        Verdict::Allow(text.to_string())
+    }
+
+    fn record_strike(&mut self, user_id: &str, group_id: Option<&str>, policy: &GroupPolicy, hits: &[ModerationHit]) {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let record = self.strikes.entry(user_id.to_string()).or_default();
+
+        if now.saturating_sub(record.last_strike_at) > policy.strike_decay_secs {
+            record.strikes = 0;
+        }
+        record.strikes += 1;
+        record.last_strike_at = now;
+
+        if record.strikes >= policy.strikes_before_escalation {
+            record.escalations += 1;
+            let sources: Vec<&str> = hits.iter().map(|hit| hit.source.as_str()).collect();
+            self.pending_escalations.push(ReputationEscalation {
+                user_id: user_id.to_string(),
+                group_id: group_id.map(str::to_string),
+                strikes: record.strikes,
+                penalty: policy.reputation_penalty * record.escalations as f32,
+                reason: format!("Repeated moderation strikes ({})", sources.join(", ")),
+                escalated_at: now,
+            });
+            record.strikes = 0;
+            println!("🚩 Moderation escalation for {}", user_id);
+        }
+    }
+
+    /// Escalations not yet applied to reputation
+    pub fn drain_escalations(&mut self) -> Vec<ReputationEscalation> {
+        std::mem::take(&mut self.pending_escalations)
+    }
+}
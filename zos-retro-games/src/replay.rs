@@ -0,0 +1,222 @@
+use crate::{GameSession, HttpResponse, RetroAIServices};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+
+/// One command and what the game answered, with the state it left behind
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayFrame {
+    pub turn: u32,
+    pub at: u64,
+    pub command: String,
+    pub args: String,
+    pub response: String,
+    pub state_after: serde_json::Value,
+    pub credits_spent: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub session_id: String,
+    pub game_id: String,
+    pub user_id: String,
+    pub started_at: u64,
+    pub initial_state: serde_json::Value,
+    pub frames: Vec<ReplayFrame>,
+    pub share_token: Option<String>, // set while publicly shared
+}
+
+impl Replay {
+    pub fn for_session(session: &GameSession) -> Self {
+        Self {
+            session_id: session.session_id.clone(),
+            game_id: session.game_id.clone(),
+            user_id: session.user_id.clone(),
+            started_at: session.started_at,
+            initial_state: session.game_state.clone(),
+            frames: Vec::new(),
+            share_token: None,
+        }
+    }
+}
+
+fn share_token(session_id: &str) -> String {
+    // RandomState is keyed per process, so tokens cannot be derived from the session id
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write(session_id.as_bytes());
+    hasher.write_u64(chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0) as u64);
+    format!("{:016x}", hasher.finish())
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn response(status_code: u16, content_type: &str, body: Vec<u8>) -> HttpResponse {
+    HttpResponse {
+        status_code,
+        headers: HashMap::from([("Content-Type".to_string(), content_type.to_string())]),
+        body,
+    }
+}
+
+fn not_found() -> HttpResponse {
+    response(
+        404,
+        "application/json",
+        br#"{"error":"Replay not found or not shared"}"#.to_vec(),
+    )
+}
+
+impl RetroAIServices {
+    pub(crate) fn record_frame(&mut self, session_id: &str, command: &str, args: &str, response: &str) {
+        let session = match self.game_sessions.get(session_id) {
+            Some(session) => session,
+            None => return,
+        };
+
+        self.replays
+            .entry(session_id.to_string())
+            .or_insert_with(|| Replay::for_session(session))
+            .frames
+            .push(ReplayFrame {
+                turn: session.turns_taken,
+                at: session.last_action,
+                command: command.to_string(),
+                args: args.to_string(),
+                response: response.to_string(),
+                state_after: session.game_state.clone(),
+                credits_spent: session.credits_spent,
+            });
+    }
+
+    /// Make a replay public; returns the share link path
+    pub fn share_replay(&mut self, session_id: &str, user_id: &str) -> Result<String, String> {
+        let replay = self
+            .replays
+            .get_mut(session_id)
+            .ok_or("Replay not found")?;
+        if replay.user_id != user_id {
+            return Err("Only the player can share this replay".to_string());
+        }
+
+        let token = replay
+            .share_token
+            .get_or_insert_with(|| share_token(session_id))
+            .clone();
+        Ok(format!("/games/replays/shared/{}", token))
+    }
+
+    /// Revoke the share link; the old link stops working
+    pub fn unshare_replay(&mut self, session_id: &str, user_id: &str) -> Result<(), String> {
+        let replay = self
+            .replays
+            .get_mut(session_id)
+            .ok_or("Replay not found")?;
+        if replay.user_id != user_id {
+            return Err("Only the player can unshare this replay".to_string());
+        }
+
+        replay.share_token = None;
+        Ok(())
+    }
+
+    /// GET /games/replays/{session_id}[/view] for the player,
+    /// GET /games/replays/shared/{token}[/view] for anyone with the link
+    pub fn handle_replay_request(&self, path: &str, requester: Option<&str>) -> Result<HttpResponse, String> {
+        let rest = path
+            .strip_prefix("/games/replays/")
+            .ok_or("Not a replay path")?;
+        let (rest, view) = match rest.strip_suffix("/view") {
+            Some(rest) => (rest, true),
+            None => (rest, false),
+        };
+
+        let replay = match rest.strip_prefix("shared/") {
+            Some(token) => self
+                .replays
+                .values()
+                .find(|replay| replay.share_token.as_deref() == Some(token)),
+            None => self
+                .replays
+                .get(rest)
+                .filter(|replay| requester == Some(replay.user_id.as_str())),
+        };
+        let replay = match replay {
+            Some(replay) => replay,
+            None => return Ok(not_found()),
+        };
+
+        if view {
+            return Ok(response(200, "text/html; charset=utf-8", self.replay_viewer_html(replay).into_bytes()));
+        }
+
+        let body = serde_json::to_vec(replay)
+            .map_err(|e| format!("Failed to serialize replay: {}", e))?;
+        Ok(response(200, "application/json", body))
+    }
+
+    /// Dashboard page that steps through a replay turn by turn
+    fn replay_viewer_html(&self, replay: &Replay) -> String {
+        let game_name = self
+            .door_games
+            .get(&replay.game_id)
+            .map(|game| game.name.as_str())
+            .unwrap_or(&replay.game_id);
+        // Keep the embedded JSON from closing the script tag
+        let data = serde_json::to_string(replay)
+            .unwrap_or_else(|_| "null".to_string())
+            .replace("</", "<\\/");
+
+        format!(
+            r#"
+    <html>
+    <head><title>ZOS Replay - {}</title></head>
+    <body style="font-family: Arial; margin: 0; padding: 20px; background: #f5f5f5;">
+        <h1>🎞️ {} Replay</h1>
+        <p>Session: <code>{}</code> · {} turns</p>
+
+        <div style="background: white; padding: 20px; border-radius: 8px; margin: 20px 0;">
+            <button onclick="step(-1)" style="padding: 8px 16px;">◀ Prev</button>
+            <button onclick="step(1)" style="padding: 8px 16px;">Next ▶</button>
+            <span id="position" style="margin-left: 10px;"></span>
+            <h3 id="command"></h3>
+            <pre id="response" style="white-space: pre-wrap;"></pre>
+            <h4>Game state</h4>
+            <pre id="state" style="background: #f0f0f0; padding: 10px;"></pre>
+        </div>
+
+        <script>
+            const replay = {};
+            let index = -1;
+
+            function render() {{
+                const frame = index < 0 ? null : replay.frames[index];
+                document.getElementById('position').textContent =
+                    (index + 1) + ' / ' + replay.frames.length;
+                document.getElementById('command').textContent =
+                    frame ? '> ' + frame.command + ' ' + frame.args : 'Start';
+                document.getElementById('response').textContent = frame ? frame.response : '';
+                document.getElementById('state').textContent =
+                    JSON.stringify(frame ? frame.state_after : replay.initial_state, null, 2);
+            }}
+
+            function step(delta) {{
+                index = Math.max(-1, Math.min(replay.frames.length - 1, index + delta));
+                render();
+            }}
+
+            render();
+        </script>
+    </body>
+    </html>
+    "#,
+            escape_html(game_name),
+            escape_html(game_name),
+            escape_html(&replay.session_id),
+            replay.frames.len(),
+            data
+        )
+    }
+}
@@ -0,0 +1,313 @@
+use crate::moderation::{Direction, Verdict};
+use crate::RetroAIServices;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+const LOUNGE_GAME_ID: &str = "ai_lounge";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PresenceStatus {
+    Online,
+    Idle,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Presence {
+    pub joined_at: u64,
+    pub last_seen: u64,
+    pub status: PresenceStatus,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum MessageAuthor {
+    User(String),
+    Ai(String), // companion display name
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomMessage {
+    pub seq: u64,
+    pub author: MessageAuthor,
+    pub text: String,
+    pub at: u64,
+}
+
+/// Pushed to every subscriber; the node's WebSocket handler sends each one
+/// as a JSON text frame
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RoomEvent {
+    Joined { room_id: String, user_id: String },
+    Left { room_id: String, user_id: String },
+    Presence { room_id: String, user_id: String, status: PresenceStatus },
+    Message { room_id: String, message: RoomMessage },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatRoom {
+    pub room_id: String,
+    pub name: String,
+    pub ai_companion: Option<String>,
+    pub capacity: u32, // DoorGame.max_players of the lounge
+    pub members: HashMap<String, Presence>, // user_id -> presence
+    pub history: VecDeque<RoomMessage>,
+    pub history_limit: usize,
+    pub next_seq: u64,
+    #[serde(skip)]
+    subscribers: Vec<(String, Sender<RoomEvent>)>, // user_id -> live connection
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresenceTimeouts {
+    pub idle_after_secs: u64,
+    pub drop_after_secs: u64,
+}
+
+impl Default for PresenceTimeouts {
+    fn default() -> Self {
+        Self {
+            idle_after_secs: 60,
+            drop_after_secs: 300,
+        }
+    }
+}
+
+fn now() -> u64 {
+    chrono::Utc::now().timestamp() as u64
+}
+
+impl ChatRoom {
+    /// Send to every live connection, forgetting the ones that hung up
+    fn broadcast(&mut self, event: RoomEvent) {
+        self.subscribers
+            .retain(|(_, subscriber)| subscriber.send(event.clone()).is_ok());
+    }
+
+    fn append(&mut self, author: MessageAuthor, text: String) -> RoomMessage {
+        let message = RoomMessage {
+            seq: self.next_seq,
+            author,
+            text,
+            at: now(),
+        };
+        self.next_seq += 1;
+
+        self.history.push_back(message.clone());
+        while self.history.len() > self.history_limit {
+            self.history.pop_front();
+        }
+
+        self.broadcast(RoomEvent::Message {
+            room_id: self.room_id.clone(),
+            message: message.clone(),
+        });
+        message
+    }
+}
+
+impl RetroAIServices {
+    /// Open a lounge room; capacity comes from the lounge's max_players
+    pub fn create_room(&mut self, room_id: &str, name: &str) -> Result<(), String> {
+        if self.rooms.contains_key(room_id) {
+            return Err("Room already exists".to_string());
+        }
+        let lounge = self
+            .door_games
+            .get(LOUNGE_GAME_ID)
+            .ok_or("AI Chat Lounge is not installed")?;
+
+        self.rooms.insert(
+            room_id.to_string(),
+            ChatRoom {
+                room_id: room_id.to_string(),
+                name: name.to_string(),
+                ai_companion: lounge.ai_personality.clone(),
+                capacity: lounge.max_players,
+                members: HashMap::new(),
+                history: VecDeque::new(),
+                history_limit: 200,
+                next_seq: 0,
+                subscribers: Vec::new(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Join a room (or reconnect to it) and subscribe to its events. Full
+    /// rooms refuse new members; members reconnecting are always let back in.
+    pub fn join_room(&mut self, room_id: &str, user_id: &str) -> Result<Receiver<RoomEvent>, String> {
+        let room = self.rooms.get_mut(room_id).ok_or("Room not found")?;
+        let rejoining = room.members.contains_key(user_id);
+        if !rejoining && room.members.len() as u32 >= room.capacity {
+            return Err(format!("Room is full ({} players)", room.capacity));
+        }
+
+        let at = now();
+        room.members
+            .entry(user_id.to_string())
+            .and_modify(|presence| {
+                presence.last_seen = at;
+                presence.status = PresenceStatus::Online;
+            })
+            .or_insert(Presence {
+                joined_at: at,
+                last_seen: at,
+                status: PresenceStatus::Online,
+            });
+
+        let (sender, receiver) = channel();
+        room.subscribers.push((user_id.to_string(), sender));
+
+        if !rejoining {
+            room.broadcast(RoomEvent::Joined {
+                room_id: room_id.to_string(),
+                user_id: user_id.to_string(),
+            });
+        }
+        Ok(receiver)
+    }
+
+    pub fn leave_room(&mut self, room_id: &str, user_id: &str) -> Result<(), String> {
+        let room = self.rooms.get_mut(room_id).ok_or("Room not found")?;
+        if room.members.remove(user_id).is_none() {
+            return Err("Not in this room".to_string());
+        }
+
+        room.subscribers.retain(|(member, _)| member != user_id);
+        room.broadcast(RoomEvent::Left {
+            room_id: room_id.to_string(),
+            user_id: user_id.to_string(),
+        });
+        Ok(())
+    }
+
+    /// Keep a member online; call on any client activity or ping
+    pub fn room_heartbeat(&mut self, room_id: &str, user_id: &str) -> Result<(), String> {
+        let room = self.rooms.get_mut(room_id).ok_or("Room not found")?;
+        let presence = room.members.get_mut(user_id).ok_or("Not in this room")?;
+
+        presence.last_seen = now();
+        if presence.status != PresenceStatus::Online {
+            presence.status = PresenceStatus::Online;
+            room.broadcast(RoomEvent::Presence {
+                room_id: room_id.to_string(),
+                user_id: user_id.to_string(),
+                status: PresenceStatus::Online,
+            });
+        }
+        Ok(())
+    }
+
+    /// Mark quiet members idle and drop silent ones; run periodically
+    pub fn sweep_presence(&mut self) {
+        let at = now();
+        let timeouts = self.presence_timeouts.clone();
+
+        for room in self.rooms.values_mut() {
+            let mut changes = Vec::new();
+            for (user_id, presence) in room.members.iter_mut() {
+                let quiet = at.saturating_sub(presence.last_seen);
+                if quiet > timeouts.drop_after_secs {
+                    changes.push((user_id.clone(), None));
+                } else if quiet > timeouts.idle_after_secs && presence.status == PresenceStatus::Online {
+                    presence.status = PresenceStatus::Idle;
+                    changes.push((user_id.clone(), Some(PresenceStatus::Idle)));
+                }
+            }
+
+            for (user_id, status) in changes {
+                let event = match status {
+                    Some(status) => RoomEvent::Presence {
+                        room_id: room.room_id.clone(),
+                        user_id,
+                        status,
+                    },
+                    None => {
+                        room.members.remove(&user_id);
+                        room.subscribers.retain(|(member, _)| *member != user_id);
+                        RoomEvent::Left {
+                            room_id: room.room_id.clone(),
+                            user_id,
+                        }
+                    }
+                };
+                room.broadcast(event);
+            }
+        }
+    }
+
+    /// Post to a room. The message goes through moderation with the room as
+    /// the policy group, then the room's AI companion answers everyone.
+    pub fn post_room_message(&mut self, room_id: &str, user_id: &str, text: &str) -> Result<RoomMessage, String> {
+        let room = self.rooms.get(room_id).ok_or("Room not found")?;
+        if !room.members.contains_key(user_id) {
+            return Err("Join the room before posting".to_string());
+        }
+        let ai_companion = room.ai_companion.clone();
+
+        let text = match self
+            .moderation
+            .moderate(user_id, Some(room_id), Direction::UserInput, text)
+        {
+            Verdict::Allow(text) | Verdict::Masked(text) => text,
+            Verdict::Blocked(reason) => return Err(reason),
+        };
+
+        // One companion reply, seen by the whole room
+        let mut reply = None;
+        if let Some(ai_id) = ai_companion {
+            if let Some(name) = self.ai_personalities.get(&ai_id).map(|ai| ai.name.clone()) {
+                let response = self.generate_ai_response(&ai_id, "", &text);
+                let response = response
+                    .trim_start()
+                    .trim_start_matches(&format!("{}: ", name))
+                    .to_string();
+                if let Verdict::Allow(response) | Verdict::Masked(response) = self
+                    .moderation
+                    .moderate(user_id, Some(room_id), Direction::AiOutput, &response)
+                {
+                    reply = Some((name, response));
+                }
+            }
+        }
+
+        let room = self.rooms.get_mut(room_id).ok_or("Room not found")?;
+        if let Some(presence) = room.members.get_mut(user_id) {
+            presence.last_seen = now();
+        }
+        let message = room.append(MessageAuthor::User(user_id.to_string()), text);
+        if let Some((name, response)) = reply {
+            room.append(MessageAuthor::Ai(name), response);
+        }
+        Ok(message)
+    }
+
+    /// Messages after `since_seq`, for clients catching up after a reconnect
+    pub fn room_history(&self, room_id: &str, since_seq: Option<u64>) -> Result<Vec<RoomMessage>, String> {
+        let room = self.rooms.get(room_id).ok_or("Room not found")?;
+        Ok(room
+            .history
+            .iter()
+            .filter(|message| since_seq.map(|seq| message.seq > seq).unwrap_or(true))
+            .cloned()
+            .collect())
+    }
+
+    /// Who is in each room, for the lounge lobby
+    pub fn room_presence(&self) -> serde_json::Value {
+        let rooms: Vec<serde_json::Value> = self
+            .rooms
+            .values()
+            .map(|room| {
+                serde_json::json!({
+                    "room_id": room.room_id,
+                    "name": room.name,
+                    "capacity": room.capacity,
+                    "members": room.members
+                })
+            })
+            .collect();
+        serde_json::json!({ "rooms": rooms })
+    }
+}
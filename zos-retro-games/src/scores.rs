@@ -0,0 +1,323 @@
+use crate::replay::Replay;
+use crate::{GameSession, HighScore, RetroAIServices};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Entries kept per game board
+pub const MAX_BOARD_ENTRIES: usize = 100;
+
+/// Replays averaging less than this between turns are scripted, not played
+pub const MIN_SECS_PER_TURN: u64 = 1;
+
+/// A client's claim: the session it played and the score it says it got.
+/// The replay is only needed when this node no longer holds its own copy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoreSubmission {
+    pub session_id: String,
+    pub score: u64,
+    #[serde(default)]
+    pub replay: Option<Replay>,
+}
+
+/// A validated score held back for an admin because it looks implausible
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlaggedScore {
+    pub review_id: String,
+    pub game_id: String,
+    pub score: HighScore,
+    pub reasons: Vec<String>,
+    pub flagged_at: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ScoreOutcome {
+    Accepted { rank: usize },
+    Flagged { review_id: String, reasons: Vec<String> },
+}
+
+pub fn signing_key() -> String {
+    std::env::var("ZOS_SCORE_SIGNING_KEY").unwrap_or_else(|_| "zos-dev-score-key".to_string())
+}
+
+pub fn sign_score(key: &str, game_id: &str, score: &HighScore) -> Result<String, String> {
+    let mut unsigned = score.clone();
+    unsigned.signature = None;
+
+    let payload = serde_json::to_vec(&(game_id, &unsigned))
+        .map_err(|e| format!("Failed to serialize score: {}", e))?;
+
+    let mut mac = HmacSha256::new_from_slice(key.as_bytes())
+        .map_err(|e| format!("Invalid signing key: {}", e))?;
+    mac.update(&payload);
+
+    Ok(mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+/// Whether a board entry is exactly what this node (or one sharing its key) signed
+pub fn verify_score(key: &str, game_id: &str, score: &HighScore) -> bool {
+    match (&score.signature, sign_score(key, game_id, score)) {
+        (Some(signature), Ok(expected)) => *signature == expected,
+        _ => false,
+    }
+}
+
+/// The most a single turn can earn in a built-in game; user games have no
+/// server-side rule
+pub fn max_points_per_turn(game_id: &str) -> Option<u64> {
+    match game_id {
+        "tradewars2035" => Some(10),
+        "lord2035" => Some(10),
+        "ai_lounge" => Some(10),
+        "quantum_puzzle" => Some(100),
+        _ => None,
+    }
+}
+
+fn u64_at(state: &serde_json::Value, field: &str) -> u64 {
+    state[field].as_u64().unwrap_or(0)
+}
+
+/// Score a validated replay by the game's rules
+pub fn rule_score(replay: &Replay) -> u64 {
+    let last = replay
+        .frames
+        .last()
+        .map(|frame| &frame.state_after)
+        .unwrap_or(&replay.initial_state);
+    match replay.game_id.as_str() {
+        // Sectors explored beyond the starting one
+        "tradewars2035" => {
+            let mut sectors: Vec<u64> = replay
+                .frames
+                .iter()
+                .map(|frame| u64_at(&frame.state_after, "sector"))
+                .filter(|sector| *sector != u64_at(&replay.initial_state, "sector"))
+                .collect();
+            sectors.sort_unstable();
+            sectors.dedup();
+            sectors.len() as u64 * 10
+        }
+        // Forest fights won
+        "lord2035" => {
+            u64_at(&replay.initial_state, "forest_fights").saturating_sub(u64_at(last, "forest_fights")) * 10
+        }
+        "ai_lounge" => u64_at(last, "friendship_level") * 10,
+        "quantum_puzzle" => u64_at(last, "puzzles_solved") * 100,
+        // User games keep their own score in their state
+        _ => u64_at(last, "score"),
+    }
+}
+
+/// The lounge's state rule, without running moderation again
+fn lounge_turn(session: &mut GameSession, command: &str) -> Result<String, String> {
+    if command == "compliment" {
+        let friendship = session.game_state["friendship_level"].as_u64().unwrap_or(0);
+        session.game_state["friendship_level"] = serde_json::Value::Number((friendship + 1).into());
+    }
+    Ok(String::new())
+}
+
+impl RetroAIServices {
+    /// Re-run a replay from the game's starting state through the game
+    /// rules; every recorded state, turn count and credit total must follow
+    pub fn validate_replay(&self, replay: &Replay) -> Result<(), String> {
+        let (template, commands) = match self.door_games.get(&replay.game_id) {
+            Some(game) => (&game.game_state_template, &game.commands),
+            None => return Err("Game not found".to_string()),
+        };
+        if !self.is_user_game(&replay.game_id) && replay.initial_state != *template {
+            return Err("Replay does not start from the game's starting state".to_string());
+        }
+
+        let mut session = GameSession {
+            session_id: replay.session_id.clone(),
+            user_id: replay.user_id.clone(),
+            game_id: replay.game_id.clone(),
+            game_state: replay.initial_state.clone(),
+            turns_taken: 0,
+            credits_spent: 0,
+            started_at: replay.started_at,
+            last_action: replay.started_at,
+            ai_companion: None,
+            group_id: None,
+        };
+
+        for frame in &replay.frames {
+            let turn = session.turns_taken + 1;
+            if frame.turn != turn {
+                return Err(format!("Turn {} recorded as turn {}", turn, frame.turn));
+            }
+            if frame.at < session.last_action {
+                return Err(format!("Turn {} is timestamped before the one before it", turn));
+            }
+            let cost = commands
+                .iter()
+                .find(|c| c.command == frame.command)
+                .map(|c| c.cost_credits)
+                .ok_or_else(|| format!("Turn {} uses unknown command {}", turn, frame.command))?;
+
+            let result = match replay.game_id.as_str() {
+                "tradewars2035" => self.execute_tradewars_command(&mut session, &frame.command, &frame.args),
+                "lord2035" => self.execute_lord_command(&mut session, &frame.command, &frame.args),
+                "ai_lounge" => lounge_turn(&mut session, &frame.command),
+                "quantum_puzzle" => self.execute_puzzle_command(&mut session, &frame.command, &frame.args),
+                game_id if self.is_user_game(game_id) => {
+                    self.execute_user_game_command(&mut session, &frame.command, &frame.args)
+                }
+                _ => Ok(String::new()),
+            };
+            // Failed commands are never recorded
+            result.map_err(|e| format!("Turn {} could not have happened: {}", turn, e))?;
+
+            if session.game_state != frame.state_after {
+                return Err(format!("Turn {} does not follow the game rules", turn));
+            }
+            session.turns_taken = turn;
+            session.credits_spent += cost;
+            session.last_action = frame.at;
+            if frame.credits_spent != session.credits_spent {
+                return Err(format!("Turn {} records the wrong credits spent", turn));
+            }
+        }
+        Ok(())
+    }
+
+    /// Why a validated score needs an admin before it goes on the board
+    fn score_anomalies(&self, replay: &Replay, score: u64, own_replay: bool) -> Vec<String> {
+        let turns = replay.frames.len() as u64;
+        let mut reasons = Vec::new();
+
+        match max_points_per_turn(&replay.game_id) {
+            Some(per_turn) if score > per_turn * turns => reasons.push(format!(
+                "{} points in {} turns is more than {} per turn",
+                score, turns, per_turn
+            )),
+            Some(_) => {}
+            None => reasons.push("no server-side score rule for this game".to_string()),
+        }
+
+        let played_secs = replay
+            .frames
+            .last()
+            .map_or(0, |frame| frame.at.saturating_sub(replay.started_at));
+        if turns > 1 && played_secs < turns * MIN_SECS_PER_TURN {
+            reasons.push(format!("{} turns played in {}s", turns, played_secs));
+        }
+
+        if !own_replay {
+            reasons.push("replay supplied by the client, not recorded here".to_string());
+        }
+        reasons
+    }
+
+    /// Accept a high score only after re-running its replay. Forged claims
+    /// are refused; plausible-but-odd ones wait in `score_reviews`.
+    pub fn submit_high_score(&mut self, user_id: &str, submission: ScoreSubmission) -> Result<ScoreOutcome, String> {
+        let own = self.replays.get(&submission.session_id);
+        let own_replay = own.is_some();
+        let replay = own
+            .or(submission.replay.as_ref())
+            .filter(|replay| replay.session_id == submission.session_id)
+            .ok_or("No replay for this session")?
+            .clone();
+        if replay.user_id != user_id {
+            return Err("Only the player can submit this session's score".to_string());
+        }
+        if replay.frames.is_empty() {
+            return Err("Nothing was played in this session".to_string());
+        }
+        let already_submitted = self
+            .high_scores
+            .values()
+            .flatten()
+            .chain(self.score_reviews.iter().map(|review| &review.score))
+            .any(|score| score.session_id.as_deref() == Some(submission.session_id.as_str()));
+        if already_submitted {
+            return Err("A score was already submitted for this session".to_string());
+        }
+
+        self.validate_replay(&replay)?;
+        let score = rule_score(&replay);
+        if submission.score != score {
+            println!(
+                "🚫 Forged score from {}: claimed {}, replay earns {}",
+                user_id, submission.score, score
+            );
+            return Err(format!("Claimed score {} does not match the replay ({})", submission.score, score));
+        }
+
+        let last = replay.frames.last().map_or(replay.started_at, |frame| frame.at);
+        let mut record = HighScore {
+            user_id: user_id.to_string(),
+            score,
+            achieved_at: last,
+            game_data: replay.frames.last().map(|frame| frame.state_after.clone()).unwrap_or_default(),
+            session_id: Some(replay.session_id.clone()),
+            turns: replay.frames.len() as u32,
+            signature: None,
+        };
+        record.signature = Some(sign_score(&signing_key(), &replay.game_id, &record)?);
+
+        let reasons = self.score_anomalies(&replay, score, own_replay);
+        if !reasons.is_empty() {
+            let review_id = format!("review_{}", replay.session_id);
+            println!("🚩 Score {} by {} flagged: {}", score, user_id, reasons.join("; "));
+            self.score_reviews.push(FlaggedScore {
+                review_id: review_id.clone(),
+                game_id: replay.game_id.clone(),
+                score: record,
+                reasons: reasons.clone(),
+                flagged_at: chrono::Utc::now().timestamp() as u64,
+            });
+            return Ok(ScoreOutcome::Flagged { review_id, reasons });
+        }
+
+        let rank = self.place_score(&replay.game_id, record);
+        Ok(ScoreOutcome::Accepted { rank })
+    }
+
+    /// Insert into the board in score order; returns the 1-based rank
+    fn place_score(&mut self, game_id: &str, record: HighScore) -> usize {
+        let board = self.high_scores.entry(game_id.to_string()).or_default();
+        let rank = board.iter().take_while(|entry| entry.score >= record.score).count();
+        board.insert(rank, record);
+        board.truncate(MAX_BOARD_ENTRIES);
+        rank + 1
+    }
+
+    /// Scores waiting for an admin
+    pub fn pending_score_reviews(&self) -> &[FlaggedScore] {
+        &self.score_reviews
+    }
+
+    /// An admin's decision on a flagged score: approved scores go on the
+    /// board, rejected ones are dropped. Returns the rank if approved.
+    pub fn review_score(&mut self, review_id: &str, approve: bool, reviewer: &str) -> Result<Option<usize>, String> {
+        let index = self
+            .score_reviews
+            .iter()
+            .position(|review| review.review_id == review_id)
+            .ok_or("Review not found")?;
+        let review = self.score_reviews.remove(index);
+        println!(
+            "🧑‍⚖️ {} {} score {} by {}",
+            reviewer,
+            if approve { "approved" } else { "rejected" },
+            review.score.score,
+            review.score.user_id
+        );
+
+        if !approve {
+            return Ok(None);
+        }
+        Ok(Some(self.place_score(&review.game_id, review.score)))
+    }
+}
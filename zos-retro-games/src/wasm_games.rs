@@ -0,0 +1,428 @@
+//! User-authored door games compiled to WASM.
+//!
+//! A game module exports the GameModule interface; strings cross the
+//! boundary as UTF-8 in the module's memory, returned as `(ptr << 32) | len`:
+//!
+//! ```text
+//! memory
+//! zos_alloc(len: i32) -> i32
+//! zos_init() -> i64                                   ; initial state JSON
+//! zos_handle(state_ptr, state_len, in_ptr, in_len) -> i64
+//!     ; in:  {"command": "...", "args": "..."}
+//!     ; out: {"state": {...}, "output": "..."}
+//! zos_render(state_ptr, state_len) -> i64             ; screen text
+//! ```
+//!
+//! Modules get no imports, so they cannot reach the host. Every call runs in
+//! a fresh instance with a fuel budget and a memory cap.
+
+use crate::{DoorGame, GameCategory, GameCommand, GameSession, RetroAIServices};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+pub const GAME_MODULE_EXPORTS: [&str; 5] = ["memory", "zos_alloc", "zos_init", "zos_handle", "zos_render"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SandboxLimits {
+    pub fuel_per_call: u64, // wasmtime fuel, roughly one unit per instruction
+    pub max_memory_bytes: usize,
+    pub max_output_bytes: usize,
+}
+
+impl Default for SandboxLimits {
+    fn default() -> Self {
+        Self {
+            fuel_per_call: 10_000_000,
+            max_memory_bytes: 16 * 1024 * 1024,
+            max_output_bytes: 64 * 1024,
+        }
+    }
+}
+
+impl SandboxLimits {
+    /// Authors may ask for less than the node allows, never more
+    fn capped_by(&self, node: &SandboxLimits) -> SandboxLimits {
+        SandboxLimits {
+            fuel_per_call: self.fuel_per_call.min(node.fuel_per_call),
+            max_memory_bytes: self.max_memory_bytes.min(node.max_memory_bytes),
+            max_output_bytes: self.max_output_bytes.min(node.max_output_bytes),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameManifest {
+    pub game_id: String,
+    pub name: String,
+    pub description: String,
+    pub max_players: u32,
+    pub credits_per_turn: u64,
+    pub commands: Vec<GameCommand>,
+    #[serde(default)]
+    pub limits: SandboxLimits,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserGame {
+    pub manifest: GameManifest,
+    pub author_wallet: String,
+    pub module_hash: String, // sha256 hex of the WASM bytes
+    pub module: Vec<u8>,
+    pub published_at: u64,
+    pub listed: bool,
+}
+
+/// Credits a player spent in a user-authored game, owed to its author.
+/// Drained by the node and paid into the author's credit balance.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthorPayout {
+    pub author_wallet: String,
+    pub game_id: String,
+    pub session_id: String,
+    pub player_id: String,
+    pub credits: u64,
+    pub at: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MarketplaceListing {
+    pub game_id: String,
+    pub name: String,
+    pub description: String,
+    pub author_wallet: String,
+    pub credits_per_turn: u64,
+    pub module_hash: String,
+    pub published_at: u64,
+}
+
+#[derive(Deserialize)]
+struct TurnResult {
+    state: serde_json::Value,
+    output: String,
+}
+
+#[cfg(feature = "wasm-games")]
+mod sandbox {
+    use super::{SandboxLimits, GAME_MODULE_EXPORTS};
+    use std::collections::HashMap;
+    use std::sync::{Mutex, OnceLock};
+    use wasmtime::{Config, Engine, Instance, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+    fn engine() -> &'static Engine {
+        static ENGINE: OnceLock<Engine> = OnceLock::new();
+        ENGINE.get_or_init(|| {
+            let mut config = Config::new();
+            config.consume_fuel(true);
+            Engine::new(&config).expect("wasmtime engine with fuel metering")
+        })
+    }
+
+    /// Compiled modules by content hash, so turns skip recompilation
+    fn compiled(module_hash: &str, bytes: &[u8]) -> Result<Module, String> {
+        static MODULES: OnceLock<Mutex<HashMap<String, Module>>> = OnceLock::new();
+        let mut modules = MODULES
+            .get_or_init(|| Mutex::new(HashMap::new()))
+            .lock()
+            .map_err(|_| "Module cache poisoned".to_string())?;
+
+        if let Some(module) = modules.get(module_hash) {
+            return Ok(module.clone());
+        }
+        let module = Module::new(engine(), bytes).map_err(|e| format!("Invalid WASM module: {}", e))?;
+        modules.insert(module_hash.to_string(), module.clone());
+        Ok(module)
+    }
+
+    pub fn validate(module_hash: &str, bytes: &[u8]) -> Result<(), String> {
+        let module = compiled(module_hash, bytes)?;
+        if let Some(import) = module.imports().next() {
+            return Err(format!(
+                "Game modules may not import anything (found {}::{})",
+                import.module(),
+                import.name()
+            ));
+        }
+        for export in GAME_MODULE_EXPORTS {
+            if module.get_export(export).is_none() {
+                return Err(format!("Game module is missing export `{}`", export));
+            }
+        }
+        Ok(())
+    }
+
+    struct Call {
+        store: Store<StoreLimits>,
+        instance: Instance,
+        max_output_bytes: usize,
+    }
+
+    impl Call {
+        fn new(module_hash: &str, bytes: &[u8], limits: &SandboxLimits) -> Result<Self, String> {
+            let module = compiled(module_hash, bytes)?;
+            let mut store = Store::new(
+                engine(),
+                StoreLimitsBuilder::new()
+                    .memory_size(limits.max_memory_bytes)
+                    .instances(1)
+                    .build(),
+            );
+            store.limiter(|limits| limits);
+            store
+                .set_fuel(limits.fuel_per_call)
+                .map_err(|e| format!("Failed to set fuel: {}", e))?;
+            let instance = Instance::new(&mut store, &module, &[])
+                .map_err(|e| format!("Game module failed to start: {}", e))?;
+
+            Ok(Self {
+                store,
+                instance,
+                max_output_bytes: limits.max_output_bytes,
+            })
+        }
+
+        fn write(&mut self, data: &str) -> Result<(i32, i32), String> {
+            let len = i32::try_from(data.len()).map_err(|_| "Input too large".to_string())?;
+            let alloc = self
+                .instance
+                .get_typed_func::<i32, i32>(&mut self.store, "zos_alloc")
+                .map_err(|e| format!("zos_alloc: {}", e))?;
+            let ptr = alloc
+                .call(&mut self.store, len)
+                .map_err(|e| format!("Game module trapped in zos_alloc: {}", e))?;
+
+            let memory = self
+                .instance
+                .get_memory(&mut self.store, "memory")
+                .ok_or("Game module has no memory")?;
+            memory
+                .write(&mut self.store, ptr as u32 as usize, data.as_bytes())
+                .map_err(|e| format!("Game module returned a bad pointer: {}", e))?;
+            Ok((ptr, len))
+        }
+
+        fn read(&mut self, packed: i64) -> Result<String, String> {
+            let ptr = (packed as u64 >> 32) as usize;
+            let len = (packed as u64 & 0xffff_ffff) as usize;
+            if len > self.max_output_bytes {
+                return Err(format!("Game output exceeds {} bytes", self.max_output_bytes));
+            }
+
+            let memory = self
+                .instance
+                .get_memory(&mut self.store, "memory")
+                .ok_or("Game module has no memory")?;
+            let bytes = memory
+                .data(&self.store)
+                .get(ptr..ptr + len)
+                .ok_or("Game module returned a bad pointer")?;
+            String::from_utf8(bytes.to_vec()).map_err(|_| "Game output is not UTF-8".to_string())
+        }
+    }
+
+    fn trap(name: &str, e: wasmtime::Error) -> String {
+        format!("Game module stopped in {} (CPU or memory limit?): {}", name, e)
+    }
+
+    pub fn init(module_hash: &str, bytes: &[u8], limits: &SandboxLimits) -> Result<String, String> {
+        let mut call = Call::new(module_hash, bytes, limits)?;
+        let init = call
+            .instance
+            .get_typed_func::<(), i64>(&mut call.store, "zos_init")
+            .map_err(|e| format!("zos_init: {}", e))?;
+        let packed = init.call(&mut call.store, ()).map_err(|e| trap("zos_init", e))?;
+        call.read(packed)
+    }
+
+    pub fn handle(module_hash: &str, bytes: &[u8], limits: &SandboxLimits, state: &str, input: &str) -> Result<String, String> {
+        let mut call = Call::new(module_hash, bytes, limits)?;
+        let (state_ptr, state_len) = call.write(state)?;
+        let (input_ptr, input_len) = call.write(input)?;
+        let handle = call
+            .instance
+            .get_typed_func::<(i32, i32, i32, i32), i64>(&mut call.store, "zos_handle")
+            .map_err(|e| format!("zos_handle: {}", e))?;
+        let packed = handle
+            .call(&mut call.store, (state_ptr, state_len, input_ptr, input_len))
+            .map_err(|e| trap("zos_handle", e))?;
+        call.read(packed)
+    }
+
+    pub fn render(module_hash: &str, bytes: &[u8], limits: &SandboxLimits, state: &str) -> Result<String, String> {
+        let mut call = Call::new(module_hash, bytes, limits)?;
+        let (state_ptr, state_len) = call.write(state)?;
+        let render = call
+            .instance
+            .get_typed_func::<(i32, i32), i64>(&mut call.store, "zos_render")
+            .map_err(|e| format!("zos_render: {}", e))?;
+        let packed = render
+            .call(&mut call.store, (state_ptr, state_len))
+            .map_err(|e| trap("zos_render", e))?;
+        call.read(packed)
+    }
+}
+
+#[cfg(not(feature = "wasm-games"))]
+mod sandbox {
+    use super::SandboxLimits;
+
+    const DISABLED: &str = "This node was built without the wasm-games feature";
+
+    pub fn validate(_module_hash: &str, _bytes: &[u8]) -> Result<(), String> {
+        Err(DISABLED.to_string())
+    }
+
+    pub fn init(_module_hash: &str, _bytes: &[u8], _limits: &SandboxLimits) -> Result<String, String> {
+        Err(DISABLED.to_string())
+    }
+
+    pub fn handle(_module_hash: &str, _bytes: &[u8], _limits: &SandboxLimits, _state: &str, _input: &str) -> Result<String, String> {
+        Err(DISABLED.to_string())
+    }
+
+    pub fn render(_module_hash: &str, _bytes: &[u8], _limits: &SandboxLimits, _state: &str) -> Result<String, String> {
+        Err(DISABLED.to_string())
+    }
+}
+
+impl RetroAIServices {
+    /// Publish or update a WASM door game. The module is checked against the
+    /// GameModule interface and run once to produce the starting state.
+    pub fn publish_user_game(&mut self, author_wallet: &str, mut manifest: GameManifest, module: Vec<u8>) -> Result<String, String> {
+        if manifest.game_id.is_empty() || manifest.commands.is_empty() {
+            return Err("Manifest needs a game_id and at least one command".to_string());
+        }
+        match self.user_games.get(&manifest.game_id) {
+            Some(existing) if existing.author_wallet != author_wallet => {
+                return Err("Game id belongs to another author".to_string());
+            }
+            None if self.door_games.contains_key(&manifest.game_id) => {
+                return Err("Game id is taken by a built-in game".to_string());
+            }
+            _ => {}
+        }
+
+        manifest.limits = manifest.limits.capped_by(&self.sandbox_limits);
+        let module_hash: String = Sha256::digest(&module)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+
+        sandbox::validate(&module_hash, &module)?;
+        let initial_state: serde_json::Value =
+            serde_json::from_str(&sandbox::init(&module_hash, &module, &manifest.limits)?)
+                .map_err(|e| format!("zos_init did not return JSON: {}", e))?;
+
+        self.door_games.insert(
+            manifest.game_id.clone(),
+            DoorGame {
+                game_id: manifest.game_id.clone(),
+                name: manifest.name.clone(),
+                description: manifest.description.clone(),
+                category: GameCategory::Community,
+                max_players: manifest.max_players,
+                credits_per_turn: manifest.credits_per_turn,
+                ai_personality: None,
+                game_state_template: initial_state,
+                commands: manifest.commands.clone(),
+            },
+        );
+
+        let game_id = manifest.game_id.clone();
+        self.user_games.insert(
+            game_id.clone(),
+            UserGame {
+                manifest,
+                author_wallet: author_wallet.to_string(),
+                module_hash: module_hash.clone(),
+                module,
+                published_at: chrono::Utc::now().timestamp() as u64,
+                listed: true,
+            },
+        );
+
+        println!("🕹️  User game published: {} by {} ({})", game_id, author_wallet, &module_hash[..12]);
+        Ok(module_hash)
+    }
+
+    /// Hide a game from the marketplace; running sessions can finish
+    pub fn unlist_user_game(&mut self, author_wallet: &str, game_id: &str) -> Result<(), String> {
+        let game = self.user_games.get_mut(game_id).ok_or("Game not found")?;
+        if game.author_wallet != author_wallet {
+            return Err("Only the author can unlist this game".to_string());
+        }
+        game.listed = false;
+        Ok(())
+    }
+
+    pub fn marketplace_listings(&self) -> Vec<MarketplaceListing> {
+        self.user_games
+            .values()
+            .filter(|game| game.listed)
+            .map(|game| MarketplaceListing {
+                game_id: game.manifest.game_id.clone(),
+                name: game.manifest.name.clone(),
+                description: game.manifest.description.clone(),
+                author_wallet: game.author_wallet.clone(),
+                credits_per_turn: game.manifest.credits_per_turn,
+                module_hash: game.module_hash.clone(),
+                published_at: game.published_at,
+            })
+            .collect()
+    }
+
+    pub(crate) fn is_user_game(&self, game_id: &str) -> bool {
+        self.user_games.contains_key(game_id)
+    }
+
+    pub(crate) fn execute_user_game_command(
+        &self,
+        session: &mut GameSession,
+        command: &str,
+        args: &str,
+    ) -> Result<String, String> {
+        let game = self
+            .user_games
+            .get(&session.game_id)
+            .ok_or("Game not found")?;
+        let limits = &game.manifest.limits;
+
+        let state = session.game_state.to_string();
+        let input = serde_json::json!({ "command": command, "args": args }).to_string();
+        let turn: TurnResult = serde_json::from_str(&sandbox::handle(&game.module_hash, &game.module, limits, &state, &input)?)
+            .map_err(|e| format!("zos_handle did not return {{state, output}}: {}", e))?;
+
+        let screen = sandbox::render(&game.module_hash, &game.module, limits, &turn.state.to_string())?;
+        session.game_state = turn.state;
+
+        Ok(format!("{}\n{}", turn.output, screen))
+    }
+
+    /// Owe a turn's credits to the author of a user-authored game
+    pub(crate) fn route_author_payout(&mut self, session_id: &str, credits: u64) {
+        let session = match self.game_sessions.get(session_id) {
+            Some(session) => session,
+            None => return,
+        };
+        let game = match self.user_games.get(&session.game_id) {
+            Some(game) => game,
+            None => return,
+        };
+        if credits == 0 {
+            return;
+        }
+
+        self.pending_author_payouts.push(AuthorPayout {
+            author_wallet: game.author_wallet.clone(),
+            game_id: session.game_id.clone(),
+            session_id: session_id.to_string(),
+            player_id: session.user_id.clone(),
+            credits,
+            at: session.last_action,
+        });
+    }
+
+    /// Payouts not yet credited to authors
+    pub fn drain_author_payouts(&mut self) -> Vec<AuthorPayout> {
+        std::mem::take(&mut self.pending_author_payouts)
+    }
+}
@@ -1,5 +1,8 @@
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use serde::{Deserialize, Serialize};
+use zos_policy::{Decision, Op, Policy, PolicySet, Request};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TelegramBouncerBot {
@@ -9,8 +12,37 @@ pub struct TelegramBouncerBot {
     pub group_permissions: HashMap<i64, GroupConfig>, // chat_id -> config
     pub access_logs: HashMap<i64, Vec<AccessLog>>,    // telegram_id -> logs
     pub webhook_url: String,
+    #[serde(default)]
+    pub tier_progress: HashMap<String, TierProgressInfo>, // wallet -> latest progress from the gateway
+    #[serde(default)]
+    pub access_log_archive: zos_archive::Archive, // segments holding access logs past retention
+    #[serde(skip)]
+    pub balance_oracle: BalanceOracleHook,
+    #[serde(default)]
+    pub service_calls: HashMap<i64, Vec<ServiceCallRecord>>, // telegram_id -> /call charges
+    #[serde(default = "default_max_call_credits")]
+    pub max_call_credits: u64, // dearer services aren't callable from chat
+    #[serde(skip)]
+    pub service_gateway: ServiceGatewayHook,
+    #[serde(skip)]
+    pub message_sender: MessageSenderHook,
+    #[serde(default = "default_stream_edit_interval_ms")]
+    pub stream_edit_interval_ms: u64, // Telegram throttles frequent edits of one message
+    #[serde(default)]
+    pub bot_username: Option<String>, // when set, commands for other bots ("/call@OtherBot") are ignored
 }
 
+fn default_max_call_credits() -> u64 {
+    10
+}
+
+fn default_stream_edit_interval_ms() -> u64 {
+    1_000
+}
+
+/// Longest message Telegram accepts; `/call` output is split at this size
+const MAX_MESSAGE_CHARS: usize = 4096;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LinkedAccount {
     pub telegram_id: i64,
@@ -42,6 +74,8 @@ pub struct GroupConfig {
     pub welcome_message: Option<String>,
     pub rules_message: Option<String>,
     pub admin_notifications: bool,
+    #[serde(default)]
+    pub policies: PolicySet, // evaluated after the requirements' own policies
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -52,6 +86,279 @@ pub struct AccessRequirements {
     pub required_verifications: Vec<String>,
     pub whitelist_wallets: Vec<String>,
     pub blacklist_wallets: Vec<String>,
+    #[serde(default)]
+    pub nft_requirements: Vec<NftRequirement>, // members must hold all of these
+}
+
+impl AccessRequirements {
+    /// The requirements as "group:join" policies: one permit needing every
+    /// threshold, and a forbid for blacklisted wallets
+    pub fn to_policies(&self) -> PolicySet {
+        let mut permit = Policy::permit("group-requirements", &["group:join"]);
+        if let Some(min_balance) = self.min_balance {
+            permit = permit.when("principal.balance", Op::Gte, serde_json::json!(min_balance));
+        }
+        if let Some(min_reputation) = self.min_reputation {
+            permit = permit.when("principal.reputation", Op::Gte, serde_json::json!(min_reputation));
+        }
+        if !self.whitelist_wallets.is_empty() {
+            permit = permit.when("principal.wallet", Op::In, serde_json::json!(self.whitelist_wallets));
+        }
+        for requirement in &self.nft_requirements {
+            permit = permit.when(&format!("principal.nfts.{}", requirement.collection_address),
+                                 Op::Gte, serde_json::json!(requirement.min_count));
+        }
+
+        let mut policies = PolicySet::new(vec![permit]);
+        if !self.blacklist_wallets.is_empty() {
+            policies.push(Policy::forbid("group-blacklist", &["group:*"])
+                .describe("Wallet is blacklisted from this group")
+                .when("principal.wallet", Op::In, serde_json::json!(self.blacklist_wallets)));
+        }
+        policies
+    }
+}
+
+/// Hold at least `min_count` NFTs from a Metaplex verified collection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NftRequirement {
+    pub collection_address: String, // collection mint
+    pub min_count: u32,
+}
+
+/// On-chain holdings lookup (an RPC or indexer), plugged in with
+/// `set_balance_oracle`
+pub trait BalanceOracle: Send + Sync {
+    fn provider(&self) -> &str;
+    fn nft_count(&self, wallet_address: &str, collection_address: &str) -> Result<u32, String>;
+}
+
+/// Default: holdings can't be looked up, so NFT-gated groups turn everyone away
+pub struct NoBalanceOracle;
+
+impl BalanceOracle for NoBalanceOracle {
+    fn provider(&self) -> &str {
+        "none"
+    }
+
+    fn nft_count(&self, _wallet_address: &str, _collection_address: &str) -> Result<u32, String> {
+        Err("No balance oracle configured".to_string())
+    }
+}
+
+#[derive(Clone)]
+pub struct BalanceOracleHook(pub Arc<dyn BalanceOracle>);
+
+impl Default for BalanceOracleHook {
+    fn default() -> Self {
+        Self(Arc::new(NoBalanceOracle))
+    }
+}
+
+impl std::fmt::Debug for BalanceOracleHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "BalanceOracleHook({})", self.0.provider())
+    }
+}
+
+/// Service calls on a linked wallet's behalf (the gateway, with credits
+/// charged to that wallet), plugged in with `set_service_gateway`
+pub trait ServiceGateway: Send + Sync {
+    fn provider(&self) -> &str;
+    fn credits(&self, wallet_address: &str) -> Result<u64, String>;
+    /// Credits one call to `service` ("owner/service") costs
+    fn price(&self, service: &str) -> Result<u64, String>;
+    /// Call `service` as the wallet, handing each piece of the response to
+    /// `on_chunk` as it arrives; returns the credits charged
+    fn invoke(&self, wallet_address: &str, service: &str, args: &str,
+              on_chunk: &mut dyn FnMut(&str)) -> Result<u64, String>;
+}
+
+/// Default: no gateway to call, so `/call` always says so
+pub struct NoServiceGateway;
+
+impl ServiceGateway for NoServiceGateway {
+    fn provider(&self) -> &str {
+        "none"
+    }
+
+    fn credits(&self, _wallet_address: &str) -> Result<u64, String> {
+        Err("No service gateway configured".to_string())
+    }
+
+    fn price(&self, _service: &str) -> Result<u64, String> {
+        Err("No service gateway configured".to_string())
+    }
+
+    fn invoke(&self, _wallet_address: &str, _service: &str, _args: &str,
+              _on_chunk: &mut dyn FnMut(&str)) -> Result<u64, String> {
+        Err("No service gateway configured".to_string())
+    }
+}
+
+#[derive(Clone)]
+pub struct ServiceGatewayHook(pub Arc<dyn ServiceGateway>);
+
+impl Default for ServiceGatewayHook {
+    fn default() -> Self {
+        Self(Arc::new(NoServiceGateway))
+    }
+}
+
+impl std::fmt::Debug for ServiceGatewayHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ServiceGatewayHook({})", self.0.provider())
+    }
+}
+
+/// Sends and edits messages while an update is still being handled, so
+/// `/call` output can stream into chat; plugged in with `set_message_sender`
+pub trait MessageSender: Send + Sync {
+    fn provider(&self) -> &str;
+    /// Send a message now; returns its message_id
+    fn send(&self, chat_id: i64, text: &str) -> Result<i64, String>;
+    fn edit(&self, chat_id: i64, message_id: i64, text: &str) -> Result<(), String>;
+}
+
+/// Default: nothing goes out mid-update, so `/call` output is returned with
+/// the other responses once the call ends
+pub struct NoMessageSender;
+
+impl MessageSender for NoMessageSender {
+    fn provider(&self) -> &str {
+        "none"
+    }
+
+    fn send(&self, _chat_id: i64, _text: &str) -> Result<i64, String> {
+        Err("No message sender configured".to_string())
+    }
+
+    fn edit(&self, _chat_id: i64, _message_id: i64, _text: &str) -> Result<(), String> {
+        Err("No message sender configured".to_string())
+    }
+}
+
+#[derive(Clone)]
+pub struct MessageSenderHook(pub Arc<dyn MessageSender>);
+
+impl Default for MessageSenderHook {
+    fn default() -> Self {
+        Self(Arc::new(NoMessageSender))
+    }
+}
+
+impl std::fmt::Debug for MessageSenderHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "MessageSenderHook({})", self.0.provider())
+    }
+}
+
+/// Where `/call` output goes. With a sender, a placeholder message is edited
+/// as chunks arrive and a fresh message is started whenever one fills up;
+/// without one, or once sending fails, output is collected into
+/// message-sized replies.
+struct CallOutput {
+    chat_id: i64,
+    sender: Option<Arc<dyn MessageSender>>,
+    message_id: Option<i64>, // message being filled, once sent
+    current: String,
+    streamed: bool,          // some output has reached chat
+    dirty: bool,
+    last_flush: Instant,
+    interval: Duration,
+    replies: Vec<TelegramResponse>,
+}
+
+impl CallOutput {
+    fn new(chat_id: i64, sender: Arc<dyn MessageSender>, placeholder: &str, interval: Duration) -> Self {
+        let message_id = sender.send(chat_id, placeholder).ok();
+        Self {
+            chat_id,
+            sender: message_id.map(|_| sender),
+            message_id,
+            current: String::new(),
+            streamed: false,
+            dirty: false,
+            last_flush: Instant::now(),
+            interval,
+            replies: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, chunk: &str) {
+        for c in chunk.chars() {
+            if self.current.chars().count() == MAX_MESSAGE_CHARS {
+                self.flush();
+                self.start_message();
+            }
+            self.current.push(c);
+            self.dirty = true;
+        }
+        if self.last_flush.elapsed() >= self.interval {
+            self.flush();
+        }
+    }
+
+    /// Put the current message's text in chat
+    fn flush(&mut self) {
+        if !self.dirty {
+            return;
+        }
+        let Some(sender) = &self.sender else {
+            return;
+        };
+
+        let sent = match self.message_id {
+            Some(message_id) => sender.edit(self.chat_id, message_id, &self.current),
+            None => sender.send(self.chat_id, &self.current).map(|message_id| self.message_id = Some(message_id)),
+        };
+        match sent {
+            Ok(()) => {
+                self.streamed = true;
+                self.dirty = false;
+            }
+            Err(e) => {
+                println!("⚠️  Streaming /call output failed, replying at the end instead: {}", e);
+                self.sender = None;
+            }
+        }
+        self.last_flush = Instant::now();
+    }
+
+    fn start_message(&mut self) {
+        let text = std::mem::take(&mut self.current);
+        if self.sender.is_some() {
+            self.message_id = None;
+        } else {
+            self.replies.push(TelegramResponse::SendMessage { chat_id: self.chat_id, text, reply_markup: None });
+        }
+    }
+
+    /// Flush what's left and close with `summary`, which replaces the
+    /// placeholder when nothing was streamed into it
+    fn finish(mut self, summary: String) -> Vec<TelegramResponse> {
+        self.flush();
+        if self.sender.is_none() && !self.current.is_empty() {
+            self.start_message();
+        }
+
+        let placeholder = self.message_id.filter(|_| !self.streamed);
+        match (&self.sender, placeholder) {
+            (Some(sender), Some(message_id)) if sender.edit(self.chat_id, message_id, &summary).is_ok() => {}
+            _ => self.replies.push(TelegramResponse::SendMessage { chat_id: self.chat_id, text: summary, reply_markup: None }),
+        }
+        self.replies
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TierProgressInfo {
+    pub current_tier: String,
+    pub next_tier: Option<String>,
+    pub referral_count: u32,
+    pub referrals_needed: u32,
+    pub percent: f64,
+    pub badges: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -63,6 +370,25 @@ pub struct AccessLog {
     pub reason: Option<String>,
 }
 
+/// A `/call` made from chat and what it cost
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceCallRecord {
+    pub timestamp: u64,
+    pub wallet_address: String,
+    pub service: String,
+    pub chat_id: i64,
+    pub credits_charged: u64,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+/// An access log in cold storage, with the member it belongs to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedAccessLog {
+    pub telegram_id: i64,
+    pub log: AccessLog,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum VerificationStatus {
     Pending,
@@ -127,6 +453,43 @@ impl TelegramBouncerBot {
             group_permissions: HashMap::new(),
             access_logs: HashMap::new(),
             webhook_url: webhook_url.to_string(),
+            tier_progress: HashMap::new(),
+            access_log_archive: zos_archive::Archive::default(),
+            balance_oracle: BalanceOracleHook::default(),
+            service_calls: HashMap::new(),
+            max_call_credits: default_max_call_credits(),
+            service_gateway: ServiceGatewayHook::default(),
+            message_sender: MessageSenderHook::default(),
+            stream_edit_interval_ms: default_stream_edit_interval_ms(),
+            bot_username: None,
+        }
+    }
+
+    pub fn set_balance_oracle(&mut self, oracle: Arc<dyn BalanceOracle>) {
+        println!("🖼️  Balance oracle: {}", oracle.provider());
+        self.balance_oracle = BalanceOracleHook(oracle);
+    }
+
+    pub fn set_service_gateway(&mut self, gateway: Arc<dyn ServiceGateway>) {
+        println!("📞 Service gateway: {}", gateway.provider());
+        self.service_gateway = ServiceGatewayHook(gateway);
+    }
+
+    pub fn set_message_sender(&mut self, sender: Arc<dyn MessageSender>) {
+        println!("📨 Message sender: {}", sender.provider());
+        self.message_sender = MessageSenderHook(sender);
+    }
+
+    /// A message's command without its "@BotName" suffix; None when the
+    /// text isn't a command or names another bot
+    fn command_name<'a>(&self, text: &'a str) -> Option<&'a str> {
+        let word = text.split_whitespace().next().filter(|word| word.starts_with('/'))?;
+        match word.split_once('@') {
+            Some((command, bot)) => match &self.bot_username {
+                Some(username) if !username.trim_start_matches('@').eq_ignore_ascii_case(bot) => None,
+                _ => Some(command),
+            },
+            None => Some(word),
         }
     }
 
@@ -211,11 +574,15 @@ impl TelegramBouncerBot {
                 }
             }
 
-            // Handle commands
+            // Handle commands; /call may answer in several messages
             if let Some(text) = &message.text {
-                if text.starts_with('/') {
-                    let response = self.handle_command(text, message)?;
-                    responses.push(response);
+                match self.command_name(text) {
+                    Some("/call") => responses.extend(self.handle_service_call(text, message)),
+                    Some(command) => {
+                        let response = self.handle_command(command, text, message)?;
+                        responses.push(response);
+                    }
+                    None => {}
                 }
             }
         }
@@ -230,15 +597,15 @@ impl TelegramBouncerBot {
     }
 
     fn handle_new_member(&mut self, member: &TelegramUser, chat: &TelegramChat) -> Result<TelegramResponse, String> {
-        let group_config = self.group_permissions.get(&chat.id);
+        let group_config = self.group_permissions.get(&chat.id).cloned();
 
         // Check if user has linked wallet
         if let Some(linked_account) = self.linked_accounts.get(&member.id) {
             // Check access requirements
-            if let Some(config) = group_config {
-                let access_granted = self.check_access_requirements(linked_account, &config.access_requirements)?;
+            if let Some(config) = &group_config {
+                let decision = self.check_access_requirements(linked_account, config);
 
-                if access_granted {
+                if decision.allowed {
                     self.log_access(member.id, chat.id, "join_approved", true, None);
 
                     let welcome_msg = config.welcome_message.as_deref()
@@ -251,7 +618,7 @@ impl TelegramBouncerBot {
                     });
                 } else {
                     // Kick user - insufficient access
-                    self.log_access(member.id, chat.id, "join_denied", false, Some("Insufficient wallet balance/tier".to_string()));
+                    self.log_access(member.id, chat.id, "join_denied", false, Some(decision.reason));
 
                     return Ok(TelegramResponse::KickChatMember {
                         chat_id: chat.id,
@@ -291,9 +658,8 @@ impl TelegramBouncerBot {
         })
     }
 
-    fn handle_command(&mut self, text: &str, message: &TelegramMessage) -> Result<TelegramResponse, String> {
+    fn handle_command(&mut self, command: &str, text: &str, message: &TelegramMessage) -> Result<TelegramResponse, String> {
         let parts: Vec<&str> = text.split_whitespace().collect();
-        let command = parts[0];
 
         match command {
             "/start" => {
@@ -304,6 +670,8 @@ impl TelegramBouncerBot {
                            /link - Link your wallet\n\
                            /status - Check your verification status\n\
                            /balance - Check wallet balance\n\
+                           /tier - Progress to your next earnings tier\n\
+                           /call <service> <args> - Call a service with your credits\n\
                            /help - Show this help".to_string(),
                     reply_markup: None,
                 })
@@ -368,6 +736,47 @@ impl TelegramBouncerBot {
                     })
                 }
             },
+            "/tier" => {
+                let user_id = message.from.as_ref().unwrap().id;
+
+                let progress = self.linked_accounts.get(&user_id)
+                    .and_then(|account| self.tier_progress.get(&account.wallet_address));
+
+                let text = match progress {
+                    Some(progress) => {
+                        let filled = (progress.percent / 10.0).round().clamp(0.0, 10.0) as usize;
+                        let bar = format!("{}{}", "▓".repeat(filled), "░".repeat(10 - filled));
+                        let next = match &progress.next_tier {
+                            Some(next) => format!("{} more referrals to *{}*", progress.referrals_needed, next),
+                            None => "Top tier reached 🎉".to_string(),
+                        };
+                        format!(
+                            "🏅 *Earnings Tier*\n\n\
+                            Tier: {}\n\
+                            Referrals: {}\n\
+                            {} {:.0}%\n\
+                            {}\n\
+                            Badges: {}",
+                            progress.current_tier,
+                            progress.referral_count,
+                            bar,
+                            progress.percent,
+                            next,
+                            if progress.badges.is_empty() { "none yet".to_string() } else { progress.badges.join(", ") }
+                        )
+                    }
+                    None if self.linked_accounts.contains_key(&user_id) => {
+                        "No earnings yet. Share a referral link to start climbing tiers.".to_string()
+                    }
+                    None => "❌ No wallet linked. Use /link <wallet_address>".to_string(),
+                };
+
+                Ok(TelegramResponse::SendMessage {
+                    chat_id: message.chat.id,
+                    text,
+                    reply_markup: None,
+                })
+            },
             _ => {
                 Ok(TelegramResponse::SendMessage {
                     chat_id: message.chat.id,
@@ -381,7 +790,7 @@ impl TelegramBouncerBot {
     fn handle_callback_query(&mut self, callback: &CallbackQuery) -> Result<TelegramResponse, String> {
         if let Some(data) = &callback.data {
             if data.starts_with("link_wallet_") {
-                let user_id: i64 = data.replace("link_wallet_", "").parse()
+                let _user_id: i64 = data.replace("link_wallet_", "").parse()
                     .map_err(|_| "Invalid callback data")?;
 
                 return Ok(TelegramResponse::SendMessage {
@@ -398,36 +807,38 @@ impl TelegramBouncerBot {
         })
     }
 
-    fn check_access_requirements(&self, account: &LinkedAccount,
-                                requirements: &AccessRequirements) -> Result<bool, String> {
-
-        // Check minimum balance (would integrate with actual wallet balance check)
-        if let Some(min_balance) = requirements.min_balance {
-            // Simplified - would check actual wallet balance
-            if min_balance > 1000 {
-                return Ok(false);
-            }
-        }
+    fn check_access_requirements(&self, account: &LinkedAccount, config: &GroupConfig) -> Decision {
+        // Simplified - would check actual wallet balance
+        let balance = 1000;
 
-        // Check reputation
-        if let Some(min_reputation) = requirements.min_reputation {
-            if account.reputation_score < min_reputation {
-                return Ok(false);
-            }
-        }
-
-        // Check whitelist/blacklist
-        if !requirements.whitelist_wallets.is_empty() {
-            if !requirements.whitelist_wallets.contains(&account.wallet_address) {
-                return Ok(false);
+        // NFT holdings; a count the oracle couldn't supply is left out, and
+        // a condition on a missing attribute never holds
+        let mut nfts = serde_json::Map::new();
+        for requirement in &config.access_requirements.nft_requirements {
+            match self.balance_oracle.0.nft_count(&account.wallet_address, &requirement.collection_address) {
+                Ok(count) => {
+                    nfts.insert(requirement.collection_address.clone(), serde_json::json!(count));
+                }
+                Err(e) => println!("⚠️  NFT check failed for {}: {}", account.wallet_address, e),
             }
         }
 
-        if requirements.blacklist_wallets.contains(&account.wallet_address) {
-            return Ok(false);
-        }
+        let request = Request::new("group:join")
+            .principal(serde_json::json!({
+                "wallet": account.wallet_address,
+                "balance": balance,
+                "reputation": account.reputation_score,
+                "access_level": format!("{:?}", account.access_level),
+                "verification": format!("{:?}", account.verification_status),
+                "tier": self.tier_progress.get(&account.wallet_address).map(|progress| &progress.current_tier),
+                "nfts": nfts,
+            }))
+            .resource(serde_json::json!({
+                "chat_id": config.chat_id,
+                "group_name": config.group_name,
+            }));
 
-        Ok(true)
+        config.access_requirements.to_policies().with(&config.policies).evaluate(&request)
     }
 
     fn verify_wallet_signature(&self, wallet_address: &str, signature: &str, message: &str) -> bool {
@@ -436,6 +847,73 @@ impl TelegramBouncerBot {
         signature.len() > 10 && signature.contains(wallet_address) && signature.contains(message)
     }
 
+    /// `/call <owner/service> <args>`: check the linked wallet can afford the
+    /// service, call it through the gateway and stream the output into chat
+    /// (see `CallOutput`), ending with what was charged
+    fn handle_service_call(&mut self, text: &str, message: &TelegramMessage) -> Vec<TelegramResponse> {
+        let chat_id = message.chat.id;
+        let reply = |text: String| vec![TelegramResponse::SendMessage { chat_id, text, reply_markup: None }];
+
+        let Some(user_id) = message.from.as_ref().map(|user| user.id) else {
+            return Vec::new();
+        };
+        let mut parts = text.trim().splitn(3, char::is_whitespace);
+        parts.next(); // "/call"
+        let Some(service) = parts.next().filter(|service| !service.is_empty()) else {
+            return reply("Usage: /call <service> <args>".to_string());
+        };
+        let args = parts.next().unwrap_or("").trim();
+
+        let Some(wallet_address) = self.linked_accounts.get(&user_id)
+            .map(|account| account.wallet_address.clone()) else {
+            return reply("🔐 Link your wallet with /link before calling services.".to_string());
+        };
+
+        let gateway = self.service_gateway.0.clone();
+        let price = match gateway.price(service) {
+            Ok(price) => price,
+            Err(e) => return reply(format!("❌ Error: {}", e)),
+        };
+        if price > self.max_call_credits {
+            return reply(format!("💸 `{}` costs {} credits; only services up to {} can be called from chat.",
+                                 service, price, self.max_call_credits));
+        }
+        let credits = match gateway.credits(&wallet_address) {
+            Ok(credits) => credits,
+            Err(e) => return reply(format!("❌ Error: {}", e)),
+        };
+        if credits < price {
+            self.log_access(user_id, chat_id, "service_call", false, Some("Insufficient credits".to_string()));
+            return reply(format!("💳 `{}` costs {} credits and you have {}.", service, price, credits));
+        }
+
+        let mut output = CallOutput::new(chat_id, self.message_sender.0.clone(), &format!("⏳ Calling `{}`…", service),
+                                         Duration::from_millis(self.stream_edit_interval_ms));
+        let result = gateway.invoke(&wallet_address, service, args, &mut |chunk: &str| output.push(chunk));
+
+        let (charged, error) = match result {
+            Ok(charged) => (charged, None),
+            Err(e) => (0, Some(e)),
+        };
+        self.service_calls.entry(user_id).or_default().push(ServiceCallRecord {
+            timestamp: chrono::Utc::now().timestamp() as u64,
+            wallet_address,
+            service: service.to_string(),
+            chat_id,
+            credits_charged: charged,
+            success: error.is_none(),
+            error: error.clone(),
+        });
+        self.log_access(user_id, chat_id, "service_call", error.is_none(), error.clone());
+
+        let summary = match error {
+            None => format!("💳 Charged {} credits for `{}` ({} left).", charged, service,
+                            credits.saturating_sub(charged)),
+            Some(e) => format!("❌ `{}` failed: {}", service, e),
+        };
+        output.finish(summary)
+    }
+
     fn log_access(&mut self, telegram_id: i64, chat_id: i64, action: &str, success: bool, reason: Option<String>) {
         let log = AccessLog {
             timestamp: chrono::Utc::now().timestamp() as u64,
@@ -446,10 +924,180 @@ impl TelegramBouncerBot {
         };
 
         self.access_logs.entry(telegram_id)
-            .or_insert_with(Vec::new)
+            .or_default()
             .push(log);
     }
 
+    /// Telegram links, access logs and `/call` charges for a wallet, for
+    /// `/api/v1/export/{wallet}`
+    pub fn export_wallet_data(&self, wallet_address: &str) -> serde_json::Value {
+        let accounts: Vec<&LinkedAccount> = self.linked_accounts.values()
+            .filter(|account| account.wallet_address == wallet_address)
+            .collect();
+
+        let access_logs: HashMap<i64, &Vec<AccessLog>> = accounts.iter()
+            .filter_map(|account| self.access_logs.get(&account.telegram_id)
+                .map(|logs| (account.telegram_id, logs)))
+            .collect();
+
+        let service_calls: Vec<&ServiceCallRecord> = self.service_calls.values()
+            .flatten()
+            .filter(|call| call.wallet_address == wallet_address)
+            .collect();
+
+        serde_json::json!({
+            "linked_accounts": accounts,
+            "access_logs": access_logs,
+            "service_calls": service_calls
+        })
+    }
+
+    /// Move access logs older than `retention_days` into archive segments;
+    /// logs leave the bot only once their segment is stored. Returns how
+    /// many were archived.
+    pub fn archive_access_logs(&mut self, store: &mut dyn zos_archive::ArchiveStore,
+                               retention_days: u64, now: u64) -> Result<usize, String> {
+        let cutoff = now.saturating_sub(retention_days * 86_400);
+        let mut old: Vec<ArchivedAccessLog> = self.access_logs.iter()
+            .flat_map(|(telegram_id, logs)| logs.iter()
+                .filter(|log| log.timestamp < cutoff)
+                .map(|log| ArchivedAccessLog { telegram_id: *telegram_id, log: log.clone() }))
+            .collect();
+        old.sort_by_key(|archived| archived.log.timestamp);
+
+        let mut archived = 0;
+        for chunk in old.chunks(zos_archive::MAX_SEGMENT_RECORDS) {
+            self.access_log_archive.seal(store, "access_logs", chunk, |archived| archived.log.timestamp, now)?;
+            for archived_log in chunk {
+                if let Some(logs) = self.access_logs.get_mut(&archived_log.telegram_id) {
+                    let sealed = &archived_log.log;
+                    if let Some(index) = logs.iter().position(|log| log.timestamp == sealed.timestamp
+                        && log.action == sealed.action && log.chat_id == sealed.chat_id) {
+                        logs.remove(index);
+                    }
+                }
+            }
+            archived += chunk.len();
+        }
+        self.access_logs.retain(|_, logs| !logs.is_empty());
+        Ok(archived)
+    }
+
+    /// A member's archived access logs; reads every segment, so slower than
+    /// `access_logs`
+    pub fn archived_access_logs(&self, store: &dyn zos_archive::ArchiveStore,
+                                telegram_id: i64) -> Result<Vec<AccessLog>, String> {
+        let found: Vec<ArchivedAccessLog> = self.access_log_archive.query(store, 0, u64::MAX,
+            |archived: &ArchivedAccessLog| archived.telegram_id == telegram_id)?;
+        Ok(found.into_iter().map(|archived| archived.log).collect())
+    }
+
+    /// Unlink a wallet and drop its access logs and `/call` charges; returns
+    /// the number of records removed
+    pub fn forget_wallet(&mut self, wallet_address: &str) -> usize {
+        let telegram_ids: Vec<i64> = self.linked_accounts.values()
+            .filter(|account| account.wallet_address == wallet_address)
+            .map(|account| account.telegram_id)
+            .collect();
+
+        let mut removed = 0;
+        for telegram_id in telegram_ids {
+            self.linked_accounts.remove(&telegram_id);
+            removed += 1 + self.access_logs.remove(&telegram_id).map(|logs| logs.len()).unwrap_or(0);
+        }
+
+        for calls in self.service_calls.values_mut() {
+            let before = calls.len();
+            calls.retain(|call| call.wallet_address != wallet_address);
+            removed += before - calls.len();
+        }
+        self.service_calls.retain(|_, calls| !calls.is_empty());
+
+        self.pending_links.retain(|_, link| link.wallet_address != wallet_address);
+        self.tier_progress.remove(wallet_address);
+
+        removed
+    }
+
+    /// Cache the gateway's tier progress for `/tier`. When the tier changed,
+    /// returns a congratulation DM for each Telegram account linked to the wallet.
+    pub fn update_tier_progress(&mut self, wallet_address: &str, progress: TierProgressInfo) -> Vec<TelegramResponse> {
+        let previous = self.tier_progress.insert(wallet_address.to_string(), progress.clone());
+
+        let changed = previous.map(|p| p.current_tier != progress.current_tier).unwrap_or(false);
+        if !changed {
+            return Vec::new();
+        }
+
+        self.linked_accounts.values()
+            .filter(|account| account.wallet_address == wallet_address)
+            .map(|account| TelegramResponse::SendMessage {
+                chat_id: account.telegram_id,
+                text: format!("🎉 You reached the *{}* earnings tier! Use /tier to see your progress.",
+                              progress.current_tier),
+                reply_markup: None,
+            })
+            .collect()
+    }
+
+    /// Lower the reputation of every Telegram account linked to a wallet after
+    /// a moderation escalation (e.g. from the AI Chat Lounge). Returns a DM
+    /// explaining the penalty to each account.
+    pub fn apply_reputation_penalty(&mut self, wallet_address: &str, penalty: f32, reason: &str) -> Vec<TelegramResponse> {
+        let penalized: Vec<(i64, f32)> = self.linked_accounts.values_mut()
+            .filter(|account| account.wallet_address == wallet_address)
+            .map(|account| {
+                account.reputation_score = (account.reputation_score - penalty).max(0.0);
+                (account.telegram_id, account.reputation_score)
+            })
+            .collect();
+
+        let mut notices = Vec::new();
+        for (telegram_id, score) in penalized {
+            self.log_access(telegram_id, 0, "reputation_penalty", true, Some(reason.to_string()));
+            notices.push(TelegramResponse::SendMessage {
+                chat_id: telegram_id,
+                text: format!("⚠️ Your reputation dropped to {:.0}: {}", score, reason),
+                reply_markup: None,
+            });
+        }
+
+        notices
+    }
+
+    /// DMs for an approval notice from the gateway or treasury, to every
+    /// Telegram account linked to one of the request's approver wallets.
+    /// New requests carry the message to sign; outcomes just report it.
+    pub fn notify_approvers(&self, notice: &zos_approvals::ApprovalNotice) -> Vec<TelegramResponse> {
+        use zos_approvals::ApprovalEvent;
+        let action = &notice.action;
+        let text = match notice.event {
+            ApprovalEvent::Requested => format!(
+                "✍️ *Approval needed* ({} of {})\n{}\n\nSign `{}` with your wallet to approve request `{}`. \
+                 Expires {}.",
+                action.required, action.approvers.len(), action.summary,
+                action.signing_message(true), action.action_id,
+                chrono::DateTime::from_timestamp(action.expires_at as i64, 0)
+                    .map(|expires| expires.format("%Y-%m-%d %H:%M UTC").to_string())
+                    .unwrap_or_default()),
+            ApprovalEvent::Approved => format!("✅ Approved ({} of {}): {}", action.approvals.len(), action.required, action.summary),
+            ApprovalEvent::Rejected => format!("❌ Rejected by approvers: {}", action.summary),
+            ApprovalEvent::Expired => format!("⌛ Approval expired with {} of {} signatures: {}",
+                                              action.approvals.len(), action.required, action.summary),
+            ApprovalEvent::Executed => format!("🏦 Executed: {}", action.summary),
+            ApprovalEvent::Signed | ApprovalEvent::Declined => return Vec::new(),
+        };
+
+        self.linked_accounts.values()
+            .filter(|account| action.approvers.contains(&account.wallet_address))
+            .map(|account| TelegramResponse::SendMessage {
+                chat_id: account.telegram_id,
+                text: text.clone(),
+                reply_markup: None,
+            })
+            .collect()
+    }
+
     pub fn configure_group(&mut self, chat_id: i64, config: GroupConfig) {
         self.group_permissions.insert(chat_id, config);
         println!("⚙️  Group configured: {}", chat_id);
@@ -484,3 +1132,207 @@ pub struct InlineKeyboardButton {
     pub text: String,
     pub callback_data: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Answers every call with the chunks it was built with, at 1 credit
+    struct ChunkedGateway {
+        chunks: Vec<String>,
+        fail: bool,
+    }
+
+    impl ServiceGateway for ChunkedGateway {
+        fn provider(&self) -> &str {
+            "test"
+        }
+
+        fn credits(&self, _wallet_address: &str) -> Result<u64, String> {
+            Ok(5)
+        }
+
+        fn price(&self, service: &str) -> Result<u64, String> {
+            if service == "alice/expensive" { Ok(50) } else { Ok(1) }
+        }
+
+        fn invoke(&self, _wallet_address: &str, _service: &str, _args: &str,
+                  on_chunk: &mut dyn FnMut(&str)) -> Result<u64, String> {
+            for chunk in &self.chunks {
+                on_chunk(chunk);
+            }
+            if self.fail { Err("backend went away".to_string()) } else { Ok(1) }
+        }
+    }
+
+    /// Records what would have been sent, as (message_id, text) per send or edit
+    #[derive(Default)]
+    struct RecordingSender {
+        log: Mutex<Vec<(i64, String)>>,
+        fail_after: Option<usize>,
+    }
+
+    impl MessageSender for RecordingSender {
+        fn provider(&self) -> &str {
+            "test"
+        }
+
+        fn send(&self, _chat_id: i64, text: &str) -> Result<i64, String> {
+            let mut log = self.log.lock().unwrap();
+            if self.fail_after.is_some_and(|limit| log.len() >= limit) {
+                return Err("429 Too Many Requests".to_string());
+            }
+            let message_id = log.iter().map(|(id, _)| id + 1).max().unwrap_or(100);
+            log.push((message_id, text.to_string()));
+            Ok(message_id)
+        }
+
+        fn edit(&self, _chat_id: i64, message_id: i64, text: &str) -> Result<(), String> {
+            let mut log = self.log.lock().unwrap();
+            if self.fail_after.is_some_and(|limit| log.len() >= limit) {
+                return Err("429 Too Many Requests".to_string());
+            }
+            log.push((message_id, text.to_string()));
+            Ok(())
+        }
+    }
+
+    fn bot(chunks: &[&str]) -> TelegramBouncerBot {
+        let mut bot = TelegramBouncerBot::new("token", "https://bot.test/webhook");
+        bot.stream_edit_interval_ms = 0;
+        bot.set_service_gateway(Arc::new(ChunkedGateway {
+            chunks: chunks.iter().map(|chunk| chunk.to_string()).collect(),
+            fail: false,
+        }));
+        bot.linked_accounts.insert(7, LinkedAccount {
+            telegram_id: 7,
+            telegram_username: None,
+            wallet_address: "Wallet1111111111".to_string(),
+            user_id: "tg_7".to_string(),
+            linked_at: 0,
+            verification_status: VerificationStatus::Verified,
+            access_level: AccessLevel::Member,
+            reputation_score: 50.0,
+            last_activity: 0,
+        });
+        bot
+    }
+
+    fn message(text: &str) -> TelegramUpdate {
+        TelegramUpdate {
+            update_id: 1,
+            message: Some(TelegramMessage {
+                message_id: 1,
+                from: Some(TelegramUser { id: 7, is_bot: false, first_name: "Ada".to_string(), last_name: None, username: None }),
+                chat: TelegramChat { id: -42, chat_type: "group".to_string(), title: None },
+                text: Some(text.to_string()),
+                new_chat_members: None,
+            }),
+            callback_query: None,
+        }
+    }
+
+    fn texts(responses: &[TelegramResponse]) -> Vec<String> {
+        responses.iter().filter_map(|response| match response {
+            TelegramResponse::SendMessage { text, .. } => Some(text.clone()),
+            _ => None,
+        }).collect()
+    }
+
+    #[test]
+    fn test_call_output_is_edited_into_a_placeholder() {
+        let mut bot = bot(&["Hello", ", ", "world"]);
+        let sender = Arc::new(RecordingSender::default());
+        bot.set_message_sender(sender.clone());
+
+        let responses = bot.handle_telegram_update(message("/call alice/echo hi")).unwrap();
+        assert_eq!(texts(&responses), vec!["💳 Charged 1 credits for `alice/echo` (4 left).".to_string()]);
+
+        let log = sender.log.lock().unwrap().clone();
+        assert_eq!(log, vec![
+            (100, "⏳ Calling `alice/echo`…".to_string()),
+            (100, "Hello".to_string()),
+            (100, "Hello, ".to_string()),
+            (100, "Hello, world".to_string()),
+        ]);
+        assert!(bot.service_calls[&7][0].success);
+    }
+
+    #[test]
+    fn test_long_output_streams_into_new_messages() {
+        let long = "x".repeat(MAX_MESSAGE_CHARS + 10);
+        let mut bot = bot(&[&long]);
+        let sender = Arc::new(RecordingSender::default());
+        bot.set_message_sender(sender.clone());
+
+        bot.handle_telegram_update(message("/call alice/echo")).unwrap();
+        let log = sender.log.lock().unwrap().clone();
+        assert_eq!(log.len(), 3);
+        assert_eq!((log[1].0, log[1].1.len()), (100, MAX_MESSAGE_CHARS));
+        assert_eq!((log[2].0, log[2].1.len()), (101, 10));
+    }
+
+    #[test]
+    fn test_without_a_sender_output_comes_back_in_message_sized_replies() {
+        let long = "y".repeat(MAX_MESSAGE_CHARS + 1);
+        let mut bot = bot(&[&long]);
+
+        let texts = texts(&bot.handle_telegram_update(message("/call alice/echo")).unwrap());
+        assert_eq!(texts.len(), 3);
+        assert_eq!(texts[0].len(), MAX_MESSAGE_CHARS);
+        assert_eq!(texts[1], "y");
+        assert!(texts[2].starts_with("💳 Charged 1"));
+    }
+
+    #[test]
+    fn test_failed_edits_fall_back_to_replies() {
+        let mut bot = bot(&["one", "two"]);
+        let sender = Arc::new(RecordingSender { fail_after: Some(2), ..RecordingSender::default() });
+        bot.set_message_sender(sender.clone());
+
+        let texts = texts(&bot.handle_telegram_update(message("/call alice/echo")).unwrap());
+        assert_eq!(sender.log.lock().unwrap().last().unwrap().1, "one");
+        assert_eq!(texts, vec!["onetwo".to_string(), "💳 Charged 1 credits for `alice/echo` (4 left).".to_string()]);
+    }
+
+    #[test]
+    fn test_failed_calls_replace_the_placeholder() {
+        let mut bot = bot(&[]);
+        bot.set_service_gateway(Arc::new(ChunkedGateway { chunks: Vec::new(), fail: true }));
+        let sender = Arc::new(RecordingSender::default());
+        bot.set_message_sender(sender.clone());
+
+        assert!(bot.handle_telegram_update(message("/call alice/echo")).unwrap().is_empty());
+        let log = sender.log.lock().unwrap().clone();
+        assert_eq!(log[1], (100, "❌ `alice/echo` failed: backend went away".to_string()));
+        assert!(!bot.service_calls[&7][0].success);
+    }
+
+    #[test]
+    fn test_commands_addressed_with_the_bot_name() {
+        let mut bot = bot(&["pong"]);
+        assert_eq!(texts(&bot.handle_telegram_update(message("/call@ZosBouncerBot alice/echo")).unwrap())[0], "pong");
+        assert!(texts(&bot.handle_telegram_update(message("/status@ZosBouncerBot")).unwrap())[0].contains("Wallet1111111111"));
+
+        // Once the bot knows its name, commands for other bots are left alone
+        bot.bot_username = Some("@ZosBouncerBot".to_string());
+        assert_eq!(bot.handle_telegram_update(message("/call@zosbouncerbot alice/echo")).unwrap().len(), 2);
+        assert!(bot.handle_telegram_update(message("/call@OtherBot alice/echo")).unwrap().is_empty());
+        assert_eq!(bot.service_calls[&7].len(), 2);
+    }
+
+    #[test]
+    fn test_calls_are_refused_before_invoking() {
+        let mut bot = bot(&["never"]);
+        assert!(texts(&bot.handle_telegram_update(message("/call")).unwrap())[0].starts_with("Usage"));
+        assert!(texts(&bot.handle_telegram_update(message("/call alice/expensive")).unwrap())[0].contains("only services up to 10"));
+
+        bot.max_call_credits = 100;
+        assert!(texts(&bot.handle_telegram_update(message("/call alice/expensive")).unwrap())[0].contains("you have 5"));
+
+        bot.linked_accounts.clear();
+        assert!(texts(&bot.handle_telegram_update(message("/call alice/echo")).unwrap())[0].contains("/link"));
+        assert!(!bot.service_calls.contains_key(&7));
+    }
+}
@@ -0,0 +1,441 @@
+use crate::UnixAccountManager;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+const KEY_LEN: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum HomeEncryptionBackend {
+    Fscrypt,     // per-directory policy on an ext4/f2fs home
+    LuksPerUser, // one LUKS image per account, mounted over the home
+}
+
+/// An encrypted home directory. The key is never stored: it is derived from
+/// the wallet's signature over `challenge` at login and checked against
+/// `key_check`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedHome {
+    pub backend: HomeEncryptionBackend,
+    pub challenge: String, // message the wallet signs to unlock
+    pub key_check: String, // sha256 of the derived key
+    pub key_epoch: u32,    // bumped whenever the home is re-keyed by recovery
+    pub escrow: Option<KeyEscrow>,
+    pub open_sessions: u32, // the home locks when the last one ends
+    pub enabled_at: u64,
+}
+
+/// Threshold shares of the home key held by the account's vouchers. Only
+/// commitments are kept here, so neither an admin nor fewer than
+/// `threshold` vouchers can rebuild the key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyEscrow {
+    pub threshold: u8,
+    pub holders: Vec<EscrowHolder>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EscrowHolder {
+    pub voucher_id: String,
+    pub share_index: u8,
+    pub commitment: String, // sha256 of username and share
+}
+
+/// A share handed to a voucher, formatted "{index}-{hex}"
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EscrowShare {
+    pub voucher_id: String,
+    pub share: String,
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn derive_home_key(username: &str, wallet_signature: &str) -> [u8; KEY_LEN] {
+    Sha256::new()
+        .chain_update(b"zos-home-key\0")
+        .chain_update(username.as_bytes())
+        .chain_update(b"\0")
+        .chain_update(wallet_signature.as_bytes())
+        .finalize()
+        .into()
+}
+
+fn key_check(key: &[u8]) -> String {
+    hex(&Sha256::digest(key))
+}
+
+fn share_commitment(username: &str, share: &str) -> String {
+    hex(&Sha256::new()
+        .chain_update(username.as_bytes())
+        .chain_update(b"\0")
+        .chain_update(share.as_bytes())
+        .finalize())
+}
+
+fn challenge(username: &str) -> String {
+    format!("zos-home-key:{}", username)
+}
+
+// Shamir secret sharing over GF(2^8), one polynomial per key byte
+
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        let carry = a & 0x80 != 0;
+        a <<= 1;
+        if carry {
+            a ^= 0x1b;
+        }
+        b >>= 1;
+    }
+    product
+}
+
+fn gf_inv(a: u8) -> u8 {
+    // a^254 = a^-1
+    let mut result = 1;
+    let mut base = a;
+    let mut exp = 254u8;
+    while exp != 0 {
+        if exp & 1 != 0 {
+            result = gf_mul(result, base);
+        }
+        base = gf_mul(base, base);
+        exp >>= 1;
+    }
+    result
+}
+
+/// Split a key into `count` shares, any `threshold` of which rebuild it.
+/// Coefficients are derived from the key itself, so without the key they
+/// are as unpredictable as random ones.
+fn split_key(key: &[u8; KEY_LEN], threshold: u8, count: u8) -> Vec<(u8, Vec<u8>)> {
+    let coefficients: Vec<[u8; KEY_LEN]> = (1..threshold)
+        .map(|degree| {
+            Sha256::new()
+                .chain_update(b"zos-home-escrow\0")
+                .chain_update(key)
+                .chain_update([degree])
+                .finalize()
+                .into()
+        })
+        .collect();
+
+    (1..=count)
+        .map(|x| {
+            let share = (0..KEY_LEN)
+                .map(|i| {
+                    // Horner's rule, highest degree first; the key is the constant term
+                    let acc = coefficients
+                        .iter()
+                        .rev()
+                        .fold(0, |acc, c| gf_mul(acc, x) ^ c[i]);
+                    gf_mul(acc, x) ^ key[i]
+                })
+                .collect();
+            (x, share)
+        })
+        .collect()
+}
+
+fn combine_shares(shares: &[(u8, Vec<u8>)]) -> [u8; KEY_LEN] {
+    let mut key = [0u8; KEY_LEN];
+    for (j, (xj, yj)) in shares.iter().enumerate() {
+        // Lagrange basis at x = 0
+        let basis = shares
+            .iter()
+            .enumerate()
+            .filter(|(m, _)| *m != j)
+            .fold(1, |acc, (_, (xm, _))| {
+                gf_mul(acc, gf_mul(*xm, gf_inv(xm ^ xj)))
+            });
+        for i in 0..KEY_LEN {
+            key[i] ^= gf_mul(yj[i], basis);
+        }
+    }
+    key
+}
+
+fn parse_share(share: &str) -> Option<(u8, Vec<u8>)> {
+    let (index, bytes) = share.split_once('-')?;
+    let index: u8 = index.parse().ok().filter(|index| *index != 0)?;
+    let bytes = unhex(bytes).filter(|bytes| bytes.len() == KEY_LEN)?;
+    Some((index, bytes))
+}
+
+impl UnixAccountManager {
+    /// Message the account's wallet signs to unlock (or set up) its home
+    pub fn home_key_challenge(&self, username: &str) -> Result<String, String> {
+        let account = self.user_accounts.get(username).ok_or("User not found")?;
+        Ok(match &account.encrypted_home {
+            Some(home) => home.challenge.clone(),
+            None => challenge(username),
+        })
+    }
+
+    /// Encrypt an account's home with a key derived from the wallet's
+    /// signature over `home_key_challenge`. With an escrow threshold, the
+    /// key is split among the account's active vouchers and their shares are
+    /// returned for delivery; without one, losing the wallet loses the home.
+    pub fn enable_home_encryption(
+        &mut self,
+        username: &str,
+        backend: HomeEncryptionBackend,
+        wallet_signature: &str,
+        escrow_threshold: Option<u8>,
+    ) -> Result<Vec<EscrowShare>, String> {
+        let account = self.user_accounts.get(username).ok_or("User not found")?;
+        if account.encrypted_home.is_some() {
+            return Err("Home directory is already encrypted".to_string());
+        }
+
+        let key = derive_home_key(username, wallet_signature);
+        let (escrow, shares) = match escrow_threshold {
+            Some(threshold) => self.escrow_home_key(username, &key, threshold)?,
+            None => (None, Vec::new()),
+        };
+
+        let home_directory = account.home_directory.clone();
+        let setup = match backend {
+            HomeEncryptionBackend::Fscrypt => format!(
+                "fscrypt encrypt {} --source=raw_key --name=zos-{}-0 --key=/dev/stdin",
+                home_directory, username
+            ),
+            HomeEncryptionBackend::LuksPerUser => format!(
+                "cryptsetup luksFormat /var/lib/zos/homes/{}.img --key-file=- && \
+                 cryptsetup open /var/lib/zos/homes/{}.img zos-home-{} --key-file=- && \
+                 mkfs.ext4 /dev/mapper/zos-home-{}",
+                username, username, username, username
+            ),
+        };
+        println!("🔐 Home encryption: {}", setup);
+        if escrow.is_none() {
+            println!(
+                "⚠️  {} has no key escrow; the home is lost with the wallet",
+                username
+            );
+        }
+
+        let account = self.user_accounts.get_mut(username).unwrap();
+        account.encrypted_home = Some(EncryptedHome {
+            backend,
+            challenge: challenge(username),
+            key_check: key_check(&key),
+            key_epoch: 0,
+            escrow,
+            open_sessions: 1, // set up from a logged-in session
+            enabled_at: chrono::Utc::now().timestamp() as u64,
+        });
+
+        Ok(shares)
+    }
+
+    fn escrow_home_key(
+        &self,
+        username: &str,
+        key: &[u8; KEY_LEN],
+        threshold: u8,
+    ) -> Result<(Option<KeyEscrow>, Vec<EscrowShare>), String> {
+        let mut vouchers: Vec<&str> = self
+            .vouching_system
+            .values()
+            .filter(|vouch| vouch.vouched_user == username && vouch.active)
+            .map(|vouch| vouch.voucher_id.as_str())
+            .collect();
+        vouchers.sort();
+        vouchers.dedup();
+
+        // A single holder would be a backdoor of its own
+        if threshold < 2 {
+            return Err("Escrow threshold must be at least 2".to_string());
+        }
+        if vouchers.len() < threshold as usize || vouchers.len() > u8::MAX as usize {
+            return Err(format!(
+                "Escrow needs at least {} active vouchers, {} has {}",
+                threshold,
+                username,
+                vouchers.len()
+            ));
+        }
+
+        let mut holders = Vec::new();
+        let mut shares = Vec::new();
+        for ((index, bytes), voucher_id) in split_key(key, threshold, vouchers.len() as u8)
+            .into_iter()
+            .zip(vouchers)
+        {
+            let share = format!("{}-{}", index, hex(&bytes));
+            holders.push(EscrowHolder {
+                voucher_id: voucher_id.to_string(),
+                share_index: index,
+                commitment: share_commitment(username, &share),
+            });
+            shares.push(EscrowShare {
+                voucher_id: voucher_id.to_string(),
+                share,
+            });
+        }
+
+        Ok((Some(KeyEscrow { threshold, holders }), shares))
+    }
+
+    /// Unlock the home for a login session. A wrong signature derives a
+    /// different key and is refused before anything is mounted.
+    pub fn unlock_home(&mut self, username: &str, wallet_signature: &str) -> Result<u32, String> {
+        let account = self
+            .user_accounts
+            .get_mut(username)
+            .ok_or("User not found")?;
+        let home = account
+            .encrypted_home
+            .as_mut()
+            .ok_or("Home directory is not encrypted")?;
+
+        let key = derive_home_key(username, wallet_signature);
+        if key_check(&key) != home.key_check {
+            return Err("Wallet signature does not unlock this home".to_string());
+        }
+
+        if home.open_sessions == 0 {
+            let unlock = match home.backend {
+                HomeEncryptionBackend::Fscrypt => {
+                    format!("fscrypt unlock {} --key=/dev/stdin", account.home_directory)
+                }
+                HomeEncryptionBackend::LuksPerUser => format!(
+                    "cryptsetup open /var/lib/zos/homes/{}.img zos-home-{} --key-file=- && \
+                     mount /dev/mapper/zos-home-{} {}",
+                    username, username, username, account.home_directory
+                ),
+            };
+            println!("🔓 Home unlocked: {}", unlock);
+        }
+
+        home.open_sessions += 1;
+        account.last_login = chrono::Utc::now().timestamp() as u64;
+        Ok(home.open_sessions)
+    }
+
+    /// End a login session; the home is locked and its key dropped from the
+    /// kernel when no sessions remain
+    pub fn lock_home(&mut self, username: &str) -> Result<u32, String> {
+        let account = self
+            .user_accounts
+            .get_mut(username)
+            .ok_or("User not found")?;
+        let home = account
+            .encrypted_home
+            .as_mut()
+            .ok_or("Home directory is not encrypted")?;
+
+        home.open_sessions = home.open_sessions.saturating_sub(1);
+        if home.open_sessions == 0 {
+            let lock = match home.backend {
+                HomeEncryptionBackend::Fscrypt => {
+                    format!("fscrypt lock {}", account.home_directory)
+                }
+                HomeEncryptionBackend::LuksPerUser => format!(
+                    "umount {} && cryptsetup close zos-home-{}",
+                    account.home_directory, username
+                ),
+            };
+            println!("🔒 Home locked: {}", lock);
+        }
+
+        Ok(home.open_sessions)
+    }
+
+    /// Recover a home after the wallet is lost: `threshold` vouchers'
+    /// shares rebuild the old key, the home is re-keyed to the new wallet's
+    /// signature over the same challenge, and fresh shares replace the old
+    /// ones (which no longer open anything).
+    pub fn recover_home(
+        &mut self,
+        username: &str,
+        shares: &[EscrowShare],
+        new_wallet_signature: &str,
+    ) -> Result<Vec<EscrowShare>, String> {
+        let account = self.user_accounts.get(username).ok_or("User not found")?;
+        let home = account
+            .encrypted_home
+            .as_ref()
+            .ok_or("Home directory is not encrypted")?;
+        let escrow = home.escrow.as_ref().ok_or("Home key was not escrowed")?;
+
+        let mut points = Vec::new();
+        for submitted in shares {
+            let holder = escrow
+                .holders
+                .iter()
+                .find(|holder| holder.voucher_id == submitted.voucher_id)
+                .ok_or_else(|| format!("{} holds no share of this key", submitted.voucher_id))?;
+            if holder.commitment != share_commitment(username, &submitted.share) {
+                return Err(format!(
+                    "Share from {} does not match escrow",
+                    submitted.voucher_id
+                ));
+            }
+            let point = parse_share(&submitted.share).ok_or("Malformed share")?;
+            if !points.iter().any(|(index, _)| *index == point.0) {
+                points.push(point);
+            }
+        }
+        if points.len() < escrow.threshold as usize {
+            return Err(format!(
+                "Recovery needs {} shares, got {}",
+                escrow.threshold,
+                points.len()
+            ));
+        }
+
+        let old_key = combine_shares(&points);
+        if key_check(&old_key) != home.key_check {
+            return Err("Shares did not rebuild the home key".to_string());
+        }
+
+        let epoch = home.key_epoch + 1;
+        let new_key = derive_home_key(username, new_wallet_signature);
+        let (new_escrow, new_shares) =
+            self.escrow_home_key(username, &new_key, escrow.threshold)?;
+
+        let account = self.user_accounts.get_mut(username).unwrap();
+        let home = account.encrypted_home.as_mut().unwrap();
+        let rekey = match home.backend {
+            HomeEncryptionBackend::Fscrypt => format!(
+                "fscrypt metadata add-protector-to-policy --source=raw_key --name=zos-{}-{} {} && \
+                 fscrypt metadata remove-protector-from-policy --name=zos-{}-{} {}",
+                username,
+                epoch,
+                account.home_directory,
+                username,
+                home.key_epoch,
+                account.home_directory
+            ),
+            HomeEncryptionBackend::LuksPerUser => format!(
+                "cryptsetup luksAddKey /var/lib/zos/homes/{}.img && \
+                 cryptsetup luksRemoveKey /var/lib/zos/homes/{}.img",
+                username, username
+            ),
+        };
+        println!("🗝️  Home re-keyed for {}: {}", username, rekey);
+
+        home.key_check = key_check(&new_key);
+        home.key_epoch = epoch;
+        home.escrow = new_escrow;
+
+        Ok(new_shares)
+    }
+}
@@ -1,6 +1,10 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+pub mod home_encryption;
+
+use home_encryption::EncryptedHome;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UnixAccountManager {
     pub user_accounts: HashMap<String, UnixAccount>,
@@ -29,6 +33,8 @@ pub struct UnixAccount {
     pub permissions: Vec<String>,
     pub good_standing: bool,
     pub reputation_score: f32,
+    #[serde(default)]
+    pub encrypted_home: Option<EncryptedHome>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -274,6 +280,7 @@ impl UnixAccountManager {
             permissions: account_tier.permissions.clone(),
             good_standing: true,
             reputation_score: 50.0, // Start neutral
+            encrypted_home: None,
         };
 
         // Create Unix account
@@ -432,7 +439,12 @@ impl UnixAccountManager {
                 "resource_limits": account.resource_limits,
                 "permissions": account.permissions,
                 "last_login": account.last_login,
-                "home_directory": account.home_directory
+                "home_directory": account.home_directory,
+                "home_encrypted": account.encrypted_home.is_some(),
+                "home_key_escrowed": account
+                    .encrypted_home
+                    .as_ref()
+                    .is_some_and(|home| home.escrow.is_some())
             });
             Some(status.to_string())
         } else {